@@ -0,0 +1,169 @@
+//! Лог приложения: stderr, файлы с ротацией и буфер последних строк
+//!
+//! env_logger пишет через `TeeWriter`: строки уходят в stderr, как раньше, в файл
+//! logs/tli-companion.log в папке данных приложения (при превышении размера файл
+//! сдвигается в .1, .2, … и самые старые удаляются) и в кольцевой буфер — для
+//! диагностического архива и команды get_recent_app_logs.
+//! Уровень меняется на лету (set_level) и хранится в AppSettings.log_level.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::LevelFilter;
+
+/// Сколько последних строк лога держать в памяти
+const RECENT_LOG_LIMIT: usize = 2000;
+
+/// Размер одного файла лога, после которого он ротируется
+const LOG_FILE_MAX_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Сколько старых файлов хранить (tli-companion.log.1 … .N)
+const LOG_FILES_KEPT: usize = 5;
+
+const LOG_FILE_NAME: &str = "tli-companion.log";
+
+lazy_static::lazy_static! {
+    static ref RECENT_LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::with_capacity(RECENT_LOG_LIMIT));
+    static ref LOG_FILE: Mutex<Option<RotatingFile>> = Mutex::new(
+        logs_dir().and_then(|dir| RotatingFile::open(&dir).ok())
+    );
+}
+
+/// Папка с файлами лога
+pub fn logs_dir() -> Option<PathBuf> {
+    crate::persistence::app_data_dir().map(|d| d.join("logs"))
+}
+
+/// Файл лога с ротацией по размеру
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(LOG_FILE_NAME);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        self.path.with_extension(format!("log.{}", index))
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > LOG_FILE_MAX_BYTES {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let _ = fs::remove_file(self.rotated(LOG_FILES_KEPT));
+        for index in (1..LOG_FILES_KEPT).rev() {
+            let from = self.rotated(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated(1))?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// Writer для env_logger: stderr + файл + кольцевой буфер
+#[derive(Default)]
+pub struct TeeWriter {
+    pending: Vec<u8>,
+}
+
+impl Write for TeeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stderr().write_all(buf)?;
+
+        self.pending.extend_from_slice(buf);
+        while let Some(pos) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            write_to_file(&line);
+            push_line(String::from_utf8_lossy(&line).trim_end().to_string());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Ok(mut file) = LOG_FILE.lock() {
+            if let Some(file) = file.as_mut() {
+                let _ = file.file.flush();
+            }
+        }
+        io::stderr().flush()
+    }
+}
+
+/// Ошибки записи в файл глотаем: логировать их некуда, stderr и буфер работают
+fn write_to_file(line: &[u8]) {
+    let Ok(mut file) = LOG_FILE.lock() else {
+        return;
+    };
+    if let Some(file) = file.as_mut() {
+        let _ = file.write_line(line);
+    }
+}
+
+fn push_line(line: String) {
+    let Ok(mut lines) = RECENT_LINES.lock() else {
+        return;
+    };
+    if lines.len() >= RECENT_LOG_LIMIT {
+        lines.pop_front();
+    }
+    lines.push_back(line);
+}
+
+/// Последние `limit` строк лога приложения (старые первыми)
+pub fn recent_lines(limit: usize) -> Vec<String> {
+    let Ok(lines) = RECENT_LINES.lock() else {
+        return Vec::new();
+    };
+    let skip = lines.len().saturating_sub(limit);
+    lines.iter().skip(skip).cloned().collect()
+}
+
+/// Уровень лога по имени ("error", "warn", "info", "debug", "trace", "off")
+pub fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level.parse::<LevelFilter>().map_err(|_| format!("Unknown log level '{}'", level))
+}
+
+/// Сменить уровень лога на лету
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_when_file_is_full() {
+        let dir = std::env::temp_dir().join(format!("tli-logs-{}", uuid::Uuid::new_v4()));
+        let mut file = RotatingFile::open(&dir).unwrap();
+        let line = vec![b'x'; (LOG_FILE_MAX_BYTES / 2 + 1) as usize];
+
+        file.write_line(&line).unwrap();
+        file.write_line(&line).unwrap();
+        assert!(file.rotated(1).exists());
+        assert_eq!(file.size, line.len() as u64);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
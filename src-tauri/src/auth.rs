@@ -9,17 +9,19 @@
 use chrono::{DateTime, Duration, Utc};
 use base64::Engine;
 use log::debug;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use rand::RngCore;
 
-use crate::supabase_sync::SupabaseConfig;
+use crate::supabase_client::{ApiError, SupabaseClient};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 const KEYRING_SERVICE: &str = "tli-companion";
 const KEYRING_USERNAME: &str = "supabase_refresh_token";
 const TOKEN_FILE_NAME: &str = "auth_token.dat";
+/// Per-account copies of refresh tokens (for fast account switching)
+const KEYRING_ACCOUNT_PREFIX: &str = "supabase_refresh_token:";
 
 #[derive(Debug, Clone)]
 pub struct AuthSession {
@@ -29,6 +31,47 @@ pub struct AuthSession {
     pub user_email: Option<String>,
 }
 
+/// Auth state pushed to the frontend as the `auth-state-changed` event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuthStateChange {
+    pub is_logged_in: bool,
+    pub email: Option<String>,
+    pub reason: AuthChangeReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthChangeReason {
+    SignedIn,
+    SignedOut,
+    /// The refresh token was rejected by the server, the user has to sign in again
+    SessionExpired,
+}
+
+/// Why a token refresh failed
+#[derive(Debug, Clone)]
+pub enum RefreshError {
+    /// Network error, 5xx or rate limit: the session is kept, retry later
+    Transient(String),
+    /// The refresh token was rejected (4xx): the session is gone
+    Rejected(String),
+}
+
+impl RefreshError {
+    pub fn is_transient(&self) -> bool {
+        matches!(self, RefreshError::Transient(_))
+    }
+}
+
+impl std::fmt::Display for RefreshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RefreshError::Transient(e) => write!(f, "transient: {}", e),
+            RefreshError::Rejected(e) => write!(f, "rejected: {}", e),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct TokenResponseUser {
     id: Option<String>,
@@ -45,8 +88,9 @@ struct TokenResponse {
     user: Option<TokenResponseUser>,
 }
 
-fn keyring_entry() -> Result<keyring::Entry, String> {
-    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USERNAME).map_err(|e| e.to_string())
+/// Keychain entry of the app; `account` names the secret (shared by every module that stores one)
+pub(crate) fn keyring_entry(account: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, account).map_err(|e| e.to_string())
 }
 
 /// Get the path to the token file in app data directory
@@ -69,7 +113,7 @@ fn deobfuscate(data: &str) -> Option<String> {
 pub fn store_refresh_token(token: &str) -> Result<(), String> {
     // Try keyring first
     log::debug!("Attempting to store refresh token in keyring...");
-    let keyring_result = keyring_entry().and_then(|e| e.set_password(token).map_err(|e| e.to_string()));
+    let keyring_result = keyring_entry(KEYRING_USERNAME).and_then(|e| e.set_password(token).map_err(|e| e.to_string()));
     
     // Always also store to file as fallback
     if let Some(path) = token_file_path() {
@@ -113,7 +157,7 @@ pub fn store_refresh_token(token: &str) -> Result<(), String> {
 pub fn load_refresh_token() -> Result<Option<String>, String> {
     // Try keyring first
     log::debug!("Attempting to load refresh token from keyring...");
-    if let Ok(entry) = keyring_entry() {
+    if let Ok(entry) = keyring_entry(KEYRING_USERNAME) {
         match entry.get_password() {
             Ok(v) if !v.trim().is_empty() => {
                 log::debug!("Loaded token from keyring (length {})", v.len());
@@ -149,7 +193,7 @@ pub fn load_refresh_token() -> Result<Option<String>, String> {
 
 pub fn clear_refresh_token() -> Result<(), String> {
     // Clear from keyring
-    let _ = keyring_entry().and_then(|e| e.set_password("").map_err(|e| e.to_string()));
+    let _ = keyring_entry(KEYRING_USERNAME).and_then(|e| e.set_password("").map_err(|e| e.to_string()));
     
     // Clear from file
     if let Some(path) = token_file_path() {
@@ -159,44 +203,93 @@ pub fn clear_refresh_token() -> Result<(), String> {
     Ok(())
 }
 
+fn sanitize_user_id(user_id: &str) -> String {
+    user_id.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect()
+}
+
+fn account_keyring_entry(user_id: &str) -> Result<keyring::Entry, String> {
+    let username = format!("{}{}", KEYRING_ACCOUNT_PREFIX, sanitize_user_id(user_id));
+    keyring_entry(&username)
+}
+
+fn account_token_file_path(user_id: &str) -> Option<std::path::PathBuf> {
+    token_file_path().and_then(|p| {
+        p.parent().map(|dir| dir.join(format!("auth_token_{}.dat", sanitize_user_id(user_id))))
+    })
+}
+
+/// Store a linked account's refresh token (keyring, file as fallback)
+pub fn store_account_token(user_id: &str, token: &str) -> Result<(), String> {
+    let keyring_result = account_keyring_entry(user_id)
+        .and_then(|e| e.set_password(token).map_err(|e| e.to_string()));
+
+    if let Some(path) = account_token_file_path(user_id) {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Err(e) = std::fs::write(&path, obfuscate(token)) {
+            if let Err(kr) = keyring_result {
+                return Err(format!("Failed to store account token: keyring ({}) and file ({}) both failed", kr, e));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Load a linked account's refresh token
+pub fn load_account_token(user_id: &str) -> Result<Option<String>, String> {
+    if let Ok(entry) = account_keyring_entry(user_id) {
+        if let Ok(v) = entry.get_password() {
+            if !v.trim().is_empty() {
+                return Ok(Some(v));
+            }
+        }
+    }
+
+    if let Some(path) = account_token_file_path(user_id) {
+        if path.exists() {
+            let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+            return Ok(deobfuscate(&content).filter(|t| !t.is_empty()));
+        }
+    }
+    Ok(None)
+}
+
+/// Remove a linked account's refresh token
+pub fn clear_account_token(user_id: &str) {
+    let _ = account_keyring_entry(user_id).and_then(|e| e.set_password("").map_err(|e| e.to_string()));
+    if let Some(path) = account_token_file_path(user_id) {
+        let _ = std::fs::remove_file(&path);
+    }
+}
+
 fn compute_expires_at(expires_in: i64) -> DateTime<Utc> {
     // небольшой запас, чтобы не словить race на границе истечения
     Utc::now() + Duration::seconds(expires_in.saturating_sub(30).max(0))
 }
 
+/// Exchange a refresh token for a new session. Goes through `SupabaseClient`, so an open
+/// circuit breaker fails the refresh as transient instead of hitting a dead server.
 pub async fn refresh_access_token(
-    http: &reqwest::Client,
-    cfg: &SupabaseConfig,
+    api: &SupabaseClient,
     refresh_token: &str,
-) -> Result<AuthSession, String> {
-    let endpoint = format!(
-        "{}/auth/v1/token?grant_type=refresh_token",
-        cfg.url.trim_end_matches('/')
-    );
-
+) -> Result<AuthSession, RefreshError> {
     let body = serde_json::json!({
         "refresh_token": refresh_token
     });
 
-    let resp = http
-        .post(endpoint)
-        .header("apikey", &cfg.anon_key)
-        .header("Authorization", format!("Bearer {}", cfg.anon_key))
-        .json(&body)
-        .send()
+    // Refresh tokens rotate: never repeat a request the server may have processed
+    let tok: TokenResponse = api
+        .post_json("Auth refresh", "auth/v1/token?grant_type=refresh_token", None, &body, None, false)
         .await
-        .map_err(|e| e.to_string())?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        return Err(format!("Auth refresh failed: {} {}", status, text));
-    }
-
-    let tok: TokenResponse = resp.json().await.map_err(|e| e.to_string())?;
+        .map_err(|e| match e {
+            // 401/403 и прочие 4xx — токен недействителен; сеть, 429, 5xx и мусор в ответе — временно
+            ApiError::Auth(_) | ApiError::Request(_) => RefreshError::Rejected(e.to_string()),
+            _ => RefreshError::Transient(e.to_string()),
+        })?;
 
     // Supabase обычно возвращает новый refresh_token — сохраняем его.
-    store_refresh_token(&tok.refresh_token)?;
+    store_refresh_token(&tok.refresh_token).map_err(RefreshError::Transient)?;
     debug!("Refreshed session and stored new refresh token");
 
     Ok(AuthSession {
@@ -424,8 +517,7 @@ async fn wait_for_localhost_callback_no_state(
 
 /// Direct Supabase OAuth with PKCE — no website proxy, more reliable.
 pub async fn sign_in_via_kripika(
-    http: &reqwest::Client,
-    cfg: &SupabaseConfig,
+    api: &SupabaseClient,
     _kripika_origin: &str, // kept for API compat, not used
     cancel: Arc<AtomicBool>,
 ) -> Result<AuthSession, String> {
@@ -442,6 +534,7 @@ pub async fn sign_in_via_kripika(
     // IMPORTANT: All possible ports (49733-49737) must be in Supabase's allowed Redirect URLs.
     // NOTE: We don't pass our own `state` - Supabase manages state internally for the OAuth
     // flow with Discord. PKCE (code_verifier) provides cryptographic security.
+    let cfg = api.config();
     let authorize_url = format!(
         "{}/auth/v1/authorize?provider=discord&redirect_to={}&code_challenge={}&code_challenge_method=s256",
         cfg.url.trim_end_matches('/'),
@@ -454,33 +547,15 @@ pub async fn sign_in_via_kripika(
     // Accept any state from Supabase callback (we verify via PKCE code_verifier instead)
     let code = wait_for_localhost_callback_no_state(listener, cancel).await?;
 
-    // Exchange code for session (PKCE)
-    let endpoint = format!(
-        "{}/auth/v1/token?grant_type=pkce",
-        cfg.url.trim_end_matches('/')
-    );
-
+    // Exchange code for session (PKCE). The auth code is single-use: no repeats.
     let body = serde_json::json!({
         "auth_code": code,
         "code_verifier": verifier
     });
 
-    let resp = http
-        .post(endpoint)
-        .header("apikey", &cfg.anon_key)
-        .header("Authorization", format!("Bearer {}", cfg.anon_key))
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        return Err(format!("OAuth token exchange failed: {} {}", status, text));
-    }
-
-    let tok: TokenResponse = resp.json().await.map_err(|e| e.to_string())?;
+    let tok: TokenResponse = api
+        .post_json("OAuth token exchange", "auth/v1/token?grant_type=pkce", None, &body, None, false)
+        .await?;
     log::info!("OAuth token exchange successful, storing refresh token...");
     match store_refresh_token(&tok.refresh_token) {
         Ok(()) => log::info!("Refresh token stored successfully in keychain"),
@@ -496,3 +571,130 @@ pub async fn sign_in_via_kripika(
         user_email: tok.user.and_then(|u| u.email),
     })
 }
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Device-code login (no localhost listener)
+// ─────────────────────────────────────────────────────────────────────────────
+//
+// Flow (RFC 8628 style, brokered by kripika.com):
+// 1. POST {api_url}/api/auth/device/code -> device_code + short user_code;
+// 2. the user opens verification_uri, signs in and confirms the user_code;
+// 3. the app polls POST {api_url}/api/auth/device/token until it gets a Supabase session.
+// Works behind firewalls that block the OAuth callback ports.
+
+/// Device code issued for a login attempt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCode {
+    #[serde(skip_serializing)]
+    pub device_code: String,
+    /// Code the user confirms in the browser
+    pub user_code: String,
+    pub verification_uri: String,
+    /// verification_uri with user_code prefilled (if the server provides it)
+    #[serde(default)]
+    pub verification_uri_complete: Option<String>,
+    pub expires_in: i64,
+    /// Minimal polling interval, seconds
+    #[serde(default = "default_device_poll_interval")]
+    pub interval: u64,
+}
+
+fn default_device_poll_interval() -> u64 {
+    5
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceTokenError {
+    error: String,
+}
+
+/// Request a device code for login
+pub async fn request_device_code(
+    http: &reqwest::Client,
+    cfg: &crate::supabase_sync::SupabaseConfig,
+    api_url: &str,
+) -> Result<DeviceCode, String> {
+    let endpoint = format!("{}/api/auth/device/code", api_url.trim_end_matches('/'));
+    let body = serde_json::json!({
+        "client": "tli-companion",
+        "supabase_url": cfg.url,
+    });
+
+    let resp = http
+        .post(endpoint)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        return Err(format!("Device code request failed: {} {}", status, text));
+    }
+
+    resp.json().await.map_err(|e| e.to_string())
+}
+
+/// Poll until the user confirms the code in the browser (or it expires / login is cancelled)
+pub async fn poll_device_token(
+    http: &reqwest::Client,
+    api_url: &str,
+    device: &DeviceCode,
+    cancel: Arc<AtomicBool>,
+) -> Result<AuthSession, String> {
+    let endpoint = format!("{}/api/auth/device/token", api_url.trim_end_matches('/'));
+    let deadline = Utc::now() + Duration::seconds(device.expires_in);
+    let mut interval = device.interval.max(1);
+
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+        if cancel.load(Ordering::Relaxed) {
+            return Err("Device login cancelled".to_string());
+        }
+        if Utc::now() > deadline {
+            return Err("Device code expired, try again".to_string());
+        }
+
+        let resp = match http
+            .post(&endpoint)
+            .json(&serde_json::json!({ "device_code": device.device_code }))
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => {
+                // Flaky network: keep polling until the code expires
+                debug!("Device token poll failed: {}", e);
+                continue;
+            }
+        };
+
+        if resp.status().is_success() {
+            let tok: TokenResponse = resp.json().await.map_err(|e| e.to_string())?;
+            store_refresh_token(&tok.refresh_token)
+                .map_err(|e| format!("Failed to store refresh token: {}", e))?;
+            log::info!("Device login successful");
+            return Ok(AuthSession {
+                access_token: tok.access_token,
+                expires_at: compute_expires_at(tok.expires_in),
+                user_id: tok.user.as_ref().and_then(|u| u.id.clone()),
+                user_email: tok.user.and_then(|u| u.email),
+            });
+        }
+
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        let error = serde_json::from_str::<DeviceTokenError>(&text)
+            .map(|e| e.error)
+            .unwrap_or_default();
+        match error.as_str() {
+            "authorization_pending" => {}
+            "slow_down" => interval += 5,
+            "access_denied" => return Err("Login was denied in the browser".to_string()),
+            "expired_token" => return Err("Device code expired, try again".to_string()),
+            _ if status.is_server_error() => debug!("Device token poll: {} {}", status, text),
+            _ => return Err(format!("Device login failed: {} {}", status, text)),
+        }
+    }
+}
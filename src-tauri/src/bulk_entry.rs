@@ -0,0 +1,191 @@
+//! Массовый ввод ручного дропа: разбор вставленного текста
+//!
+//! Одна продажа на строку: `2x Deification Ember @ 350`, `Deification Ember x2 @ 1.2k`,
+//! `Deification Ember @ 350` (1 шт.). Пустые строки и строки с `#` в начале пропускаются.
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::types::ManualDropEntry;
+
+/// Разобранная строка (имя ещё не сопоставлено с базой)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BulkLine {
+    /// Номер строки во вставленном тексте (с 1)
+    pub line: usize,
+    pub name: String,
+    pub quantity: i32,
+    /// Цена за штуку (FE)
+    pub price: f64,
+}
+
+/// Строка, которую не удалось разобрать
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BulkLineError {
+    pub line: usize,
+    pub text: String,
+    pub error: String,
+}
+
+/// Предпросмотр массового ввода: готовые записи (game_id — если имя нашлось в базе) и ошибки
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkDropPreview {
+    pub entries: Vec<ManualDropEntry>,
+    pub errors: Vec<BulkLineError>,
+}
+
+/// Проверить записи, пришедшие с фронтенда после предпросмотра. Клиенту доверяем только
+/// содержимое строк: id выдаём заново, отметку продажи и цену в валюте сбрасываем
+/// (цены массового ввода — в FE). Одна неверная запись отклоняет всю пачку
+pub fn sanitize(entries: Vec<ManualDropEntry>) -> Result<Vec<ManualDropEntry>, String> {
+    entries
+        .into_iter()
+        .map(|entry| {
+            if entry.name.trim().is_empty() {
+                return Err("Manual drop name is empty".to_string());
+            }
+            if entry.quantity <= 0 {
+                return Err(format!("Invalid quantity for '{}': {}", entry.name, entry.quantity));
+            }
+            if !entry.price.is_finite() || entry.price < 0.0 {
+                return Err(format!("Invalid price for '{}': {}", entry.name, entry.price));
+            }
+            Ok(ManualDropEntry {
+                id: uuid::Uuid::new_v4().to_string(),
+                sold: None,
+                quoted: None,
+                ..entry
+            })
+        })
+        .collect()
+}
+
+/// Разобрать вставленный текст: удачные строки и ошибки по остальным
+pub fn parse(text: &str) -> (Vec<BulkLine>, Vec<BulkLineError>) {
+    // Количество: `2x Имя` / `Имя x2` (x, х, ×, *)
+    let prefix = Regex::new(r"^(\d+)\s*[xXхХ×*]\s*(.+)$").expect("valid regex");
+    let suffix = Regex::new(r"^(.+?)\s*[xXхХ×*]\s*(\d+)$").expect("valid regex");
+
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, raw) in text.lines().enumerate() {
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let error = |error: &str| BulkLineError { line: idx + 1, text: trimmed.to_string(), error: error.to_string() };
+
+        let Some((item, price)) = trimmed.rsplit_once('@') else {
+            errors.push(error("missing '@ price'"));
+            continue;
+        };
+        let Some(price) = parse_price(price) else {
+            errors.push(error("invalid price"));
+            continue;
+        };
+        let item = item.trim();
+        let (name, quantity) = if let Some(caps) = prefix.captures(item) {
+            (caps[2].trim().to_string(), caps[1].parse::<i32>().ok())
+        } else if let Some(caps) = suffix.captures(item) {
+            (caps[1].trim().to_string(), caps[2].parse::<i32>().ok())
+        } else {
+            (item.to_string(), Some(1))
+        };
+        match quantity {
+            _ if name.is_empty() => errors.push(error("missing item name")),
+            Some(quantity) if quantity > 0 => lines.push(BulkLine { line: idx + 1, name, quantity, price }),
+            _ => errors.push(error("invalid quantity")),
+        }
+    }
+    (lines, errors)
+}
+
+/// Цена: `350`, `1.5k`, `2,5k`, `1 200`, `1,200`, `1.2m`.
+/// Запятая перед группой из трёх цифр — разделитель тысяч, иначе — десятичная
+fn parse_price(s: &str) -> Option<f64> {
+    let s: String = s.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_lowercase();
+    let s = s.trim_end_matches("fe");
+    let (number, multiplier) = match s.strip_suffix(['k', 'к']) {
+        Some(n) => (n, 1_000.0),
+        None => match s.strip_suffix(['m', 'м']) {
+            Some(n) => (n, 1_000_000.0),
+            None => (s, 1.0),
+        },
+    };
+    let number = if is_thousands_grouped(number) {
+        number.replace(',', "")
+    } else if number.contains('.') && number.contains(',') {
+        // `1,5.0` и подобное — непонятно, что имелось в виду
+        return None;
+    } else {
+        number.replace(',', ".")
+    };
+    let value: f64 = number.parse().ok()?;
+    (value.is_finite() && value > 0.0).then_some(value * multiplier)
+}
+
+/// `1,200`, `12,500,000`, `1,200.5` — запятые разбивают целую часть на группы по три цифры
+fn is_thousands_grouped(number: &str) -> bool {
+    let integer = number.split_once('.').map_or(number, |(int, _)| int);
+    let mut groups = integer.split(',');
+    let head = groups.next().unwrap_or_default();
+    let mut rest = groups.peekable();
+    rest.peek().is_some()
+        && (1..=3).contains(&head.len())
+        && head.chars().all(|c| c.is_ascii_digit())
+        && rest.all(|g| g.len() == 3 && g.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quantity_name_and_price() {
+        let text = "2x Deification Ember @ 350\n\n# комментарий\nFlame Elementium x3 @ 1.5k\nPriest Mask @ 2 500\nbroken line\nEmber @ abc";
+        let (lines, errors) = parse(text);
+        assert_eq!(
+            lines,
+            vec![
+                BulkLine { line: 1, name: "Deification Ember".into(), quantity: 2, price: 350.0 },
+                BulkLine { line: 4, name: "Flame Elementium".into(), quantity: 3, price: 1500.0 },
+                BulkLine { line: 5, name: "Priest Mask".into(), quantity: 1, price: 2500.0 },
+            ]
+        );
+        assert_eq!(errors.iter().map(|e| e.line).collect::<Vec<_>>(), vec![6, 7]);
+    }
+
+    #[test]
+    fn sanitize_rejects_bad_entries_and_resets_client_fields() {
+        let entry = |id: &str, quantity: i32, price: f64| ManualDropEntry {
+            id: id.to_string(),
+            game_id: Some(1),
+            name: "Ember".to_string(),
+            name_ru: None,
+            quantity,
+            price,
+            tier: None,
+            sold: Some(crate::types::ManualDropSale { actual_price: 1.0, sold_at: chrono::Utc::now() }),
+            quoted: None,
+        };
+        let clean = sanitize(vec![entry("same", 2, 350.0), entry("same", 1, 0.0)]).unwrap();
+        assert_eq!(clean.len(), 2);
+        assert_ne!(clean[0].id, "same");
+        assert_ne!(clean[0].id, clean[1].id);
+        assert!(clean.iter().all(|e| e.sold.is_none()));
+
+        assert!(sanitize(vec![entry("a", 0, 350.0)]).is_err());
+        assert!(sanitize(vec![entry("a", 1, -1.0)]).is_err());
+        assert!(sanitize(vec![entry("a", 1, f64::NAN)]).is_err());
+    }
+
+    #[test]
+    fn comma_before_three_digits_separates_thousands() {
+        assert_eq!(parse_price("1,200"), Some(1200.0));
+        assert_eq!(parse_price("12,500,000"), Some(12_500_000.0));
+        assert_eq!(parse_price("1,200.5"), Some(1200.5));
+        assert_eq!(parse_price("1,2"), Some(1.2));
+        assert_eq!(parse_price("2,5k"), Some(2500.0));
+        assert_eq!(parse_price("1,5.0"), None);
+    }
+}
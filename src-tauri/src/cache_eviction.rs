@@ -0,0 +1,179 @@
+//! Ограничение кэшей предметов и цен
+//!
+//! prices_cache копит цены всех сезонов, items_cache — весь каталог. Раз в
+//! CACHE_EVICTION_INTERVAL_SEC (и после загрузки кэшей) лишнее выбрасывается:
+//! - цены чужих лиг старше AppSettings.cache_limits.price_ttl_days;
+//! - сверх max_prices — сначала чужие лиги, затем самые давно обновлённые;
+//! - предметы сверх max_items — только без цены и не из текущей сессии, сначала
+//!   давно не падавшие (и не падавшие ни разу).
+//!
+//! Дроп текущей сессии, контейнеры гэмбла и базовая валюта не выбрасываются никогда.
+
+use std::collections::{HashMap, HashSet};
+use std::mem::size_of;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::persistence::PersistedPriceEntry;
+use crate::types::{CacheLimits, ItemInfo};
+
+/// Как часто проверять размеры кэшей
+pub const CACHE_EVICTION_INTERVAL_SEC: u64 = 10 * 60;
+
+/// Размеры кэшей (для get_cache_stats)
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub items: usize,
+    /// Примерный объём в памяти, байт
+    pub items_bytes: usize,
+    pub prices: usize,
+    pub prices_bytes: usize,
+    /// Цен текущей лиги
+    pub prices_current_league: usize,
+    pub current_league: Option<String>,
+    pub limits: CacheLimits,
+}
+
+/// Цена текущей лиги. Лигу без названия считаем текущей, если она помечена как текущая
+pub fn is_current(entry: &PersistedPriceEntry, current_league: Option<&str>) -> bool {
+    entry.is_current_league
+        && match (entry.league_name.as_deref(), current_league) {
+            (Some(league), Some(current)) => league == current,
+            _ => true,
+        }
+}
+
+/// Выбросить лишние цены. Возвращает game_id выброшенных
+pub fn evict_prices(
+    prices: &mut HashMap<i64, PersistedPriceEntry>,
+    limits: &CacheLimits,
+    current_league: Option<&str>,
+    keep: &HashSet<i64>,
+    now: DateTime<Utc>,
+) -> Vec<i64> {
+    let mut evicted = Vec::new();
+
+    if limits.price_ttl_days > 0 {
+        let cutoff = now - Duration::days(limits.price_ttl_days as i64);
+        prices.retain(|game_id, entry| {
+            let stays = keep.contains(game_id) || is_current(entry, current_league) || entry.updated_at >= cutoff;
+            if !stays {
+                evicted.push(*game_id);
+            }
+            stays
+        });
+    }
+
+    if prices.len() > limits.max_prices {
+        let mut candidates: Vec<(bool, DateTime<Utc>, i64)> = prices
+            .iter()
+            .filter(|(game_id, _)| !keep.contains(game_id))
+            .map(|(game_id, entry)| (is_current(entry, current_league), entry.updated_at, *game_id))
+            .collect();
+        // Сначала чужие лиги, внутри — самые старые
+        candidates.sort();
+        let excess = prices.len() - limits.max_prices;
+        for (_, _, game_id) in candidates.into_iter().take(excess) {
+            prices.remove(&game_id);
+            evicted.push(game_id);
+        }
+    }
+
+    evicted
+}
+
+/// Выбросить предметы сверх лимита: только те, что не нужны (`keep` = false) и не базовая валюта.
+/// Первыми уходят давно не использованные по `last_used` (без записи — раньше всех)
+pub fn evict_items(
+    items: &mut HashMap<i64, ItemInfo>,
+    max_items: usize,
+    last_used: &HashMap<i64, DateTime<Utc>>,
+    keep: impl Fn(i64) -> bool,
+) -> usize {
+    if items.len() <= max_items {
+        return 0;
+    }
+    let mut candidates: Vec<(Option<DateTime<Utc>>, i64)> = items
+        .values()
+        .filter(|item| !item.is_base_currency && !keep(item.game_id))
+        .map(|item| (last_used.get(&item.game_id).copied(), item.game_id))
+        .collect();
+    candidates.sort_unstable();
+    let excess = (items.len() - max_items).min(candidates.len());
+    for (_, game_id) in &candidates[..excess] {
+        items.remove(game_id);
+    }
+    excess
+}
+
+fn opt_len(s: &Option<String>) -> usize {
+    s.as_ref().map_or(0, |s| s.capacity())
+}
+
+/// Примерный объём записи items_cache в памяти
+pub fn item_bytes(item: &ItemInfo) -> usize {
+    size_of::<(i64, ItemInfo)>()
+        + item.name.capacity()
+        + opt_len(&item.name_en)
+        + opt_len(&item.name_ru)
+        + opt_len(&item.name_cn)
+        + item.category.capacity()
+        + opt_len(&item.icon_url)
+}
+
+/// Примерный объём записи prices_cache в памяти
+pub fn price_bytes(entry: &PersistedPriceEntry) -> usize {
+    size_of::<(i64, PersistedPriceEntry)>() + opt_len(&entry.league_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(league: &str, current: bool, age_days: i64, now: DateTime<Utc>) -> PersistedPriceEntry {
+        PersistedPriceEntry {
+            price: 1.0,
+            updated_at: now - Duration::days(age_days),
+            is_current_league: current,
+            league_name: Some(league.to_string()),
+            sample_count: None,
+            spread: None,
+            tiers: Default::default(),
+        }
+    }
+
+    #[test]
+    fn old_league_prices_go_first() {
+        let now = Utc::now();
+        let limits = CacheLimits { max_items: 10, max_prices: 2, price_ttl_days: 30 };
+        let mut prices = HashMap::from([
+            (1, entry("SS10", false, 60, now)),
+            (2, entry("SS10", false, 5, now)),
+            (3, entry("SS11", true, 90, now)),
+            (4, entry("SS11", true, 1, now)),
+            (5, entry("SS10", false, 120, now)),
+        ]);
+        let keep = HashSet::from([5]);
+
+        let mut evicted = evict_prices(&mut prices, &limits, Some("SS11"), &keep, now);
+        evicted.sort();
+        // 1 — по TTL; сверх лимита 2 (чужая лига), затем 3 (самая старая текущая); 5 в текущей сессии
+        assert_eq!(evicted, vec![1, 2, 3]);
+        assert!(prices.contains_key(&4) && prices.contains_key(&5));
+    }
+
+    #[test]
+    fn least_recently_used_items_go_first() {
+        let now = Utc::now();
+        let item = |game_id: i64| ItemInfo { game_id, name: game_id.to_string(), ..ItemInfo::default() };
+        let mut items: HashMap<i64, ItemInfo> = (1..=5).map(|id| (id, item(id))).collect();
+        let last_used = HashMap::from([(1, now), (2, now - Duration::days(3)), (3, now - Duration::days(1))]);
+        // 5 в текущей сессии; 4 не падал ни разу, затем самый давний 2
+        let evicted = evict_items(&mut items, 2, &last_used, |id| id == 5);
+        assert_eq!(evicted, 3);
+        let mut left: Vec<i64> = items.keys().copied().collect();
+        left.sort();
+        assert_eq!(left, vec![1, 5]);
+    }
+}
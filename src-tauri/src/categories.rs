@@ -0,0 +1,173 @@
+//! Метаданные категорий предметов
+//!
+//! В tli_game_items категория — просто строка (`ember`, `fossil`, ...). Таблица
+//! tli_item_categories даёт к ней имена на языках, порядок сортировки и родительскую
+//! группу («Валюта/Угли»), чтобы разбивки и фильтры группировались одинаково и
+//! на языке пользователя. Без таблицы всё работает как раньше: имя = строка категории.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Строка tli_item_categories
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryInfo {
+    /// Строка категории, как в tli_game_items.category (у групп — своё имя)
+    pub id: String,
+    /// Родительская группа (None — верхний уровень)
+    #[serde(default)]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub name_en: Option<String>,
+    #[serde(default)]
+    pub name_ru: Option<String>,
+    #[serde(default)]
+    pub name_cn: Option<String>,
+    #[serde(default)]
+    pub sort_order: i32,
+}
+
+impl CategoryInfo {
+    fn localized_name(&self, language: &str) -> &str {
+        let preferred = match language {
+            "ru" => self.name_ru.as_deref(),
+            "zh" => self.name_cn.as_deref(),
+            _ => self.name_en.as_deref(),
+        };
+        preferred
+            .filter(|n| !n.trim().is_empty())
+            .or_else(|| self.name_en.as_deref().filter(|n| !n.trim().is_empty()))
+            .unwrap_or(&self.id)
+    }
+}
+
+/// Категория для UI (на языке отображения)
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryView {
+    pub id: String,
+    pub name: String,
+    pub parent: Option<String>,
+    /// Путь от верхней группы: «Валюта/Угли»
+    pub path: String,
+    /// Верхняя группа (для свёрнутых разбивок); у самой группы — она же
+    pub group: String,
+    pub depth: usize,
+    pub sort_order: i32,
+}
+
+/// Справочник категорий (пустой, пока таблицу не загрузили)
+#[derive(Debug, Clone, Default)]
+pub struct CategoryCatalog {
+    categories: HashMap<String, CategoryInfo>,
+}
+
+/// Глубже не бывает; защита от циклов в parent
+const MAX_DEPTH: usize = 8;
+
+impl CategoryCatalog {
+    pub fn new(categories: Vec<CategoryInfo>) -> Self {
+        Self { categories: categories.into_iter().map(|c| (c.id.clone(), c)).collect() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.categories.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.categories.is_empty()
+    }
+
+    /// Цепочка от верхней группы до категории (неизвестная — только она сама)
+    fn chain<'a>(&'a self, id: &'a str) -> Vec<&'a str> {
+        let mut chain = vec![id];
+        let mut current = id;
+        while let Some(parent) = self.categories.get(current).and_then(|c| c.parent.as_deref()) {
+            if chain.len() >= MAX_DEPTH || chain.contains(&parent) {
+                break;
+            }
+            chain.push(parent);
+            current = parent;
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// Категория `id` — это `ancestor` или лежит внутри этой группы
+    pub fn is_within(&self, id: &str, ancestor: &str) -> bool {
+        self.chain(id).contains(&ancestor)
+    }
+
+    /// Фильтр разбивок: сама категория (без учёта регистра) или любая категория внутри группы
+    pub fn matches_filter(&self, id: &str, filter: &str) -> bool {
+        id.eq_ignore_ascii_case(filter) || self.is_within(id, filter)
+    }
+
+    fn name<'a>(&'a self, id: &'a str, language: &str) -> &'a str {
+        self.categories.get(id).map(|c| c.localized_name(language)).unwrap_or(id)
+    }
+
+    /// Представление категории (в том числе неизвестной справочнику)
+    pub fn view(&self, id: &str, language: &str) -> CategoryView {
+        let chain = self.chain(id);
+        let info = self.categories.get(id);
+        CategoryView {
+            id: id.to_string(),
+            name: self.name(id, language).to_string(),
+            parent: info.and_then(|c| c.parent.clone()),
+            path: chain.iter().map(|c| self.name(c, language)).collect::<Vec<_>>().join("/"),
+            group: self.name(chain[0], language).to_string(),
+            depth: chain.len() - 1,
+            sort_order: info.map(|c| c.sort_order).unwrap_or(i32::MAX),
+        }
+    }
+
+    /// Все категории: группа, затем её дети (по sort_order, затем по имени)
+    pub fn views(&self, language: &str) -> Vec<CategoryView> {
+        let key = |id: &str| -> Vec<(i32, String)> {
+            self.chain(id)
+                .into_iter()
+                .map(|c| (self.categories.get(c).map(|i| i.sort_order).unwrap_or(i32::MAX), self.name(c, language).to_lowercase()))
+                .collect()
+        };
+        let mut ids: Vec<&String> = self.categories.keys().collect();
+        ids.sort_by_cached_key(|id| key(id));
+        ids.into_iter().map(|id| self.view(id, language)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn category(id: &str, parent: Option<&str>, name_ru: &str, sort_order: i32) -> CategoryInfo {
+        CategoryInfo {
+            id: id.to_string(),
+            parent: parent.map(str::to_string),
+            name_en: Some(id.to_string()),
+            name_ru: Some(name_ru.to_string()),
+            name_cn: None,
+            sort_order,
+        }
+    }
+
+    #[test]
+    fn builds_localized_paths_in_hierarchy_order() {
+        let catalog = CategoryCatalog::new(vec![
+            category("ember", Some("currency"), "Угли", 2),
+            category("gear", None, "Снаряжение", 2),
+            category("currency", None, "Валюта", 1),
+            category("fossil", Some("currency"), "Окаменелости", 1),
+            category("loop_a", Some("loop_b"), "А", 0),
+            category("loop_b", Some("loop_a"), "Б", 0),
+        ]);
+        let ember = catalog.view("ember", "ru");
+        assert_eq!((ember.path.as_str(), ember.group.as_str(), ember.depth), ("Валюта/Угли", "Валюта", 1));
+        assert_eq!(catalog.view("unknown", "ru").path, "unknown");
+        assert!(catalog.view("loop_a", "en").depth < MAX_DEPTH);
+        assert!(catalog.matches_filter("ember", "currency") && catalog.matches_filter("Ember", "ember"));
+        assert!(!catalog.matches_filter("gear", "currency"));
+
+        let order: Vec<String> = catalog.views("en").into_iter().map(|v| v.id).filter(|id| !id.starts_with("loop")).collect();
+        assert_eq!(order, vec!["currency", "fossil", "ember", "gear"]);
+    }
+}
@@ -8,8 +8,8 @@ use log::info;
 use serde::Serialize;
 
 use crate::state::AppState;
-use crate::types::{SessionStats, AggregatedDrop, AppSettings, ItemInfo, UserProfile};
-use crate::file_watcher::find_log_path;
+use crate::types::{SessionStats, AggregatedDrop, AppSettings, ItemInfo, ManualDropEntry, UserProfile, LogWatchMode, CompanionWindowSettings};
+use crate::file_watcher::{find_log_path_for, WatcherConfig};
 use std::sync::atomic::AtomicBool;
 
 /// Статус лог-файла
@@ -21,12 +21,15 @@ pub struct LogFileStatus {
     pub size_bytes: Option<u64>,
 }
 
-/// Найти путь к файлу логов автоматически
+/// Найти путь к файлу логов автоматически (с учётом клиента игры из настроек)
 #[tauri::command]
-pub async fn find_log_file() -> Result<Option<String>, String> {
+pub async fn find_log_file(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Option<String>, String> {
     info!("Looking for TLI log file...");
     
-    match find_log_path() {
+    let client = state.settings.read().await.game_client;
+    match find_log_path_for(client) {
         Some(path) => {
             let path_str = path.to_string_lossy().to_string();
             info!("Found log file: {}", path_str);
@@ -39,32 +42,59 @@ pub async fn find_log_file() -> Result<Option<String>, String> {
     }
 }
 
-/// Установить путь к файлу логов вручную
-#[tauri::command]
-pub async fn set_log_path(
-    state: State<'_, Arc<AppState>>,
-    path: String,
-) -> Result<bool, String> {
+/// Проверить, что путь указывает на существующий UE_game.log
+fn validate_log_file_path(path: &str) -> Result<(), String> {
     // Проверяем, существует ли файл
-    if !std::path::Path::new(&path).exists() {
+    if !std::path::Path::new(path).exists() {
         return Err("File does not exist".to_string());
     }
 
     // Безопасность: по умолчанию разрешаем только UE_game.log, чтобы не дать приложению
     // читать произвольные файлы пользователя через IPC.
-    if let Some(file_name) = std::path::Path::new(&path).file_name().and_then(|n| n.to_str()) {
+    if let Some(file_name) = std::path::Path::new(path).file_name().and_then(|n| n.to_str()) {
         if !file_name.eq_ignore_ascii_case("UE_game.log") {
             return Err("Only UE_game.log is supported for security reasons".to_string());
         }
     } else {
         return Err("Invalid path".to_string());
     }
+    Ok(())
+}
+
+/// Установить путь к файлу логов вручную
+#[tauri::command]
+pub async fn set_log_path(
+    state: State<'_, Arc<AppState>>,
+    path: String,
+) -> Result<bool, String> {
+    validate_log_file_path(&path)?;
     
     state.set_log_path(Some(path.clone())).await;
     info!("Log path set to: {}", path);
     Ok(true)
 }
 
+/// Задать дополнительные лог-файлы (второй клиент игры). Применяются после перезапуска.
+#[tauri::command]
+pub async fn set_extra_log_paths(
+    state: State<'_, Arc<AppState>>,
+    paths: Vec<String>,
+) -> Result<(), String> {
+    for path in &paths {
+        validate_log_file_path(path)?;
+    }
+    state.set_extra_log_paths(paths).await;
+    Ok(())
+}
+
+/// Получить подсессии по клиентам игры (мультибокс)
+#[tauri::command]
+pub async fn get_source_sessions(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<crate::types::SourceSessionStats>, String> {
+    Ok(state.get_source_sessions().await)
+}
+
 /// Начать новую сессию фарма
 #[tauri::command]
 pub async fn start_session(
@@ -75,270 +105,2263 @@ pub async fn start_session(
     Ok(())
 }
 
-/// Установить состояние паузы сессии
+/// Список сохранённых шаблонов сессий
+#[tauri::command]
+pub async fn list_session_templates() -> Result<Vec<crate::types::SessionTemplate>, String> {
+    crate::persistence::load_session_templates().map_err(|e| e.to_string())
+}
+
+/// Сохранить шаблон сессии (создать новый или перезаписать по id)
+#[tauri::command]
+pub async fn save_session_template(
+    state: State<'_, Arc<AppState>>,
+    template: crate::types::SessionTemplate,
+) -> Result<crate::types::SessionTemplate, String> {
+    let mut template = template;
+    if template.id.is_empty() {
+        template.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    let _guard = state.templates_lock.lock().await;
+    let mut templates = crate::persistence::load_session_templates().map_err(|e| e.to_string())?;
+    match templates.iter_mut().find(|t| t.id == template.id) {
+        Some(existing) => *existing = template.clone(),
+        None => templates.push(template.clone()),
+    }
+    crate::persistence::save_session_templates(&templates).map_err(|e| e.to_string())?;
+    info!("Session template saved: {}", template.name);
+    Ok(template)
+}
+
+/// Удалить шаблон сессии
+#[tauri::command]
+pub async fn delete_session_template(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+) -> Result<bool, String> {
+    let _guard = state.templates_lock.lock().await;
+    let mut templates = crate::persistence::load_session_templates().map_err(|e| e.to_string())?;
+    let before = templates.len();
+    templates.retain(|t| t.id != id);
+    if templates.len() == before {
+        return Ok(false);
+    }
+    crate::persistence::save_session_templates(&templates).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// Запланированные блоки фарма (по времени начала)
+#[tauri::command]
+pub async fn list_farm_blocks() -> Result<Vec<crate::types::FarmBlock>, String> {
+    let mut blocks = crate::persistence::load_farm_blocks().map_err(|e| e.to_string())?;
+    blocks.sort_by_key(|b| b.starts_at);
+    Ok(blocks)
+}
+
+/// Сохранить блок фарма (создать новый или перезаписать по id).
+/// Изменение времени начала заново включает напоминание и автостарт
+#[tauri::command]
+pub async fn save_farm_block(
+    state: State<'_, Arc<AppState>>,
+    block: crate::types::FarmBlock,
+) -> Result<crate::types::FarmBlock, String> {
+    let mut block = block;
+    if block.duration_min == 0 {
+        return Err("Farm block duration must be positive".to_string());
+    }
+    if block.id.is_empty() {
+        block.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    let _guard = state.farm_blocks_lock.lock().await;
+    let mut blocks = crate::persistence::load_farm_blocks().map_err(|e| e.to_string())?;
+    match blocks.iter_mut().find(|b| b.id == block.id) {
+        Some(existing) => {
+            if existing.starts_at != block.starts_at {
+                block.reminded = false;
+                block.started = false;
+            }
+            *existing = block.clone();
+        }
+        None => {
+            block.reminded = false;
+            block.started = false;
+            blocks.push(block.clone());
+        }
+    }
+    crate::persistence::save_farm_blocks(&blocks).map_err(|e| e.to_string())?;
+    info!("Farm block saved: {} at {}", block.name, block.starts_at.to_rfc3339());
+    Ok(block)
+}
+
+/// Удалить блок фарма
+#[tauri::command]
+pub async fn delete_farm_block(state: State<'_, Arc<AppState>>, id: String) -> Result<bool, String> {
+    let _guard = state.farm_blocks_lock.lock().await;
+    let mut blocks = crate::persistence::load_farm_blocks().map_err(|e| e.to_string())?;
+    let before = blocks.len();
+    blocks.retain(|b| b.id != id);
+    if blocks.len() == before {
+        return Ok(false);
+    }
+    crate::persistence::save_farm_blocks(&blocks).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// Записать открытие контейнера вручную (opened_count штук, содержимое: game_id -> количество)
+#[tauri::command]
+pub async fn record_gamble_opening(
+    container_game_id: i64,
+    opened_count: Option<i32>,
+    outputs: std::collections::HashMap<i64, i32>,
+) -> Result<crate::gamble::GambleOpening, String> {
+    let opened_count = opened_count.unwrap_or(1);
+    if opened_count <= 0 || outputs.values().any(|q| *q <= 0) {
+        return Err("Quantities must be positive".to_string());
+    }
+    let opening = crate::gamble::GambleOpening {
+        id: uuid::Uuid::new_v4().to_string(),
+        container_game_id,
+        opened_count,
+        opened_at: chrono::Utc::now(),
+        outputs,
+        manual: true,
+    };
+    crate::persistence::add_gamble_opening(opening.clone()).map_err(|e| e.to_string())?;
+    Ok(opening)
+}
+
+/// Наблюдаемая EV контейнеров по всем записанным открытиям (или одного контейнера)
+#[tauri::command]
+pub async fn get_gamble_ev(
+    state: State<'_, Arc<AppState>>,
+    container_game_id: Option<i64>,
+) -> Result<Vec<crate::gamble::GambleEv>, String> {
+    state.flush_stale_opening();
+    let openings = crate::persistence::load_gamble_openings().map_err(|e| e.to_string())?;
+    let valuation = state.valuation().await;
+    let language = state.display_language().await;
+    let items_cache = state.items_cache.read().await;
+    let prices = state.prices_cache.read().await;
+    Ok(crate::gamble::ev_stats(&openings, container_game_id, &items_cache, &prices, &valuation, &language))
+}
+
+/// Дроп за карту с 95% интервалами по окну истории (по умолчанию — все карты)
+#[tauri::command]
+pub async fn get_drop_rates(
+    state: State<'_, Arc<AppState>>,
+    window: Option<crate::drop_rates::DropRateWindow>,
+) -> Result<crate::drop_rates::DropRates, String> {
+    let log = crate::persistence::load_map_log().map_err(|e| e.to_string())?;
+    let league = state.current_league().await;
+    let maps = crate::drop_rates::select(&log, &window.unwrap_or_default(), league.as_deref());
+    let language = state.display_language().await;
+    let items_cache = state.items_cache.read().await;
+    Ok(crate::drop_rates::estimate(&maps, &items_cache, &language))
+}
+
+/// Рецепты крафта
+#[tauri::command]
+pub async fn list_recipes() -> Result<Vec<crate::types::Recipe>, String> {
+    crate::persistence::load_recipes().map_err(|e| e.to_string())
+}
+
+/// Сохранить рецепт (создать новый или перезаписать по id)
+#[tauri::command]
+pub async fn save_recipe(
+    state: State<'_, Arc<AppState>>,
+    recipe: crate::types::Recipe,
+) -> Result<crate::types::Recipe, String> {
+    let mut recipe = recipe;
+    crate::crafting::validate(&recipe)?;
+    if recipe.id.is_empty() {
+        recipe.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    let _guard = state.recipes_lock.lock().await;
+    let mut recipes = crate::persistence::load_recipes().map_err(|e| e.to_string())?;
+    match recipes.iter_mut().find(|r| r.id == recipe.id) {
+        Some(existing) => *existing = recipe.clone(),
+        None => recipes.push(recipe.clone()),
+    }
+    crate::persistence::save_recipes(&recipes).map_err(|e| e.to_string())?;
+    Ok(recipe)
+}
+
+/// Удалить рецепт
+#[tauri::command]
+pub async fn delete_recipe(state: State<'_, Arc<AppState>>, id: String) -> Result<bool, String> {
+    let _guard = state.recipes_lock.lock().await;
+    let mut recipes = crate::persistence::load_recipes().map_err(|e| e.to_string())?;
+    let before = recipes.len();
+    recipes.retain(|r| r.id != id);
+    if recipes.len() == before {
+        return Ok(false);
+    }
+    crate::persistence::save_recipes(&recipes).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// Оценить рецепт по текущим ценам: стоимость компонентов против ожидаемой стоимости результата
+#[tauri::command]
+pub async fn evaluate_recipe(
+    state: State<'_, Arc<AppState>>,
+    recipe_id: String,
+) -> Result<crate::types::RecipeEvaluation, String> {
+    let recipe = crate::persistence::load_recipes()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|r| r.id == recipe_id)
+        .ok_or_else(|| "Recipe not found".to_string())?;
+    let valuation = state.valuation().await;
+    let language = state.display_language().await;
+    let items_cache = state.items_cache.read().await;
+    let prices = state.prices_cache.read().await;
+    Ok(crate::crafting::evaluate(&recipe, &items_cache, &prices, &valuation, &language))
+}
+
+/// Начать новую сессию по шаблону
+#[tauri::command]
+pub async fn start_session_from_template(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+) -> Result<(), String> {
+    let template = crate::persistence::load_session_templates()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|t| t.id == id)
+        .ok_or_else(|| "Template not found".to_string())?;
+    state.start_session_from_template(&template).await;
+    Ok(())
+}
+
+/// Будильник по чистому профиту сессии (threshold_fe = null — выключить)
+#[tauri::command]
+pub async fn set_profit_alarm(
+    state: State<'_, Arc<AppState>>,
+    threshold_fe: Option<f64>,
+) -> Result<Option<crate::types::ProfitAlarm>, String> {
+    state.set_profit_alarm(threshold_fe).await
+}
+
+/// Цели текущей сессии
+#[tauri::command]
+pub async fn get_session_goals(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::types::SessionGoals, String> {
+    Ok(state.get_session_goals().await)
+}
+
+/// Установить состояние паузы сессии
+#[tauri::command]
+pub async fn set_paused(
+    state: State<'_, Arc<AppState>>,
+    paused: bool,
+) -> Result<(), String> {
+    state.set_paused(paused).await;
+    Ok(())
+}
+
+/// Обновить время сессии (устарело)
+///
+/// Секундомер теперь ведёт backend и шлёт событие `session-duration`.
+/// Команда оставлена, чтобы старый фронтенд не падал на invoke.
+#[tauri::command]
+pub async fn update_session_duration(
+    _state: State<'_, Arc<AppState>>,
+    duration_sec: i32,
+) -> Result<(), String> {
+    log::debug!("Ignoring frontend session duration push: {} sec", duration_sec);
+    Ok(())
+}
+
+/// Добавить трату вручную (currency_id — если цена указана не в FE)
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn add_expense(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+    game_id: Option<i64>,
+    name: String,
+    name_ru: Option<String>,
+    quantity: i32,
+    price: f64,
+    currency_id: Option<i64>,
+) -> Result<(), String> {
+    state.add_expense(id, game_id, name, name_ru, quantity, price, currency_id).await
+}
+
+/// Удалить трату
+#[tauri::command]
+pub async fn remove_expense(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+) -> Result<(), String> {
+    state.remove_expense(&id).await;
+    Ok(())
+}
+
+/// Получить список трат
+#[tauri::command]
+pub async fn get_expenses(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<crate::types::ExpenseEntry>, String> {
+    Ok(state.get_expenses().await)
+}
+
+/// Поиск предметов по названию
+#[tauri::command]
+pub async fn search_items(
+    state: State<'_, Arc<AppState>>,
+    query: String,
+) -> Result<Vec<crate::types::ItemInfo>, String> {
+    Ok(state.search_items(&query).await)
+}
+
+/// Подсказка цены для ручного дропа/траты: последняя ручная цена предмета и рыночная
+#[tauri::command]
+pub async fn get_item_price_suggestion(
+    state: State<'_, Arc<AppState>>,
+    game_id: i64,
+) -> Result<crate::types::PriceSuggestion, String> {
+    Ok(state.get_item_price_suggestion(game_id).await)
+}
+
+/// Получить все кэшированные цены
+#[tauri::command]
+pub async fn get_cached_prices(
+    state: State<'_, Arc<AppState>>,
+) -> Result<std::collections::HashMap<i64, f64>, String> {
+    Ok(state.get_all_prices().await)
+}
+
+/// История цены предмета по дням (из Supabase) для графика
+#[tauri::command]
+pub async fn get_price_history(
+    state: State<'_, Arc<AppState>>,
+    game_id: i64,
+    days: Option<i32>,
+) -> Result<Vec<crate::supabase_sync::DailyPricePoint>, String> {
+    let api = state
+        .supabase_client()
+        .await
+        .ok_or_else(|| "Supabase config missing".to_string())?;
+    let days = days.unwrap_or(30).clamp(1, 365);
+    Ok(crate::supabase_sync::fetch_price_history(&api, game_id, days).await?)
+}
+
+/// Состояние фонового обновления цен (последний успех, следующий запуск, ошибка)
+#[tauri::command]
+pub async fn get_price_sync_status(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::price_sync::PriceSyncStatus, String> {
+    Ok(state.get_price_sync_status().await)
+}
+
+/// Оценка состояния: последний полный снимок инвентаря по кэшированным ценам, по категориям.
+/// None — снимка ещё не было (появляется после входа в мир). `category` — фильтр по категории или группе
+#[tauri::command]
+pub async fn get_net_worth(
+    state: State<'_, Arc<AppState>>,
+    category: Option<String>,
+) -> Result<Option<crate::types::NetWorth>, String> {
+    Ok(state.get_net_worth(category.as_deref()).await)
+}
+
+/// Очередь прайсчека: предметы текущего дропа без актуальной цены
+#[tauri::command]
+pub async fn get_pricecheck_queue(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::types::PricecheckQueue, String> {
+    Ok(state.get_pricecheck_queue().await)
+}
+
+/// Отложить предмет в очереди прайсчека
+#[tauri::command]
+pub async fn skip_pricecheck_item(
+    state: State<'_, Arc<AppState>>,
+    game_id: i64,
+) -> Result<crate::types::PricecheckQueue, String> {
+    state.skip_pricecheck_item(game_id).await;
+    Ok(state.get_pricecheck_queue().await)
+}
+
+/// Добавить ручной дроп (для уников/экипировки; currency_id — если цена указана не в FE)
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn add_manual_drop(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+    game_id: Option<i64>,
+    name: String,
+    name_ru: Option<String>,
+    quantity: i32,
+    price: f64,
+    tier: Option<i32>,
+    currency_id: Option<i64>,
+) -> Result<(), String> {
+    state.add_manual_drop(id, game_id, name, name_ru, quantity, price, tier, currency_id).await
+}
+
+/// Курсы торговых валют к FE по наблюдениям из прайсчеков
+#[tauri::command]
+pub async fn get_exchange_rates(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<crate::exchange_rates::ExchangeRateSeries>, String> {
+    Ok(state.get_exchange_rates().await)
+}
+
+/// Создать пользовательский предмет (нет в базе игры) с ценой по умолчанию
+#[tauri::command]
+pub async fn create_custom_item(
+    state: State<'_, Arc<AppState>>,
+    name: String,
+    category: String,
+    default_price: Option<f64>,
+) -> Result<ItemInfo, String> {
+    state.create_custom_item(name, category, default_price).await
+}
+
+/// Разобрать вставленный текст («2x Имя @ 350» на строку) в записи ручного дропа для предпросмотра
+#[tauri::command]
+pub async fn parse_manual_drops_bulk(
+    state: State<'_, Arc<AppState>>,
+    text: String,
+) -> Result<crate::bulk_entry::BulkDropPreview, String> {
+    let (lines, errors) = crate::bulk_entry::parse(&text);
+    let mut entries = Vec::with_capacity(lines.len());
+    for line in lines {
+        let item = state.find_item_by_name(&line.name).await;
+        entries.push(ManualDropEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            game_id: item.as_ref().map(|i| i.game_id),
+            name: item.as_ref().and_then(|i| i.name_en.clone()).unwrap_or(line.name),
+            name_ru: item.and_then(|i| i.name_ru),
+            quantity: line.quantity,
+            price: line.price,
+            tier: None,
+            sold: None,
+            quoted: None,
+        });
+    }
+    Ok(crate::bulk_entry::BulkDropPreview { entries, errors })
+}
+
+/// Добавить пачку ручного дропа (после предпросмотра parse_manual_drops_bulk)
+#[tauri::command]
+pub async fn add_manual_drops_bulk(
+    state: State<'_, Arc<AppState>>,
+    entries: Vec<ManualDropEntry>,
+) -> Result<usize, String> {
+    state.add_manual_drops_bulk(entries).await
+}
+
+/// Отметить ручной дроп проданным: фактическая цена за штуку и время продажи
+/// (actual_price = null снимает отметку)
+#[tauri::command]
+pub async fn mark_manual_drop_sold(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+    actual_price: Option<f64>,
+    sold_at: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<ManualDropEntry, String> {
+    state.mark_manual_drop_sold(&id, actual_price, sold_at).await
+}
+
+/// Удалить ручной дроп
+#[tauri::command]
+pub async fn remove_manual_drop(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+) -> Result<(), String> {
+    if !state.remove_manual_drop(&id).await {
+        return Err(format!("Manual drop {} not found", id));
+    }
+    Ok(())
+}
+
+/// Прогресс догонялки лога по источникам (warm-up при старте или большой накопившийся объём)
+#[tauri::command]
+pub async fn get_catchup_progress(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<crate::file_watcher::CatchupProgress>, String> {
+    Ok(state.get_catchup_progress())
+}
+
+/// Сколько FE было при старте сессии и сколько сейчас («живая» валюта отдельно от оценки дропа)
+#[tauri::command]
+pub async fn get_session_currency(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::types::SessionCurrency, String> {
+    Ok(state.get_session_currency().await)
+}
+
+/// Карты, завершённые в активной сессии: сцена, время, длительность
+#[tauri::command]
+pub async fn get_map_runs(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<crate::types::MapRun>, String> {
+    Ok(state.get_map_runs().await)
+}
+
+/// Личные рекорды по картам (самая дорогая и самая быстрая карта за все сессии)
+#[tauri::command]
+pub async fn get_records(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::types::PersonalRecords, String> {
+    Ok(state.get_records().await)
+}
+
+/// Ввести количество FE вручную (point: start | end), если лог его не видел или ошибся
+#[tauri::command]
+pub async fn set_session_currency(
+    state: State<'_, Arc<AppState>>,
+    point: crate::types::CurrencyPoint,
+    amount: i64,
+) -> Result<crate::types::CurrencyReading, String> {
+    state.set_session_currency(point, amount).await
+}
+
+/// Получить список ручного дропа
+#[tauri::command]
+pub async fn get_manual_drops(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<crate::types::ManualDropEntry>, String> {
+    Ok(state.get_manual_drops().await)
+}
+
+/// Завершить сессию фарма
+#[tauri::command]
+pub async fn end_session(
+    state: State<'_, Arc<AppState>>,
+    _app_handle: tauri::AppHandle,
+) -> Result<SessionStats, String> {
+    // ВАЖНО: сначала берём финальные stats и данные сессии, потом сбрасываем состояние.
+    let stats = state.get_session_stats().await;
+    
+    // Get session data before reset (and update duration from wall-clock)
+    let session = {
+        let mut s = state.session.write().await;
+        // Update duration to wall-clock time (not just map time)
+        s.total_duration_sec = stats.duration_sec;
+        s.clone()
+    };
+    
+    // Calculate totals
+    let valuation = state.valuation().await;
+    let total_expenses = valuation.expenses_total(&session.expenses);
+    let total_income = stats.total_value;
+    let total_fee = stats.total_fee;
+    let total_profit = valuation.round(total_income - total_expenses - total_fee);
+    let (raw_currency_start, raw_currency_end) = state.session_currency_readings().await;
+    
+    let history_record = crate::persistence::SessionHistoryRecord {
+        id: uuid::Uuid::new_v4().to_string(),
+        started_at: session.started_at.unwrap_or_else(chrono::Utc::now),
+        ended_at: chrono::Utc::now(),
+        maps_completed: session.maps_completed,
+        total_duration_sec: stats.duration_sec,
+        total_profit,
+        total_expenses,
+        total_income,
+        total_fee,
+        remote_id: None, // Проставляется после выгрузки в облако
+        drops: session.drops.clone(),
+        drop_tiers: session.drop_tiers.clone(),
+        share_code: None,
+        preset_id: session.preset_id.clone(),
+        raw_currency_start,
+        raw_currency_end,
+        raw_currency_gained: crate::persistence::raw_currency_gained(raw_currency_start, raw_currency_end),
+        manual_realized: stats.manual_realized_value,
+        manual_pending: stats.manual_pending_value,
+        manual_pending_quoted: session.pending_quoted(),
+        recovered: session.recovered,
+        recovery_gap_sec: session.recovery_gap_sec,
+        merged_remote_ids: Vec::new(),
+    };
+
+    // Строка в Google-таблицу — в фоне, не зависит от входа в аккаунт
+    let sheets = state.settings.read().await.google_sheets.clone();
+    if sheets.enabled {
+        let state = state.inner().clone();
+        let row = crate::google_sheets::session_row(&history_record, &valuation);
+        let session_id = history_record.id.clone();
+        tauri::async_runtime::spawn(async move {
+            let _guard = state.sheets_queue_lock.lock().await;
+            match crate::google_sheets::push_rows(&state.http(), &sheets, Some(row)).await {
+                Ok(n) => log::info!("Session {} appended to Google Sheets ({} rows)", session_id, n),
+                Err(e) => log::warn!("Google Sheets append failed, will retry on the next sync: {}", e),
+            }
+        });
+    }
+    
+    // Save to local history (if logged in)
+    if let Some(user_id) = state.get_auth_user_id().await {
+        let saved = {
+            let _guard = state.history_lock.lock().await;
+            crate::persistence::add_session_to_history(&user_id, history_record.clone())
+        };
+        if let Err(e) = saved {
+            log::warn!("Failed to save session to local history: {}", e);
+        } else {
+            log::info!("Session saved to local history");
+            // Выгрузка в облако (opt-in) — в фоне, чтобы не задерживать завершение сессии
+            if state.settings.read().await.sync_sessions_to_cloud {
+                let state = state.inner().clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = upload_history_record(&state, &user_id, &history_record).await {
+                        log::warn!("Cloud sync of session {} failed, will retry via sync_pending_sessions: {}", history_record.id, e);
+                    }
+                });
+            }
+        }
+    }
+    
+    let twitch = state.settings.read().await.twitch.clone();
+    state.twitch.announce_summary(&twitch, &stats, total_profit, &valuation);
+
+    // Неизвестные предметы — на сервер, если пользователь согласился (в фоне)
+    if state.settings.read().await.report_unknown_items {
+        let state = state.inner().clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = state.report_unknown_items().await {
+                log::debug!("Unknown items report failed: {}", e);
+            }
+        });
+    }
+
+    // Reset session state
+    let _session_data = state.end_session().await;
+    Ok(stats)
+}
+
+/// Сохранить ключ доступа к Google Sheets (JSON сервисного аккаунта или OAuth-токен) в keychain
+#[tauri::command]
+pub async fn set_google_sheets_credential(
+    credential: String,
+) -> Result<crate::google_sheets::CredentialInfo, String> {
+    let info = crate::google_sheets::store_credential(&credential)?;
+    info!("Google Sheets credential stored");
+    Ok(info)
+}
+
+/// Какой ключ Google Sheets сохранён (сам секрет не возвращается)
+#[tauri::command]
+pub async fn get_google_sheets_credential() -> Result<crate::google_sheets::CredentialInfo, String> {
+    let credential = crate::google_sheets::load_credential()?;
+    Ok(crate::google_sheets::credential_info(credential.as_ref()))
+}
+
+/// Удалить ключ Google Sheets из keychain
+#[tauri::command]
+pub async fn clear_google_sheets_credential() -> Result<(), String> {
+    crate::google_sheets::clear_credential()
+}
+
+/// Проверить доступ к таблице из настроек. Возвращает её название
+#[tauri::command]
+pub async fn test_google_sheets(
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, String> {
+    let sheets = state.settings.read().await.google_sheets.clone();
+    crate::google_sheets::check_access(&state.http(), &sheets).await
+}
+
+/// Разделить активную сессию: всё до `timestamp` (по умолчанию — сейчас) уходит в историю
+/// отдельной записью, а фарм продолжается новой сессией без сброса текущей карты
+#[tauri::command]
+pub async fn split_session_at(
+    state: State<'_, Arc<AppState>>,
+    timestamp: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<crate::persistence::SessionHistoryRecord, String> {
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+    let at = timestamp.unwrap_or_else(chrono::Utc::now);
+
+    let closed = state.split_session_at(at).await?;
+    let valuation = state.valuation().await;
+    let total_expenses = valuation.expenses_total(&closed.expenses);
+    let total_income = state.value_of_drops(&closed.drops, &closed.drop_tiers).await;
+    let total_fee = state.fee_of_drops(&closed.drops, &closed.drop_tiers).await;
+    let raw_currency_start = closed.currency_start.as_ref().map(|r| r.amount);
+    let raw_currency_end = closed.currency_end.as_ref().map(|r| r.amount);
+    let (manual_realized, manual_pending) = closed.manual_sales();
+    let manual_pending_quoted = closed.pending_quoted();
+    let record = crate::persistence::SessionHistoryRecord {
+        id: uuid::Uuid::new_v4().to_string(),
+        started_at: closed.started_at.unwrap_or(at),
+        ended_at: at,
+        maps_completed: closed.maps_completed,
+        total_duration_sec: closed.session_duration_sec,
+        total_profit: valuation.round(total_income - total_expenses - total_fee),
+        total_expenses,
+        total_income,
+        total_fee,
+        remote_id: None,
+        drops: closed.drops,
+        drop_tiers: closed.drop_tiers,
+        share_code: None,
+        preset_id: closed.preset_id,
+        raw_currency_start,
+        raw_currency_end,
+        raw_currency_gained: crate::persistence::raw_currency_gained(raw_currency_start, raw_currency_end),
+        manual_realized: valuation.round(manual_realized),
+        manual_pending: valuation.round(manual_pending),
+        manual_pending_quoted,
+        recovered: closed.recovered,
+        recovery_gap_sec: closed.recovery_gap_sec,
+        merged_remote_ids: Vec::new(),
+    };
+
+    let _guard = state.history_lock.lock().await;
+    crate::persistence::add_session_to_history(&user_id, record.clone())
+        .map_err(|e| e.to_string())?;
+    log::info!("Split part saved to local history: {}", record.id);
+    Ok(record)
+}
+
+/// Выгрузить запись истории в облако и сохранить remote_id
+async fn upload_history_record(
+    state: &AppState,
+    user_id: &str,
+    record: &crate::persistence::SessionHistoryRecord,
+) -> Result<String, String> {
+    let api = state
+        .supabase_client()
+        .await
+        .ok_or_else(|| "Supabase config missing".to_string())?;
+    let jwt = state
+        .get_valid_access_token(&api)
+        .await
+        .ok_or_else(|| "Not logged in".to_string())?;
+
+    let remote_id = crate::supabase_sync::sync_farm_session(
+        &api, &jwt, user_id, record, env!("CARGO_PKG_VERSION"),
+    )
+    .await?;
+    let _guard = state.history_lock.lock().await;
+    crate::persistence::update_session_in_history(user_id, &record.id, |s| {
+        s.remote_id = Some(remote_id.clone());
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(remote_id)
+}
+
+/// Итог дозагрузки истории в облако
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingSyncResult {
+    pub uploaded: usize,
+    pub failed: usize,
+    /// Ошибки по записям: "id: текст ошибки"
+    pub errors: Vec<String>,
+}
+
+/// Выгрузить в облако все сессии истории без remote_id (старые первыми);
+/// заодно дослать строки Google Sheets из очереди
+#[tauri::command]
+pub async fn sync_pending_sessions(
+    state: State<'_, Arc<AppState>>,
+) -> Result<PendingSyncResult, String> {
+    // Строки Google Sheets, не ушедшие при завершении сессий, — заодно
+    let sheets = state.settings.read().await.google_sheets.clone();
+    if sheets.enabled {
+        let _guard = state.sheets_queue_lock.lock().await;
+        match crate::google_sheets::push_rows(&state.http(), &sheets, None).await {
+            Ok(0) => {}
+            Ok(n) => info!("Google Sheets: {} queued rows appended", n),
+            Err(e) => log::warn!("Google Sheets retry failed: {}", e),
+        }
+    }
+
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+    let result = push_pending_sessions(&state, &user_id).await?;
+    info!("Pending sessions sync: {} uploaded, {} failed", result.uploaded, result.failed);
+    Ok(result)
+}
+
+async fn push_pending_sessions(state: &AppState, user_id: &str) -> Result<PendingSyncResult, String> {
+    let history = crate::persistence::load_session_history(user_id)
+        .map_err(|e| e.to_string())?;
+
+    let mut result = PendingSyncResult { uploaded: 0, failed: 0, errors: Vec::new() };
+    for record in history.iter().rev().filter(|r| r.remote_id.is_none()) {
+        match upload_history_record(state, user_id, record).await {
+            Ok(_) => result.uploaded += 1,
+            Err(e) => {
+                result.failed += 1;
+                result.errors.push(format!("{}: {}", record.id, e));
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Двусторонняя синхронизация истории: выгрузить локальные сессии без remote_id,
+/// подтянуть сессии с других устройств и слить их с локальной историей
+#[tauri::command]
+pub async fn sync_history_now(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::history_sync::HistorySyncReport, String> {
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+
+    // Сначала push: свежевыгруженные записи получают remote_id и не приедут обратно дублями
+    let pushed = push_pending_sessions(&state, &user_id).await?;
+
+    let api = state
+        .supabase_client()
+        .await
+        .ok_or_else(|| "Supabase config missing".to_string())?;
+    let jwt = state
+        .get_valid_access_token(&api)
+        .await
+        .ok_or_else(|| "Not logged in".to_string())?;
+
+    let remote = crate::supabase_sync::fetch_session_history(&api, &jwt, 100).await?;
+    // Сессии прошлой лиги уже в архиве — в текущую историю их не возвращаем
+    let archived_at = crate::persistence::app_data_dir()
+        .and_then(|dir| crate::league_archive::list_archives(&dir).first().map(|a| a.archived_at));
+    let remote = crate::history_sync::skip_archived(remote, archived_at);
+    // Держим до записи слитой истории: push выше уже отпустил блокировку
+    let _guard = state.history_lock.lock().await;
+    let local = crate::persistence::load_session_history(&user_id)
+        .map_err(|e| e.to_string())?;
+    let to_pull = crate::history_sync::unknown_remote_ids(&local, &remote);
+    let remote_drops = crate::supabase_sync::fetch_session_drops(&api, &jwt, &to_pull).await?;
+
+    let (mut merged, merge_report) = crate::history_sync::merge(local, &remote, &remote_drops);
+    merged.truncate(100);
+    crate::persistence::save_session_history(&user_id, &merged)
+        .map_err(|e| e.to_string())?;
+
+    let report = crate::history_sync::HistorySyncReport {
+        pushed: pushed.uploaded,
+        pulled: merge_report.pulled,
+        linked: merge_report.linked,
+        conflicted: merge_report.conflicted,
+        errors: pushed.errors,
+    };
+    info!(
+        "History sync: {} pushed, {} pulled, {} linked, {} conflicted",
+        report.pushed, report.pulled, report.linked, report.conflicted
+    );
+    Ok(report)
+}
+
+/// Объединить случайно разделённые сессии истории в одну запись (метрики и дроп суммируются)
+#[tauri::command]
+pub async fn merge_history_sessions(
+    state: State<'_, Arc<AppState>>,
+    ids: Vec<String>,
+) -> Result<crate::persistence::SessionHistoryRecord, String> {
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+
+    let _guard = state.history_lock.lock().await;
+    let merged = crate::persistence::merge_sessions_in_history(&user_id, &ids)
+        .map_err(|e| e.to_string())?;
+    log::info!("Merged {} history sessions into {}", ids.len(), merged.id);
+    Ok(merged)
+}
+
+/// Получить историю сессий пользователя (локально)
+#[tauri::command]
+pub async fn get_session_history(
+    state: State<'_, Arc<AppState>>,
+    limit: Option<i32>,
+) -> Result<Vec<crate::persistence::SessionHistoryRecord>, String> {
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+    
+    let mut sessions = crate::persistence::load_session_history(&user_id)
+        .map_err(|e| e.to_string())?;
+    
+    // Apply limit
+    let limit = limit.unwrap_or(20) as usize;
+    sessions.truncate(limit);
+    
+    Ok(sessions)
+}
+
+/// Доступность Supabase: закрыт ли предохранитель и когда следующая попытка
+#[tauri::command]
+pub async fn get_connectivity_status(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::supabase_client::ConnectivityStatus, String> {
+    Ok(state.connectivity_status())
+}
+
+/// Переключиться на свой сервер цен (или обратно на встроенный). Свой сервер
+/// сохраняется, только если проверка соединения и схемы прошла
+#[tauri::command]
+pub async fn set_backend_endpoint(
+    state: State<'_, Arc<AppState>>,
+    endpoint: crate::types::BackendEndpoint,
+) -> Result<crate::types::BackendCheckResult, String> {
+    Ok(state.apply_backend_endpoint(endpoint).await)
+}
+
+/// Сохранить пароль прокси в keychain (пустой — удалить) и применить прокси заново
+#[tauri::command]
+pub async fn set_proxy_password(
+    state: State<'_, Arc<AppState>>,
+    password: Option<String>,
+) -> Result<(), String> {
+    crate::proxy::store_password(password.as_deref().unwrap_or_default())?;
+    let proxy = state.settings.read().await.proxy.clone();
+    state.apply_proxy_settings(&proxy)
+}
+
+/// Проверить соединение с Supabase: через переданные настройки прокси (ещё не сохранённые)
+/// или через текущий клиент
+#[tauri::command]
+pub async fn test_connection(
+    state: State<'_, Arc<AppState>>,
+    proxy: Option<crate::types::ProxySettings>,
+) -> Result<crate::proxy::ConnectionTestResult, String> {
+    let cfg = state
+        .resolve_supabase_config()
+        .await
+        .ok_or("Supabase is not configured")?;
+    let http = match proxy {
+        Some(proxy) => {
+            let password = if proxy.enabled { crate::proxy::load_password()? } else { None };
+            crate::proxy::build_client(&proxy, password.as_deref())?
+        }
+        None => state.http(),
+    };
+    Ok(crate::proxy::test_connection(&http, &cfg).await)
+}
+
+/// Результат проверки совместимости с Supabase (None — ещё не проверяли или офлайн)
+#[tauri::command]
+pub async fn get_schema_handshake(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Option<crate::schema_handshake::HandshakeStatus>, String> {
+    Ok(state.get_schema_handshake().await)
+}
+
+/// Лига текущих данных, лига по ценам и заархивированные лиги
+#[tauri::command]
+pub async fn get_league_status(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::league_archive::LeagueStatus, String> {
+    Ok(state.league_status().await)
+}
+
+/// Перенести данные прошлой лиги (история, цены, рекорды) в архив и начать новую с чистыми кэшами
+#[tauri::command]
+pub async fn archive_league_data(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::league_archive::ArchivedLeague, String> {
+    state.archive_league_data().await
+}
+
+/// Просмотр заархивированной лиги (только чтение)
+#[tauri::command]
+pub async fn get_archived_league(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+) -> Result<crate::league_archive::ArchivedLeagueView, String> {
+    state.get_archived_league(&id).await
+}
+
+/// Пересчитать сессию из истории по сегодняшним ценам (исходные цифры — рядом)
+#[tauri::command]
+pub async fn revalue_session(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+) -> Result<crate::types::SessionRevaluation, String> {
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+    let record = crate::persistence::load_session_history(&user_id)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|r| r.id == id)
+        .ok_or_else(|| "Session not found".to_string())?;
+    if record.drops.is_empty() && record.manual_pending_quoted.is_empty() {
+        return Err("Session has no stored drops to re-value".to_string());
+    }
+    Ok(state.revalue_history_record(&record).await)
+}
+
+/// Дневная сводка по истории: итоги по дням (для тепловой карты) и серия дней подряд.
+/// Даты в формате YYYY-MM-DD; по умолчанию — последний год
+#[tauri::command]
+pub async fn get_daily_summary(
+    state: State<'_, Arc<AppState>>,
+    from: Option<chrono::NaiveDate>,
+    to: Option<chrono::NaiveDate>,
+) -> Result<crate::daily_summary::DailySummary, String> {
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+    let (from, to) = crate::daily_summary::resolve_range(from, to)?;
+
+    let history = crate::persistence::load_session_history(&user_id)
+        .map_err(|e| e.to_string())?;
+    let today = chrono::Local::now().date_naive();
+    Ok(crate::daily_summary::build(&history, from, to, today))
+}
+
+/// Удалить сессию из истории
+#[tauri::command]
+pub async fn delete_session_history(
+    state: State<'_, Arc<AppState>>,
+    session_id: String,
+) -> Result<bool, String> {
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+    
+    let _guard = state.history_lock.lock().await;
+    let removed = crate::persistence::delete_session_from_history(&user_id, &session_id)
+        .map_err(|e| e.to_string())?;
+    
+    if removed.is_some() {
+        log::info!("Deleted session from history: {}", session_id);
+    }
+    
+    Ok(removed.is_some())
+}
+
+/// Найти сессию в локальной истории пользователя
+fn find_history_session(
+    user_id: &str,
+    session_id: &str,
+) -> Result<crate::persistence::SessionHistoryRecord, String> {
+    crate::persistence::load_session_history(user_id)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|s| s.id == session_id)
+        .ok_or_else(|| "Session not found".to_string())
+}
+
+/// Опубликовать сессию из истории: анонимный снимок + публичная ссылка на kripika.com
+#[tauri::command]
+pub async fn share_session(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+) -> Result<String, String> {
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+    let record = find_history_session(&user_id, &id)?;
+    let api_url = state.settings.read().await.api_url.clone();
+
+    // Уже опубликована — отдаём ту же ссылку
+    if let Some(code) = &record.share_code {
+        return Ok(crate::supabase_sync::shared_session_url(&api_url, code));
+    }
+
+    let api = state
+        .supabase_client()
+        .await
+        .ok_or_else(|| "Supabase config missing".to_string())?;
+    let jwt = state
+        .get_valid_access_token(&api)
+        .await
+        .ok_or_else(|| "Not logged in".to_string())?;
+
+    let snapshot = state.build_shared_snapshot(&record).await;
+    let share_code =
+        crate::supabase_sync::create_session_share(&api, &jwt, &user_id, &snapshot).await?;
+
+    let _guard = state.history_lock.lock().await;
+    crate::persistence::update_session_in_history(&user_id, &id, |s| {
+        s.share_code = Some(share_code.clone());
+    })
+    .map_err(|e| e.to_string())?;
+
+    info!("Session {} shared with code {}", id, share_code);
+    Ok(crate::supabase_sync::shared_session_url(&api_url, &share_code))
+}
+
+/// Начать live-трансляцию текущей сессии (снимок обновляется, пока сессия идёт).
+/// Возвращает код, который напарник передаёт в spectate_session
+#[tauri::command]
+pub async fn start_live_share(state: State<'_, Arc<AppState>>) -> Result<String, String> {
+    crate::live_share::start_publishing(state.inner().clone()).await
+}
+
+/// Остановить live-трансляцию и удалить снимок с сервера
+#[tauri::command]
+pub async fn stop_live_share(state: State<'_, Arc<AppState>>) -> Result<bool, String> {
+    crate::live_share::stop_publishing(&state).await
+}
+
+/// Наблюдать за трансляцией напарника: текущий снимок сразу, дальше — событие spectate-updated
+#[tauri::command]
+pub async fn spectate_session(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+    share_code: String,
+) -> Result<crate::supabase_sync::SharedSessionSnapshot, String> {
+    crate::live_share::start_spectating(app, &state, share_code).await
+}
+
+/// Прекратить наблюдение за трансляцией
+#[tauri::command]
+pub async fn stop_spectating(state: State<'_, Arc<AppState>>) -> Result<bool, String> {
+    Ok(crate::live_share::stop_spectating(&state).await)
+}
+
+/// Создать групповую сессию и вступить в неё. Возвращает код группы для напарников
+#[tauri::command]
+pub async fn create_group_session(
+    state: State<'_, Arc<AppState>>,
+    display_name: Option<String>,
+) -> Result<String, String> {
+    crate::party::create(state.inner().clone(), display_name).await
+}
+
+/// Вступить в групповую сессию по коду (своя статистика начнёт уходить в группу)
+#[tauri::command]
+pub async fn join_group_session(
+    state: State<'_, Arc<AppState>>,
+    group_id: String,
+    display_name: Option<String>,
+) -> Result<String, String> {
+    crate::party::join(state.inner().clone(), group_id, display_name).await
+}
+
+/// Выйти из групповой сессии
+#[tauri::command]
+pub async fn leave_group_session(state: State<'_, Arc<AppState>>) -> Result<bool, String> {
+    crate::party::leave(&state).await
+}
+
+/// Сводка группы: участники, общий профит и карты, переводы для дележа поровну
+#[tauri::command]
+pub async fn get_group_stats(state: State<'_, Arc<AppState>>) -> Result<crate::party::GroupStats, String> {
+    crate::party::stats(&state).await
+}
+
+/// Дележ лута группы: возврат затрат, доли по весам, переводы FE или раздача предметов.
+/// В ответе есть готовый текст для буфера обмена
+#[tauri::command]
+pub async fn compute_loot_split(
+    participants: Vec<crate::loot_split::LootParticipant>,
+    rules: crate::loot_split::LootSplitRules,
+) -> Result<crate::loot_split::LootSplit, String> {
+    crate::loot_split::compute(&participants, &rules)
+}
+
+/// История пауз текущей сессии: когда, насколько и почему (вручную, игра закрыта, AFK)
+#[tauri::command]
+pub async fn get_pause_history(state: State<'_, Arc<AppState>>) -> Result<Vec<crate::types::PauseRecord>, String> {
+    Ok(state.get_pause_history().await)
+}
+
+/// Отформатировать суммы в FE по общим правилам: точно, коротко и по крупным валютам
+#[tauri::command]
+pub async fn format_values(
+    state: State<'_, Arc<AppState>>,
+    values: Vec<f64>,
+) -> Result<Vec<crate::value_format::FormattedValue>, String> {
+    let denominations = state.denominations().await;
+    Ok(values
+        .into_iter()
+        .map(|value| crate::value_format::format_value(value, &denominations))
+        .collect())
+}
+
+/// Отозвать публичную ссылку на сессию
+#[tauri::command]
+pub async fn unshare_session(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+) -> Result<bool, String> {
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+    let record = find_history_session(&user_id, &id)?;
+    let Some(share_code) = record.share_code else {
+        return Ok(false);
+    };
+
+    let api = state
+        .supabase_client()
+        .await
+        .ok_or_else(|| "Supabase config missing".to_string())?;
+    let jwt = state
+        .get_valid_access_token(&api)
+        .await
+        .ok_or_else(|| "Not logged in".to_string())?;
+
+    crate::supabase_sync::delete_session_share(&api, &jwt, &share_code).await?;
+    let _guard = state.history_lock.lock().await;
+    crate::persistence::update_session_in_history(&user_id, &id, |s| s.share_code = None)
+        .map_err(|e| e.to_string())?;
+
+    info!("Session {} unshared", id);
+    Ok(true)
+}
+
+/// Отправить метрики сессии из истории в лидерборд (только при включённом согласии)
+#[tauri::command]
+pub async fn submit_leaderboard_entry(
+    state: State<'_, Arc<AppState>>,
+    session_id: String,
+) -> Result<(), String> {
+    if !state.settings.read().await.leaderboard_opt_in {
+        return Err("Leaderboard submission is disabled in settings".to_string());
+    }
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+    let record = find_history_session(&user_id, &session_id)?;
+    let submission = crate::supabase_sync::LeaderboardSubmission::from_history(&record)
+        .ok_or_else(|| "Session is too short for the leaderboard".to_string())?;
+
+    let api = state
+        .supabase_client()
+        .await
+        .ok_or_else(|| "Supabase config missing".to_string())?;
+    let jwt = state
+        .get_valid_access_token(&api)
+        .await
+        .ok_or_else(|| "Not logged in".to_string())?;
+
+    crate::supabase_sync::submit_leaderboard_entry(&api, &jwt, &user_id, &submission).await?;
+    info!("Submitted session {} to leaderboard", session_id);
+    Ok(())
+}
+
+/// Лидерборд текущей лиги (постранично)
+#[tauri::command]
+pub async fn get_leaderboard(
+    state: State<'_, Arc<AppState>>,
+    metric: Option<crate::supabase_sync::LeaderboardMetric>,
+    page: Option<i32>,
+    page_size: Option<i32>,
+) -> Result<Vec<crate::supabase_sync::LeaderboardEntry>, String> {
+    let api = state
+        .supabase_client()
+        .await
+        .ok_or_else(|| "Supabase config missing".to_string())?;
+    let page_size = page_size.unwrap_or(50).clamp(1, 100);
+    let offset = page.unwrap_or(0).max(0) * page_size;
+
+    Ok(crate::supabase_sync::fetch_leaderboard(&api, metric.unwrap_or_default(), page_size, offset).await?)
+}
+
+/// Получить текущую статистику сессии
+#[tauri::command]
+pub async fn get_session_stats(
+    state: State<'_, Arc<AppState>>,
+) -> Result<SessionStats, String> {
+    Ok(state.get_session_stats().await)
+}
+
+/// Получить список дропов за сессию
+#[tauri::command]
+pub async fn get_drops(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<AggregatedDrop>, String> {
+    Ok(state.get_aggregated_drops().await)
+}
+
+/// Страница дропа за сессию: сортировка (value/quantity/recent/name), направление,
+/// фильтр по категории и offset/limit — чтобы не гонять весь список на каждое обновление
+#[tauri::command]
+pub async fn get_drops_page(
+    state: State<'_, Arc<AppState>>,
+    query: Option<crate::types::DropsQuery>,
+) -> Result<crate::types::DropsPage, String> {
+    Ok(state.get_drops_page(&query.unwrap_or_default()).await)
+}
+
+/// Кривая стоимости активной сессии (точка в минуту) — для графика профита
+#[tauri::command]
+pub async fn get_value_timeline(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<crate::types::ValuePoint>, String> {
+    Ok(state.get_value_timeline().await)
+}
+
+/// Экспорт сессии в .xlsx: активной (session_id = null) или из истории.
+/// Листы: дроп, траты, карты. Возвращает путь к файлу
+#[tauri::command]
+pub async fn export_session_xlsx(
+    state: State<'_, Arc<AppState>>,
+    path: String,
+    session_id: Option<String>,
+) -> Result<String, String> {
+    let path = xlsx_path(&path)?;
+    let language = state.display_language().await;
+    let valuation = state.valuation().await;
+    let export = match session_id {
+        None => {
+            let session = state.session.read().await.clone();
+            if session.started_at.is_none() {
+                return Err("No active session".to_string());
+            }
+            let mut drops = state.export_drop_rows(&session.drops).await;
+            let fees = state.fee_model().await;
+            let items_cache = state.items_cache.read().await;
+            drops.extend(session.manual_drops.iter().map(|m| {
+                let total_value = valuation.line_value(m.price, m.quantity);
+                let item = m.game_id.and_then(|id| items_cache.get(&id));
+                crate::xlsx_export::DropRow {
+                    name: match (language.as_str(), &m.name_ru) {
+                        ("ru", Some(name_ru)) if !name_ru.is_empty() => name_ru.clone(),
+                        _ => m.name.clone(),
+                    },
+                    category: "manual".to_string(),
+                    quantity: m.quantity,
+                    unit_price: m.price,
+                    total_value,
+                    fee: valuation.round(total_value * fees.rate(item)),
+                    icon_url: None,
+                }
+            }));
+            drop(items_cache);
+            crate::xlsx_export::SessionExport {
+                language,
+                drops,
+                expenses: session.expenses,
+                map_runs: session.map_runs,
+                valuation,
+            }
+        }
+        Some(id) => {
+            let user_id = state.get_auth_user_id().await
+                .ok_or_else(|| "Not logged in".to_string())?;
+            let record = crate::persistence::load_session_history(&user_id)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .find(|r| r.id == id)
+                .ok_or_else(|| "Session not found".to_string())?;
+            crate::xlsx_export::SessionExport {
+                language,
+                drops: state.export_drop_rows(&record.drops).await,
+                expenses: Vec::new(),
+                map_runs: Vec::new(),
+                valuation,
+            }
+        }
+    };
+    crate::xlsx_export::write_session(&path, &export).map_err(|e| e.to_string())?;
+    info!("Session exported to {}", path.display());
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Сводка активной сессии (текст, Markdown или BBCode) — копируется в буфер обмена и возвращается
+#[tauri::command]
+pub async fn copy_session_summary(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+    format: Option<crate::session_summary::SummaryFormat>,
+) -> Result<String, String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    let summary = session_summary(&state, None).await?;
+    let language = state.display_language().await;
+    let text = crate::session_summary::render(&summary, format.unwrap_or_default(), &language);
+    app.clipboard().write_text(text.clone()).map_err(|e| e.to_string())?;
+    Ok(text)
+}
+
+/// PNG-карточка сессии: активной (session_id = null) или из истории.
+/// Сохраняется в «Изображения/TLI Companion», по желанию копируется в буфер. Возвращает путь
+#[tauri::command]
+pub async fn render_summary_card(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+    session_id: Option<String>,
+    copy_to_clipboard: Option<bool>,
+) -> Result<String, String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    let summary = session_summary(&state, session_id.as_deref()).await?;
+    let language = state.display_language().await;
+    let urls: Vec<Option<String>> = summary.top_drops.iter().map(|d| d.icon_url.clone()).collect();
+    let icons = crate::summary_card::fetch_icons(&state.http(), &urls).await;
+
+    let card = tauri::async_runtime::spawn_blocking(move || {
+        let fonts = crate::summary_card::load_fonts(&language)?;
+        Ok::<_, String>(crate::summary_card::render(&summary, &icons, &language, &fonts))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let dir = crate::summary_card::cards_dir().ok_or_else(|| "No directory to save the card".to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(format!("session-{}.png", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    card.save(&path).map_err(|e| e.to_string())?;
+    info!("Summary card saved to {}", path.display());
+
+    if copy_to_clipboard.unwrap_or(false) {
+        let (width, height) = card.dimensions();
+        let image = tauri::image::Image::new_owned(card.into_raw(), width, height);
+        app.clipboard().write_image(&image).map_err(|e| e.to_string())?;
+    }
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Данные сводки: активная сессия (None) или запись истории
+async fn session_summary(
+    state: &AppState,
+    session_id: Option<&str>,
+) -> Result<crate::session_summary::SessionSummary, String> {
+    let valuation = state.valuation().await;
+    let (drops, duration_sec, maps_completed, income, expenses, fees, hourly_profit) = match session_id {
+        None => {
+            let session = state.session.read().await.clone();
+            if session.started_at.is_none() {
+                return Err("No active session".to_string());
+            }
+            let stats = state.get_session_stats().await;
+            let expenses = valuation.expenses_total(&session.expenses);
+            (session.drops, stats.duration_sec, stats.maps_completed, stats.total_value, expenses, stats.total_fee, stats.net_hourly_profit)
+        }
+        Some(id) => {
+            let user_id = state.get_auth_user_id().await
+                .ok_or_else(|| "Not logged in".to_string())?;
+            let record = crate::persistence::load_session_history(&user_id)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .find(|r| r.id == id)
+                .ok_or_else(|| "Session not found".to_string())?;
+            let hourly = valuation.per_hour(record.total_profit, record.total_duration_sec);
+            (record.drops, record.total_duration_sec, record.maps_completed, record.total_income, record.total_expenses, record.total_fee, hourly)
+        }
+    };
+    let top_drops = state
+        .export_drop_rows(&drops)
+        .await
+        .into_iter()
+        .take(crate::session_summary::SUMMARY_TOP_DROPS)
+        .map(|row| crate::session_summary::SummaryDrop {
+            name: row.name,
+            quantity: row.quantity,
+            total_value: row.total_value,
+            icon_url: row.icon_url,
+        })
+        .collect();
+    Ok(crate::session_summary::SessionSummary {
+        duration_sec,
+        maps_completed,
+        income,
+        expenses,
+        fees,
+        hourly_profit,
+        top_drops,
+    })
+}
+
+/// Экспорт сводки по истории сессий в .xlsx. Возвращает путь к файлу
+#[tauri::command]
+pub async fn export_history_xlsx(
+    state: State<'_, Arc<AppState>>,
+    path: String,
+) -> Result<String, String> {
+    let path = xlsx_path(&path)?;
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+    let records = crate::persistence::load_session_history(&user_id).map_err(|e| e.to_string())?;
+    let language = state.display_language().await;
+    let valuation = state.valuation().await;
+    crate::xlsx_export::write_history(&path, &records, &language, &valuation).map_err(|e| e.to_string())?;
+    info!("Session history exported to {} ({} sessions)", path.display(), records.len());
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Экспорт истории сессий в JSON (для переноса на другой компьютер). Возвращает путь к файлу
+#[tauri::command]
+pub async fn export_session_history(
+    state: State<'_, Arc<AppState>>,
+    path: String,
+) -> Result<String, String> {
+    let trimmed = path.trim();
+    if trimmed.is_empty() {
+        return Err("Export path is empty".to_string());
+    }
+    let mut path = std::path::PathBuf::from(trimmed);
+    if path.extension().is_none() {
+        path.set_extension("json");
+    }
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+    let records = crate::persistence::load_session_history(&user_id).map_err(|e| e.to_string())?;
+    crate::persistence::write_session_history_export(&path, &records).map_err(|e| e.to_string())?;
+    info!("Session history exported to {} ({} sessions)", path.display(), records.len());
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Импорт истории сессий, выгруженной на другом компьютере: дубликаты (тот же id или то же
+/// время начала и конца) пропускаются
+#[tauri::command]
+pub async fn import_session_history(
+    state: State<'_, Arc<AppState>>,
+    path: String,
+) -> Result<crate::history_sync::HistoryImportReport, String> {
+    let user_id = state.get_auth_user_id().await
+        .ok_or_else(|| "Not logged in".to_string())?;
+    let imported = crate::persistence::read_session_history_export(std::path::Path::new(path.trim()))
+        .map_err(|e| format!("Failed to read history file: {}", e))?;
+    let _guard = state.history_lock.lock().await;
+    let local = crate::persistence::load_session_history(&user_id).map_err(|e| e.to_string())?;
+    let (merged, report) = crate::history_sync::merge_imported(local, imported);
+    if report.added > 0 {
+        crate::persistence::save_session_history(&user_id, &merged).map_err(|e| e.to_string())?;
+    }
+    info!("Session history imported from {}: {} added, {} skipped", path, report.added, report.skipped);
+    Ok(report)
+}
+
+/// ID журнала событий: активной сессии (session_id = None) или сессии из истории
+async fn session_journal_id(state: &AppState, session_id: Option<String>) -> Result<String, String> {
+    let started_at = match session_id {
+        None => state.session.read().await.started_at.ok_or("No active session")?,
+        Some(session_id) => {
+            let user_id = state.get_auth_user_id().await
+                .ok_or_else(|| "Not logged in".to_string())?;
+            find_history_session(&user_id, &session_id)?.started_at
+        }
+    };
+    Ok(crate::event_journal::journal_id(started_at))
+}
+
+/// Экспорт журнала событий сессии (NDJSON, по строке на событие): активной (session_id = null)
+/// или из истории. Возвращает путь к файлу
+#[tauri::command]
+pub async fn export_session_journal(
+    state: State<'_, Arc<AppState>>,
+    path: String,
+    session_id: Option<String>,
+) -> Result<String, String> {
+    let trimmed = path.trim();
+    if trimmed.is_empty() {
+        return Err("Export path is empty".to_string());
+    }
+    let mut path = std::path::PathBuf::from(trimmed);
+    if path.extension().is_none() {
+        path.set_extension("ndjson");
+    }
+    let id = session_journal_id(&state, session_id).await?;
+    let events = crate::event_journal::export_journal(&id, &path)?;
+    info!("Session event journal exported to {} ({} events)", path.display(), events);
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Статистика сессии на момент времени по журналу событий (ползунок по времени сессии):
+/// активной (id = null) или из истории; until_ts = null — на последнее событие
+#[tauri::command]
+pub async fn replay_session_events(
+    state: State<'_, Arc<AppState>>,
+    id: Option<String>,
+    until_ts: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<crate::session_replay::ReplayStats, String> {
+    let journal_id = session_journal_id(&state, id).await?;
+    state.replay_session_events(&journal_id, until_ts).await
+}
+
+/// Путь для экспорта: выбран в диалоге сохранения, расширение .xlsx дописываем при необходимости
+fn xlsx_path(path: &str) -> Result<std::path::PathBuf, String> {
+    let trimmed = path.trim();
+    if trimmed.is_empty() {
+        return Err("Export path is empty".to_string());
+    }
+    let mut path = std::path::PathBuf::from(trimmed);
+    if !path.extension().map(|e| e.eq_ignore_ascii_case("xlsx")).unwrap_or(false) {
+        path.set_extension("xlsx");
+    }
+    Ok(path)
+}
+
+/// Отложить предметы на продажу (портфель профиля). Без acquired_price берётся текущая цена
+#[tauri::command]
+pub async fn add_holding(
+    state: State<'_, Arc<AppState>>,
+    game_id: i64,
+    quantity: i32,
+    acquired_price: Option<f64>,
+) -> Result<crate::types::Holding, String> {
+    if quantity <= 0 {
+        return Err("Quantity must be positive".to_string());
+    }
+    let acquired_price = match acquired_price {
+        Some(price) if price >= 0.0 => price,
+        Some(_) => return Err("Price must not be negative".to_string()),
+        None => {
+            let items_cache = state.items_cache.read().await;
+            let prices = state.prices_cache.read().await;
+            crate::valuation::Valuation::unit_price(game_id, &items_cache, &prices)
+                .ok_or_else(|| "No current price for this item, enter it manually".to_string())?
+        }
+    };
+
+    let _guard = state.portfolio_lock.lock().await;
+    let profile = state.profile_key().await;
+    let mut holdings = crate::persistence::load_portfolio(&profile).map_err(|e| e.to_string())?;
+    let holding = crate::types::Holding {
+        id: uuid::Uuid::new_v4().to_string(),
+        game_id,
+        quantity,
+        acquired_price,
+        acquired_at: chrono::Utc::now(),
+    };
+    holdings.push(holding.clone());
+    crate::persistence::save_portfolio(&profile, &holdings).map_err(|e| e.to_string())?;
+    info!("Holding added: {} x{} @ {}", game_id, quantity, acquired_price);
+    Ok(holding)
+}
+
+/// Убрать партию из портфеля (продана или использована). quantity — частично, без него — целиком
+#[tauri::command]
+pub async fn remove_holding(
+    state: State<'_, Arc<AppState>>,
+    holding_id: String,
+    quantity: Option<i32>,
+) -> Result<bool, String> {
+    let _guard = state.portfolio_lock.lock().await;
+    let profile = state.profile_key().await;
+    let mut holdings = crate::persistence::load_portfolio(&profile).map_err(|e| e.to_string())?;
+    let Some(index) = holdings.iter().position(|h| h.id == holding_id) else {
+        return Ok(false);
+    };
+    match quantity {
+        Some(q) if q <= 0 => return Err("Quantity must be positive".to_string()),
+        Some(q) if q < holdings[index].quantity => holdings[index].quantity -= q,
+        _ => {
+            holdings.remove(index);
+        }
+    }
+    crate::persistence::save_portfolio(&profile, &holdings).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// Портфель, переоценённый текущими ценами, с нереализованной прибылью
+#[tauri::command]
+pub async fn get_portfolio_value(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::types::PortfolioValue, String> {
+    let profile = state.profile_key().await;
+    let holdings = crate::persistence::load_portfolio(&profile).map_err(|e| e.to_string())?;
+    Ok(state.value_portfolio(holdings).await)
+}
+
+/// Проверить, активна ли сессия
+#[tauri::command]
+pub async fn is_session_active(
+    state: State<'_, Arc<AppState>>,
+) -> Result<bool, String> {
+    Ok(state.is_session_active().await)
+}
+
+/// Получить настройки приложения
+#[tauri::command]
+pub async fn get_settings(
+    state: State<'_, Arc<AppState>>,
+) -> Result<AppSettings, String> {
+    let settings = state.settings.read().await;
+    Ok(settings.clone())
+}
+
+/// Сохранить настройки приложения
+#[tauri::command]
+pub async fn save_settings(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+    settings: AppSettings,
+) -> Result<(), String> {
+    let mut settings = settings;
+    settings.language = crate::types::normalize_language(&settings.language);
+    if !crate::types::LOG_UTC_OFFSET_MINUTES.contains(&settings.log_utc_offset_minutes) {
+        return Err(format!(
+            "Log UTC offset must be between -12:00 and +14:00, got {} min",
+            settings.log_utc_offset_minutes
+        ));
+    }
+    if settings.live_stats.enabled {
+        crate::live_stats::validated_path(&settings.live_stats)?;
+    }
+    let mut current = state.settings.write().await;
+    let client_changed = current.game_client != settings.game_client;
+    if current.proxy != settings.proxy {
+        // Неверный прокси не сохраняем и не применяем: остаются прежние настройки и клиент
+        state.apply_proxy_settings(&settings.proxy)?;
+    }
+    if current.click_through_hotkey != settings.click_through_hotkey {
+        crate::overlay_mode::register_hotkey(&app, Some(&current.click_through_hotkey), &settings.click_through_hotkey)?;
+    }
+    // Сервер меняется только через set_backend_endpoint (после проверки).
+    // Поля, которые ведёт backend через свои команды, из копии фронтенда не берём:
+    // она могла устареть (окно-компаньон двигали, пока открыты настройки)
+    settings.backend = current.backend.clone();
+    settings.companion_window = current.companion_window.clone();
+    settings.log_watch_mode = current.log_watch_mode;
+    settings.log_poll_interval_ms = current.log_poll_interval_ms;
+    settings.extra_log_paths = current.extra_log_paths.clone();
+    settings.leaderboard_opt_in = current.leaderboard_opt_in;
+    settings.detached_panels = current.detached_panels.clone();
+    let controller_changed = current.controller_api != settings.controller_api;
+    *current = settings;
+    info!("Settings saved");
+    drop(current);
+    state.save_settings_to_disk().await;
+    state.sync_watcher_config().await;
+    if controller_changed {
+        let controller = state.settings.read().await.controller_api.clone();
+        crate::controller_api::apply(&app, &controller).await;
+    }
+    // Другой клиент игры — другой формат строк лога
+    if client_changed {
+        state.apply_local_log_patterns().await?;
+    }
+    // Язык отображения влияет на имена в дропе — рассылаем обновлённый снимок
+    state.notify_changed();
+    Ok(())
+}
+
+/// Показать окно-компаньон с живой статистикой
+#[tauri::command]
+pub async fn show_companion_window(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<CompanionWindowSettings, String> {
+    let cfg = state.set_companion_visible(true).await;
+    crate::companion_window::show(&app, &cfg).map_err(|e| e.to_string())?;
+    Ok(cfg)
+}
+
+/// Спрятать окно-компаньон
+#[tauri::command]
+pub async fn hide_companion_window(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    state.set_companion_visible(false).await;
+    crate::companion_window::hide(&app).map_err(|e| e.to_string())
+}
+
+/// Переключить окно-компаньон. Возвращает true, если окно теперь видно
+#[tauri::command]
+pub async fn toggle_companion_window(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<bool, String> {
+    crate::companion_window::toggle(&app, &state).await.map_err(|e| e.to_string())
+}
+
+/// Сменить уровень лога приложения на лету (сохраняется в настройки)
+#[tauri::command]
+pub async fn set_log_level(
+    state: State<'_, Arc<AppState>>,
+    level: String,
+) -> Result<String, String> {
+    let filter = crate::app_logs::parse_level(&level)?;
+    crate::app_logs::set_level(filter);
+    let level = filter.to_string().to_lowercase();
+    state.settings.write().await.log_level = level.clone();
+    state.save_settings_to_disk().await;
+    info!("Log level set to {}", level);
+    Ok(level)
+}
+
+/// Последние строки лога приложения (по умолчанию 200) — чтобы вставить в обращение в поддержку
+#[tauri::command]
+pub async fn get_recent_app_logs(n: Option<usize>) -> Result<Vec<String>, String> {
+    Ok(crate::app_logs::recent_lines(n.unwrap_or(200)))
+}
+
+/// Включить/выключить режим «сквозь клики» главного окна
+#[tauri::command]
+pub async fn set_click_through(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+    enabled: bool,
+) -> Result<crate::overlay_mode::OverlayMode, String> {
+    crate::overlay_mode::set_click_through(&app, &state, enabled).await.map_err(|e| e.to_string())
+}
+
+/// Переключить режим «сквозь клики» (то же делает глобальный хоткей)
+#[tauri::command]
+pub async fn toggle_click_through(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::overlay_mode::OverlayMode, String> {
+    let enabled = !state.click_through.load(std::sync::atomic::Ordering::Relaxed);
+    crate::overlay_mode::set_click_through(&app, &state, enabled).await.map_err(|e| e.to_string())
+}
+
+/// Прозрачность главного окна (0.5 - 1.0)
+#[tauri::command]
+pub async fn set_overlay_opacity(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+    opacity: f64,
+) -> Result<crate::overlay_mode::OverlayMode, String> {
+    crate::overlay_mode::set_opacity(&app, &state, opacity).await.map_err(|e| e.to_string())
+}
+
+/// Отсоединить панель (статистика, дроп, лента лута) в отдельное окно. Возвращает label окна
+#[tauri::command]
+pub async fn open_panel_window(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+    kind: crate::panel_windows::PanelKind,
+) -> Result<String, String> {
+    let panel = state.set_panel_open(kind, true).await;
+    crate::panel_windows::open(&app, &panel).map_err(|e| e.to_string())?;
+    Ok(kind.label())
+}
+
+/// Закрыть отсоединённую панель
+#[tauri::command]
+pub async fn close_panel_window(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+    kind: crate::panel_windows::PanelKind,
+) -> Result<(), String> {
+    state.set_panel_open(kind, false).await;
+    crate::panel_windows::close(&app, kind).map_err(|e| e.to_string())
+}
+
+/// Подписать окно на события состояния (по умолчанию — вызывающее окно).
+/// Пустой список — окно снова получает все события
+#[tauri::command]
+pub async fn subscribe_window_events(
+    window: tauri::WebviewWindow,
+    state: State<'_, Arc<AppState>>,
+    window_label: Option<String>,
+    events: Vec<String>,
+) -> Result<(), String> {
+    let label = window_label.unwrap_or_else(|| window.label().to_string());
+    let mut subscriptions = state.window_subscriptions.lock().unwrap();
+    if events.is_empty() {
+        subscriptions.remove(&label);
+    } else {
+        log::debug!("Window {} subscribed to {:?}", label, events);
+        subscriptions.set(&label, events);
+    }
+    Ok(())
+}
+
+/// Задать позицию/размер/прозрачность окна-компаньона (None — не менять)
+#[tauri::command]
+pub async fn set_companion_window_geometry(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+    x: Option<i32>,
+    y: Option<i32>,
+    width: Option<u32>,
+    height: Option<u32>,
+    opacity: Option<f64>,
+) -> Result<CompanionWindowSettings, String> {
+    let cfg = state.update_companion_window(|c| {
+        if let Some(x) = x { c.x = x; }
+        if let Some(y) = y { c.y = y; }
+        if let Some(width) = width { c.width = width; }
+        if let Some(height) = height { c.height = height; }
+        if let Some(opacity) = opacity { c.opacity = opacity; }
+    }).await;
+    state.save_settings_to_disk().await;
+    crate::companion_window::apply(&app, &cfg).map_err(|e| e.to_string())?;
+    Ok(cfg)
+}
+
+/// Проверить обновления в канале из настроек (stable/beta)
+#[tauri::command]
+pub async fn check_for_updates(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::updater::UpdateInfo, String> {
+    let channel = state.settings.read().await.update_channel;
+    crate::updater::check(&app, channel).await
+}
+
+/// Скачать и установить обновление, найденное check_for_updates.
+/// Прогресс приходит событием `update-progress`; перезапуск делает фронтенд.
+#[tauri::command]
+pub async fn install_update(app: tauri::AppHandle) -> Result<(), String> {
+    crate::updater::install(&app).await
+}
+
+/// Собрать диагностический архив (логи приложения, обезличенные настройки, статистика парсера,
+/// последние нераспознанные строки). Возвращает путь к zip для приложения к баг-репорту
+#[tauri::command]
+pub async fn generate_diagnostics_bundle(
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, String> {
+    let (parser_stats, unparsed_lines) = {
+        let parser = state.log_parser.lock().map_err(|e| e.to_string())?;
+        (parser.stats(), parser.recent_unparsed())
+    };
+    let manifest = crate::diagnostics::DiagnosticsManifest {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        log_path_configured: state.get_log_path().await.is_some(),
+        log_sources: state.log_sources.read().await.len(),
+        items_cached: state.items_cache.read().await.len(),
+        prices_cached: state.prices_cache.read().await.len(),
+        session_active: state.is_session_active().await,
+    };
+    let input = crate::diagnostics::DiagnosticsInput {
+        manifest,
+        settings: state.settings.read().await.clone(),
+        parser_stats,
+        unparsed_lines,
+        app_log_lines: crate::app_logs::recent_lines(crate::diagnostics::APP_LOG_LINES),
+    };
+
+    let path = crate::diagnostics::write_bundle(&input).map_err(|e| e.to_string())?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Счётчики парсера основного лога: события по типам, нераспознанные строки и их примеры.
+/// Если после патча игры дропы перестали считаться — здесь будет расти unknown_formats
+#[tauri::command]
+pub async fn get_parser_stats(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::log_parser::ParserStats, String> {
+    let parser = state.log_parser.lock().map_err(|e| e.to_string())?;
+    Ok(parser.stats())
+}
+
+/// Перезагрузить паттерны лога без перезапуска: локальный override
+/// (tli-companion/log_patterns.json) и свежий набор из Supabase
+#[tauri::command]
+pub async fn reload_parser_patterns(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::log_patterns::PatternsReloadResult, String> {
+    state.reload_log_patterns().await
+}
+
+/// Получить текущий механизм отслеживания лог-файла
+#[tauri::command]
+pub async fn get_watcher_config(
+    state: State<'_, Arc<AppState>>,
+) -> Result<WatcherConfig, String> {
+    Ok(state.get_watcher_config())
+}
+
+/// Сменить механизм отслеживания лог-файла (native/poll) и интервал опроса без перезапуска
+#[tauri::command]
+pub async fn set_watcher_config(
+    state: State<'_, Arc<AppState>>,
+    mode: LogWatchMode,
+    poll_interval_ms: u64,
+) -> Result<WatcherConfig, String> {
+    Ok(state.set_watcher_config(mode, poll_interval_ms).await)
+}
+
+/// Получить информацию о предмете по game_id
+#[tauri::command]
+pub async fn get_item_info(
+    state: State<'_, Arc<AppState>>,
+    game_id: i64,
+) -> Result<Option<ItemInfo>, String> {
+    Ok(state.get_item_info(game_id).await)
+}
+
+/// Загрузить кэш предметов
+#[tauri::command]
+pub async fn load_items_cache(
+    state: State<'_, Arc<AppState>>,
+    items: Vec<ItemInfo>,
+) -> Result<(), String> {
+    state.load_items_cache(items).await;
+    Ok(())
+}
+
+/// Перечитать список предметов (после патча игры) и показать новые,
+/// переименованные и перенесённые в другую категорию
+#[tauri::command]
+pub async fn refresh_items_cache(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::items_diff::ItemsDiff, String> {
+    state.refresh_items_cache().await
+}
+
+/// Категории предметов с именами на языке отображения, группами и порядком
+#[tauri::command]
+pub async fn get_categories(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<crate::categories::CategoryView>, String> {
+    Ok(state.get_categories().await)
+}
+
+/// Встроенные звуки на дроп
+#[tauri::command]
+pub async fn list_bundled_sounds() -> Result<Vec<crate::drop_sounds::BundledSound>, String> {
+    Ok(crate::drop_sounds::bundled_sounds())
+}
+
+/// Проиграть звук (встроенный или файл) для проверки правила
+#[tauri::command]
+pub async fn preview_drop_sound(
+    state: State<'_, Arc<AppState>>,
+    sound: String,
+    volume: Option<f32>,
+) -> Result<(), String> {
+    state.preview_drop_sound(&sound, volume).await;
+    Ok(())
+}
+
+/// Проговорить пример голосового объявления
+#[tauri::command]
+pub async fn preview_tts(state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    state.preview_tts().await;
+    Ok(())
+}
+
+/// Адрес и токен локального API для плагина Stream Deck
+#[tauri::command]
+pub async fn get_controller_api_info(
+    state: State<'_, Arc<AppState>>,
+) -> Result<crate::controller_api::ControllerApiInfo, String> {
+    let port = state.settings.read().await.controller_api.port;
+    let running = state.controller_api.read().await.as_ref().map(|s| s.port == port).unwrap_or(false);
+    Ok(crate::controller_api::ControllerApiInfo {
+        running,
+        url: format!("http://127.0.0.1:{}", port),
+        token: crate::controller_api::load_or_create_token()?,
+    })
+}
+
+/// Выпустить новый токен локального API (сервер перезапускается с ним)
 #[tauri::command]
-pub async fn set_paused(
+pub async fn regenerate_controller_api_token(
+    app: tauri::AppHandle,
     state: State<'_, Arc<AppState>>,
-    paused: bool,
-) -> Result<(), String> {
-    state.set_paused(paused).await;
-    Ok(())
+) -> Result<crate::controller_api::ControllerApiInfo, String> {
+    crate::controller_api::regenerate_token()?;
+    let controller = state.settings.read().await.controller_api.clone();
+    crate::controller_api::apply(&app, &controller).await;
+    get_controller_api_info(state).await
 }
 
-/// Обновить время сессии (вызывается фронтендом)
+/// Сохранить пароль obs-websocket в keychain (пустой/None — удалить)
 #[tauri::command]
-pub async fn update_session_duration(
-    state: State<'_, Arc<AppState>>,
-    duration_sec: i32,
-) -> Result<(), String> {
-    state.update_session_duration(duration_sec).await;
-    Ok(())
+pub async fn set_obs_password(password: Option<String>) -> Result<(), String> {
+    crate::obs::store_password(password.as_deref().unwrap_or_default())
 }
 
-/// Добавить трату вручную
+/// Проверить соединение с OBS: по переданным (ещё не сохранённым) настройкам или текущим
 #[tauri::command]
-pub async fn add_expense(
+pub async fn test_obs_connection(
     state: State<'_, Arc<AppState>>,
-    id: String,
-    game_id: Option<i64>,
-    name: String,
-    name_ru: Option<String>,
-    quantity: i32,
-    price: f64,
-) -> Result<(), String> {
-    state.add_expense(id, game_id, name, name_ru, quantity, price).await;
-    Ok(())
+    obs: Option<crate::types::ObsSettings>,
+) -> Result<crate::obs::ObsStatus, String> {
+    let obs = match obs {
+        Some(obs) => obs,
+        None => state.settings.read().await.obs.clone(),
+    };
+    crate::obs::test_connection(&obs).await
 }
 
-/// Удалить трату
+/// Выполнить действие OBS вручную (кнопка проверки у правила)
 #[tauri::command]
-pub async fn remove_expense(
+pub async fn test_obs_action(
     state: State<'_, Arc<AppState>>,
-    id: String,
+    action: crate::types::ObsAction,
+    obs: Option<crate::types::ObsSettings>,
 ) -> Result<(), String> {
-    state.remove_expense(&id).await;
-    Ok(())
+    crate::obs::validate_action(&action)?;
+    let obs = match obs {
+        Some(obs) => obs,
+        None => state.settings.read().await.obs.clone(),
+    };
+    crate::obs::test_action(&obs, &action).await
 }
 
-/// Получить список трат
+/// Сохранить OAuth-токен Twitch в keychain (пустой/None — удалить)
 #[tauri::command]
-pub async fn get_expenses(
+pub async fn set_twitch_token(
     state: State<'_, Arc<AppState>>,
-) -> Result<Vec<crate::types::ExpenseEntry>, String> {
-    Ok(state.get_expenses().await)
+    token: Option<String>,
+) -> Result<crate::twitch::TwitchStatus, String> {
+    crate::twitch::store_token(token.as_deref().unwrap_or_default())?;
+    Ok(state.twitch.status())
 }
 
-/// Поиск предметов по названию
+/// Есть ли токен Twitch и не приглушены ли объявления
 #[tauri::command]
-pub async fn search_items(
+pub async fn get_twitch_status(
     state: State<'_, Arc<AppState>>,
-    query: String,
-) -> Result<Vec<crate::types::ItemInfo>, String> {
-    Ok(state.search_items(&query).await)
+) -> Result<crate::twitch::TwitchStatus, String> {
+    Ok(state.twitch.status())
 }
 
-/// Получить все кэшированные цены
+/// Приглушить/вернуть объявления в чат до конца сессии (None — переключить)
 #[tauri::command]
-pub async fn get_cached_prices(
+pub async fn toggle_twitch_chat(
     state: State<'_, Arc<AppState>>,
-) -> Result<std::collections::HashMap<i64, f64>, String> {
-    Ok(state.get_all_prices().await)
+    muted: Option<bool>,
+) -> Result<crate::twitch::TwitchStatus, String> {
+    let muted = muted.unwrap_or(!state.twitch.is_muted());
+    state.twitch.set_muted(muted);
+    Ok(state.twitch.status())
 }
 
-/// Добавить ручной дроп (для уников/экипировки)
+/// Отправить в чат пример объявления: по переданным (ещё не сохранённым) настройкам или текущим
 #[tauri::command]
-pub async fn add_manual_drop(
+pub async fn send_twitch_test_message(
     state: State<'_, Arc<AppState>>,
-    id: String,
-    game_id: Option<i64>,
-    name: String,
-    name_ru: Option<String>,
-    quantity: i32,
-    price: f64,
+    twitch: Option<crate::types::TwitchSettings>,
 ) -> Result<(), String> {
-    state.add_manual_drop(id, game_id, name, name_ru, quantity, price).await;
-    Ok(())
+    let twitch = match twitch {
+        Some(twitch) => twitch,
+        None => state.settings.read().await.twitch.clone(),
+    };
+    let text = crate::twitch::drop_message(&twitch, "Flame Elementium", 1, twitch.drop_min_value);
+    crate::twitch::send_test_message(&twitch, text).await
 }
 
-/// Удалить ручной дроп
+/// Записать live_stats.json сейчас (проверка пути из настроек); возвращает путь к файлу
 #[tauri::command]
-pub async fn remove_manual_drop(
+pub async fn write_live_stats(
     state: State<'_, Arc<AppState>>,
-    id: String,
-) -> Result<(), String> {
-    state.remove_manual_drop(&id).await;
-    Ok(())
+    live_stats: Option<crate::types::LiveStatsSettings>,
+) -> Result<String, String> {
+    let live_stats = match live_stats {
+        Some(live_stats) => live_stats,
+        None => state.settings.read().await.live_stats.clone(),
+    };
+    let path = crate::live_stats::write_snapshot(&state, &live_stats).await?;
+    Ok(path.display().to_string())
 }
 
-/// Получить список ручного дропа
+/// Подобранные предметы, которых нет в справочнике (с числом подборов и именем из лога)
 #[tauri::command]
-pub async fn get_manual_drops(
+pub async fn get_unknown_items(
     state: State<'_, Arc<AppState>>,
-) -> Result<Vec<crate::types::ManualDropEntry>, String> {
-    Ok(state.get_manual_drops().await)
+) -> Result<Vec<crate::unknown_items::UnknownItem>, String> {
+    Ok(state.get_unknown_items())
 }
 
-/// Завершить сессию фарма
+/// Отправить неизвестные предметы на сервер для пополнения справочника. Возвращает число позиций
 #[tauri::command]
-pub async fn end_session(
+pub async fn report_unknown_items(
     state: State<'_, Arc<AppState>>,
-    _app_handle: tauri::AppHandle,
-) -> Result<SessionStats, String> {
-    // ВАЖНО: сначала берём финальные stats и данные сессии, потом сбрасываем состояние.
-    let stats = state.get_session_stats().await;
-    
-    // Get session data before reset (and update duration from wall-clock)
-    let session = {
-        let mut s = state.session.write().await;
-        // Update duration to wall-clock time (not just map time)
-        s.total_duration_sec = stats.duration_sec;
-        s.clone()
-    };
-    
-    // Calculate totals
-    let total_expenses: f64 = session.expenses.iter().map(|e| e.price * e.quantity as f64).sum();
-    let total_income = stats.total_value;
-    let total_profit = total_income - total_expenses;
-    
-    // Save to local history (if logged in)
-    if let Some(user_id) = state.get_auth_user_id().await {
-        let history_record = crate::persistence::SessionHistoryRecord {
-            id: uuid::Uuid::new_v4().to_string(),
-            started_at: session.started_at.unwrap_or_else(chrono::Utc::now),
-            ended_at: chrono::Utc::now(),
-            maps_completed: session.maps_completed,
-            total_duration_sec: stats.duration_sec,
-            total_profit,
-            total_expenses,
-            total_income,
-            remote_id: None, // Not syncing to cloud anymore
-        };
-        
-        if let Err(e) = crate::persistence::add_session_to_history(&user_id, history_record) {
-            log::warn!("Failed to save session to local history: {}", e);
-        } else {
-            log::info!("Session saved to local history");
-        }
-    }
-    
-    // Reset session state
-    let _session_data = state.end_session().await;
-    Ok(stats)
+) -> Result<usize, String> {
+    state.report_unknown_items().await
 }
 
-/// Получить историю сессий пользователя (локально)
+/// Очистить список неизвестных предметов
 #[tauri::command]
-pub async fn get_session_history(
+pub async fn clear_unknown_items(
     state: State<'_, Arc<AppState>>,
-    limit: Option<i32>,
-) -> Result<Vec<crate::persistence::SessionHistoryRecord>, String> {
-    let user_id = state.get_auth_user_id().await
-        .ok_or_else(|| "Not logged in".to_string())?;
-    
-    let mut sessions = crate::persistence::load_session_history(&user_id)
-        .map_err(|e| e.to_string())?;
-    
-    // Apply limit
-    let limit = limit.unwrap_or(20) as usize;
-    sessions.truncate(limit);
-    
-    Ok(sessions)
+) -> Result<(), String> {
+    state.clear_unknown_items();
+    Ok(())
 }
 
-/// Удалить сессию из истории
+/// Согласие на отправку метрик сессий в лидерборд
 #[tauri::command]
-pub async fn delete_session_history(
+pub async fn set_leaderboard_opt_in(state: State<'_, Arc<AppState>>, enabled: bool) -> Result<(), String> {
+    state.settings.write().await.leaderboard_opt_in = enabled;
+    state.save_settings_to_disk().await;
+    info!("Leaderboard opt-in: {}", enabled);
+    Ok(())
+}
+
+/// Обновить цену предмета
+#[tauri::command]
+pub async fn update_item_price(
     state: State<'_, Arc<AppState>>,
-    session_id: String,
-) -> Result<bool, String> {
-    let user_id = state.get_auth_user_id().await
-        .ok_or_else(|| "Not logged in".to_string())?;
-    
-    let removed = crate::persistence::delete_session_from_history(&user_id, &session_id)
-        .map_err(|e| e.to_string())?;
-    
-    if removed.is_some() {
-        log::info!("Deleted session from history: {}", session_id);
+    game_id: i64,
+    price: f64,
+    tier: Option<i32>,
+) -> Result<(), String> {
+    match tier {
+        Some(tier) => state.pin_tier_price(game_id, tier, price).await,
+        None => state.pin_price(game_id, price).await,
     }
-    
-    Ok(removed.is_some())
+    Ok(())
 }
 
-/// Получить текущую статистику сессии
+/// Скорректировать количество предмета в дропе сессии (с записью в аудит)
 #[tauri::command]
-pub async fn get_session_stats(
+pub async fn adjust_drop_quantity(
     state: State<'_, Arc<AppState>>,
-) -> Result<SessionStats, String> {
-    Ok(state.get_session_stats().await)
+    game_id: i64,
+    delta: i32,
+    reason: String,
+) -> Result<crate::types::QuantityAdjustment, String> {
+    state.adjust_drop_quantity(game_id, delta, reason).await
 }
 
-/// Получить список дропов за сессию
+/// Аудит ручных корректировок количества в текущей сессии
 #[tauri::command]
-pub async fn get_drops(
+pub async fn get_drop_adjustments(
     state: State<'_, Arc<AppState>>,
-) -> Result<Vec<AggregatedDrop>, String> {
-    Ok(state.get_aggregated_drops().await)
+) -> Result<Vec<crate::types::QuantityAdjustment>, String> {
+    Ok(state.get_drop_adjustments().await)
 }
 
-/// Проверить, активна ли сессия
+/// Отменить последнюю ручную правку сессии (трата, ручной дроп, цена)
 #[tauri::command]
-pub async fn is_session_active(
+pub async fn undo_last(
     state: State<'_, Arc<AppState>>,
-) -> Result<bool, String> {
-    Ok(state.is_session_active().await)
+) -> Result<Option<crate::state::SessionEdit>, String> {
+    Ok(state.undo_last().await)
 }
 
-/// Получить настройки приложения
+/// Повторить последнюю отменённую правку
 #[tauri::command]
-pub async fn get_settings(
+pub async fn redo_last(
     state: State<'_, Arc<AppState>>,
-) -> Result<AppSettings, String> {
-    let settings = state.settings.read().await;
-    Ok(settings.clone())
+) -> Result<Option<crate::state::SessionEdit>, String> {
+    Ok(state.redo_last().await)
 }
 
-/// Сохранить настройки приложения
+/// Замер парсера на файле-образце: строк/с, события по типам, время каждого паттерна.
+/// max_lines ограничивает прогон первыми N строками
 #[tauri::command]
-pub async fn save_settings(
+pub async fn benchmark_parser(
     state: State<'_, Arc<AppState>>,
-    settings: AppSettings,
-) -> Result<(), String> {
-    let mut current = state.settings.write().await;
-    *current = settings;
-    info!("Settings saved");
-    drop(current);
-    state.save_settings_to_disk().await;
-    Ok(())
+    path: String,
+    max_lines: Option<usize>,
+) -> Result<crate::parser_bench::ParserBenchmark, String> {
+    let patterns = state.log_patterns.read().await.compile()?;
+    let report = tokio::task::spawn_blocking(move || {
+        crate::parser_bench::run(std::path::Path::new(&path), patterns, max_lines)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    info!(
+        "Parser benchmark: {} lines in {:.1} ms ({:.0} lines/s)",
+        report.lines, report.parse_ms, report.lines_per_sec
+    );
+    Ok(report)
 }
 
-/// Получить информацию о предмете по game_id
+/// Размеры кэшей предметов и цен, примерный объём в памяти и действующие лимиты
 #[tauri::command]
-pub async fn get_item_info(
+pub async fn get_cache_stats(
     state: State<'_, Arc<AppState>>,
-    game_id: i64,
-) -> Result<Option<ItemInfo>, String> {
-    Ok(state.get_item_info(game_id).await)
+) -> Result<crate::cache_eviction::CacheStats, String> {
+    Ok(state.cache_stats().await)
 }
 
-/// Загрузить кэш предметов
+/// Повреждённые файлы данных, найденные при загрузке: восстановлены из .bak или сброшены
 #[tauri::command]
-pub async fn load_items_cache(
+pub async fn get_startup_warnings() -> Result<Vec<crate::persistence::StartupWarning>, String> {
+    Ok(crate::persistence::startup_warnings())
+}
+
+/// Счётчики фильтра шумных строк лога (по паттернам)
+#[tauri::command]
+pub async fn get_noise_filter_stats(
     state: State<'_, Arc<AppState>>,
-    items: Vec<ItemInfo>,
-) -> Result<(), String> {
-    state.load_items_cache(items).await;
-    Ok(())
+) -> Result<crate::noise_filter::NoiseFilterStats, String> {
+    Ok(state.noise_filter.stats())
 }
 
-/// Обновить цену предмета
+/// Все найденные лог-файлы игры (библиотеки Steam, реестр, типичные пути),
+/// самые свежие первыми — для выбора в UI
 #[tauri::command]
-pub async fn update_item_price(
+pub async fn list_log_candidates(
     state: State<'_, Arc<AppState>>,
-    game_id: i64,
-    price: f64,
-) -> Result<(), String> {
-    state.update_price(game_id, price).await;
-    Ok(())
+) -> Result<Vec<crate::file_watcher::LogCandidate>, String> {
+    let client = state.settings.read().await.game_client;
+    tokio::task::spawn_blocking(move || crate::file_watcher::discover_log_candidates(client))
+        .await
+        .map_err(|e| e.to_string())
 }
 
 /// Получить путь к логам
@@ -435,11 +2458,10 @@ pub async fn auth_status(state: State<'_, Arc<AppState>>) -> Result<AuthStatus,
 /// Войти через kripika.com (единая точка входа)
 #[tauri::command]
 pub async fn auth_sign_in_kripika(state: State<'_, Arc<AppState>>) -> Result<AuthStatus, String> {
-    let cfg = state
-        .resolve_supabase_config()
+    let api = state
+        .supabase_client()
         .await
         .ok_or_else(|| "Supabase config missing".to_string())?;
-    let http = reqwest::Client::new();
     let api_url = {
         let s = state.settings.read().await;
         s.api_url.clone()
@@ -452,7 +2474,7 @@ pub async fn auth_sign_in_kripika(state: State<'_, Arc<AppState>>) -> Result<Aut
         *lock = Some(cancel.clone());
     }
 
-    let sess = crate::auth::sign_in_via_kripika(&http, &cfg, &api_url, cancel.clone()).await?;
+    let sess = crate::auth::sign_in_via_kripika(&api, &api_url, cancel.clone()).await?;
     state.set_auth_session(Some(sess)).await;
 
     // Clear cancel flag after success
@@ -463,6 +2485,78 @@ pub async fn auth_sign_in_kripika(state: State<'_, Arc<AppState>>) -> Result<Aut
     auth_status(state).await
 }
 
+/// Войти по коду устройства (без локального порта — для строгих файрволов).
+/// Код для подтверждения приходит в UI событием auth-device-code, браузер открывается сам.
+#[tauri::command]
+pub async fn auth_sign_in_device(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<AuthStatus, String> {
+    use tauri::Emitter;
+
+    let cfg = state
+        .resolve_supabase_config()
+        .await
+        .ok_or_else(|| "Supabase config missing".to_string())?;
+    let http = state.http();
+    let api_url = {
+        let s = state.settings.read().await;
+        s.api_url.clone()
+    };
+
+    let device = crate::auth::request_device_code(&http, &cfg, &api_url).await?;
+    if let Err(e) = app.emit("auth-device-code", &device) {
+        log::debug!("Failed to emit auth-device-code event: {}", e);
+    }
+    let open_url = device.verification_uri_complete.as_deref().unwrap_or(&device.verification_uri);
+    if let Err(e) = open::that(open_url) {
+        // Не страшно: UI показывает ссылку и код
+        log::warn!("Failed to open browser for device login: {}", e);
+    }
+
+    // Отмена — та же, что и для OAuth (auth_cancel_login)
+    let cancel = Arc::new(AtomicBool::new(false));
+    {
+        let mut lock = state.auth_oauth_cancel.write().await;
+        *lock = Some(cancel.clone());
+    }
+
+    let result = crate::auth::poll_device_token(&http, &api_url, &device, cancel).await;
+    {
+        let mut lock = state.auth_oauth_cancel.write().await;
+        *lock = None;
+    }
+    state.set_auth_session(Some(result?)).await;
+    auth_status(state).await
+}
+
+/// Войти по вставленному refresh token (для машин без браузера, например VM рядом с игровым ПК).
+/// Токен проверяется обменом на сессию; в keychain сохраняется уже новый, ротированный.
+#[tauri::command]
+pub async fn auth_sign_in_with_token(
+    state: State<'_, Arc<AppState>>,
+    refresh_token: String,
+) -> Result<AuthStatus, String> {
+    let refresh_token = refresh_token.trim();
+    if refresh_token.is_empty() {
+        return Err("Token is empty".to_string());
+    }
+    let api = state
+        .supabase_client()
+        .await
+        .ok_or_else(|| "Supabase config missing".to_string())?;
+
+    let sess = crate::auth::refresh_access_token(&api, refresh_token)
+        .await
+        .map_err(|e| match e {
+            crate::auth::RefreshError::Rejected(_) => "Token is invalid or expired".to_string(),
+            crate::auth::RefreshError::Transient(msg) => msg,
+        })?;
+    info!("Signed in with pasted token: user_id={:?}", sess.user_id);
+    state.set_auth_session(Some(sess)).await;
+    auth_status(state).await
+}
+
 /// Отменить текущий процесс OAuth (освобождает порт 49733 сразу)
 #[tauri::command]
 pub async fn auth_cancel_login(state: State<'_, Arc<AppState>>) -> Result<(), String> {
@@ -478,11 +2572,66 @@ pub async fn auth_cancel_login(state: State<'_, Arc<AppState>>) -> Result<(), St
 /// Выйти — удаляем refresh token из keychain и очищаем in-memory сессию
 #[tauri::command]
 pub async fn auth_sign_out(state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    if let Some(user_id) = state.get_auth_user_id().await {
+        state.forget_linked_account(&user_id);
+    }
     crate::auth::clear_refresh_token()?;
     state.set_auth_session(None).await;
     Ok(())
 }
 
+/// Привязанный к устройству аккаунт (для переключателя)
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountInfo {
+    pub user_id: String,
+    pub email: Option<String>,
+    pub last_used_at: chrono::DateTime<chrono::Utc>,
+    pub is_active: bool,
+}
+
+/// Список аккаунтов, в которые входили на этом устройстве (последний использованный первым)
+#[tauri::command]
+pub async fn list_accounts(state: State<'_, Arc<AppState>>) -> Result<Vec<AccountInfo>, String> {
+    let active = state.get_auth_user_id().await;
+    let accounts = crate::persistence::load_linked_accounts().map_err(|e| e.to_string())?;
+    Ok(accounts
+        .into_iter()
+        .map(|a| AccountInfo {
+            is_active: active.as_deref() == Some(a.user_id.as_str()),
+            user_id: a.user_id,
+            email: a.email,
+            last_used_at: a.last_used_at,
+        })
+        .collect())
+}
+
+/// Переключиться на другой привязанный аккаунт без повторного входа через браузер.
+/// Активная сессия завершается и уходит в историю текущего аккаунта, а не следующего
+#[tauri::command]
+pub async fn switch_account(
+    state: State<'_, Arc<AppState>>,
+    app_handle: tauri::AppHandle,
+    id: String,
+) -> Result<AuthStatus, String> {
+    if state.get_auth_user_id().await.as_deref() != Some(id.as_str()) {
+        let api = state
+            .supabase_client()
+            .await
+            .ok_or_else(|| "Supabase config missing".to_string())?;
+        if crate::auth::load_account_token(&id)?.is_none() {
+            return Err("No stored session for this account, sign in again".to_string());
+        }
+        if state.is_session_active().await {
+            end_session(state.clone(), app_handle).await?;
+        }
+        state.switch_auth_account(&api, &id).await?;
+    }
+    Ok(AuthStatus {
+        is_logged_in: state.is_logged_in().await,
+        email: state.get_auth_email().await,
+    })
+}
+
 #[derive(serde::Deserialize)]
 struct AuthUserResponse {
     id: String,
@@ -492,31 +2641,21 @@ struct AuthUserResponse {
 /// Получить профиль пользователя из public.profiles (kripika.com)
 #[tauri::command]
 pub async fn get_my_profile(state: State<'_, Arc<AppState>>) -> Result<Option<UserProfile>, String> {
-    let cfg = state
-        .resolve_supabase_config()
+    let api = state
+        .supabase_client()
         .await
         .ok_or_else(|| "Supabase config missing".to_string())?;
 
-    let http = reqwest::Client::new();
     let jwt = state
-        .get_valid_access_token(&http, &cfg)
+        .get_valid_access_token(&api)
         .await
         .ok_or_else(|| "Not logged in".to_string())?;
 
     // Prefer user_id from auth session; fallback to /auth/v1/user (paranoia).
     let mut user_id = state.get_auth_user_id().await;
     if user_id.is_none() {
-        let endpoint = format!("{}/auth/v1/user", cfg.url.trim_end_matches('/'));
-        let resp = http
-            .get(endpoint)
-            .header("apikey", &cfg.anon_key)
-            .header("Authorization", format!("Bearer {}", jwt))
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
-
-        if resp.status().is_success() {
-            if let Ok(u) = resp.json::<AuthUserResponse>().await {
+        match api.get_json::<AuthUserResponse>("Auth user fetch", "auth/v1/user", Some(&jwt)).await {
+            Ok(u) => {
                 user_id = Some(u.id);
                 // Patch in-memory session so subsequent calls have user_id/email.
                 let mut lock = state.auth_session.write().await;
@@ -525,31 +2664,16 @@ pub async fn get_my_profile(state: State<'_, Arc<AppState>>) -> Result<Option<Us
                     sess.user_email = u.email;
                 }
             }
+            Err(e) => log::debug!("{}", e),
         }
     }
 
     let user_id = user_id.ok_or_else(|| "Missing user id".to_string())?;
 
-    let endpoint = format!(
-        "{}/rest/v1/profiles?id=eq.{}&select=id,username,display_name,avatar_url,level,total_xp",
-        cfg.url.trim_end_matches('/'),
+    let path = format!(
+        "rest/v1/profiles?id=eq.{}&select=id,username,display_name,avatar_url,level,total_xp",
         user_id
     );
-
-    let resp = http
-        .get(endpoint)
-        .header("apikey", &cfg.anon_key)
-        .header("Authorization", format!("Bearer {}", jwt))
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        return Err(format!("Profile fetch failed: {} {}", status, text));
-    }
-
-    let rows: Vec<UserProfile> = resp.json().await.map_err(|e| e.to_string())?;
+    let rows: Vec<UserProfile> = api.get_json("Profile fetch", &path, Some(&jwt)).await?;
     Ok(rows.into_iter().next())
 }
@@ -0,0 +1,111 @@
+//! Компактное окно-компаньон с живой статистикой сессии
+//!
+//! Отдельное Tauri-окно без рамки поверх игры. Управляется с backend, чтобы его можно было
+//! переключать (трей, хоткей) даже когда главное окно свёрнуто в трей.
+//! Геометрия и прозрачность хранятся в AppSettings.companion_window.
+
+use std::sync::Arc;
+use log::{debug, info};
+use tauri::{AppHandle, Emitter, Manager, PhysicalPosition, PhysicalSize, WebviewUrl, WebviewWindow, WebviewWindowBuilder, WindowEvent};
+
+use crate::state::AppState;
+use crate::types::CompanionWindowSettings;
+
+/// Label окна-компаньона
+pub const COMPANION_LABEL: &str = "companion";
+
+/// Правка сохранённой геометрии по событию окна
+type GeometryUpdate = Box<dyn FnOnce(&mut CompanionWindowSettings) + Send>;
+
+/// Показать окно-компаньон (создаёт его при первом вызове)
+pub fn show(app: &AppHandle, cfg: &CompanionWindowSettings) -> tauri::Result<WebviewWindow> {
+    let window = match app.get_webview_window(COMPANION_LABEL) {
+        Some(window) => window,
+        None => build(app, cfg)?,
+    };
+    window.show()?;
+    Ok(window)
+}
+
+/// Спрятать окно-компаньон (окно не уничтожается, чтобы повторный показ был мгновенным)
+pub fn hide(app: &AppHandle) -> tauri::Result<()> {
+    if let Some(window) = app.get_webview_window(COMPANION_LABEL) {
+        window.hide()?;
+    }
+    Ok(())
+}
+
+/// Видимо ли окно-компаньон сейчас
+pub fn is_visible(app: &AppHandle) -> bool {
+    app.get_webview_window(COMPANION_LABEL)
+        .and_then(|w| w.is_visible().ok())
+        .unwrap_or(false)
+}
+
+/// Применить геометрию и прозрачность к уже открытому окну
+pub fn apply(app: &AppHandle, cfg: &CompanionWindowSettings) -> tauri::Result<()> {
+    if let Some(window) = app.get_webview_window(COMPANION_LABEL) {
+        window.set_position(PhysicalPosition::new(cfg.x, cfg.y))?;
+        window.set_size(PhysicalSize::new(cfg.width, cfg.height))?;
+        // Прозрачность применяется через CSS на стороне окна
+        window.emit("companion-window-updated", cfg)?;
+    }
+    Ok(())
+}
+
+fn build(app: &AppHandle, cfg: &CompanionWindowSettings) -> tauri::Result<WebviewWindow> {
+    let window = WebviewWindowBuilder::new(app, COMPANION_LABEL, WebviewUrl::App("index.html?view=companion".into()))
+        .title("TLI Companion — Live")
+        .decorations(false)
+        .transparent(true)
+        .shadow(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .resizable(true)
+        .focused(false)
+        .visible(false)
+        .build()?;
+
+    // Позиционируем в физических пикселях: так же они приходят в событиях Moved/Resized
+    window.set_position(PhysicalPosition::new(cfg.x, cfg.y))?;
+    window.set_size(PhysicalSize::new(cfg.width, cfg.height))?;
+
+    // Запоминаем геометрию, когда пользователь двигает/растягивает окно
+    let app_for_events = app.clone();
+    window.on_window_event(move |event| {
+        let update: Option<GeometryUpdate> = match event {
+            WindowEvent::Moved(pos) => {
+                let (x, y) = (pos.x, pos.y);
+                Some(Box::new(move |c| { c.x = x; c.y = y; }))
+            }
+            WindowEvent::Resized(size) if size.width > 0 && size.height > 0 => {
+                let (width, height) = (size.width, size.height);
+                Some(Box::new(move |c| { c.width = width; c.height = height; }))
+            }
+            _ => None,
+        };
+        if let Some(update) = update {
+            let state = app_for_events.state::<Arc<AppState>>().inner().clone();
+            tauri::async_runtime::spawn(async move {
+                state.update_companion_window(update).await;
+                state.schedule_geometry_save();
+            });
+        }
+    });
+
+    info!("Companion window created at ({}, {}) {}x{}", cfg.x, cfg.y, cfg.width, cfg.height);
+    debug!("Companion window opacity: {}", cfg.opacity);
+    Ok(window)
+}
+
+/// Переключить видимость окна-компаньона (трей/хоткей/команда). Возвращает новое состояние
+pub async fn toggle(app: &AppHandle, state: &AppState) -> tauri::Result<bool> {
+    let visible = !is_visible(app);
+    let cfg = state.set_companion_visible(visible).await;
+    if visible {
+        show(app, &cfg)?;
+    } else {
+        hide(app)?;
+    }
+    Ok(visible)
+}
@@ -0,0 +1,431 @@
+//! Локальный API для Stream Deck и других внешних контроллеров
+//!
+//! Маленький HTTP-сервер на 127.0.0.1 (порт из настроек), заточенный под кнопки:
+//! - `GET /v1/stats` — компактные значения сессии и готовые подписи («12.3k/ч»);
+//! - `POST /v1/session/{start,pause,resume,toggle-pause,end}` — управление сессией;
+//! - `GET /v1/events` — SSE-поток: событие `stats` при каждом изменении значений.
+//!
+//! Каждый запрос несёт токен: `Authorization: Bearer <токен>` или `?token=` (EventSource
+//! заголовки не умеет). Токен хранится в keychain и показывается в настройках.
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::state::AppState;
+use crate::types::ControllerApiSettings;
+
+const KEYRING_USERNAME: &str = "controller-api-token";
+
+/// Как часто SSE-поток проверяет, изменились ли значения
+const EVENTS_POLL: Duration = Duration::from_secs(1);
+/// Комментарий-пинг, чтобы прокси и клиенты не рвали тихое соединение
+const EVENTS_KEEPALIVE: Duration = Duration::from_secs(15);
+/// Заголовки запроса больше не бывают (тело команды не нужно)
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+/// Сколько ждать заголовки запроса, прежде чем закрыть молчащее соединение
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Пауза после ошибки accept (например, кончились дескрипторы), чтобы не крутить цикл впустую
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(500);
+
+fn generate_token() -> String {
+    use rand::Rng;
+    let bytes: [u8; 24] = rand::thread_rng().gen();
+    use base64::Engine;
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Токен API (создаётся при первом обращении)
+pub fn load_or_create_token() -> Result<String, String> {
+    match crate::auth::keyring_entry(KEYRING_USERNAME)?.get_password() {
+        Ok(token) if !token.is_empty() => Ok(token),
+        Ok(_) | Err(keyring::Error::NoEntry) => regenerate_token(),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Выпустить новый токен (старый перестаёт работать после перезапуска сервера)
+pub fn regenerate_token() -> Result<String, String> {
+    let token = generate_token();
+    crate::auth::keyring_entry(KEYRING_USERNAME)?
+        .set_password(&token)
+        .map_err(|e| format!("Failed to store controller API token in keychain: {}", e))?;
+    Ok(token)
+}
+
+/// Адрес и токен для настройки плагина
+#[derive(Debug, Clone, Serialize)]
+pub struct ControllerApiInfo {
+    pub running: bool,
+    pub url: String,
+    pub token: String,
+}
+
+/// Значения для кнопок
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ControllerStats {
+    pub active: bool,
+    pub paused: bool,
+    pub duration_sec: i32,
+    pub maps: i32,
+    /// Чистый профит (доход − комиссия − расходы)
+    pub profit: f64,
+    pub profit_per_hour: f64,
+    /// Готовые короткие подписи (кнопка Stream Deck — 72×72)
+    pub labels: ControllerLabels,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ControllerLabels {
+    pub profit: String,
+    pub profit_per_hour: String,
+    pub maps: String,
+    pub duration: String,
+}
+
+fn format_duration(sec: i32) -> String {
+    let sec = sec.max(0);
+    format!("{}:{:02}", sec / 3600, sec / 60 % 60)
+}
+
+pub async fn controller_stats(state: &AppState) -> ControllerStats {
+    let stats = state.get_session_stats().await;
+    let active = state.is_session_active().await;
+    let valuation = state.valuation().await;
+    let expenses = valuation.expenses_total(&state.session.read().await.expenses);
+    let profit = valuation.round(stats.total_value - stats.total_fee - expenses);
+    ControllerStats {
+        active,
+        paused: stats.is_paused,
+        duration_sec: stats.duration_sec,
+        maps: stats.maps_completed,
+        profit,
+        profit_per_hour: stats.hourly_profit,
+        labels: ControllerLabels {
+            profit: crate::value_format::compact(profit),
+            profit_per_hour: format!("{}/h", crate::value_format::compact(stats.hourly_profit)),
+            maps: stats.maps_completed.to_string(),
+            duration: format_duration(stats.duration_sec),
+        },
+    }
+}
+
+/// Запущенный сервер
+pub struct ControllerServer {
+    pub port: u16,
+    cancel: Arc<AtomicBool>,
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+impl ControllerServer {
+    fn stop(self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.task.abort();
+    }
+}
+
+/// Привести сервер в соответствие с настройками (запустить, перезапустить или остановить)
+pub async fn apply(app: &AppHandle, settings: &ControllerApiSettings) {
+    let state = app.state::<Arc<AppState>>().inner().clone();
+    let mut server = state.controller_api.write().await;
+    if let Some(current) = server.take() {
+        current.stop();
+        info!("Controller API stopped");
+    }
+    if !settings.enabled {
+        return;
+    }
+    let token = match load_or_create_token() {
+        Ok(token) => token,
+        Err(e) => {
+            warn!("Controller API disabled, no token: {}", e);
+            return;
+        }
+    };
+    let listener = match TcpListener::bind(listen_addr(settings.port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Controller API: cannot bind port {}: {}", settings.port, e);
+            return;
+        }
+    };
+    let cancel = Arc::new(AtomicBool::new(false));
+    let task = tauri::async_runtime::spawn(accept_loop(app.clone(), listener, token, cancel.clone()));
+    info!("Controller API listening on 127.0.0.1:{}", settings.port);
+    *server = Some(ControllerServer { port: settings.port, cancel, task });
+}
+
+/// Сервер слушает только loopback: с других машин API недоступен
+fn listen_addr(port: u16) -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::LOCALHOST, port))
+}
+
+/// Принимаем соединения только с этой машины (на случай, если адрес прослушивания поменяют)
+fn is_allowed_peer(peer: &SocketAddr) -> bool {
+    peer.ip().is_loopback()
+}
+
+async fn accept_loop(app: AppHandle, listener: TcpListener, token: String, cancel: Arc<AtomicBool>) {
+    let token = Arc::new(token);
+    loop {
+        let stream = match listener.accept().await {
+            Ok((_, peer)) if !is_allowed_peer(&peer) => {
+                warn!("Controller API: rejected connection from {}", peer);
+                continue;
+            }
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                debug!("Controller API accept failed: {}", e);
+                tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                continue;
+            }
+        };
+        let (app, token, cancel) = (app.clone(), token.clone(), cancel.clone());
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = handle_connection(app, stream, &token, cancel).await {
+                debug!("Controller API connection error: {}", e);
+            }
+        });
+    }
+}
+
+/// Разобранная строка запроса
+#[derive(Debug, PartialEq)]
+struct Request {
+    method: String,
+    path: String,
+    token: Option<String>,
+}
+
+fn parse_request(head: &str) -> Option<Request> {
+    let mut lines = head.lines();
+    let mut parts = lines.next()?.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut token = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == "token")
+        .and_then(|(_, v)| urlencoding::decode(v).ok())
+        .map(|v| v.into_owned());
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else { continue };
+        if name.trim().eq_ignore_ascii_case("authorization") {
+            if let Some(bearer) = value.trim().strip_prefix("Bearer ") {
+                token = Some(bearer.trim().to_string());
+            }
+        }
+    }
+    Some(Request { method, path: path.trim_end_matches('/').to_string(), token })
+}
+
+/// Сравнение без раннего выхода (время ответа не подсказывает токен)
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Что делать с запросом до маршрутизации: ответить сразу или передать обработчику
+#[derive(Debug, PartialEq)]
+enum Gate {
+    Respond { status: &'static str, body: String },
+    Proceed(Request),
+}
+
+/// Разбор запроса, CORS preflight и проверка токена
+fn gate(head: &str, token: &str) -> Gate {
+    let Some(request) = parse_request(head) else {
+        return Gate::Respond { status: "400 Bad Request", body: error_body("bad request") };
+    };
+    if request.method == "OPTIONS" {
+        return Gate::Respond { status: "204 No Content", body: String::new() };
+    }
+    if !request.token.as_deref().map(|t| token_matches(t, token)).unwrap_or(false) {
+        return Gate::Respond { status: "401 Unauthorized", body: error_body("invalid token") };
+    }
+    Gate::Proceed(request)
+}
+
+async fn write_response(stream: &mut TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nAccess-Control-Allow-Headers: Authorization\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+/// Заголовки запроса, прочитанные до пустой строки
+enum RequestHead {
+    Complete(String),
+    TooLarge,
+    /// Клиент закрыл соединение, не дослав заголовки
+    Closed,
+}
+
+async fn read_head(stream: &mut TcpStream) -> std::io::Result<RequestHead> {
+    let mut buf = vec![0u8; MAX_REQUEST_BYTES];
+    let mut len = 0;
+    while !buf[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+        if len == buf.len() {
+            return Ok(RequestHead::TooLarge);
+        }
+        let n = stream.read(&mut buf[len..]).await?;
+        if n == 0 {
+            return Ok(RequestHead::Closed);
+        }
+        len += n;
+    }
+    Ok(RequestHead::Complete(String::from_utf8_lossy(&buf[..len]).into_owned()))
+}
+
+async fn handle_connection(app: AppHandle, mut stream: TcpStream, token: &str, cancel: Arc<AtomicBool>) -> std::io::Result<()> {
+    // Молчащий клиент не держит задачу вечно
+    let head = match tokio::time::timeout(REQUEST_READ_TIMEOUT, read_head(&mut stream)).await {
+        Err(_) => return write_response(&mut stream, "408 Request Timeout", &error_body("request timeout")).await,
+        Ok(head) => head?,
+    };
+    let head = match head {
+        RequestHead::Complete(head) => head,
+        RequestHead::TooLarge => {
+            return write_response(&mut stream, "431 Request Header Fields Too Large", &error_body("request too large")).await;
+        }
+        RequestHead::Closed => return Ok(()),
+    };
+    let request = match gate(&head, token) {
+        Gate::Respond { status, body } => return write_response(&mut stream, status, &body).await,
+        Gate::Proceed(request) => request,
+    };
+
+    let state = app.state::<Arc<AppState>>().inner().clone();
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/v1/stats") => {
+            let body = serde_json::to_string(&controller_stats(&state).await).unwrap_or_default();
+            write_response(&mut stream, "200 OK", &body).await
+        }
+        ("GET", "/v1/events") => stream_events(stream, &state, cancel).await,
+        ("POST", path) if path.starts_with("/v1/session/") => {
+            match session_action(&app, &state, &path["/v1/session/".len()..]).await {
+                Ok(()) => {
+                    let body = serde_json::to_string(&controller_stats(&state).await).unwrap_or_default();
+                    write_response(&mut stream, "200 OK", &body).await
+                }
+                Err(e) => write_response(&mut stream, "400 Bad Request", &error_body(&e)).await,
+            }
+        }
+        _ => write_response(&mut stream, "404 Not Found", &error_body("not found")).await,
+    }
+}
+
+/// Команды кнопок (как в меню трея: старт без активной сессии, конец — с сохранением в историю)
+async fn session_action(app: &AppHandle, state: &AppState, action: &str) -> Result<(), String> {
+    match action {
+        "start" => {
+            if !state.is_session_active().await {
+                state.start_session(None).await;
+            }
+        }
+        "pause" => state.set_paused(true).await,
+        "resume" => state.set_paused(false).await,
+        "toggle-pause" => {
+            let paused = state.is_paused().await;
+            state.set_paused(!paused).await;
+        }
+        "end" => {
+            if state.is_session_active().await {
+                crate::commands::end_session(app.state::<Arc<AppState>>(), app.clone()).await?;
+            }
+        }
+        other => return Err(format!("Unknown session action: {}", other)),
+    }
+    state.notify_changed();
+    Ok(())
+}
+
+async fn stream_events(mut stream: TcpStream, state: &AppState, cancel: Arc<AtomicBool>) -> std::io::Result<()> {
+    stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\
+              Access-Control-Allow-Origin: *\r\nConnection: keep-alive\r\n\r\n",
+        )
+        .await?;
+    let mut last: Option<ControllerStats> = None;
+    let mut since_write = Duration::ZERO;
+    while !cancel.load(Ordering::Relaxed) {
+        let stats = controller_stats(state).await;
+        if last.as_ref() != Some(&stats) {
+            let json = serde_json::to_string(&stats).unwrap_or_default();
+            stream.write_all(format!("event: stats\ndata: {}\n\n", json).as_bytes()).await?;
+            last = Some(stats);
+            since_write = Duration::ZERO;
+        } else if since_write >= EVENTS_KEEPALIVE {
+            stream.write_all(b": keepalive\n\n").await?;
+            since_write = Duration::ZERO;
+        }
+        tokio::time::sleep(EVENTS_POLL).await;
+        since_write += EVENTS_POLL;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_token_from_header_or_query() {
+        let req = parse_request("GET /v1/stats/?token=a%2Bb HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        assert_eq!((req.method.as_str(), req.path.as_str(), req.token.as_deref()), ("GET", "/v1/stats", Some("a+b")));
+
+        let req = parse_request("POST /v1/session/end HTTP/1.1\r\nauthorization: Bearer secret\r\n\r\n").unwrap();
+        assert_eq!(req.token.as_deref(), Some("secret"));
+        assert!(token_matches("secret", "secret"));
+        assert!(!token_matches("secreT", "secret"));
+        assert!(!token_matches("secret1", "secret"));
+        assert_eq!(format_duration(3725), "1:02");
+    }
+
+    #[test]
+    fn rejects_missing_or_wrong_token() {
+        let unauthorized = Gate::Respond { status: "401 Unauthorized", body: error_body("invalid token") };
+        assert_eq!(gate("GET /v1/stats HTTP/1.1\r\n\r\n", "secret"), unauthorized);
+        assert_eq!(gate("GET /v1/stats?token=wrong HTTP/1.1\r\n\r\n", "secret"), unauthorized);
+        assert_eq!(gate("POST /v1/session/end HTTP/1.1\r\nAuthorization: Bearer secreT\r\n\r\n", "secret"), unauthorized);
+        // Пустой токен в настройках не открывает API запросу без токена
+        assert_eq!(gate("GET /v1/stats?token= HTTP/1.1\r\n\r\n", "secret"), unauthorized);
+
+        assert!(matches!(gate("", "secret"), Gate::Respond { status: "400 Bad Request", .. }));
+        assert!(matches!(gate("OPTIONS /v1/stats HTTP/1.1\r\n\r\n", "secret"), Gate::Respond { status: "204 No Content", .. }));
+        match gate("GET /v1/stats HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n", "secret") {
+            Gate::Proceed(req) => assert_eq!(req.path, "/v1/stats"),
+            other => panic!("expected the request to pass, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn listens_and_accepts_on_loopback_only() {
+        let addr = listen_addr(17890);
+        assert!(addr.ip().is_loopback());
+        assert_eq!(addr.port(), 17890);
+
+        assert!(is_allowed_peer(&"127.0.0.1:50000".parse().unwrap()));
+        assert!(is_allowed_peer(&"[::1]:50000".parse().unwrap()));
+        assert!(!is_allowed_peer(&"192.168.1.10:50000".parse().unwrap()));
+        assert!(!is_allowed_peer(&"0.0.0.0:50000".parse().unwrap()));
+    }
+}
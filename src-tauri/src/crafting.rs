@@ -0,0 +1,103 @@
+//! Калькулятор крафта
+//!
+//! Рецепты (компоненты с количеством -> результат) лежат в recipes.json и правятся
+//! из UI или руками. Оценка берёт цены из кэша: стоимость компонентов против
+//! ожидаемой стоимости результата с учётом шанса успеха.
+
+use std::collections::HashMap;
+
+use crate::persistence::PersistedPriceEntry;
+use crate::types::{ItemInfo, Recipe, RecipeEvaluation, RecipeLine};
+use crate::valuation::Valuation;
+
+/// Проверить рецепт перед сохранением
+pub fn validate(recipe: &Recipe) -> Result<(), String> {
+    if recipe.inputs.is_empty() {
+        return Err("Recipe has no inputs".to_string());
+    }
+    if recipe.inputs.iter().any(|i| i.quantity <= 0) || recipe.output_quantity <= 0 {
+        return Err("Recipe quantities must be positive".to_string());
+    }
+    if !(0.0..=1.0).contains(&recipe.success_chance) {
+        return Err("Success chance must be between 0 and 1".to_string());
+    }
+    Ok(())
+}
+
+/// Оценить рецепт по кэшу цен (language — для имён предметов)
+pub fn evaluate(
+    recipe: &Recipe,
+    items_cache: &HashMap<i64, ItemInfo>,
+    prices: &HashMap<i64, PersistedPriceEntry>,
+    valuation: &Valuation,
+    language: &str,
+) -> RecipeEvaluation {
+    let mut missing_prices = Vec::new();
+    let mut line = |game_id: i64, quantity: i32| {
+        let unit_price = Valuation::unit_price(game_id, items_cache, prices);
+        if unit_price.is_none() && !missing_prices.contains(&game_id) {
+            missing_prices.push(game_id);
+        }
+        RecipeLine {
+            game_id,
+            item_info: items_cache.get(&game_id).map(|i| i.localized(language)),
+            quantity,
+            unit_price,
+            value: valuation.line_value(unit_price.unwrap_or(0.0), quantity),
+        }
+    };
+
+    let inputs: Vec<RecipeLine> = recipe.inputs.iter().map(|i| line(i.game_id, i.quantity)).collect();
+    let output = line(recipe.output_game_id, recipe.output_quantity);
+
+    let input_cost = valuation.round(inputs.iter().map(|l| l.value).sum());
+    let expected_output_value = valuation.round(output.value * recipe.success_chance);
+    RecipeEvaluation {
+        recipe_id: recipe.id.clone(),
+        inputs,
+        output,
+        input_cost,
+        expected_output_value,
+        expected_profit: valuation.round(expected_output_value - input_cost),
+        missing_prices,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::RecipeInput;
+
+    fn price(price: f64) -> PersistedPriceEntry {
+        PersistedPriceEntry {
+            price,
+            updated_at: chrono::Utc::now(),
+            is_current_league: true,
+            league_name: None,
+            sample_count: None,
+            spread: None,
+            tiers: Default::default(),
+        }
+    }
+
+    #[test]
+    fn evaluates_expected_profit() {
+        let recipe = Recipe {
+            id: "r".to_string(),
+            name: "Test".to_string(),
+            inputs: vec![RecipeInput { game_id: 1, quantity: 3 }, RecipeInput { game_id: 2, quantity: 1 }],
+            output_game_id: 10,
+            output_quantity: 1,
+            success_chance: 0.5,
+        };
+        let prices = HashMap::from([(1, price(10.0)), (10, price(100.0))]);
+        let eval = evaluate(&recipe, &HashMap::new(), &prices, &Valuation::default(), "en");
+
+        assert_eq!(eval.input_cost, 30.0);
+        assert_eq!(eval.expected_output_value, 50.0);
+        assert_eq!(eval.expected_profit, 20.0);
+        assert_eq!(eval.missing_prices, vec![2]);
+        assert!(validate(&recipe).is_ok());
+        assert!(validate(&Recipe { success_chance: 1.5, ..recipe }).is_err());
+    }
+}
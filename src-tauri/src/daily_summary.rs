@@ -0,0 +1,189 @@
+//! Дневная сводка фарма по локальной истории сессий
+//!
+//! Итоги по дням (сессии, карты, профит, часы) для тепловой карты активности
+//! и текущая серия дней подряд с фармом. Сессия относится к дню своего начала
+//! (по локальному времени).
+
+use std::collections::HashMap;
+use chrono::{Duration, Local, NaiveDate};
+use serde::Serialize;
+
+use crate::persistence::SessionHistoryRecord;
+
+/// Максимальная длина диапазона (дней)
+pub const MAX_RANGE_DAYS: i64 = 366;
+
+/// Итоги одного дня
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyTotals {
+    pub date: NaiveDate,
+    pub sessions: i32,
+    pub maps: i32,
+    pub profit: f64,
+    pub hours: f64,
+}
+
+/// Сводка за диапазон дат
+#[derive(Debug, Clone, Serialize)]
+pub struct DailySummary {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Все дни диапазона по порядку (дни без сессий — с нулями)
+    pub days: Vec<DailyTotals>,
+    /// Дней подряд с фармом, заканчивая сегодня (или вчера, если сегодня ещё не фармили)
+    pub current_streak: i32,
+    /// Самая длинная серия внутри диапазона
+    pub longest_streak: i32,
+}
+
+fn session_day(record: &SessionHistoryRecord) -> NaiveDate {
+    record.started_at.with_timezone(&Local).date_naive()
+}
+
+/// Построить сводку. `today` передаётся явно, чтобы серия считалась от одной даты
+pub fn build(history: &[SessionHistoryRecord], from: NaiveDate, to: NaiveDate, today: NaiveDate) -> DailySummary {
+    let mut by_day: HashMap<NaiveDate, DailyTotals> = HashMap::new();
+    for record in history {
+        let date = session_day(record);
+        let totals = by_day.entry(date).or_insert(DailyTotals {
+            date,
+            sessions: 0,
+            maps: 0,
+            profit: 0.0,
+            hours: 0.0,
+        });
+        totals.sessions += 1;
+        totals.maps += record.maps_completed;
+        totals.profit += record.total_profit;
+        totals.hours += record.total_duration_sec as f64 / 3600.0;
+    }
+
+    let days: Vec<DailyTotals> = from
+        .iter_days()
+        .take_while(|d| *d <= to)
+        .map(|date| {
+            by_day.get(&date).cloned().unwrap_or(DailyTotals {
+                date,
+                sessions: 0,
+                maps: 0,
+                profit: 0.0,
+                hours: 0.0,
+            })
+        })
+        .collect();
+
+    let mut longest_streak = 0;
+    let mut run = 0;
+    for day in &days {
+        run = if day.sessions > 0 { run + 1 } else { 0 };
+        longest_streak = longest_streak.max(run);
+    }
+
+    // Текущая серия считается по всей истории, а не только по диапазону
+    let is_active = |d: &NaiveDate| by_day.contains_key(d);
+    let mut cursor = if is_active(&today) { today } else { today - Duration::days(1) };
+    let mut current_streak = 0;
+    while is_active(&cursor) {
+        current_streak += 1;
+        cursor -= Duration::days(1);
+    }
+
+    DailySummary { from, to, days, current_streak, longest_streak }
+}
+
+/// Диапазон по умолчанию и проверка границ: (from, to), не длиннее MAX_RANGE_DAYS
+pub fn resolve_range(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<(NaiveDate, NaiveDate), String> {
+    let to = to.unwrap_or_else(|| Local::now().date_naive());
+    let from = from.unwrap_or(to - Duration::days(MAX_RANGE_DAYS - 1));
+    if from > to {
+        return Err("from must not be after to".to_string());
+    }
+    if (to - from).num_days() >= MAX_RANGE_DAYS {
+        return Err(format!("Date range is limited to {} days", MAX_RANGE_DAYS));
+    }
+    Ok((from, to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 1, day).unwrap()
+    }
+
+    /// Сессия с началом в локальное время 2026-01-`day` `hour`:`min`
+    fn record(day: u32, hour: u32, min: u32, duration_min: i64, profit: f64) -> SessionHistoryRecord {
+        let started_at = Local.with_ymd_and_hms(2026, 1, day, hour, min, 0).unwrap().with_timezone(&Utc);
+        SessionHistoryRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            started_at,
+            ended_at: started_at + Duration::minutes(duration_min),
+            maps_completed: 2,
+            total_duration_sec: (duration_min * 60) as i32,
+            total_profit: profit,
+            total_expenses: 0.0,
+            total_income: profit,
+            total_fee: 0.0,
+            remote_id: None,
+            drops: HashMap::new(),
+            drop_tiers: HashMap::new(),
+            share_code: None,
+            preset_id: None,
+            raw_currency_start: None,
+            raw_currency_end: None,
+            raw_currency_gained: None,
+            manual_realized: 0.0,
+            manual_pending: 0.0,
+            manual_pending_quoted: Vec::new(),
+            recovered: false,
+            recovery_gap_sec: 0,
+            merged_remote_ids: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn buckets_sessions_by_local_day() {
+        let history = [record(10, 9, 0, 60, 100.0), record(10, 18, 0, 30, 50.0), record(12, 12, 0, 90, 10.0)];
+        let summary = build(&history, date(9), date(12), date(12));
+
+        let days: Vec<_> = summary.days.iter().map(|d| (d.date, d.sessions, d.maps, d.profit, d.hours)).collect();
+        assert_eq!(days, vec![
+            (date(9), 0, 0, 0.0, 0.0),
+            (date(10), 2, 4, 150.0, 1.5),
+            (date(11), 0, 0, 0.0, 0.0),
+            (date(12), 1, 2, 10.0, 1.5),
+        ]);
+        assert_eq!((summary.current_streak, summary.longest_streak), (1, 1));
+    }
+
+    #[test]
+    fn session_crossing_midnight_counts_for_its_start_day() {
+        let history = [record(10, 23, 30, 120, 40.0)];
+        let summary = build(&history, date(10), date(11), date(11));
+
+        assert_eq!((summary.days[0].sessions, summary.days[0].hours), (1, 2.0));
+        assert_eq!(summary.days[1].sessions, 0);
+        // Сегодня ещё не фармили — серия идёт от вчера
+        assert_eq!((summary.current_streak, summary.longest_streak), (1, 1));
+    }
+
+    #[test]
+    fn streaks_span_consecutive_days() {
+        let history = [record(5, 10, 0, 60, 1.0), record(6, 10, 0, 60, 1.0), record(7, 10, 0, 60, 1.0), record(9, 10, 0, 60, 1.0)];
+        let summary = build(&history, date(6), date(9), date(9));
+
+        assert_eq!(summary.current_streak, 1);
+        assert_eq!(summary.longest_streak, 2);
+        // Серия до вчера считается по всей истории, а не только по диапазону
+        assert_eq!(build(&history, date(7), date(8), date(8)).current_streak, 3);
+    }
+
+    #[test]
+    fn rejects_bad_ranges() {
+        assert!(resolve_range(Some(date(12)), Some(date(10))).is_err());
+        assert!(resolve_range(Some(date(1) - Duration::days(MAX_RANGE_DAYS)), Some(date(1))).is_err());
+        assert_eq!(resolve_range(Some(date(1)), Some(date(31))), Ok((date(1), date(31))));
+    }
+}
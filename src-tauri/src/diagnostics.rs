@@ -0,0 +1,107 @@
+//! Диагностический архив для баг-репортов
+//!
+//! Собирает в zip последние строки лога приложения, настройки (без личных путей),
+//! счётчики парсера и последние нераспознанные строки UE_game.log.
+//! Архив кладётся в data_local_dir()/tli-companion/diagnostics/.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::Value;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::log_parser::ParserStats;
+use crate::persistence;
+use crate::types::AppSettings;
+
+/// Сколько последних строк лога приложения класть в архив
+pub const APP_LOG_LINES: usize = 1000;
+
+/// Общие сведения о системе и состоянии приложения
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsManifest {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub created_at: String,
+    pub log_path_configured: bool,
+    pub log_sources: usize,
+    pub items_cached: usize,
+    pub prices_cached: usize,
+    pub session_active: bool,
+}
+
+/// Всё, что попадает в архив
+pub struct DiagnosticsInput {
+    pub manifest: DiagnosticsManifest,
+    pub settings: AppSettings,
+    pub parser_stats: ParserStats,
+    pub unparsed_lines: Vec<String>,
+    pub app_log_lines: Vec<String>,
+}
+
+/// Заменить домашнюю папку и имя пользователя Windows на плейсхолдеры
+pub fn anonymize_text(text: &str) -> String {
+    let Some(home) = dirs::home_dir() else {
+        return text.to_string();
+    };
+    let mut out = text.replace(&*home.to_string_lossy(), "~");
+    if let Some(user) = home.file_name().map(|n| n.to_string_lossy().to_string()) {
+        if user.len() > 2 {
+            out = out.replace(&user, "<user>");
+        }
+    }
+    out
+}
+
+/// Настройки без личных данных (пути к логам обезличены)
+pub fn anonymize_settings(settings: &AppSettings) -> Value {
+    let mut value = serde_json::to_value(settings).unwrap_or(Value::Null);
+    if let Some(path) = value.get_mut("custom_log_path") {
+        anonymize_path_value(path);
+    }
+    if let Some(Value::Array(paths)) = value.get_mut("extra_log_paths") {
+        paths.iter_mut().for_each(anonymize_path_value);
+    }
+    value
+}
+
+fn anonymize_path_value(value: &mut Value) {
+    if let Some(anonymized) = value.as_str().map(anonymize_text) {
+        *value = Value::String(anonymized);
+    }
+}
+
+fn pretty<T: Serialize>(value: &T) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+/// Записать архив. Возвращает путь к zip-файлу
+pub fn write_bundle(input: &DiagnosticsInput) -> io::Result<PathBuf> {
+    let dir = persistence::app_data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "data dir is not available"))?
+        .join("diagnostics");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("tli-diagnostics-{}.zip", Utc::now().format("%Y%m%d-%H%M%S")));
+
+    let mut zip = ZipWriter::new(fs::File::create(&path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let files: [(&str, String); 5] = [
+        ("manifest.json", pretty(&input.manifest)),
+        ("settings.json", pretty(&anonymize_settings(&input.settings))),
+        ("parser_stats.json", pretty(&input.parser_stats)),
+        ("app_log.txt", anonymize_text(&input.app_log_lines.join("\n"))),
+        ("unparsed_lines.txt", anonymize_text(&input.unparsed_lines.join("\n"))),
+    ];
+    for (name, content) in files {
+        zip.start_file(name, options).map_err(io::Error::other)?;
+        zip.write_all(content.as_bytes())?;
+    }
+    zip.finish().map_err(io::Error::other)?;
+
+    log::info!("Diagnostics bundle written: {}", path.display());
+    Ok(path)
+}
@@ -0,0 +1,184 @@
+//! Голосовые объявления ценных дропов
+//!
+//! Дроп дороже порога проговаривается системным синтезатором речи (SAPI/WinRT на
+//! Windows, AVFoundation на macOS; на Linux синтезатора нет — объявления молчат): имя
+//! предмета и стоимость на языке отображения. Объявления не чаще раза в `min_interval_secs` —
+//! пачка дропов с одного сундука не должна превращаться в бесконечный монолог.
+//!
+//! Синтезатор живёт в отдельном потоке: на части платформ он не Send.
+
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+
+use crate::types::TtsSettings;
+
+struct Utterance {
+    text: String,
+    language: String,
+    volume: f32,
+}
+
+/// Текст объявления: «Имя, 3 штуки, 1250» / «3 Name, worth 1250»
+pub fn announcement_text(name: &str, quantity: i32, value: f64, language: &str) -> String {
+    let value = crate::value_format::fe(value);
+    match (language, quantity) {
+        ("ru", 1) => format!("{}, {}", name, value),
+        ("ru", q) => format!("{}, {} {}, {}", name, q, pieces_ru(q), value),
+        ("zh", 1) => format!("{}，{}", name, value),
+        ("zh", q) => format!("{} {}个，{}", name, q, value),
+        (_, 1) => format!("{}, worth {}", name, value),
+        (_, q) => format!("{} {}, worth {}", q, name, value),
+    }
+}
+
+/// «штука/штуки/штук» по правилам русского счёта
+fn pieces_ru(quantity: i32) -> &'static str {
+    let n = quantity.unsigned_abs();
+    match (n % 10, n % 100) {
+        (1, r) if r != 11 => "штука",
+        (2..=4, r) if !(12..=14).contains(&r) => "штуки",
+        _ => "штук",
+    }
+}
+
+/// Пропускает не чаще раза в интервал
+#[derive(Debug, Default)]
+pub(crate) struct RateLimiter {
+    last: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub(crate) fn allow(&mut self, now: Instant, interval: Duration) -> bool {
+        if self.last.map(|t| now.duration_since(t) < interval).unwrap_or(false) {
+            return false;
+        }
+        self.last = Some(now);
+        true
+    }
+}
+
+/// Диктор: поток синтезатора поднимается при первом объявлении
+#[derive(Default)]
+pub struct DropAnnouncer {
+    tx: Mutex<Option<Sender<Utterance>>>,
+    limiter: Mutex<RateLimiter>,
+}
+
+impl DropAnnouncer {
+    /// Объявить дроп, если он дороже порога и интервал выдержан
+    pub fn announce_drop(&self, settings: &TtsSettings, name: &str, quantity: i32, value: f64, language: &str) {
+        if !settings.enabled || value < settings.min_value {
+            return;
+        }
+        let interval = Duration::from_secs(settings.min_interval_secs);
+        if !self.limiter.lock().unwrap().allow(Instant::now(), interval) {
+            debug!("TTS announcement skipped (rate limit): {}", name);
+            return;
+        }
+        self.speak(announcement_text(name, quantity, value, language), language, settings.volume);
+    }
+
+    /// Произнести текст (без порога и ограничения частоты — для проверки в настройках)
+    pub fn speak(&self, text: String, language: &str, volume: f32) {
+        let utterance = Utterance { text, language: language.to_string(), volume: volume.clamp(0.0, 1.0) };
+        let mut tx = self.tx.lock().unwrap();
+        let sender = tx.get_or_insert_with(spawn_tts_thread);
+        if let Err(mpsc::SendError(utterance)) = sender.send(utterance) {
+            let sender = spawn_tts_thread();
+            let _ = sender.send(utterance);
+            *tx = Some(sender);
+        }
+    }
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+fn spawn_tts_thread() -> Sender<Utterance> {
+    let (tx, rx) = mpsc::channel::<Utterance>();
+    std::thread::spawn(move || {
+        let mut tts = match tts::Tts::default() {
+            Ok(tts) => tts,
+            Err(e) => {
+                warn!("Text-to-speech is not available: {}", e);
+                return;
+            }
+        };
+        // Голос под язык выбирается один раз (None — голоса нет, говорит голос по умолчанию)
+        let mut voices: std::collections::HashMap<String, Option<tts::Voice>> = Default::default();
+        for utterance in rx {
+            let voice = voices
+                .entry(utterance.language.clone())
+                .or_insert_with(|| find_voice(&tts, &utterance.language));
+            if let Some(voice) = voice {
+                if let Err(e) = tts.set_voice(voice) {
+                    debug!("Failed to select TTS voice: {}", e);
+                }
+            }
+            let (min, max) = (tts.min_volume(), tts.max_volume());
+            let _ = tts.set_volume(min + (max - min) * utterance.volume);
+            // Новое объявление не перебивает текущее: встаёт в очередь синтезатора
+            if let Err(e) = tts.speak(utterance.text, false) {
+                warn!("TTS failed: {}", e);
+            }
+        }
+    });
+    tx
+}
+
+/// Без синтезатора объявления принимаются и отбрасываются (предупреждение — один раз)
+#[cfg(not(any(windows, target_os = "macos")))]
+fn spawn_tts_thread() -> Sender<Utterance> {
+    let (tx, rx) = mpsc::channel::<Utterance>();
+    std::thread::spawn(move || {
+        warn!("Text-to-speech is not available on this platform");
+        for utterance in rx {
+            debug!("TTS skipped ({}, volume {}): {}", utterance.language, utterance.volume, utterance.text);
+        }
+    });
+    tx
+}
+
+/// Голос для языка отображения (уже приведённого normalize_language: китайский — "zh")
+#[cfg(any(windows, target_os = "macos"))]
+fn find_voice(tts: &tts::Tts, language: &str) -> Option<tts::Voice> {
+    if !tts.supported_features().voice {
+        return None;
+    }
+    let voices = tts.voices().ok()?;
+    let found = voices.into_iter().find(|v| v.language().primary_language().eq_ignore_ascii_case(language));
+    if found.is_none() {
+        debug!("No TTS voice for language '{}', using the default voice", language);
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_text_and_limits_rate() {
+        assert_eq!(announcement_text("Flame Elementium", 1, 1250.4, "en"), "Flame Elementium, worth 1250");
+        assert_eq!(announcement_text("Угли", 3, 300.0, "ru"), "Угли, 3 штуки, 300");
+        assert_eq!(announcement_text("Угли", 12, 300.0, "ru"), "Угли, 12 штук, 300");
+
+        let mut limiter = RateLimiter::default();
+        let t0 = Instant::now();
+        let interval = Duration::from_secs(5);
+        assert!(limiter.allow(t0, interval));
+        assert!(!limiter.allow(t0 + Duration::from_secs(2), interval));
+        assert!(limiter.allow(t0 + Duration::from_secs(5), interval));
+    }
+
+    #[test]
+    fn saved_cn_language_is_announced_in_chinese() {
+        let mut saved = serde_json::to_value(crate::types::AppSettings::default()).unwrap();
+        saved["language"] = "cn".into();
+        let settings: crate::types::AppSettings = serde_json::from_value(saved).unwrap();
+        let language = crate::types::normalize_language(&settings.language);
+        assert_eq!(language, "zh");
+        assert_eq!(announcement_text("余烬", 2, 300.0, &language), "余烬 2个，300");
+    }
+}
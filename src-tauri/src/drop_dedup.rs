@@ -0,0 +1,145 @@
+//! Отсечение повторных строк слотов инвентаря
+//!
+//! Игра иногда заново логирует BagMgr@:Modfy для уже обновлённого слота (обновление
+//! страницы, переход между зонами). Парсер считает дельту к последнему увиденному
+//! количеству, поэтому устаревшая строка с меньшим количеством, за которой идёт
+//! повтор актуального, даёт лишний дроп. Трекер помнит отпечаток слота
+//! (источник, страница, слот, предмет) и максимум количества за короткое окно:
+//! в пределах окна засчитывается только рост сверх этого максимума.
+//! Устаревшая строка возвращает слот ровно к количеству до засчитанного роста;
+//! любое другое уменьшение (трата, продажа) настоящее — после него рост снова честный.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::types::ItemDropEvent;
+
+/// Окно, в котором повтор состояния слота считается дублем (по времени из лога)
+const DEDUP_WINDOW_MS: i64 = 3000;
+
+/// Ключ слота: источник, страница, слот
+type SlotKey = (String, i32, i32);
+
+/// Последнее засчитанное состояние слота
+#[derive(Debug, Clone)]
+struct SlotState {
+    game_id: i64,
+    /// Максимальное количество в слоте за окно
+    peak_quantity: i32,
+    /// Количество до последнего засчитанного роста (к нему откатывает устаревшая строка)
+    base_quantity: i32,
+    /// Время последнего засчитанного изменения
+    last_seen: DateTime<Utc>,
+}
+
+/// Трекер состояний слотов для дедупликации дропов
+#[derive(Debug, Default)]
+pub struct SlotTracker {
+    slots: HashMap<SlotKey, SlotState>,
+}
+
+impl SlotTracker {
+    /// Сколько из события засчитать как дроп (None — дубль, не считать)
+    pub fn accept(&mut self, event: &ItemDropEvent) -> Option<i32> {
+        // Старые события без количества в слоте — доверяем дельте парсера
+        if event.slot_quantity <= 0 {
+            return Some(event.quantity);
+        }
+
+        let key = (event.source_id.clone(), event.page_id, event.slot_id);
+        let window = Duration::milliseconds(DEDUP_WINDOW_MS);
+        // Количество, от которого парсер посчитал дельту
+        let previous = event.slot_quantity - event.quantity;
+        let fresh = SlotState {
+            game_id: event.game_id,
+            peak_quantity: event.slot_quantity,
+            base_quantity: previous,
+            last_seen: event.timestamp,
+        };
+
+        let state = match self.slots.get_mut(&key) {
+            Some(state) => state,
+            None => {
+                self.slots.insert(key, fresh);
+                return Some(event.quantity);
+            }
+        };
+
+        // Другой предмет в слоте, окно прошло или слот реально уменьшался — дельта парсера честная
+        let real_decrease = previous < state.peak_quantity && previous != state.base_quantity;
+        if state.game_id != event.game_id || event.timestamp - state.last_seen > window || real_decrease {
+            *state = fresh;
+            return Some(event.quantity);
+        }
+
+        if event.slot_quantity <= state.peak_quantity {
+            return None;
+        }
+
+        let gained = (event.slot_quantity - state.peak_quantity).min(event.quantity);
+        state.base_quantity = state.peak_quantity;
+        state.peak_quantity = event.slot_quantity;
+        state.last_seen = event.timestamp;
+        Some(gained)
+    }
+
+    /// Забыть слоты источника (лог пересоздан — номера слотов и количества с нуля)
+    pub fn reset_source(&mut self, source_id: &str) {
+        self.slots.retain(|(source, _, _), _| source != source_id);
+    }
+
+    /// Забыть все слоты (новая сессия)
+    pub fn reset(&mut self) {
+        self.slots.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drop_event(quantity: i32, slot_quantity: i32, ms: i64) -> ItemDropEvent {
+        ItemDropEvent {
+            game_id: 100300,
+            quantity,
+            timestamp: DateTime::<Utc>::UNIX_EPOCH + Duration::milliseconds(ms),
+            page_id: 102,
+            slot_id: 7,
+            slot_quantity,
+            source_id: "main".to_string(),
+            tier: None,
+            rarity: None,
+            item_name: None,
+        }
+    }
+
+    #[test]
+    fn skips_relogged_slot_state() {
+        let mut tracker = SlotTracker::default();
+        // 40 -> 50: честный дроп
+        assert_eq!(tracker.accept(&drop_event(10, 50, 0)), Some(10));
+        // Устаревшая строка 40 (парсер дропа не даёт), затем повтор 50 — дубль
+        assert_eq!(tracker.accept(&drop_event(10, 50, 500)), None);
+        // 40 -> 55 по мнению парсера, реально выросло на 5
+        assert_eq!(tracker.accept(&drop_event(15, 55, 1000)), Some(5));
+        // После окна дельта парсера снова принимается как есть
+        assert_eq!(tracker.accept(&drop_event(5, 55, 10_000)), Some(5));
+    }
+
+    #[test]
+    fn counts_gain_after_real_decrease() {
+        let mut tracker = SlotTracker::default();
+        // 40 -> 50
+        assert_eq!(tracker.accept(&drop_event(10, 50, 0)), Some(10));
+        // Потратили до 30 (парсер дропа не даёт), подобрали 10: 30 -> 40 — честный дроп
+        assert_eq!(tracker.accept(&drop_event(10, 40, 1000)), Some(10));
+        // Повтор того же состояния остаётся дублем
+        assert_eq!(tracker.accept(&drop_event(10, 40, 1500)), None);
+
+        tracker.reset_source("main");
+        assert_eq!(tracker.accept(&drop_event(10, 40, 2000)), Some(10));
+        tracker.reset();
+        assert_eq!(tracker.accept(&drop_event(10, 40, 2500)), Some(10));
+    }
+}
@@ -0,0 +1,184 @@
+//! Оценка дропа за карту с доверительными интервалами
+//!
+//! Завершённые карты (дроп по карте уже атрибутирован) копятся в map_log.json через
+//! все сессии. По выбранному окну (N последних карт, текущая лига, с момента) для
+//! каждого предмета считаем среднее за карту и 95% интервал. Редкие предметы живут
+//! по Пуассону, стакающиеся (валюта) — шире Пуассона, поэтому берём более широкий из
+//! двух интервалов: пуассоновского по сумме и нормального по разбросу между картами.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::types::ItemInfo;
+
+/// z для 95% интервала
+const Z_95: f64 = 1.96;
+
+/// Сколько карт храним в журнале (старые отбрасываются)
+pub const MAX_LOGGED_MAPS: usize = 5000;
+
+/// Завершённая карта в журнале
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapLogEntry {
+    pub ended_at: DateTime<Utc>,
+    pub duration_sec: i64,
+    #[serde(default)]
+    pub scene_name: Option<String>,
+    #[serde(default)]
+    pub preset_id: Option<String>,
+    /// Лига на момент карты (по ценам текущего сезона; None — неизвестна)
+    #[serde(default)]
+    pub league: Option<String>,
+    pub drops: HashMap<i64, i32>,
+}
+
+/// Какие карты учитывать
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DropRateWindow {
+    /// N последних карт
+    LastMaps { count: usize },
+    /// Карты текущей лиги
+    League,
+    /// Карты, завершённые после момента (например, после смены стратегии)
+    Since { at: DateTime<Utc> },
+    #[default]
+    All,
+}
+
+/// Оценка дропа одного предмета
+#[derive(Debug, Clone, Serialize)]
+pub struct DropRate {
+    pub game_id: i64,
+    pub item_info: Option<ItemInfo>,
+    /// Всего штук за окно
+    pub total: i64,
+    /// Карт, на которых предмет выпал хотя бы раз
+    pub maps_with_drop: i32,
+    /// Среднее за карту
+    pub per_map: f64,
+    /// 95% доверительный интервал для среднего за карту
+    pub ci_low: f64,
+    pub ci_high: f64,
+}
+
+/// Оценки по окну
+#[derive(Debug, Clone, Serialize)]
+pub struct DropRates {
+    /// Карт в окне
+    pub maps: i32,
+    /// Самая ранняя карта окна
+    pub window_start: Option<DateTime<Utc>>,
+    /// По убыванию среднего за карту
+    pub rates: Vec<DropRate>,
+}
+
+/// Карты окна (журнал упорядочен по времени)
+pub fn select<'a>(log: &'a [MapLogEntry], window: &DropRateWindow, league: Option<&str>) -> Vec<&'a MapLogEntry> {
+    match window {
+        DropRateWindow::LastMaps { count } => log.iter().skip(log.len().saturating_sub(*count)).collect(),
+        DropRateWindow::League => log.iter().filter(|m| league.is_some() && m.league.as_deref() == league).collect(),
+        DropRateWindow::Since { at } => log.iter().filter(|m| m.ended_at >= *at).collect(),
+        DropRateWindow::All => log.iter().collect(),
+    }
+}
+
+/// Граница пуассоновского интервала для суммы total (аппроксимация Уилсона–Хилферти)
+fn poisson_bounds(total: f64) -> (f64, f64) {
+    let low = if total > 0.0 {
+        total * (1.0 - 1.0 / (9.0 * total) - Z_95 / (3.0 * total.sqrt())).powi(3)
+    } else {
+        0.0
+    };
+    let k = total + 1.0;
+    let high = k * (1.0 - 1.0 / (9.0 * k) + Z_95 / (3.0 * k.sqrt())).powi(3);
+    (low.max(0.0), high)
+}
+
+/// Оценить дроп за карту по картам окна
+pub fn estimate(maps: &[&MapLogEntry], items_cache: &HashMap<i64, ItemInfo>, language: &str) -> DropRates {
+    let n = maps.len();
+    let mut per_item: HashMap<i64, Vec<i32>> = HashMap::new();
+    for map in maps {
+        for (game_id, qty) in &map.drops {
+            per_item.entry(*game_id).or_default().push(*qty);
+        }
+    }
+
+    let mut rates: Vec<DropRate> = per_item
+        .into_iter()
+        .map(|(game_id, counts)| {
+            let n_f = n as f64;
+            let total: i64 = counts.iter().map(|c| *c as i64).sum();
+            let mean = total as f64 / n_f;
+
+            // Разброс между картами; карты без предмета — нули
+            let sum_sq: f64 = counts.iter().map(|c| (*c as f64 - mean).powi(2)).sum::<f64>()
+                + (n - counts.len()) as f64 * mean * mean;
+            let (p_low, p_high) = poisson_bounds(total as f64);
+            let (mut ci_low, mut ci_high) = (p_low / n_f, p_high / n_f);
+            if n > 1 {
+                let half_width = Z_95 * (sum_sq / (n_f - 1.0)).sqrt() / n_f.sqrt();
+                ci_low = ci_low.min(mean - half_width);
+                ci_high = ci_high.max(mean + half_width);
+            }
+            DropRate {
+                game_id,
+                item_info: items_cache.get(&game_id).map(|i| i.localized(language)),
+                total,
+                maps_with_drop: counts.len() as i32,
+                per_map: mean,
+                ci_low: ci_low.max(0.0),
+                ci_high,
+            }
+        })
+        .collect();
+    rates.sort_by(|a, b| b.per_map.partial_cmp(&a.per_map).unwrap_or(std::cmp::Ordering::Equal));
+
+    DropRates {
+        maps: n as i32,
+        window_start: maps.iter().map(|m| m.ended_at).min(),
+        rates,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(drops: &[(i64, i32)]) -> MapLogEntry {
+        MapLogEntry {
+            ended_at: DateTime::<Utc>::UNIX_EPOCH,
+            duration_sec: 120,
+            scene_name: None,
+            preset_id: None,
+            league: Some("SS11".to_string()),
+            drops: drops.iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn interval_narrows_with_more_maps() {
+        let few: Vec<MapLogEntry> = (0..10).map(|i| map(if i == 0 { &[(1, 1)] } else { &[] })).collect();
+        let many: Vec<MapLogEntry> = (0..1000).map(|i| map(if i % 10 == 0 { &[(1, 1)] } else { &[] })).collect();
+
+        let few = estimate(&select(&few, &DropRateWindow::All, None), &HashMap::new(), "en");
+        let many = estimate(&select(&many, &DropRateWindow::All, None), &HashMap::new(), "en");
+        let (f, m) = (&few.rates[0], &many.rates[0]);
+
+        assert!((f.per_map - 0.1).abs() < 1e-9 && (m.per_map - 0.1).abs() < 1e-9);
+        assert!(f.ci_low <= 0.1 && f.ci_high >= 0.1);
+        assert!(m.ci_high - m.ci_low < f.ci_high - f.ci_low);
+        assert!(m.ci_low > 0.07 && m.ci_high < 0.13);
+    }
+
+    #[test]
+    fn selects_window() {
+        let log = vec![map(&[]), map(&[]), map(&[])];
+        assert_eq!(select(&log, &DropRateWindow::LastMaps { count: 2 }, None).len(), 2);
+        assert_eq!(select(&log, &DropRateWindow::League, Some("SS11")).len(), 3);
+        assert!(select(&log, &DropRateWindow::League, None).is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+//! Звуки на дроп
+//!
+//! Правила из настроек (стоимость, категория) сопоставляются с каждым засчитанным
+//! дропом прямо в обработчике лога, а звук играет отдельный аудио-поток. Так звук
+//! «ценного дропа» слышен, даже когда окно свёрнуто и webview спит.
+//!
+//! Встроенные звуки синтезируются (последовательность тонов), свои — читаются
+//! из файла (wav/ogg/mp3/flac).
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+use rodio::{OutputStream, Sink, Source};
+use serde::Serialize;
+
+use crate::categories::CategoryCatalog;
+use crate::types::{DropSoundRule, DropSoundSettings, ItemInfo};
+
+/// Префикс встроенных звуков в `DropSoundRule::sound`
+pub const BUNDLED_PREFIX: &str = "bundled:";
+
+/// Чаще не играем: пачка дропов с одного сундука не должна превращаться в трель.
+/// Внутри окна звучит только правило выше по списку, чем уже сыгравшее
+const MIN_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Встроенные звуки: имя и ноты (частота Гц, длительность мс)
+const BUNDLED: &[(&str, &[(f32, u64)])] = &[
+    ("chime", &[(880.0, 90), (1318.5, 160)]),
+    ("ping", &[(1568.0, 120)]),
+    ("fanfare", &[(523.3, 100), (659.3, 100), (784.0, 100), (1046.5, 260)]),
+    ("bell", &[(1046.5, 70), (1046.5, 70), (1568.0, 300)]),
+    ("low", &[(220.0, 220)]),
+];
+
+/// Встроенный звук для UI
+#[derive(Debug, Clone, Serialize)]
+pub struct BundledSound {
+    /// Значение для `DropSoundRule::sound`
+    pub id: String,
+    pub name: String,
+}
+
+pub fn bundled_sounds() -> Vec<BundledSound> {
+    BUNDLED
+        .iter()
+        .map(|(name, _)| BundledSound { id: format!("{}{}", BUNDLED_PREFIX, name), name: name.to_string() })
+        .collect()
+}
+
+/// Первое включённое правило, подходящее под дроп. Категория правила совпадает
+/// с категорией предмета или с любой её родительской группой
+pub fn match_rule<'a>(
+    settings: &'a DropSoundSettings,
+    item: &ItemInfo,
+    value: Option<f64>,
+    catalog: &CategoryCatalog,
+) -> Option<&'a DropSoundRule> {
+    settings.rules.iter().filter(|r| r.enabled).find(|rule| {
+        let value_ok = match rule.min_value {
+            Some(min) => value.map(|v| v >= min).unwrap_or(false),
+            None => true,
+        };
+        let category_ok = match rule.category.as_deref().filter(|c| !c.is_empty()) {
+            Some(category) => catalog.is_within(&item.category, category),
+            None => true,
+        };
+        value_ok && category_ok
+    })
+}
+
+/// Ограничение частоты: в окне MIN_INTERVAL дешёвый дроп не глушит ценный.
+/// Приоритет — позиция правила в списке (0 — самое важное)
+#[derive(Debug, Default)]
+struct Throttle {
+    last: Option<(Instant, usize)>,
+}
+
+impl Throttle {
+    fn admit(&mut self, now: Instant, priority: usize) -> bool {
+        let admitted = match self.last {
+            Some((at, played)) if now.duration_since(at) < MIN_INTERVAL => priority < played,
+            _ => true,
+        };
+        if admitted {
+            self.last = Some((now, priority));
+        }
+        admitted
+    }
+}
+
+struct PlayRequest {
+    sound: String,
+    volume: f32,
+}
+
+/// Проигрыватель: аудио-поток поднимается при первом звуке и живёт до выхода
+#[derive(Default)]
+pub struct SoundPlayer {
+    tx: Mutex<Option<Sender<PlayRequest>>>,
+    throttle: Mutex<Throttle>,
+}
+
+impl SoundPlayer {
+    /// Сыграть звук правила (с ограничением частоты)
+    pub fn play_rule(&self, settings: &DropSoundSettings, rule: &DropSoundRule) {
+        let priority = settings.rules.iter().position(|r| std::ptr::eq(r, rule)).unwrap_or(usize::MAX);
+        if !self.throttle.lock().unwrap().admit(Instant::now(), priority) {
+            return;
+        }
+        self.play(&rule.sound, settings.volume * rule.volume.unwrap_or(1.0));
+    }
+
+    /// Сыграть звук без правил (предпрослушивание в настройках)
+    pub fn play(&self, sound: &str, volume: f32) {
+        let request = PlayRequest { sound: sound.to_string(), volume: volume.clamp(0.0, 1.0) };
+        let mut tx = self.tx.lock().unwrap();
+        let sender = tx.get_or_insert_with(spawn_audio_thread);
+        if let Err(mpsc::SendError(request)) = sender.send(request) {
+            // Поток умер (например, пропало устройство вывода) — поднимаем заново
+            let sender = spawn_audio_thread();
+            let _ = sender.send(request);
+            *tx = Some(sender);
+        }
+    }
+}
+
+/// Поток с аудио-выходом (OutputStream не Send, поэтому живёт в своём потоке)
+fn spawn_audio_thread() -> Sender<PlayRequest> {
+    let (tx, rx) = mpsc::channel::<PlayRequest>();
+    std::thread::spawn(move || {
+        let (_stream, handle) = match OutputStream::try_default() {
+            Ok(output) => output,
+            Err(e) => {
+                warn!("No audio output device, drop sounds disabled: {}", e);
+                return;
+            }
+        };
+        for request in rx {
+            let sink = match Sink::try_new(&handle) {
+                Ok(sink) => sink,
+                Err(e) => {
+                    warn!("Failed to open audio sink: {}", e);
+                    continue;
+                }
+            };
+            sink.set_volume(request.volume);
+            match append_sound(&sink, &request.sound) {
+                Ok(()) => sink.detach(),
+                Err(e) => warn!("Failed to play sound '{}': {}", request.sound, e),
+            }
+        }
+        debug!("Audio thread stopped");
+    });
+    tx
+}
+
+fn append_sound(sink: &Sink, sound: &str) -> Result<(), String> {
+    if let Some(name) = sound.strip_prefix(BUNDLED_PREFIX) {
+        let (_, notes) = BUNDLED
+            .iter()
+            .find(|(n, _)| *n == name)
+            .ok_or_else(|| format!("Unknown bundled sound: {}", name))?;
+        for (freq, ms) in notes.iter() {
+            let duration = Duration::from_millis(*ms);
+            sink.append(
+                rodio::source::SineWave::new(*freq)
+                    .take_duration(duration)
+                    .fade_in(Duration::from_millis(5))
+                    .amplify(0.4),
+            );
+        }
+        return Ok(());
+    }
+    let path = Path::new(sound);
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let decoder = rodio::Decoder::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+    sink.append(decoder);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::categories::CategoryInfo;
+
+    fn rule(min_value: Option<f64>, category: Option<&str>, sound: &str) -> DropSoundRule {
+        DropSoundRule { enabled: true, min_value, category: category.map(str::to_string), sound: sound.to_string(), volume: None }
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let settings = DropSoundSettings {
+            enabled: true,
+            volume: 1.0,
+            rules: vec![rule(Some(1000.0), None, "big"), rule(None, Some("currency"), "currency"), rule(Some(10.0), None, "small")],
+        };
+        let catalog = CategoryCatalog::new(vec![CategoryInfo {
+            id: "ember".into(),
+            parent: Some("currency".into()),
+            name_en: None,
+            name_ru: None,
+            name_cn: None,
+            sort_order: 0,
+        }]);
+        let item = |category: &str| ItemInfo { game_id: 1, name: "x".into(), category: category.into(), ..ItemInfo::default() };
+
+        let sound = |category: &str, value: Option<f64>| match_rule(&settings, &item(category), value, &catalog).map(|r| r.sound.as_str());
+        assert_eq!(sound("gear", Some(5000.0)), Some("big"));
+        assert_eq!(sound("ember", Some(1.0)), Some("currency"));
+        assert_eq!(sound("gear", Some(50.0)), Some("small"));
+        assert_eq!(sound("gear", None), None);
+        assert!(bundled_sounds().iter().all(|s| s.id.starts_with(BUNDLED_PREFIX)));
+    }
+
+    #[test]
+    fn throttle_lets_higher_priority_through() {
+        let mut throttle = Throttle::default();
+        let start = Instant::now();
+        assert!(throttle.admit(start, 2));
+        // Пачка с сундука: тот же или более дешёвый звук молчит, ценный звучит
+        assert!(!throttle.admit(start + Duration::from_millis(50), 2));
+        assert!(!throttle.admit(start + Duration::from_millis(60), 3));
+        assert!(throttle.admit(start + Duration::from_millis(100), 0));
+        assert!(!throttle.admit(start + Duration::from_millis(200), 1));
+        assert!(throttle.admit(start + Duration::from_millis(400), 3));
+    }
+}
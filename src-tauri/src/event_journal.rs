@@ -0,0 +1,216 @@
+//! Журнал событий сессии (NDJSON)
+//!
+//! Каждое засчитанное событие сессии — дроп, смена карты, пауза, цена, ручная правка —
+//! дописывается отдельной JSON-строкой в `journal/session-<ms>.ndjson` в папке
+//! данных (`<ms>` — время старта сессии). Файл только дополняется: для исследования
+//! дроп-рейтов нужны сырые события, а не агрегаты. Журнал переживает перезапуск:
+//! восстановленная сессия продолжает тот же файл.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{MapEventType, PauseReason};
+
+/// Событие журнала
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalEvent {
+    SessionStart {
+        #[serde(default)]
+        preset_id: Option<String>,
+    },
+    /// Засчитанный дроп (цена — из кэша на момент дропа)
+    Drop {
+        game_id: i64,
+        quantity: i32,
+        #[serde(default)]
+        tier: Option<i32>,
+        source_id: String,
+        #[serde(default)]
+        unit_price: Option<f64>,
+    },
+    /// Смена карты основного клиента (после дедупликации)
+    MapChange {
+        event_type: MapEventType,
+        scene: String,
+    },
+    Pause {
+        reason: PauseReason,
+    },
+    Resume,
+    PriceUpdate {
+        game_id: i64,
+        price: f64,
+    },
+    /// Ручная корректировка количества: new_quantity — итог после правки
+    Adjust {
+        game_id: i64,
+        delta: i32,
+        new_quantity: i32,
+    },
+    /// Предмет убран из дропа (корректировка до нуля)
+    Remove {
+        game_id: i64,
+    },
+    /// Отмена ручной цены: прежняя цена (None — цены не было) и цены тиров
+    PriceRestore {
+        game_id: i64,
+        price: Option<f64>,
+        #[serde(default)]
+        tiers: HashMap<i32, f64>,
+    },
+    /// Цена отдельного тира экипировки
+    TierPriceUpdate {
+        game_id: i64,
+        tier: i32,
+        price: f64,
+    },
+    /// Трата сессии (добавлена или возвращена отменой)
+    Expense {
+        id: String,
+        quantity: i32,
+        price: f64,
+    },
+    ExpenseRemove {
+        id: String,
+    },
+    /// Сессия восстановлена после перезапуска приложения; offline_since — последняя
+    /// запись сессии перед выходом (время закрытого приложения в сессию не идёт)
+    Restored {
+        #[serde(default)]
+        offline_since: Option<DateTime<Utc>>,
+    },
+    SessionEnd,
+}
+
+/// Строка журнала
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JournalEntry {
+    pub ts: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: JournalEvent,
+}
+
+/// ID журнала сессии: миллисекунды времени старта
+pub fn journal_id(started_at: DateTime<Utc>) -> String {
+    started_at.timestamp_millis().to_string()
+}
+
+fn journal_dir() -> Option<PathBuf> {
+    crate::persistence::app_data_dir().map(|d| d.join("journal"))
+}
+
+pub fn journal_path(id: &str) -> Option<PathBuf> {
+    // ID приходит и из фронтенда — только цифры, чтобы не выйти за пределы папки
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    journal_dir().map(|d| d.join(format!("session-{}.ndjson", id)))
+}
+
+/// Прочитать журнал; битые строки (обрыв записи при падении) пропускаются
+pub fn read_journal(path: &Path) -> io::Result<Vec<JournalEntry>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<JournalEntry>(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => debug!("Skipping journal line {} of {}: {}", index + 1, path.display(), e),
+        }
+    }
+    Ok(entries)
+}
+
+/// Скопировать журнал в выбранный файл; возвращает число событий
+pub fn export_journal(id: &str, destination: &Path) -> Result<usize, String> {
+    let path = journal_path(id).ok_or("Invalid journal id")?;
+    if !path.exists() {
+        return Err("No event journal for this session".to_string());
+    }
+    fs::copy(&path, destination).map_err(|e| format!("Failed to export journal: {}", e))?;
+    let events = read_journal(&path).map(|e| e.len()).unwrap_or(0);
+    Ok(events)
+}
+
+/// Писатель журнала: файл текущей сессии держится открытым
+#[derive(Default)]
+pub struct EventJournal {
+    file: Mutex<Option<(String, File)>>,
+}
+
+impl EventJournal {
+    /// Дописать событие в журнал сессии, начатой в `started_at`
+    pub fn record(&self, started_at: DateTime<Utc>, ts: DateTime<Utc>, event: JournalEvent) {
+        let id = journal_id(started_at);
+        let line = match serde_json::to_string(&JournalEntry { ts, event }) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize journal event: {}", e);
+                return;
+            }
+        };
+        let mut file = self.file.lock().unwrap();
+        if file.as_ref().map(|(open_id, _)| open_id != &id).unwrap_or(true) {
+            *file = match Self::open(&id) {
+                Ok(handle) => Some((id, handle)),
+                Err(e) => {
+                    warn!("Failed to open event journal: {}", e);
+                    return;
+                }
+            };
+        }
+        if let Some((_, handle)) = file.as_mut() {
+            if let Err(e) = writeln!(handle, "{}", line) {
+                warn!("Failed to append to event journal: {}", e);
+                *file = None;
+            }
+        }
+    }
+
+    /// Закрыть файл (сессия завершена)
+    pub fn close(&self) {
+        *self.file.lock().unwrap() = None;
+    }
+
+    fn open(id: &str) -> io::Result<File> {
+        let path = journal_path(id).ok_or_else(|| io::Error::other("no data directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_round_trip_as_flat_json_lines() {
+        let ts = DateTime::parse_from_rfc3339("2025-03-01T12:00:00Z").unwrap().with_timezone(&Utc);
+        let entry = JournalEntry {
+            ts,
+            event: JournalEvent::Drop { game_id: 100300, quantity: 2, tier: None, source_id: "main".into(), unit_price: Some(1.5) },
+        };
+        let line = serde_json::to_string(&entry).unwrap();
+        assert!(line.starts_with(r#"{"ts":"2025-03-01T12:00:00Z","type":"drop","game_id":100300"#));
+        assert_eq!(serde_json::from_str::<JournalEntry>(&line).unwrap(), entry);
+
+        let resume: JournalEntry = serde_json::from_str(r#"{"ts":"2025-03-01T12:00:00Z","type":"resume"}"#).unwrap();
+        assert_eq!(resume.event, JournalEvent::Resume);
+
+        assert_eq!(journal_id(ts), "1740830400000");
+        assert!(journal_path("../settings").is_none());
+    }
+}
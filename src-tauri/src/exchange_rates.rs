@@ -0,0 +1,102 @@
+//! Курсы торговых валют к FE по наблюдениям из прайсчеков
+//!
+//! Валюта попадает в отслеживаемые, когда аукцион впервые показывает цены в ней
+//! (currency_id ≠ FE) или когда она указана в настройке номиналов. Каждый прайсчек
+//! самой валюты в FE добавляет точку курса. Цены и ручные записи в такой валюте
+//! переводятся в FE по курсу на нужный момент.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Сколько точек курса хранить на валюту (старые выбрасываются)
+pub const MAX_RATE_POINTS: usize = 500;
+
+/// Наблюдение курса: сколько FE стоит одна единица валюты
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RatePoint {
+    pub at: DateTime<Utc>,
+    pub fe_per_unit: f64,
+}
+
+/// Курсы по валютам: currency_id -> точки по времени (старые первыми)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExchangeRates {
+    pub currencies: HashMap<i64, Vec<RatePoint>>,
+}
+
+/// Курс одной валюты для UI
+#[derive(Debug, Clone, Serialize)]
+pub struct ExchangeRateSeries {
+    pub currency_id: i64,
+    pub name: String,
+    /// Последний известный курс (None — валюта встречалась, но ещё не прайсчекалась)
+    pub latest: Option<f64>,
+    pub points: Vec<RatePoint>,
+}
+
+impl ExchangeRates {
+    /// Начать отслеживать валюту. true — раньше её не было
+    pub fn track(&mut self, currency_id: i64) -> bool {
+        if self.currencies.contains_key(&currency_id) {
+            return false;
+        }
+        self.currencies.insert(currency_id, Vec::new());
+        true
+    }
+
+    pub fn is_tracked(&self, currency_id: i64) -> bool {
+        self.currencies.contains_key(&currency_id)
+    }
+
+    /// Добавить точку курса (по времени; лишние старые точки выбрасываются)
+    pub fn record(&mut self, currency_id: i64, fe_per_unit: f64, at: DateTime<Utc>) {
+        if !fe_per_unit.is_finite() || fe_per_unit <= 0.0 {
+            return;
+        }
+        let points = self.currencies.entry(currency_id).or_default();
+        let idx = points.partition_point(|p| p.at <= at);
+        points.insert(idx, RatePoint { at, fe_per_unit });
+        if points.len() > MAX_RATE_POINTS {
+            let excess = points.len() - MAX_RATE_POINTS;
+            points.drain(..excess);
+        }
+    }
+
+    /// Курс на момент `at`: последняя точка не позже, иначе самая ранняя
+    pub fn rate_at(&self, currency_id: i64, at: DateTime<Utc>) -> Option<f64> {
+        let points = self.currencies.get(&currency_id)?;
+        let idx = points.partition_point(|p| p.at <= at);
+        points.get(idx.saturating_sub(1)).or_else(|| points.first()).map(|p| p.fe_per_unit)
+    }
+
+    pub fn latest(&self, currency_id: i64) -> Option<f64> {
+        self.currencies.get(&currency_id)?.last().map(|p| p.fe_per_unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn rate_at_uses_last_point_before_moment() {
+        let t0 = DateTime::<Utc>::UNIX_EPOCH;
+        let mut rates = ExchangeRates::default();
+        assert!(rates.track(200));
+        assert_eq!(rates.rate_at(200, t0), None);
+
+        rates.record(200, 120.0, t0 + Duration::hours(2));
+        rates.record(200, 100.0, t0 + Duration::hours(1));
+        rates.record(200, 0.0, t0 + Duration::hours(3));
+
+        // До первой точки — самый ранний курс
+        assert_eq!(rates.rate_at(200, t0), Some(100.0));
+        assert_eq!(rates.rate_at(200, t0 + Duration::minutes(90)), Some(100.0));
+        assert_eq!(rates.rate_at(200, t0 + Duration::hours(5)), Some(120.0));
+        assert_eq!(rates.latest(200), Some(120.0));
+        assert!(!rates.track(200));
+    }
+}
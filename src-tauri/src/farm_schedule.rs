@@ -0,0 +1,129 @@
+//! Планировщик блоков фарма
+//!
+//! Блоки (время начала, длительность, шаблон/пресет) хранятся в farm_blocks.json.
+//! Фоновая задача раз в SCHEDULE_POLL_SEC проверяет расписание: шлёт напоминание
+//! за remind_before_min до начала и, если включено, запускает сессию в момент начала.
+//! Блоки, время которых прошло, пока приложение было закрыто, просто помечаются.
+
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+
+use crate::persistence;
+use crate::state::AppState;
+use crate::types::FarmBlock;
+
+/// Как часто проверять расписание
+pub const SCHEDULE_POLL_SEC: u64 = 30;
+
+/// Что наступило для блока на этом тике
+#[derive(Debug, Clone)]
+pub enum ScheduleAction {
+    /// Пора напомнить: до начала minutes_until минут
+    Remind { block: FarmBlock, minutes_until: i64 },
+    /// Блок начался
+    Start { block: FarmBlock },
+    /// Блок закончился, пока приложение не работало
+    Missed { block: FarmBlock },
+}
+
+/// Отметить наступившие напоминания и начала блоков
+pub fn due_actions(blocks: &mut [FarmBlock], now: DateTime<Utc>) -> Vec<ScheduleAction> {
+    let mut actions = Vec::new();
+    for block in blocks.iter_mut().filter(|b| !b.started) {
+        if now >= block.ends_at() {
+            block.reminded = true;
+            block.started = true;
+            actions.push(ScheduleAction::Missed { block: block.clone() });
+        } else if now >= block.starts_at {
+            block.reminded = true;
+            block.started = true;
+            actions.push(ScheduleAction::Start { block: block.clone() });
+        } else if !block.reminded
+            && now >= block.starts_at - Duration::minutes(block.remind_before_min as i64)
+        {
+            block.reminded = true;
+            // Округляем вверх: за 4 мин 10 сек до начала напоминаем «через 5 минут»
+            let minutes_until = ((block.starts_at - now).num_seconds() + 59) / 60;
+            actions.push(ScheduleAction::Remind { block: block.clone(), minutes_until });
+        }
+    }
+    actions
+}
+
+/// Один тик планировщика: действия, наступившие с прошлой проверки (флаги сохраняются на диск).
+/// Вызывать под `AppState::farm_blocks_lock`
+pub fn tick(now: DateTime<Utc>) -> Vec<ScheduleAction> {
+    let mut blocks = match persistence::load_farm_blocks() {
+        Ok(blocks) => blocks,
+        Err(e) => {
+            warn!("Failed to load farm blocks: {}", e);
+            return Vec::new();
+        }
+    };
+    let actions = due_actions(&mut blocks, now);
+    if !actions.is_empty() {
+        if let Err(e) = persistence::save_farm_blocks(&blocks) {
+            warn!("Failed to save farm blocks: {}", e);
+        }
+    }
+    actions
+}
+
+/// Автостарт сессии блока (если включён и сессия ещё не идёт). Возвращает true, если сессия запущена
+pub async fn auto_start(state: &AppState, block: &FarmBlock) -> bool {
+    if !block.auto_start || state.is_session_active().await {
+        return false;
+    }
+
+    let template = block.template_id.as_ref().and_then(|id| {
+        persistence::load_session_templates()
+            .map_err(|e| warn!("Failed to load session templates: {}", e))
+            .ok()?
+            .into_iter()
+            .find(|t| &t.id == id)
+    });
+    match template {
+        Some(template) => state.start_session_from_template(&template).await,
+        None => state.start_session(block.preset_id.clone()).await,
+    }
+    info!("Farm block '{}' started the session", block.name);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(starts_in_min: i64, now: DateTime<Utc>) -> FarmBlock {
+        FarmBlock {
+            id: "b".to_string(),
+            name: "Evening".to_string(),
+            starts_at: now + Duration::minutes(starts_in_min),
+            duration_min: 60,
+            template_id: None,
+            preset_id: None,
+            remind_before_min: 5,
+            auto_start: true,
+            reminded: false,
+            started: false,
+        }
+    }
+
+    #[test]
+    fn reminds_then_starts_once() {
+        let now = DateTime::<Utc>::UNIX_EPOCH;
+        let mut blocks = vec![block(10, now), block(-120, now)];
+
+        // Рано для напоминания; прошедший блок помечается пропущенным
+        let actions = due_actions(&mut blocks, now);
+        assert!(matches!(actions.as_slice(), [ScheduleAction::Missed { .. }]));
+
+        let actions = due_actions(&mut blocks, now + Duration::seconds(5 * 60 + 50));
+        assert!(matches!(actions.as_slice(), [ScheduleAction::Remind { minutes_until: 5, .. }]));
+        assert!(due_actions(&mut blocks, now + Duration::minutes(6)).is_empty());
+
+        let actions = due_actions(&mut blocks, now + Duration::minutes(10));
+        assert!(matches!(actions.as_slice(), [ScheduleAction::Start { .. }]));
+        assert!(due_actions(&mut blocks, now + Duration::minutes(11)).is_empty());
+    }
+}
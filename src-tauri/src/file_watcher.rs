@@ -1,54 +1,429 @@
 //! File Watcher для отслеживания изменений в логах TLI
 //! 
-//! Использует notify для отслеживания изменений файла UE_game.log
+//! Отслеживает изменения файла UE_game.log (опросом или через события notify)
 //! и читает новые строки по мере их появления (tail -f поведение).
 
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Seek, SeekFrom};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
-// notify импорты убраны - используем простой polling
+use chrono::{DateTime, Utc};
 use log::{info, warn, error, debug};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
 use crate::log_parser::LogParser;
-use crate::types::LogEvent;
-
-/// Найти путь к файлу логов TLI
-pub fn find_log_path() -> Option<PathBuf> {
-    // Типичные пути установки Steam
-    let possible_paths = [
-        // Steam по умолчанию
-        r"C:\Program Files (x86)\Steam\steamapps\common\Torchlight Infinite\UE_game\TorchLight\Saved\Logs\UE_game.log",
-        r"C:\Program Files\Steam\steamapps\common\Torchlight Infinite\UE_game\TorchLight\Saved\Logs\UE_game.log",
-        // D: диск
-        r"D:\Steam\steamapps\common\Torchlight Infinite\UE_game\TorchLight\Saved\Logs\UE_game.log",
-        r"D:\steam\steamapps\common\Torchlight Infinite\UE_game\TorchLight\Saved\Logs\UE_game.log",
-        r"D:\Games\Steam\steamapps\common\Torchlight Infinite\UE_game\TorchLight\Saved\Logs\UE_game.log",
-        // E: диск
-        r"E:\Steam\steamapps\common\Torchlight Infinite\UE_game\TorchLight\Saved\Logs\UE_game.log",
-        r"E:\Games\Steam\steamapps\common\Torchlight Infinite\UE_game\TorchLight\Saved\Logs\UE_game.log",
-        // F: диск
-        r"F:\Steam\steamapps\common\Torchlight Infinite\UE_game\TorchLight\Saved\Logs\UE_game.log",
-    ];
-    
-    for path_str in &possible_paths {
-        let path = PathBuf::from(path_str);
-        if path.exists() {
-            info!("Found TLI log file at: {}", path.display());
-            return Some(path);
+use crate::noise_filter::NoiseFilter;
+use crate::types::{AppSettings, GameClient, LogEvent, LogWatchMode};
+
+/// Границы интервала опроса (мс)
+const MIN_POLL_INTERVAL_MS: u64 = 50;
+const MAX_POLL_INTERVAL_MS: u64 = 10_000;
+/// В native режиме всё равно заглядываем в файл не реже, чем раз в секунду:
+/// события ФС иногда теряются.
+const NATIVE_FALLBACK_POLL_MS: u64 = 1000;
+/// Хвост файла для warm-up: baseline слотов и последний снимок инвентаря
+const WARMUP_MAX_BYTES: u64 = 1024 * 1024;
+/// Непрочитанный объём, начиная с которого чтение считается догонялкой
+const CATCHUP_THRESHOLD_BYTES: u64 = 256 * 1024;
+/// Прогресс догонялки публикуется не чаще, чем через столько байт
+const PROGRESS_STEP_BYTES: u64 = 256 * 1024;
+/// Строк в очереди между читателем и воркером парсинга
+const LINE_CHANNEL_CAPACITY: usize = 4096;
+/// Бинарный поиск по времени останавливается на окне такого размера, дальше — построчно
+const SEEK_RESOLUTION_BYTES: u64 = 64 * 1024;
+/// Сколько строк просматривать от точки поиска в поисках метки времени
+const SEEK_PROBE_LINES: usize = 500;
+
+/// Конфигурация механизма отслеживания (применяется на лету)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WatcherConfig {
+    pub mode: LogWatchMode,
+    pub poll_interval_ms: u64,
+}
+
+impl WatcherConfig {
+    pub fn new(mode: LogWatchMode, poll_interval_ms: u64) -> Self {
+        Self {
+            mode,
+            poll_interval_ms: poll_interval_ms.clamp(MIN_POLL_INTERVAL_MS, MAX_POLL_INTERVAL_MS),
         }
     }
-    
-    // Попробуем найти через реестр или libraryfolders.vdf
-    // TODO: реализовать поиск через Steam
-    
-    warn!("Could not find TLI log file automatically");
+
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self::new(settings.log_watch_mode, settings.log_poll_interval_ms)
+    }
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        Self::from_settings(&AppSettings::default())
+    }
+}
+
+/// Сообщение от читателя воркеру парсинга
+enum WatcherMessage {
+    /// Строка хвоста при старте: только baseline, без событий
+    Warmup(String),
+    /// Хвост прочитан
+    WarmupDone,
+    /// Новая строка лога
+    Line(String),
+    /// Файл пересоздан — сбросить состояние слотов
+    Reset,
+}
+
+/// Прогресс догонялки лога (warm-up при старте или большой накопившийся объём)
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct CatchupProgress {
+    pub source_id: String,
+    pub is_catching_up: bool,
+    /// Прочитано байт из объёма догонялки
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Прогресс по всем источникам: source_id -> прогресс (общий с AppState)
+pub type CatchupRegistry = Arc<Mutex<HashMap<String, CatchupProgress>>>;
+
+/// Учёт прогресса в потоке чтения; в реестр пишет только крупными шагами
+struct CatchupTracker {
+    registry: CatchupRegistry,
+    progress: CatchupProgress,
+    last_published: u64,
+}
+
+impl CatchupTracker {
+    fn new(registry: CatchupRegistry, source_id: String) -> Self {
+        Self {
+            registry,
+            progress: CatchupProgress { source_id, ..Default::default() },
+            last_published: 0,
+        }
+    }
+
+    /// Начать догонялку объёмом `total` байт
+    fn begin(&mut self, total: u64) {
+        if self.progress.is_catching_up {
+            return;
+        }
+        self.progress.is_catching_up = true;
+        self.progress.bytes_done = 0;
+        self.progress.bytes_total = total;
+        self.progress.started_at = Some(Utc::now());
+        self.progress.finished_at = None;
+        self.last_published = 0;
+        self.publish();
+    }
+
+    fn advance(&mut self, bytes: u64) {
+        if !self.progress.is_catching_up {
+            return;
+        }
+        self.progress.bytes_done += bytes;
+        // Файл растёт, пока догоняем
+        self.progress.bytes_total = self.progress.bytes_total.max(self.progress.bytes_done);
+        if self.progress.bytes_done - self.last_published >= PROGRESS_STEP_BYTES {
+            self.last_published = self.progress.bytes_done;
+            self.publish();
+        }
+    }
+
+    fn finish(&mut self) {
+        if !self.progress.is_catching_up {
+            return;
+        }
+        self.progress.is_catching_up = false;
+        self.progress.finished_at = Some(Utc::now());
+        if let Some(started_at) = self.progress.started_at {
+            info!(
+                "Log catch-up [{}] done: {} bytes in {} ms",
+                self.progress.source_id,
+                self.progress.bytes_done,
+                (Utc::now() - started_at).num_milliseconds()
+            );
+        }
+        self.publish();
+    }
+
+    fn publish(&self) {
+        self.registry
+            .lock()
+            .unwrap()
+            .insert(self.progress.source_id.clone(), self.progress.clone());
+    }
+}
+
+/// Подписка на события ФС для лог-файла (watcher должен жить, пока читаем канал)
+struct FsEvents {
+    _watcher: RecommendedWatcher,
+    rx: std::sync::mpsc::Receiver<notify::Result<notify::Event>>,
+}
+
+impl FsEvents {
+    fn subscribe(log_path: &Path) -> Option<Self> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(w) => w,
+            Err(e) => {
+                warn!("Failed to create native file watcher: {}", e);
+                return None;
+            }
+        };
+        if let Err(e) = watcher.watch(log_path, RecursiveMode::NonRecursive) {
+            warn!("Failed to watch {} natively: {}", log_path.display(), e);
+            return None;
+        }
+        debug!("Native file events enabled for {}", log_path.display());
+        Some(Self { _watcher: watcher, rx })
+    }
+
+    /// Ждём событие ФС (или таймаут), затем вычитываем накопившиеся события
+    fn wait(&self, timeout: Duration) {
+        let _ = self.rx.recv_timeout(timeout);
+        while self.rx.try_recv().is_ok() {}
+    }
+}
+
+/// Относительный путь лога внутри папки игры (одинаков у обоих клиентов)
+const LOG_RELATIVE_PATH: &str = r"UE_game\TorchLight\Saved\Logs\UE_game.log";
+
+/// Типичные пути установки глобального клиента (Steam)
+const GLOBAL_INSTALL_DIRS: [&str; 8] = [
+    // Steam по умолчанию
+    r"C:\Program Files (x86)\Steam\steamapps\common\Torchlight Infinite",
+    r"C:\Program Files\Steam\steamapps\common\Torchlight Infinite",
+    // D: диск
+    r"D:\Steam\steamapps\common\Torchlight Infinite",
+    r"D:\steam\steamapps\common\Torchlight Infinite",
+    r"D:\Games\Steam\steamapps\common\Torchlight Infinite",
+    // E: диск
+    r"E:\Steam\steamapps\common\Torchlight Infinite",
+    r"E:\Games\Steam\steamapps\common\Torchlight Infinite",
+    // F: диск
+    r"F:\Steam\steamapps\common\Torchlight Infinite",
+];
+
+/// Корни, куда обычно ставят китайский клиент (через его лаунчер или WeGame)
+const CN_INSTALL_ROOTS: [&str; 7] = [
+    r"C:\Program Files",
+    r"C:\Program Files (x86)",
+    r"C:\WeGameApps",
+    r"D:\",
+    r"D:\Games",
+    r"D:\WeGameApps",
+    r"E:\Games",
+];
+
+/// Имена папки китайского клиента
+const CN_INSTALL_FOLDERS: [&str; 3] = ["火炬之光无限", "火炬之光：无限", "TorchlightInfiniteCN"];
+
+/// Признаки китайского клиента в пути к логу
+const CN_PATH_MARKERS: [&str; 2] = ["火炬之光", "TorchlightInfiniteCN"];
+
+/// Кандидаты пути к логу для клиента (Auto — сначала глобальный, затем китайский)
+fn candidate_log_paths(client: GameClient) -> Vec<PathBuf> {
+    let global = || GLOBAL_INSTALL_DIRS.iter().map(|dir| Path::new(dir).join(LOG_RELATIVE_PATH));
+    let cn = || {
+        CN_INSTALL_ROOTS.iter().flat_map(|root| {
+            CN_INSTALL_FOLDERS.iter().map(move |folder| Path::new(root).join(folder).join(LOG_RELATIVE_PATH))
+        })
+    };
+    match client {
+        GameClient::Global => global().collect(),
+        GameClient::Cn => cn().collect(),
+        GameClient::Auto => global().chain(cn()).collect(),
+    }
+}
+
+/// Определить клиент по пути к логу
+pub fn detect_game_client(log_path: &Path) -> GameClient {
+    let path = log_path.to_string_lossy();
+    if CN_PATH_MARKERS.iter().any(|m| path.contains(m)) {
+        GameClient::Cn
+    } else {
+        GameClient::Global
+    }
+}
+
+/// Клиент из настроек; Auto разрешается по пути к логу (без пути — глобальный)
+pub fn resolve_game_client(setting: GameClient, log_path: Option<&Path>) -> GameClient {
+    match setting {
+        GameClient::Auto => log_path.map(detect_game_client).unwrap_or(GameClient::Global),
+        explicit => explicit,
+    }
+}
+
+/// Steam App ID Torchlight: Infinite
+const STEAM_APP_ID: u32 = 1974050;
+/// Папка игры внутри steamapps/common
+const STEAM_GAME_FOLDER: &str = "Torchlight Infinite";
+
+/// Откуда найден кандидат пути к логу
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogCandidateOrigin {
+    /// Типичный путь установки
+    KnownPath,
+    /// Библиотека Steam из libraryfolders.vdf
+    SteamLibrary,
+    /// Папка установки из реестра (деинсталлятор Steam)
+    Registry,
+}
+
+/// Найденный лог-файл для выбора в UI
+#[derive(Debug, Clone, Serialize)]
+pub struct LogCandidate {
+    pub path: String,
+    pub client: GameClient,
+    pub origin: LogCandidateOrigin,
+    pub last_modified_secs_ago: Option<u64>,
+    pub size_bytes: Option<u64>,
+}
+
+/// Значение из реестра Windows через `reg query` (без отдельной зависимости)
+#[cfg(windows)]
+fn read_registry_value(key: &str, value: &str) -> Option<String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let output = std::process::Command::new("reg")
+        .args(["query", key, "/v", value])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // "    SteamPath    REG_SZ    c:/program files (x86)/steam"
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.split_once("REG_SZ").map(|(_, v)| v.trim().to_string()))
+        .filter(|v| !v.is_empty())
+}
+
+#[cfg(not(windows))]
+fn read_registry_value(_key: &str, _value: &str) -> Option<String> {
     None
 }
 
+/// Папки установки Steam: реестр + путь по умолчанию
+fn steam_install_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = [
+        (r"HKCU\Software\Valve\Steam", "SteamPath"),
+        (r"HKLM\SOFTWARE\WOW6432Node\Valve\Steam", "InstallPath"),
+        (r"HKLM\SOFTWARE\Valve\Steam", "InstallPath"),
+    ]
+    .iter()
+    .filter_map(|(key, value)| read_registry_value(key, value))
+    .map(PathBuf::from)
+    .collect();
+    dirs.push(PathBuf::from(r"C:\Program Files (x86)\Steam"));
+    dirs
+}
+
+/// Пути библиотек из steamapps/libraryfolders.vdf.
+/// Поддерживает новый формат ("path" "D:\\SteamLibrary") и старый ("1" "D:\\SteamLibrary")
+fn parse_library_folders(vdf: &str) -> Vec<PathBuf> {
+    let re = regex::Regex::new(r#"^\s*"(path|\d+)"\s+"([^"]+)""#).expect("valid regex");
+    vdf.lines()
+        .filter_map(|line| re.captures(line))
+        .map(|caps| caps[2].replace(r"\\", r"\"))
+        // В старом формате под числовыми ключами бывают не только пути
+        .filter(|value| value.contains(':') || value.starts_with('/'))
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Логи игры во всех библиотеках Steam
+fn steam_library_log_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for steam_dir in steam_install_dirs() {
+        let vdf_path = steam_dir.join("steamapps").join("libraryfolders.vdf");
+        let Ok(vdf) = std::fs::read_to_string(&vdf_path) else {
+            continue;
+        };
+        debug!("Reading Steam libraries from {}", vdf_path.display());
+        let mut libraries = parse_library_folders(&vdf);
+        // Сама папка Steam — тоже библиотека, даже если её нет в vdf
+        libraries.push(steam_dir.clone());
+        for library in libraries {
+            paths.push(library.join("steamapps").join("common").join(STEAM_GAME_FOLDER).join(LOG_RELATIVE_PATH));
+        }
+    }
+    paths
+}
+
+/// Логи из папок установки, записанных деинсталляторами в реестр
+fn registry_log_paths() -> Vec<PathBuf> {
+    let uninstall_key = format!("Steam App {}", STEAM_APP_ID);
+    [
+        r"HKLM\SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\Uninstall",
+        r"HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall",
+        r"HKCU\Software\Microsoft\Windows\CurrentVersion\Uninstall",
+    ]
+    .iter()
+    .filter_map(|root| read_registry_value(&format!(r"{}\{}", root, uninstall_key), "InstallLocation"))
+    .map(|dir| Path::new(&dir).join(LOG_RELATIVE_PATH))
+    .collect()
+}
+
+/// Все существующие лог-файлы клиента, самые свежие первыми
+pub fn discover_log_candidates(client: GameClient) -> Vec<LogCandidate> {
+    let mut found: Vec<(PathBuf, LogCandidateOrigin)> = Vec::new();
+    if client != GameClient::Cn {
+        found.extend(steam_library_log_paths().into_iter().map(|p| (p, LogCandidateOrigin::SteamLibrary)));
+        found.extend(registry_log_paths().into_iter().map(|p| (p, LogCandidateOrigin::Registry)));
+    }
+    found.extend(candidate_log_paths(client).into_iter().map(|p| (p, LogCandidateOrigin::KnownPath)));
+
+    let mut seen = std::collections::HashSet::new();
+    let mut candidates: Vec<LogCandidate> = found
+        .into_iter()
+        .filter(|(path, _)| path.exists())
+        // Пути из реестра и vdf отличаются регистром и слэшами
+        .filter(|(path, _)| seen.insert(path.to_string_lossy().to_lowercase().replace('/', r"\")))
+        .map(|(path, origin)| {
+            let meta = std::fs::metadata(&path).ok();
+            LogCandidate {
+                client: detect_game_client(&path),
+                path: path.to_string_lossy().to_string(),
+                origin,
+                last_modified_secs_ago: meta
+                    .as_ref()
+                    .and_then(|m| m.modified().ok())
+                    .and_then(|t| t.elapsed().ok())
+                    .map(|d| d.as_secs()),
+                size_bytes: meta.map(|m| m.len()),
+            }
+        })
+        .collect();
+
+    // Свежие первыми; файлы без даты изменения — в конце
+    candidates.sort_by_key(|c| c.last_modified_secs_ago.unwrap_or(u64::MAX));
+    candidates
+}
+
+/// Найти путь к файлу логов TLI (Auto — любой клиент): самый свежий из найденных
+pub fn find_log_path_for(client: GameClient) -> Option<PathBuf> {
+    match discover_log_candidates(client).into_iter().next() {
+        Some(candidate) => {
+            info!("Found TLI log file at: {} ({:?}, {:?})", candidate.path, candidate.client, candidate.origin);
+            Some(PathBuf::from(candidate.path))
+        }
+        None => {
+            warn!("Could not find TLI log file automatically ({:?})", client);
+            None
+        }
+    }
+}
+
 /// Состояние watcher'а
 pub struct LogWatcher {
     /// Путь к файлу логов
@@ -59,31 +434,148 @@ pub struct LogWatcher {
     running: Arc<Mutex<bool>>,
     /// Парсер логов
     parser: Arc<Mutex<LogParser>>,
+    /// Механизм отслеживания (общий с AppState, меняется на лету)
+    config: Arc<Mutex<WatcherConfig>>,
+    /// Прогресс догонялки (общий с AppState)
+    progress: CatchupRegistry,
+    /// Разбирать лог с этого момента (None — с конца файла)
+    start_from: Option<DateTime<Utc>>,
+    /// Отсев шумных строк до парсера (общий с AppState)
+    noise_filter: Option<Arc<NoiseFilter>>,
 }
 
 impl LogWatcher {
     /// Создать новый watcher
-    pub fn new(log_path: PathBuf, parser: Arc<Mutex<LogParser>>) -> Self {
+    pub fn new(
+        log_path: PathBuf,
+        parser: Arc<Mutex<LogParser>>,
+        config: Arc<Mutex<WatcherConfig>>,
+        progress: CatchupRegistry,
+    ) -> Self {
         Self {
             log_path,
             file_position: Arc::new(Mutex::new(0)),
             running: Arc::new(Mutex::new(false)),
             parser,
+            config,
+            progress,
+            start_from: None,
+            noise_filter: None,
+        }
+    }
+
+    /// Начать живое чтение с первой строки не раньше `since` вместо конца файла
+    pub fn with_start_from(mut self, since: Option<DateTime<Utc>>) -> Self {
+        self.start_from = since;
+        self
+    }
+
+    /// Отбрасывать строки, совпавшие с шумовыми паттернами, ещё в читателе
+    pub fn with_noise_filter(mut self, filter: Arc<NoiseFilter>) -> Self {
+        self.noise_filter = Some(filter);
+        self
+    }
+
+    /// Первая метка времени в строках, начиная с `offset` (первая строка может быть обрезана)
+    fn probe_timestamp(reader: &mut BufReader<File>, offset: u64, parser: &Mutex<LogParser>) -> Option<DateTime<Utc>> {
+        reader.seek(SeekFrom::Start(offset)).ok()?;
+        let mut line = String::new();
+        if offset > 0 {
+            reader.read_line(&mut line).ok()?;
+        }
+        for _ in 0..SEEK_PROBE_LINES {
+            line.clear();
+            if reader.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            if let Some(ts) = parser.lock().unwrap().line_timestamp(&line) {
+                return Some(ts);
+            }
+        }
+        None
+    }
+
+    /// Смещение первой строки с меткой не раньше `since`. Метки в логе идут по возрастанию,
+    /// поэтому бинарный поиск сужает окно, а границу строки находит построчный проход
+    fn offset_since(reader: &mut BufReader<File>, len: u64, since: DateTime<Utc>, parser: &Mutex<LogParser>) -> u64 {
+        let mut lo = 0u64;
+        let mut hi = len;
+        while hi - lo > SEEK_RESOLUTION_BYTES {
+            let mid = lo + (hi - lo) / 2;
+            match Self::probe_timestamp(reader, mid, parser) {
+                Some(ts) if ts < since => lo = mid,
+                _ => hi = mid,
+            }
+        }
+
+        if reader.seek(SeekFrom::Start(lo)).is_err() {
+            return len;
+        }
+        let mut pos = lo;
+        let mut line = String::new();
+        if lo > 0 {
+            pos += reader.read_line(&mut line).unwrap_or(0) as u64;
+        }
+        loop {
+            line.clear();
+            let bytes = match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => return len,
+                Ok(bytes) => bytes as u64,
+            };
+            if parser.lock().unwrap().line_timestamp(&line).is_some_and(|ts| ts >= since) {
+                return pos;
+            }
+            pos += bytes;
+        }
+    }
+
+    /// Дождаться новых данных в файле согласно текущей конфигурации
+    fn wait_for_changes(log_path: &Path, config: WatcherConfig, fs_events: &mut Option<FsEvents>) {
+        match config.mode {
+            LogWatchMode::Poll => {
+                // Переключились обратно на опрос — отписываемся от событий ФС
+                *fs_events = None;
+                thread::sleep(Duration::from_millis(config.poll_interval_ms));
+            }
+            LogWatchMode::Native => {
+                if fs_events.is_none() {
+                    *fs_events = FsEvents::subscribe(log_path);
+                }
+                match fs_events {
+                    Some(events) => events.wait(Duration::from_millis(
+                        NATIVE_FALLBACK_POLL_MS.max(config.poll_interval_ms),
+                    )),
+                    // notify недоступен — деградируем до опроса
+                    None => thread::sleep(Duration::from_millis(config.poll_interval_ms)),
+                }
+            }
         }
     }
     
-    /// Запустить отслеживание файла
-    /// Возвращает канал для получения событий
-    pub fn start(&self) -> mpsc::Receiver<LogEvent> {
-        let (tx, rx) = mpsc::channel(1000);
-        
+    /// Запустить отслеживание файла, отправляя события в общий канал
+    /// (несколько watcher'ов могут писать в один канал).
+    ///
+    /// Два потока: читатель только достаёт строки из файла, парсинг идёт в отдельном
+    /// воркере. Между ними ограниченный канал, к AppState — тоже ограниченный, поэтому
+    /// при догонялке большого лога читатель притормаживает, а не раздувает память.
+    pub fn start_into(&self, tx: mpsc::Sender<LogEvent>) {
         let log_path = self.log_path.clone();
         let file_position = self.file_position.clone();
         let running = self.running.clone();
         let parser = self.parser.clone();
+        let config = self.config.clone();
+        let progress = self.progress.clone();
+        let start_from = self.start_from;
+        let mut noise_reader = self.noise_filter.as_ref().map(NoiseFilter::reader);
+        let mut is_noise = move |line: &str| noise_reader.as_mut().is_some_and(|r| r.is_noise(line));
+        let source_id = parser.lock().unwrap().source_id().to_string();
+        let search_parser = parser.clone();
         
         // Устанавливаем флаг работы
         *running.lock().unwrap() = true;
+
+        let (line_tx, line_rx) = std::sync::mpsc::sync_channel::<WatcherMessage>(LINE_CHANNEL_CAPACITY);
+        Self::spawn_parse_worker(parser, line_rx, tx);
         
         // Запускаем поток чтения
         thread::spawn(move || {
@@ -99,52 +591,77 @@ impl LogWatcher {
             };
             
             let mut reader = BufReader::new(file);
+            let mut tracker = CatchupTracker::new(progress, source_id);
 
-            // Warm-up: читаем небольшой хвост файла, чтобы собрать baseline по слотам,
-            // иначе первый дроп стаков часто теряется.
-            if let Ok(metadata) = std::fs::metadata(&log_path) {
-                let len = metadata.len();
-                // 1MB хвост — обычно достаточно, чтобы захватить несколько PickItems и BagMgr линий.
-                let warmup_start = len.saturating_sub(1024 * 1024);
-                if reader.seek(SeekFrom::Start(warmup_start)).is_ok() {
-                    let mut warm_line = String::new();
-                    // Если не с начала файла — отбросим первую “обрезанную” строку.
-                    if warmup_start > 0 {
-                        let _ = reader.read_line(&mut warm_line);
-                        warm_line.clear();
-                    }
+            let len = std::fs::metadata(&log_path).map(|m| m.len()).unwrap_or(0);
 
-                    let mut warmed = 0usize;
-                    while reader.read_line(&mut warm_line).unwrap_or(0) > 0 {
-                        let line = warm_line.trim_end();
-                        parser.lock().unwrap().warmup_line(line);
-                        warmed += 1;
-                        warm_line.clear();
+            // Откуда начинается живое чтение: конец файла или первая строка не раньше start_from
+            let live_start = match start_from {
+                Some(since) => {
+                    let offset = Self::offset_since(&mut reader, len, since, &search_parser);
+                    info!("Log attach since {}: offset {} of {}", since.to_rfc3339(), offset, len);
+                    offset
+                }
+                None => len,
+            };
+
+            // Warm-up: читаем кусок перед точкой старта, чтобы собрать baseline по слотам и
+            // последний снимок инвентаря, иначе первый дроп стаков часто теряется.
+            let warmup_start = live_start.saturating_sub(WARMUP_MAX_BYTES);
+            if reader.seek(SeekFrom::Start(warmup_start)).is_ok() {
+                let mut warm_pos = warmup_start;
+                let mut warm_line = String::new();
+                // Если не с начала файла — отбросим первую “обрезанную” строку.
+                if warmup_start > 0 {
+                    warm_pos += reader.read_line(&mut warm_line).unwrap_or(0) as u64;
+                    warm_line.clear();
+                }
+
+                tracker.begin(live_start - warmup_start);
+                let mut warmed = 0usize;
+                while warm_pos < live_start {
+                    let bytes = match reader.read_line(&mut warm_line) {
+                        Ok(0) | Err(_) => break,
+                        Ok(bytes) => bytes,
+                    };
+                    warm_pos += bytes as u64;
+                    let line = warm_line.trim_end();
+                    if !is_noise(line) && line_tx.send(WatcherMessage::Warmup(line.to_string())).is_err() {
+                        return;
                     }
-                    debug!("Warm-up parsed {} lines from tail", warmed);
+                    tracker.advance(bytes as u64);
+                    warmed += 1;
+                    warm_line.clear();
                 }
+                let _ = line_tx.send(WatcherMessage::WarmupDone);
+                tracker.finish();
+                debug!("Warm-up read {} lines before offset {}", warmed, live_start);
             }
 
-            // Переходим в конец файла (реальный tail)
-            if let Ok(pos) = reader.seek(SeekFrom::End(0)) {
+            // Переходим к точке старта (для режима End — реальный tail)
+            if let Ok(pos) = reader.seek(SeekFrom::Start(live_start)) {
                 *file_position.lock().unwrap() = pos;
                 info!("Starting from position: {}", pos);
+                // История с момента start_from разбирается как живые события — с прогрессом
+                if len > pos {
+                    tracker.begin(len - pos);
+                }
             }
             
-            // Буфер для многострочных событий (например, цены)
-            let mut price_buffer: Vec<String> = Vec::new();
-            let mut in_price_block = false;
-            let mut current_price_event: Option<crate::types::PriceSearchEvent> = None;
+            let mut fs_events: Option<FsEvents> = None;
             
             while *running.lock().unwrap() {
                 let mut line = String::new();
                 
                 match reader.read_line(&mut line) {
                     Ok(0) => {
+                        // Догнали конец файла
+                        tracker.finish();
+
                         // Нет новых данных, ждём
-                        thread::sleep(Duration::from_millis(100));
+                        let cfg = *config.lock().unwrap();
+                        Self::wait_for_changes(&log_path, cfg, &mut fs_events);
                         
-                        // Проверяем, не был ли файл пересоздан (ротация логов)
                         if let Ok(metadata) = std::fs::metadata(&log_path) {
                             let current_pos = *file_position.lock().unwrap();
                             if metadata.len() < current_pos {
@@ -155,79 +672,30 @@ impl LogWatcher {
                                 if let Ok(new_file) = File::open(&log_path) {
                                     reader = BufReader::new(new_file);
                                     *file_position.lock().unwrap() = 0;
-                                    parser.lock().unwrap().reset_slot_cache();
+                                    // Сброс идёт через воркер, чтобы не обогнать ещё не разобранные строки
+                                    if line_tx.send(WatcherMessage::Reset).is_err() {
+                                        break;
+                                    }
                                 }
+                            } else if metadata.len() - current_pos > CATCHUP_THRESHOLD_BYTES {
+                                // Пока ждали, игра успела написать много — это догонялка
+                                tracker.begin(metadata.len() - current_pos);
                             }
                         }
                     }
                     Ok(bytes) => {
                         // Обновляем позицию
-                        let mut pos = file_position.lock().unwrap();
-                        *pos += bytes as u64;
-                        drop(pos);
+                        *file_position.lock().unwrap() += bytes as u64;
+                        tracker.advance(bytes as u64);
                         
-                        // Убираем trailing whitespace
                         let line = line.trim_end();
-                        
-                        let is_price_recv_start =
-                            line.contains("----Socket RecvMessage STT----XchgSearchPrice");
-
-                        // Обработка многострочных блоков цен
-                        if is_price_recv_start {
-                            // Сначала даём парсеру обработать STT строку, чтобы появился PriceSearchEvent
-                            // (иначе current_price_event останется None).
-                            if let Some(event) = parser.lock().unwrap().parse_line(line) {
-                                if let LogEvent::PriceSearch(pe) = &event {
-                                    current_price_event = Some(pe.clone());
-                                }
-                            }
-
-                            in_price_block = true;
-                            price_buffer.clear();
-                            price_buffer.push(line.to_string());
-                            continue;
-                        }
-                        
-                        if in_price_block {
-                            price_buffer.push(line.to_string());
-                            
-                            if line.contains("----Socket RecvMessage End----") {
-                                in_price_block = false;
-                                
-                                // Парсим блок цен
-                                if let Some(mut event) = current_price_event.take() {
-                                    let lines: Vec<&str> = price_buffer.iter().map(|s| s.as_str()).collect();
-                                    let (prices, currency) = parser.lock().unwrap().parse_price_block(&lines);
-                                    event.prices = prices;
-                                    event.currency_id = currency;
-                                    
-                                    if !event.prices.is_empty() {
-                                        debug!("Price event complete: game_id={}, prices={:?}", 
-                                               event.game_id, event.prices);
-                                        let _ = tx.blocking_send(LogEvent::PriceSearch(event));
-                                    }
-                                }
-                                price_buffer.clear();
-                            }
+                        if is_noise(line) {
                             continue;
                         }
-                        
-                        // Парсим строку
-                        let mut parser_guard = parser.lock().unwrap();
-                        if let Some(event) = parser_guard.parse_line(line) {
-                            match &event {
-                                LogEvent::PriceSearch(pe) => {
-                                    // Сохраняем для заполнения ценами
-                                    current_price_event = Some(pe.clone());
-                                }
-                                _ => {
-                                    // Отправляем событие
-                                    if tx.blocking_send(event).is_err() {
-                                        warn!("Failed to send event, receiver dropped");
-                                        break;
-                                    }
-                                }
-                            }
+                        // Воркер занят — ждём (backpressure), воркер завершился — выходим
+                        if line_tx.send(WatcherMessage::Line(line.to_string())).is_err() {
+                            warn!("Parse worker stopped, stopping log watcher");
+                            break;
                         }
                     }
                     Err(e) => {
@@ -237,10 +705,120 @@ impl LogWatcher {
                 }
             }
             
+            tracker.finish();
             info!("Log watcher stopped");
         });
-        
-        rx
+    }
+
+    /// Воркер парсинга: строки от читателя -> события в канал AppState
+    fn spawn_parse_worker(
+        parser: Arc<Mutex<LogParser>>,
+        line_rx: std::sync::mpsc::Receiver<WatcherMessage>,
+        tx: mpsc::Sender<LogEvent>,
+    ) {
+        thread::spawn(move || {
+            // Буфер для многострочных событий (например, цены)
+            let mut price_buffer: Vec<String> = Vec::new();
+            let mut in_price_block = false;
+            let mut current_price_event: Option<crate::types::PriceSearchEvent> = None;
+
+            while let Ok(message) = line_rx.recv() {
+                let line = match message {
+                    WatcherMessage::Warmup(line) => {
+                        parser.lock().unwrap().warmup_line(&line);
+                        continue;
+                    }
+                    WatcherMessage::WarmupDone => {
+                        // Снимок инвентаря из хвоста — последнее известное состояние склада
+                        let snapshot = parser.lock().unwrap().take_inventory_snapshot();
+                        if let Some(snapshot) = snapshot {
+                            let _ = tx.blocking_send(LogEvent::InventorySnapshot(snapshot));
+                        }
+                        continue;
+                    }
+                    WatcherMessage::Reset => {
+                        let source_id = {
+                            let mut parser = parser.lock().unwrap();
+                            parser.reset_slot_cache();
+                            parser.source_id().to_string()
+                        };
+                        let _ = tx.blocking_send(LogEvent::LogReset { source_id });
+                        price_buffer.clear();
+                        in_price_block = false;
+                        current_price_event = None;
+                        continue;
+                    }
+                    WatcherMessage::Line(line) => line,
+                };
+                let line = line.as_str();
+
+                let is_price_recv_start =
+                    line.contains("----Socket RecvMessage STT----XchgSearchPrice");
+
+                // Обработка многострочных блоков цен
+                if is_price_recv_start {
+                    // Сначала даём парсеру обработать STT строку, чтобы появился PriceSearchEvent
+                    // (иначе current_price_event останется None).
+                    if let Some(LogEvent::PriceSearch(pe)) = parser.lock().unwrap().parse_line(line) {
+                        current_price_event = Some(pe);
+                    }
+
+                    in_price_block = true;
+                    price_buffer.clear();
+                    price_buffer.push(line.to_string());
+                    continue;
+                }
+                
+                if in_price_block {
+                    price_buffer.push(line.to_string());
+                    
+                    if line.contains("----Socket RecvMessage End----") {
+                        in_price_block = false;
+                        
+                        // Парсим блок цен
+                        if let Some(mut event) = current_price_event.take() {
+                            let lines: Vec<&str> = price_buffer.iter().map(|s| s.as_str()).collect();
+                            let (prices, currency) = parser.lock().unwrap().parse_price_block(&lines);
+                            event.prices = prices;
+                            event.currency_id = currency;
+                            
+                            if !event.prices.is_empty() {
+                                debug!("Price event complete: game_id={}, prices={:?}", 
+                                       event.game_id, event.prices);
+                                let _ = tx.blocking_send(LogEvent::PriceSearch(event));
+                            }
+                        }
+                        price_buffer.clear();
+                    }
+                    continue;
+                }
+                
+                // Парсим строку (блокировку парсера отпускаем до отправки: канал может быть полон)
+                let (event, snapshot) = {
+                    let mut parser_guard = parser.lock().unwrap();
+                    let event = parser_guard.parse_line(line);
+                    (event, parser_guard.take_inventory_snapshot())
+                };
+                if let Some(snapshot) = snapshot {
+                    let _ = tx.blocking_send(LogEvent::InventorySnapshot(snapshot));
+                }
+                match event {
+                    Some(LogEvent::PriceSearch(pe)) => {
+                        // Сохраняем для заполнения ценами
+                        current_price_event = Some(pe);
+                    }
+                    Some(event) => {
+                        // Отправляем событие
+                        if tx.blocking_send(event).is_err() {
+                            warn!("Failed to send event, receiver dropped");
+                            break;
+                        }
+                    }
+                    None => {}
+                }
+            }
+            debug!("Parse worker stopped");
+        });
     }
     
     /// Остановить отслеживание
@@ -267,3 +845,96 @@ impl LogWatcher {
         self.parser.lock().unwrap().reset_slot_cache();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Лог из `count` строк по секунде, кроме строк `run` — у них одна метка времени.
+    /// Возвращает путь, метки строк и смещения их начал
+    fn write_log(count: usize, run: std::ops::Range<usize>) -> (PathBuf, Vec<String>, Vec<u64>) {
+        let base = chrono::NaiveDate::from_ymd_opt(2026, 1, 12).unwrap().and_hms_opt(11, 0, 0).unwrap();
+        let mut content = String::new();
+        let (mut lines, mut offsets) = (Vec::new(), Vec::new());
+        for i in 0..count {
+            let second = if run.contains(&i) { run.start } else { i };
+            let at = base + chrono::Duration::seconds(second as i64);
+            let line = format!("[{}][980]GameLog: Display: [Game] filler line {:06}\n", at.format("%Y.%m.%d-%H.%M.%S:000"), i);
+            offsets.push(content.len() as u64);
+            content.push_str(&line);
+            lines.push(line);
+        }
+        let path = std::env::temp_dir().join(format!("tli-seek-{}.log", uuid::Uuid::new_v4()));
+        std::fs::write(&path, content).unwrap();
+        (path, lines, offsets)
+    }
+
+    #[test]
+    fn offset_since_finds_line_boundaries() {
+        let (path, lines, offsets) = write_log(4000, 1500..2500);
+        let len = std::fs::metadata(&path).unwrap().len();
+        assert!(len > SEEK_RESOLUTION_BYTES * 4);
+        let parser = Mutex::new(LogParser::new());
+        let ts = |i: usize| parser.lock().unwrap().line_timestamp(&lines[i]).unwrap();
+        let mut reader = BufReader::new(File::open(&path).unwrap());
+        let mut seek = |since| LogWatcher::offset_since(&mut reader, len, since, &parser);
+
+        assert_eq!(seek(ts(0) - chrono::Duration::hours(1)), 0);
+        assert_eq!(seek(ts(3999) + chrono::Duration::seconds(1)), len);
+        assert_eq!(seek(ts(3200)), offsets[3200]);
+        // Серия одинаковых меток: с первой строки серии, а не из её середины
+        assert_eq!(seek(ts(2000)), offsets[1500]);
+        assert_eq!(seek(ts(1500) + chrono::Duration::milliseconds(500)), offsets[2500]);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    fn tracker() -> (CatchupRegistry, CatchupTracker) {
+        let registry: CatchupRegistry = Arc::new(Mutex::new(HashMap::new()));
+        let tracker = CatchupTracker::new(registry.clone(), "main".to_string());
+        (registry, tracker)
+    }
+
+    fn published(registry: &CatchupRegistry) -> CatchupProgress {
+        registry.lock().unwrap().get("main").cloned().unwrap()
+    }
+
+    #[test]
+    fn catchup_publishes_in_steps() {
+        let (registry, mut tracker) = tracker();
+        tracker.advance(100);
+        assert!(registry.lock().unwrap().is_empty());
+
+        tracker.begin(PROGRESS_STEP_BYTES * 3);
+        assert!(published(&registry).is_catching_up);
+
+        tracker.advance(PROGRESS_STEP_BYTES / 2);
+        assert_eq!(published(&registry).bytes_done, 0);
+        tracker.advance(PROGRESS_STEP_BYTES / 2);
+        assert_eq!(published(&registry).bytes_done, PROGRESS_STEP_BYTES);
+
+        // Повторный begin во время догонялки не сбрасывает прогресс
+        tracker.begin(1);
+        assert_eq!(tracker.progress.bytes_done, PROGRESS_STEP_BYTES);
+
+        tracker.finish();
+        let done = published(&registry);
+        assert!(!done.is_catching_up && done.finished_at.is_some());
+        assert_eq!(done.bytes_done, PROGRESS_STEP_BYTES);
+    }
+
+    #[test]
+    fn catchup_total_follows_growing_file() {
+        let (registry, mut tracker) = tracker();
+        tracker.begin(10);
+        tracker.advance(PROGRESS_STEP_BYTES);
+        let progress = published(&registry);
+        assert_eq!((progress.bytes_done, progress.bytes_total), (PROGRESS_STEP_BYTES, PROGRESS_STEP_BYTES));
+
+        // Новая догонялка начинается с нуля
+        tracker.finish();
+        tracker.begin(500);
+        let progress = published(&registry);
+        assert_eq!((progress.bytes_done, progress.bytes_total, progress.is_catching_up), (0, 500, true));
+    }
+}
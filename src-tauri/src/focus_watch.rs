@@ -0,0 +1,155 @@
+//! Скрытие окон, когда игра не в фокусе
+//!
+//! Раз в FOCUS_POLL_MS смотрим, чьё окно на переднем плане (только Windows). Если
+//! включено AppSettings.hide_when_game_unfocused и фокус ушёл в другое приложение,
+//! видимые окна компаньона прячутся, а при возврате в игру показываются снова — без
+//! активации, чтобы не отнять фокус у игры. Наше собственное окно в фокусе — это
+//! пользователь что-то правит, тогда ничего не прячем. Пока игра не запущена (по
+//! game_process), окна не прячутся вовсе: прятать их не от чего.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, info};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::state::AppState;
+
+/// Как часто проверять окно на переднем плане
+const FOCUS_POLL_MS: u64 = 500;
+
+/// Сколько проверок подряд фокус должен быть вне игры, чтобы прятать окна
+/// (Alt+Tab проходит через переключатель задач)
+const UNFOCUSED_CONFIRMATIONS: u32 = 2;
+
+/// Кто на переднем плане
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(windows), allow(dead_code))]
+enum Foreground {
+    Game,
+    Companion,
+    Other,
+}
+
+/// Окно на переднем плане. None — определить нельзя (не Windows)
+#[cfg(windows)]
+fn foreground() -> Option<Foreground> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    // SAFETY: вызовы Win32 без владения памятью, кроме буфера имени на стеке
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_null() {
+            return Some(Foreground::Other);
+        }
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        if pid == std::process::id() {
+            return Some(Foreground::Companion);
+        }
+
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            return Some(Foreground::Other);
+        }
+        let mut buf = [0u16; 1024];
+        let mut len = buf.len() as u32;
+        let ok = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, buf.as_mut_ptr(), &mut len);
+        CloseHandle(process);
+        if ok == 0 {
+            return Some(Foreground::Other);
+        }
+
+        let path = String::from_utf16_lossy(&buf[..len as usize]);
+        let image = path.rsplit(['\\', '/']).next().unwrap_or("");
+        Some(if crate::game_process::is_game_image(image) { Foreground::Game } else { Foreground::Other })
+    }
+}
+
+#[cfg(not(windows))]
+fn foreground() -> Option<Foreground> {
+    None
+}
+
+/// Показать окно, не забирая фокус у игры
+#[cfg(windows)]
+fn show_without_focus(window: &WebviewWindow) -> tauri::Result<()> {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{ShowWindow, SW_SHOWNOACTIVATE};
+
+    let hwnd = window.hwnd()?;
+    // SAFETY: hwnd принадлежит живому окну Tauri
+    unsafe {
+        ShowWindow(hwnd.0 as _, SW_SHOWNOACTIVATE);
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn show_without_focus(window: &WebviewWindow) -> tauri::Result<()> {
+    window.show()
+}
+
+/// Спрятать все видимые окна. Возвращает их label для последующего показа
+fn hide_visible(app: &AppHandle) -> HashSet<String> {
+    let mut hidden = HashSet::new();
+    for (label, window) in app.webview_windows() {
+        if window.is_visible().unwrap_or(false) && window.hide().is_ok() {
+            hidden.insert(label);
+        }
+    }
+    hidden
+}
+
+/// Вернуть спрятанные окна
+fn restore(app: &AppHandle, hidden: &mut HashSet<String>) {
+    for label in hidden.drain() {
+        if let Some(window) = app.get_webview_window(&label) {
+            if let Err(e) = show_without_focus(&window) {
+                debug!("Failed to show window {}: {}", label, e);
+            }
+        }
+    }
+}
+
+/// Цикл наблюдения за фокусом (до выхода из приложения)
+pub async fn run(app: AppHandle, state: Arc<AppState>) {
+    let mut ticker = tokio::time::interval(Duration::from_millis(FOCUS_POLL_MS));
+    let mut hidden: HashSet<String> = HashSet::new();
+    let mut unfocused_checks = 0u32;
+    loop {
+        ticker.tick().await;
+        let enabled = state.settings.read().await.hide_when_game_unfocused;
+        let game_running = state.game_running() == Some(true);
+        let current = if enabled && game_running { foreground() } else { None };
+
+        let Some(current) = current else {
+            // Выключили настройку, игра не запущена (или фокус не определить) — вернуть спрятанное
+            if !hidden.is_empty() {
+                restore(&app, &mut hidden);
+            }
+            unfocused_checks = 0;
+            continue;
+        };
+
+        if current == Foreground::Other {
+            unfocused_checks += 1;
+            if unfocused_checks == UNFOCUSED_CONFIRMATIONS && hidden.is_empty() {
+                hidden = hide_visible(&app);
+                if !hidden.is_empty() {
+                    info!("Game lost focus, hiding {} window(s)", hidden.len());
+                }
+            }
+        } else {
+            unfocused_checks = 0;
+            if current == Foreground::Game && !hidden.is_empty() {
+                info!("Game focused again, restoring {} window(s)", hidden.len());
+                restore(&app, &mut hidden);
+            }
+        }
+    }
+}
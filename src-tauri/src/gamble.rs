@@ -0,0 +1,317 @@
+//! Ожидаемая стоимость (EV) открытия контейнеров
+//!
+//! Открытия копятся в gamble_openings.json через все сессии: вводом вручную или из
+//! лога. Из лога открытие собирается так: стак контейнера из списка
+//! `AppSettings.gamble_containers` уменьшился вне подбора, и в течение
+//! OPENING_WINDOW_MS выросли слоты инвентаря (вне PickItems — так игра кладёт
+//! содержимое) или пришли подборы — это и есть содержимое. Уменьшение без прибавок
+//! (продажа, перекладывание) открытием не считается.
+//! EV считается по текущим ценам: средняя стоимость содержимого минус цена контейнера.
+
+use std::collections::HashMap;
+use std::time::{Duration as StdDuration, Instant};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::persistence::PersistedPriceEntry;
+use crate::types::{ItemConsumedEvent, ItemDropEvent, ItemGainedEvent, ItemInfo};
+use crate::valuation::Valuation;
+
+/// Окно после расхода контейнера, в которое подборы считаются его содержимым (по времени лога)
+const OPENING_WINDOW_MS: i64 = 2000;
+
+/// Сколько открытий храним (старые отбрасываются)
+pub const MAX_STORED_OPENINGS: usize = 10_000;
+
+/// Открытие контейнера (одно или пачка из opened_count штук)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GambleOpening {
+    pub id: String,
+    pub container_game_id: i64,
+    pub opened_count: i32,
+    pub opened_at: DateTime<Utc>,
+    /// Содержимое: game_id -> количество
+    pub outputs: HashMap<i64, i32>,
+    /// Введено вручную (иначе собрано из лога)
+    #[serde(default)]
+    pub manual: bool,
+}
+
+/// Предмет из контейнера в статистике
+#[derive(Debug, Clone, Serialize)]
+pub struct GambleOutcome {
+    pub game_id: i64,
+    pub item_info: Option<ItemInfo>,
+    pub total_quantity: i32,
+    /// Среднее количество на одно открытие
+    pub per_opening: f64,
+    /// Сколько открытий (в штуках) дали этот предмет, доля от всех
+    pub hit_rate: f64,
+    /// Вклад в среднюю стоимость открытия (FE)
+    pub value_per_opening: f64,
+}
+
+/// Наблюдаемая EV контейнера
+#[derive(Debug, Clone, Serialize)]
+pub struct GambleEv {
+    pub container_game_id: i64,
+    pub container: Option<ItemInfo>,
+    /// Открыто штук
+    pub openings: i32,
+    /// Текущая цена контейнера (None — нет цены, EV без учёта стоимости контейнера)
+    pub container_price: Option<f64>,
+    /// Средняя стоимость содержимого одного открытия
+    pub avg_output_value: f64,
+    /// avg_output_value - container_price
+    pub ev: f64,
+    /// По убыванию вклада в стоимость
+    pub outcomes: Vec<GambleOutcome>,
+}
+
+/// Собираемое из лога открытие
+#[derive(Debug)]
+struct PendingOpening {
+    opening: GambleOpening,
+    /// Когда получено событие расхода (для сброса, если после него лог затих)
+    received: Instant,
+}
+
+/// Сборщик открытий из событий лога
+#[derive(Debug, Default)]
+pub struct OpeningTracker {
+    pending: Option<PendingOpening>,
+}
+
+impl OpeningTracker {
+    /// Расход предмета. Контейнер из списка начинает новое открытие; предыдущее
+    /// (если было) возвращается готовым
+    pub fn on_consumed(&mut self, event: &ItemConsumedEvent, containers: &[i64]) -> Option<GambleOpening> {
+        if !containers.contains(&event.game_id) {
+            return None;
+        }
+        let finished = self.take_finished();
+        self.pending = Some(PendingOpening {
+            opening: GambleOpening {
+                id: uuid::Uuid::new_v4().to_string(),
+                container_game_id: event.game_id,
+                opened_count: event.quantity,
+                opened_at: event.timestamp,
+                outputs: HashMap::new(),
+                manual: false,
+            },
+            received: Instant::now(),
+        });
+        finished
+    }
+
+    /// Подбор: в окне открытия — его содержимое. Возвращает открытие, если окно уже закрылось
+    pub fn on_drop(&mut self, event: &ItemDropEvent) -> Option<GambleOpening> {
+        self.on_output(event.game_id, event.quantity, event.timestamp)
+    }
+
+    /// Рост слота вне подбора — так в инвентарь попадает содержимое контейнера
+    pub fn on_gained(&mut self, event: &ItemGainedEvent) -> Option<GambleOpening> {
+        self.on_output(event.game_id, event.quantity, event.timestamp)
+    }
+
+    fn on_output(&mut self, game_id: i64, quantity: i32, timestamp: DateTime<Utc>) -> Option<GambleOpening> {
+        let pending = self.pending.as_mut()?;
+        let window_end = pending.opening.opened_at + Duration::milliseconds(OPENING_WINDOW_MS);
+        if timestamp >= pending.opening.opened_at && timestamp <= window_end {
+            // Контейнер, переложенный в другой слот, своим содержимым не считается
+            if game_id != pending.opening.container_game_id {
+                *pending.opening.outputs.entry(game_id).or_insert(0) += quantity;
+            }
+            return None;
+        }
+        self.take_finished()
+    }
+
+    /// Закрыть открытие, если после расхода лог молчит дольше окна
+    pub fn flush_stale(&mut self) -> Option<GambleOpening> {
+        let window = StdDuration::from_millis(OPENING_WINDOW_MS as u64 * 2);
+        match &self.pending {
+            Some(p) if p.received.elapsed() >= window => self.take_finished(),
+            _ => None,
+        }
+    }
+
+    /// Забрать текущее открытие; без содержимого это не открытие (продажа, перекладывание)
+    fn take_finished(&mut self) -> Option<GambleOpening> {
+        self.pending.take().map(|p| p.opening).filter(|o| !o.outputs.is_empty())
+    }
+}
+
+/// Штук открыто и game_id -> (количество, штук открытий с этим предметом)
+type ContainerOpenings = (i32, HashMap<i64, (i32, i32)>);
+
+/// EV по контейнерам (filter — только один контейнер), по убыванию числа открытий
+pub fn ev_stats(
+    openings: &[GambleOpening],
+    filter: Option<i64>,
+    items_cache: &HashMap<i64, ItemInfo>,
+    prices: &HashMap<i64, PersistedPriceEntry>,
+    valuation: &Valuation,
+    language: &str,
+) -> Vec<GambleEv> {
+    let mut grouped: HashMap<i64, ContainerOpenings> = HashMap::new();
+    for opening in openings.iter().filter(|o| filter.map(|id| o.container_game_id == id).unwrap_or(true)) {
+        let entry = grouped.entry(opening.container_game_id).or_default();
+        entry.0 += opening.opened_count.max(1);
+        for (game_id, qty) in &opening.outputs {
+            let outcome = entry.1.entry(*game_id).or_insert((0, 0));
+            outcome.0 += qty;
+            outcome.1 += opening.opened_count.max(1);
+        }
+    }
+
+    let mut stats: Vec<GambleEv> = grouped
+        .into_iter()
+        .map(|(container_game_id, (count, outputs))| {
+            let count_f = count as f64;
+            let mut outcomes: Vec<GambleOutcome> = outputs
+                .into_iter()
+                .map(|(game_id, (total_quantity, hits))| {
+                    let unit_price = Valuation::unit_price(game_id, items_cache, prices).unwrap_or(0.0);
+                    GambleOutcome {
+                        game_id,
+                        item_info: items_cache.get(&game_id).map(|i| i.localized(language)),
+                        total_quantity,
+                        per_opening: total_quantity as f64 / count_f,
+                        hit_rate: (hits as f64 / count_f).min(1.0),
+                        value_per_opening: valuation.round(unit_price * total_quantity as f64 / count_f),
+                    }
+                })
+                .collect();
+            outcomes.sort_by(|a, b| {
+                b.value_per_opening.partial_cmp(&a.value_per_opening).unwrap_or(std::cmp::Ordering::Equal)
+            });
+
+            let avg_output_value = valuation.round(outcomes.iter().map(|o| o.value_per_opening).sum());
+            let container_price = Valuation::unit_price(container_game_id, items_cache, prices);
+            GambleEv {
+                container_game_id,
+                container: items_cache.get(&container_game_id).map(|i| i.localized(language)),
+                openings: count,
+                container_price,
+                avg_output_value,
+                ev: valuation.round(avg_output_value - container_price.unwrap_or(0.0)),
+                outcomes,
+            }
+        })
+        .collect();
+    stats.sort_by_key(|s| std::cmp::Reverse(s.openings));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        DateTime::<Utc>::UNIX_EPOCH + Duration::milliseconds(ms)
+    }
+
+    fn consumed(game_id: i64, ms: i64) -> ItemConsumedEvent {
+        ItemConsumedEvent { game_id, quantity: 1, timestamp: at(ms), source_id: "main".to_string() }
+    }
+
+    fn pickup(game_id: i64, quantity: i32, ms: i64) -> ItemDropEvent {
+        ItemDropEvent {
+            game_id,
+            quantity,
+            timestamp: at(ms),
+            page_id: 102,
+            slot_id: 1,
+            slot_quantity: 0,
+            source_id: "main".to_string(),
+            tier: None,
+            rarity: None,
+            item_name: None,
+        }
+    }
+
+    #[test]
+    fn collects_pickups_after_container_use() {
+        let mut tracker = OpeningTracker::default();
+        let containers = [500];
+
+        // Не контейнер — игнорируется
+        assert!(tracker.on_consumed(&consumed(1, 0), &containers).is_none());
+        assert!(tracker.on_consumed(&consumed(500, 0), &containers).is_none());
+        assert!(tracker.on_drop(&pickup(7, 3, 500)).is_none());
+
+        // Подбор за окном закрывает открытие
+        let opening = tracker.on_drop(&pickup(8, 1, 5000)).expect("opening finished");
+        assert_eq!(opening.container_game_id, 500);
+        assert_eq!(opening.outputs, HashMap::from([(7, 3)]));
+
+        // Расход без подборов (продажа) открытием не считается
+        assert!(tracker.on_consumed(&consumed(500, 6000), &containers).is_none());
+        assert!(tracker.on_consumed(&consumed(500, 9000), &containers).is_none());
+    }
+
+    #[test]
+    fn collects_bag_increases_from_a_real_log_sequence() {
+        use crate::log_parser::LogParser;
+        use crate::types::LogEvent;
+
+        let line = |time: &str, slot: i32, game_id: i64, num: i32| {
+            format!(
+                "[2026.01.12-11.34.{}][980]GameLog: Display: [Game] BagMgr@:Modfy BagItem PageId = 103 SlotId = {} ConfigBaseId = {} Num = {}",
+                time, slot, game_id, num
+            )
+        };
+        let log = [
+            line("07:000", 4, 500, 5),
+            line("07:000", 7, 100300, 10),
+            // Открыли контейнер: стак 5 -> 4, содержимое ложится в инвентарь вне PickItems
+            line("08:000", 4, 500, 4),
+            line("08:200", 7, 100300, 13),
+            line("08:300", 7, 100300, 15),
+            // Следующая строка за окном закрывает открытие
+            line("15:000", 7, 100300, 16),
+        ];
+
+        let mut parser = LogParser::new();
+        let mut tracker = OpeningTracker::default();
+        let mut finished = Vec::new();
+        for raw in &log {
+            let opening = match parser.parse_line(raw) {
+                Some(LogEvent::ItemConsumed(event)) => tracker.on_consumed(&event, &[500]),
+                Some(LogEvent::ItemGained(event)) => tracker.on_gained(&event),
+                Some(LogEvent::ItemDrop(event)) => tracker.on_drop(&event),
+                _ => None,
+            };
+            finished.extend(opening);
+        }
+
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].container_game_id, 500);
+        assert_eq!(finished[0].opened_count, 1);
+        assert_eq!(finished[0].outputs, HashMap::from([(100300, 5)]));
+    }
+
+    #[test]
+    fn averages_outcomes_per_opening() {
+        let opening = |outputs: HashMap<i64, i32>| GambleOpening {
+            id: String::new(),
+            container_game_id: 500,
+            opened_count: 1,
+            opened_at: at(0),
+            outputs,
+            manual: true,
+        };
+        let openings = vec![opening(HashMap::from([(7, 2)])), opening(HashMap::from([(7, 1), (8, 1)]))];
+        let stats = ev_stats(&openings, None, &HashMap::new(), &HashMap::new(), &Valuation::default(), "en");
+
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].openings, 2);
+        let seven = stats[0].outcomes.iter().find(|o| o.game_id == 7).unwrap();
+        assert_eq!(seven.per_opening, 1.5);
+        assert_eq!(seven.hit_rate, 1.0);
+        let eight = stats[0].outcomes.iter().find(|o| o.game_id == 8).unwrap();
+        assert_eq!(eight.hit_rate, 0.5);
+    }
+}
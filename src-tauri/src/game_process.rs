@@ -0,0 +1,80 @@
+//! Наблюдение за процессом игры
+//!
+//! Раз в несколько секунд проверяем, запущен ли клиент TLI (через `tasklist`, без
+//! отдельной зависимости). Закрылась игра или упала — сессия встаёт на автопаузу,
+//! запустилась снова — автопауза снимается. Ручную паузу пользователя не трогаем.
+
+use serde::Serialize;
+
+/// Как часто проверять процесс игры
+pub const GAME_PROCESS_POLL_SEC: u64 = 5;
+
+/// Сколько проверок подряд игра должна отсутствовать, чтобы считать её закрытой
+/// (перезапуск клиента и лаунчер не должны ставить паузу)
+pub const GAME_CLOSED_CONFIRMATIONS: u32 = 2;
+
+/// Имена исполняемых файлов клиента (глобальный и CN)
+const GAME_PROCESS_NAMES: &[&str] = &[
+    "torchlight_infinite.exe",
+    "torchlight-win64-shipping.exe",
+];
+
+/// Изменение состояния процесса игры (событие для UI)
+#[derive(Debug, Clone, Serialize)]
+pub struct GameProcessEvent {
+    pub running: bool,
+    /// Сессия поставлена на паузу или снята с неё из-за этого изменения
+    pub session_paused: Option<bool>,
+}
+
+/// Запущена ли игра. None — определить нельзя (не Windows или tasklist недоступен)
+#[cfg(windows)]
+pub fn is_game_running() -> Option<bool> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let output = std::process::Command::new("tasklist")
+        .args(["/FO", "CSV", "/NH"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(contains_game_process(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(not(windows))]
+pub fn is_game_running() -> Option<bool> {
+    None
+}
+
+/// Есть ли клиент игры в выводе `tasklist /FO CSV /NH`
+/// ("torchlight_infinite.exe","1234","Console","1","1 234 567 K")
+#[cfg_attr(not(any(windows, test)), allow(dead_code))]
+fn contains_game_process(tasklist_csv: &str) -> bool {
+    tasklist_csv.lines().any(|line| {
+        let image = line.split(',').next().unwrap_or("").trim().trim_matches('"');
+        is_game_image(image)
+    })
+}
+
+/// Имя исполняемого файла — клиент игры
+#[cfg_attr(not(any(windows, test)), allow(dead_code))]
+pub(crate) fn is_game_image(image: &str) -> bool {
+    GAME_PROCESS_NAMES.iter().any(|name| image.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_game_in_tasklist_output() {
+        let running = "\"explorer.exe\",\"4120\",\"Console\",\"1\",\"120 400 K\"\r\n\
+                       \"TorchLight_Infinite.exe\",\"9932\",\"Console\",\"1\",\"3 204 112 K\"\r\n";
+        assert!(contains_game_process(running));
+        assert!(!contains_game_process("\"explorer.exe\",\"4120\",\"Console\",\"1\",\"120 400 K\"\r\n"));
+        assert!(!contains_game_process(""));
+    }
+}
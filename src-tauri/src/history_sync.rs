@@ -0,0 +1,322 @@
+//! Two-way session history sync between devices
+//!
+//! Local history (per user, see `persistence`) is merged with tli_farm_sessions:
+//! - records already linked by `remote_id` keep the local copy (it is the richer one);
+//! - an unlinked local record with the same `started_at` (±1s) as a remote one is linked to it
+//!   (e.g. an upload whose response was lost);
+//! - remaining remote sessions are pulled in as new local records;
+//! - several local records pointing at the same remote id are deduplicated;
+//! - remote sessions that were merged into a local record (`merged_remote_ids`) are skipped.
+//!
+//! Remote sessions started before the last league archive belong to that archive and are
+//! not pulled back into the current history (`skip_archived`).
+//!
+//! Every rule is deterministic, so running the sync on both devices converges.
+//!
+//! History exported to a file on another machine is merged with `merge_imported`.
+
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::persistence::SessionHistoryRecord;
+use crate::supabase_sync::SessionHistoryItem;
+
+/// Max `started_at` difference for an unlinked local record to match a remote session
+const LINK_TOLERANCE_SEC: i64 = 1;
+
+/// What happened during a merge
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HistoryMergeReport {
+    /// Remote sessions added to local history
+    pub pulled: usize,
+    /// Local records linked to an existing remote session by start time
+    pub linked: usize,
+    /// Linked records whose metrics differ from the remote copy (local copy kept),
+    /// plus dropped local duplicates of the same remote session
+    pub conflicted: usize,
+}
+
+/// Report returned by `import_session_history`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HistoryImportReport {
+    /// Records found in the file
+    pub total: usize,
+    /// Records added to local history
+    pub added: usize,
+    /// Records already present (same id, or same start and end time)
+    pub skipped: usize,
+}
+
+/// Report returned by `sync_history_now`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HistorySyncReport {
+    pub pushed: usize,
+    pub pulled: usize,
+    pub linked: usize,
+    pub conflicted: usize,
+    pub errors: Vec<String>,
+}
+
+fn metrics_differ(local: &SessionHistoryRecord, remote: &SessionHistoryItem) -> bool {
+    local.maps_completed != remote.maps_completed
+        || local.total_duration_sec != remote.total_duration_sec
+        || remote
+            .total_profit_calculated
+            .map(|p| (p - local.total_profit).abs() > 0.01)
+            .unwrap_or(false)
+}
+
+/// Drop remote sessions that started before the last league archive (`archived_at`)
+pub fn skip_archived(remote: Vec<SessionHistoryItem>, archived_at: Option<DateTime<Utc>>) -> Vec<SessionHistoryItem> {
+    match archived_at {
+        Some(archived_at) => remote.into_iter().filter(|r| r.started_at >= archived_at).collect(),
+        None => remote,
+    }
+}
+
+/// Remote ids already represented locally: linked records plus sources of merged ones
+fn known_remote_ids(local: &[SessionHistoryRecord]) -> HashSet<&str> {
+    local
+        .iter()
+        .flat_map(|r| r.remote_id.iter().chain(&r.merged_remote_ids))
+        .map(String::as_str)
+        .collect()
+}
+
+/// Remote ids that are not linked to any local record yet (their drops need fetching)
+pub fn unknown_remote_ids(local: &[SessionHistoryRecord], remote: &[SessionHistoryItem]) -> Vec<String> {
+    let known = known_remote_ids(local);
+    remote
+        .iter()
+        .filter(|r| !known.contains(r.id.as_str()))
+        .map(|r| r.id.clone())
+        .collect()
+}
+
+/// Merge remote sessions into local history. Result is sorted newest first.
+pub fn merge(
+    local: Vec<SessionHistoryRecord>,
+    remote: &[SessionHistoryItem],
+    remote_drops: &HashMap<String, HashMap<i64, i32>>,
+) -> (Vec<SessionHistoryRecord>, HistoryMergeReport) {
+    let mut report = HistoryMergeReport::default();
+
+    // Deduplicate local records by remote id: keep the latest ended_at, ties by id
+    let mut local = local;
+    local.sort_by(|a, b| b.ended_at.cmp(&a.ended_at).then_with(|| a.id.cmp(&b.id)));
+    let mut seen_remote: HashSet<String> = HashSet::new();
+    local.retain(|r| match &r.remote_id {
+        Some(id) if !seen_remote.insert(id.clone()) => {
+            report.conflicted += 1;
+            false
+        }
+        _ => true,
+    });
+
+    let merged_away: HashSet<String> = local.iter().flat_map(|r| r.merged_remote_ids.iter().cloned()).collect();
+    for remote_session in remote {
+        if merged_away.contains(&remote_session.id) {
+            continue;
+        }
+        if let Some(existing) = local.iter().find(|r| r.remote_id.as_deref() == Some(remote_session.id.as_str())) {
+            if metrics_differ(existing, remote_session) {
+                report.conflicted += 1;
+            }
+            continue;
+        }
+
+        let tolerance = Duration::seconds(LINK_TOLERANCE_SEC);
+        if let Some(unlinked) = local.iter_mut().find(|r| {
+            r.remote_id.is_none() && (r.started_at - remote_session.started_at).abs() <= tolerance
+        }) {
+            unlinked.remote_id = Some(remote_session.id.clone());
+            report.linked += 1;
+            if metrics_differ(unlinked, remote_session) {
+                report.conflicted += 1;
+            }
+            continue;
+        }
+
+        let total_profit = remote_session.total_profit_calculated.unwrap_or(0.0);
+        let total_expenses = remote_session.expenses_calculated.unwrap_or(0.0);
+        let total_fee = remote_session.fee_calculated.unwrap_or(0.0);
+        local.push(SessionHistoryRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            started_at: remote_session.started_at,
+            ended_at: remote_session
+                .ended_at
+                .unwrap_or(remote_session.started_at + Duration::seconds(remote_session.total_duration_sec as i64)),
+            maps_completed: remote_session.maps_completed,
+            total_duration_sec: remote_session.total_duration_sec,
+            total_profit,
+            total_expenses,
+            total_income: total_profit + total_expenses + total_fee,
+            total_fee,
+            remote_id: Some(remote_session.id.clone()),
+            drops: remote_drops.get(&remote_session.id).cloned().unwrap_or_default(),
+            drop_tiers: HashMap::new(),
+            share_code: None,
+            preset_id: remote_session.preset_id.clone(),
+            raw_currency_start: None,
+            raw_currency_end: None,
+            raw_currency_gained: None,
+            manual_realized: 0.0,
+            manual_pending: 0.0,
+            manual_pending_quoted: Vec::new(),
+            recovered: false,
+            recovery_gap_sec: 0,
+            merged_remote_ids: Vec::new(),
+        });
+        report.pulled += 1;
+    }
+
+    local.sort_by(|a, b| b.started_at.cmp(&a.started_at).then_with(|| a.id.cmp(&b.id)));
+    (local, report)
+}
+
+/// Merge records imported from a file into local history. A record is a duplicate if its id
+/// is already known or a local record has the same `started_at` and `ended_at` (±1s).
+/// Added records lose `remote_id` and `share_code`: those point at the exporting machine's
+/// cloud rows, so the next sync uploads them as new sessions. Result is sorted newest first.
+pub fn merge_imported(
+    mut local: Vec<SessionHistoryRecord>,
+    imported: Vec<SessionHistoryRecord>,
+) -> (Vec<SessionHistoryRecord>, HistoryImportReport) {
+    let mut report = HistoryImportReport { total: imported.len(), ..Default::default() };
+    let tolerance = Duration::seconds(LINK_TOLERANCE_SEC);
+    for record in imported {
+        let duplicate = local.iter().any(|r| {
+            r.id == record.id
+                || ((r.started_at - record.started_at).abs() <= tolerance
+                    && (r.ended_at - record.ended_at).abs() <= tolerance)
+        });
+        if duplicate {
+            report.skipped += 1;
+        } else {
+            local.push(SessionHistoryRecord { remote_id: None, share_code: None, ..record });
+            report.added += 1;
+        }
+    }
+    local.sort_by(|a, b| b.started_at.cmp(&a.started_at).then_with(|| a.id.cmp(&b.id)));
+    (local, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn local(id: &str, start_sec: i64, remote_id: Option<&str>) -> SessionHistoryRecord {
+        let started_at = Utc.timestamp_opt(start_sec, 0).unwrap();
+        SessionHistoryRecord {
+            id: id.to_string(),
+            started_at,
+            ended_at: started_at + Duration::seconds(600),
+            maps_completed: 3,
+            total_duration_sec: 600,
+            total_profit: 100.0,
+            total_expenses: 10.0,
+            total_income: 110.0,
+            total_fee: 0.0,
+            remote_id: remote_id.map(str::to_string),
+            drops: HashMap::new(),
+            drop_tiers: HashMap::new(),
+            share_code: None,
+            preset_id: None,
+            raw_currency_start: None,
+            raw_currency_end: None,
+            raw_currency_gained: None,
+            manual_realized: 0.0,
+            manual_pending: 0.0,
+            manual_pending_quoted: Vec::new(),
+            recovered: false,
+            recovery_gap_sec: 0,
+            merged_remote_ids: Vec::new(),
+        }
+    }
+
+    fn remote(id: &str, start_sec: i64) -> SessionHistoryItem {
+        SessionHistoryItem {
+            id: id.to_string(),
+            started_at: Utc.timestamp_opt(start_sec, 0).unwrap(),
+            ended_at: None,
+            maps_completed: 3,
+            total_duration_sec: 600,
+            total_profit_calculated: Some(100.0),
+            expenses_calculated: Some(10.0),
+            fee_calculated: Some(5.0),
+            preset_id: None,
+        }
+    }
+
+    #[test]
+    fn merge_links_pulls_and_dedups() {
+        let locals = vec![
+            local("a", 1_000, Some("r1")),
+            local("b", 1_000, Some("r1")),
+            local("c", 5_000, None),
+        ];
+        let remotes = vec![remote("r1", 1_000), remote("r2", 5_000), remote("r3", 9_000)];
+        let mut drops = HashMap::new();
+        drops.insert("r3".to_string(), HashMap::from([(100300_i64, 5)]));
+
+        assert_eq!(unknown_remote_ids(&locals, &remotes), vec!["r2".to_string(), "r3".to_string()]);
+
+        let (merged, report) = merge(locals, &remotes, &drops);
+        assert_eq!(report.pulled, 1);
+        assert_eq!(report.linked, 1);
+        assert_eq!(report.conflicted, 1);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0].remote_id.as_deref(), Some("r3"));
+        assert_eq!(merged[0].drops.get(&100300), Some(&5));
+        // Доход восстанавливается вместе с комиссией: прибыль с сервера уже чистая
+        assert_eq!((merged[0].total_income, merged[0].total_fee), (115.0, 5.0));
+        assert_eq!(merged[1].id, "c");
+        assert_eq!(merged[1].remote_id.as_deref(), Some("r2"));
+        assert_eq!(merged[2].id, "a");
+    }
+
+    #[test]
+    fn merged_sessions_are_not_pulled_back() {
+        let locals = [local("a", 1_000, Some("r1")), local("b", 2_000, Some("r2")), local("c", 5_000, None)];
+        let mut merged = crate::persistence::merge_history_records(&locals[..2]).unwrap();
+        assert_eq!(merged.merged_remote_ids, vec!["r1".to_string(), "r2".to_string()]);
+        // Следующий sync выгрузил объединённую запись как новую сессию
+        merged.remote_id = Some("r4".to_string());
+        let locals = vec![merged, locals[2].clone()];
+        let remotes = vec![remote("r1", 1_000), remote("r2", 2_000), remote("r4", 1_000), remote("r3", 9_000)];
+
+        assert_eq!(unknown_remote_ids(&locals, &remotes), vec!["r3".to_string()]);
+        let (synced, report) = merge(locals, &remotes, &HashMap::new());
+        assert_eq!((report.pulled, report.linked), (1, 0));
+        assert_eq!(synced.len(), 3);
+        assert_eq!(
+            synced.iter().map(|r| r.remote_id.as_deref()).collect::<Vec<_>>(),
+            vec![Some("r3"), None, Some("r4")]
+        );
+    }
+
+    #[test]
+    fn archived_league_sessions_are_not_pulled() {
+        let remotes = vec![remote("old", 1_000), remote("new", 9_000)];
+        let archived_at = Utc.timestamp_opt(5_000, 0).unwrap();
+
+        let kept = skip_archived(remotes.clone(), Some(archived_at));
+        assert_eq!(kept.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), vec!["new"]);
+        assert_eq!(skip_archived(remotes, None).len(), 2);
+    }
+
+    #[test]
+    fn import_skips_known_ids_and_same_times() {
+        let locals = vec![local("a", 1_000, None), local("b", 5_000, None)];
+        let mut shared = local("y", 9_000, Some("r-y"));
+        shared.share_code = Some("code".to_string());
+        let imported = vec![local("a", 2_000, None), local("x", 5_000, None), shared];
+
+        let (merged, report) = merge_imported(locals, imported);
+        assert_eq!((report.total, report.added, report.skipped), (3, 1, 2));
+        assert_eq!(merged.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), vec!["y", "b", "a"]);
+        assert_eq!((merged[0].remote_id.as_deref(), merged[0].share_code.as_deref()), (None, None));
+    }
+}
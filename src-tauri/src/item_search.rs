@@ -0,0 +1,169 @@
+//! Индекс поиска предметов
+//!
+//! Строится один раз при загрузке кэша предметов: имена (основное и локализованные)
+//! приводятся к нижнему регистру заранее, а для запросов от трёх символов есть
+//! триграммный индекс — кандидаты берутся из самого короткого списка и проверяются
+//! полным вхождением. Порядок результатов детерминированный: точное совпадение,
+//! начало имени, начало слова, вхождение; дальше более короткое имя и меньший game_id.
+
+use std::collections::HashMap;
+
+use crate::types::ItemInfo;
+
+/// Предмет в индексе: game_id и все его имена в нижнем регистре
+#[derive(Debug, Clone)]
+struct IndexedItem {
+    game_id: i64,
+    keys: Vec<String>,
+}
+
+/// Насколько хорошо имя совпало с запросом (меньше — лучше)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchRank {
+    Exact,
+    Prefix,
+    WordPrefix,
+    Contains,
+}
+
+/// Индекс для поиска по именам предметов
+#[derive(Debug, Default)]
+pub struct ItemSearchIndex {
+    /// Отсортированы по game_id (порядок для пустого запроса)
+    items: Vec<IndexedItem>,
+    /// Триграмма -> индексы в items (по возрастанию, без повторов)
+    trigrams: HashMap<[char; 3], Vec<u32>>,
+}
+
+/// Триграммы строки (по символам, не байтам — важно для кириллицы и иероглифов)
+fn trigrams(s: &str) -> Vec<[char; 3]> {
+    let chars: Vec<char> = s.chars().collect();
+    chars.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+/// Ранг совпадения имени с запросом (None — не совпало)
+fn match_rank(key: &str, query: &str) -> Option<MatchRank> {
+    if key == query {
+        return Some(MatchRank::Exact);
+    }
+    let pos = key.find(query)?;
+    if pos == 0 {
+        return Some(MatchRank::Prefix);
+    }
+    // Начало слова: вхождение после пробела/дефиса (ищем среди всех вхождений)
+    let word_start = key.match_indices(query).any(|(i, _)| {
+        key[..i].chars().next_back().map(|c| c == ' ' || c == '-').unwrap_or(false)
+    });
+    Some(if word_start { MatchRank::WordPrefix } else { MatchRank::Contains })
+}
+
+impl ItemSearchIndex {
+    /// Построить индекс по кэшу предметов
+    pub fn build<'a>(items: impl IntoIterator<Item = &'a ItemInfo>) -> Self {
+        let mut indexed: Vec<IndexedItem> = items
+            .into_iter()
+            .map(|item| {
+                let mut keys = vec![item.name.to_lowercase()];
+                for name in [&item.name_en, &item.name_ru, &item.name_cn].into_iter().flatten() {
+                    let key = name.to_lowercase();
+                    if !keys.contains(&key) {
+                        keys.push(key);
+                    }
+                }
+                IndexedItem { game_id: item.game_id, keys }
+            })
+            .collect();
+        indexed.sort_by_key(|i| i.game_id);
+
+        let mut trigrams_index: HashMap<[char; 3], Vec<u32>> = HashMap::new();
+        for (idx, item) in indexed.iter().enumerate() {
+            for key in &item.keys {
+                for tri in trigrams(key) {
+                    let postings = trigrams_index.entry(tri).or_default();
+                    // Индексы идут по возрастанию, повтор возможен только подряд
+                    if postings.last() != Some(&(idx as u32)) {
+                        postings.push(idx as u32);
+                    }
+                }
+            }
+        }
+
+        Self { items: indexed, trigrams: trigrams_index }
+    }
+
+    /// Найти предметы по подстроке имени (любой язык). Пустой запрос — первые `limit` по game_id
+    pub fn search(&self, query: &str, limit: usize) -> Vec<i64> {
+        let q = query.trim().to_lowercase();
+        if q.is_empty() {
+            return self.items.iter().take(limit).map(|i| i.game_id).collect();
+        }
+
+        let query_trigrams = trigrams(&q);
+        let candidates: Box<dyn Iterator<Item = &IndexedItem>> = if query_trigrams.is_empty() {
+            // Короткий запрос: перебор по готовым ключам (без to_lowercase на каждый ввод)
+            Box::new(self.items.iter())
+        } else {
+            // Самый короткий список триграммы; отсутствующая триграмма — результатов нет
+            let mut shortest: Option<&Vec<u32>> = None;
+            for tri in &query_trigrams {
+                match self.trigrams.get(tri) {
+                    Some(list) if shortest.map(|s| list.len() < s.len()).unwrap_or(true) => shortest = Some(list),
+                    Some(_) => {}
+                    None => return Vec::new(),
+                }
+            }
+            match shortest {
+                Some(list) => Box::new(list.iter().map(|&idx| &self.items[idx as usize])),
+                None => return Vec::new(),
+            }
+        };
+
+        let mut ranked: Vec<(MatchRank, usize, i64)> = candidates
+            .filter_map(|item| {
+                item.keys
+                    .iter()
+                    .filter_map(|key| match_rank(key, &q).map(|rank| (rank, key.chars().count())))
+                    .min()
+                    .map(|(rank, len)| (rank, len, item.game_id))
+            })
+            .collect();
+        ranked.sort();
+        ranked.into_iter().take(limit).map(|(_, _, game_id)| game_id).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(game_id: i64, name: &str, name_ru: Option<&str>) -> ItemInfo {
+        ItemInfo {
+            game_id,
+            name: name.to_string(),
+            name_en: Some(name.to_string()),
+            name_ru: name_ru.map(str::to_string),
+            category: "currency".to_string(),
+            ..ItemInfo::default()
+        }
+    }
+
+    #[test]
+    fn ranks_exact_prefix_word_and_contains() {
+        let items = vec![
+            item(1, "Flame Elementium", Some("Элементий пламени")),
+            item(2, "Flame Sand", None),
+            item(3, "Elixir of Flame", None),
+            item(4, "Flameless Ember", None),
+            item(5, "Sand", None),
+        ];
+        let index = ItemSearchIndex::build(&items);
+
+        assert_eq!(index.search("flame", 10), vec![2, 4, 1, 3]);
+        assert_eq!(index.search("sand", 10), vec![5, 2]);
+        assert_eq!(index.search("ПЛАМЕН", 10), vec![1]);
+        assert!(index.search("xyz", 10).is_empty());
+        // Короткий запрос без триграмм
+        assert_eq!(index.search("fl", 2), vec![2, 4]);
+        assert_eq!(index.search("", 3), vec![1, 2, 3]);
+    }
+}
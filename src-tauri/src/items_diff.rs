@@ -0,0 +1,112 @@
+//! Отчёт об изменениях списка предметов
+//!
+//! После патча игры в Supabase появляются новые предметы, а старые иногда
+//! переименовывают или переносят в другую категорию. `refresh_items_cache`
+//! перечитывает список и показывает, что именно изменилось относительно кэша.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::types::ItemInfo;
+
+/// Предмет сменил имя (на любом из языков)
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemRename {
+    pub game_id: i64,
+    pub old_name: String,
+    pub new_name: String,
+}
+
+/// Предмет перенесён в другую категорию
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemCategoryChange {
+    pub game_id: i64,
+    pub name: String,
+    pub old_category: String,
+    pub new_category: String,
+}
+
+/// Что изменилось после обновления списка предметов
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ItemsDiff {
+    /// Предметов в свежем списке
+    pub total: usize,
+    /// Новые предметы (имена на языке отображения)
+    pub new_items: Vec<ItemInfo>,
+    pub renamed: Vec<ItemRename>,
+    pub category_changes: Vec<ItemCategoryChange>,
+}
+
+impl ItemsDiff {
+    pub fn is_empty(&self) -> bool {
+        self.new_items.is_empty() && self.renamed.is_empty() && self.category_changes.is_empty()
+    }
+}
+
+fn names(item: &ItemInfo) -> [Option<&str>; 4] {
+    [Some(item.name.as_str()), item.name_en.as_deref(), item.name_ru.as_deref(), item.name_cn.as_deref()]
+}
+
+/// Сравнить кэш со свежим списком. Предметы, которых в списке нет, не считаются
+/// удалёнными: кэш только дополняется (там же живут пользовательские предметы)
+pub fn diff(cache: &HashMap<i64, ItemInfo>, fresh: &[ItemInfo], language: &str) -> ItemsDiff {
+    let mut report = ItemsDiff { total: fresh.len(), ..Default::default() };
+    for item in fresh {
+        let Some(old) = cache.get(&item.game_id) else {
+            report.new_items.push(item.localized(language));
+            continue;
+        };
+        if names(old) != names(item) {
+            report.renamed.push(ItemRename {
+                game_id: item.game_id,
+                old_name: old.localized_name(language).to_string(),
+                new_name: item.localized_name(language).to_string(),
+            });
+        }
+        if old.category != item.category {
+            report.category_changes.push(ItemCategoryChange {
+                game_id: item.game_id,
+                name: item.localized_name(language).to_string(),
+                old_category: old.category.clone(),
+                new_category: item.category.clone(),
+            });
+        }
+    }
+    report.new_items.sort_by_key(|i| i.game_id);
+    report.renamed.sort_by_key(|r| r.game_id);
+    report.category_changes.sort_by_key(|c| c.game_id);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(game_id: i64, name: &str, category: &str) -> ItemInfo {
+        ItemInfo {
+            game_id,
+            name: name.to_string(),
+            name_en: Some(name.to_string()),
+            category: category.to_string(),
+            ..ItemInfo::default()
+        }
+    }
+
+    #[test]
+    fn reports_new_renamed_and_moved_items() {
+        let cache: HashMap<i64, ItemInfo> = [item(1, "Ember", "ember"), item(2, "Fossil", "fossil"), item(3, "Old", "misc")]
+            .into_iter()
+            .map(|i| (i.game_id, i))
+            .collect();
+        let fresh = vec![item(1, "Ember", "ember"), item(2, "Fossil", "compass"), item(3, "New", "misc"), item(4, "Shard", "ember")];
+
+        let report = diff(&cache, &fresh, "en");
+        assert_eq!(report.total, 4);
+        assert_eq!(report.new_items.iter().map(|i| i.game_id).collect::<Vec<_>>(), vec![4]);
+        assert_eq!(report.renamed.len(), 1);
+        assert_eq!((report.renamed[0].old_name.as_str(), report.renamed[0].new_name.as_str()), ("Old", "New"));
+        assert_eq!(report.category_changes[0].new_category, "compass");
+        assert!(diff(&cache, &fresh[..1], "en").is_empty());
+    }
+}
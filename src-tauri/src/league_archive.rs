@@ -0,0 +1,240 @@
+//! Архив прошлых лиг
+//!
+//! Когда начинается новая лига, история сессий, кэш цен, ручные цены, курсы валют,
+//! рекорды и журнал карт прошлой лиги переносятся в `archive/<лига>/` внутри папки
+//! данных, а текущие кэши начинаются с нуля. Шаблоны сессий копируются: ими пользуются
+//! и в новой лиге. Перенос всё-или-ничего: при ошибке уже перенесённое возвращается на
+//! место. Архив — только для чтения: его можно просматривать, но не менять.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::persistence::SessionHistoryRecord;
+use crate::types::PersonalRecords;
+
+/// Папка архива внутри папки данных приложения
+pub const ARCHIVE_DIR: &str = "archive";
+/// Описание архива лиги (лежит в её папке)
+const MANIFEST_FILE: &str = "league.json";
+/// Файлы, которые уезжают в архив (вместе с `.bak`)
+const MOVED_FILES: &[&str] = &[
+    "prices_cache.json",
+    "manual_prices.json",
+    "exchange_rates.json",
+    "records.json",
+    "map_log.json",
+];
+/// Файлы, которые копируются в архив и остаются на месте
+const COPIED_FILES: &[&str] = &["session_templates.json"];
+/// История сессий по пользователям: `sessions_<user_id>.json`
+const HISTORY_PREFIX: &str = "sessions_";
+
+/// Заархивированная лига
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedLeague {
+    /// Имя папки в архиве (идентификатор для команд)
+    pub id: String,
+    pub league: String,
+    pub archived_at: DateTime<Utc>,
+    /// Что попало в архив (имена файлов)
+    pub files: Vec<String>,
+}
+
+/// Лига текущих данных и архивы (для настроек)
+#[derive(Debug, Clone, Serialize)]
+pub struct LeagueStatus {
+    /// Лига, к которой относятся текущие история и цены
+    pub active_league: Option<String>,
+    /// Лига по свежим ценам
+    pub current_league: Option<String>,
+    /// Началась новая лига, данные прошлой ещё не в архиве
+    pub rollover_pending: bool,
+    pub archives: Vec<ArchivedLeague>,
+}
+
+/// Содержимое архива лиги для просмотра
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchivedLeagueView {
+    pub league: ArchivedLeague,
+    /// История сессий текущего пользователя (пусто без входа)
+    pub sessions: Vec<SessionHistoryRecord>,
+    pub records: PersonalRecords,
+}
+
+/// Имя папки для лиги: только буквы, цифры, `-` и `_`
+fn dir_name(league: &str) -> String {
+    let safe: String = league
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if safe.is_empty() { "league".to_string() } else { safe }
+}
+
+/// Перенести файл (rename, между дисками — копия и удаление)
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+/// Вернуть перенесённые файлы на место и убрать папку архива (после ошибки переноса)
+fn roll_back(moved: &[(PathBuf, PathBuf)], target: &Path) {
+    for (from, to) in moved.iter().rev() {
+        if let Err(e) = move_file(to, from) {
+            warn!("Failed to restore {} from the league archive: {}", from.display(), e);
+        }
+    }
+    if let Err(e) = fs::remove_dir_all(target) {
+        warn!("Failed to remove incomplete league archive {}: {}", target.display(), e);
+    }
+}
+
+/// Перенести данные лиги `league` из `data_dir` в архив
+pub fn archive_league(data_dir: &Path, league: &str, now: DateTime<Utc>) -> io::Result<ArchivedLeague> {
+    let root = data_dir.join(ARCHIVE_DIR);
+    let mut id = dir_name(league);
+    if root.join(&id).exists() {
+        // Лигу уже архивировали (например, откат и повторный старт) — новая папка рядом
+        id = format!("{}_{}", id, now.format("%Y%m%d%H%M%S"));
+    }
+    let target = root.join(&id);
+    fs::create_dir_all(&target)?;
+
+    let mut moved: Vec<String> = MOVED_FILES.iter().map(|f| f.to_string()).collect();
+    for entry in fs::read_dir(data_dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if name.starts_with(HISTORY_PREFIX) && name.ends_with(".json") {
+            moved.push(name);
+        }
+    }
+
+    let mut done: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let result = (|| -> io::Result<ArchivedLeague> {
+        let mut files = Vec::new();
+        for name in &moved {
+            for file in [name.clone(), format!("{}.bak", name)] {
+                let from = data_dir.join(&file);
+                if from.exists() {
+                    let to = target.join(&file);
+                    move_file(&from, &to)?;
+                    done.push((from, to));
+                    files.push(file);
+                }
+            }
+        }
+        for name in COPIED_FILES {
+            let from = data_dir.join(name);
+            if from.exists() {
+                fs::copy(&from, target.join(name))?;
+                files.push(name.to_string());
+            }
+        }
+
+        let archived = ArchivedLeague { id: id.clone(), league: league.to_string(), archived_at: now, files };
+        let json = serde_json::to_string_pretty(&archived)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(target.join(MANIFEST_FILE), json)?;
+        Ok(archived)
+    })();
+
+    match result {
+        Ok(archived) => {
+            info!("League '{}' archived to {} ({} files)", league, target.display(), archived.files.len());
+            Ok(archived)
+        }
+        Err(e) => {
+            warn!("Archiving league '{}' failed, restoring moved files: {}", league, e);
+            roll_back(&done, &target);
+            Err(e)
+        }
+    }
+}
+
+/// Заархивированные лиги, новые первыми
+pub fn list_archives(data_dir: &Path) -> Vec<ArchivedLeague> {
+    let Ok(entries) = fs::read_dir(data_dir.join(ARCHIVE_DIR)) else {
+        return Vec::new();
+    };
+    let mut leagues: Vec<ArchivedLeague> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let manifest = e.path().join(MANIFEST_FILE);
+            let data = fs::read_to_string(&manifest).ok()?;
+            serde_json::from_str(&data)
+                .map_err(|err| warn!("Invalid league archive manifest {}: {}", manifest.display(), err))
+                .ok()
+        })
+        .collect();
+    leagues.sort_by_key(|l| std::cmp::Reverse(l.archived_at));
+    leagues
+}
+
+/// Архив и его папка по id (только из списка — id не может увести за пределы архива)
+pub fn find_archive(data_dir: &Path, id: &str) -> Option<(ArchivedLeague, PathBuf)> {
+    list_archives(data_dir).into_iter().find(|l| l.id == id).map(|l| {
+        let path = data_dir.join(ARCHIVE_DIR).join(&l.id);
+        (l, path)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_league_files_and_copies_templates() {
+        let dir = std::env::temp_dir().join(format!("tli-archive-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "prices_cache.json",
+            "manual_prices.json",
+            "exchange_rates.json",
+            "records.json",
+            "sessions_abc.json",
+            "session_templates.json",
+            "settings.json",
+        ] {
+            fs::write(dir.join(name), "{}").unwrap();
+        }
+
+        let archived = archive_league(&dir, "SS7 Vorax", Utc::now()).unwrap();
+        assert_eq!(archived.id, "SS7_Vorax");
+        assert!(!dir.join("prices_cache.json").exists());
+        assert!(!dir.join("sessions_abc.json").exists());
+        assert!(!dir.join("manual_prices.json").exists());
+        assert!(dir.join("archive/SS7_Vorax/exchange_rates.json").exists());
+        assert!(dir.join("session_templates.json").exists());
+        assert!(dir.join("settings.json").exists());
+        assert!(dir.join("archive/SS7_Vorax/sessions_abc.json").exists());
+
+        let listed = list_archives(&dir);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(find_archive(&dir, "SS7_Vorax").map(|(_, path)| path), Some(dir.join("archive/SS7_Vorax")));
+        assert!(find_archive(&dir, "../..").is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn failed_archive_restores_moved_files() {
+        let dir = std::env::temp_dir().join(format!("tli-archive-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("prices_cache.json"), "{}").unwrap();
+        fs::write(dir.join("records.json"), "{}").unwrap();
+        // Папка на месте файла шаблонов: копирование падает после переноса
+        fs::create_dir_all(dir.join("session_templates.json")).unwrap();
+
+        assert!(archive_league(&dir, "SS7", Utc::now()).is_err());
+        assert!(dir.join("prices_cache.json").exists());
+        assert!(dir.join("records.json").exists());
+        assert!(!dir.join("archive/SS7").exists());
+        assert!(list_archives(&dir).is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
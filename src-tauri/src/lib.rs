@@ -4,15 +4,67 @@
 
 pub mod types;
 pub mod log_parser;
+pub mod log_patterns;
 pub mod file_watcher;
 pub mod state;
 pub mod commands;
 pub mod persistence;
+pub mod migrations;
+pub mod valuation;
+pub mod item_search;
+pub mod drop_dedup;
+pub mod event_journal;
+pub mod live_stats;
+pub mod obs;
+pub mod session_replay;
+pub mod twitch;
+pub mod unknown_items;
+pub mod controller_api;
+pub mod drop_announcer;
+pub mod drop_sounds;
+pub mod categories;
+pub mod items_diff;
+pub mod proxy;
+pub mod schema_handshake;
+pub mod league_archive;
+pub mod exchange_rates;
+pub mod bulk_entry;
+pub mod noise_filter;
+pub mod value_format;
+pub mod loot_split;
+pub mod party;
+pub mod live_share;
+pub mod cache_eviction;
+pub mod parser_bench;
+pub mod focus_watch;
+pub mod overlay_mode;
+pub mod panel_windows;
+pub mod drop_rates;
+pub mod gamble;
+pub mod crafting;
+pub mod summary_card;
+pub mod session_summary;
+pub mod google_sheets;
+pub mod xlsx_export;
+pub mod farm_schedule;
+pub mod game_process;
+pub mod records;
+pub mod companion_window;
+pub mod tray;
+pub mod updater;
+pub mod app_logs;
+pub mod diagnostics;
+pub mod daily_summary;
 pub mod supabase_sync;
+pub mod supabase_client;
+pub mod price_sync;
+pub mod price_confidence;
+pub mod history_sync;
 pub mod auth;
+pub mod token_manager;
 pub mod supabase_defaults;
 
 pub use types::*;
 pub use log_parser::LogParser;
-pub use file_watcher::{LogWatcher, find_log_path};
+pub use file_watcher::{LogWatcher, find_log_path_for};
 pub use state::AppState;
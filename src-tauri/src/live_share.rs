@@ -0,0 +1,208 @@
+//! Live-трансляция идущей сессии и режим наблюдателя
+//!
+//! Хозяин сессии сам включает трансляцию (start_live_share): в tli_shared_sessions
+//! создаётся строка, и раз в LIVE_SHARE_PUSH_SEC её payload заменяется свежим снимком.
+//! Код трансляции он передаёт напарнику, тот вызывает spectate_session(share_code) и раз
+//! в SPECTATE_POLL_SEC получает снимок событием spectate-updated — только чтение.
+//! Трансляция заканчивается вместе с сессией (строка удаляется), наблюдатель получает ended.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::state::AppState;
+use crate::supabase_client::SupabaseClient;
+use crate::supabase_sync::{self, SharedSessionSnapshot};
+
+/// Как часто обновлять снимок трансляции
+const LIVE_SHARE_PUSH_SEC: u64 = 30;
+
+/// Как часто наблюдатель запрашивает снимок
+const SPECTATE_POLL_SEC: u64 = 15;
+
+/// Сколько ждать удаления трансляции при завершении сессии или выходе
+const STOP_TIMEOUT_SEC: u64 = 3;
+
+/// Фоновая задача трансляции или наблюдения
+#[derive(Debug)]
+pub struct SharedTask {
+    pub share_code: String,
+    cancel: Arc<AtomicBool>,
+}
+
+impl SharedTask {
+    fn new(share_code: String) -> Self {
+        Self { share_code, cancel: Arc::new(AtomicBool::new(false)) }
+    }
+
+    fn stop(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Payload события spectate-updated
+#[derive(Debug, Clone, Serialize)]
+pub struct SpectateUpdate {
+    pub share_code: String,
+    pub snapshot: Option<SharedSessionSnapshot>,
+    /// Ошибка очередного запроса (наблюдение продолжается)
+    pub error: Option<String>,
+    /// Трансляция закончилась — опрос остановлен
+    pub ended: bool,
+}
+
+/// Клиент Supabase и токен вошедшего пользователя
+pub(crate) async fn authorized(state: &AppState) -> Result<(SupabaseClient, String), String> {
+    let api = state
+        .supabase_client()
+        .await
+        .ok_or_else(|| "Supabase config missing".to_string())?;
+    let jwt = state
+        .get_valid_access_token(&api)
+        .await
+        .ok_or_else(|| "Not logged in".to_string())?;
+    Ok((api, jwt))
+}
+
+/// Включить трансляцию текущей сессии. Возвращает код для напарника
+pub async fn start_publishing(state: Arc<AppState>) -> Result<String, String> {
+    if let Some(task) = state.live_share.read().await.as_ref() {
+        return Ok(task.share_code.clone());
+    }
+    let user_id = state.get_auth_user_id().await.ok_or_else(|| "Not logged in".to_string())?;
+    let snapshot = state.build_live_snapshot().await.ok_or_else(|| "No active session".to_string())?;
+    let (api, jwt) = authorized(&state).await?;
+    let share_code = supabase_sync::create_session_share(&api, &jwt, &user_id, &snapshot).await?;
+
+    let task = SharedTask::new(share_code.clone());
+    let cancel = task.cancel.clone();
+    *state.live_share.write().await = Some(task);
+    tauri::async_runtime::spawn(publish_loop(state.clone(), share_code.clone(), cancel));
+
+    info!("Live share started with code {}", share_code);
+    Ok(share_code)
+}
+
+/// Выключить трансляцию и удалить снимок с сервера
+pub async fn stop_publishing(state: &AppState) -> Result<bool, String> {
+    let Some(task) = state.live_share.write().await.take() else {
+        return Ok(false);
+    };
+    task.stop();
+    let (api, jwt) = authorized(state).await?;
+    supabase_sync::delete_session_share(&api, &jwt, &task.share_code).await?;
+    info!("Live share {} stopped", task.share_code);
+    Ok(true)
+}
+
+/// Снять трансляцию при завершении сессии или выходе из приложения — не ждём
+/// следующего тика цикла, иначе после выхода снимок так и остался бы «живым».
+/// Ошибки только логируются, ожидание ограничено `STOP_TIMEOUT_SEC`
+pub async fn stop_on_shutdown(state: &AppState) {
+    match tokio::time::timeout(Duration::from_secs(STOP_TIMEOUT_SEC), stop_publishing(state)).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => warn!("Failed to remove live share: {}", e),
+        Err(_) => warn!("Timed out removing live share"),
+    }
+}
+
+async fn publish_loop(state: Arc<AppState>, share_code: String, cancel: Arc<AtomicBool>) {
+    loop {
+        tokio::time::sleep(Duration::from_secs(LIVE_SHARE_PUSH_SEC)).await;
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let Some(snapshot) = state.build_live_snapshot().await else {
+            info!("Session ended, stopping live share {}", share_code);
+            if let Err(e) = stop_publishing(&state).await {
+                warn!("Failed to remove live share {}: {}", share_code, e);
+            }
+            break;
+        };
+        let (api, jwt) = match authorized(&state).await {
+            Ok(auth) => auth,
+            Err(e) => {
+                debug!("Live share update skipped: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = supabase_sync::update_session_share(&api, &jwt, &share_code, &snapshot).await {
+            warn!("Failed to update live share {}: {}", share_code, e);
+        }
+    }
+}
+
+/// Начать наблюдение за чужой трансляцией (предыдущее наблюдение прекращается).
+/// Возвращает текущий снимок
+pub async fn start_spectating(
+    app: AppHandle,
+    state: &AppState,
+    share_code: String,
+) -> Result<SharedSessionSnapshot, String> {
+    let share_code = share_code.trim().to_string();
+    let api = state
+        .supabase_client()
+        .await
+        .ok_or_else(|| "Supabase config missing".to_string())?;
+    let snapshot = supabase_sync::fetch_shared_session(&api, &share_code)
+        .await?
+        .ok_or_else(|| "Shared session not found".to_string())?;
+
+    let task = SharedTask::new(share_code.clone());
+    let cancel = task.cancel.clone();
+    if let Some(previous) = state.spectating.write().await.replace(task) {
+        previous.stop();
+    }
+    if snapshot.is_live {
+        tauri::async_runtime::spawn(spectate_loop(app, api, share_code.clone(), cancel));
+    }
+
+    info!("Spectating shared session {}", share_code);
+    Ok(snapshot)
+}
+
+/// Прекратить наблюдение
+pub async fn stop_spectating(state: &AppState) -> bool {
+    match state.spectating.write().await.take() {
+        Some(task) => {
+            task.stop();
+            true
+        }
+        None => false,
+    }
+}
+
+async fn spectate_loop(app: AppHandle, api: SupabaseClient, share_code: String, cancel: Arc<AtomicBool>) {
+    loop {
+        tokio::time::sleep(Duration::from_secs(SPECTATE_POLL_SEC)).await;
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let update = match supabase_sync::fetch_shared_session(&api, &share_code).await {
+            Ok(snapshot) => SpectateUpdate {
+                share_code: share_code.clone(),
+                ended: snapshot.as_ref().is_none_or(|s| !s.is_live),
+                snapshot,
+                error: None,
+            },
+            Err(e) => SpectateUpdate { share_code: share_code.clone(), snapshot: None, error: Some(e.to_string()), ended: false },
+        };
+        let ended = update.ended;
+        if let Err(e) = app.emit("spectate-updated", &update) {
+            debug!("Failed to emit spectate-updated: {}", e);
+        }
+        if ended {
+            info!("Shared session {} ended", share_code);
+            let state = app.state::<Arc<AppState>>();
+            let mut spectating = state.spectating.write().await;
+            if spectating.as_ref().is_some_and(|t| t.share_code == share_code) {
+                *spectating = None;
+            }
+            break;
+        }
+    }
+}
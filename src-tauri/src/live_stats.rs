@@ -0,0 +1,174 @@
+//! Файл live_stats.json для сторонних инструментов
+//!
+//! Пока опция включена, раз в `interval_secs` в файл пишется короткий снимок:
+//! статистика сессии (как у локального API контроллеров) и самые ценные дропы.
+//! Таблицы, текстовые источники OBS и скрипты читают файл без сетевой интеграции.
+//! Запись атомарная (`persistence::atomic_write_no_backup`) — читатель не увидит половину JSON.
+//! Свой путь — только файл `.json` и не внутри папки данных приложения (кроме файла по умолчанию),
+//! чтобы опечатка в настройках не перезаписала settings.json или сессию.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use serde::Serialize;
+
+use crate::controller_api::ControllerStats;
+use crate::state::AppState;
+use crate::types::{AggregatedDrop, LiveStatsSettings};
+
+const LIVE_STATS_FILE: &str = "live_stats.json";
+
+/// Строка топа дропов
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LiveDrop {
+    pub game_id: i64,
+    pub name: String,
+    pub quantity: i32,
+    pub value: f64,
+}
+
+/// Содержимое файла
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveStatsSnapshot {
+    pub updated_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub stats: ControllerStats,
+    pub top_drops: Vec<LiveDrop>,
+}
+
+/// Путь из настроек, иначе live_stats.json в папке данных приложения
+pub fn resolve_path(settings: &LiveStatsSettings) -> Option<PathBuf> {
+    match settings.path.as_deref().map(str::trim) {
+        Some(path) if !path.is_empty() => Some(PathBuf::from(path)),
+        _ => crate::persistence::app_data_dir().map(|d| d.join(LIVE_STATS_FILE)),
+    }
+}
+
+/// Проверить путь для записи: расширение `.json`, без `..`, внутри папки данных — только
+/// файл по умолчанию
+fn check_path(path: &Path, data_dir: Option<&Path>) -> Result<(), String> {
+    let is_json = path.extension().map(|e| e.eq_ignore_ascii_case("json")).unwrap_or(false);
+    if !is_json {
+        return Err(format!("live_stats path must be a .json file: {}", path.display()));
+    }
+    if path.components().any(|c| c == Component::ParentDir) {
+        return Err(format!("live_stats path must not contain '..': {}", path.display()));
+    }
+    if let Some(dir) = data_dir {
+        if path.starts_with(dir) && path != dir.join(LIVE_STATS_FILE) {
+            return Err(format!("live_stats path must not be inside the app data folder: {}", path.display()));
+        }
+    }
+    Ok(())
+}
+
+/// Путь из настроек после проверки (команда сохранения настроек и запись снимка)
+pub fn validated_path(settings: &LiveStatsSettings) -> Result<PathBuf, String> {
+    let path = resolve_path(settings).ok_or("No data directory for live_stats.json")?;
+    check_path(&path, crate::persistence::app_data_dir().as_deref())?;
+    Ok(path)
+}
+
+/// Самые ценные строки дропа (строки одного предмета с разными тирами — вместе)
+pub fn top_drops(drops: &[AggregatedDrop], limit: usize, language: &str) -> Vec<LiveDrop> {
+    let mut top: Vec<LiveDrop> = Vec::new();
+    for drop in drops {
+        if let Some(existing) = top.iter_mut().find(|d| d.game_id == drop.game_id) {
+            existing.quantity += drop.quantity;
+            existing.value += drop.total_value;
+            continue;
+        }
+        let name = drop
+            .item_info
+            .as_ref()
+            .map(|info| info.localized_name(language).to_string())
+            .unwrap_or_else(|| drop.game_id.to_string());
+        top.push(LiveDrop { game_id: drop.game_id, name, quantity: drop.quantity, value: drop.total_value });
+    }
+    top.retain(|d| d.value > 0.0);
+    top.sort_by(|a, b| b.value.total_cmp(&a.value));
+    top.truncate(limit);
+    top
+}
+
+pub async fn snapshot(state: &AppState, settings: &LiveStatsSettings) -> LiveStatsSnapshot {
+    let language = state.settings.read().await.language.clone();
+    let drops = state.get_aggregated_drops().await;
+    LiveStatsSnapshot {
+        updated_at: Utc::now(),
+        stats: crate::controller_api::controller_stats(state).await,
+        top_drops: top_drops(&drops, settings.top_drops, &language),
+    }
+}
+
+/// Записать снимок сейчас (команда и фоновый цикл)
+pub async fn write_snapshot(state: &AppState, settings: &LiveStatsSettings) -> Result<PathBuf, String> {
+    let path = validated_path(settings)?;
+    let json = serde_json::to_string_pretty(&snapshot(state, settings).await).map_err(|e| e.to_string())?;
+    crate::persistence::atomic_write_no_backup(&path, &json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Цикл записи (до выхода из приложения); настройки перечитываются на каждом шаге
+pub async fn run(state: Arc<AppState>) {
+    let mut last_error: Option<String> = None;
+    loop {
+        let settings = state.settings.read().await.live_stats.clone();
+        tokio::time::sleep(Duration::from_secs(settings.interval_secs.max(1))).await;
+        if !settings.enabled {
+            continue;
+        }
+        match write_snapshot(&state, &settings).await {
+            Ok(path) => {
+                if last_error.take().is_some() {
+                    debug!("live_stats.json is written again to {}", path.display());
+                }
+            }
+            // Одна и та же ошибка (нет прав на папку) — в лог один раз
+            Err(e) => {
+                if last_error.as_ref() != Some(&e) {
+                    warn!("{}", e);
+                    last_error = Some(e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(game_id: i64, tier: Option<i32>, quantity: i32, total_value: f64) -> AggregatedDrop {
+        AggregatedDrop { game_id, tier, quantity, total_value, ..AggregatedDrop::default() }
+    }
+
+    #[test]
+    fn merges_tiers_and_keeps_most_valuable() {
+        let drops = vec![
+            line(1, None, 10, 100.0),
+            line(2, Some(1), 1, 300.0),
+            line(2, Some(2), 2, 50.0),
+            line(3, None, 5, 0.0),
+            line(4, None, 1, 500.0),
+        ];
+        let top = top_drops(&drops, 2, "en");
+        assert_eq!(top.iter().map(|d| d.game_id).collect::<Vec<_>>(), vec![4, 2]);
+        assert_eq!((top[1].quantity, top[1].value), (3, 350.0));
+        assert_eq!(top[0].name, "4");
+        assert!(top_drops(&drops, 10, "en").iter().all(|d| d.game_id != 3));
+    }
+
+    #[test]
+    fn rejects_paths_that_could_clobber_app_files() {
+        let data = Path::new("/data/tli-companion");
+        assert!(check_path(&data.join(LIVE_STATS_FILE), Some(data)).is_ok());
+        assert!(check_path(Path::new("/obs/stats.JSON"), Some(data)).is_ok());
+        assert!(check_path(&data.join("settings.json"), Some(data)).is_err());
+        assert!(check_path(Path::new("/obs/stats.txt"), Some(data)).is_err());
+        assert!(check_path(Path::new("/obs/../data/tli-companion/session.json"), Some(data)).is_err());
+    }
+}
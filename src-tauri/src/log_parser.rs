@@ -4,39 +4,106 @@
 //! - Подбор предметов (PickItems)
 //! - Оценка цен на аукционе (XchgSearchPrice)
 //! - Смена карты (MapChange)
+//! - Снимок инвентаря при входе в мир (InitBagData)
 
-use regex::Regex;
-use chrono::{DateTime, NaiveDateTime, Utc, TimeZone};
-use crate::types::{ItemDropEvent, PriceSearchEvent, MapChangeEvent, MapEventType, LogEvent};
-use std::collections::HashMap;
-use log::{debug, trace};
+use chrono::{DateTime, FixedOffset, Local, LocalResult, NaiveDateTime, Offset, Utc, TimeZone};
+use crate::log_patterns::{CompiledPatterns, LogPatterns};
+use crate::types::{
+    InventorySlot, InventorySnapshotEvent, ItemConsumedEvent, ItemDropEvent, ItemGainedEvent, LogEvent, LogTimeZone,
+    MapChangeEvent, MapEventType, PriceSearchEvent, ZoneKind, LOG_UTC_OFFSET_MINUTES, PRIMARY_LOG_SOURCE,
+};
+use std::collections::{HashMap, VecDeque};
+use log::{debug, trace, warn};
+use serde::Serialize;
+
+/// Сколько последних нераспознанных строк хранить (для диагностики)
+const RECENT_UNPARSED_LIMIT: usize = 200;
+/// Строки длиннее обрезаются, чтобы буфер не разрастался
+const UNPARSED_LINE_MAX_LEN: usize = 300;
+/// Сколько примеров нераспознанных строк хранить
+const UNKNOWN_SAMPLES_LIMIT: usize = 20;
+/// Не чаще одного примера на паттерн за этот интервал (сломанный regex не должен забить буфер)
+const UNKNOWN_SAMPLE_INTERVAL_SEC: i64 = 60;
+
+/// Счётчики работы парсера
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ParserStats {
+    /// Всего обработано строк
+    pub lines_parsed: u64,
+    /// Строк, давших событие
+    pub events_emitted: u64,
+    /// Строк с известным маркером (BagMgr/XchgSearchPrice/_UpdateGameEnd), не совпавших с regex —
+    /// признак того, что формат лога поменялся
+    pub unknown_formats: u64,
+    /// Событий по типам
+    pub item_drops: u64,
+    pub price_searches: u64,
+    pub map_changes: u64,
+    /// Строк BagMgr внутри блока PickItems (должны давать дропы)
+    pub bag_lines_in_pick: u64,
+    /// Когда в последний раз распознан дроп / смена карты (по часам приложения)
+    pub last_item_drop_at: Option<DateTime<Utc>>,
+    pub last_map_change_at: Option<DateTime<Utc>>,
+    /// Примеры строк с известным маркером, не совпавших с regex (по паттернам, с rate limit)
+    pub unknown_samples: Vec<UnknownLineSample>,
+    /// Ревизия действующего набора паттернов
+    pub patterns_revision: u32,
+}
+
+/// Пример нераспознанной строки
+#[derive(Debug, Clone, Serialize)]
+pub struct UnknownLineSample {
+    /// Какой regex должен был совпасть: bag_modify / bag_init / price_message / map_change
+    pub pattern: String,
+    pub line: String,
+    pub seen_at: DateTime<Utc>,
+}
+
+/// Обрезать строку до max_len байт по границе символа
+fn truncate_line(line: &str, max_len: usize) -> &str {
+    let mut end = line.len().min(max_len);
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+/// Слот из строки InitBagData (группы: page, slot, game_id, num)
+fn parse_inventory_slot(caps: &regex::Captures) -> Option<InventorySlot> {
+    Some(InventorySlot {
+        page_id: caps.get(1)?.as_str().parse().ok()?,
+        slot_id: caps.get(2)?.as_str().parse().ok()?,
+        game_id: caps.get(3)?.as_str().parse().ok()?,
+        quantity: caps.get(4)?.as_str().parse().ok()?,
+    })
+}
+
+/// Местное время лога -> UTC в поясе `tz`.
+/// Час перехода с летнего времени повторяется: из двух вариантов берём тот, что не
+/// раньше предыдущей метки (`after`), иначе длительность карты скачет на час.
+/// Несуществующее время (перевод вперёд) сдвигаем смещением до перехода
+fn log_time_to_utc<Tz: TimeZone>(tz: &Tz, naive: &NaiveDateTime, after: Option<DateTime<Utc>>) -> DateTime<Utc> {
+    match tz.from_local_datetime(naive) {
+        LocalResult::Single(t) => t.with_timezone(&Utc),
+        LocalResult::Ambiguous(early, late) => {
+            let (early, late) = (early.with_timezone(&Utc), late.with_timezone(&Utc));
+            match after {
+                Some(after) if early < after => late,
+                _ => early,
+            }
+        }
+        LocalResult::None => {
+            // Смещение до перехода: сутки назад перехода ещё не было (чаще раза в сутки пояса не переводят)
+            let offset = tz.offset_from_utc_datetime(&(*naive - chrono::Duration::days(1))).fix();
+            Utc.from_utc_datetime(&(*naive - chrono::Duration::seconds(offset.local_minus_utc() as i64)))
+        }
+    }
+}
 
 /// Парсер логов TLI
 pub struct LogParser {
-    // Regex для временной метки
-    timestamp_re: Regex,
-    // Regex для начала блока подбора
-    pick_start_re: Regex,
-    // Regex для конца блока подбора
-    pick_end_re: Regex,
-    // Regex для изменения инвентаря
-    bag_modify_re: Regex,
-    // Regex для отправки запроса цены
-    price_send_re: Regex,
-    // Regex для получения ответа цены
-    price_recv_re: Regex,
-    // Regex для ID предмета в запросе цены
-    price_refer_re: Regex,
-    // Regex для цены в ответе
-    price_unit_re: Regex,
-    // Regex для дополнительных unitPrices строк в ответе (вложенные +N [price])
-    price_unit_cont_re: Regex,
-    // Regex для валюты в ответе
-    price_currency_re: Regex,
-    // Regex для смены карты
-    map_change_re: Regex,
-    // Regex для убежища
-    hideout_re: Regex,
+    /// Скомпилированные regex (встроенный набор или override, см. log_patterns)
+    patterns: CompiledPatterns,
     
     // Состояние парсера
     /// Текущее количество предметов по слотам (для вычисления дельты)
@@ -49,65 +116,70 @@ pub struct LogParser {
     pending_price_requests: HashMap<i32, i64>,
     /// Последний sync_id из SendMessage (для связывания с refer)
     last_price_sync_id: Option<i32>,
+    /// Собираемый снимок инвентаря (идёт пачка InitBagData)
+    inventory_burst: Option<InventorySnapshotEvent>,
+    /// Готовый снимок, ещё не забранный watcher'ом
+    completed_snapshot: Option<InventorySnapshotEvent>,
+    /// Содержимое слотов (game_id, количество) по всем строкам BagMgr, а не только подборам —
+    /// чтобы знать, сколько валюты на руках
+    holdings: HashMap<(i32, i32), (i64, i32)>,
+    /// После полного снимка инвентаря неизвестный слот считается пустым
+    holdings_complete: bool,
+    /// Источник (клиент игры), которым помечаются события
+    source_id: String,
+    /// Счётчики распознанных/нераспознанных строк
+    stats: ParserStats,
+    /// Последние строки, не давшие события
+    recent_unparsed: VecDeque<String>,
+    /// Когда последний раз сохранялся пример по каждому паттерну (rate limit)
+    last_unknown_sample: HashMap<&'static str, DateTime<Utc>>,
+    /// Пояс меток времени лога (все события переводятся в UTC)
+    time_zone: LogTimeZone,
+    /// Смещение для LogTimeZone::Fixed (минуты)
+    utc_offset_minutes: i32,
+    /// Последняя переведённая метка — для выбора варианта в повторяющемся часе
+    last_timestamp: Option<DateTime<Utc>>,
 }
 
 impl LogParser {
     /// Создать новый парсер
     pub fn new() -> Self {
+        let patterns = LogPatterns::bundled()
+            .compile()
+            .expect("bundled log patterns must compile");
+        let stats = ParserStats { patterns_revision: patterns.revision, ..Default::default() };
         Self {
-            // Временная метка: [2026.01.12-11.34.07:799]
-            timestamp_re: Regex::new(r"\[(\d{4})\.(\d{2})\.(\d{2})-(\d{2})\.(\d{2})\.(\d{2}):(\d{3})\]").unwrap(),
-            
-            // ItemChange@ ProtoName=PickItems start
-            pick_start_re: Regex::new(r"ItemChange@ ProtoName=PickItems start").unwrap(),
-            
-            // ItemChange@ ProtoName=PickItems end
-            pick_end_re: Regex::new(r"ItemChange@ ProtoName=PickItems end").unwrap(),
-            
-            // BagMgr@:Modfy BagItem PageId = 102 SlotId = 1 ConfigBaseId = 100200 Num = 904
-            bag_modify_re: Regex::new(
-                r"BagMgr@:Modfy BagItem PageId = (\d+) SlotId = (\d+) ConfigBaseId = (\d+) Num = (\d+)"
-            ).unwrap(),
-            
-            // ----Socket SendMessage STT----XchgSearchPrice----SynId = 4006
-            price_send_re: Regex::new(r"----Socket SendMessage STT----XchgSearchPrice----SynId = (\d+)").unwrap(),
-            
-            // ----Socket RecvMessage STT----XchgSearchPrice----SynId = 4006
-            price_recv_re: Regex::new(r"----Socket RecvMessage STT----XchgSearchPrice----SynId = (\d+)").unwrap(),
-            
-            // +refer [101010037_15] или |       | +refer [200029]
-            // Нам нужен только ведущий числовой game_id до '_' (если он есть)
-            price_refer_re: Regex::new(r"\+refer \[(\d+)").unwrap(),
-            
-            // +unitPrices+1 [1.0]
-            price_unit_re: Regex::new(r"\+unitPrices\+\d+ \[([\d.]+)\]").unwrap(),
-
-            // continuation: |      | |          +2 [1.0] (формат варьируется по отступам)
-            price_unit_cont_re: Regex::new(r"^\s*\|.*\+\d+ \[([\d.]+)\]").unwrap(),
-            
-            // +currency [100300]
-            price_currency_re: Regex::new(r"\+currency \[(\d+)\]").unwrap(),
-            
-            // PageApplyBase@ _UpdateGameEnd: ... NextSceneName = World'/Game/Art/Maps/...'
-            // caps[1] = /Game/Art/Maps/...
-            map_change_re: Regex::new(
-                r"PageApplyBase@\s*_UpdateGameEnd:.*NextSceneName\s*=\s*World'(/Game/Art/Maps[^']*)'"
-            ).unwrap(),
-            
-            // Убежище: XZ_YuJinZhiXiBiNanSuo200
-            hideout_re: Regex::new(r"XZ_YuJinZhiXiBiNanSuo200").unwrap(),
+            patterns,
             
             slot_quantities: HashMap::new(),
             initialized_slots: std::collections::HashSet::new(),
             in_pick_block: false,
             pending_price_requests: HashMap::new(),
             last_price_sync_id: None,
+            inventory_burst: None,
+            completed_snapshot: None,
+            holdings: HashMap::new(),
+            holdings_complete: false,
+            source_id: PRIMARY_LOG_SOURCE.to_string(),
+            stats,
+            recent_unparsed: VecDeque::with_capacity(RECENT_UNPARSED_LIMIT),
+            last_unknown_sample: HashMap::new(),
+            time_zone: LogTimeZone::Local,
+            utc_offset_minutes: 0,
+            last_timestamp: None,
         }
     }
+
+    /// Создать парсер для дополнительного клиента (события помечаются source_id)
+    pub fn for_source(source_id: &str) -> Self {
+        let mut parser = Self::new();
+        parser.source_id = source_id.to_string();
+        parser
+    }
     
-    /// Парсить временную метку из строки лога
+    /// Парсить временную метку из строки лога (состояние парсера не меняется)
     fn parse_timestamp(&self, line: &str) -> Option<DateTime<Utc>> {
-        let caps = self.timestamp_re.captures(line)?;
+        let caps = self.patterns.timestamp.captures(line)?;
         
         let year: i32 = caps.get(1)?.as_str().parse().ok()?;
         let month: u32 = caps.get(2)?.as_str().parse().ok()?;
@@ -122,20 +194,198 @@ impl LogParser {
             chrono::NaiveTime::from_hms_milli_opt(hour, min, sec, millis)?
         );
         
-        Some(Utc.from_utc_datetime(&naive))
+        self.local_to_utc(&naive, self.last_timestamp)
+    }
+
+    /// Метка разбираемой строки: запоминается для выбора варианта в повторяющемся часе.
+    /// Строка без метки получает текущее время
+    fn consume_timestamp(&mut self, line: &str) -> DateTime<Utc> {
+        let ts = self.parse_timestamp(line);
+        if ts.is_some() {
+            self.last_timestamp = ts;
+        }
+        ts.unwrap_or_else(Utc::now)
+    }
+
+    /// Местное время лога -> UTC по настроенному поясу (None — неверное смещение)
+    fn local_to_utc(&self, naive: &NaiveDateTime, after: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+        Some(match self.time_zone {
+            LogTimeZone::Local => log_time_to_utc(&Local, naive, after),
+            LogTimeZone::Utc => Utc.from_utc_datetime(naive),
+            LogTimeZone::Fixed => {
+                let offset = FixedOffset::east_opt(self.utc_offset_minutes * 60)?;
+                log_time_to_utc(&offset, naive, after)
+            }
+        })
+    }
+
+    /// Метка из сессии, сохранённой до поддержки поясов (местное время лога, прочитанное
+    /// как UTC), в настоящий UTC. Пересчитывается один раз при восстановлении сессии
+    pub fn legacy_to_utc(&self, legacy: DateTime<Utc>) -> DateTime<Utc> {
+        self.local_to_utc(&legacy.naive_utc(), None).unwrap_or(legacy)
+    }
+
+    /// Сменить пояс меток времени лога (настройки)
+    pub fn set_time_zone(&mut self, time_zone: LogTimeZone, utc_offset_minutes: i32) {
+        // Настройки с диска могли поправить руками: вне диапазона FixedOffset меток бы не было
+        let utc_offset_minutes = utc_offset_minutes.clamp(*LOG_UTC_OFFSET_MINUTES.start(), *LOG_UTC_OFFSET_MINUTES.end());
+        if (self.time_zone, self.utc_offset_minutes) != (time_zone, utc_offset_minutes) {
+            debug!("Log time zone: {:?} (offset {} min)", time_zone, utc_offset_minutes);
+        }
+        self.time_zone = time_zone;
+        self.utc_offset_minutes = utc_offset_minutes;
+        self.last_timestamp = None;
+    }
+
+    /// Тир и редкость экипировки из строки подбора (у расходников этих полей нет)
+    fn parse_tier(&self, line: &str) -> (Option<i32>, Option<i32>) {
+        let Some(caps) = self.patterns.item_tier.captures(line) else {
+            return (None, None);
+        };
+        let field = |name: &str| caps.name(name).and_then(|m| m.as_str().parse().ok());
+        (field("tier"), field("rarity"))
+    }
+    
+    /// Имя предмета, если строка подбора его содержит
+    fn parse_item_name(&self, line: &str) -> Option<String> {
+        let name = self.patterns.item_name.captures(line)?.name("name")?.as_str().trim();
+        (!name.is_empty()).then(|| name.to_string())
     }
     
     /// Парсить одну строку лога
     /// Возвращает Option<LogEvent> если строка содержит интересное событие
     pub fn parse_line(&mut self, line: &str) -> Option<LogEvent> {
+        self.stats.lines_parsed += 1;
+        if self.track_bag_init(line) {
+            return None;
+        }
+        let holding_change = self.track_holdings(line);
+        if self.in_pick_block && is_bag_modify_line(line) {
+            self.stats.bag_lines_in_pick += 1;
+        }
+        let event = self.parse_line_inner(line).or(holding_change);
+        match &event {
+            Some(event) => self.record_event(event),
+            None => self.record_unparsed(line),
+        }
+        event
+    }
+
+    fn record_event(&mut self, event: &LogEvent) {
+        self.stats.events_emitted += 1;
+        let now = Utc::now();
+        match event {
+            LogEvent::ItemDrop(_) => {
+                self.stats.item_drops += 1;
+                self.stats.last_item_drop_at = Some(now);
+            }
+            LogEvent::PriceSearch(_) => self.stats.price_searches += 1,
+            LogEvent::MapChange(_) => {
+                self.stats.map_changes += 1;
+                self.stats.last_map_change_at = Some(now);
+            }
+            _ => {}
+        }
+    }
+
+    /// Какой известный паттерн должен был совпасть со строкой, но не совпал
+    fn unknown_pattern(&self, line: &str) -> Option<&'static str> {
+        if is_bag_modify_line(line) && !self.patterns.bag_modify.is_match(line) {
+            return Some("bag_modify");
+        }
+        if line.contains("InitBagData") && !self.patterns.bag_init.is_match(line) {
+            return Some("bag_init");
+        }
+        if line.contains("STT----XchgSearchPrice")
+            && !self.patterns.price_send.is_match(line)
+            && !self.patterns.price_recv.is_match(line)
+        {
+            return Some("price_message");
+        }
+        if line.contains("_UpdateGameEnd") && line.contains("NextSceneName") && !self.patterns.map_change.is_match(line) {
+            return Some("map_change");
+        }
+        None
+    }
+
+    /// Учесть строку без события: кольцевой буфер + детектор изменившегося формата
+    fn record_unparsed(&mut self, line: &str) {
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() {
+            return;
+        }
+
+        if let Some(pattern) = self.unknown_pattern(trimmed) {
+            self.stats.unknown_formats += 1;
+            self.sample_unknown(pattern, trimmed);
+        }
+
+        if self.recent_unparsed.len() >= RECENT_UNPARSED_LIMIT {
+            self.recent_unparsed.pop_front();
+        }
+        self.recent_unparsed.push_back(truncate_line(trimmed, UNPARSED_LINE_MAX_LEN).to_string());
+    }
+
+    /// Сохранить пример нераспознанной строки (не чаще раза в минуту на паттерн)
+    fn sample_unknown(&mut self, pattern: &'static str, line: &str) {
+        let now = Utc::now();
+        if let Some(last) = self.last_unknown_sample.get(pattern) {
+            if (now - *last).num_seconds() < UNKNOWN_SAMPLE_INTERVAL_SEC {
+                return;
+            }
+        }
+        self.last_unknown_sample.insert(pattern, now);
+        warn!("Log line matched marker but not the {} pattern (log format changed?)", pattern);
+
+        if self.stats.unknown_samples.len() >= UNKNOWN_SAMPLES_LIMIT {
+            self.stats.unknown_samples.remove(0);
+        }
+        self.stats.unknown_samples.push(UnknownLineSample {
+            pattern: pattern.to_string(),
+            line: truncate_line(line, UNPARSED_LINE_MAX_LEN).to_string(),
+            seen_at: now,
+        });
+    }
+
+    /// Заменить набор regex (перезагрузка паттернов без перезапуска).
+    /// Состояние слотов сохраняется — формат строк меняется, а инвентарь нет
+    pub fn set_patterns(&mut self, patterns: CompiledPatterns) {
+        debug!("Log patterns r{} -> r{}", self.patterns.revision, patterns.revision);
+        self.stats.patterns_revision = patterns.revision;
+        self.patterns = patterns;
+        self.last_unknown_sample.clear();
+    }
+
+    /// Метка времени строки по действующему regex (None — строка без метки).
+    /// Только подсматривает: разобранной строкой не считается и состояние не меняет
+    pub fn line_timestamp(&self, line: &str) -> Option<DateTime<Utc>> {
+        self.parse_timestamp(line)
+    }
+
+    /// Источник, которым помечаются события
+    pub fn source_id(&self) -> &str {
+        &self.source_id
+    }
+
+    /// Счётчики парсера
+    pub fn stats(&self) -> ParserStats {
+        self.stats.clone()
+    }
+
+    /// Последние строки лога, не давшие события (старые первыми)
+    pub fn recent_unparsed(&self) -> Vec<String> {
+        self.recent_unparsed.iter().cloned().collect()
+    }
+
+    fn parse_line_inner(&mut self, line: &str) -> Option<LogEvent> {
         // Проверяем начало/конец блока PickItems
-        if self.pick_start_re.is_match(line) {
+        if self.patterns.pick_start.is_match(line) {
             self.in_pick_block = true;
             debug!(">>> Entered PickItems block");
             return None;
         }
         
-        if self.pick_end_re.is_match(line) {
+        if self.patterns.pick_end.is_match(line) {
             self.in_pick_block = false;
             debug!("<<< Exited PickItems block");
             return None;
@@ -158,7 +408,7 @@ impl LogParser {
         }
         
         // Если есть refer в строке — связываем с последним sync_id
-        if let Some(caps) = self.price_refer_re.captures(line) {
+        if let Some(caps) = self.patterns.price_refer.captures(line) {
             if let Ok(game_id) = caps.get(1).unwrap().as_str().parse::<i64>() {
                 if let Some(sync_id) = self.last_price_sync_id {
                     self.pending_price_requests.insert(sync_id, game_id);
@@ -185,7 +435,11 @@ impl LogParser {
     /// Нужен, чтобы после старта приложения (когда мы tail'им с конца файла) у нас был
     /// baseline для стаков — иначе первый дроп в слот часто теряется.
     pub fn warmup_line(&mut self, line: &str) {
-        if let Some(caps) = self.bag_modify_re.captures(line) {
+        if self.track_bag_init(line) {
+            return;
+        }
+        self.track_holdings(line);
+        if let Some(caps) = self.patterns.bag_modify.captures(line) {
             let page_id: i32 = match caps.get(1).and_then(|m| m.as_str().parse().ok()) {
                 Some(v) => v,
                 None => return,
@@ -205,9 +459,102 @@ impl LogParser {
         }
     }
     
+    /// Учесть строку InitBagData: слот идёт в собираемый снимок и заодно задаёт baseline.
+    /// Пачка заканчивается на первой строке другого вида — тогда снимок готов.
+    /// Возвращает true, если строка была строкой снимка
+    fn track_bag_init(&mut self, line: &str) -> bool {
+        let Some(caps) = self.patterns.bag_init.captures(line) else {
+            if let Some(snapshot) = self.inventory_burst.take() {
+                debug!("Inventory snapshot complete: {} slots", snapshot.slots.len());
+                self.completed_snapshot = Some(snapshot);
+                self.holdings_complete = true;
+            }
+            return false;
+        };
+        let Some(slot) = parse_inventory_slot(&caps) else {
+            return true;
+        };
+
+        let slot_key = (slot.page_id, slot.slot_id);
+        self.slot_quantities.insert(slot_key, slot.quantity);
+        self.initialized_slots.insert(slot_key);
+
+        if self.inventory_burst.is_none() {
+            // Новая пачка описывает инвентарь целиком
+            self.holdings.clear();
+            let timestamp = self.consume_timestamp(line);
+            self.inventory_burst = Some(InventorySnapshotEvent {
+                timestamp,
+                slots: Vec::new(),
+                source_id: self.source_id.clone(),
+            });
+        }
+        self.holdings.insert(slot_key, (slot.game_id, slot.quantity));
+        if slot.quantity > 0 {
+            if let Some(burst) = self.inventory_burst.as_mut() {
+                burst.slots.push(slot);
+            }
+        }
+        true
+    }
+
+    /// Обновить содержимое слота по строке Modfy (подбор, трата, перемещение)
+    /// Вне блока подбора возвращает уменьшение стака (открытие контейнера, продажа и т.п.)
+    /// или рост слота (содержимое контейнера). Рост неизвестного слота считается только
+    /// после полного снимка инвентаря — до него базовое количество неизвестно
+    fn track_holdings(&mut self, line: &str) -> Option<LogEvent> {
+        let caps = self.patterns.bag_modify.captures(line)?;
+        let slot = parse_inventory_slot(&caps)?;
+        let previous = self.holdings.insert((slot.page_id, slot.slot_id), (slot.game_id, slot.quantity));
+        if self.in_pick_block {
+            return None;
+        }
+        let old_quantity = match previous {
+            Some((game_id, old_quantity)) if game_id == slot.game_id => old_quantity,
+            Some(_) => 0,
+            None if self.holdings_complete => 0,
+            None => return None,
+        };
+        let timestamp = self.consume_timestamp(line);
+        match slot.quantity.cmp(&old_quantity) {
+            std::cmp::Ordering::Less => Some(LogEvent::ItemConsumed(ItemConsumedEvent {
+                game_id: slot.game_id,
+                quantity: old_quantity - slot.quantity,
+                timestamp,
+                source_id: self.source_id.clone(),
+            })),
+            std::cmp::Ordering::Greater => Some(LogEvent::ItemGained(ItemGainedEvent {
+                game_id: slot.game_id,
+                quantity: slot.quantity - old_quantity,
+                timestamp,
+                source_id: self.source_id.clone(),
+            })),
+            _ => None,
+        }
+    }
+
+    /// Сколько штук предмета сейчас в инвентаре по известным слотам.
+    /// None — предмет ни разу не встречался (например, с запуска не было снимка)
+    pub fn quantity_held(&self, game_id: i64) -> Option<i64> {
+        let mut seen = false;
+        let mut total = 0i64;
+        for (id, qty) in self.holdings.values() {
+            if *id == game_id {
+                seen = true;
+                total += *qty as i64;
+            }
+        }
+        seen.then_some(total)
+    }
+
+    /// Забрать готовый снимок инвентаря (если пачка InitBagData закончилась)
+    pub fn take_inventory_snapshot(&mut self) -> Option<InventorySnapshotEvent> {
+        self.completed_snapshot.take()
+    }
+    
     /// Парсить изменение инвентаря
     fn parse_bag_modify(&mut self, line: &str) -> Option<ItemDropEvent> {
-        let caps = self.bag_modify_re.captures(line)?;
+        let caps = self.patterns.bag_modify.captures(line)?;
         
         let page_id: i32 = caps.get(1)?.as_str().parse().ok()?;
         let slot_id: i32 = caps.get(2)?.as_str().parse().ok()?;
@@ -231,7 +578,8 @@ impl LogParser {
                 return None;
             }
 
-            let timestamp = self.parse_timestamp(line).unwrap_or_else(Utc::now);
+            let timestamp = self.consume_timestamp(line);
+            let (tier, rarity) = self.parse_tier(line);
             debug!(
                 "First seen slot {:?} baseline={}, counting minimal drop=1 (game_id={})",
                 slot_key, new_quantity, game_id
@@ -243,6 +591,11 @@ impl LogParser {
                 timestamp,
                 page_id,
                 slot_id,
+                slot_quantity: new_quantity,
+                source_id: self.source_id.clone(),
+                tier,
+                rarity,
+                item_name: self.parse_item_name(line),
             });
         }
         
@@ -260,7 +613,8 @@ impl LogParser {
             return None;
         }
         
-        let timestamp = self.parse_timestamp(line).unwrap_or_else(Utc::now);
+        let timestamp = self.consume_timestamp(line);
+        let (tier, rarity) = self.parse_tier(line);
         
         debug!("Item picked up: game_id={}, quantity={}, page={}, slot={}", 
                game_id, delta, page_id, slot_id);
@@ -271,25 +625,30 @@ impl LogParser {
             timestamp,
             page_id,
             slot_id,
+            slot_quantity: new_quantity,
+            source_id: self.source_id.clone(),
+            tier,
+            rarity,
+            item_name: self.parse_item_name(line),
         })
     }
     
     /// Парсить отправку запроса цены
     fn parse_price_send(&mut self, line: &str) -> Option<i32> {
-        let caps = self.price_send_re.captures(line)?;
+        let caps = self.patterns.price_send.captures(line)?;
         let sync_id: i32 = caps.get(1)?.as_str().parse().ok()?;
         Some(sync_id)
     }
     
     /// Парсить ответ с ценами
     fn parse_price_recv(&mut self, line: &str) -> Option<PriceSearchEvent> {
-        let caps = self.price_recv_re.captures(line)?;
+        let caps = self.patterns.price_recv.captures(line)?;
         let sync_id: i32 = caps.get(1)?.as_str().parse().ok()?;
         
         // Получаем game_id из pending запросов
         let game_id = self.pending_price_requests.remove(&sync_id)?;
         
-        let timestamp = self.parse_timestamp(line).unwrap_or_else(Utc::now);
+        let timestamp = self.consume_timestamp(line);
         
         // Цены будут в следующих строках, создаём событие с пустыми ценами
         // Они будут заполнены при парсинге следующих строк
@@ -301,32 +660,37 @@ impl LogParser {
             currency_id: 100300, // По умолчанию Flame Elementium
             timestamp,
             sync_id,
+            source_id: self.source_id.clone(),
         })
     }
     
     /// Парсить смену карты
-    fn parse_map_change(&self, line: &str) -> Option<MapChangeEvent> {
-        let caps = self.map_change_re.captures(line)?;
+    fn parse_map_change(&mut self, line: &str) -> Option<MapChangeEvent> {
+        let caps = self.patterns.map_change.captures(line)?;
 
-        let timestamp = self.parse_timestamp(line).unwrap_or_else(Utc::now);
+        let timestamp = self.consume_timestamp(line);
 
             // NextSceneName (для будущих фич — маппинг карты/типа контента)
             // Если не удалось — fallback на всю строку.
             let scene_name = caps.get(1).map(|m| m.as_str().to_string()).unwrap_or_else(|| line.to_string());
 
             // Определяем тип события строго по NextSceneName, иначе можно ошибочно матчить hideout в LastSceneName.
-            let event_type = if self.hideout_re.is_match(&scene_name) {
-                MapEventType::ExitToHideout
+            let (event_type, zone) = if self.patterns.hideout.is_match(&scene_name) {
+                (MapEventType::ExitToHideout, ZoneKind::Hideout)
+            } else if self.patterns.farming_zone.is_match(&scene_name) {
+                (MapEventType::EnterMap, ZoneKind::FarmingMap)
             } else {
-                MapEventType::EnterMap
+                (MapEventType::EnterMap, ZoneKind::Other)
             };
 
-        debug!("Map change: {:?} -> {}", event_type, scene_name);
+        debug!("Map change: {:?} ({:?}) -> {}", event_type, zone, scene_name);
 
         Some(MapChangeEvent {
             event_type,
             scene_name,
+            zone,
             timestamp,
+            source_id: self.source_id.clone(),
         })
     }
     
@@ -338,21 +702,21 @@ impl LogParser {
         
         for line in lines {
             // Извлекаем цены
-            for caps in self.price_unit_re.captures_iter(line) {
+            for caps in self.patterns.price_unit.captures_iter(line) {
                 if let Ok(price) = caps.get(1).unwrap().as_str().parse::<f64>() {
                     prices.push(price);
                 }
             }
 
             // В некоторых логах цены идут продолжением строк без "unitPrices+" (| | +2 [..]).
-            if let Some(caps) = self.price_unit_cont_re.captures(line) {
+            if let Some(caps) = self.patterns.price_unit_cont.captures(line) {
                 if let Ok(price) = caps.get(1).unwrap().as_str().parse::<f64>() {
                     prices.push(price);
                 }
             }
             
             // Извлекаем валюту
-            if let Some(caps) = self.price_currency_re.captures(line) {
+            if let Some(caps) = self.patterns.price_currency.captures(line) {
                 if let Ok(cid) = caps.get(1).unwrap().as_str().parse::<i64>() {
                     currency_id = cid;
                 }
@@ -369,9 +733,17 @@ impl LogParser {
         self.pending_price_requests.clear();
         self.in_pick_block = false;
         self.last_price_sync_id = None;
+        self.inventory_burst = None;
+        self.holdings.clear();
+        self.holdings_complete = false;
     }
 }
 
+/// Строка BagMgr@:Modfy (в CN-клиенте двоеточие бывает полноширинным, как в bag_modify `[:：]`)
+fn is_bag_modify_line(line: &str) -> bool {
+    line.contains("BagMgr@") && line.contains("Modfy BagItem")
+}
+
 impl Default for LogParser {
     fn default() -> Self {
         Self::new()
@@ -404,6 +776,33 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_parse_gear_tier() {
+        let mut parser = LogParser::new();
+        parser.in_pick_block = true;
+
+        let gear = "[2026.01.12-11.34.07:799][980]GameLog: Display: [Game] BagMgr@:Modfy BagItem PageId = 100 SlotId = 3 ConfigBaseId = 200131 Num = 1 Tier = 3 Quality = 2";
+        match parser.parse_line(gear) {
+            Some(LogEvent::ItemDrop(drop)) => {
+                assert_eq!(drop.tier, Some(3));
+                assert_eq!(drop.rarity, Some(2));
+            }
+            _ => panic!("Expected ItemDrop event"),
+        }
+
+        let currency = "[2026.01.12-11.34.08:799][980]GameLog: Display: [Game] BagMgr@:Modfy BagItem PageId = 102 SlotId = 1 ConfigBaseId = 100200 Num = 50";
+        match parser.parse_line(currency) {
+            Some(LogEvent::ItemDrop(drop)) => assert_eq!((drop.tier, drop.rarity, drop.item_name), (None, None, None)),
+            _ => panic!("Expected ItemDrop event"),
+        }
+
+        let named = "[2026.01.12-11.34.09:799][980]GameLog: Display: [Game] BagMgr@:Modfy BagItem PageId = 102 SlotId = 2 ConfigBaseId = 999001 Num = 1 Name = Ember Shard Tier = 1";
+        match parser.parse_line(named) {
+            Some(LogEvent::ItemDrop(drop)) => assert_eq!(drop.item_name.as_deref(), Some("Ember Shard")),
+            _ => panic!("Expected ItemDrop event"),
+        }
+    }
+    
     #[test]
     fn test_parse_timestamp() {
         let parser = LogParser::new();
@@ -417,6 +816,103 @@ mod tests {
         assert_eq!(ts.month(), 1);
         assert_eq!(ts.day(), 12);
     }
+
+    #[test]
+    fn peeking_a_timestamp_keeps_parser_state() {
+        let mut parser = LogParser::new();
+        let line = "[2026.01.12-11.34.07:799]";
+        assert!(parser.line_timestamp(line).is_some());
+        assert_eq!(parser.last_timestamp, None);
+
+        let ts = parser.consume_timestamp(line);
+        assert_eq!(parser.last_timestamp, Some(ts));
+    }
+
+    #[test]
+    fn test_timestamp_time_zones() {
+        let mut parser = LogParser::new();
+        let line = "[2026.01.12-01.34.07:799]";
+        let utc = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+
+        parser.set_time_zone(LogTimeZone::Utc, 0);
+        assert_eq!(parser.parse_timestamp(line), Some(utc("2026-01-12T01:34:07.799Z")));
+
+        // Местное UTC+3 -> на три часа раньше, в предыдущие сутки по UTC
+        parser.set_time_zone(LogTimeZone::Fixed, 180);
+        assert_eq!(parser.parse_timestamp(line), Some(utc("2026-01-11T22:34:07.799Z")));
+        parser.set_time_zone(LogTimeZone::Fixed, -300);
+        assert_eq!(parser.parse_timestamp(line), Some(utc("2026-01-12T06:34:07.799Z")));
+
+        // Смещение вне диапазона не теряет метки
+        parser.set_time_zone(LogTimeZone::Fixed, 100 * 60);
+        assert_eq!(parser.parse_timestamp(line), Some(utc("2026-01-11T11:34:07.799Z")));
+    }
+
+    /// Пояс UTC+1 с летним временем UTC+2 с 29.03.2026 01:00 UTC по 25.10.2026 01:00 UTC
+    #[derive(Debug, Clone, Copy)]
+    struct DstZone;
+
+    impl DstZone {
+        fn winter() -> FixedOffset { FixedOffset::east_opt(3600).unwrap() }
+        fn summer() -> FixedOffset { FixedOffset::east_opt(7200).unwrap() }
+    }
+
+    impl TimeZone for DstZone {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            DstZone
+        }
+
+        fn offset_from_local_date(&self, local: &chrono::NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(12, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            // Подходит смещение, при котором обратный перевод даёт то же смещение;
+            // раньше по UTC — то, что больше
+            let fits: Vec<FixedOffset> = [Self::summer(), Self::winter()]
+                .into_iter()
+                .filter(|o| self.offset_from_utc_datetime(&(*local - chrono::Duration::seconds(o.local_minus_utc() as i64))) == *o)
+                .collect();
+            match fits[..] {
+                [] => LocalResult::None,
+                [single] => LocalResult::Single(single),
+                [early, late, ..] => LocalResult::Ambiguous(early, late),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &chrono::NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+            if *utc >= at("2026-03-29 01:00") && *utc < at("2026-10-25 01:00") {
+                Self::summer()
+            } else {
+                Self::winter()
+            }
+        }
+    }
+
+    #[test]
+    fn test_dst_transitions() {
+        let local = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        let utc = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+
+        assert_eq!(log_time_to_utc(&DstZone, &local("2026-07-01 12:00"), None), utc("2026-07-01T10:00:00Z"));
+
+        // Повторяющийся час: без предыдущей метки — первый проход, после неё — второй
+        let repeated = local("2026-10-25 02:30");
+        assert_eq!(log_time_to_utc(&DstZone, &repeated, None), utc("2026-10-25T00:30:00Z"));
+        assert_eq!(log_time_to_utc(&DstZone, &repeated, Some(utc("2026-10-25T00:50:00Z"))), utc("2026-10-25T01:30:00Z"));
+        assert_eq!(log_time_to_utc(&DstZone, &repeated, Some(utc("2026-10-25T00:10:00Z"))), utc("2026-10-25T00:30:00Z"));
+
+        // Пропущенный час: метка не теряется и считается по смещению до перехода
+        let skipped = log_time_to_utc(&DstZone, &local("2026-03-29 02:30"), None);
+        assert_eq!(skipped, utc("2026-03-29T01:30:00Z"));
+    }
     
     #[test]
     fn test_delta_calculation() {
@@ -439,4 +935,140 @@ mod tests {
             assert_eq!(drop.quantity, 25);
         }
     }
+    
+    #[test]
+    fn test_stack_decrease_outside_pickup_is_consumption() {
+        let mut parser = LogParser::new();
+        let line = |num: i32| format!(
+            "[2026.01.12-11.34.07:799][980]GameLog: Display: [Game] BagMgr@:Modfy BagItem PageId = 103 SlotId = 4 ConfigBaseId = 5028 Num = {}",
+            num
+        );
+
+        assert!(parser.parse_line(&line(5)).is_none());
+        match parser.parse_line(&line(3)) {
+            Some(LogEvent::ItemConsumed(consumed)) => {
+                assert_eq!(consumed.game_id, 5028);
+                assert_eq!(consumed.quantity, 2);
+            }
+            other => panic!("Expected ItemConsumed, got {:?}", other),
+        }
+        // Рост стака вне подбора — не расход, а прибавка (содержимое контейнера)
+        match parser.parse_line(&line(4)) {
+            Some(LogEvent::ItemGained(gained)) => assert_eq!(gained.quantity, 1),
+            other => panic!("Expected ItemGained, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_parser_stats_count_events_and_sample_unknown_once() {
+        let mut parser = LogParser::new();
+        parser.in_pick_block = true;
+
+        let drop = "[2026.01.12-11.34.07:799][980]GameLog: Display: [Game] BagMgr@:Modfy BagItem PageId = 102 SlotId = 1 ConfigBaseId = 100200 Num = 50";
+        assert!(parser.parse_line(drop).is_some());
+
+        // Маркер есть, но формат поменялся — пример сохраняется один раз за интервал
+        let broken = "[2026.01.12-11.34.08:799][980]GameLog: Display: [Game] BagMgr@:Modfy BagItem Page = 102 Slot = 1 Id = 100200 Count = 50";
+        assert!(parser.parse_line(broken).is_none());
+        assert!(parser.parse_line(broken).is_none());
+
+        let stats = parser.stats();
+        assert_eq!(stats.item_drops, 1);
+        assert!(stats.last_item_drop_at.is_some());
+        assert_eq!(stats.unknown_formats, 2);
+        assert_eq!(stats.unknown_samples.len(), 1);
+        assert_eq!(stats.unknown_samples[0].pattern, "bag_modify");
+    }
+
+    #[test]
+    fn test_set_patterns_hot_reload() {
+        let mut parser = LogParser::new();
+        parser.in_pick_block = true;
+        let patched = "[2026.01.12-11.34.07:799][980]GameLog: Display: [Game] BagMgr@:Modfy BagItem Page = 102 Slot = 1 Id = 100200 Count = 50";
+        assert!(parser.parse_line(patched).is_none());
+
+        let mut patterns = LogPatterns::bundled();
+        patterns.revision += 1;
+        patterns.bag_modify = r"BagMgr@:Modfy BagItem Page = (\d+) Slot = (\d+) Id = (\d+) Count = (\d+)".to_string();
+        parser.set_patterns(patterns.compile().unwrap());
+
+        // Тот же слот теперь распознаётся новым regex
+        match parser.parse_line(patched) {
+            Some(LogEvent::ItemDrop(drop)) => assert_eq!(drop.game_id, 100200),
+            other => panic!("Expected ItemDrop event, got {:?}", other),
+        }
+        assert_eq!(parser.stats().patterns_revision, patterns.revision);
+    }
+
+    #[test]
+    fn test_cn_patterns_accept_fullwidth_colon() {
+        let mut parser = LogParser::new();
+        parser.set_patterns(LogPatterns::bundled_for(crate::types::GameClient::Cn).compile().unwrap());
+        parser.in_pick_block = true;
+
+        let line = "[2026.01.12-11.34.07:799][980]GameLog: Display: [Game] BagMgr@：Modfy BagItem PageId = 102 SlotId = 1 ConfigBaseId = 100200 Num = 50";
+        match parser.parse_line(line) {
+            Some(LogEvent::ItemDrop(drop)) => assert_eq!(drop.game_id, 100200),
+            other => panic!("Expected ItemDrop event, got {:?}", other),
+        }
+        assert_eq!(parser.stats().bag_lines_in_pick, 1);
+
+        let changed = "[2026.01.12-11.34.08:799][980]GameLog: Display: [Game] BagMgr@：Modfy BagItem Page = 102";
+        assert_eq!(parser.unknown_pattern(changed), Some("bag_modify"));
+    }
+
+    #[test]
+    fn test_inventory_snapshot_burst() {
+        let mut parser = LogParser::new();
+        let init = |slot: i32, id: i64, num: i32| format!(
+            "[2026.01.12-11.30.00:100][980]GameLog: Display: [Game] BagMgr@:InitBagData PageId = 102 SlotId = {} ConfigBaseId = {} Num = {}",
+            slot, id, num
+        );
+        assert!(parser.parse_line(&init(0, 100300, 671)).is_none());
+        assert!(parser.parse_line(&init(1, 100200, 0)).is_none());
+        assert!(parser.parse_line(&init(2, 100200, 40)).is_none());
+        assert!(parser.take_inventory_snapshot().is_none(), "burst is still open");
+
+        parser.parse_line("[2026.01.12-11.30.01:000][980]GameLog: Display: [Game] something else");
+        let snapshot = parser.take_inventory_snapshot().expect("snapshot after burst");
+        assert_eq!(snapshot.slots.len(), 2, "empty slots are skipped");
+        assert_eq!(snapshot.slots[0].quantity, 671);
+
+        // Снимок задаёт baseline: следующий подбор считается по дельте
+        parser.in_pick_block = true;
+        let pick = "[2026.01.12-11.31.00:000][980]GameLog: Display: [Game] BagMgr@:Modfy BagItem PageId = 102 SlotId = 2 ConfigBaseId = 100200 Num = 45";
+        match parser.parse_line(pick) {
+            Some(LogEvent::ItemDrop(drop)) => assert_eq!(drop.quantity, 5),
+            other => panic!("Expected ItemDrop event, got {:?}", other),
+        }
+        assert_eq!(parser.quantity_held(100300), Some(671));
+        assert_eq!(parser.quantity_held(100200), Some(45));
+        assert_eq!(parser.quantity_held(999), None);
+    }
+
+    #[test]
+    fn test_map_change_zone_classification() {
+        let mut parser = LogParser::new();
+        let line = |next: &str| format!(
+            "[2026.01.12-11.40.00:000][980]GameLog: Display: [Game] PageApplyBase@ _UpdateGameEnd: LastSceneName = World'/Game/Art/Maps/Prev' NextSceneName = World'/Game/Art/Maps/{}'",
+            next
+        );
+        let zone = |parser: &mut LogParser, next: &str| match parser.parse_line(&line(next)) {
+            Some(LogEvent::MapChange(map)) => (map.event_type, map.zone),
+            other => panic!("Expected MapChange event, got {:?}", other),
+        };
+
+        assert_eq!(
+            zone(&mut parser, "04DD/DD_TanXiZhiQiang000/DD_TanXiZhiQiang000"),
+            (MapEventType::EnterMap, ZoneKind::FarmingMap)
+        );
+        assert_eq!(
+            zone(&mut parser, "01XZ/XZ_YuJinZhiXiBiNanSuo200/XZ_YuJinZhiXiBiNanSuo200"),
+            (MapEventType::ExitToHideout, ZoneKind::Hideout)
+        );
+        assert_eq!(
+            zone(&mut parser, "02ZX/ZX_Town000/ZX_Town000"),
+            (MapEventType::EnterMap, ZoneKind::Other)
+        );
+    }
 }
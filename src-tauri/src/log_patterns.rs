@@ -0,0 +1,212 @@
+//! Определения паттернов лога (regex) для LogParser
+//!
+//! Встроенные наборы лежат в resources/ (log_patterns.json — глобальный клиент,
+//! log_patterns_cn.json — китайский) и вшиваются в бинарник.
+//! Поверх них может действовать override из data_local_dir()/tli-companion/log_patterns.json:
+//! его кладёт пользователь вручную или приложение, скачав свежий набор из Supabase
+//! (tli_log_patterns). Действует набор с наибольшей ревизией — так после патча игры
+//! исправленный regex доезжает до пользователей без релиза приложения.
+//! Override применяется, только если он написан для того же клиента.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::types::GameClient;
+
+/// Встроенные наборы паттернов
+const BUNDLED_PATTERNS: &str = include_str!("../resources/log_patterns.json");
+const BUNDLED_PATTERNS_CN: &str = include_str!("../resources/log_patterns_cn.json");
+
+/// Набор regex в исходном (текстовом) виде
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogPatterns {
+    /// Ревизия набора (растёт с каждым исправлением под новый формат лога)
+    pub revision: u32,
+    /// Для какого клиента набор (global/cn)
+    #[serde(default = "default_client")]
+    pub client: GameClient,
+    pub timestamp: String,
+    pub pick_start: String,
+    pub pick_end: String,
+    pub bag_modify: String,
+    /// Появился в r1 без смены ревизии: старые override-файлы его не содержат
+    #[serde(default = "default_bag_init")]
+    pub bag_init: String,
+    pub price_send: String,
+    pub price_recv: String,
+    pub price_refer: String,
+    pub price_unit: String,
+    pub price_unit_cont: String,
+    pub price_currency: String,
+    pub map_change: String,
+    pub hideout: String,
+    /// Сцены, где идёт фарм (остальное — город, кампания, испытания)
+    #[serde(default = "default_farming_zone")]
+    pub farming_zone: String,
+    /// Тир/редкость экипировки в строке подбора (именованные группы tier/rarity)
+    #[serde(default = "default_item_tier")]
+    pub item_tier: String,
+    /// Имя предмета в строке подбора, если клиент его пишет (именованная группа name)
+    #[serde(default = "default_item_name")]
+    pub item_name: String,
+}
+
+/// Скомпилированный набор паттернов
+#[derive(Debug, Clone)]
+pub struct CompiledPatterns {
+    pub revision: u32,
+    /// [2026.01.12-11.34.07:799]
+    pub timestamp: Regex,
+    /// ItemChange@ ProtoName=PickItems start
+    pub pick_start: Regex,
+    /// ItemChange@ ProtoName=PickItems end
+    pub pick_end: Regex,
+    /// BagMgr@:Modfy BagItem PageId = 102 SlotId = 1 ConfigBaseId = 100200 Num = 904
+    pub bag_modify: Regex,
+    /// BagMgr@:InitBagData PageId = 102 SlotId = 0 ConfigBaseId = 100300 Num = 671 (снимок при входе)
+    pub bag_init: Regex,
+    /// ----Socket SendMessage STT----XchgSearchPrice----SynId = 4006
+    pub price_send: Regex,
+    /// ----Socket RecvMessage STT----XchgSearchPrice----SynId = 4006
+    pub price_recv: Regex,
+    /// +refer [101010037_15] или |       | +refer [200029] — нужен только ведущий game_id
+    pub price_refer: Regex,
+    /// +unitPrices+1 [1.0]
+    pub price_unit: Regex,
+    /// Продолжение: |      | |          +2 [1.0] (формат варьируется по отступам)
+    pub price_unit_cont: Regex,
+    /// +currency [100300]
+    pub price_currency: Regex,
+    /// PageApplyBase@ _UpdateGameEnd: ... NextSceneName = World'/Game/Art/Maps/...' (caps[1] = путь карты)
+    pub map_change: Regex,
+    /// Убежище: XZ_YuJinZhiXiBiNanSuo200
+    pub hideout: Regex,
+    /// Фарм-зона (карты Netherrealm): /Game/Art/Maps/04DD/DD_TanXiZhiQiang000
+    pub farming_zone: Regex,
+    /// ... ConfigBaseId = 200131 Num = 1 Tier = 3 Quality = 2 (поля есть только у экипировки)
+    pub item_tier: Regex,
+    /// ... ConfigBaseId = 200131 Num = 1 Name = Flame Elementium (есть не во всех версиях клиента)
+    pub item_name: Regex,
+}
+
+impl CompiledPatterns {
+    /// Все regex набора с именами (порядок как в JSON)
+    pub fn named(&self) -> [(&'static str, &Regex); 16] {
+        [
+            ("timestamp", &self.timestamp),
+            ("pick_start", &self.pick_start),
+            ("pick_end", &self.pick_end),
+            ("bag_modify", &self.bag_modify),
+            ("bag_init", &self.bag_init),
+            ("price_send", &self.price_send),
+            ("price_recv", &self.price_recv),
+            ("price_refer", &self.price_refer),
+            ("price_unit", &self.price_unit),
+            ("price_unit_cont", &self.price_unit_cont),
+            ("price_currency", &self.price_currency),
+            ("map_change", &self.map_change),
+            ("hideout", &self.hideout),
+            ("farming_zone", &self.farming_zone),
+            ("item_tier", &self.item_tier),
+            ("item_name", &self.item_name),
+        ]
+    }
+}
+
+/// Откуда взят действующий набор
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternsSource {
+    Bundled,
+    Override,
+}
+
+/// Результат перезагрузки паттернов (для UI)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternsReloadResult {
+    pub revision: u32,
+    /// Клиент, под который выбран набор (Auto уже разрешён по пути к логу)
+    pub client: GameClient,
+    pub source: PatternsSource,
+    /// Ревизия из Supabase (None — не удалось получить или набора нет)
+    pub remote_revision: Option<u32>,
+    pub remote_error: Option<String>,
+}
+
+fn default_client() -> GameClient {
+    GameClient::Global
+}
+
+fn default_bag_init() -> String {
+    r"BagMgr@\s*[:：]\s*InitBagData PageId\s*=\s*(\d+)\s*SlotId\s*=\s*(\d+)\s*ConfigBaseId\s*=\s*(\d+)\s*Num\s*=\s*(\d+)".to_string()
+}
+
+fn default_farming_zone() -> String {
+    r"/Maps/04DD/".to_string()
+}
+
+fn default_item_tier() -> String {
+    r"\bTier\s*=\s*(?P<tier>\d+)(?:.*?\b(?:Quality|Rarity)\s*=\s*(?P<rarity>\d+))?".to_string()
+}
+
+fn default_item_name() -> String {
+    r"\bName\s*=\s*(?P<name>[^=\r\n]+?)\s*(?:\b\w+\s*=|$)".to_string()
+}
+
+impl LogPatterns {
+    /// Встроенный набор глобального клиента
+    pub fn bundled() -> Self {
+        Self::bundled_for(GameClient::Global)
+    }
+
+    /// Встроенный набор для клиента (Auto трактуется как глобальный)
+    pub fn bundled_for(client: GameClient) -> Self {
+        let raw = match client {
+            GameClient::Cn => BUNDLED_PATTERNS_CN,
+            GameClient::Global | GameClient::Auto => BUNDLED_PATTERNS,
+        };
+        serde_json::from_str(raw).expect("bundled log patterns must be valid")
+    }
+
+    /// Скомпилировать все regex. Ошибка содержит имя сломанного паттерна
+    pub fn compile(&self) -> Result<CompiledPatterns, String> {
+        let re = |name: &str, src: &str| {
+            Regex::new(src).map_err(|e| format!("log pattern '{}' is invalid: {}", name, e))
+        };
+        Ok(CompiledPatterns {
+            revision: self.revision,
+            timestamp: re("timestamp", &self.timestamp)?,
+            pick_start: re("pick_start", &self.pick_start)?,
+            pick_end: re("pick_end", &self.pick_end)?,
+            bag_modify: re("bag_modify", &self.bag_modify)?,
+            bag_init: re("bag_init", &self.bag_init)?,
+            price_send: re("price_send", &self.price_send)?,
+            price_recv: re("price_recv", &self.price_recv)?,
+            price_refer: re("price_refer", &self.price_refer)?,
+            price_unit: re("price_unit", &self.price_unit)?,
+            price_unit_cont: re("price_unit_cont", &self.price_unit_cont)?,
+            price_currency: re("price_currency", &self.price_currency)?,
+            map_change: re("map_change", &self.map_change)?,
+            hideout: re("hideout", &self.hideout)?,
+            farming_zone: re("farming_zone", &self.farming_zone)?,
+            item_tier: re("item_tier", &self.item_tier)?,
+            item_name: re("item_name", &self.item_name)?,
+        })
+    }
+}
+
+/// Выбрать действующий набор: override применяется, только если он для того же клиента,
+/// его ревизия не ниже встроенной и все regex компилируются
+pub fn select(override_patterns: Option<LogPatterns>, client: GameClient) -> (LogPatterns, PatternsSource) {
+    let bundled = LogPatterns::bundled_for(client);
+    match override_patterns {
+        Some(p) if p.client == bundled.client && p.revision >= bundled.revision => match p.compile() {
+            Ok(_) => (p, PatternsSource::Override),
+            Err(e) => {
+                log::warn!("Ignoring log patterns override r{}: {}", p.revision, e);
+                (bundled, PatternsSource::Bundled)
+            }
+        },
+        _ => (bundled, PatternsSource::Bundled),
+    }
+}
@@ -0,0 +1,247 @@
+//! Дележ лута после групповой сессии
+//!
+//! Каждый участник указывает, сколько лута у него на руках (FE или список предметов),
+//! сколько он потратил и свой вес доли. Затраты (если так решили) сначала
+//! возвращаются, остаток делится по весам. Два режима:
+//! - fe_equalized — предметы остаются у владельцев, разница выравнивается переводами FE;
+//! - itemized — предметы складываются в общий котёл и раздаются по долям (самые дорогие
+//!   первыми), остаток добивается переводами FE.
+//!
+//! Результат включает готовый текст для вставки в чат.
+
+use std::fmt::Write as _;
+
+use serde::{Deserialize, Serialize};
+
+use crate::party::{split_transfers, SplitTransfer};
+use crate::value_format::fe;
+
+/// Режим дележа
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitMode {
+    #[default]
+    FeEqualized,
+    Itemized,
+}
+
+/// Правила дележа
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LootSplitRules {
+    #[serde(default)]
+    pub mode: SplitMode,
+    /// Сначала вернуть участникам их затраты, делить только остаток
+    #[serde(default = "default_true")]
+    pub refund_expenses: bool,
+}
+
+fn default_true() -> bool { true }
+
+fn default_weight() -> f64 { 1.0 }
+
+/// Строка лута участника
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LootLine {
+    pub name: String,
+    pub quantity: i32,
+    /// Цена за штуку, FE
+    pub unit_value: f64,
+}
+
+/// Участник дележа
+#[derive(Debug, Clone, Deserialize)]
+pub struct LootParticipant {
+    pub name: String,
+    /// Предметы на руках (для режима itemized и как основа стоимости)
+    #[serde(default)]
+    pub items: Vec<LootLine>,
+    /// Лут на руках в FE сверх списка предметов
+    #[serde(default)]
+    pub loot_value: f64,
+    /// Затраты на сессию (входы, расходники), FE
+    #[serde(default)]
+    pub expenses: f64,
+    /// Вес доли (1 — обычная доля)
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+impl LootParticipant {
+    fn held(&self) -> f64 {
+        self.loot_value + self.items.iter().map(|l| l.unit_value * l.quantity as f64).sum::<f64>()
+    }
+}
+
+/// Итог по участнику
+#[derive(Debug, Clone, Serialize)]
+pub struct ParticipantShare {
+    pub name: String,
+    /// Стоимость лута на руках до дележа
+    pub held: f64,
+    pub expenses: f64,
+    /// Сколько ему причитается (доля + возврат затрат)
+    pub entitled: f64,
+    /// Предметы, которые ему достаются (режим itemized)
+    pub items: Vec<LootLine>,
+}
+
+/// Результат compute_loot_split
+#[derive(Debug, Clone, Serialize)]
+pub struct LootSplit {
+    pub mode: SplitMode,
+    pub total_loot: f64,
+    pub total_expenses: f64,
+    /// Чистый профит группы (лут минус затраты)
+    pub net_profit: f64,
+    pub shares: Vec<ParticipantShare>,
+    pub transfers: Vec<SplitTransfer>,
+    /// Текст для чата
+    pub text: String,
+}
+
+/// Посчитать дележ
+pub fn compute(participants: &[LootParticipant], rules: &LootSplitRules) -> Result<LootSplit, String> {
+    if participants.is_empty() {
+        return Err("No participants".to_string());
+    }
+    let non_negative = |v: f64| v.is_finite() && v >= 0.0;
+    if participants.iter().any(|p| !p.weight.is_finite() || p.weight <= 0.0 || !non_negative(p.expenses) || !non_negative(p.loot_value)) {
+        return Err("Weights must be positive, loot and expenses non-negative".to_string());
+    }
+    if participants.iter().flat_map(|p| &p.items).any(|l| l.quantity < 0 || !non_negative(l.unit_value)) {
+        return Err("Item quantities and values must be non-negative".to_string());
+    }
+
+    let total_loot: f64 = participants.iter().map(LootParticipant::held).sum();
+    let total_expenses: f64 = participants.iter().map(|p| p.expenses).sum();
+    let total_weight: f64 = participants.iter().map(|p| p.weight).sum();
+    let refunded = if rules.refund_expenses { total_expenses } else { 0.0 };
+    let pot = total_loot - refunded;
+
+    let mut shares: Vec<ParticipantShare> = participants
+        .iter()
+        .map(|p| ParticipantShare {
+            name: p.name.clone(),
+            held: p.held(),
+            expenses: p.expenses,
+            entitled: (if rules.refund_expenses { p.expenses } else { 0.0 }) + pot * p.weight / total_weight,
+            items: Vec::new(),
+        })
+        .collect();
+
+    // Сколько у каждого после раздачи предметов
+    let after: Vec<f64> = match rules.mode {
+        SplitMode::FeEqualized => shares.iter().map(|s| s.held).collect(),
+        SplitMode::Itemized => allocate_items(participants, &mut shares),
+    };
+    let balances = shares.iter().zip(&after).map(|(s, got)| (s.name.clone(), got - s.entitled)).collect();
+    let transfers = split_transfers(balances);
+
+    let mut split = LootSplit {
+        mode: rules.mode,
+        total_loot,
+        total_expenses,
+        net_profit: total_loot - total_expenses,
+        shares,
+        transfers,
+        text: String::new(),
+    };
+    split.text = render_text(&split);
+    Ok(split)
+}
+
+/// Раздать все предметы из котла: каждую штуку — тому, кому больше всех недодано.
+/// FE сверх предметов остаются у владельцев. Возвращает стоимость на руках после раздачи
+fn allocate_items(participants: &[LootParticipant], shares: &mut [ParticipantShare]) -> Vec<f64> {
+    let mut got: Vec<f64> = participants.iter().map(|p| p.loot_value).collect();
+    let mut pool: Vec<LootLine> = participants.iter().flat_map(|p| p.items.iter().cloned()).collect();
+    pool.sort_by(|a, b| b.unit_value.total_cmp(&a.unit_value));
+
+    for line in pool {
+        let mut left = line.quantity;
+        while left > 0 {
+            let (idx, need) = shares
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (i, s.entitled - got[i]))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .expect("participants are not empty");
+            // Сколько штук закрывают недостачу; хотя бы одна — котёл раздаём целиком
+            let fits = if line.unit_value > 0.0 { (need / line.unit_value).floor() as i32 } else { left };
+            let count = fits.clamp(1, left);
+            got[idx] += line.unit_value * count as f64;
+            left -= count;
+            match shares[idx].items.iter_mut().find(|l| l.name == line.name && l.unit_value == line.unit_value) {
+                Some(existing) => existing.quantity += count,
+                None => shares[idx].items.push(LootLine { quantity: count, ..line.clone() }),
+            }
+        }
+    }
+    got
+}
+
+fn render_text(split: &LootSplit) -> String {
+    let mut text = String::new();
+    let _ = writeln!(
+        text,
+        "Loot: {} FE, expenses: {} FE, net: {} FE",
+        fe(split.total_loot),
+        fe(split.total_expenses),
+        fe(split.net_profit)
+    );
+    for share in &split.shares {
+        let _ = writeln!(text, "{}: share {} FE", share.name, fe(share.entitled));
+        for item in &share.items {
+            let _ = writeln!(text, "  {} x{}", item.name, item.quantity);
+        }
+    }
+    for t in &split.transfers {
+        let _ = writeln!(text, "{} -> {}: {} FE", t.from, t.to, fe(t.amount));
+    }
+    text.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn participant(name: &str, loot_value: f64, expenses: f64) -> LootParticipant {
+        LootParticipant { name: name.to_string(), items: Vec::new(), loot_value, expenses, weight: 1.0 }
+    }
+
+    #[test]
+    fn expenses_are_refunded_before_equal_split() {
+        let rules = LootSplitRules { mode: SplitMode::FeEqualized, refund_expenses: true };
+        // Лут 1000, затраты 200 (у b) → по 400 каждому, b дополнительно возвращают 200
+        let split = compute(&[participant("a", 1000.0, 0.0), participant("b", 0.0, 200.0)], &rules).unwrap();
+        assert_eq!(split.shares[1].entitled, 600.0);
+        assert_eq!(split.transfers, vec![SplitTransfer { from: "a".into(), to: "b".into(), amount: 600.0 }]);
+    }
+
+    #[test]
+    fn itemized_hands_out_the_pool() {
+        let rules = LootSplitRules { mode: SplitMode::Itemized, refund_expenses: false };
+        let mut a = participant("a", 0.0, 0.0);
+        a.items = vec![LootLine { name: "Ember".into(), quantity: 10, unit_value: 50.0 }];
+        let split = compute(&[a, participant("b", 0.0, 0.0)], &rules).unwrap();
+        assert_eq!(split.shares[0].items[0].quantity, 5);
+        assert_eq!(split.shares[1].items[0].quantity, 5);
+        assert!(split.transfers.is_empty());
+    }
+
+    #[test]
+    fn rejects_negative_or_non_finite_values() {
+        let rules = LootSplitRules { mode: SplitMode::Itemized, refund_expenses: true };
+        let with_item = |quantity: i32, unit_value: f64| {
+            let mut a = participant("a", 0.0, 0.0);
+            a.items = vec![LootLine { name: "Ember".into(), quantity, unit_value }];
+            vec![a, participant("b", 0.0, 0.0)]
+        };
+        assert!(compute(&with_item(1_000_000, -1.0), &rules).is_err());
+        assert!(compute(&with_item(1, f64::NAN), &rules).is_err());
+        assert!(compute(&with_item(-5, 10.0), &rules).is_err());
+        assert!(compute(&[participant("a", f64::INFINITY, 0.0)], &rules).is_err());
+        assert!(compute(&[participant("a", 0.0, f64::NAN)], &rules).is_err());
+        assert!(compute(&with_item(0, 10.0), &rules).is_ok());
+    }
+}
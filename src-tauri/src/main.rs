@@ -11,26 +11,76 @@
 
 mod types;
 mod log_parser;
+mod log_patterns;
 mod file_watcher;
 mod state;
 mod commands;
 mod persistence;
+mod migrations;
+mod valuation;
+mod item_search;
+mod drop_dedup;
+mod event_journal;
+mod live_stats;
+mod obs;
+mod session_replay;
+mod twitch;
+mod unknown_items;
+mod controller_api;
+mod drop_announcer;
+mod drop_sounds;
+mod categories;
+mod items_diff;
+mod proxy;
+mod schema_handshake;
+mod league_archive;
+mod exchange_rates;
+mod bulk_entry;
+mod noise_filter;
+mod value_format;
+mod loot_split;
+mod party;
+mod live_share;
+mod cache_eviction;
+mod parser_bench;
+mod focus_watch;
+mod overlay_mode;
+mod panel_windows;
+mod drop_rates;
+mod gamble;
+mod crafting;
+mod summary_card;
+mod session_summary;
+mod google_sheets;
+mod xlsx_export;
+mod farm_schedule;
+mod game_process;
+mod records;
+mod companion_window;
+mod tray;
+mod updater;
+mod app_logs;
+mod diagnostics;
+mod daily_summary;
 mod supabase_sync;
+mod supabase_client;
+mod price_sync;
+mod price_confidence;
+mod history_sync;
 mod auth;
+mod token_manager;
 mod supabase_defaults;
 
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::atomic::{AtomicU64, Ordering};
 use tauri::{Manager, Emitter};
-use tauri::menu::{Menu, MenuItem};
-use tauri::tray::{TrayIconBuilder, TrayIconEvent, MouseButton, MouseButtonState};
 use log::{info, warn, error, debug, LevelFilter};
 use env_logger::Builder;
 
 use state::AppState;
-use file_watcher::{find_log_path, LogWatcher};
-use types::LogEvent;
+use file_watcher::{find_log_path_for, LogWatcher};
+use types::{FarmBlockReminder, LogEvent, PRIMARY_LOG_SOURCE, extra_log_source_id};
 use log_parser::LogParser;
 
 // Rate limiting для crowd price upload
@@ -38,6 +88,18 @@ use log_parser::LogParser;
 const PRICE_UPLOAD_RATE_LIMIT: u64 = 10;
 const PRICE_UPLOAD_WINDOW_SEC: u64 = 60;
 
+/// Как часто изменённые цены пишутся на диск (плюс запись при выходе)
+const PRICES_FLUSH_INTERVAL_SEC: u64 = 5;
+
+/// Как часто проверять AFK-автопаузу
+const AFK_CHECK_INTERVAL_SEC: u64 = 30;
+
+/// Как часто отмечать в файле сессии, что приложение живо
+const SESSION_HEARTBEAT_SEC: u64 = 60;
+
+/// Шаг кривой стоимости сессии (секунды времени сессии)
+const VALUE_TIMELINE_STEP_SEC: i32 = 60;
+
 struct RateLimiter {
     window_start: AtomicU64,
     count: AtomicU64,
@@ -105,17 +167,37 @@ fn main() {
     // Инициализируем логирование
     Builder::new()
         .filter_level(LevelFilter::Info)
-        .filter_module("tli_companion", LevelFilter::Debug)
+        // Код приложения пропускаем целиком, реальный уровень задаёт app_logs::set_level
+        .filter_module("tli_companion", LevelFilter::Trace)
+        // stderr + файлы с ротацией + буфер последних строк (для диагностики)
+        .target(env_logger::Target::Pipe(Box::new(app_logs::TeeWriter::default())))
         .init();
+    app_logs::set_level(LevelFilter::Debug);
     
     info!("TLI Companion v{} starting...", env!("CARGO_PKG_VERSION"));
     
     tauri::Builder::default()
+        // Должен быть первым: второй экземпляр завершается здесь, не успев запустить
+        // watcher и записать active_session.json, а работающему уходит просьба показать окно
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            info!("Second instance launched (args: {:?}), focusing the running one", argv);
+            tray::show_main_window(app);
+        }))
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
+        .manage(updater::PendingUpdate::default())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(|app, _shortcut, event| {
+                    if event.state() == tauri_plugin_global_shortcut::ShortcutState::Pressed {
+                        overlay_mode::on_hotkey(app);
+                    }
+                })
+                .build(),
+        )
         .setup(|app| {
             info!("Setting up application...");
             
@@ -126,45 +208,203 @@ fn main() {
             let app_state = Arc::new(AppState::new(shared_parser.clone()));
             app.manage(app_state.clone());
             
-            // Создаём меню для tray иконки
-            let show_item = MenuItem::with_id(app, "show", "Показать", true, None::<&str>)?;
-            let quit_item = MenuItem::with_id(app, "quit", "Выход", true, None::<&str>)?;
-            let tray_menu = Menu::with_items(app, &[&show_item, &quit_item])?;
+            // Иконка в трее с быстрыми действиями (старт/пауза/завершение сессии)
+            let tray_handles = tray::build(app.handle())?;
             
-            // Создаём tray иконку с меню
-            let _tray = TrayIconBuilder::new()
-                .icon(app.default_window_icon().unwrap().clone())
-                .menu(&tray_menu)
-                .show_menu_on_left_click(false)
-                .on_menu_event(|app, event| {
-                    match event.id.as_ref() {
-                        "show" => {
-                            if let Some(window) = app.get_webview_window("main") {
-                                let _ = window.show();
-                                let _ = window.set_focus();
-                            }
+            // Получаем handle для отправки событий в frontend
+            let app_handle = app.handle().clone();
+
+            // Секундомер сессии ведёт backend: таймеры webview троттлятся, когда окно в фоне.
+            let state_for_clock = app_state.clone();
+            let handle_for_clock = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut ticker = tokio::time::interval(std::time::Duration::from_secs(1));
+                loop {
+                    ticker.tick().await;
+                    if let Some(duration_sec) = state_for_clock.tick_session_clock().await {
+                        if let Err(e) = panel_windows::broadcast(&handle_for_clock, "session-duration", duration_sec) {
+                            debug!("Failed to emit session-duration event: {}", e);
                         }
-                        "quit" => {
-                            info!("Quit requested from tray menu");
-                            app.exit(0);
+                        // Профит/час меняется со временем и без событий — освежаем раз в 30 сек
+                        if duration_sec % 30 == 0 {
+                            state_for_clock.notify_changed();
+                        }
+                        // На паузе секундомер стоит — точку уже записали
+                        if duration_sec > 0
+                            && duration_sec % VALUE_TIMELINE_STEP_SEC == 0
+                            && !state_for_clock.is_paused().await
+                        {
+                            state_for_clock.record_value_point().await;
                         }
-                        _ => {}
                     }
-                })
-                .on_tray_icon_event(|tray, event| {
-                    // Клик левой кнопкой - показать окно
-                    if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
-                        if let Some(window) = tray.app_handle().get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
+                }
+            });
+            
+            // Push-рассылка статистики и дропа: UI не опрашивает backend по таймеру.
+            // Пачка изменений (например, мердж цен) схлопывается в один снимок.
+            let state_for_broadcast = app_state.clone();
+            let handle_for_broadcast = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut drops_diff = panel_windows::DropsDiff::default();
+                loop {
+                    state_for_broadcast.state_changed.notified().await;
+                    tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+
+                    let stats = state_for_broadcast.get_session_stats().await;
+                    if let Err(e) = panel_windows::broadcast(&handle_for_broadcast, "session-stats-updated", &stats) {
+                        debug!("Failed to emit session-stats-updated event: {}", e);
+                    }
+                    if let Some(alarm) = state_for_broadcast.check_profit_alarm(&stats).await {
+                        if let Err(e) = panel_windows::broadcast(&handle_for_broadcast, "profit-alarm", &alarm) {
+                            debug!("Failed to emit profit-alarm event: {}", e);
                         }
                     }
-                })
-                .build(app)?;
+                    tray_handles.refresh(&stats, state_for_broadcast.is_session_active().await);
+                    // Только изменившиеся строки: на сотнях предметов полный список — большой payload
+                    let delta = drops_diff.update(&state_for_broadcast.get_aggregated_drops().await);
+                    if !delta.is_empty() {
+                        if let Err(e) = panel_windows::broadcast(&handle_for_broadcast, "drops-updated", &delta) {
+                            debug!("Failed to emit drops-updated event: {}", e);
+                        }
+                    }
+                }
+            });
             
-            // Получаем handle для отправки событий в frontend
-            let app_handle = app.handle().clone();
+            // Игра не в фокусе — окна прячутся (если включено в настройках)
+            tauri::async_runtime::spawn(focus_watch::run(app_handle.clone(), app_state.clone()));
+
+            // live_stats.json для сторонних инструментов (если включено в настройках)
+            tauri::async_runtime::spawn(live_stats::run(app_state.clone()));
+
+            // Процесс игры: закрылась — автопауза сессии, запустилась снова — автопауза снимается
+            let state_for_process = app_state.clone();
+            let handle_for_process = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut ticker = tokio::time::interval(std::time::Duration::from_secs(game_process::GAME_PROCESS_POLL_SEC));
+                let mut last_running: Option<bool> = None;
+                let mut missing_checks = 0u32;
+                loop {
+                    ticker.tick().await;
+                    let Ok(Some(seen)) = tokio::task::spawn_blocking(game_process::is_game_running).await else {
+                        continue;
+                    };
+                    missing_checks = if seen { 0 } else { missing_checks + 1 };
+                    let running = seen || missing_checks < game_process::GAME_CLOSED_CONFIRMATIONS;
+                    state_for_process.set_game_running(running);
+                    if last_running == Some(running) {
+                        continue;
+                    }
+                    last_running = Some(running);
+
+                    let auto_pause = state_for_process.settings.read().await.auto_pause_on_game_exit;
+                    let session_paused = if auto_pause {
+                        state_for_process.apply_game_process(running).await
+                    } else {
+                        None
+                    };
+                    let event = game_process::GameProcessEvent { running, session_paused };
+                    if let Err(e) = handle_for_process.emit("game-process", &event) {
+                        debug!("Failed to emit game-process event: {}", e);
+                    }
+                }
+            });
+            
+            // AFK-автопауза: долго нет активности в логе
+            let state_for_afk = app_state.clone();
+            let handle_for_afk = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut ticker = tokio::time::interval(std::time::Duration::from_secs(AFK_CHECK_INTERVAL_SEC));
+                loop {
+                    ticker.tick().await;
+                    if state_for_afk.apply_afk_check().await {
+                        if let Err(e) = tray::emit_session_state(&handle_for_afk, &state_for_afk).await {
+                            debug!("Failed to emit session-state-changed: {}", e);
+                        }
+                    }
+                }
+            });
+
+            // Признак жизни в файле сессии: по нему считается пропуск после сбоя
+            let state_for_heartbeat = app_state.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut ticker = tokio::time::interval(std::time::Duration::from_secs(SESSION_HEARTBEAT_SEC));
+                loop {
+                    ticker.tick().await;
+                    state_for_heartbeat.session_heartbeat().await;
+                }
+            });
+
+            // Расписание блоков фарма: напоминания и автостарт сессии
+            let state_for_schedule = app_state.clone();
+            let handle_for_schedule = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut ticker = tokio::time::interval(std::time::Duration::from_secs(farm_schedule::SCHEDULE_POLL_SEC));
+                loop {
+                    ticker.tick().await;
+                    let actions = {
+                        let _guard = state_for_schedule.farm_blocks_lock.lock().await;
+                        tokio::task::spawn_blocking(|| farm_schedule::tick(chrono::Utc::now()))
+                            .await
+                            .unwrap_or_default()
+                    };
+                    for action in actions {
+                        let reminder = match action {
+                            farm_schedule::ScheduleAction::Remind { block, minutes_until } => {
+                                FarmBlockReminder { block, minutes_until, auto_started: false }
+                            }
+                            farm_schedule::ScheduleAction::Start { block } => {
+                                let auto_started = farm_schedule::auto_start(&state_for_schedule, &block).await;
+                                if auto_started {
+                                    if let Err(e) = tray::emit_session_state(&handle_for_schedule, &state_for_schedule).await {
+                                        debug!("Failed to emit session-state-changed event: {}", e);
+                                    }
+                                }
+                                FarmBlockReminder { block, minutes_until: 0, auto_started }
+                            }
+                            farm_schedule::ScheduleAction::Missed { block } => {
+                                info!("Farm block '{}' was missed", block.name);
+                                continue;
+                            }
+                        };
+                        if let Err(e) = handle_for_schedule.emit("farm-block-reminder", &reminder) {
+                            debug!("Failed to emit farm-block-reminder event: {}", e);
+                        }
+                    }
+                }
+            });
             
+            // Прогресс догонялки лога: пока идёт — раз в полсекунды, и финальный снимок по окончании
+            let state_for_catchup = app_state.clone();
+            let handle_for_catchup = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut ticker = tokio::time::interval(std::time::Duration::from_millis(500));
+                let mut was_catching_up = false;
+                loop {
+                    ticker.tick().await;
+                    let catching_up = state_for_catchup.is_catching_up();
+                    if catching_up || was_catching_up {
+                        let progress = state_for_catchup.get_catchup_progress();
+                        if let Err(e) = handle_for_catchup.emit("log-catchup-progress", &progress) {
+                            debug!("Failed to emit log-catchup-progress event: {}", e);
+                        }
+                    }
+                    was_catching_up = catching_up;
+                }
+            });
+
+            // Статус авторизации пушится в UI: выход из-за протухшего токена виден сразу
+            let mut auth_rx = app_state.auth_events.subscribe();
+            let handle_for_auth = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                while auth_rx.changed().await.is_ok() {
+                    let change = auth_rx.borrow_and_update().clone();
+                    if let Err(e) = handle_for_auth.emit("auth-state-changed", &change) {
+                        debug!("Failed to emit auth-state-changed event: {}", e);
+                    }
+                }
+            });
+            tauri::async_runtime::spawn(token_manager::run(app_state.clone()));
+
             // СИНХРОННАЯ инициализация: загрузка настроек, цен, и восстановление логина.
             // Это должно завершиться ДО того как UI начнёт делать запросы.
             let state_clone = app_state.clone();
@@ -174,18 +414,17 @@ fn main() {
 
                 // Восстанавливаем кэш цен (чтобы цены сохранялись между сессиями и перезапусками).
                 state_clone.load_prices_cache_from_disk().await;
+                // Пользовательские предметы — до загрузки списка с сервера (он может быть недоступен)
+                state_clone.load_custom_items_from_disk().await;
                 
                 // Восстанавливаем активную сессию (если было аварийное закрытие)
                 if state_clone.load_session_from_disk().await {
                     info!("Restored active session from previous run");
                 }
 
-                let http = reqwest::Client::new();
-                let sb_cfg = state_clone.resolve_supabase_config().await;
-
                 // Загружаем список предметов из Supabase
-                if let Some(cfg) = sb_cfg.clone() {
-                    match supabase_sync::fetch_game_items(&http, &cfg).await {
+                if let Some(api) = state_clone.supabase_client().await {
+                    match supabase_sync::fetch_game_items(&api).await {
                         Ok(items) => {
                             info!("Loaded {} game items from Supabase", items.len());
                             state_clone.load_items_cache(items).await;
@@ -194,19 +433,20 @@ fn main() {
                             info!("Failed to load game items from Supabase: {}", e);
                         }
                     }
+                    state_clone.load_categories(&api).await;
                 }
 
                 // Автовосстановление логина: если есть refresh token в keychain — поднимем access token.
-                if let Some(cfg) = sb_cfg.clone() {
+                if let Some(api) = state_clone.supabase_client().await {
                     match crate::auth::load_refresh_token() {
-                        Ok(Some(refresh)) => {
+                        Ok(Some(_)) => {
                             info!("Found refresh token in keychain, attempting auto-restore...");
-                            match crate::auth::refresh_access_token(&http, &cfg, &refresh).await {
-                                Ok(sess) => {
-                                    info!("Auth auto-restore SUCCESS: user_id={:?}", sess.user_id);
-                                    state_clone.set_auth_session(Some(sess)).await;
+                            match state_clone.refresh_auth_session(&api, chrono::Duration::zero()).await {
+                                Ok(_) => {
+                                    info!("Auth auto-restore SUCCESS: user_id={:?}", state_clone.get_auth_user_id().await);
                                 }
                                 Err(e) => {
+                                    // Временную ошибку (нет сети) дожмёт менеджер токенов в фоне
                                     info!("Auth auto-restore FAILED: {}", e);
                                 }
                             }
@@ -223,47 +463,99 @@ fn main() {
                 }
             });
 
+            // Восстанавливаем окно-компаньон, если оно было открыто при выходе
+            let companion_cfg = tauri::async_runtime::block_on(async {
+                app_state.settings.read().await.companion_window.clone()
+            });
+            if companion_cfg.visible {
+                if let Err(e) = companion_window::show(&app_handle, &companion_cfg) {
+                    warn!("Failed to restore companion window: {}", e);
+                }
+            }
+
+            // Локальный API для Stream Deck (если включён)
+            let controller_cfg = tauri::async_runtime::block_on(async {
+                app_state.settings.read().await.controller_api.clone()
+            });
+            if controller_cfg.enabled {
+                let handle_for_controller = app_handle.clone();
+                tauri::async_runtime::spawn(async move {
+                    controller_api::apply(&handle_for_controller, &controller_cfg).await;
+                });
+            }
+
+            // Хоткей «сквозь клики» работает, пока фокус у игры
+            let hotkey = tauri::async_runtime::block_on(async {
+                app_state.settings.read().await.click_through_hotkey.clone()
+            });
+            if let Err(e) = overlay_mode::register_hotkey(&app_handle, None, &hotkey) {
+                warn!("Failed to register click-through hotkey: {}", e);
+            }
+
+            // Восстанавливаем отсоединённые панели
+            let panels = tauri::async_runtime::block_on(async {
+                app_state.settings.read().await.detached_panels.clone()
+            });
+            for panel in &panels {
+                if let Err(e) = panel_windows::open(&app_handle, panel) {
+                    warn!("Failed to restore panel window {}: {}", panel.kind.label(), e);
+                }
+            }
+
             // Запускаем асинхронную инициализацию (фоновые задачи)
             let state_clone = app_state.clone();
             let sb_cfg = tauri::async_runtime::block_on(state_clone.resolve_supabase_config());
             tauri::async_runtime::spawn(async move {
-                let http = reqwest::Client::new();
-
-                // Периодический фоновый рефреш цен (каждую минуту) с поддержкой fallback на предыдущий сезон
+                // Фоновый рефреш цен: планировщик с разбросом интервала и backoff (см. price_sync)
                 if let Some(_cfg) = sb_cfg.clone() {
-                    let state_for_task = state_clone.clone();
-                    let http_for_task = http.clone();
+                    tauri::async_runtime::spawn(price_sync::run(state_clone.clone()));
+                }
+
+                // Совместимость с Supabase: устаревшему клиенту показываем «обновите приложение»
+                if let Some(api) = state_clone.supabase_client().await {
+                    let state_for_handshake = state_clone.clone();
+                    let handle_for_handshake = app_handle.clone();
                     tauri::async_runtime::spawn(async move {
-                        loop {
-                            if let Some(cfg) = state_for_task.resolve_supabase_config().await {
-                                // Используем новую функцию с fallback на предыдущий сезон
-                                match supabase_sync::fetch_prices_with_fallback(&http_for_task, &cfg).await {
-                                    Ok(rows) => {
-                                        state_for_task.merge_prices_with_league(rows).await;
-                                    }
-                                    Err(e) => {
-                                        debug!("Supabase fetch_prices_with_fallback error: {}", e);
-                                        // Fallback на старый метод если новый не работает
-                                        if let Ok(legacy_rows) = supabase_sync::fetch_current_prices(&http_for_task, &cfg).await {
-                                            state_for_task.merge_remote_prices(legacy_rows).await;
-                                        }
-                                    }
+                        if let Some(status) = state_for_handshake.run_schema_handshake(&api).await {
+                            if status.needs_update() {
+                                if let Err(e) = handle_for_handshake.emit("schema-handshake", &status) {
+                                    error!("Failed to emit schema-handshake event: {}", e);
                                 }
                             }
-                            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
                         }
                     });
                 }
 
+                // Отложенная запись кэша цен: изменения копятся и пишутся одной записью
+                let state_for_flush = state_clone.clone();
+                tauri::async_runtime::spawn(async move {
+                    let mut interval = tokio::time::interval(std::time::Duration::from_secs(PRICES_FLUSH_INTERVAL_SEC));
+                    loop {
+                        interval.tick().await;
+                        state_for_flush.flush_prices_cache().await;
+                    }
+                });
+
+                // Ограничение кэшей цен и предметов
+                let state_for_eviction = state_clone.clone();
+                tauri::async_runtime::spawn(async move {
+                    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+                        cache_eviction::CACHE_EVICTION_INTERVAL_SEC,
+                    ));
+                    loop {
+                        interval.tick().await;
+                        state_for_eviction.evict_caches().await;
+                    }
+                });
+
                 // Периодический фоновый рефреш списка предметов (каждые 5 минут)
                 if let Some(_cfg) = sb_cfg.clone() {
                     let state_for_task = state_clone.clone();
-                    let http_for_task = http.clone();
                     tauri::async_runtime::spawn(async move {
                         loop {
                             tokio::time::sleep(std::time::Duration::from_secs(300)).await; // 5 min
-                            if let Some(cfg) = state_for_task.resolve_supabase_config().await {
-                                match supabase_sync::fetch_game_items(&http_for_task, &cfg).await {
+                            if let Some(api) = state_for_task.supabase_client().await {
+                                match supabase_sync::fetch_game_items(&api).await {
                                     Ok(items) => {
                                         debug!("Refreshed game items: {} items", items.len());
                                         state_for_task.load_items_cache(items).await;
@@ -278,6 +570,7 @@ fn main() {
                 }
 
                 // Ищем файл логов: сначала проверяем custom_log_path из настроек
+                let game_client_setting = state_clone.settings.read().await.game_client;
                 let custom_path = state_clone.get_custom_log_path().await;
                 let log_path_option = if let Some(custom) = custom_path {
                     let p = std::path::PathBuf::from(&custom);
@@ -286,95 +579,183 @@ fn main() {
                         Some(p)
                     } else {
                         warn!("Custom log path does not exist: {}, trying auto-detect", custom);
-                        find_log_path()
+                        find_log_path_for(game_client_setting)
                     }
                 } else {
-                    find_log_path()
+                    find_log_path_for(game_client_setting)
                 };
                 
+                // Откуда читать логи при подключении (настройка: с конца или с момента времени)
+                let attach_since = state_clone.take_log_attach_start().await;
+
+                // Все события из логов (основной клиент + дополнительные) идут в один канал
+                let (event_tx, mut rx) = tokio::sync::mpsc::channel::<LogEvent>(1000);
+
                 if let Some(log_path) = log_path_option {
                     let path_str = log_path.to_string_lossy().to_string();
                     state_clone.set_log_path(Some(path_str.clone())).await;
+                    state_clone.register_log_source(PRIMARY_LOG_SOURCE, &path_str).await;
+
+                    // Набор паттернов под клиент (глобальный/китайский) — до первой прочитанной строки
+                    if let Err(e) = state_clone.apply_local_log_patterns().await {
+                        warn!("Failed to apply log patterns: {}", e);
+                    }
                     
                     info!("Starting log watcher for: {}", path_str);
                     
                     // Создаём watcher
-                    let watcher = LogWatcher::new(log_path, shared_parser.clone());
-                    let mut rx = watcher.start();
-                    
-                    // Обрабатываем события из логов
-                    while let Some(event) = rx.recv().await {
-                        match &event {
-                            LogEvent::ItemDrop(drop) => {
-                                state_clone.add_drop(drop).await;
-                                
-                                // Отправляем событие в frontend
-                                info!("Emitting item-drop event: game_id={}", drop.game_id);
-                                if let Err(e) = app_handle.emit("item-drop", drop) {
-                                    error!("Failed to emit item-drop event: {}", e);
-                                }
+                    let watcher = LogWatcher::new(
+                        log_path,
+                        shared_parser.clone(),
+                        state_clone.watcher_config.clone(),
+                        state_clone.catchup_progress.clone(),
+                    )
+                    .with_start_from(attach_since)
+                    .with_noise_filter(state_clone.noise_filter.clone());
+                    watcher.start_into(event_tx.clone());
+                } else {
+                    info!("Log file not found, waiting for manual configuration");
+                    // Отправляем событие что нужно настроить путь
+                    let _ = app_handle.emit("log-path-needed", ());
+                }
+
+                // Дополнительные клиенты (dual-boxing): у каждого свой парсер,
+                // т.к. состояние слотов инвентаря у клиентов независимое.
+                for (idx, extra) in state_clone.get_extra_log_paths().await.iter().enumerate() {
+                    let extra_path = std::path::PathBuf::from(extra);
+                    if !extra_path.exists() {
+                        warn!("Extra log path does not exist, skipping: {}", extra);
+                        continue;
+                    }
+                    let source_id = extra_log_source_id(idx);
+                    state_clone.register_log_source(&source_id, extra).await;
+                    info!("Starting log watcher [{}] for: {}", source_id, extra);
+
+                    let parser = Arc::new(Mutex::new(LogParser::for_source(&source_id)));
+                    state_clone.register_source_parser(&source_id, parser.clone()).await;
+                    let watcher = LogWatcher::new(
+                        extra_path,
+                        parser,
+                        state_clone.watcher_config.clone(),
+                        state_clone.catchup_progress.clone(),
+                    )
+                    .with_start_from(attach_since)
+                    .with_noise_filter(state_clone.noise_filter.clone());
+                    watcher.start_into(event_tx.clone());
+                }
+                // Канал закроется, когда остановятся все watcher'ы
+                drop(event_tx);
+
+                // Свежий набор паттернов из Supabase (после патча игры исправленный regex
+                // приходит без обновления приложения)
+                let state_for_patterns = state_clone.clone();
+                tauri::async_runtime::spawn(async move {
+                    match state_for_patterns.reload_log_patterns().await {
+                        Ok(r) => info!("Log patterns r{} for {:?} ({:?})", r.revision, r.client, r.source),
+                        Err(e) => warn!("Failed to reload log patterns: {}", e),
+                    }
+                });
+                
+                // Обрабатываем события из логов
+                while let Some(event) = rx.recv().await {
+                    state_clone.track_opening(&event).await;
+                    match &event {
+                        LogEvent::ItemDrop(drop) => {
+                            if let Some(quantity) = state_clone.add_drop(drop).await {
+                                state_clone.alert_drop(drop.game_id, quantity, drop.timestamp).await;
+                            }
+                            
+                            // Отправляем событие в frontend
+                            info!("Emitting item-drop event: game_id={}", drop.game_id);
+                            if let Err(e) = panel_windows::broadcast(&app_handle, "item-drop", drop) {
+                                error!("Failed to emit item-drop event: {}", e);
                             }
-                            LogEvent::PriceSearch(price) => {
-                                if let Some(selected) = select_market_price(&price.prices) {
-                                    state_clone.update_price(price.game_id, selected).await;
-
-                                    // Crowd price upload (optional): если пользователь залогинен.
-                                    // Rate limited: максимум 10 запросов в минуту
-                                    if PRICE_RATE_LIMITER.check_and_increment() {
-                                        if let Some(cfg) = sb_cfg.clone() {
-                                            let jwt = state_clone.get_valid_access_token(&http, &cfg).await;
-                                            if let Some(jwt) = jwt {
-                                                let prices = price.prices.clone();
-                                                let game_id = price.game_id;
-                                                let currency_id = price.currency_id;
-                                                let http2 = http.clone();
-                                                tauri::async_runtime::spawn(async move {
-                                                    if let Err(e) = supabase_sync::upsert_market_price(
-                                                        &http2,
-                                                        &cfg,
-                                                        &jwt,
-                                                        game_id,
-                                                        &prices,
-                                                        currency_id,
-                                                    )
-                                                    .await
-                                                    {
-                                                        debug!("Supabase upsert_market_price error: {}", e);
-                                                    }
-                                                });
-                                            } 
-                                        }
-                                    } else {
-                                        debug!("Price upload rate limited, skipping");
+                        }
+                        LogEvent::PriceSearch(price) => {
+                            if let Some(selected) = select_market_price(&price.prices) {
+                                // Цены не в FE пересчитываются по курсу; курс неизвестен — цену не сохраняем
+                                let samples = price_confidence::SampleStats::from_prices(&price.prices);
+                                match state_clone.observe_price_search(price, selected).await {
+                                    Some(fe_price) => {
+                                        state_clone.update_price_with_samples(price.game_id, fe_price, samples).await;
                                     }
+                                    None => debug!(
+                                        "No exchange rate for currency {}, price of {} not stored",
+                                        price.currency_id, price.game_id
+                                    ),
                                 }
-                                
-                                // Отправляем событие в frontend
-                                if let Err(e) = app_handle.emit("price-update", price) {
-                                    error!("Failed to emit price-update event: {}", e);
+
+                                // Продвигаем очередь прайсчека, если чекнули предмет из дропа
+                                if state_clone.complete_pricecheck(price.game_id).await {
+                                    let queue = state_clone.get_pricecheck_queue().await;
+                                    if let Err(e) = panel_windows::broadcast(&app_handle, "pricecheck-queue-updated", &queue) {
+                                        error!("Failed to emit pricecheck-queue-updated event: {}", e);
+                                    }
                                 }
-                            }
-                            LogEvent::MapChange(map) => {
-                                state_clone.handle_map_change(map).await;
-                                
-                                // Отправляем событие в frontend
-                                if let Err(e) = app_handle.emit("map-change", map) {
-                                    error!("Failed to emit map-change event: {}", e);
+
+                                // Crowd price upload (optional): если пользователь залогинен.
+                                // Rate limited: максимум 10 запросов в минуту
+                                if PRICE_RATE_LIMITER.check_and_increment() {
+                                    if let Some(api) = state_clone.supabase_client().await {
+                                        let jwt = state_clone.get_valid_access_token(&api).await;
+                                        if let Some(jwt) = jwt {
+                                            let prices = price.prices.clone();
+                                            let game_id = price.game_id;
+                                            let currency_id = price.currency_id;
+                                            tauri::async_runtime::spawn(async move {
+                                                if let Err(e) = supabase_sync::upsert_market_price(
+                                                    &api,
+                                                    &jwt,
+                                                    game_id,
+                                                    &prices,
+                                                    currency_id,
+                                                )
+                                                .await
+                                                {
+                                                    debug!("Supabase upsert_market_price error: {}", e);
+                                                }
+                                            });
+                                        } 
+                                    }
+                                } else {
+                                    debug!("Price upload rate limited, skipping");
                                 }
                             }
+                            
+                            // Отправляем событие в frontend
+                            if let Err(e) = panel_windows::broadcast(&app_handle, "price-update", price) {
+                                error!("Failed to emit price-update event: {}", e);
+                            }
+                        }
+                        LogEvent::InventorySnapshot(snapshot) => {
+                            state_clone.set_inventory_snapshot(snapshot.clone()).await;
+                            if let Err(e) = app_handle.emit("inventory-snapshot", snapshot.slots.len()) {
+                                error!("Failed to emit inventory-snapshot event: {}", e);
+                            }
                         }
-                        
-                        // Отправляем обновлённую статистику
-                        let stats = state_clone.get_session_stats().await;
-                        info!("Emitting stats-update: items={}, maps={}", stats.total_items, stats.maps_completed);
-                        if let Err(e) = app_handle.emit("stats-update", &stats) {
-                            error!("Failed to emit stats-update event: {}", e);
+                        // Нужны только для открытий контейнеров — статистика не меняется
+                        LogEvent::ItemConsumed(_) | LogEvent::ItemGained(_) => continue,
+                        LogEvent::LogReset { source_id } => {
+                            state_clone.reset_slot_tracker(source_id);
+                            continue;
+                        }
+                        LogEvent::MapChange(map) => {
+                            state_clone.handle_map_change(map).await;
+                            for broken in state_clone.settle_map_runs().await {
+                                if let Err(e) = panel_windows::broadcast(&app_handle, "record-broken", &broken) {
+                                    error!("Failed to emit record-broken event: {}", e);
+                                }
+                            }
+                            
+                            // Отправляем событие в frontend
+                            if let Err(e) = panel_windows::broadcast(&app_handle, "map-change", map) {
+                                error!("Failed to emit map-change event: {}", e);
+                            }
                         }
                     }
-                } else {
-                    info!("Log file not found, waiting for manual configuration");
-                    // Отправляем событие что нужно настроить путь
-                    let _ = app_handle.emit("log-path-needed", ());
+                    
+                    // Статистику рассылает push-рассыльщик: пачка событий схлопывается в один снимок
+                    state_clone.notify_changed();
                 }
             });
             
@@ -383,7 +764,14 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             commands::find_log_file,
             commands::set_log_path,
+            commands::set_extra_log_paths,
+            commands::get_source_sessions,
             commands::start_session,
+            commands::list_session_templates,
+            commands::save_session_template,
+            commands::delete_session_template,
+            commands::start_session_from_template,
+            commands::get_session_goals,
             commands::set_paused,
             commands::update_session_duration,
             commands::add_expense,
@@ -391,20 +779,144 @@ fn main() {
             commands::get_expenses,
             commands::search_items,
             commands::get_cached_prices,
+            commands::get_price_history,
+            commands::get_pricecheck_queue,
+            commands::skip_pricecheck_item,
             commands::add_manual_drop,
             commands::remove_manual_drop,
             commands::get_manual_drops,
             commands::end_session,
             commands::get_session_history,
             commands::delete_session_history,
+            commands::share_session,
+            commands::unshare_session,
+            commands::submit_leaderboard_entry,
+            commands::get_leaderboard,
             commands::get_session_stats,
             commands::get_drops,
             commands::is_session_active,
             commands::get_settings,
             commands::save_settings,
+            commands::generate_diagnostics_bundle,
+            commands::get_parser_stats,
+            commands::reload_parser_patterns,
+            commands::list_log_candidates,
+            commands::split_session_at,
+            commands::merge_history_sessions,
+            commands::get_daily_summary,
+            commands::sync_pending_sessions,
+            commands::sync_history_now,
+            commands::list_accounts,
+            commands::switch_account,
+            commands::auth_sign_in_device,
+            commands::auth_sign_in_with_token,
+            commands::get_price_sync_status,
+            commands::get_net_worth,
+            commands::get_session_currency,
+            commands::get_map_runs,
+            commands::get_records,
+            commands::get_drop_rates,
+            commands::record_gamble_opening,
+            commands::get_gamble_ev,
+            commands::list_recipes,
+            commands::save_recipe,
+            commands::delete_recipe,
+            commands::evaluate_recipe,
+            commands::add_holding,
+            commands::remove_holding,
+            commands::get_portfolio_value,
+            commands::render_summary_card,
+            commands::copy_session_summary,
+            commands::set_google_sheets_credential,
+            commands::get_google_sheets_credential,
+            commands::clear_google_sheets_credential,
+            commands::test_google_sheets,
+            commands::export_session_xlsx,
+            commands::export_history_xlsx,
+            commands::get_value_timeline,
+            commands::get_drops_page,
+            commands::set_profit_alarm,
+            commands::list_farm_blocks,
+            commands::save_farm_block,
+            commands::delete_farm_block,
+            commands::get_catchup_progress,
+            commands::set_session_currency,
+            commands::check_for_updates,
+            commands::install_update,
+            commands::show_companion_window,
+            commands::hide_companion_window,
+            commands::toggle_companion_window,
+            commands::set_log_level,
+            commands::benchmark_parser,
+            commands::get_cache_stats,
+            commands::get_noise_filter_stats,
+            commands::get_startup_warnings,
+            commands::create_custom_item,
+            commands::get_item_price_suggestion,
+            commands::parse_manual_drops_bulk,
+            commands::add_manual_drops_bulk,
+            commands::mark_manual_drop_sold,
+            commands::get_exchange_rates,
+            commands::revalue_session,
+            commands::get_league_status,
+            commands::archive_league_data,
+            commands::get_archived_league,
+            commands::export_session_history,
+            commands::import_session_history,
+            commands::get_schema_handshake,
+            commands::get_connectivity_status,
+            commands::set_proxy_password,
+            commands::test_connection,
+            commands::set_backend_endpoint,
+            commands::start_live_share,
+            commands::stop_live_share,
+            commands::spectate_session,
+            commands::stop_spectating,
+            commands::create_group_session,
+            commands::join_group_session,
+            commands::leave_group_session,
+            commands::get_group_stats,
+            commands::compute_loot_split,
+            commands::get_pause_history,
+            commands::format_values,
+            commands::get_recent_app_logs,
+            commands::set_click_through,
+            commands::toggle_click_through,
+            commands::set_overlay_opacity,
+            commands::open_panel_window,
+            commands::close_panel_window,
+            commands::subscribe_window_events,
+            commands::set_companion_window_geometry,
+            commands::get_watcher_config,
+            commands::set_watcher_config,
             commands::get_item_info,
             commands::load_items_cache,
+            commands::refresh_items_cache,
+            commands::get_categories,
+            commands::list_bundled_sounds,
+            commands::preview_drop_sound,
+            commands::preview_tts,
+            commands::get_controller_api_info,
+            commands::regenerate_controller_api_token,
+            commands::set_obs_password,
+            commands::test_obs_connection,
+            commands::test_obs_action,
+            commands::set_twitch_token,
+            commands::get_twitch_status,
+            commands::toggle_twitch_chat,
+            commands::send_twitch_test_message,
+            commands::write_live_stats,
+            commands::export_session_journal,
+            commands::replay_session_events,
+            commands::get_unknown_items,
+            commands::report_unknown_items,
+            commands::clear_unknown_items,
+            commands::set_leaderboard_opt_in,
             commands::update_item_price,
+            commands::adjust_drop_quantity,
+            commands::get_drop_adjustments,
+            commands::undo_last,
+            commands::redo_last,
             commands::get_log_path,
             commands::check_log_status,
             commands::get_app_version,
@@ -419,12 +931,31 @@ fn main() {
             // При закрытии окна - полностью выходим из приложения
             // Это помогает корректно удалить tray иконку
             if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                // Не предотвращаем закрытие - просто выходим
-                let _ = api;
+                // Окно-компаньон приложение не закрывает — только прячется
+                if window.label() == companion_window::COMPANION_LABEL {
+                    api.prevent_close();
+                    let _ = window.hide();
+                    let state = window.state::<Arc<AppState>>().inner().clone();
+                    tauri::async_runtime::spawn(async move {
+                        state.set_companion_visible(false).await;
+                    });
+                    return;
+                }
+                
+                // Не предотвращаем закрытие - просто выходим (запись состояния — в RunEvent::Exit)
                 info!("Window close requested, exiting application");
                 window.app_handle().exit(0);
             }
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            // Любой путь выхода (окно, трей, app.exit, перезапуск после обновления)
+            // проходит здесь: сохраняем состояние и снимаем флаг работы у сессии
+            if let tauri::RunEvent::Exit = event {
+                let state = app_handle.state::<Arc<AppState>>().inner().clone();
+                tauri::async_runtime::block_on(state.prepare_exit());
+                tauri::async_runtime::block_on(live_share::stop_on_shutdown(&state));
+            }
+        });
 }
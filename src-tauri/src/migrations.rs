@@ -0,0 +1,268 @@
+//! Schema versioning for persisted files
+//!
+//! Every file written by `persistence` is registered here with its current version and
+//! a chain of upgrade steps (`from` -> `from + 1`). Loaders run the raw JSON through
+//! [`migrate`] before deserializing, so format changes are handled in one place instead
+//! of ad-hoc "legacy format" branches.
+//!
+//! Files without a top-level `"version"` field are treated as version 0 (pre-wrapper).
+
+use chrono::Utc;
+use serde_json::{json, Map, Value};
+
+/// One upgrade step: converts a document of version `from` into version `from + 1`
+pub struct Migration {
+    pub from: u32,
+    pub upgrade: fn(Value) -> Result<Value, String>,
+}
+
+/// Persisted file schema: current version plus the upgrade chain leading to it
+pub struct FileSchema {
+    pub name: &'static str,
+    pub current_version: u32,
+    pub migrations: &'static [Migration],
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Registry
+// ─────────────────────────────────────────────────────────────────────────────
+
+pub const PRICES_CACHE: FileSchema = FileSchema {
+    name: "prices_cache",
+    current_version: 2,
+    migrations: &[
+        Migration { from: 0, upgrade: prices_v0_to_v1 },
+        Migration { from: 1, upgrade: prices_v1_to_v2 },
+    ],
+};
+
+pub const SETTINGS: FileSchema = FileSchema {
+    name: "settings",
+    current_version: 1,
+    migrations: &[Migration { from: 0, upgrade: settings_v0_to_v1 }],
+};
+
+/// Active session is stored as a bare `FarmSessionState` (new fields use serde defaults)
+pub const ACTIVE_SESSION: FileSchema = FileSchema {
+    name: "active_session",
+    current_version: 0,
+    migrations: &[],
+};
+
+pub const SESSION_TEMPLATES: FileSchema = FileSchema {
+    name: "session_templates",
+    current_version: 1,
+    migrations: &[],
+};
+
+pub const FARM_BLOCKS: FileSchema = FileSchema {
+    name: "farm_blocks",
+    current_version: 1,
+    migrations: &[],
+};
+
+/// Log patterns override is stored bare: `revision` is the definitions' own counter,
+/// the file layout itself has not changed yet
+pub const LOG_PATTERNS: FileSchema = FileSchema {
+    name: "log_patterns",
+    current_version: 0,
+    migrations: &[],
+};
+
+pub const SESSION_HISTORY: FileSchema = FileSchema {
+    name: "session_history",
+    current_version: 1,
+    migrations: &[],
+};
+
+pub const LINKED_ACCOUNTS: FileSchema = FileSchema {
+    name: "linked_accounts",
+    current_version: 1,
+    migrations: &[],
+};
+
+pub const INVENTORY_SNAPSHOT: FileSchema = FileSchema {
+    name: "inventory_snapshot",
+    current_version: 1,
+    migrations: &[],
+};
+
+pub const PERSONAL_RECORDS: FileSchema = FileSchema {
+    name: "records",
+    current_version: 1,
+    migrations: &[],
+};
+
+pub const PORTFOLIO: FileSchema = FileSchema {
+    name: "portfolio",
+    current_version: 1,
+    migrations: &[],
+};
+
+pub const RECIPES: FileSchema = FileSchema {
+    name: "recipes",
+    current_version: 1,
+    migrations: &[],
+};
+
+pub const GAMBLE_OPENINGS: FileSchema = FileSchema {
+    name: "gamble_openings",
+    current_version: 1,
+    migrations: &[],
+};
+
+pub const MAP_LOG: FileSchema = FileSchema {
+    name: "map_log",
+    current_version: 1,
+    migrations: &[],
+};
+
+pub const CUSTOM_ITEMS: FileSchema = FileSchema {
+    name: "custom_items",
+    current_version: 1,
+    migrations: &[],
+};
+
+pub const MANUAL_PRICES: FileSchema = FileSchema {
+    name: "manual_prices",
+    current_version: 1,
+    migrations: &[],
+};
+
+pub const EXCHANGE_RATES: FileSchema = FileSchema {
+    name: "exchange_rates",
+    current_version: 1,
+    migrations: &[],
+};
+
+pub const SHEETS_QUEUE: FileSchema = FileSchema {
+    name: "google_sheets_queue",
+    current_version: 1,
+    migrations: &[],
+};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Migration runner
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Version stored in a document (0 if there is no `"version"` field)
+pub fn document_version(value: &Value) -> u32 {
+    value
+        .get("version")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+        .unwrap_or(0)
+}
+
+/// Upgrade a raw document to `schema.current_version`
+pub fn migrate(schema: &FileSchema, mut value: Value) -> Result<Value, String> {
+    let mut version = document_version(&value);
+    if version > schema.current_version {
+        return Err(format!(
+            "{}: file version {} is newer than supported {}",
+            schema.name, version, schema.current_version
+        ));
+    }
+
+    while version < schema.current_version {
+        let step = schema
+            .migrations
+            .iter()
+            .find(|m| m.from == version)
+            .ok_or_else(|| format!("{}: no migration from version {}", schema.name, version))?;
+        value = (step.upgrade)(value)
+            .map_err(|e| format!("{}: migration from v{} failed: {}", schema.name, version, e))?;
+        version += 1;
+        log::info!("Migrated {} to version {}", schema.name, version);
+    }
+
+    Ok(value)
+}
+
+fn set_version(value: &mut Value, version: u32) -> Result<(), String> {
+    let obj = value.as_object_mut().ok_or("expected JSON object")?;
+    obj.insert("version".to_string(), json!(version));
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Upgrade steps
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// v0: bare map `game_id -> price` -> v1: `{ version, prices: { game_id -> price } }`
+fn prices_v0_to_v1(value: Value) -> Result<Value, String> {
+    if !value.is_object() {
+        return Err("expected JSON object".to_string());
+    }
+    Ok(json!({ "version": 1, "prices": value }))
+}
+
+/// v1: plain prices -> v2: entries with timestamp and league flag (invalid prices dropped)
+fn prices_v1_to_v2(mut value: Value) -> Result<Value, String> {
+    let now = Utc::now().to_rfc3339();
+    let prices = value
+        .get("prices")
+        .and_then(|p| p.as_object())
+        .ok_or("missing prices")?;
+
+    let upgraded: Map<String, Value> = prices
+        .iter()
+        .filter_map(|(id, p)| {
+            // Some v1 files were written with v2-shaped entries already
+            if p.is_object() {
+                return Some((id.clone(), p.clone()));
+            }
+            let price = p.as_f64().filter(|p| p.is_finite() && *p > 0.0)?;
+            Some((
+                id.clone(),
+                json!({ "price": price, "updated_at": now, "is_current_league": true }),
+            ))
+        })
+        .collect();
+
+    value["prices"] = Value::Object(upgraded);
+    set_version(&mut value, 2)?;
+    Ok(value)
+}
+
+/// v0: bare `AppSettings` -> v1: `{ version, settings }`
+fn settings_v0_to_v1(value: Value) -> Result<Value, String> {
+    if !value.is_object() {
+        return Err("expected JSON object".to_string());
+    }
+    Ok(json!({ "version": 1, "settings": value }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_prices_upgrade_through_whole_chain() {
+        let legacy = json!({ "100": 12.5, "200": -1.0 });
+        let migrated = migrate(&PRICES_CACHE, legacy).unwrap();
+
+        assert_eq!(document_version(&migrated), 2);
+        assert_eq!(migrated["prices"]["100"]["price"], json!(12.5));
+        assert_eq!(migrated["prices"]["100"]["is_current_league"], json!(true));
+        assert!(migrated["prices"].get("200").is_none());
+    }
+
+    #[test]
+    fn current_version_is_left_untouched() {
+        let doc = json!({ "version": 1, "settings": { "overlay_opacity": 0.5 } });
+        assert_eq!(migrate(&SETTINGS, doc.clone()).unwrap(), doc);
+
+        let legacy = json!({ "overlay_opacity": 0.5 });
+        assert_eq!(migrate(&SETTINGS, legacy).unwrap(), doc);
+    }
+
+    #[test]
+    fn newer_or_unknown_versions_are_rejected() {
+        let future = json!({ "version": 3, "prices": {} });
+        assert!(migrate(&PRICES_CACHE, future).is_err());
+
+        let orphan = json!({ "sessions": [] });
+        assert!(migrate(&SESSION_HISTORY, orphan).is_err());
+    }
+}
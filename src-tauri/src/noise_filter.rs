@@ -0,0 +1,215 @@
+//! Отсев «шумных» строк лога до парсера
+//!
+//! Некоторые отладочные настройки игры заваливают UE_game.log однотипными строками.
+//! Строки, совпавшие с пользовательскими паттернами, отбрасываются прямо в читателе
+//! watcher'а и не доходят ни до канала, ни до LogParser. Строки, которые нужны парсеру
+//! (инвентарь, подборы, цены, смена карты), пропускаются при любых паттернах.
+//! Читатель держит свою копию собранного набора и берёт новую только после смены паттернов.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::{info, warn};
+use regex::RegexSet;
+use serde::Serialize;
+
+/// Маркеры строк, которые разбирает LogParser (см. resources/log_patterns*.json)
+const PARSER_MARKERS: [&str; 8] = [
+    "BagMgr@",
+    "ItemChange@",
+    "XchgSearchPrice",
+    "RecvMessage End",
+    "+refer",
+    "+unitPrices",
+    "+currency",
+    "_UpdateGameEnd",
+];
+
+/// Строка нужна парсеру. Продолжения блока цен начинаются с `|` (price_unit_cont)
+fn is_parser_line(line: &str) -> bool {
+    line.trim_start().starts_with('|') || PARSER_MARKERS.iter().any(|marker| line.contains(marker))
+}
+
+/// Счётчик отброшенных строк по одному паттерну
+#[derive(Debug, Clone, Serialize)]
+pub struct NoisePatternStats {
+    pub pattern: String,
+    pub dropped: u64,
+}
+
+/// Статистика фильтра с момента последней смены набора паттернов
+#[derive(Debug, Clone, Serialize)]
+pub struct NoiseFilterStats {
+    /// Строк проверено (пока фильтр пуст, не считается)
+    pub lines_checked: u64,
+    /// Строк отброшено
+    pub lines_dropped: u64,
+    pub patterns: Vec<NoisePatternStats>,
+    /// Паттерны, которые не компилируются (не применяются)
+    pub invalid: Vec<String>,
+}
+
+/// Собранный набор паттернов со счётчиками (неизменяемый, кроме атомарных счётчиков)
+#[derive(Default)]
+struct FilterSnapshot {
+    set: Option<RegexSet>,
+    patterns: Vec<String>,
+    invalid: Vec<String>,
+    hits: Vec<AtomicU64>,
+    lines_checked: AtomicU64,
+    lines_dropped: AtomicU64,
+}
+
+impl FilterSnapshot {
+    fn is_noise(&self, line: &str) -> bool {
+        let Some(set) = &self.set else {
+            return false;
+        };
+        self.lines_checked.fetch_add(1, Ordering::Relaxed);
+        if is_parser_line(line) {
+            return false;
+        }
+        let matched = set.matches(line);
+        if !matched.matched_any() {
+            return false;
+        }
+        for idx in matched.iter() {
+            self.hits[idx].fetch_add(1, Ordering::Relaxed);
+        }
+        self.lines_dropped.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+}
+
+/// Фильтр шумных строк (общий для всех watcher'ов, набор меняется на лету)
+#[derive(Default)]
+pub struct NoiseFilter {
+    current: Mutex<Arc<FilterSnapshot>>,
+    /// Растёт при каждой смене набора — по нему читатели понимают, что копия устарела
+    generation: AtomicU64,
+}
+
+impl NoiseFilter {
+    /// Применить набор паттернов. Счётчики сбрасываются, только если набор изменился
+    pub fn configure(&self, patterns: &[String]) {
+        let mut valid = Vec::new();
+        let mut invalid = Vec::new();
+        for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            match regex::Regex::new(pattern) {
+                Ok(_) => valid.push(pattern.to_string()),
+                Err(e) => {
+                    warn!("Noise pattern '{}' is invalid: {}", pattern, e);
+                    invalid.push(pattern.to_string());
+                }
+            }
+        }
+
+        let mut current = self.current.lock().unwrap();
+        if current.patterns == valid && current.invalid == invalid {
+            return;
+        }
+        // Каждый паттерн уже проверен по отдельности — набор из них тоже соберётся
+        let set = (!valid.is_empty()).then(|| RegexSet::new(&valid).ok()).flatten();
+        info!("Log noise filter: {} pattern(s), {} invalid", valid.len(), invalid.len());
+        *current = Arc::new(FilterSnapshot {
+            set,
+            hits: valid.iter().map(|_| AtomicU64::new(0)).collect(),
+            patterns: valid,
+            invalid,
+            ..FilterSnapshot::default()
+        });
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    /// Копия для потока-читателя
+    pub fn reader(self: &Arc<Self>) -> NoiseReader {
+        NoiseReader {
+            filter: self.clone(),
+            generation: self.generation.load(Ordering::Acquire),
+            snapshot: self.current.lock().unwrap().clone(),
+        }
+    }
+
+    pub fn stats(&self) -> NoiseFilterStats {
+        let current = self.current.lock().unwrap().clone();
+        NoiseFilterStats {
+            lines_checked: current.lines_checked.load(Ordering::Relaxed),
+            lines_dropped: current.lines_dropped.load(Ordering::Relaxed),
+            patterns: current
+                .patterns
+                .iter()
+                .zip(&current.hits)
+                .map(|(pattern, dropped)| NoisePatternStats {
+                    pattern: pattern.clone(),
+                    dropped: dropped.load(Ordering::Relaxed),
+                })
+                .collect(),
+            invalid: current.invalid.clone(),
+        }
+    }
+}
+
+/// Фильтр в потоке-читателе: на строку — одно атомарное чтение поколения, без блокировки
+pub struct NoiseReader {
+    filter: Arc<NoiseFilter>,
+    generation: u64,
+    snapshot: Arc<FilterSnapshot>,
+}
+
+impl NoiseReader {
+    /// Строка — шум (её надо отбросить до парсера)
+    pub fn is_noise(&mut self, line: &str) -> bool {
+        let generation = self.filter.generation.load(Ordering::Acquire);
+        if generation != self.generation {
+            self.snapshot = self.filter.current.lock().unwrap().clone();
+            self.generation = generation;
+        }
+        self.snapshot.is_noise(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_matching_lines_and_counts_per_pattern() {
+        let filter = Arc::new(NoiseFilter::default());
+        let mut reader = filter.reader();
+        assert!(!reader.is_noise("LogStreaming: Display: anything"));
+
+        // Читатель подхватывает новый набор без пересоздания
+        filter.configure(&["^LogStreaming".to_string(), "RHI".to_string(), "([".to_string()]);
+        assert!(reader.is_noise("LogStreaming: Display: chunk loaded"));
+        assert!(reader.is_noise("LogRHI: flush"));
+        assert!(!reader.is_noise("BagMgr@:Modfy BagItem PageId = 102 SlotId = 1 ConfigBaseId = 100200 Num = 5"));
+
+        let stats = filter.stats();
+        assert_eq!((stats.lines_checked, stats.lines_dropped), (3, 2));
+        assert_eq!(stats.patterns[0].dropped, 1);
+        assert_eq!(stats.patterns[1].dropped, 1);
+        assert_eq!(stats.invalid, vec!["([".to_string()]);
+
+        // Тот же набор — счётчики сохраняются
+        filter.configure(&["^LogStreaming".to_string(), "RHI".to_string(), "([".to_string()]);
+        assert_eq!(filter.stats().lines_dropped, 2);
+    }
+
+    #[test]
+    fn parser_lines_pass_any_pattern() {
+        let filter = Arc::new(NoiseFilter::default());
+        filter.configure(&["GameLog".to_string(), "^\\s*\\|".to_string()]);
+        let mut reader = filter.reader();
+        let prefix = "[2026.01.12-11.34.07:799][980]GameLog: Display: [Game] ";
+        for line in [
+            "BagMgr@:Modfy BagItem PageId = 102 SlotId = 1 ConfigBaseId = 100200 Num = 5",
+            "ItemChange@ ProtoName=PickItems start",
+            "----Socket RecvMessage STT----XchgSearchPrice----SynId = 12",
+            "PageApplyBase@ _UpdateGameEnd: NextSceneName = World'/Game/Art/Maps/01SD/X'",
+        ] {
+            assert!(!reader.is_noise(&format!("{}{}", prefix, line)), "{}", line);
+        }
+        assert!(!reader.is_noise("  | | +2 [150.5]"));
+        assert!(reader.is_noise(&format!("{}LogStreaming chunk", prefix)));
+    }
+}
@@ -0,0 +1,329 @@
+//! Автоматизация OBS через obs-websocket (v5)
+//!
+//! Правила из настроек связывают события сессии (старт, конец, крупный дроп) с
+//! действиями в OBS: переключить сцену или показать/скрыть/переключить источник.
+//! На каждое срабатывание открывается короткое соединение: Hello → Identify →
+//! запросы → закрытие. Пароль obs-websocket хранится в keychain.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, info, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::drop_announcer::RateLimiter;
+use crate::types::{ObsAction, ObsRule, ObsSettings, ObsTrigger};
+
+const KEYRING_USERNAME: &str = "obs-websocket-password";
+
+/// Версия RPC obs-websocket 5.x
+const RPC_VERSION: u64 = 1;
+/// Подключение и каждый ответ — не дольше
+const OBS_TIMEOUT: Duration = Duration::from_secs(5);
+/// Одно событие срабатывает не чаще (серия крупных дропов не дёргает сцены)
+const MIN_TRIGGER_INTERVAL: Duration = Duration::from_secs(10);
+
+const OP_HELLO: u64 = 0;
+const OP_IDENTIFY: u64 = 1;
+const OP_IDENTIFIED: u64 = 2;
+const OP_REQUEST: u64 = 6;
+const OP_REQUEST_RESPONSE: u64 = 7;
+
+/// Сохранить пароль obs-websocket (пустой — удалить)
+pub fn store_password(password: &str) -> Result<(), String> {
+    if password.is_empty() {
+        return match crate::auth::keyring_entry(KEYRING_USERNAME)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        };
+    }
+    crate::auth::keyring_entry(KEYRING_USERNAME)?
+        .set_password(password)
+        .map_err(|e| format!("Failed to store OBS password in keychain: {}", e))
+}
+
+fn load_password() -> Option<String> {
+    match crate::auth::keyring_entry(KEYRING_USERNAME).and_then(|e| e.get_password().map_err(|e| e.to_string())) {
+        Ok(password) if !password.is_empty() => Some(password),
+        _ => None,
+    }
+}
+
+/// Строка аутентификации: base64(sha256(base64(sha256(пароль + salt)) + challenge))
+fn auth_response(password: &str, salt: &str, challenge: &str) -> String {
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let secret = b64.encode(Sha256::digest(format!("{}{}", password, salt)));
+    b64.encode(Sha256::digest(format!("{}{}", secret, challenge)))
+}
+
+/// Действия правил, сработавших на событие
+pub fn matching_actions(settings: &ObsSettings, trigger: ObsTrigger, value: Option<f64>) -> Vec<ObsAction> {
+    if !settings.enabled {
+        return Vec::new();
+    }
+    settings
+        .rules
+        .iter()
+        .filter(|rule| rule.enabled && rule.trigger == trigger)
+        .filter(|rule| match (rule.min_value, value) {
+            (Some(min), Some(value)) => value >= min,
+            (Some(_), None) => false,
+            (None, _) => true,
+        })
+        .map(|rule: &ObsRule| rule.action.clone())
+        .collect()
+}
+
+/// Ограничение частоты срабатываний — отдельно для каждого события
+#[derive(Default)]
+pub struct TriggerLimiter {
+    limiters: Mutex<HashMap<ObsTrigger, RateLimiter>>,
+}
+
+impl TriggerLimiter {
+    pub fn allow(&self, trigger: ObsTrigger, now: Instant) -> bool {
+        self.limiters.lock().unwrap().entry(trigger).or_default().allow(now, MIN_TRIGGER_INTERVAL)
+    }
+}
+
+/// Состояние OBS для настроек (проверка соединения)
+#[derive(Debug, Clone, Serialize)]
+pub struct ObsStatus {
+    pub obs_version: String,
+    pub websocket_version: String,
+    pub current_scene: Option<String>,
+    pub scenes: Vec<String>,
+}
+
+/// Соединение с OBS после Identify
+struct ObsConnection {
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    next_id: u64,
+}
+
+impl ObsConnection {
+    async fn connect(settings: &ObsSettings) -> Result<Self, String> {
+        let url = format!("ws://{}:{}", settings.host.trim(), settings.port);
+        let (ws, _) = tokio::time::timeout(OBS_TIMEOUT, tokio_tungstenite::connect_async(url.as_str()))
+            .await
+            .map_err(|_| format!("OBS connection timed out ({})", url))?
+            .map_err(|e| format!("Cannot connect to OBS at {}: {}", url, e))?;
+        let mut conn = Self { ws, next_id: 0 };
+
+        let hello = conn.receive(OP_HELLO).await?;
+        let mut identify = json!({ "rpcVersion": RPC_VERSION, "eventSubscriptions": 0 });
+        if let Some(auth) = hello.get("authentication") {
+            let password = load_password().ok_or("OBS requires a password")?;
+            identify["authentication"] = json!(auth_response(
+                &password,
+                auth["salt"].as_str().unwrap_or_default(),
+                auth["challenge"].as_str().unwrap_or_default(),
+            ));
+        }
+        conn.send(OP_IDENTIFY, identify).await?;
+        conn.receive(OP_IDENTIFIED).await.map_err(|e| {
+            if e.contains("closed") { "OBS rejected the password".to_string() } else { e }
+        })?;
+        Ok(conn)
+    }
+
+    async fn send(&mut self, op: u64, d: Value) -> Result<(), String> {
+        let message = json!({ "op": op, "d": d }).to_string();
+        self.ws.send(Message::Text(message)).await.map_err(|e| e.to_string())
+    }
+
+    /// Дождаться сообщения с нужным op (прочие — пропустить)
+    async fn receive(&mut self, op: u64) -> Result<Value, String> {
+        loop {
+            let message = tokio::time::timeout(OBS_TIMEOUT, self.ws.next())
+                .await
+                .map_err(|_| "OBS did not respond".to_string())?
+                .ok_or("OBS connection closed")?
+                .map_err(|e| e.to_string())?;
+            let text = match message {
+                Message::Text(text) => text,
+                Message::Close(frame) => {
+                    return Err(format!("OBS connection closed: {}", frame.map(|f| f.reason.to_string()).unwrap_or_default()))
+                }
+                _ => continue,
+            };
+            let value: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+            if value["op"].as_u64() == Some(op) {
+                return Ok(value["d"].clone());
+            }
+        }
+    }
+
+    async fn request(&mut self, request_type: &str, data: Value) -> Result<Value, String> {
+        self.next_id += 1;
+        let request_id = self.next_id.to_string();
+        self.send(OP_REQUEST, json!({ "requestType": request_type, "requestId": request_id, "requestData": data }))
+            .await?;
+        loop {
+            let response = self.receive(OP_REQUEST_RESPONSE).await?;
+            if response["requestId"].as_str() != Some(request_id.as_str()) {
+                continue;
+            }
+            let status = &response["requestStatus"];
+            if status["result"].as_bool() != Some(true) {
+                return Err(format!(
+                    "OBS {} failed: {}",
+                    request_type,
+                    status["comment"].as_str().unwrap_or("unknown error")
+                ));
+            }
+            return Ok(response.get("responseData").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    async fn run(&mut self, action: &ObsAction) -> Result<(), String> {
+        match action {
+            ObsAction::SwitchScene { scene } => {
+                self.request("SetCurrentProgramScene", json!({ "sceneName": scene })).await?;
+            }
+            ObsAction::SetSourceVisible { scene, source, visible } => {
+                let item = self
+                    .request("GetSceneItemId", json!({ "sceneName": scene, "sourceName": source }))
+                    .await?;
+                let item_id = item["sceneItemId"].as_i64().ok_or("OBS returned no scene item id")?;
+                let enabled = match visible {
+                    Some(visible) => *visible,
+                    None => {
+                        let current = self
+                            .request("GetSceneItemEnabled", json!({ "sceneName": scene, "sceneItemId": item_id }))
+                            .await?;
+                        !current["sceneItemEnabled"].as_bool().unwrap_or(false)
+                    }
+                };
+                self.request(
+                    "SetSceneItemEnabled",
+                    json!({ "sceneName": scene, "sceneItemId": item_id, "sceneItemEnabled": enabled }),
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn close(mut self) {
+        let _ = self.ws.close(None).await;
+    }
+}
+
+/// Выполнить действия по порядку (ошибка одного не отменяет остальные)
+pub async fn run_actions(settings: ObsSettings, actions: Vec<ObsAction>) {
+    if actions.is_empty() {
+        return;
+    }
+    let mut conn = match ObsConnection::connect(&settings).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("OBS automation skipped: {}", e);
+            return;
+        }
+    };
+    for action in &actions {
+        match conn.run(action).await {
+            Ok(()) => debug!("OBS action done: {:?}", action),
+            Err(e) => warn!("OBS action {:?} failed: {}", action, e),
+        }
+    }
+    conn.close().await;
+}
+
+/// Выполнить одно действие (кнопка «Проверить» у правила)
+pub async fn test_action(settings: &ObsSettings, action: &ObsAction) -> Result<(), String> {
+    let mut conn = ObsConnection::connect(settings).await?;
+    let result = conn.run(action).await;
+    conn.close().await;
+    result
+}
+
+/// Проверить соединение: версии и список сцен
+pub async fn test_connection(settings: &ObsSettings) -> Result<ObsStatus, String> {
+    let mut conn = ObsConnection::connect(settings).await?;
+    let version = conn.request("GetVersion", json!({})).await?;
+    let scenes = conn.request("GetSceneList", json!({})).await?;
+    conn.close().await;
+    let status = ObsStatus {
+        obs_version: version["obsVersion"].as_str().unwrap_or_default().to_string(),
+        websocket_version: version["obsWebSocketVersion"].as_str().unwrap_or_default().to_string(),
+        current_scene: scenes["currentProgramSceneName"].as_str().map(str::to_string),
+        scenes: scenes["scenes"]
+            .as_array()
+            .map(|list| list.iter().filter_map(|s| s["sceneName"].as_str().map(str::to_string)).rev().collect())
+            .unwrap_or_default(),
+    };
+    info!("Connected to OBS {} (obs-websocket {})", status.obs_version, status.websocket_version);
+    Ok(status)
+}
+
+/// Действие без сцены/источника бессмысленно — отсекаем до подключения
+pub fn validate_action(action: &ObsAction) -> Result<(), String> {
+    let missing = match action {
+        ObsAction::SwitchScene { scene } => scene.trim().is_empty(),
+        ObsAction::SetSourceVisible { scene, source, .. } => scene.trim().is_empty() || source.trim().is_empty(),
+    };
+    if missing {
+        return Err("Scene and source names are required".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(trigger: ObsTrigger, min_value: Option<f64>, scene: &str) -> ObsRule {
+        ObsRule { enabled: true, trigger, min_value, action: ObsAction::SwitchScene { scene: scene.into() } }
+    }
+
+    #[test]
+    fn selects_rules_for_trigger_and_value() {
+        let settings = ObsSettings {
+            enabled: true,
+            rules: vec![
+                rule(ObsTrigger::SessionStart, None, "Farming"),
+                rule(ObsTrigger::BigDrop, Some(5000.0), "Highlight"),
+                rule(ObsTrigger::SessionEnd, None, "Break"),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(matching_actions(&settings, ObsTrigger::SessionStart, None).len(), 1);
+        assert!(matching_actions(&settings, ObsTrigger::BigDrop, Some(100.0)).is_empty());
+        assert_eq!(
+            matching_actions(&settings, ObsTrigger::BigDrop, Some(9000.0)),
+            vec![ObsAction::SwitchScene { scene: "Highlight".into() }]
+        );
+        assert!(matching_actions(&ObsSettings { enabled: false, ..settings }, ObsTrigger::SessionEnd, None).is_empty());
+
+        // Пример из спецификации obs-websocket v5 (раздел Creating an authentication string)
+        assert_eq!(
+            auth_response(
+                "supersecretpassword",
+                "lM1GncleQOaCu9lT1yeUZhFYnqhsLLP1G5lAGo3ixaI=",
+                "+IxH4CnCiqpX1rM9scsNynZzbOe4KhDeYcTNS3PDaeY=",
+            ),
+            "1Ct943GAT+6YQUUX47Ia/ncufilbe6+oD6lY+5kaCu4="
+        );
+        assert!(validate_action(&ObsAction::SwitchScene { scene: " ".into() }).is_err());
+    }
+
+    #[test]
+    fn limits_each_trigger_separately() {
+        let limiter = TriggerLimiter::default();
+        let t0 = Instant::now();
+        assert!(limiter.allow(ObsTrigger::BigDrop, t0));
+        assert!(!limiter.allow(ObsTrigger::BigDrop, t0 + Duration::from_secs(3)));
+        assert!(limiter.allow(ObsTrigger::SessionEnd, t0 + Duration::from_secs(3)));
+        assert!(limiter.allow(ObsTrigger::BigDrop, t0 + MIN_TRIGGER_INTERVAL));
+    }
+}
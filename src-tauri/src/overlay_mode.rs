@@ -0,0 +1,96 @@
+//! Режим «сквозь клики» и прозрачность главного окна
+//!
+//! Окно в режиме click-through не получает мышь — клики уходят в игру. Выключить режим
+//! из самого окна нельзя, поэтому переключение живёт на стороне Rust: команда и
+//! глобальный хоткей (AppSettings.click_through_hotkey), который срабатывает, пока фокус
+//! у игры. Режим не сохраняется — после перезапуска окно снова кликабельно.
+//! Прозрачность применяется фронтендом (CSS) по событию overlay-mode-changed.
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use log::info;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+
+use crate::state::AppState;
+
+/// Label главного окна
+const MAIN_LABEL: &str = "main";
+
+/// Пределы прозрачности главного окна
+const MIN_OPACITY: f64 = 0.5;
+const MAX_OPACITY: f64 = 1.0;
+
+/// Текущее состояние оверлея (payload события overlay-mode-changed)
+#[derive(Debug, Clone, Serialize)]
+pub struct OverlayMode {
+    pub click_through: bool,
+    pub opacity: f64,
+}
+
+async fn current(state: &AppState) -> OverlayMode {
+    OverlayMode {
+        click_through: state.click_through.load(Ordering::Relaxed),
+        opacity: state.settings.read().await.opacity,
+    }
+}
+
+/// Включить/выключить click-through главного окна
+pub async fn set_click_through(app: &AppHandle, state: &AppState, enabled: bool) -> tauri::Result<OverlayMode> {
+    if let Some(window) = app.get_webview_window(MAIN_LABEL) {
+        window.set_ignore_cursor_events(enabled)?;
+    }
+    state.click_through.store(enabled, Ordering::Relaxed);
+    info!("Overlay click-through {}", if enabled { "enabled" } else { "disabled" });
+
+    let mode = current(state).await;
+    app.emit("overlay-mode-changed", &mode)?;
+    Ok(mode)
+}
+
+/// Задать прозрачность главного окна (сохраняется в настройки)
+pub async fn set_opacity(app: &AppHandle, state: &AppState, opacity: f64) -> tauri::Result<OverlayMode> {
+    state.settings.write().await.opacity = opacity.clamp(MIN_OPACITY, MAX_OPACITY);
+    state.save_settings_to_disk().await;
+
+    let mode = current(state).await;
+    app.emit("overlay-mode-changed", &mode)?;
+    Ok(mode)
+}
+
+/// Проверить строку хоткея ("CommandOrControl+Shift+X")
+pub fn parse_hotkey(hotkey: &str) -> Result<Shortcut, String> {
+    hotkey.parse::<Shortcut>().map_err(|e| format!("Invalid hotkey '{}': {}", hotkey, e))
+}
+
+/// Зарегистрировать хоткей переключения click-through. Предыдущий (`previous`) снимается
+/// только после успешной регистрации нового — при ошибке остаётся рабочий старый
+pub fn register_hotkey(app: &AppHandle, previous: Option<&str>, hotkey: &str) -> Result<(), String> {
+    let shortcut = parse_hotkey(hotkey)?;
+    let shortcuts = app.global_shortcut();
+    if shortcuts.is_registered(shortcut) {
+        return Ok(());
+    }
+    shortcuts.register(shortcut).map_err(|e| e.to_string())?;
+    info!("Click-through hotkey registered: {}", hotkey);
+    if let Some(old) = previous.and_then(|p| parse_hotkey(p).ok()).filter(|old| *old != shortcut) {
+        if let Err(e) = shortcuts.unregister(old) {
+            log::warn!("Failed to unregister previous click-through hotkey: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Обработчик нажатия глобального хоткея
+pub fn on_hotkey(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<Arc<AppState>>().inner().clone();
+        let enabled = !state.click_through.load(Ordering::Relaxed);
+        if let Err(e) = set_click_through(&app, &state, enabled).await {
+            log::warn!("Failed to toggle click-through: {}", e);
+        }
+    });
+}
@@ -0,0 +1,273 @@
+//! Отсоединяемые панели: статистика, список дропа, лента лута
+//!
+//! Каждая панель — отдельное Tauri-окно `panel-<kind>` с тем же фронтендом
+//! (`index.html?view=panel&panel=<kind>`). Окно сообщает, какие события ему нужны
+//! (команда subscribe_window_events), и рассылка состояния идёт через `broadcast`:
+//! окну без подписки (главное, компаньон) уходит всё, панели — только её события.
+//! Открытые панели и их геометрия хранятся в AppSettings.detached_panels.
+//! Список дропа рассылается изменениями (`DropsDiff`): строки, которые поменялись
+//! с прошлой рассылки, и ключи исчезнувших строк; полный список окно берёт get_drops.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, Emitter, EventTarget, Manager, PhysicalPosition, PhysicalSize, WebviewUrl, WebviewWindow,
+    WebviewWindowBuilder, WindowEvent,
+};
+
+use crate::state::AppState;
+use crate::types::AggregatedDrop;
+
+/// Префикс label окон-панелей
+const PANEL_LABEL_PREFIX: &str = "panel-";
+
+/// Вид отсоединяемой панели
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum PanelKind {
+    /// Статистика сессии
+    Stats,
+    /// Список дропа
+    Drops,
+    /// Лента последних подборов
+    Ticker,
+}
+
+impl PanelKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            PanelKind::Stats => "stats",
+            PanelKind::Drops => "drops",
+            PanelKind::Ticker => "ticker",
+        }
+    }
+
+    /// Label окна панели
+    pub fn label(self) -> String {
+        format!("{}{}", PANEL_LABEL_PREFIX, self.as_str())
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            PanelKind::Stats => "TLI Companion — Stats",
+            PanelKind::Drops => "TLI Companion — Drops",
+            PanelKind::Ticker => "TLI Companion — Loot",
+        }
+    }
+}
+
+/// Открытая панель: геометрия в физических пикселях
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetachedPanel {
+    pub kind: PanelKind,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl DetachedPanel {
+    /// Геометрия по умолчанию для вида панели
+    pub fn new(kind: PanelKind) -> Self {
+        let (width, height) = match kind {
+            PanelKind::Stats => (300, 200),
+            PanelKind::Drops => (340, 480),
+            PanelKind::Ticker => (320, 160),
+        };
+        Self { kind, x: 80, y: 80, width, height }
+    }
+}
+
+/// Подписки окон на события (по label окна)
+#[derive(Debug, Default)]
+pub struct WindowSubscriptions {
+    by_window: HashMap<String, HashSet<String>>,
+}
+
+impl WindowSubscriptions {
+    /// Заменить подписки окна
+    pub fn set(&mut self, label: &str, events: Vec<String>) {
+        self.by_window.insert(label.to_string(), events.into_iter().collect());
+    }
+
+    /// Забыть окно (закрыто) — снова получает всё
+    pub fn remove(&mut self, label: &str) {
+        self.by_window.remove(label);
+    }
+
+    /// Нужно ли окну событие: без подписки — нужно всё
+    pub fn wants(&self, label: &str, event: &str) -> bool {
+        self.by_window.get(label).map(|events| events.contains(event)).unwrap_or(true)
+    }
+}
+
+/// Разослать событие всем окнам с учётом их подписок
+pub fn broadcast<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) -> tauri::Result<()> {
+    let labels: Vec<String> = {
+        let state = app.state::<Arc<AppState>>();
+        let subscriptions = state.window_subscriptions.lock().unwrap();
+        app.webview_windows()
+            .into_keys()
+            .filter(|label| subscriptions.wants(label, event))
+            .collect()
+    };
+    for label in labels {
+        app.emit_to(EventTarget::WebviewWindow { label }, event, payload.clone())?;
+    }
+    Ok(())
+}
+
+/// Открыть панель (или показать уже открытую)
+pub fn open(app: &AppHandle, panel: &DetachedPanel) -> tauri::Result<WebviewWindow> {
+    let window = match app.get_webview_window(&panel.kind.label()) {
+        Some(window) => window,
+        None => build(app, panel)?,
+    };
+    window.show()?;
+    window.set_focus()?;
+    Ok(window)
+}
+
+/// Закрыть панель
+pub fn close(app: &AppHandle, kind: PanelKind) -> tauri::Result<()> {
+    if let Some(window) = app.get_webview_window(&kind.label()) {
+        window.close()?;
+    }
+    Ok(())
+}
+
+fn build(app: &AppHandle, panel: &DetachedPanel) -> tauri::Result<WebviewWindow> {
+    let kind = panel.kind;
+    let url = format!("index.html?view=panel&panel={}", kind.as_str());
+    let window = WebviewWindowBuilder::new(app, kind.label(), WebviewUrl::App(url.into()))
+        .title(kind.title())
+        .decorations(false)
+        .transparent(true)
+        .shadow(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .resizable(true)
+        .visible(false)
+        .build()?;
+
+    window.set_position(PhysicalPosition::new(panel.x, panel.y))?;
+    window.set_size(PhysicalSize::new(panel.width, panel.height))?;
+
+    let app_for_events = app.clone();
+    window.on_window_event(move |event| {
+        let state = app_for_events.state::<Arc<AppState>>().inner().clone();
+        match event {
+            WindowEvent::Moved(pos) => {
+                let (x, y) = (pos.x, pos.y);
+                tauri::async_runtime::spawn(async move {
+                    state.update_detached_panel(kind, |p| { p.x = x; p.y = y; }).await;
+                    state.schedule_geometry_save();
+                });
+            }
+            WindowEvent::Resized(size) if size.width > 0 && size.height > 0 => {
+                let (width, height) = (size.width, size.height);
+                tauri::async_runtime::spawn(async move {
+                    state.update_detached_panel(kind, |p| { p.width = width; p.height = height; }).await;
+                    state.schedule_geometry_save();
+                });
+            }
+            // Закрыта пользователем — при следующем запуске не восстанавливаем
+            WindowEvent::CloseRequested { .. } => {
+                tauri::async_runtime::spawn(async move {
+                    state.set_panel_open(kind, false).await;
+                });
+            }
+            WindowEvent::Destroyed => {
+                state.window_subscriptions.lock().unwrap().remove(&kind.label());
+                debug!("Panel window {} destroyed", kind.label());
+            }
+            _ => {}
+        }
+    });
+
+    info!("Panel window {} created at ({}, {}) {}x{}", kind.label(), panel.x, panel.y, panel.width, panel.height);
+    Ok(window)
+}
+
+/// Ключ строки дропа: предмет и тир
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Hash)]
+pub struct DropKey {
+    pub game_id: i64,
+    pub tier: Option<i32>,
+}
+
+/// Содержимое события drops-updated
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DropsDelta {
+    /// Новые и изменившиеся строки
+    pub changed: Vec<AggregatedDrop>,
+    /// Строки, которых больше нет
+    pub removed: Vec<DropKey>,
+}
+
+impl DropsDelta {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Последний разосланный список дропа (строка → её JSON для сравнения)
+#[derive(Default)]
+pub struct DropsDiff {
+    last: HashMap<DropKey, serde_json::Value>,
+}
+
+impl DropsDiff {
+    /// Изменения относительно прошлого вызова; запоминает новый список
+    pub fn update(&mut self, drops: &[AggregatedDrop]) -> DropsDelta {
+        let mut delta = DropsDelta::default();
+        let mut next = HashMap::with_capacity(drops.len());
+        for drop in drops {
+            let key = DropKey { game_id: drop.game_id, tier: drop.tier };
+            let value = serde_json::to_value(drop).unwrap_or_default();
+            if self.last.get(&key) != Some(&value) {
+                delta.changed.push(drop.clone());
+            }
+            next.insert(key, value);
+        }
+        delta.removed = self.last.keys().filter(|key| !next.contains_key(key)).copied().collect();
+        self.last = next;
+        delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(game_id: i64, quantity: i32) -> AggregatedDrop {
+        AggregatedDrop { game_id, quantity, total_value: quantity as f64, ..AggregatedDrop::default() }
+    }
+
+    #[test]
+    fn drops_diff_sends_only_changed_lines() {
+        let mut diff = DropsDiff::default();
+        assert_eq!(diff.update(&[line(1, 1), line(2, 5)]).changed.len(), 2);
+        assert!(diff.update(&[line(1, 1), line(2, 5)]).is_empty());
+
+        let delta = diff.update(&[line(1, 2)]);
+        assert_eq!(delta.changed.iter().map(|d| d.game_id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(delta.removed, vec![DropKey { game_id: 2, tier: None }]);
+    }
+
+    #[test]
+    fn unsubscribed_windows_get_everything() {
+        let mut subscriptions = WindowSubscriptions::default();
+        subscriptions.set("panel-drops", vec!["drops-updated".to_string()]);
+
+        assert!(subscriptions.wants("main", "session-stats-updated"));
+        assert!(subscriptions.wants("panel-drops", "drops-updated"));
+        assert!(!subscriptions.wants("panel-drops", "session-stats-updated"));
+
+        subscriptions.remove("panel-drops");
+        assert!(subscriptions.wants("panel-drops", "session-stats-updated"));
+    }
+}
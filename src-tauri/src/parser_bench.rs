@@ -0,0 +1,141 @@
+//! Замер производительности парсера лога (для разработки)
+//!
+//! Прогоняет LogParser с действующими паттернами по файлу-образцу и считает скорость
+//! (строк/с, МБ/с) и извлечённые события. Затем каждый regex набора отдельно
+//! прогоняется по тем же строкам — видно, какой паттерн съедает время.
+//! Файл читается потоково, в памяти он целиком не держится.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::log_parser::{LogParser, ParserStats};
+use crate::log_patterns::CompiledPatterns;
+
+/// Время одного паттерна
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternTiming {
+    pub name: &'static str,
+    /// Строк, на которых паттерн сработал
+    pub matches: u64,
+    pub total_ms: f64,
+    /// В среднем на строку, нс
+    pub ns_per_line: f64,
+}
+
+/// Отчёт benchmark_parser
+#[derive(Debug, Clone, Serialize)]
+pub struct ParserBenchmark {
+    pub lines: u64,
+    pub bytes: u64,
+    /// Время полного прогона парсера (без чтения файла)
+    pub parse_ms: f64,
+    pub lines_per_sec: f64,
+    pub mb_per_sec: f64,
+    /// Счётчики парсера после прогона: события по типам, нераспознанные строки
+    pub parser: ParserStats,
+    /// Паттерны по убыванию затраченного времени
+    pub patterns: Vec<PatternTiming>,
+}
+
+/// Прочитать строки файла (не-UTF-8 заменяется), не больше `max_lines`
+fn for_each_line(path: &Path, max_lines: Option<usize>, mut f: impl FnMut(&str)) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = Vec::new();
+    let mut count = 0usize;
+    while max_lines.is_none_or(|max| count < max) {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        let line = String::from_utf8_lossy(&buf);
+        f(line.trim_end_matches(['\r', '\n']));
+        count += 1;
+    }
+    Ok(())
+}
+
+fn per_sec(amount: f64, elapsed: Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+    if secs > 0.0 { amount / secs } else { 0.0 }
+}
+
+/// Прогнать парсер и каждый паттерн по файлу
+pub fn run(path: &Path, patterns: CompiledPatterns, max_lines: Option<usize>) -> io::Result<ParserBenchmark> {
+    let mut parser = LogParser::new();
+    parser.set_patterns(patterns.clone());
+
+    let mut lines = 0u64;
+    let mut bytes = 0u64;
+    let mut parse_time = Duration::ZERO;
+    for_each_line(path, max_lines, |line| {
+        lines += 1;
+        bytes += line.len() as u64 + 1;
+        let started = Instant::now();
+        let _ = parser.parse_line(line);
+        parse_time += started.elapsed();
+    })?;
+
+    let named = patterns.named();
+    let mut matches = [0u64; 16];
+    let mut times = [Duration::ZERO; 16];
+    for_each_line(path, max_lines, |line| {
+        for (i, (_, re)) in named.iter().enumerate() {
+            let started = Instant::now();
+            let hit = re.is_match(line);
+            times[i] += started.elapsed();
+            matches[i] += hit as u64;
+        }
+    })?;
+
+    let mut timings: Vec<PatternTiming> = named
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| PatternTiming {
+            name,
+            matches: matches[i],
+            total_ms: times[i].as_secs_f64() * 1000.0,
+            ns_per_line: if lines > 0 { times[i].as_nanos() as f64 / lines as f64 } else { 0.0 },
+        })
+        .collect();
+    timings.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+
+    Ok(ParserBenchmark {
+        lines,
+        bytes,
+        parse_ms: parse_time.as_secs_f64() * 1000.0,
+        lines_per_sec: per_sec(lines as f64, parse_time),
+        mb_per_sec: per_sec(bytes as f64 / (1024.0 * 1024.0), parse_time),
+        parser: parser.stats(),
+        patterns: timings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_patterns::LogPatterns;
+
+    #[test]
+    fn counts_lines_and_pattern_matches() {
+        let path = std::env::temp_dir().join(format!("tli-bench-{}.log", uuid::Uuid::new_v4()));
+        let sample = "[2026.01.12-11.34.07:799][980]GameLog: Display: [Game] ItemChange@ ProtoName=PickItems start\n\
+                      [2026.01.12-11.34.07:799][980]GameLog: Display: [Game] BagMgr@:Modfy BagItem PageId = 102 SlotId = 1 ConfigBaseId = 100200 Num = 50\n\
+                      [2026.01.12-11.34.07:800][980]GameLog: Display: [Game] ItemChange@ ProtoName=PickItems end\n";
+        std::fs::write(&path, sample).unwrap();
+
+        let report = run(&path, LogPatterns::bundled().compile().unwrap(), None).unwrap();
+        assert_eq!(report.lines, 3);
+        assert_eq!(report.parser.item_drops, 1);
+        let bag_modify = report.patterns.iter().find(|p| p.name == "bag_modify").unwrap();
+        assert_eq!(bag_modify.matches, 1);
+
+        let limited = run(&path, LogPatterns::bundled().compile().unwrap(), Some(1)).unwrap();
+        assert_eq!(limited.lines, 1);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,265 @@
+//! Групповые сессии (режим пати)
+//!
+//! Участники вступают в группу по общему коду (join_group_session). Пока идёт своя
+//! сессия, клиент раз в GROUP_PUSH_SEC отправляет её снимок в tli_group_members, а
+//! get_group_stats собирает строки всех участников: общий профит, карты и кто кому
+//! сколько должен перевести, чтобы поделить лут поровну.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::live_share::authorized;
+use crate::state::AppState;
+use crate::supabase_sync::{self, GroupMemberRow};
+
+/// Как часто отправлять свой снимок в группу
+const GROUP_PUSH_SEC: u64 = 30;
+
+/// Участник без обновлений дольше этого считается отключившимся
+const MEMBER_STALE_SEC: i64 = 5 * 60;
+
+/// Переводы меньше этой суммы (FE) не предлагаем
+const MIN_TRANSFER_FE: f64 = 1.0;
+
+/// Участие в группе
+#[derive(Debug)]
+pub struct PartyMembership {
+    pub group_id: String,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Участник в сводке группы
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupMemberStats {
+    pub display_name: String,
+    pub is_me: bool,
+    /// Давно не присылал снимок (закрыл клиент или пропала сеть)
+    pub is_stale: bool,
+    pub is_live: bool,
+    pub income: f64,
+    pub profit: f64,
+    pub maps_completed: i32,
+    pub duration_sec: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Предложенный перевод для дележа лута
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SplitTransfer {
+    pub from: String,
+    pub to: String,
+    pub amount: f64,
+}
+
+/// Сводка группы (get_group_stats)
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupStats {
+    pub group_id: String,
+    pub members: Vec<GroupMemberStats>,
+    pub total_income: f64,
+    pub total_profit: f64,
+    pub total_maps: i32,
+    /// Доля каждого при дележе поровну
+    pub fair_share: f64,
+    pub transfers: Vec<SplitTransfer>,
+}
+
+/// Собрать сводку из строк участников
+pub fn aggregate(group_id: &str, my_user_id: &str, rows: Vec<GroupMemberRow>, now: DateTime<Utc>) -> GroupStats {
+    let mut members: Vec<GroupMemberStats> = rows
+        .into_iter()
+        .map(|row| GroupMemberStats {
+            display_name: row.display_name.unwrap_or_else(|| "Player".to_string()),
+            is_me: row.user_id == my_user_id,
+            is_stale: (now - row.updated_at).num_seconds() > MEMBER_STALE_SEC,
+            is_live: row.payload.is_live,
+            income: row.payload.total_income,
+            profit: row.payload.total_profit,
+            maps_completed: row.payload.maps_completed,
+            duration_sec: row.payload.total_duration_sec,
+            updated_at: row.updated_at,
+        })
+        .collect();
+    members.sort_by(|a, b| b.profit.total_cmp(&a.profit));
+
+    let total_income: f64 = members.iter().map(|m| m.income).sum();
+    let total_profit: f64 = members.iter().map(|m| m.profit).sum();
+    let total_maps = members.iter().map(|m| m.maps_completed).sum();
+    let fair_share = if members.is_empty() { 0.0 } else { total_profit / members.len() as f64 };
+    let balances: Vec<(String, f64)> =
+        members.iter().map(|m| (m.display_name.clone(), m.profit - fair_share)).collect();
+
+    GroupStats {
+        group_id: group_id.to_string(),
+        members,
+        total_income,
+        total_profit,
+        total_maps,
+        fair_share,
+        transfers: split_transfers(balances),
+    }
+}
+
+/// Переводы от тех, у кого профит выше доли, тем, у кого ниже (жадно, от крупных сумм).
+/// balances: имя → сколько сверх доли (отрицательное — недополучил)
+pub(crate) fn split_transfers(balances: Vec<(String, f64)>) -> Vec<SplitTransfer> {
+    let mut payers: Vec<(String, f64)> = balances.iter().filter(|(_, b)| *b > 0.0).cloned().collect();
+    let mut receivers: Vec<(String, f64)> =
+        balances.into_iter().filter(|(_, b)| *b < 0.0).map(|(name, b)| (name, -b)).collect();
+    payers.sort_by(|a, b| b.1.total_cmp(&a.1));
+    receivers.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut transfers = Vec::new();
+    let (mut p, mut r) = (0, 0);
+    while p < payers.len() && r < receivers.len() {
+        let amount = payers[p].1.min(receivers[r].1);
+        if amount >= MIN_TRANSFER_FE {
+            transfers.push(SplitTransfer {
+                from: payers[p].0.clone(),
+                to: receivers[r].0.clone(),
+                amount: amount.round(),
+            });
+        }
+        payers[p].1 -= amount;
+        receivers[r].1 -= amount;
+        if payers[p].1 < MIN_TRANSFER_FE {
+            p += 1;
+        }
+        if receivers[r].1 < MIN_TRANSFER_FE {
+            r += 1;
+        }
+    }
+    transfers
+}
+
+/// Анонимный ник из хэша user_id — email в группу не публикуем
+fn anonymous_handle(user_id: &str) -> String {
+    let digest = Sha256::digest(user_id.as_bytes());
+    format!("Player-{:02X}{:02X}", digest[0], digest[1])
+}
+
+/// Вступить в группу (из другой группы выходим). Пустое имя — анонимный ник
+pub async fn join(state: Arc<AppState>, group_id: String, display_name: Option<String>) -> Result<String, String> {
+    let group_id = group_id.trim().to_string();
+    if group_id.is_empty() {
+        return Err("Group code is empty".to_string());
+    }
+    let Some(user_id) = state.get_auth_user_id().await else {
+        return Err("Not logged in".to_string());
+    };
+    leave(&state).await?;
+
+    let display_name = match display_name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()) {
+        Some(name) => name,
+        None => anonymous_handle(&user_id),
+    };
+    let cancel = Arc::new(AtomicBool::new(false));
+    *state.party.write().await = Some(PartyMembership {
+        group_id: group_id.clone(),
+        cancel: cancel.clone(),
+    });
+    tauri::async_runtime::spawn(push_loop(state.clone(), group_id.clone(), display_name, cancel));
+
+    info!("Joined group session {}", group_id);
+    Ok(group_id)
+}
+
+/// Создать группу с новым кодом и вступить в неё
+pub async fn create(state: Arc<AppState>, display_name: Option<String>) -> Result<String, String> {
+    let group_id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+    join(state, group_id, display_name).await
+}
+
+/// Выйти из группы: своя строка удаляется. false — не были в группе
+pub async fn leave(state: &AppState) -> Result<bool, String> {
+    let Some(membership) = state.party.write().await.take() else {
+        return Ok(false);
+    };
+    membership.cancel.store(true, Ordering::Relaxed);
+    let user_id = state.get_auth_user_id().await.ok_or_else(|| "Not logged in".to_string())?;
+    let (api, jwt) = authorized(state).await?;
+    supabase_sync::delete_group_member(&api, &jwt, &membership.group_id, &user_id).await?;
+    info!("Left group session {}", membership.group_id);
+    Ok(true)
+}
+
+/// Сводка текущей группы
+pub async fn stats(state: &AppState) -> Result<GroupStats, String> {
+    let group_id = state
+        .party
+        .read()
+        .await
+        .as_ref()
+        .map(|m| m.group_id.clone())
+        .ok_or_else(|| "Not in a group session".to_string())?;
+    let user_id = state.get_auth_user_id().await.ok_or_else(|| "Not logged in".to_string())?;
+    let (api, jwt) = authorized(state).await?;
+    let rows = supabase_sync::fetch_group_members(&api, &jwt, &group_id).await?;
+    Ok(aggregate(&group_id, &user_id, rows, Utc::now()))
+}
+
+async fn push_loop(state: Arc<AppState>, group_id: String, display_name: String, cancel: Arc<AtomicBool>) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(GROUP_PUSH_SEC));
+    loop {
+        ticker.tick().await;
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        // Своя сессия не идёт — в группе остаётся последний снимок
+        let Some(snapshot) = state.build_live_snapshot().await else {
+            continue;
+        };
+        let Some(user_id) = state.get_auth_user_id().await else {
+            continue;
+        };
+        let (api, jwt) = match authorized(&state).await {
+            Ok(auth) => auth,
+            Err(e) => {
+                debug!("Group update skipped: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) =
+            supabase_sync::upsert_group_member(&api, &jwt, &user_id, &group_id, &display_name, &snapshot).await
+        {
+            warn!("Failed to push group stats to {}: {}", group_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfers_even_out_profit() {
+        let balances = vec![
+            ("a".to_string(), 600.0),
+            ("b".to_string(), -200.0),
+            ("c".to_string(), -400.0),
+        ];
+        let transfers = split_transfers(balances);
+        assert_eq!(
+            transfers,
+            vec![
+                SplitTransfer { from: "a".into(), to: "c".into(), amount: 400.0 },
+                SplitTransfer { from: "a".into(), to: "b".into(), amount: 200.0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn anonymous_handle_hides_user_id() {
+        let handle = anonymous_handle("3f1c2b9e-user");
+        assert!(handle.starts_with("Player-"));
+        assert_eq!(handle.len(), "Player-".len() + 4);
+        assert!(!handle.contains("3f1c"));
+        assert_eq!(handle, anonymous_handle("3f1c2b9e-user"));
+    }
+}
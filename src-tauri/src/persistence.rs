@@ -4,13 +4,24 @@
 //! Безопасность: пишем только в data_local_dir()/tli-companion/, никаких произвольных путей.
 
 use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use log::warn;
 use std::fs;
-use std::io;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-use crate::types::{AppSettings, FarmSessionState};
+use crate::migrations::{self, FileSchema};
+use crate::log_patterns::LogPatterns;
+use crate::types::{
+    AppSettings, FarmBlock, FarmSessionState, Holding, InventorySnapshotEvent, ItemInfo,
+    LastManualPrice, PersonalRecords, QuotedPrice, Recipe, SessionTemplate,
+};
+use crate::gamble::{GambleOpening, MAX_STORED_OPENINGS};
+use crate::drop_rates::{MapLogEntry, MAX_LOGGED_MAPS};
+use crate::exchange_rates::{ExchangeRates, RatePoint};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PricesCacheFile {
@@ -28,17 +39,61 @@ pub struct PersistedPriceEntry {
     /// Название лиги (SS10, SS11, etc)
     #[serde(default)]
     pub league_name: Option<String>,
+    /// Сколько лотов было в выборке (None — неизвестно)
+    #[serde(default)]
+    pub sample_count: Option<u32>,
+    /// Разброс цен лотов: stddev / mean (None — неизвестно)
+    #[serde(default)]
+    pub spread: Option<f64>,
+    /// Свои цены тиров экипировки (тир -> цена); тира нет в списке — общая цена
+    #[serde(default)]
+    pub tiers: HashMap<i32, f64>,
 }
 
 fn default_true() -> bool { true }
 
+/// What happened to a corrupt persisted file when it was loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryAction {
+    /// Restored from the `.bak` copy
+    RestoredFromBackup,
+    /// No usable backup: the data starts from defaults
+    Reset,
+    /// Written by a newer app version: left as is and protected from being overwritten
+    NewerVersion,
+}
+
+/// A corrupt or too new file detected while loading (corrupt ones are quarantined as `.corrupt`)
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupWarning {
+    /// File name inside the app data dir (e.g. `settings.json`)
+    pub file: String,
+    pub action: RecoveryAction,
+    /// Where the corrupt copy was moved (None — rename failed, file left in place)
+    pub quarantined_to: Option<String>,
+    pub error: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+lazy_static::lazy_static! {
+    static ref STARTUP_WARNINGS: Mutex<Vec<StartupWarning>> = Mutex::new(Vec::new());
+    /// Files written by a newer app version: never overwritten by this one
+    static ref NEWER_VERSION_FILES: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
+}
+
+/// Corrupt files recovered or reset since the app started
+pub fn startup_warnings() -> Vec<StartupWarning> {
+    STARTUP_WARNINGS.lock().unwrap().clone()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SettingsFile {
     pub version: u32,
     pub settings: AppSettings,
 }
 
-fn app_data_dir() -> Option<PathBuf> {
+pub(crate) fn app_data_dir() -> Option<PathBuf> {
     dirs::data_local_dir().map(|d| d.join("tli-companion"))
 }
 
@@ -54,6 +109,60 @@ fn session_path() -> Option<PathBuf> {
     app_data_dir().map(|d| d.join("active_session.json"))
 }
 
+fn session_templates_path() -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join("session_templates.json"))
+}
+
+fn log_patterns_path() -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join("log_patterns.json"))
+}
+
+fn linked_accounts_path() -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join("linked_accounts.json"))
+}
+
+fn inventory_snapshot_path() -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join("inventory_snapshot.json"))
+}
+
+fn farm_blocks_path() -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join("farm_blocks.json"))
+}
+
+const PERSONAL_RECORDS_FILE: &str = "records.json";
+
+fn personal_records_path() -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join(PERSONAL_RECORDS_FILE))
+}
+
+fn recipes_path() -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join("recipes.json"))
+}
+
+fn gamble_openings_path() -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join("gamble_openings.json"))
+}
+
+fn map_log_path() -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join("map_log.json"))
+}
+
+fn custom_items_path() -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join("custom_items.json"))
+}
+
+fn manual_prices_path() -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join("manual_prices.json"))
+}
+
+fn exchange_rates_path() -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join("exchange_rates.json"))
+}
+
+fn sheets_queue_path() -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join("google_sheets_queue.json"))
+}
+
 pub fn load_prices_cache() -> io::Result<HashMap<i64, PersistedPriceEntry>> {
     let Some(path) = prices_cache_path() else {
         return Ok(HashMap::new());
@@ -62,38 +171,207 @@ pub fn load_prices_cache() -> io::Result<HashMap<i64, PersistedPriceEntry>> {
         return Ok(HashMap::new());
     }
 
-    let data = fs::read_to_string(&path)?;
-    // v2 format
-    if let Ok(parsed) = serde_json::from_str::<PricesCacheFile>(&data) {
-        return Ok(parsed.prices);
+    read_with_backup(&path, |data| {
+        let file: PricesCacheFile = parse_versioned(&migrations::PRICES_CACHE, data)?;
+        Ok(file.prices)
+    })
+}
+
+fn invalid_data(e: serde_json::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Parse a persisted document, upgrading it to the schema's current version first
+///
+/// A file from a newer app version fails with `ErrorKind::Unsupported`: it is not corrupt
+fn parse_versioned<T: DeserializeOwned>(schema: &FileSchema, data: &str) -> io::Result<T> {
+    let raw: serde_json::Value = serde_json::from_str(data).map_err(invalid_data)?;
+    let version = migrations::document_version(&raw);
+    if version > schema.current_version {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{}: file version {} is newer than supported {}", schema.name, version, schema.current_version),
+        ));
+    }
+    let value = migrations::migrate(schema, raw)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    serde_json::from_value(value).map_err(invalid_data)
+}
+
+/// Path of the backup copy kept next to a persisted file (`foo.json` -> `foo.json.bak`)
+fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension("json.bak")
+}
+
+/// Path a corrupt file is moved to (`foo.json` -> `foo.json.corrupt`)
+fn corrupt_path(path: &Path) -> PathBuf {
+    path.with_extension("json.corrupt")
+}
+
+/// Read and parse a persisted file; if it is unreadable or corrupt, fall back to its `.bak` copy.
+///
+/// A file that exists but does not parse is quarantined as `.corrupt` (so the next save does
+/// not silently overwrite the evidence), the backup is copied back in its place when usable,
+/// and the outcome is recorded for `startup_warnings`.
+///
+/// A file from a newer app version is neither quarantined nor replaced by the backup:
+/// it is left as is and protected from being overwritten (see `atomic_write`).
+fn read_with_backup<T, F>(path: &Path, parse: F) -> io::Result<T>
+where
+    F: Fn(&str) -> io::Result<T>,
+{
+    let primary_err = match fs::read_to_string(path).and_then(|data| parse(&data)) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+    if primary_err.kind() == io::ErrorKind::Unsupported {
+        NEWER_VERSION_FILES.lock().unwrap().insert(path.to_path_buf());
+        record_warning(StartupWarning {
+            file: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+            action: RecoveryAction::NewerVersion,
+            quarantined_to: None,
+            error: primary_err.to_string(),
+            detected_at: Utc::now(),
+        });
+        return Err(primary_err);
+    }
+    // Transient I/O (locked file, permissions) is not corruption: leave the file alone
+    let corrupt = primary_err.kind() == io::ErrorKind::InvalidData;
+    let quarantined_to = if corrupt { quarantine(path) } else { None };
+
+    let bak = backup_path(path);
+    let restored = if bak.exists() {
+        warn!("Failed to load {}: {}; falling back to backup", path.display(), primary_err);
+        fs::read_to_string(&bak)
+            .and_then(|data| parse(&data))
+            .map_err(|bak_err| warn!("Backup {} is unusable too: {}", bak.display(), bak_err))
+            .ok()
+    } else {
+        None
+    };
+
+    if corrupt {
+        if restored.is_some() {
+            if let Err(e) = fs::copy(&bak, path) {
+                warn!("Failed to restore {} from backup: {}", path.display(), e);
+            }
+        }
+        record_warning(StartupWarning {
+            file: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+            action: if restored.is_some() { RecoveryAction::RestoredFromBackup } else { RecoveryAction::Reset },
+            quarantined_to: quarantined_to.map(|p| p.display().to_string()),
+            error: primary_err.to_string(),
+            detected_at: Utc::now(),
+        });
+    }
+
+    restored.ok_or(primary_err)
+}
+
+/// Move a corrupt file aside as `.corrupt` (replacing an older quarantined copy)
+fn quarantine(path: &Path) -> Option<PathBuf> {
+    if !path.exists() {
+        return None;
+    }
+    let target = corrupt_path(path);
+    match fs::rename(path, &target) {
+        Ok(()) => {
+            warn!("Quarantined corrupt {} as {}", path.display(), target.display());
+            Some(target)
+        }
+        Err(e) => {
+            warn!("Failed to quarantine corrupt {}: {}", path.display(), e);
+            None
+        }
     }
+}
 
-    // v1 legacy: game_id -> price (without timestamp)
-    let legacy: HashMap<i64, f64> = serde_json::from_str(&data).unwrap_or_default();
-    let now = Utc::now();
-    Ok(legacy
-        .into_iter()
-        .filter(|(_, p)| p.is_finite() && *p > 0.0)
-        .map(|(k, p)| (k, PersistedPriceEntry { 
-            price: p, 
-            updated_at: now,
-            is_current_league: true,
-            league_name: None,
-        }))
-        .collect())
+fn record_warning(warning: StartupWarning) {
+    warn!(
+        "Persisted file {} could not be loaded: {:?} ({})",
+        warning.file, warning.action, warning.error
+    );
+    STARTUP_WARNINGS.lock().unwrap().push(warning);
 }
 
+/// Crash-safe write: temp file -> fsync -> rename over the target.
+///
+/// The previous version is kept as `.bak` (rotated on every write), so a torn or corrupt
+/// file can always be recovered by the loaders. The old file is hard-linked to `.bak`
+/// rather than copied or re-parsed: it was written by this function, and a corrupt one
+/// would have been quarantined by the loader already. `fs::rename` then replaces the
+/// destination atomically on both platforms (MoveFileExW with MOVEFILE_REPLACE_EXISTING
+/// on Windows), so (with hard links available) there is no window where the target is missing.
 fn atomic_write(path: &Path, content: &str) -> io::Result<()> {
-    let dir = path.parent().unwrap_or(Path::new("."));
+    write_replacing(path, content, true)
+}
+
+/// Same atomic write without the `.bak` copy (files other tools read, not app state)
+pub(crate) fn atomic_write_no_backup(path: &Path, content: &str) -> io::Result<()> {
+    write_replacing(path, content, false)
+}
+
+/// Move the current file to `.bak` without reading it. A hard link keeps the target in place;
+/// where links are unsupported (FAT, some network shares) the file is renamed instead and
+/// the target is briefly missing until the new version is renamed over it.
+fn rotate_backup(path: &Path) -> io::Result<()> {
+    let bak = backup_path(path);
+    match fs::remove_file(&bak) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    fs::hard_link(path, &bak).or_else(|_| fs::rename(path, &bak))
+}
+
+/// Refuse to overwrite a file written by a newer app version; create the parent dir
+fn prepare_write(path: &Path) -> io::Result<&Path> {
+    if NEWER_VERSION_FILES.lock().unwrap().contains(path) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} is from a newer app version; not overwriting it", path.display()),
+        ));
+    }
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
     fs::create_dir_all(dir)?;
+    Ok(dir)
+}
 
+/// Temp file -> rename without fsync and without touching `.bak`: for files rewritten
+/// many times a minute. The rename still never exposes a half-written file to readers;
+/// after a power loss the loader falls back to the `.bak` of the last durable write.
+fn quick_replace(path: &Path, content: &str) -> io::Result<()> {
+    prepare_write(path)?;
     let tmp = path.with_extension("json.tmp");
     fs::write(&tmp, content)?;
-    // Windows: rename поверх существующего может падать, поэтому сначала удаляем старый.
-    if path.exists() {
-        let _ = fs::remove_file(path);
+    fs::rename(&tmp, path)
+}
+
+fn write_replacing(path: &Path, content: &str, keep_backup: bool) -> io::Result<()> {
+    let dir = prepare_write(path)?;
+
+    let tmp = path.with_extension("json.tmp");
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+    }
+
+    if keep_backup && path.exists() {
+        if let Err(e) = rotate_backup(path) {
+            warn!("Failed to rotate backup for {}: {}", path.display(), e);
+        }
+    }
+
+    fs::rename(&tmp, path)?;
+
+    // Persist the rename itself (directory entry) where the platform allows it.
+    #[cfg(unix)]
+    {
+        if let Ok(d) = fs::File::open(dir) {
+            let _ = d.sync_all();
+        }
     }
-    fs::rename(tmp, path)?;
     Ok(())
 }
 
@@ -111,7 +389,7 @@ pub fn save_prices_cache(prices: &HashMap<i64, PersistedPriceEntry>) -> io::Resu
     }
 
     let file = PricesCacheFile {
-        version: 2,
+        version: migrations::PRICES_CACHE.current_version,
         prices: sanitized,
     };
     let json = serde_json::to_string(&file).unwrap_or_else(|_| "{\"version\":1,\"prices\":{}}".to_string());
@@ -126,15 +404,10 @@ pub fn load_settings() -> io::Result<Option<AppSettings>> {
         return Ok(None);
     }
 
-    let data = fs::read_to_string(&path)?;
-    if let Ok(parsed) = serde_json::from_str::<SettingsFile>(&data) {
-        return Ok(Some(parsed.settings));
-    }
-
-    // legacy: raw AppSettings without wrapper
-    let legacy: AppSettings =
-        serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-    Ok(Some(legacy))
+    read_with_backup(&path, |data| {
+        let file: SettingsFile = parse_versioned(&migrations::SETTINGS, data)?;
+        Ok(Some(file.settings))
+    })
 }
 
 pub fn save_settings(settings: &AppSettings) -> io::Result<()> {
@@ -143,7 +416,7 @@ pub fn save_settings(settings: &AppSettings) -> io::Result<()> {
     };
 
     let file = SettingsFile {
-        version: 1,
+        version: migrations::SETTINGS.current_version,
         settings: settings.clone(),
     };
     let json =
@@ -160,9 +433,9 @@ pub fn load_session() -> io::Result<Option<FarmSessionState>> {
         return Ok(None);
     }
 
-    let data = fs::read_to_string(&path)?;
-    let session: FarmSessionState = serde_json::from_str(&data)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let session: FarmSessionState = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::ACTIVE_SESSION, data)
+    })?;
     
     // Only return session if it was actually started
     if session.started_at.is_some() {
@@ -172,7 +445,8 @@ pub fn load_session() -> io::Result<Option<FarmSessionState>> {
     }
 }
 
-/// Save active session to disk (for recovery)
+/// Save active session to disk (for recovery): fsync + `.bak` rotation.
+/// Used on the heartbeat and on exit; in-between saves go through `save_session_quick`.
 pub fn save_session(session: &FarmSessionState) -> io::Result<()> {
     let Some(path) = session_path() else {
         return Ok(());
@@ -183,17 +457,149 @@ pub fn save_session(session: &FarmSessionState) -> io::Result<()> {
     atomic_write(&path, &json)
 }
 
+/// Save active session after a mutation: no fsync, no backup rotation
+pub fn save_session_quick(session: &FarmSessionState) -> io::Result<()> {
+    let Some(path) = session_path() else {
+        return Ok(());
+    };
+
+    let json = serde_json::to_string(session)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    quick_replace(&path, &json)
+}
+
+/// When the active session file was last written (None if there is none)
+pub fn session_saved_at() -> Option<DateTime<Utc>> {
+    let modified = fs::metadata(session_path()?).ok()?.modified().ok()?;
+    Some(DateTime::<Utc>::from(modified))
+}
+
 /// Delete session file (when session ends normally)
 pub fn delete_session() -> io::Result<()> {
     let Some(path) = session_path() else {
         return Ok(());
     };
     if path.exists() {
-        fs::remove_file(path)?;
+        fs::remove_file(&path)?;
+    }
+    // The backup belongs to the ended session too; never resurrect it on next start.
+    let bak = backup_path(&path);
+    if bak.exists() {
+        fs::remove_file(bak)?;
     }
     Ok(())
 }
 
+// ─────────────────────────────────────────────────────────────────────────────
+// Log Patterns Override
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Load the log patterns override (user-provided or cached from Supabase)
+pub fn load_log_patterns() -> io::Result<Option<LogPatterns>> {
+    let Some(path) = log_patterns_path() else {
+        return Ok(None);
+    };
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let patterns = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::LOG_PATTERNS, data)
+    })?;
+    Ok(Some(patterns))
+}
+
+/// Save the log patterns override
+pub fn save_log_patterns(patterns: &LogPatterns) -> io::Result<()> {
+    let Some(path) = log_patterns_path() else {
+        return Ok(());
+    };
+
+    let json = serde_json::to_string_pretty(patterns)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Session Templates
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionTemplatesFile {
+    version: u32,
+    templates: Vec<SessionTemplate>,
+}
+
+/// Load saved session templates
+pub fn load_session_templates() -> io::Result<Vec<SessionTemplate>> {
+    let Some(path) = session_templates_path() else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file: SessionTemplatesFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::SESSION_TEMPLATES, data)
+    })?;
+    Ok(file.templates)
+}
+
+/// Save session templates
+pub fn save_session_templates(templates: &[SessionTemplate]) -> io::Result<()> {
+    let Some(path) = session_templates_path() else {
+        return Ok(());
+    };
+
+    let file = SessionTemplatesFile {
+        version: migrations::SESSION_TEMPLATES.current_version,
+        templates: templates.to_vec(),
+    };
+    let json = serde_json::to_string(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Farm Blocks (planned farming schedule)
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FarmBlocksFile {
+    version: u32,
+    blocks: Vec<FarmBlock>,
+}
+
+/// Load planned farm blocks
+pub fn load_farm_blocks() -> io::Result<Vec<FarmBlock>> {
+    let Some(path) = farm_blocks_path() else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file: FarmBlocksFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::FARM_BLOCKS, data)
+    })?;
+    Ok(file.blocks)
+}
+
+/// Save planned farm blocks
+pub fn save_farm_blocks(blocks: &[FarmBlock]) -> io::Result<()> {
+    let Some(path) = farm_blocks_path() else {
+        return Ok(());
+    };
+
+    let file = FarmBlocksFile {
+        version: migrations::FARM_BLOCKS.current_version,
+        blocks: blocks.to_vec(),
+    };
+    let json = serde_json::to_string(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // Session History (local storage per user)
 // ─────────────────────────────────────────────────────────────────────────────
@@ -209,8 +615,55 @@ pub struct SessionHistoryRecord {
     pub total_profit: f64,
     pub total_expenses: f64,
     pub total_income: f64,
+    /// Sale fees on the drops (already subtracted from total_profit)
+    #[serde(default)]
+    pub total_fee: f64,
     /// Remote ID in Supabase (if synced)
     pub remote_id: Option<String>,
+    /// Drops of the session: game_id -> quantity
+    #[serde(default)]
+    pub drops: HashMap<i64, i32>,
+    /// Tiered part of the drops: game_id -> tier -> quantity (priced per tier on re-valuation)
+    #[serde(default)]
+    pub drop_tiers: HashMap<i64, HashMap<i32, i32>>,
+    /// Public share code (if the session was shared via link)
+    #[serde(default)]
+    pub share_code: Option<String>,
+    /// Preset the session was farmed with
+    #[serde(default)]
+    pub preset_id: Option<String>,
+    /// Base currency (FE) on hand at session start / end, if it was observed or entered
+    #[serde(default)]
+    pub raw_currency_start: Option<i64>,
+    #[serde(default)]
+    pub raw_currency_end: Option<i64>,
+    /// FE actually gained (end - start), independent of estimated item value
+    #[serde(default)]
+    pub raw_currency_gained: Option<i64>,
+    /// Manual drops already sold, at actual prices
+    #[serde(default)]
+    pub manual_realized: f64,
+    /// Manual drops still waiting to be sold, at estimated prices
+    #[serde(default)]
+    pub manual_pending: f64,
+    /// Part of manual_pending entered in trade currencies (total per currency),
+    /// so re-valuation can convert it at today's rate
+    #[serde(default)]
+    pub manual_pending_quoted: Vec<QuotedPrice>,
+    /// Session was restored after the app crashed or was killed; figures may undercount
+    #[serde(default)]
+    pub recovered: bool,
+    /// Seconds the app was down across abnormal exits
+    #[serde(default)]
+    pub recovery_gap_sec: i64,
+    /// Cloud rows of the records this one was merged from. Sync must not pull them back
+    #[serde(default)]
+    pub merged_remote_ids: Vec<String>,
+}
+
+/// Raw currency gained between two readings (None unless both are known)
+pub fn raw_currency_gained(start: Option<i64>, end: Option<i64>) -> Option<i64> {
+    Some(end? - start?)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -219,12 +672,16 @@ struct SessionHistoryFile {
     sessions: Vec<SessionHistoryRecord>,
 }
 
-fn session_history_path(user_id: &str) -> Option<PathBuf> {
+fn session_history_file_name(user_id: &str) -> String {
     // Sanitize user_id for filename (remove special chars)
     let safe_id: String = user_id.chars()
         .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
         .collect();
-    app_data_dir().map(|d| d.join(format!("sessions_{}.json", safe_id)))
+    format!("sessions_{}.json", safe_id)
+}
+
+fn session_history_path(user_id: &str) -> Option<PathBuf> {
+    app_data_dir().map(|d| d.join(session_history_file_name(user_id)))
 }
 
 /// Load session history for user
@@ -236,20 +693,53 @@ pub fn load_session_history(user_id: &str) -> io::Result<Vec<SessionHistoryRecor
         return Ok(Vec::new());
     }
 
-    let data = fs::read_to_string(&path)?;
-    let file: SessionHistoryFile = serde_json::from_str(&data)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let file: SessionHistoryFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::SESSION_HISTORY, data)
+    })?;
+    Ok(file.sessions)
+}
+
+/// Load session history for user from an archived league folder (read-only, no recovery)
+pub fn load_archived_session_history(dir: &Path, user_id: &str) -> io::Result<Vec<SessionHistoryRecord>> {
+    let path = dir.join(session_history_file_name(user_id));
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file: SessionHistoryFile = parse_versioned(&migrations::SESSION_HISTORY, &fs::read_to_string(path)?)?;
+    Ok(file.sessions)
+}
+
+/// Read a history file exported from another machine: a versioned history file
+/// (`export_session_history` or a copied `sessions_*.json`) or a bare JSON array of records
+pub fn read_session_history_export(path: &Path) -> io::Result<Vec<SessionHistoryRecord>> {
+    let data = fs::read_to_string(path)?;
+    if data.trim_start().starts_with('[') {
+        return serde_json::from_str(&data).map_err(invalid_data);
+    }
+    let file: SessionHistoryFile = parse_versioned(&migrations::SESSION_HISTORY, &data)?;
     Ok(file.sessions)
 }
 
-/// Save session history for user
+/// Write session history to a file for moving it to another machine
+pub fn write_session_history_export(path: &Path, sessions: &[SessionHistoryRecord]) -> io::Result<()> {
+    let file = SessionHistoryFile {
+        version: migrations::SESSION_HISTORY.current_version,
+        sessions: sessions.to_vec(),
+    };
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, json)
+}
+
+/// Save session history for user.
+/// This and the read-modify-write helpers below run under `AppState::history_lock`
 pub fn save_session_history(user_id: &str, sessions: &[SessionHistoryRecord]) -> io::Result<()> {
     let Some(path) = session_history_path(user_id) else {
         return Ok(());
     };
 
     let file = SessionHistoryFile {
-        version: 1,
+        version: migrations::SESSION_HISTORY.current_version,
         sessions: sessions.to_vec(),
     };
     let json = serde_json::to_string(&file)
@@ -282,3 +772,671 @@ pub fn delete_session_from_history(user_id: &str, session_id: &str) -> io::Resul
     
     Ok(removed)
 }
+
+/// Combine several history records into one: metrics and drops are summed,
+/// the time range spans all of them. The merged record is local-only (no remote id/share code);
+/// the source records' cloud rows go to `merged_remote_ids` so sync does not pull them back.
+pub fn merge_history_records(records: &[SessionHistoryRecord]) -> Option<SessionHistoryRecord> {
+    let started_at = records.iter().map(|r| r.started_at).min()?;
+    let ended_at = records.iter().map(|r| r.ended_at).max()?;
+
+    let mut drops: HashMap<i64, i32> = HashMap::new();
+    let mut drop_tiers: HashMap<i64, HashMap<i32, i32>> = HashMap::new();
+    for record in records {
+        for (game_id, qty) in &record.drops {
+            *drops.entry(*game_id).or_insert(0) += qty;
+        }
+        for (game_id, by_tier) in &record.drop_tiers {
+            for (tier, qty) in by_tier {
+                *drop_tiers.entry(*game_id).or_default().entry(*tier).or_insert(0) += qty;
+            }
+        }
+    }
+
+    let first = records.iter().min_by_key(|r| r.started_at)?;
+    let last = records.iter().max_by_key(|r| r.ended_at)?;
+    let gained: Vec<i64> = records.iter().filter_map(|r| r.raw_currency_gained).collect();
+    // One total per currency, like FarmSessionState::pending_quoted
+    let mut manual_pending_quoted: Vec<QuotedPrice> = Vec::new();
+    for quoted in records.iter().flat_map(|r| &r.manual_pending_quoted) {
+        match manual_pending_quoted.iter_mut().find(|t| t.currency_id == quoted.currency_id) {
+            Some(total) => total.amount += quoted.amount,
+            None => manual_pending_quoted.push(*quoted),
+        }
+    }
+
+    let mut merged_remote_ids: Vec<String> = records
+        .iter()
+        .flat_map(|r| r.remote_id.iter().chain(&r.merged_remote_ids))
+        .cloned()
+        .collect();
+    merged_remote_ids.sort();
+    merged_remote_ids.dedup();
+
+    Some(SessionHistoryRecord {
+        id: uuid::Uuid::new_v4().to_string(),
+        started_at,
+        ended_at,
+        maps_completed: records.iter().map(|r| r.maps_completed).sum(),
+        total_duration_sec: records.iter().map(|r| r.total_duration_sec).sum(),
+        total_profit: records.iter().map(|r| r.total_profit).sum(),
+        total_expenses: records.iter().map(|r| r.total_expenses).sum(),
+        total_income: records.iter().map(|r| r.total_income).sum(),
+        total_fee: records.iter().map(|r| r.total_fee).sum(),
+        remote_id: None,
+        drops,
+        drop_tiers,
+        share_code: None,
+        preset_id: None,
+        raw_currency_start: first.raw_currency_start,
+        raw_currency_end: last.raw_currency_end,
+        raw_currency_gained: if gained.is_empty() { None } else { Some(gained.iter().sum()) },
+        manual_realized: records.iter().map(|r| r.manual_realized).sum(),
+        manual_pending: records.iter().map(|r| r.manual_pending).sum(),
+        manual_pending_quoted,
+        recovered: records.iter().any(|r| r.recovered),
+        recovery_gap_sec: records.iter().map(|r| r.recovery_gap_sec).sum(),
+        merged_remote_ids,
+    })
+}
+
+/// Replace the given sessions with one merged record (placed where the newest of them was).
+/// Fails if any id is missing or fewer than two ids are given.
+pub fn merge_sessions_in_history(user_id: &str, session_ids: &[String]) -> io::Result<SessionHistoryRecord> {
+    let mut unique_ids: Vec<&String> = session_ids.iter().collect();
+    unique_ids.sort();
+    unique_ids.dedup();
+    if unique_ids.len() < 2 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "at least two sessions are required to merge"));
+    }
+
+    let mut sessions = load_session_history(user_id)?;
+    let mut positions = Vec::with_capacity(unique_ids.len());
+    for id in &unique_ids {
+        let idx = sessions.iter().position(|s| &&s.id == id).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("session {} not found in history", id))
+        })?;
+        positions.push(idx);
+    }
+    positions.sort_unstable();
+
+    // Remove from the back so earlier indices stay valid
+    let mut merged_from: Vec<SessionHistoryRecord> = positions.iter().rev().map(|&i| sessions.remove(i)).collect();
+    merged_from.reverse();
+    let merged = merge_history_records(&merged_from).expect("at least two records");
+    sessions.insert(positions[0], merged.clone());
+
+    save_session_history(user_id, &sessions)?;
+    Ok(merged)
+}
+
+/// Update a session in history in place. Returns the updated record (None if not found).
+pub fn update_session_in_history<F>(user_id: &str, session_id: &str, update: F) -> io::Result<Option<SessionHistoryRecord>>
+where
+    F: FnOnce(&mut SessionHistoryRecord),
+{
+    let mut sessions = load_session_history(user_id)?;
+    let Some(record) = sessions.iter_mut().find(|s| s.id == session_id) else {
+        return Ok(None);
+    };
+    update(record);
+    let updated = record.clone();
+    save_session_history(user_id, &sessions)?;
+    Ok(Some(updated))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Linked accounts (index only; refresh tokens live in the keychain)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Account the user has signed in with on this device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedAccount {
+    pub user_id: String,
+    pub email: Option<String>,
+    pub last_used_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LinkedAccountsFile {
+    version: u32,
+    accounts: Vec<LinkedAccount>,
+}
+
+/// Load linked accounts (most recently used first)
+pub fn load_linked_accounts() -> io::Result<Vec<LinkedAccount>> {
+    let Some(path) = linked_accounts_path() else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file: LinkedAccountsFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::LINKED_ACCOUNTS, data)
+    })?;
+    Ok(file.accounts)
+}
+
+fn save_linked_accounts(accounts: &[LinkedAccount]) -> io::Result<()> {
+    let Some(path) = linked_accounts_path() else {
+        return Ok(());
+    };
+
+    let file = LinkedAccountsFile {
+        version: migrations::LINKED_ACCOUNTS.current_version,
+        accounts: accounts.to_vec(),
+    };
+    let json = serde_json::to_string(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
+/// Add or refresh an account entry and move it to the front
+pub fn upsert_linked_account(user_id: &str, email: Option<String>) -> io::Result<()> {
+    let mut accounts = load_linked_accounts()?;
+    let email = email.or_else(|| {
+        accounts.iter().find(|a| a.user_id == user_id).and_then(|a| a.email.clone())
+    });
+    accounts.retain(|a| a.user_id != user_id);
+    accounts.insert(0, LinkedAccount {
+        user_id: user_id.to_string(),
+        email,
+        last_used_at: Utc::now(),
+    });
+    save_linked_accounts(&accounts)
+}
+
+/// Forget an account. Returns false if it was not linked.
+pub fn remove_linked_account(user_id: &str) -> io::Result<bool> {
+    let mut accounts = load_linked_accounts()?;
+    let before = accounts.len();
+    accounts.retain(|a| a.user_id != user_id);
+    if accounts.len() == before {
+        return Ok(false);
+    }
+    save_linked_accounts(&accounts)?;
+    Ok(true)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Inventory snapshot (latest InitBagData burst, used for net worth)
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct InventorySnapshotFile {
+    version: u32,
+    snapshot: InventorySnapshotEvent,
+}
+
+/// Load the latest inventory snapshot (None if none has been seen yet)
+pub fn load_inventory_snapshot() -> io::Result<Option<InventorySnapshotEvent>> {
+    let Some(path) = inventory_snapshot_path() else {
+        return Ok(None);
+    };
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let file: InventorySnapshotFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::INVENTORY_SNAPSHOT, data)
+    })?;
+    Ok(Some(file.snapshot))
+}
+
+/// Replace the stored inventory snapshot
+pub fn save_inventory_snapshot(snapshot: &InventorySnapshotEvent) -> io::Result<()> {
+    let Some(path) = inventory_snapshot_path() else {
+        return Ok(());
+    };
+
+    let file = InventorySnapshotFile {
+        version: migrations::INVENTORY_SNAPSHOT.current_version,
+        snapshot: snapshot.clone(),
+    };
+    let json = serde_json::to_string(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Personal records (best map value, fastest map across all sessions)
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PersonalRecordsFile {
+    version: u32,
+    records: PersonalRecords,
+}
+
+/// Load personal records (empty if none have been set yet)
+pub fn load_personal_records() -> io::Result<PersonalRecords> {
+    let Some(path) = personal_records_path() else {
+        return Ok(PersonalRecords::default());
+    };
+    if !path.exists() {
+        return Ok(PersonalRecords::default());
+    }
+
+    let file: PersonalRecordsFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::PERSONAL_RECORDS, data)
+    })?;
+    Ok(file.records)
+}
+
+/// Load personal records from an archived league folder (read-only, no recovery)
+pub fn load_archived_personal_records(dir: &Path) -> io::Result<PersonalRecords> {
+    let path = dir.join(PERSONAL_RECORDS_FILE);
+    if !path.exists() {
+        return Ok(PersonalRecords::default());
+    }
+    let file: PersonalRecordsFile = parse_versioned(&migrations::PERSONAL_RECORDS, &fs::read_to_string(path)?)?;
+    Ok(file.records)
+}
+
+/// Replace the stored personal records
+pub fn save_personal_records(records: &PersonalRecords) -> io::Result<()> {
+    let Some(path) = personal_records_path() else {
+        return Ok(());
+    };
+
+    let file = PersonalRecordsFile {
+        version: migrations::PERSONAL_RECORDS.current_version,
+        records: records.clone(),
+    };
+    let json = serde_json::to_string(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Portfolio (items held for selling later, per profile)
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PortfolioFile {
+    version: u32,
+    holdings: Vec<Holding>,
+}
+
+fn portfolio_path(profile: &str) -> Option<PathBuf> {
+    let safe_id: String = profile.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    app_data_dir().map(|d| d.join(format!("portfolio_{}.json", safe_id)))
+}
+
+/// Load holdings of a profile (user id, or "local" when signed out)
+pub fn load_portfolio(profile: &str) -> io::Result<Vec<Holding>> {
+    let Some(path) = portfolio_path(profile) else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file: PortfolioFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::PORTFOLIO, data)
+    })?;
+    Ok(file.holdings)
+}
+
+/// Save holdings of a profile
+pub fn save_portfolio(profile: &str, holdings: &[Holding]) -> io::Result<()> {
+    let Some(path) = portfolio_path(profile) else {
+        return Ok(());
+    };
+
+    let file = PortfolioFile {
+        version: migrations::PORTFOLIO.current_version,
+        holdings: holdings.to_vec(),
+    };
+    let json = serde_json::to_string(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Crafting recipes (local recipe store, hand-editable JSON)
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecipesFile {
+    version: u32,
+    recipes: Vec<Recipe>,
+}
+
+/// Load crafting recipes
+pub fn load_recipes() -> io::Result<Vec<Recipe>> {
+    let Some(path) = recipes_path() else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file: RecipesFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::RECIPES, data)
+    })?;
+    Ok(file.recipes)
+}
+
+/// Save crafting recipes (pretty-printed: the file is meant to be edited by hand too)
+pub fn save_recipes(recipes: &[Recipe]) -> io::Result<()> {
+    let Some(path) = recipes_path() else {
+        return Ok(());
+    };
+
+    let file = RecipesFile {
+        version: migrations::RECIPES.current_version,
+        recipes: recipes.to_vec(),
+    };
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Container openings (observed EV across sessions)
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GambleOpeningsFile {
+    version: u32,
+    openings: Vec<GambleOpening>,
+}
+
+/// Load recorded container openings
+pub fn load_gamble_openings() -> io::Result<Vec<GambleOpening>> {
+    let Some(path) = gamble_openings_path() else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file: GambleOpeningsFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::GAMBLE_OPENINGS, data)
+    })?;
+    Ok(file.openings)
+}
+
+/// Append an opening, dropping the oldest ones beyond the storage cap
+pub fn add_gamble_opening(opening: GambleOpening) -> io::Result<()> {
+    let Some(path) = gamble_openings_path() else {
+        return Ok(());
+    };
+
+    let mut openings = load_gamble_openings()?;
+    openings.push(opening);
+    if openings.len() > MAX_STORED_OPENINGS {
+        let excess = openings.len() - MAX_STORED_OPENINGS;
+        openings.drain(..excess);
+    }
+    let file = GambleOpeningsFile {
+        version: migrations::GAMBLE_OPENINGS.current_version,
+        openings,
+    };
+    let json = serde_json::to_string(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Map log (completed maps across sessions, for drop rate estimates)
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MapLogFile {
+    version: u32,
+    maps: Vec<MapLogEntry>,
+}
+
+/// Load the completed maps log, oldest first
+pub fn load_map_log() -> io::Result<Vec<MapLogEntry>> {
+    let Some(path) = map_log_path() else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file: MapLogFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::MAP_LOG, data)
+    })?;
+    Ok(file.maps)
+}
+
+/// Append completed maps, dropping the oldest ones beyond the storage cap
+pub fn append_map_log(entries: Vec<MapLogEntry>) -> io::Result<()> {
+    let Some(path) = map_log_path() else {
+        return Ok(());
+    };
+
+    let mut maps = load_map_log()?;
+    maps.extend(entries);
+    if maps.len() > MAX_LOGGED_MAPS {
+        let excess = maps.len() - MAX_LOGGED_MAPS;
+        maps.drain(..excess);
+    }
+    let file = MapLogFile {
+        version: migrations::MAP_LOG.current_version,
+        maps,
+    };
+    let json = serde_json::to_string(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Custom items (user-defined items missing from tli_game_items, negative game_id)
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CustomItemsFile {
+    version: u32,
+    items: Vec<ItemInfo>,
+}
+
+/// Load user-defined custom items
+pub fn load_custom_items() -> io::Result<Vec<ItemInfo>> {
+    let Some(path) = custom_items_path() else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file: CustomItemsFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::CUSTOM_ITEMS, data)
+    })?;
+    Ok(file.items)
+}
+
+/// Save user-defined custom items (pretty-printed: small and hand-editable)
+pub fn save_custom_items(items: &[ItemInfo]) -> io::Result<()> {
+    let Some(path) = custom_items_path() else {
+        return Ok(());
+    };
+
+    let file = CustomItemsFile {
+        version: migrations::CUSTOM_ITEMS.current_version,
+        items: items.to_vec(),
+    };
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Last manual prices (pre-fill for manual drops and expenses)
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ManualPricesFile {
+    version: u32,
+    prices: HashMap<i64, LastManualPrice>,
+}
+
+/// Load the last manually entered price per item
+pub fn load_manual_prices() -> io::Result<HashMap<i64, LastManualPrice>> {
+    let Some(path) = manual_prices_path() else {
+        return Ok(HashMap::new());
+    };
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+
+    let file: ManualPricesFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::MANUAL_PRICES, data)
+    })?;
+    Ok(file.prices)
+}
+
+/// Replace the stored manual prices
+pub fn save_manual_prices(prices: &HashMap<i64, LastManualPrice>) -> io::Result<()> {
+    let Some(path) = manual_prices_path() else {
+        return Ok(());
+    };
+
+    let file = ManualPricesFile {
+        version: migrations::MANUAL_PRICES.current_version,
+        prices: prices.clone(),
+    };
+    let json = serde_json::to_string(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Exchange rates of trade currencies (FE per unit)
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ExchangeRatesFile {
+    version: u32,
+    currencies: HashMap<i64, Vec<RatePoint>>,
+}
+
+/// Load the observed exchange rates
+pub fn load_exchange_rates() -> io::Result<ExchangeRates> {
+    let Some(path) = exchange_rates_path() else {
+        return Ok(ExchangeRates::default());
+    };
+    if !path.exists() {
+        return Ok(ExchangeRates::default());
+    }
+
+    let file: ExchangeRatesFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::EXCHANGE_RATES, data)
+    })?;
+    Ok(ExchangeRates { currencies: file.currencies })
+}
+
+/// Replace the stored exchange rates
+pub fn save_exchange_rates(rates: &ExchangeRates) -> io::Result<()> {
+    let Some(path) = exchange_rates_path() else {
+        return Ok(());
+    };
+
+    let file = ExchangeRatesFile {
+        version: migrations::EXCHANGE_RATES.current_version,
+        currencies: rates.currencies.clone(),
+    };
+    let json = serde_json::to_string(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Google Sheets rows that failed to append (retried on the next push)
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SheetsQueueFile {
+    version: u32,
+    rows: Vec<Vec<serde_json::Value>>,
+}
+
+/// Load the rows still waiting to be appended to the sheet
+pub fn load_sheets_queue() -> io::Result<Vec<Vec<serde_json::Value>>> {
+    let Some(path) = sheets_queue_path() else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file: SheetsQueueFile = read_with_backup(&path, |data| {
+        parse_versioned(&migrations::SHEETS_QUEUE, data)
+    })?;
+    Ok(file.rows)
+}
+
+/// Replace the queued rows
+pub fn save_sheets_queue(rows: &[Vec<serde_json::Value>]) -> io::Result<()> {
+    let Some(path) = sheets_queue_path() else {
+        return Ok(());
+    };
+
+    let file = SheetsQueueFile {
+        version: migrations::SHEETS_QUEUE.current_version,
+        rows: rows.to_vec(),
+    };
+    let json = serde_json::to_string(&file)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    atomic_write(&path, &json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(started_h: i64, hours: i64, drops: &[(i64, i32)], quoted: &[(i64, f64)]) -> SessionHistoryRecord {
+        let base = Utc::now() - chrono::Duration::days(1);
+        SessionHistoryRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            started_at: base + chrono::Duration::hours(started_h),
+            ended_at: base + chrono::Duration::hours(started_h + hours),
+            maps_completed: 3,
+            total_duration_sec: (hours * 3600) as i32,
+            total_profit: 100.0,
+            total_expenses: 10.0,
+            total_income: 115.0,
+            total_fee: 5.0,
+            remote_id: Some("remote".to_string()),
+            drops: drops.iter().copied().collect(),
+            drop_tiers: HashMap::new(),
+            share_code: Some("code".to_string()),
+            preset_id: None,
+            raw_currency_start: None,
+            raw_currency_end: None,
+            raw_currency_gained: None,
+            manual_realized: 1.0,
+            manual_pending: 20.0,
+            manual_pending_quoted: quoted.iter().map(|&(currency_id, amount)| QuotedPrice { currency_id, amount }).collect(),
+            recovered: false,
+            recovery_gap_sec: 0,
+            merged_remote_ids: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn merges_history_records() {
+        let first = record(0, 1, &[(1, 2), (2, 1)], &[(77, 3.0), (88, 1.0)]);
+        let second = record(2, 2, &[(1, 5)], &[(77, 4.5)]);
+        let merged = merge_history_records(&[second.clone(), first.clone()]).unwrap();
+
+        assert_eq!((merged.started_at, merged.ended_at), (first.started_at, second.ended_at));
+        assert_eq!((merged.maps_completed, merged.total_duration_sec), (6, 3 * 3600));
+        assert_eq!((merged.total_profit, merged.total_fee, merged.manual_pending), (200.0, 10.0, 40.0));
+        assert_eq!(merged.drops, HashMap::from([(1, 7), (2, 1)]));
+        // Quoted manual drops are summed per currency
+        assert_eq!(
+            merged.manual_pending_quoted,
+            vec![QuotedPrice { currency_id: 77, amount: 7.5 }, QuotedPrice { currency_id: 88, amount: 1.0 }]
+        );
+        assert!(merged.remote_id.is_none() && merged.share_code.is_none());
+        assert!(merge_history_records(&[]).is_none());
+    }
+}
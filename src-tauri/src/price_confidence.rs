@@ -0,0 +1,142 @@
+//! Уверенность в цене предмета
+//!
+//! Цена редкого предмета часто держится на одном лоте, и "900 FE" за эмбер может
+//! оказаться случайной ценой одного продавца. Оценка 0..1 складывается из трёх множителей:
+//! - число лотов в выборке (1 лот — низкая уверенность, от 10 — полная);
+//! - разброс цен (коэффициент вариации: чем шире разброс, тем ниже);
+//! - возраст (после TTL цены уверенность плавно падает, за сутки — вдвое).
+//!
+//! Если число лотов или разброс неизвестны (старый кэш, ручная цена), множитель нейтральный.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::persistence::PersistedPriceEntry;
+
+/// Выборка, начиная с которой число лотов не снижает уверенность
+const FULL_CONFIDENCE_SAMPLES: f64 = 10.0;
+/// Множитель при неизвестном числе лотов
+const UNKNOWN_SAMPLES_FACTOR: f64 = 0.7;
+/// За сколько секунд после TTL уверенность падает вдвое
+const AGE_HALF_DECAY_SEC: f64 = 24.0 * 3600.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceConfidenceLevel {
+    High,
+    Medium,
+    Low,
+}
+
+/// Уверенность в цене (для предупреждения в UI)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PriceConfidence {
+    /// 0..1
+    pub score: f64,
+    pub level: PriceConfidenceLevel,
+    /// Сколько лотов было в выборке (None — неизвестно)
+    pub sample_count: Option<u32>,
+}
+
+impl PriceConfidence {
+    /// Цена, в которой сомнений нет (базовая валюта)
+    pub fn certain() -> Self {
+        Self { score: 1.0, level: PriceConfidenceLevel::High, sample_count: None }
+    }
+}
+
+/// Статистика выборки цен (лоты из прайсчека или агрегат сервера)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleStats {
+    pub count: u32,
+    /// Коэффициент вариации (stddev / mean)
+    pub spread: f64,
+}
+
+impl SampleStats {
+    /// Посчитать по ценам лотов (некорректные значения отбрасываются)
+    pub fn from_prices(prices: &[f64]) -> Option<Self> {
+        let valid: Vec<f64> = prices.iter().copied().filter(|p| p.is_finite() && *p > 0.0).collect();
+        if valid.is_empty() {
+            return None;
+        }
+        let n = valid.len() as f64;
+        let mean = valid.iter().sum::<f64>() / n;
+        let variance = valid.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / n;
+        Some(Self {
+            count: valid.len() as u32,
+            spread: variance.sqrt() / mean,
+        })
+    }
+}
+
+/// Оценить уверенность в цене из кэша. `ttl_sec` — после какого возраста цена считается устаревшей
+pub fn evaluate(entry: &PersistedPriceEntry, ttl_sec: i64, now: DateTime<Utc>) -> PriceConfidence {
+    let samples_factor = match entry.sample_count {
+        Some(n) => ((1.0 + n as f64).ln() / (1.0 + FULL_CONFIDENCE_SAMPLES).ln()).min(1.0),
+        None => UNKNOWN_SAMPLES_FACTOR,
+    };
+    // inf/NaN (деление на нулевую цену в старом кэше) — как неизвестный разброс
+    let spread_factor = entry
+        .spread
+        .filter(|cv| cv.is_finite())
+        .map(|cv| 1.0 / (1.0 + cv.max(0.0)))
+        .unwrap_or(1.0);
+    let overdue_sec = ((now - entry.updated_at).num_seconds() - ttl_sec).max(0) as f64;
+    let age_factor = 1.0 / (1.0 + overdue_sec / AGE_HALF_DECAY_SEC);
+
+    let score = (samples_factor * spread_factor * age_factor).clamp(0.0, 1.0);
+    let level = if score >= 0.7 {
+        PriceConfidenceLevel::High
+    } else if score >= 0.4 {
+        PriceConfidenceLevel::Medium
+    } else {
+        PriceConfidenceLevel::Low
+    };
+    PriceConfidence { score, level, sample_count: entry.sample_count }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(samples: Option<u32>, spread: Option<f64>, age_sec: i64, now: DateTime<Utc>) -> PersistedPriceEntry {
+        PersistedPriceEntry {
+            price: 900.0,
+            updated_at: now - chrono::Duration::seconds(age_sec),
+            is_current_league: true,
+            league_name: None,
+            sample_count: samples,
+            spread,
+            tiers: Default::default(),
+        }
+    }
+
+    #[test]
+    fn single_listing_is_low_confidence() {
+        let now = Utc::now();
+        assert_eq!(evaluate(&entry(Some(1), Some(0.0), 0, now), 3600, now).level, PriceConfidenceLevel::Low);
+        assert_eq!(evaluate(&entry(Some(20), Some(0.1), 0, now), 3600, now).level, PriceConfidenceLevel::High);
+        // Много лотов, но цена двухдневной давности
+        assert_eq!(evaluate(&entry(Some(20), Some(0.1), 3 * 86400, now), 3600, now).level, PriceConfidenceLevel::Low);
+    }
+
+    #[test]
+    fn sample_stats_spread() {
+        let s = SampleStats::from_prices(&[100.0, 100.0, f64::NAN, -1.0]).unwrap();
+        assert_eq!(s.count, 2);
+        assert_eq!(s.spread, 0.0);
+        assert!(SampleStats::from_prices(&[]).is_none());
+    }
+
+    #[test]
+    fn broken_spread_is_ignored() {
+        let now = Utc::now();
+        let known = evaluate(&entry(Some(20), None, 0, now), 3600, now);
+        for spread in [f64::INFINITY, f64::NAN] {
+            let score = evaluate(&entry(Some(20), Some(spread), 0, now), 3600, now).score;
+            assert!(score.is_finite());
+            assert_eq!(score, known.score);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Планировщик обновления цен из Supabase
+//!
+//! Цены обновляются раз в минуту со случайным разбросом ±20%, чтобы клиенты не били
+//! в сервер синхронно. После ошибки интервал растёт экспоненциально (до 30 минут),
+//! 429 учитывается отдельно — сервер просит притормозить. В офлайн-режиме запросы
+//! не выполняются. Состояние планировщика отдаётся в настройки через `get_price_sync_status`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::debug;
+use rand::Rng;
+use serde::Serialize;
+
+use crate::state::AppState;
+use crate::supabase_client::ApiError;
+use crate::supabase_sync;
+
+/// Базовый интервал обновления
+const BASE_INTERVAL: Duration = Duration::from_secs(60);
+/// Потолок интервала при серии ошибок
+const MAX_INTERVAL: Duration = Duration::from_secs(30 * 60);
+/// Разброс интервала: ±20%
+const JITTER: f64 = 0.2;
+
+/// Состояние синхронизации цен (для экрана настроек)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PriceSyncStatus {
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    /// Когда планировщик проснётся в следующий раз
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Ошибок подряд (определяет backoff)
+    pub consecutive_failures: u32,
+    /// Сколько цен пришло в последнем успешном обновлении
+    pub last_price_count: usize,
+    /// Включён офлайн-режим — обновление на паузе
+    pub offline: bool,
+}
+
+/// Интервал до следующего запуска: экспонента от числа ошибок подряд + разброс
+fn next_interval(consecutive_failures: u32, rate_limited: bool) -> Duration {
+    // На 429 сразу стартуем с большего шага
+    let exponent = consecutive_failures + u32::from(rate_limited) * 2;
+    let base = BASE_INTERVAL
+        .saturating_mul(2u32.saturating_pow(exponent.min(10)))
+        .min(MAX_INTERVAL);
+    let factor = rand::thread_rng().gen_range((1.0 - JITTER)..=(1.0 + JITTER));
+    base.mul_f64(factor)
+}
+
+/// Одно обновление цен. Возвращает число полученных цен.
+async fn refresh_prices(state: &AppState) -> Result<usize, ApiError> {
+    let api = state
+        .supabase_client()
+        .await
+        .ok_or_else(|| ApiError::Request("Supabase config missing".to_string()))?;
+
+    // Новая функция с fallback на предыдущий сезон, при ошибке — старый метод
+    match supabase_sync::fetch_prices_with_fallback(&api).await {
+        Ok(rows) => {
+            let count = rows.len();
+            state.merge_prices_with_league(rows).await;
+            // Новая лига в ценах — UI предложит архивировать прошлую (get_league_status)
+            state.check_league_rollover().await;
+            Ok(count)
+        }
+        Err(e @ ApiError::RateLimited(_)) => Err(e),
+        Err(e) => {
+            debug!("Supabase fetch_prices_with_fallback error: {}", e);
+            let legacy_rows = supabase_sync::fetch_current_prices(&api).await?;
+            let count = legacy_rows.len();
+            state.merge_remote_prices(legacy_rows).await;
+            Ok(count)
+        }
+    }
+}
+
+/// Фоновая задача планировщика (живёт всё время работы приложения)
+pub async fn run(state: Arc<AppState>) {
+    loop {
+        let offline = state.settings.read().await.offline_mode;
+        let wait = if offline {
+            state.update_price_sync_status(|s| s.offline = true).await;
+            BASE_INTERVAL
+        } else {
+            let started_at = Utc::now();
+            let result = refresh_prices(&state).await;
+            let rate_limited = matches!(result, Err(ApiError::RateLimited(_)));
+            let mut failures = 0;
+            state
+                .update_price_sync_status(|s| {
+                    s.offline = false;
+                    s.last_attempt_at = Some(started_at);
+                    match &result {
+                        Ok(count) => {
+                            s.last_success_at = Some(Utc::now());
+                            s.last_price_count = *count;
+                            s.last_error = None;
+                            s.consecutive_failures = 0;
+                        }
+                        Err(e) => {
+                            s.last_error = Some(e.to_string());
+                            s.consecutive_failures += 1;
+                        }
+                    }
+                    failures = s.consecutive_failures;
+                })
+                .await;
+            if let Err(e) = &result {
+                debug!("Price sync failed ({} in a row): {}", failures, e);
+            }
+            next_interval(failures, rate_limited)
+        };
+
+        let next_run = Utc::now() + chrono::Duration::from_std(wait).unwrap_or_else(|_| chrono::Duration::zero());
+        state.update_price_sync_status(|s| s.next_run_at = Some(next_run)).await;
+        tokio::time::sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_grows_and_is_capped() {
+        let within = |d: Duration, base: Duration| {
+            d >= base.mul_f64(1.0 - JITTER) && d <= base.mul_f64(1.0 + JITTER)
+        };
+        assert!(within(next_interval(0, false), BASE_INTERVAL));
+        assert!(within(next_interval(2, false), BASE_INTERVAL * 4));
+        assert!(within(next_interval(0, true), BASE_INTERVAL * 4));
+        assert!(within(next_interval(50, true), MAX_INTERVAL));
+    }
+}
@@ -0,0 +1,119 @@
+//! Прокси для сетевых запросов
+//!
+//! Настройки (HTTP или SOCKS5, хост, порт, логин) лежат в AppSettings.proxy, пароль — в
+//! keychain. Прокси применяется к общему HTTP-клиенту AppState: его пересобирают при
+//! загрузке и сохранении настроек, так что все запросы к Supabase идут через него.
+
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::types::{ProxyKind, ProxySettings};
+
+const KEYRING_USERNAME: &str = "proxy-password";
+
+/// Сохранить пароль прокси (пустой — удалить)
+pub fn store_password(password: &str) -> Result<(), String> {
+    if password.is_empty() {
+        return clear_password();
+    }
+    crate::auth::keyring_entry(KEYRING_USERNAME)?
+        .set_password(password)
+        .map_err(|e| format!("Failed to store proxy password in keychain: {}", e))
+}
+
+pub fn load_password() -> Result<Option<String>, String> {
+    match crate::auth::keyring_entry(KEYRING_USERNAME)?.get_password() {
+        Ok(password) if !password.is_empty() => Ok(Some(password)),
+        Ok(_) | Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub fn clear_password() -> Result<(), String> {
+    match crate::auth::keyring_entry(KEYRING_USERNAME)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// URL прокси для reqwest. SOCKS5 — с резолвом имён на стороне прокси (socks5h)
+pub fn proxy_url(settings: &ProxySettings) -> Result<String, String> {
+    let host = settings.host.trim();
+    if host.is_empty() {
+        return Err("Proxy host is empty".to_string());
+    }
+    if settings.port == 0 {
+        return Err("Proxy port is not set".to_string());
+    }
+    let scheme = match settings.kind {
+        ProxyKind::Http => "http",
+        ProxyKind::Socks5 => "socks5h",
+    };
+    Ok(format!("{}://{}:{}", scheme, host, settings.port))
+}
+
+/// HTTP-клиент со стандартными таймаутами и прокси из настроек (выключен — напрямую)
+pub fn build_client(settings: &ProxySettings, password: Option<&str>) -> Result<reqwest::Client, String> {
+    let mut builder = crate::supabase_client::http_client_builder();
+    if settings.enabled {
+        let mut proxy = reqwest::Proxy::all(proxy_url(settings)?).map_err(|e| e.to_string())?;
+        if let Some(username) = settings.username.as_deref().filter(|u| !u.is_empty()) {
+            proxy = proxy.basic_auth(username, password.unwrap_or_default());
+        }
+        builder = builder.proxy(proxy);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Клиент для неверных настроек прокси: все запросы падают, а не уходят напрямую
+/// в обход прокси (пользователь мог включить его, чтобы скрыть свой адрес).
+/// Запасного прямого клиента нет: если не собрался даже этот, не соберётся никакой
+pub fn blocked_client() -> reqwest::Client {
+    crate::supabase_client::http_client_builder()
+        .proxy(reqwest::Proxy::all("http://127.0.0.1:0").expect("static proxy url"))
+        .build()
+        .expect("HTTP client with a static proxy")
+}
+
+/// Результат проверки соединения
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionTestResult {
+    pub ok: bool,
+    /// HTTP-статус ответа (None — до сервера не достучались)
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+/// Проверить, доступен ли Supabase через данный клиент (health-check сервиса авторизации)
+pub async fn test_connection(http: &reqwest::Client, cfg: &crate::supabase_sync::SupabaseConfig) -> ConnectionTestResult {
+    let started = Instant::now();
+    let url = format!("{}/auth/v1/health", cfg.url.trim_end_matches('/'));
+    let result = http.get(url).header("apikey", &cfg.anon_key).send().await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(resp) => ConnectionTestResult {
+            ok: resp.status().is_success(),
+            status: Some(resp.status().as_u16()),
+            latency_ms,
+            error: (!resp.status().is_success()).then(|| resp.status().to_string()),
+        },
+        Err(e) => ConnectionTestResult { ok: false, status: None, latency_ms, error: Some(e.to_string()) },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_proxy_urls() {
+        let mut settings = ProxySettings { enabled: true, host: " proxy.local ".into(), port: 1080, ..Default::default() };
+        assert_eq!(proxy_url(&settings).unwrap(), "http://proxy.local:1080");
+        settings.kind = ProxyKind::Socks5;
+        assert_eq!(proxy_url(&settings).unwrap(), "socks5h://proxy.local:1080");
+        settings.port = 0;
+        assert!(proxy_url(&settings).is_err());
+    }
+}
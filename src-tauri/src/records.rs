@@ -0,0 +1,83 @@
+//! Личные рекорды по картам
+//!
+//! Каждая завершённая и оценённая карта сравнивается с рекордами за все сессии:
+//! самая дорогая карта и самая быстрая. Быстрые «карты» короче минимума не
+//! считаются — это обычно вход и сразу выход через портал.
+
+use crate::types::{MapRecord, MapRun, PersonalRecords, RecordBrokenEvent, RecordKind};
+use chrono::{DateTime, Utc};
+
+/// Карта короче этого не претендует на рекорд скорости
+const MIN_RECORD_MAP_SEC: i64 = 30;
+
+/// Запись рекорда по завершённой карте (None — карта ещё не оценена или брошена)
+pub fn map_record(run: &MapRun, session_started_at: Option<DateTime<Utc>>) -> Option<MapRecord> {
+    if run.abandoned {
+        return None;
+    }
+    Some(MapRecord {
+        value: run.value?,
+        duration_sec: run.duration_sec,
+        scene_name: run.scene_name.clone(),
+        achieved_at: run.ended_at,
+        session_started_at,
+    })
+}
+
+/// Сравнить карту с рекордами, обновить побитые. Возвращает побитые рекорды
+pub fn consider(records: &mut PersonalRecords, candidate: &MapRecord) -> Vec<RecordBrokenEvent> {
+    let mut broken = Vec::new();
+
+    if candidate.value > 0.0
+        && records.best_map_value.as_ref().map(|r| candidate.value > r.value).unwrap_or(true)
+    {
+        broken.push(RecordBrokenEvent {
+            kind: RecordKind::BestMapValue,
+            record: candidate.clone(),
+            previous: records.best_map_value.replace(candidate.clone()),
+        });
+    }
+
+    if candidate.duration_sec >= MIN_RECORD_MAP_SEC
+        && records.fastest_map.as_ref().map(|r| candidate.duration_sec < r.duration_sec).unwrap_or(true)
+    {
+        broken.push(RecordBrokenEvent {
+            kind: RecordKind::FastestMap,
+            record: candidate.clone(),
+            previous: records.fastest_map.replace(candidate.clone()),
+        });
+    }
+
+    broken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(value: f64, duration_sec: i64) -> MapRecord {
+        MapRecord {
+            value,
+            duration_sec,
+            scene_name: None,
+            achieved_at: DateTime::<Utc>::UNIX_EPOCH,
+            session_started_at: None,
+        }
+    }
+
+    #[test]
+    fn updates_only_broken_records() {
+        let mut records = PersonalRecords::default();
+        assert_eq!(consider(&mut records, &record(100.0, 120)).len(), 2);
+
+        // Дороже, но медленнее
+        let broken = consider(&mut records, &record(150.0, 200));
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].kind, RecordKind::BestMapValue);
+        assert_eq!(broken[0].previous.as_ref().map(|r| r.value), Some(100.0));
+
+        // Слишком короткая карта и пустой дроп рекордов не ставят
+        assert!(consider(&mut records, &record(0.0, 10)).is_empty());
+        assert_eq!(records.fastest_map.as_ref().map(|r| r.duration_sec), Some(120));
+    }
+}
@@ -0,0 +1,104 @@
+//! Проверка совместимости с бэкендом Supabase
+//!
+//! При старте клиент читает строку `tli_client_meta`: минимальную поддерживаемую версию
+//! приложения и версию схемы RPC/таблиц. Если приложение устарело или схема ушла вперёд,
+//! UI просит обновиться — вместо непонятных 400 от PostgREST.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Версия схемы Supabase, под которую собран клиент
+pub const EXPECTED_SCHEMA_VERSION: i32 = 1;
+
+/// Строка tli_client_meta
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClientMeta {
+    pub min_app_version: String,
+    pub schema_version: i32,
+    /// Сообщение для пользователя (например, что изменилось)
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Результат проверки для UI
+#[derive(Debug, Clone, Serialize)]
+pub struct HandshakeStatus {
+    pub checked_at: DateTime<Utc>,
+    pub app_version: String,
+    pub min_app_version: Option<String>,
+    pub server_schema_version: Option<i32>,
+    pub expected_schema_version: i32,
+    /// Версия приложения ниже минимальной
+    pub update_required: bool,
+    /// Схема на сервере новее, чем знает клиент (часть запросов может падать)
+    pub schema_changed: bool,
+    pub message: Option<String>,
+}
+
+impl HandshakeStatus {
+    /// Сравнить версию приложения с метаданными сервера (None — строки нет, проверять нечего)
+    pub fn evaluate(app_version: &str, meta: Option<ClientMeta>, now: DateTime<Utc>) -> Self {
+        let update_required = meta
+            .as_ref()
+            .map(|m| is_older(app_version, &m.min_app_version))
+            .unwrap_or(false);
+        let schema_changed = meta
+            .as_ref()
+            .map(|m| m.schema_version > EXPECTED_SCHEMA_VERSION)
+            .unwrap_or(false);
+        Self {
+            checked_at: now,
+            app_version: app_version.to_string(),
+            min_app_version: meta.as_ref().map(|m| m.min_app_version.clone()),
+            server_schema_version: meta.as_ref().map(|m| m.schema_version),
+            expected_schema_version: EXPECTED_SCHEMA_VERSION,
+            update_required,
+            schema_changed,
+            message: meta.and_then(|m| m.message),
+        }
+    }
+
+    /// Нужно ли показывать пользователю предупреждение
+    pub fn needs_update(&self) -> bool {
+        self.update_required || self.schema_changed
+    }
+}
+
+/// Числовые части версии (`1.4.2-beta` -> [1, 4, 2])
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+/// `version` старше `min` (недостающие части считаются нулями)
+fn is_older(version: &str, min: &str) -> bool {
+    let (a, b) = (version_parts(version), version_parts(min));
+    let len = a.len().max(b.len());
+    let pad = |v: &[u64], i: usize| v.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| pad(&a, i).cmp(&pad(&b, i)))
+        .find(|o| o.is_ne())
+        .map(|o| o.is_lt())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_old_app_and_newer_schema() {
+        assert!(is_older("0.9.12", "0.10"));
+        assert!(!is_older("1.2.0", "1.2"));
+        assert!(!is_older("v1.3.0-beta", "1.2.9"));
+
+        let meta = ClientMeta { min_app_version: "2.0.0".into(), schema_version: EXPECTED_SCHEMA_VERSION + 1, message: None };
+        let status = HandshakeStatus::evaluate("1.9.0", Some(meta), Utc::now());
+        assert!(status.update_required && status.schema_changed);
+        assert!(!HandshakeStatus::evaluate("1.9.0", None, Utc::now()).needs_update());
+    }
+}
@@ -0,0 +1,359 @@
+//! Воспроизведение журнала событий сессии
+//!
+//! По NDJSON-журналу (см. event_journal) восстанавливается состояние сессии на любой
+//! момент: секундомер без пауз, время на картах, число карт и дроп с ценами, известными
+//! к этому моменту. На этом строится ползунок по времени сессии во фронтенде
+//! («какой был профит на 30-й минуте?»).
+//!
+//! Стоимость считается так же, как в живой статистике: цены тиров, комиссия, траты
+//! и ручные корректировки. Время, пока приложение было закрыто (событие Restored),
+//! секундомер не считает — как и живая сессия после восстановления.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::event_journal::{JournalEntry, JournalEvent};
+use crate::persistence::PersistedPriceEntry;
+use crate::types::{MapEventType, PRIMARY_LOG_SOURCE};
+
+/// Состояние сессии на момент `at`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayState {
+    pub started_at: Option<DateTime<Utc>>,
+    pub at: Option<DateTime<Utc>>,
+    /// Секундомер сессии (паузы не считаются)
+    pub session_sec: i64,
+    /// Время на картах, включая текущую
+    pub map_time_sec: i64,
+    pub maps_completed: i32,
+    /// Брошенные карты (дольше AppSettings.abandoned_map_minutes): не в maps_completed и map_time_sec
+    pub abandoned_maps: i32,
+    pub is_paused: bool,
+    pub is_on_map: bool,
+    pub ended: bool,
+    pub drops: HashMap<i64, i32>,
+    /// Разбивка дропа по тирам (как FarmSessionState::drop_tiers)
+    pub drop_tiers: HashMap<i64, HashMap<i32, i32>>,
+    /// Последняя известная к моменту `at` цена (из дропа или обновления цены)
+    pub prices: HashMap<i64, f64>,
+    /// Цены отдельных тиров
+    pub tier_prices: HashMap<i64, HashMap<i32, f64>>,
+    /// Траты: id -> (цена за единицу, количество)
+    pub expenses: HashMap<String, (f64, i32)>,
+    pub events_applied: usize,
+}
+
+impl ReplayState {
+    /// Цены в виде кэша цен — для общих расчётов Valuation и FeeModel
+    pub fn price_entries(&self) -> HashMap<i64, PersistedPriceEntry> {
+        self.prices
+            .iter()
+            .map(|(game_id, price)| {
+                let entry = PersistedPriceEntry {
+                    price: *price,
+                    updated_at: self.at.unwrap_or_default(),
+                    is_current_league: true,
+                    league_name: None,
+                    sample_count: None,
+                    spread: None,
+                    tiers: self.tier_prices.get(game_id).cloned().unwrap_or_default(),
+                };
+                (*game_id, entry)
+            })
+            .collect()
+    }
+}
+
+/// Карта дольше `cap_minutes` брошена: не считается завершённой и в рекорды не идёт (0 — не проверять)
+pub fn is_abandoned_run(duration_sec: i64, cap_minutes: u32) -> bool {
+    cap_minutes > 0 && duration_sec > cap_minutes as i64 * 60
+}
+
+/// Проиграть события до `until` включительно (None — весь журнал).
+/// `primary_only` — дроп других клиентов не входит в общую сессию (LogSourceMode::PerSource),
+/// `abandoned_map_minutes` — порог брошенной карты, как в живой сессии
+pub fn replay(
+    entries: &[JournalEntry],
+    until: Option<DateTime<Utc>>,
+    primary_only: bool,
+    abandoned_map_minutes: u32,
+) -> ReplayState {
+    let mut state = ReplayState::default();
+    let mut clock_resumed: Option<DateTime<Utc>> = None;
+    let mut map_started: Option<DateTime<Utc>> = None;
+    let mut last_ts: Option<DateTime<Utc>> = None;
+
+    let mut ordered: Vec<&JournalEntry> = entries.iter().collect();
+    ordered.sort_by_key(|e| e.ts);
+
+    for entry in ordered {
+        if until.map(|until| entry.ts > until).unwrap_or(false) {
+            break;
+        }
+        let ts = entry.ts;
+        let previous_ts = last_ts.replace(ts);
+        state.at = Some(ts);
+        state.events_applied += 1;
+        match &entry.event {
+            JournalEvent::SessionStart { .. } => {
+                if state.started_at.is_none() {
+                    state.started_at = Some(ts);
+                    clock_resumed = Some(ts);
+                }
+            }
+            JournalEvent::Pause { .. } => {
+                if let Some(resumed) = clock_resumed.take() {
+                    state.session_sec += (ts - resumed).num_seconds().max(0);
+                }
+                state.is_paused = true;
+            }
+            JournalEvent::Resume => {
+                if clock_resumed.is_none() && !state.ended {
+                    clock_resumed = Some(ts);
+                }
+                state.is_paused = false;
+            }
+            JournalEvent::MapChange { event_type: MapEventType::EnterMap, .. } => {
+                if !state.is_on_map {
+                    state.is_on_map = true;
+                    map_started = Some(ts);
+                }
+            }
+            JournalEvent::MapChange { event_type: MapEventType::ExitToHideout, .. } => {
+                let duration = map_started.take().or(state.started_at).map(|started| (ts - started).num_seconds().max(0)).unwrap_or(0);
+                if is_abandoned_run(duration, abandoned_map_minutes) {
+                    state.abandoned_maps += 1;
+                } else {
+                    state.maps_completed += 1;
+                    state.map_time_sec += duration;
+                }
+                state.is_on_map = false;
+            }
+            JournalEvent::Drop { game_id, quantity, tier, source_id, unit_price } => {
+                if primary_only && source_id != PRIMARY_LOG_SOURCE {
+                    continue;
+                }
+                *state.drops.entry(*game_id).or_insert(0) += quantity;
+                if let Some(tier) = tier {
+                    *state.drop_tiers.entry(*game_id).or_default().entry(*tier).or_insert(0) += quantity;
+                }
+                if let Some(price) = unit_price {
+                    state.prices.entry(*game_id).or_insert(*price);
+                }
+            }
+            JournalEvent::PriceUpdate { game_id, price } => {
+                state.prices.insert(*game_id, *price);
+            }
+            JournalEvent::Adjust { game_id, new_quantity, .. } => {
+                state.drops.insert(*game_id, *new_quantity);
+                if let Some(by_tier) = state.drop_tiers.get_mut(game_id) {
+                    crate::valuation::trim_tiers(by_tier, *new_quantity);
+                }
+            }
+            JournalEvent::Remove { game_id } => {
+                state.drops.remove(game_id);
+                state.drop_tiers.remove(game_id);
+            }
+            JournalEvent::PriceRestore { game_id, price, tiers } => {
+                match price {
+                    Some(price) => state.prices.insert(*game_id, *price),
+                    None => state.prices.remove(game_id),
+                };
+                if tiers.is_empty() {
+                    state.tier_prices.remove(game_id);
+                } else {
+                    state.tier_prices.insert(*game_id, tiers.clone());
+                }
+            }
+            JournalEvent::TierPriceUpdate { game_id, tier, price } => {
+                // Как set_tier_price: без общей цены цена тира становится общей
+                state.prices.entry(*game_id).or_insert(*price);
+                state.tier_prices.entry(*game_id).or_default().insert(*tier, *price);
+            }
+            JournalEvent::Expense { id, quantity, price } => {
+                state.expenses.insert(id.clone(), (*price, *quantity));
+            }
+            JournalEvent::ExpenseRemove { id } => {
+                state.expenses.remove(id);
+            }
+            JournalEvent::Restored { offline_since } => {
+                // Секундомер стоял с последней записи перед выходом до восстановления
+                if let Some(resumed) = clock_resumed.take() {
+                    let stopped = offline_since.or(previous_ts).unwrap_or(ts).clamp(resumed, ts);
+                    state.session_sec += (stopped - resumed).num_seconds();
+                    clock_resumed = Some(ts);
+                }
+            }
+            JournalEvent::SessionEnd => {
+                if let Some(resumed) = clock_resumed.take() {
+                    state.session_sec += (ts - resumed).num_seconds().max(0);
+                }
+                if let Some(started) = map_started.take() {
+                    state.map_time_sec += (ts - started).num_seconds().max(0);
+                }
+                state.is_on_map = false;
+                state.ended = true;
+            }
+        }
+    }
+
+    // Между последним событием и `until` секундомер и текущая карта продолжают идти
+    let at = match (until, state.at) {
+        (Some(until), Some(last)) if !state.ended => until.max(last),
+        (_, last) => last.unwrap_or_default(),
+    };
+    if state.started_at.is_some() {
+        state.at = Some(at);
+    }
+    if let Some(resumed) = clock_resumed {
+        state.session_sec += (at - resumed).num_seconds().max(0);
+    }
+    if let Some(started) = map_started {
+        state.map_time_sec += (at - started).num_seconds().max(0);
+    }
+    state
+}
+
+/// Статистика на момент времени для ползунка
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayStats {
+    /// Границы журнала: ползунок ходит от начала до последнего события
+    pub session_started_at: Option<DateTime<Utc>>,
+    pub last_event_at: Option<DateTime<Utc>>,
+    pub at: Option<DateTime<Utc>>,
+    pub duration_sec: i64,
+    pub map_time_sec: i64,
+    pub maps_completed: i32,
+    pub abandoned_maps: i32,
+    pub total_items: i32,
+    pub unique_items: usize,
+    pub total_value: f64,
+    /// Комиссия при продаже дропа
+    pub total_fee: f64,
+    pub total_expenses: f64,
+    /// Доход за вычетом комиссии и трат
+    pub net_profit: f64,
+    /// Чистая прибыль в час
+    pub hourly_profit: f64,
+    /// Позиций дропа без известной к этому моменту цены
+    pub unpriced_items: usize,
+    pub is_paused: bool,
+    pub is_on_map: bool,
+    pub events_applied: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(min: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2025-03-01T12:00:00Z").unwrap().with_timezone(&Utc) + chrono::Duration::minutes(min)
+    }
+
+    fn entry(min: i64, event: JournalEvent) -> JournalEntry {
+        JournalEntry { ts: at(min), event }
+    }
+
+    fn drop_event(game_id: i64, quantity: i32, source_id: &str, unit_price: Option<f64>) -> JournalEvent {
+        JournalEvent::Drop { game_id, quantity, tier: None, source_id: source_id.into(), unit_price }
+    }
+
+    #[test]
+    fn reconstructs_state_at_any_moment() {
+        let map = |event_type| JournalEvent::MapChange { event_type, scene: "Map".into() };
+        let entries = vec![
+            entry(0, JournalEvent::SessionStart { preset_id: None }),
+            entry(1, map(MapEventType::EnterMap)),
+            entry(3, drop_event(1, 5, PRIMARY_LOG_SOURCE, Some(10.0))),
+            entry(4, drop_event(2, 1, "second", None)),
+            entry(6, map(MapEventType::ExitToHideout)),
+            entry(10, JournalEvent::Pause { reason: crate::types::PauseReason::Manual }),
+            entry(20, JournalEvent::Resume),
+            entry(25, JournalEvent::PriceUpdate { game_id: 1, price: 20.0 }),
+            entry(30, JournalEvent::SessionEnd),
+        ];
+
+        let early = replay(&entries, Some(at(5)), false, 0);
+        assert_eq!((early.session_sec, early.map_time_sec, early.maps_completed), (300, 240, 0));
+        assert!(early.is_on_map);
+        assert_eq!(early.drops.get(&2), Some(&1));
+        assert_eq!(replay(&entries, Some(at(5)), true, 0).drops.get(&2), None);
+
+        let paused = replay(&entries, Some(at(15)), false, 0);
+        assert_eq!((paused.session_sec, paused.map_time_sec, paused.maps_completed), (600, 300, 1));
+        assert!(paused.is_paused);
+        assert_eq!(paused.prices.get(&1), Some(&10.0));
+
+        let full = replay(&entries, None, false, 0);
+        assert!(full.ended);
+        assert_eq!(full.session_sec, 1200);
+        assert_eq!(full.prices.get(&1), Some(&20.0));
+        assert_eq!(full.events_applied, entries.len());
+    }
+
+    #[test]
+    fn applies_manual_edits() {
+        let entries = vec![
+            entry(0, JournalEvent::SessionStart { preset_id: None }),
+            entry(1, drop_event(1, 5, PRIMARY_LOG_SOURCE, Some(10.0))),
+            entry(2, drop_event(2, 1, PRIMARY_LOG_SOURCE, None)),
+            entry(3, JournalEvent::Adjust { game_id: 1, delta: -2, new_quantity: 3 }),
+            entry(4, JournalEvent::Remove { game_id: 2 }),
+            entry(5, JournalEvent::PriceUpdate { game_id: 1, price: 50.0 }),
+            entry(6, JournalEvent::TierPriceUpdate { game_id: 1, tier: 2, price: 70.0 }),
+            entry(7, JournalEvent::PriceRestore { game_id: 1, price: Some(10.0), tiers: HashMap::from([(1, 30.0)]) }),
+        ];
+
+        let state = replay(&entries, None, false, 0);
+        assert_eq!(state.drops, HashMap::from([(1, 3)]));
+        assert_eq!(state.prices.get(&1), Some(&10.0));
+        // Откат возвращает и цены тиров на момент до правки
+        assert_eq!(state.tier_prices.get(&1), Some(&HashMap::from([(1, 30.0)])));
+        let before_restore = replay(&entries, Some(at(6)), false, 0);
+        assert_eq!(before_restore.prices.get(&1), Some(&50.0));
+        assert_eq!(before_restore.tier_prices.get(&1), Some(&HashMap::from([(2, 70.0)])));
+    }
+
+    #[test]
+    fn clock_stops_while_app_is_closed() {
+        let entries = vec![
+            entry(0, JournalEvent::SessionStart { preset_id: None }),
+            entry(10, JournalEvent::Expense { id: "e1".into(), quantity: 2, price: 5.0 }),
+            entry(12, JournalEvent::Expense { id: "e2".into(), quantity: 1, price: 1.0 }),
+            entry(13, JournalEvent::ExpenseRemove { id: "e2".into() }),
+            // Приложение закрыто с 15-й по 60-ю минуту
+            entry(60, JournalEvent::Restored { offline_since: Some(at(15)) }),
+            entry(70, JournalEvent::Drop { game_id: 1, quantity: 3, tier: Some(0), source_id: PRIMARY_LOG_SOURCE.into(), unit_price: None }),
+            entry(71, JournalEvent::TierPriceUpdate { game_id: 1, tier: 0, price: 100.0 }),
+            entry(80, JournalEvent::SessionEnd),
+        ];
+
+        let state = replay(&entries, None, false, 0);
+        assert_eq!(state.session_sec, 35 * 60);
+        assert_eq!(state.expenses, HashMap::from([("e1".to_string(), (5.0, 2))]));
+        assert_eq!(state.drop_tiers[&1], HashMap::from([(0, 3)]));
+        assert_eq!(state.price_entries()[&1].tiers, HashMap::from([(0, 100.0)]));
+    }
+
+    #[test]
+    fn abandoned_cap() {
+        assert!(!is_abandoned_run(10 * 3600, 0));
+        assert!(!is_abandoned_run(60 * 60, 60));
+        assert!(is_abandoned_run(60 * 60 + 1, 60));
+
+        let map = |event_type| JournalEvent::MapChange { event_type, scene: "Map".into() };
+        let entries = vec![
+            entry(0, JournalEvent::SessionStart { preset_id: None }),
+            entry(1, map(MapEventType::EnterMap)),
+            entry(6, map(MapEventType::ExitToHideout)),
+            entry(7, map(MapEventType::EnterMap)),
+            entry(100, map(MapEventType::ExitToHideout)),
+        ];
+        let capped = replay(&entries, None, false, 60);
+        assert_eq!((capped.maps_completed, capped.abandoned_maps, capped.map_time_sec), (1, 1, 300));
+        let uncapped = replay(&entries, None, false, 0);
+        assert_eq!((uncapped.maps_completed, uncapped.abandoned_maps), (2, 0));
+    }
+}
@@ -0,0 +1,156 @@
+//! Текстовая сводка сессии для буфера обмена
+//!
+//! Длительность, карты, профит, профит в час и топ дропа одним блоком, готовым
+//! для вставки в Discord (Markdown), на форум (BBCode) или куда угодно (текст).
+
+use serde::Deserialize;
+
+use crate::value_format::fe;
+
+/// Сколько позиций дропа попадает в сводку
+pub const SUMMARY_TOP_DROPS: usize = 10;
+
+/// Разметка сводки
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryFormat {
+    #[default]
+    Text,
+    Markdown,
+    Bbcode,
+}
+
+/// Позиция топа дропа
+#[derive(Debug, Clone)]
+pub struct SummaryDrop {
+    pub name: String,
+    pub quantity: i32,
+    pub total_value: f64,
+    pub icon_url: Option<String>,
+}
+
+/// Данные сводки
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub duration_sec: i32,
+    pub maps_completed: i32,
+    pub income: f64,
+    pub expenses: f64,
+    /// Комиссия при продаже дропа
+    pub fees: f64,
+    /// Чистая прибыль в час
+    pub hourly_profit: f64,
+    /// Уже отсортирован по убыванию стоимости
+    pub top_drops: Vec<SummaryDrop>,
+}
+
+impl SessionSummary {
+    /// Чистый профит: доход минус траты и комиссия
+    pub fn profit(&self) -> f64 {
+        self.income - self.expenses - self.fees
+    }
+}
+
+pub(crate) fn label(language: &str, ru: &'static str, en: &'static str) -> &'static str {
+    if language == "ru" { ru } else { en }
+}
+
+pub(crate) fn duration(language: &str, sec: i32) -> String {
+    let (h, m) = (sec / 3600, sec % 3600 / 60);
+    let (hs, ms) = (label(language, "ч", "h"), label(language, "м", "m"));
+    if h > 0 { format!("{}{} {}{}", h, hs, m, ms) } else { format!("{}{}", m, ms) }
+}
+
+/// Собрать сводку в нужной разметке
+pub fn render(summary: &SessionSummary, format: SummaryFormat, language: &str) -> String {
+    let bold = |s: &str| match format {
+        SummaryFormat::Text => s.to_string(),
+        SummaryFormat::Markdown => format!("**{}**", s),
+        SummaryFormat::Bbcode => format!("[b]{}[/b]", s),
+    };
+    let profit = summary.profit();
+    let sign = if profit >= 0.0 { "+" } else { "" };
+
+    let mut lines = vec![
+        bold(label(language, "TLI Companion — сессия фарма", "TLI Companion — farm session")),
+        format!(
+            "{}: {} · {}: {}",
+            label(language, "Время", "Time"),
+            duration(language, summary.duration_sec),
+            label(language, "Карт", "Maps"),
+            summary.maps_completed,
+        ),
+        format!(
+            "{}: {} ({} {} · {} {}{})",
+            label(language, "Профит", "Profit"),
+            bold(&format!("{}{} FE", sign, fe(profit))),
+            label(language, "доход", "income"),
+            fe(summary.income),
+            label(language, "траты", "expenses"),
+            fe(summary.expenses),
+            if summary.fees > 0.0 {
+                format!(" · {} {}", label(language, "комиссия", "fees"), fe(summary.fees))
+            } else {
+                String::new()
+            },
+        ),
+        format!("{}: {} FE", label(language, "В час", "Per hour"), fe(summary.hourly_profit)),
+    ];
+
+    if !summary.top_drops.is_empty() {
+        lines.push(String::new());
+        lines.push(bold(label(language, "Топ дропа", "Top drops")));
+        if format == SummaryFormat::Bbcode {
+            lines.push("[list=1]".to_string());
+        }
+        for (i, drop) in summary.top_drops.iter().take(SUMMARY_TOP_DROPS).enumerate() {
+            let item = format!("{} ×{} — {} FE", drop.name, drop.quantity, fe(drop.total_value));
+            lines.push(match format {
+                SummaryFormat::Bbcode => format!("[*]{}", item),
+                _ => format!("{}. {}", i + 1, item),
+            });
+        }
+        if format == SummaryFormat::Bbcode {
+            lines.push("[/list]".to_string());
+        }
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary() -> SessionSummary {
+        SessionSummary {
+            duration_sec: 5400,
+            maps_completed: 12,
+            income: 1500.0,
+            expenses: 250.0,
+            fees: 0.0,
+            hourly_profit: 833.3,
+            top_drops: vec![SummaryDrop {
+                name: "Flame Elementium".to_string(),
+                quantity: 3,
+                total_value: 450.0,
+                icon_url: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn renders_each_format() {
+        let text = render(&summary(), SummaryFormat::Text, "en");
+        assert!(text.contains("Time: 1h 30m · Maps: 12"));
+        assert!(text.contains("Profit: +1250 FE"));
+        assert!(text.contains("1. Flame Elementium ×3 — 450 FE"));
+
+        let md = render(&summary(), SummaryFormat::Markdown, "ru");
+        assert!(md.starts_with("**TLI Companion"));
+        assert!(md.contains("Время: 1ч 30м"));
+
+        let bb = render(&summary(), SummaryFormat::Bbcode, "en");
+        assert!(bb.contains("[list=1]\n[*]Flame Elementium ×3 — 450 FE\n[/list]"));
+    }
+}
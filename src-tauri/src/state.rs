@@ -2,20 +2,52 @@
 //! 
 //! Управляет состоянием сессии фарма, кэшем предметов и настройками.
 
-use std::collections::HashMap;
-use tokio::sync::RwLock;
+use std::collections::{HashMap, HashSet, VecDeque};
+use tokio::sync::{watch, Notify, RwLock};
 use chrono::{DateTime, Utc};
-use log::{info, debug};
+use log::{info, debug, warn};
+use serde::Serialize;
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 use crate::types::{
-    AppSettings, FarmSessionState, ItemInfo, SessionStats, 
-    ItemDropEvent, MapChangeEvent, MapEventType, AggregatedDrop, ExpenseEntry, ManualDropEntry
+    AppSettings, FarmSessionState, ObsTrigger, ItemInfo, SessionStats, 
+    ItemDropEvent, MapChangeEvent, MapEventType, AggregatedDrop, ExpenseEntry, ManualDropEntry, ManualDropSale,
+    LogWatchMode, LogSourceMode, SourceSessionStats, PRIMARY_LOG_SOURCE,
+    PricecheckItem, PricecheckQueue, PricecheckReason, SessionGoals, SessionTemplate,
+    QuantityAdjustment, CompanionWindowSettings, GameClient,
+    InventorySnapshotEvent, NetWorth, NetWorthCategory, Holding, HoldingValue, PortfolioValue,
+    LogAttachMode, CurrencyPoint, CurrencyReading, CurrencyReadingSource, SessionCurrency, BASE_CURRENCY_ID,
+    MapRun, PersonalRecords, RecordBrokenEvent, ZoneKind, PauseReason, PauseRecord, ProfitAlarm, ProfitAlarmEvent,
+    DropsQuery, DropsPage, ValuePoint, LogEvent, LastManualPrice, ManualPriceKind, PriceSuggestion,
+    PriceSearchEvent, QuotedPrice, SessionRevaluation, ProxySettings, BackendEndpoint, BackendCheckResult,
 };
 use crate::log_parser::LogParser;
+use crate::log_patterns::{LogPatterns, PatternsReloadResult};
+use crate::file_watcher::{CatchupProgress, CatchupRegistry, WatcherConfig};
+use crate::noise_filter::NoiseFilter;
+use crate::exchange_rates::{ExchangeRateSeries, ExchangeRates};
+use crate::league_archive::{self, ArchivedLeague, ArchivedLeagueView, LeagueStatus};
+use crate::schema_handshake::HandshakeStatus;
 use crate::persistence;
-use crate::auth::{AuthSession};
+use crate::valuation::{FeeModel, Valuation};
+use crate::item_search::ItemSearchIndex;
+use crate::categories::{CategoryCatalog, CategoryView};
+use crate::drop_sounds::SoundPlayer;
+use crate::drop_announcer::DropAnnouncer;
+use crate::twitch::TwitchChat;
+use crate::event_journal::{EventJournal, JournalEvent};
+use crate::unknown_items::{UnknownItem, UnknownItems};
+use crate::drop_dedup::SlotTracker;
+use crate::gamble::{GambleOpening, OpeningTracker};
+use crate::panel_windows::{DetachedPanel, PanelKind, WindowSubscriptions};
+use crate::drop_rates::MapLogEntry;
+use crate::records;
+use crate::xlsx_export::DropRow;
+use crate::supabase_client::{CircuitBreaker, ConnectivityStatus, SupabaseClient};
+use crate::price_sync::PriceSyncStatus;
+use crate::price_confidence::{self, PriceConfidence, SampleStats};
+use crate::auth::{AuthChangeReason, AuthSession, AuthStateChange, RefreshError};
 
 /// Глобальное состояние приложения
 pub struct AppState {
@@ -25,6 +57,20 @@ pub struct AppState {
     pub session: RwLock<FarmSessionState>,
     /// Кэш информации о предметах (game_id -> ItemInfo)
     pub items_cache: RwLock<HashMap<i64, ItemInfo>>,
+    /// Справочник категорий с сервера (имена, порядок, группы)
+    pub categories: RwLock<CategoryCatalog>,
+    /// Проигрыватель звуков на дроп
+    sound_player: SoundPlayer,
+    /// Голосовые объявления ценных дропов
+    announcer: DropAnnouncer,
+    /// Объявления в чат Twitch
+    pub twitch: TwitchChat,
+    /// Частота срабатываний действий OBS
+    obs_limiter: crate::obs::TriggerLimiter,
+    /// Журнал событий сессии (NDJSON)
+    journal: EventJournal,
+    /// Индекс поиска по именам предметов (перестраивается вместе с items_cache)
+    pub item_search: RwLock<ItemSearchIndex>,
     /// Кэш текущих цен (game_id -> price)
     pub prices_cache: RwLock<HashMap<i64, persistence::PersistedPriceEntry>>,
     /// Флаг подключения к серверу (зарезервировано для будущего)
@@ -32,19 +78,160 @@ pub struct AppState {
     pub is_connected: RwLock<bool>,
     /// Путь к файлу логов
     pub log_path: RwLock<Option<String>>,
+    /// Запущенные источники логов: source_id -> путь к файлу
+    pub log_sources: RwLock<HashMap<String, String>>,
     /// Auth session (access token in-memory)
     pub auth_session: RwLock<Option<AuthSession>>,
     /// Cancel flag for in-progress OAuth login
     pub auth_oauth_cancel: RwLock<Option<Arc<AtomicBool>>>,
+    /// Общий HTTP-клиент (таймауты, пул соединений, прокси) для запросов к Supabase;
+    /// пересобирается при смене настроек прокси
+    http: Mutex<reqwest::Client>,
+    /// Состояние планировщика обновления цен
+    price_sync_status: RwLock<PriceSyncStatus>,
+    /// Результат проверки совместимости с Supabase (None — ещё не проверяли)
+    schema_handshake: RwLock<Option<HandshakeStatus>>,
+    /// Общий для всех запросов к Supabase предохранитель (быстрый отказ, пока сервер лежит)
+    supabase_breaker: Arc<CircuitBreaker>,
+    /// Цены, изменённые после последней записи prices_cache.json
+    prices_dirty: Mutex<HashSet<i64>>,
+    /// Состояния слотов инвентаря для отсечения повторных строк лога
+    slot_tracker: Mutex<SlotTracker>,
+    /// Подобранные предметы, которых нет в справочнике
+    unknown_items: Mutex<UnknownItems>,
+    /// game_id всего справочника: items_cache ограничен по размеру, а выброшенный
+    /// из кэша предмет неизвестным не считается
+    catalog_ids: Mutex<HashSet<i64>>,
+    /// Когда предмет падал последний раз (порядок выброса из items_cache)
+    item_last_used: Mutex<HashMap<i64, DateTime<Utc>>>,
+    /// Сбор открытий контейнеров из лога (для EV)
+    opening_tracker: Mutex<OpeningTracker>,
+    /// Запущена ли игра по последней проверке процесса (None — ещё не знаем или не определить)
+    game_running: Mutex<Option<bool>>,
+    /// Подписки окон на события (см. panel_windows::broadcast)
+    pub window_subscriptions: Mutex<WindowSubscriptions>,
+    /// Live-трансляция текущей сессии (None — выключена)
+    pub live_share: RwLock<Option<crate::live_share::SharedTask>>,
+    /// Локальный API для контроллеров (None — выключен)
+    pub controller_api: RwLock<Option<crate::controller_api::ControllerServer>>,
+    /// Наблюдение за чужой трансляцией (None — не наблюдаем)
+    pub spectating: RwLock<Option<crate::live_share::SharedTask>>,
+    /// Участие в групповой сессии (None — не в группе)
+    pub party: RwLock<Option<crate::party::PartyMembership>>,
+    /// Главное окно в режиме «сквозь клики» (см. overlay_mode)
+    pub click_through: AtomicBool,
+    /// Счётчик отложенных сохранений геометрии окон (пишет только последнее)
+    geometry_save_gen: AtomicU64,
+    /// Последний снимок инвентаря (None — ещё не загружен с диска и не приходил из лога)
+    inventory_snapshot: RwLock<Option<InventorySnapshotEvent>>,
+    /// Личные рекорды по картам (None — ещё не загружены с диска)
+    personal_records: RwLock<Option<PersonalRecords>>,
+    /// Последние цены ручного ввода по предметам (None — ещё не загружены с диска)
+    manual_prices: RwLock<Option<HashMap<i64, LastManualPrice>>>,
+    /// Курсы торговых валют к FE (None — ещё не загружены с диска)
+    exchange_rates: RwLock<Option<ExchangeRates>>,
+    /// Изменения статуса авторизации (вход/выход/протухшая сессия) — для события в UI и менеджера токенов
+    pub auth_events: watch::Sender<AuthStateChange>,
+    /// Один refresh за раз: refresh token одноразовый, параллельный обмен его потеряет
+    auth_refresh_lock: tokio::sync::Mutex<()>,
+    /// session_templates.json — правки шаблонов по одной
+    pub templates_lock: tokio::sync::Mutex<()>,
+    /// История сессий читается и переписывается целиком: сохранение, синхронизация,
+    /// слияние, удаление и импорт — по одному
+    pub history_lock: tokio::sync::Mutex<()>,
+    /// farm_blocks.json читается и переписывается целиком: команды и планировщик — по одному
+    pub farm_blocks_lock: tokio::sync::Mutex<()>,
+    /// portfolio_*.json читается и переписывается целиком: правки портфеля — по одной
+    pub portfolio_lock: tokio::sync::Mutex<()>,
+    /// recipes.json — так же, правки рецептов по одной
+    pub recipes_lock: tokio::sync::Mutex<()>,
+    /// Очередь строк Google Sheets читается и переписывается целиком: отправки — по одной
+    pub sheets_queue_lock: tokio::sync::Mutex<()>,
     /// Общий парсер логов (нужен, чтобы сбрасывать кэш слотов при старте сессии)
     #[allow(dead_code)]
     pub log_parser: Arc<Mutex<LogParser>>,
+    /// Парсеры дополнительных клиентов: source_id -> парсер
+    pub source_parsers: RwLock<HashMap<String, Arc<Mutex<LogParser>>>>,
+    /// Действующий набор паттернов лога
+    pub log_patterns: RwLock<LogPatterns>,
     /// Флаг паузы сессии — если true, дропы не записываются
     pub is_paused: RwLock<bool>,
+    /// Последний дроп или смена карты в логе (по часам приложения) — для AFK-автопаузы.
+    /// До первой активности — запуск приложения: восстановленная сессия не уходит в AFK сразу
+    last_log_activity: Mutex<DateTime<Utc>>,
+    /// Механизм отслеживания лог-файла (общий с LogWatcher, применяется на лету)
+    pub watcher_config: Arc<Mutex<WatcherConfig>>,
+    /// Прогресс догонялки лога по источникам (общий с LogWatcher)
+    pub catchup_progress: CatchupRegistry,
+    /// Отсев шумных строк лога (общий с LogWatcher, набор меняется на лету)
+    pub noise_filter: Arc<NoiseFilter>,
+    /// Отложенные в очереди прайсчека предметы (в порядке откладывания)
+    pub pricecheck_skipped: RwLock<Vec<i64>>,
+    /// Журнал ручных правок сессии (undo/redo)
+    pub edit_journal: RwLock<EditJournal>,
+    /// Сигнал "состояние изменилось" для рассылки session-stats-updated/drops-updated
+    pub state_changed: Notify,
 }
 
 const PRICE_TTL_SEC: i64 = 60 * 60; // 1 hour
 
+/// Сколько точек кривой стоимости держать; дальше ряд прореживается вдвое
+const VALUE_TIMELINE_MAX_POINTS: usize = 720;
+
+/// Сколько последних ручных правок можно отменить
+const EDIT_JOURNAL_LIMIT: usize = 50;
+
+/// Карта, ждущая оценки: индекс, дроп (game_id -> количество) и количества по тирам
+type PendingMapRun = (i32, HashMap<i64, i32>, HashMap<i64, HashMap<i32, i32>>);
+
+/// Дроп старше этого (сек) — прочитан из истории лога, а не только что: без звука и объявлений
+const ALERT_MAX_AGE_SEC: i64 = 10;
+
+/// Геометрия окон пишется на диск через столько после последнего перемещения/ресайза
+const GEOMETRY_SAVE_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Ручная правка активной сессии (запись журнала undo/redo)
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SessionEdit {
+    AddExpense { entry: ExpenseEntry },
+    RemoveExpense { index: usize, entry: ExpenseEntry },
+    AddManualDrop { entry: ManualDropEntry },
+    /// Пачка ручного дропа (вставка таблицы) — отменяется целиком за один шаг
+    AddManualDrops { entries: Vec<ManualDropEntry> },
+    RemoveManualDrop { index: usize, entry: ManualDropEntry },
+    /// Ручная цена предмета (или одного тира); previous — запись кэша до правки
+    PricePin {
+        game_id: i64,
+        tier: Option<i32>,
+        price: f64,
+        previous: Option<persistence::PersistedPriceEntry>,
+    },
+    /// Отметка о продаже ручного дропа; previous — отметка до правки
+    ManualDropSale {
+        id: String,
+        sale: Option<ManualDropSale>,
+        previous: Option<ManualDropSale>,
+    },
+}
+
+/// Журнал ручных правок: отменённые правки уходят в redo,
+/// любая новая правка очищает redo
+#[derive(Debug, Default)]
+pub struct EditJournal {
+    undo: VecDeque<SessionEdit>,
+    redo: Vec<SessionEdit>,
+}
+
+impl EditJournal {
+    fn push_undo(&mut self, edit: SessionEdit) {
+        self.undo.push_back(edit);
+        while self.undo.len() > EDIT_JOURNAL_LIMIT {
+            self.undo.pop_front();
+        }
+    }
+}
+
 impl AppState {
     /// Создать новое состояние
     pub fn new(log_parser: Arc<Mutex<LogParser>>) -> Self {
@@ -52,19 +239,178 @@ impl AppState {
             settings: RwLock::new(AppSettings::default()),
             session: RwLock::new(FarmSessionState::default()),
             items_cache: RwLock::new(HashMap::new()),
+            categories: RwLock::new(CategoryCatalog::default()),
+            sound_player: SoundPlayer::default(),
+            announcer: DropAnnouncer::default(),
+            twitch: TwitchChat::default(),
+            obs_limiter: crate::obs::TriggerLimiter::default(),
+            journal: EventJournal::default(),
+            item_search: RwLock::new(ItemSearchIndex::default()),
             prices_cache: RwLock::new(HashMap::new()),
             is_connected: RwLock::new(false),
             log_path: RwLock::new(None),
+            log_sources: RwLock::new(HashMap::new()),
             auth_session: RwLock::new(None),
             auth_oauth_cancel: RwLock::new(None),
+            http: Mutex::new(crate::supabase_client::build_http_client()),
+            price_sync_status: RwLock::new(PriceSyncStatus::default()),
+            schema_handshake: RwLock::new(None),
+            supabase_breaker: Arc::new(CircuitBreaker::default()),
+            prices_dirty: Mutex::new(HashSet::new()),
+            slot_tracker: Mutex::new(SlotTracker::default()),
+            unknown_items: Mutex::new(UnknownItems::default()),
+            catalog_ids: Mutex::new(HashSet::new()),
+            item_last_used: Mutex::new(HashMap::new()),
+            opening_tracker: Mutex::new(OpeningTracker::default()),
+            game_running: Mutex::new(None),
+            window_subscriptions: Mutex::new(WindowSubscriptions::default()),
+            live_share: RwLock::new(None),
+            controller_api: RwLock::new(None),
+            spectating: RwLock::new(None),
+            party: RwLock::new(None),
+            click_through: AtomicBool::new(false),
+            geometry_save_gen: AtomicU64::new(0),
+            inventory_snapshot: RwLock::new(None),
+            personal_records: RwLock::new(None),
+            manual_prices: RwLock::new(None),
+            exchange_rates: RwLock::new(None),
+            auth_events: watch::Sender::new(AuthStateChange {
+                is_logged_in: false,
+                email: None,
+                reason: AuthChangeReason::SignedOut,
+            }),
+            auth_refresh_lock: tokio::sync::Mutex::new(()),
+            templates_lock: tokio::sync::Mutex::new(()),
+            history_lock: tokio::sync::Mutex::new(()),
+            farm_blocks_lock: tokio::sync::Mutex::new(()),
+            portfolio_lock: tokio::sync::Mutex::new(()),
+            recipes_lock: tokio::sync::Mutex::new(()),
+            sheets_queue_lock: tokio::sync::Mutex::new(()),
             log_parser,
+            source_parsers: RwLock::new(HashMap::new()),
+            log_patterns: RwLock::new(LogPatterns::bundled()),
             is_paused: RwLock::new(false),
+            last_log_activity: Mutex::new(Utc::now()),
+            watcher_config: Arc::new(Mutex::new(WatcherConfig::default())),
+            catchup_progress: Arc::new(Mutex::new(HashMap::new())),
+            noise_filter: Arc::new(NoiseFilter::default()),
+            pricecheck_skipped: RwLock::new(Vec::new()),
+            edit_journal: RwLock::new(EditJournal::default()),
+            state_changed: Notify::new(),
         }
     }
+    
+    /// Сообщить рассыльщику событий, что статистика/дроп могли измениться.
+    /// Частые изменения схлопываются: рассыльщик ждёт permit и делает один снимок.
+    pub fn notify_changed(&self) {
+        self.state_changed.notify_one();
+    }
 
     pub async fn set_auth_session(&self, session: Option<AuthSession>) {
-        let mut s = self.auth_session.write().await;
-        *s = session;
+        let reason = if session.is_some() { AuthChangeReason::SignedIn } else { AuthChangeReason::SignedOut };
+        self.replace_auth_session(session, reason).await;
+    }
+
+    /// Заменить сессию и разослать событие, если сменился пользователь или статус входа
+    /// (обычный refresh токена события не порождает)
+    async fn replace_auth_session(&self, session: Option<AuthSession>, reason: AuthChangeReason) {
+        let change = AuthStateChange {
+            is_logged_in: session.is_some(),
+            email: session.as_ref().and_then(|x| x.user_email.clone()),
+            reason,
+        };
+        if let Some(user_id) = session.as_ref().and_then(|x| x.user_id.clone()) {
+            Self::remember_linked_account(&user_id, change.email.clone());
+        }
+        {
+            let mut s = self.auth_session.write().await;
+            *s = session;
+        }
+        self.auth_events.send_if_modified(|current| {
+            let modified = current.is_logged_in != change.is_logged_in || current.email != change.email;
+            if modified {
+                *current = change;
+            }
+            modified
+        });
+    }
+
+    /// Запомнить аккаунт для быстрого переключения: копия актуального refresh token
+    /// (он ротируется при каждом обновлении) + запись в индексе аккаунтов
+    fn remember_linked_account(user_id: &str, email: Option<String>) {
+        match crate::auth::load_refresh_token() {
+            Ok(Some(token)) => {
+                if let Err(e) = crate::auth::store_account_token(user_id, &token) {
+                    warn!("Failed to store token of linked account: {}", e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read refresh token for linked account: {}", e),
+        }
+        if let Err(e) = persistence::upsert_linked_account(user_id, email) {
+            warn!("Failed to save linked accounts: {}", e);
+        }
+    }
+
+    /// Выйти и забыть все привязанные аккаунты (их токены действуют только на прежнем сервере)
+    async fn sign_out_all_accounts(&self) {
+        let _guard = self.auth_refresh_lock.lock().await;
+        if let Err(e) = crate::auth::clear_refresh_token() {
+            warn!("Failed to clear refresh token: {}", e);
+        }
+        match persistence::load_linked_accounts() {
+            Ok(accounts) => {
+                for account in accounts {
+                    self.forget_linked_account(&account.user_id);
+                }
+            }
+            Err(e) => warn!("Failed to load linked accounts: {}", e),
+        }
+        if self.is_logged_in().await {
+            info!("Backend changed, signing out");
+        }
+        self.replace_auth_session(None, AuthChangeReason::SignedOut).await;
+    }
+
+    /// Забыть аккаунт (выход или отозванный токен)
+    pub fn forget_linked_account(&self, user_id: &str) {
+        crate::auth::clear_account_token(user_id);
+        if let Err(e) = persistence::remove_linked_account(user_id) {
+            warn!("Failed to save linked accounts: {}", e);
+        }
+    }
+
+    /// Переключиться на другой привязанный аккаунт: обменять его refresh token
+    /// на новую сессию. История читается с диска по user_id, так что после смены
+    /// токена команды сразу видят данные нового аккаунта; активную сессию до этого
+    /// завершает команда switch_account.
+    pub async fn switch_auth_account(
+        &self,
+        api: &SupabaseClient,
+        user_id: &str,
+    ) -> Result<(), String> {
+        let _guard = self.auth_refresh_lock.lock().await;
+        let token = crate::auth::load_account_token(user_id)?
+            .ok_or_else(|| "No stored session for this account, sign in again".to_string())?;
+
+        match crate::auth::refresh_access_token(api, &token).await {
+            Ok(sess) => {
+                info!("Switched account to {}", user_id);
+                self.set_auth_session(Some(sess)).await;
+                Ok(())
+            }
+            Err(e) if e.is_transient() => Err(e.to_string()),
+            Err(e) => {
+                self.forget_linked_account(user_id);
+                Err(format!("Account session expired, sign in again: {}", e))
+            }
+        }
+    }
+
+    /// Когда истекает текущий access token (None — не залогинен)
+    pub async fn auth_expires_at(&self) -> Option<DateTime<Utc>> {
+        let s = self.auth_session.read().await;
+        s.as_ref().map(|x| x.expires_at)
     }
 
     pub async fn get_auth_email(&self) -> Option<String> {
@@ -83,42 +429,89 @@ impl AppState {
     }
 
     /// Получить валидный access token (refresh при необходимости).
-    pub async fn get_valid_access_token(
+    pub async fn get_valid_access_token(&self, api: &SupabaseClient) -> Option<String> {
+        match self.refresh_auth_session(api, chrono::Duration::zero()).await {
+            Ok(token) => token,
+            Err(e) => {
+                debug!("Access token unavailable: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Обновить access token, если он истекает раньше чем через `min_valid`.
+    /// Ok(None) — пользователь не залогинен (нет refresh token).
+    /// При временной ошибке сессия сохраняется; при отказе сервера — сбрасывается
+    /// вместе с refresh token и в UI уходит событие SessionExpired.
+    pub async fn refresh_auth_session(
         &self,
-        http: &reqwest::Client,
-        cfg: &crate::supabase_sync::SupabaseConfig,
-    ) -> Option<String> {
+        api: &SupabaseClient,
+        min_valid: chrono::Duration,
+    ) -> Result<Option<String>, RefreshError> {
+        let valid_token = |s: &Option<AuthSession>| {
+            s.as_ref()
+                .filter(|sess| Utc::now() + min_valid < sess.expires_at)
+                .map(|sess| sess.access_token.clone())
+        };
+
         // fast-path
-        {
-            let s = self.auth_session.read().await;
-            if let Some(sess) = s.as_ref() {
-                if Utc::now() < sess.expires_at {
-                    return Some(sess.access_token.clone());
-                }
-            }
+        if let Some(token) = valid_token(&*self.auth_session.read().await) {
+            return Ok(Some(token));
+        }
+
+        let _guard = self.auth_refresh_lock.lock().await;
+        // Пока ждали блокировку, токен мог обновить другой вызов
+        if let Some(token) = valid_token(&*self.auth_session.read().await) {
+            return Ok(Some(token));
         }
 
         // refresh-path (requires refresh token from keychain)
-        let refresh = crate::auth::load_refresh_token().ok().flatten()?;
-        match crate::auth::refresh_access_token(http, cfg, &refresh).await {
+        let Some(refresh) = crate::auth::load_refresh_token().map_err(RefreshError::Transient)? else {
+            return Ok(None);
+        };
+        match crate::auth::refresh_access_token(api, &refresh).await {
             Ok(new_sess) => {
                 let token = new_sess.access_token.clone();
                 self.set_auth_session(Some(new_sess)).await;
-                Some(token)
+                Ok(Some(token))
             }
-            Err(_) => {
-                // если refresh не удался — сбрасываем сессию
-                self.set_auth_session(None).await;
-                None
+            Err(e) if e.is_transient() => {
+                warn!("Token refresh failed, keeping session: {}", e);
+                Err(e)
+            }
+            Err(e) => {
+                warn!("Refresh token rejected, signing out: {}", e);
+                if let Err(clear_err) = crate::auth::clear_refresh_token() {
+                    warn!("Failed to clear rejected refresh token: {}", clear_err);
+                }
+                if let Some(user_id) = self.get_auth_user_id().await {
+                    self.forget_linked_account(&user_id);
+                }
+                self.replace_auth_session(None, AuthChangeReason::SessionExpired).await;
+                Err(e)
             }
         }
     }
 
     pub async fn load_settings_from_disk(&self) {
         match persistence::load_settings() {
-            Ok(Some(settings)) => {
+            Ok(Some(mut settings)) => {
+                settings.language = crate::types::normalize_language(&settings.language);
+                *self.watcher_config.lock().unwrap() = WatcherConfig::from_settings(&settings);
+                self.noise_filter.configure(&settings.log_noise_patterns);
+                match crate::app_logs::parse_level(&settings.log_level) {
+                    Ok(level) => crate::app_logs::set_level(level),
+                    Err(e) => debug!("{}", e),
+                }
+                // Неверный прокси с диска: сеть закрыта до исправления настроек
+                if let Err(e) = self.apply_proxy_settings(&settings.proxy) {
+                    warn!("{}, network requests are blocked", e);
+                    *self.http.lock().unwrap() = crate::proxy::blocked_client();
+                }
                 let mut s = self.settings.write().await;
                 *s = settings;
+                drop(s);
+                self.sync_log_time_zone().await;
                 debug!("Loaded settings from disk");
             }
             Ok(None) => {}
@@ -135,11 +528,290 @@ impl AppState {
         }
     }
 
+    /// Сохранить настройки, когда окно перестанут двигать: каждое перемещение
+    /// откладывает запись на GEOMETRY_SAVE_DELAY
+    pub fn schedule_geometry_save(self: &Arc<Self>) {
+        let generation = self.geometry_save_gen.fetch_add(1, Ordering::Relaxed) + 1;
+        let state = self.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(GEOMETRY_SAVE_DELAY).await;
+            if state.geometry_save_gen.load(Ordering::Relaxed) == generation {
+                state.save_settings_to_disk().await;
+            }
+        });
+    }
+
+    /// Штатный выход: снять флаг работы у сохранённой сессии, чтобы следующий
+    /// запуск не принял её за восстановленную после сбоя
+    pub async fn mark_clean_exit(&self) {
+        self.set_app_running(false).await;
+    }
+
+    /// Выход не состоялся (например, установка обновления не удалась): сессия снова «живая»
+    pub async fn mark_app_running(&self) {
+        self.set_app_running(true).await;
+    }
+
+    async fn set_app_running(&self, running: bool) {
+        let mut session = self.session.write().await;
+        if session.started_at.is_none() {
+            return;
+        }
+        let now = Utc::now();
+        Self::fold_session_clock(&mut session, now);
+        session.app_running = running;
+        session.heartbeat_at = Some(now);
+        Self::save_session_durable(&session);
+    }
+
+    /// Отметка «приложение живо» в файле сессии (см. FarmSessionState::heartbeat_at)
+    pub async fn session_heartbeat(&self) {
+        let mut session = self.session.write().await;
+        if session.started_at.is_none() {
+            return;
+        }
+        let now = Utc::now();
+        Self::fold_session_clock(&mut session, now);
+        session.heartbeat_at = Some(now);
+        Self::save_session_durable(&session);
+    }
+
+    /// Всё, что нужно записать перед выходом: настройки (геометрия окон живёт в памяти),
+    /// отложенные цены и флаг штатного выхода у сессии
+    pub async fn prepare_exit(&self) {
+        self.save_settings_to_disk().await;
+        self.flush_prices_cache().await;
+        self.mark_clean_exit().await;
+    }
+
+    /// Изменить настройки окна-компаньона в памяти (без записи на диск:
+    /// геометрия меняется десятки раз в секунду, пока окно тащат — см. schedule_geometry_save)
+    pub async fn update_companion_window<F>(&self, update: F) -> CompanionWindowSettings
+    where
+        F: FnOnce(&mut CompanionWindowSettings),
+    {
+        let mut s = self.settings.write().await;
+        update(&mut s.companion_window);
+        s.companion_window.opacity = s.companion_window.opacity.clamp(0.2, 1.0);
+        s.companion_window.width = s.companion_window.width.max(120);
+        s.companion_window.height = s.companion_window.height.max(60);
+        s.companion_window.clone()
+    }
+    
+    /// Запомнить видимость окна-компаньона и сохранить настройки на диск
+    pub async fn set_companion_visible(&self, visible: bool) -> CompanionWindowSettings {
+        let cfg = self.update_companion_window(|c| c.visible = visible).await;
+        self.save_settings_to_disk().await;
+        cfg
+    }
+
+    /// Обновить геометрию открытой панели (в памяти; на диск — при открытии/закрытии)
+    pub async fn update_detached_panel<F>(&self, kind: PanelKind, update: F)
+    where
+        F: FnOnce(&mut DetachedPanel),
+    {
+        let mut s = self.settings.write().await;
+        if let Some(panel) = s.detached_panels.iter_mut().find(|p| p.kind == kind) {
+            update(panel);
+            panel.width = panel.width.max(160);
+            panel.height = panel.height.max(80);
+        }
+    }
+
+    /// Отметить панель открытой/закрытой и сохранить настройки. Возвращает её геометрию
+    pub async fn set_panel_open(&self, kind: PanelKind, open: bool) -> DetachedPanel {
+        let panel = {
+            let mut s = self.settings.write().await;
+            let existing = s.detached_panels.iter().position(|p| p.kind == kind);
+            match (open, existing) {
+                (true, Some(index)) => s.detached_panels[index].clone(),
+                (true, None) => {
+                    let panel = DetachedPanel::new(kind);
+                    s.detached_panels.push(panel.clone());
+                    panel
+                }
+                (false, Some(index)) => s.detached_panels.remove(index),
+                (false, None) => DetachedPanel::new(kind),
+            }
+        };
+        self.save_settings_to_disk().await;
+        panel
+    }
+
+    /// Применить настройки отслеживания лога из AppSettings к работающему watcher'у
+    pub async fn sync_watcher_config(&self) {
+        let s = self.settings.read().await;
+        *self.watcher_config.lock().unwrap() = WatcherConfig::from_settings(&s);
+        self.noise_filter.configure(&s.log_noise_patterns);
+        drop(s);
+        self.sync_log_time_zone().await;
+    }
+
+    /// Применить пояс меток времени лога из настроек ко всем парсерам
+    pub async fn sync_log_time_zone(&self) {
+        let (time_zone, offset) = {
+            let s = self.settings.read().await;
+            (s.log_time_zone, s.log_utc_offset_minutes)
+        };
+        let mut parsers = vec![self.log_parser.clone()];
+        parsers.extend(self.source_parsers.read().await.values().cloned());
+        for parser in parsers {
+            if let Ok(mut p) = parser.lock() {
+                p.set_time_zone(time_zone, offset);
+            }
+        }
+    }
+
+    /// Сменить механизм отслеживания лога (сохраняется в настройки, применяется сразу)
+    pub async fn set_watcher_config(&self, mode: LogWatchMode, poll_interval_ms: u64) -> WatcherConfig {
+        let config = WatcherConfig::new(mode, poll_interval_ms);
+        {
+            let mut s = self.settings.write().await;
+            s.log_watch_mode = config.mode;
+            s.log_poll_interval_ms = config.poll_interval_ms;
+        }
+        self.save_settings_to_disk().await;
+        *self.watcher_config.lock().unwrap() = config;
+        info!("Watcher config applied: {:?}, poll every {} ms", config.mode, config.poll_interval_ms);
+        config
+    }
+
+    /// Текущая конфигурация отслеживания лога
+    pub fn get_watcher_config(&self) -> WatcherConfig {
+        *self.watcher_config.lock().unwrap()
+    }
+
     pub async fn resolve_supabase_config(&self) -> Option<crate::supabase_sync::SupabaseConfig> {
+        // A self-hosted backend from settings wins (it is only saved after a check).
+        if let Some(cfg) = crate::supabase_sync::SupabaseConfig::from_endpoint(&self.settings.read().await.backend) {
+            return Some(cfg);
+        }
         // For distributed builds, defaults are embedded in code (public anon key).
         // For dev/CI, env can override.
         Some(crate::supabase_sync::SupabaseConfig::from_env_or_compile()?)
     }
+
+    /// Переключить сервер цен. Свой сервер сохраняется, только если он отвечает и его
+    /// схема совместима (tli_client_meta с учётом префикса); выключение — без проверки
+    pub async fn apply_backend_endpoint(&self, endpoint: BackendEndpoint) -> BackendCheckResult {
+        if endpoint.enabled {
+            let rejected = |connection, handshake, error| BackendCheckResult { applied: false, connection, handshake, error };
+            let cfg = match crate::supabase_sync::SupabaseConfig::validate_endpoint(&endpoint) {
+                Ok(cfg) => cfg,
+                Err(e) => return rejected(None, None, Some(e)),
+            };
+            let http = self.http();
+            let connection = crate::proxy::test_connection(&http, &cfg).await;
+            if !connection.ok {
+                return rejected(Some(connection), None, None);
+            }
+            // Отдельный предохранитель: неудачная проверка не должна «ронять» текущий сервер
+            let api = SupabaseClient::new(http, cfg, Arc::new(CircuitBreaker::default()));
+            let handshake = match crate::supabase_sync::fetch_client_meta(&api).await {
+                Ok(meta) => HandshakeStatus::evaluate(env!("CARGO_PKG_VERSION"), meta, Utc::now()),
+                Err(e) => return rejected(Some(connection), None, Some(e.to_string())),
+            };
+            if handshake.needs_update() {
+                return rejected(Some(connection), Some(handshake), None);
+            }
+            info!("Switching to self-hosted backend {}", endpoint.url.trim());
+            self.switch_backend(endpoint, Some(handshake.clone())).await;
+            return BackendCheckResult { applied: true, connection: Some(connection), handshake: Some(handshake), error: None };
+        }
+
+        info!("Switching back to the built-in backend");
+        self.switch_backend(endpoint, None).await;
+        BackendCheckResult { applied: true, connection: None, handshake: None, error: None }
+    }
+
+    async fn switch_backend(&self, endpoint: BackendEndpoint, handshake: Option<HandshakeStatus>) {
+        // Сессия и refresh token выданы прежним сервером — новому их отправлять нельзя.
+        // Выходим до смены адреса, чтобы token_manager не успел обновить токен на новом
+        if self.settings.read().await.backend != endpoint {
+            self.sign_out_all_accounts().await;
+        }
+        self.settings.write().await.backend = endpoint;
+        self.save_settings_to_disk().await;
+        // Ошибки прошлого сервера к новому не относятся
+        self.supabase_breaker.reset();
+        *self.schema_handshake.write().await = handshake;
+    }
+
+    pub async fn get_price_sync_status(&self) -> PriceSyncStatus {
+        self.price_sync_status.read().await.clone()
+    }
+
+    pub async fn update_price_sync_status<F>(&self, update: F)
+    where
+        F: FnOnce(&mut PriceSyncStatus),
+    {
+        update(&mut *self.price_sync_status.write().await);
+    }
+
+    /// Проверить совместимость с Supabase (tli_client_meta). Ошибка запроса — не повод
+    /// пугать пользователя: статус остаётся прежним
+    pub async fn run_schema_handshake(&self, api: &SupabaseClient) -> Option<HandshakeStatus> {
+        if self.settings.read().await.offline_mode {
+            return None;
+        }
+        let meta = match crate::supabase_sync::fetch_client_meta(api).await {
+            Ok(meta) => meta,
+            Err(e) => {
+                debug!("Schema handshake failed: {}", e);
+                return None;
+            }
+        };
+        let status = HandshakeStatus::evaluate(env!("CARGO_PKG_VERSION"), meta, Utc::now());
+        if status.needs_update() {
+            warn!(
+                "Backend requires an update: min app version {:?}, schema {:?} (client expects {})",
+                status.min_app_version, status.server_schema_version, status.expected_schema_version
+            );
+        }
+        *self.schema_handshake.write().await = Some(status.clone());
+        Some(status)
+    }
+
+    pub async fn get_schema_handshake(&self) -> Option<HandshakeStatus> {
+        self.schema_handshake.read().await.clone()
+    }
+
+    /// Доступность Supabase по общему предохранителю
+    pub fn connectivity_status(&self) -> ConnectivityStatus {
+        self.supabase_breaker.status()
+    }
+
+    /// Общий HTTP-клиент (клон дешёвый: пул соединений общий)
+    pub fn http(&self) -> reqwest::Client {
+        self.http.lock().unwrap().clone()
+    }
+
+    /// Пересобрать общий HTTP-клиент под настройки прокси (пароль — из keychain).
+    /// Если прокси настроен неверно — ошибка, а текущий клиент не трогаем
+    pub fn apply_proxy_settings(&self, proxy: &ProxySettings) -> Result<(), String> {
+        let password = if proxy.enabled {
+            crate::proxy::load_password().unwrap_or_else(|e| {
+                warn!("Failed to read proxy password from keychain: {}", e);
+                None
+            })
+        } else {
+            None
+        };
+        // Клиент меняем только после успешной сборки: при ошибке остаётся прежний
+        let client = crate::proxy::build_client(proxy, password.as_deref())
+            .map_err(|e| format!("Invalid proxy settings: {}", e))?;
+        if proxy.enabled {
+            info!("Using {:?} proxy {}:{}", proxy.kind, proxy.host, proxy.port);
+        }
+        *self.http.lock().unwrap() = client;
+        Ok(())
+    }
+
+    /// Клиент Supabase REST (общий HTTP-клиент с таймаутами и ретраями)
+    pub async fn supabase_client(&self) -> Option<SupabaseClient> {
+        let cfg = self.resolve_supabase_config().await?;
+        Some(SupabaseClient::new(self.http(), cfg, self.supabase_breaker.clone()))
+    }
     
     /// Начать новую сессию фарма
     pub async fn start_session(&self, preset_id: Option<String>) {
@@ -148,6 +820,14 @@ impl AppState {
             let mut p = self.is_paused.write().await;
             *p = false;
         }
+        self.pricecheck_skipped.write().await.clear();
+        *self.edit_journal.write().await = EditJournal::default();
+        self.slot_tracker.lock().unwrap().reset();
+        // Приглушённый чат Twitch снова объявляет с новой сессией
+        if self.twitch.is_muted() {
+            self.twitch.set_muted(false);
+        }
+        let currency_start = self.observe_base_currency().await;
         
         let now = Utc::now();
         let mut session = self.session.write().await;
@@ -164,19 +844,536 @@ impl AppState {
             drops: HashMap::new(),
             preset_id,
             is_paused: false,
+            pause_reason: None,
             expenses: Vec::new(),
             manual_drops: Vec::new(),
             session_duration_sec: 0,
+            clock_resumed_at: Some(now),
+            sources: HashMap::new(),
+            goals: SessionGoals::default(),
+            ignored_item_ids: Vec::new(),
+            adjustments: Vec::new(),
+            currency_start,
+            currency_end: None,
+            current_map_scene: None,
+            map_runs: Vec::new(),
+            current_map_drops: HashMap::new(),
+            current_map_tiers: HashMap::new(),
+            profit_alarm: None,
+            last_drop_at: HashMap::new(),
+            value_timeline: Vec::new(),
+            pause_history: Vec::new(),
+            drop_tiers: HashMap::new(),
+            app_running: true,
+            recovered: false,
+            recovery_gap_sec: 0,
+            log_times_utc: true,
+            heartbeat_at: Some(now),
+            abandoned_maps: 0,
+            abandoned_map_sec: 0,
         };
         info!("Farm session started");
+        self.journal_event(&session, now, JournalEvent::SessionStart { preset_id: session.preset_id.clone() });
         // Auto-save session
         Self::save_session_internal(&session);
+        drop(session);
+        self.notify_changed();
+        self.fire_obs_trigger(ObsTrigger::SessionStart, None).await;
+    }
+    
+    /// Начать сессию по шаблону: пресет, траты, цели и игнор-лист
+    pub async fn start_session_from_template(&self, template: &SessionTemplate) {
+        self.start_session(template.preset_id.clone()).await;
+        
+        let mut session = self.session.write().await;
+        // Свежие id: правки и журнал по id не должны задевать шаблон или прошлые сессии
+        session.expenses = template
+            .expenses
+            .iter()
+            .map(|e| ExpenseEntry { id: uuid::Uuid::new_v4().to_string(), ..e.clone() })
+            .collect();
+        for entry in &session.expenses {
+            self.journal_expense(&session, entry, true);
+        }
+        session.goals = template.goals.clone();
+        session.ignored_item_ids = template.ignored_item_ids.clone();
+        info!("Farm session started from template '{}' ({} expenses, {} ignored items)",
+            template.name, template.expenses.len(), template.ignored_item_ids.len());
+        Self::save_session_internal(&session);
+        self.notify_changed();
+    }
+    
+    /// ID базовой валюты: из кэша предметов, иначе Flame Elementium
+    async fn base_currency_id(&self) -> i64 {
+        self.items_cache
+            .read()
+            .await
+            .values()
+            .find(|i| i.is_base_currency)
+            .map(|i| i.game_id)
+            .unwrap_or(BASE_CURRENCY_ID)
+    }
+
+    /// Сколько FE на руках по логу основного клиента (None — слоты с FE ещё не встречались)
+    pub async fn observe_base_currency(&self) -> Option<CurrencyReading> {
+        let currency_id = self.base_currency_id().await;
+        let amount = self.log_parser.lock().unwrap().quantity_held(currency_id)?;
+        Some(CurrencyReading {
+            amount,
+            source: CurrencyReadingSource::Log,
+            observed_at: Utc::now(),
+        })
+    }
+
+    /// Ввести количество FE на руках вручную (для начала или конца сессии)
+    pub async fn set_session_currency(&self, point: CurrencyPoint, amount: i64) -> Result<CurrencyReading, String> {
+        if amount < 0 {
+            return Err("Amount must not be negative".to_string());
+        }
+        let mut session = self.session.write().await;
+        if session.started_at.is_none() {
+            return Err("No active session".to_string());
+        }
+        let reading = CurrencyReading {
+            amount,
+            source: CurrencyReadingSource::Manual,
+            observed_at: Utc::now(),
+        };
+        match point {
+            CurrencyPoint::Start => session.currency_start = Some(reading.clone()),
+            CurrencyPoint::End => session.currency_end = Some(reading.clone()),
+        }
+        Self::save_session_internal(&session);
+        self.notify_changed();
+        Ok(reading)
+    }
+
+    /// FE активной сессии. Текущее — введённое вручную значение конца, иначе по логу
+    pub async fn get_session_currency(&self) -> SessionCurrency {
+        let (start, manual_end) = {
+            let session = self.session.read().await;
+            (session.currency_start.clone(), session.currency_end.clone())
+        };
+        let current = match manual_end {
+            Some(reading) => Some(reading),
+            None => self.observe_base_currency().await,
+        };
+        let gained = persistence::raw_currency_gained(
+            start.as_ref().map(|r| r.amount),
+            current.as_ref().map(|r| r.amount),
+        );
+        SessionCurrency { start, current, gained }
+    }
+
+    /// Добавить точку кривой стоимости (фоновая задача, раз в минуту времени сессии)
+    pub async fn record_value_point(&self) {
+        let stats = self.get_session_stats().await;
+        let valuation = self.valuation().await;
+        let mut session = self.session.write().await;
+        if session.started_at.is_none() {
+            return;
+        }
+        if session.value_timeline.last().map(|p| p.duration_sec) == Some(stats.duration_sec) {
+            return;
+        }
+        let net_profit = valuation.round(stats.total_value - stats.total_fee - valuation.expenses_total(&session.expenses));
+        session.value_timeline.push(ValuePoint {
+            at: Utc::now(),
+            duration_sec: stats.duration_sec,
+            total_value: stats.total_value,
+            net_profit,
+        });
+        // Длинная сессия: прореживаем, сохраняя первую и последнюю точки
+        if session.value_timeline.len() > VALUE_TIMELINE_MAX_POINTS {
+            let last = session.value_timeline.len() - 1;
+            let mut index = 0;
+            session.value_timeline.retain(|_| {
+                let keep = index % 2 == 0 || index == last;
+                index += 1;
+                keep
+            });
+        }
+        Self::save_session_internal(&session);
+    }
+
+    /// Кривая стоимости активной сессии
+    pub async fn get_value_timeline(&self) -> Vec<ValuePoint> {
+        self.session.read().await.value_timeline.clone()
+    }
+
+    /// Завершённые карты активной сессии (в порядке прохождения)
+    pub async fn get_map_runs(&self) -> Vec<MapRun> {
+        self.session.read().await.map_runs.clone()
+    }
+
+    /// Оценить только что завершённые карты и сверить их с личными рекордами.
+    /// Возвращает побитые рекорды
+    pub async fn settle_map_runs(&self) -> Vec<RecordBrokenEvent> {
+        let (pending, session_started_at, preset_id) = {
+            let session = self.session.read().await;
+            let pending: Vec<PendingMapRun> = session
+                .map_runs
+                .iter()
+                .filter(|run| run.value.is_none())
+                .map(|run| (run.index, run.drops.clone(), run.tiers.clone()))
+                .collect();
+            (pending, session.started_at, session.preset_id.clone())
+        };
+        if pending.is_empty() {
+            return Vec::new();
+        }
+
+        let mut values = Vec::with_capacity(pending.len());
+        for (index, drops, tiers) in pending {
+            values.push((index, self.value_of_drops(&drops, &tiers).await));
+        }
+
+        let settled: Vec<MapRun> = {
+            let mut session = self.session.write().await;
+            let mut settled = Vec::new();
+            for (index, value) in values {
+                if let Some(run) = session.map_runs.iter_mut().find(|r| r.index == index && r.value.is_none()) {
+                    run.value = Some(value);
+                    settled.push(run.clone());
+                }
+            }
+            Self::save_session_internal(&session);
+            settled
+        };
+
+        let league = self.current_league().await;
+        // Брошенные карты искажают дроп в час — в журнал карт не пишем
+        let log_entries = settled
+            .iter()
+            .filter(|run| !run.abandoned)
+            .map(|run| MapLogEntry {
+                ended_at: run.ended_at,
+                duration_sec: run.duration_sec,
+                scene_name: run.scene_name.clone(),
+                preset_id: preset_id.clone(),
+                league: league.clone(),
+                drops: run.drops.clone(),
+            })
+            .collect();
+        if let Err(e) = persistence::append_map_log(log_entries) {
+            warn!("Failed to append map log: {}", e);
+        }
+
+        let mut current = self.get_records().await;
+        let mut broken = Vec::new();
+        // Брошенные карты рекордов не ставят (map_record их отсекает)
+        for run in &settled {
+            if let Some(candidate) = records::map_record(run, session_started_at) {
+                broken.extend(records::consider(&mut current, &candidate));
+            }
+        }
+        if !broken.is_empty() {
+            if let Err(e) = persistence::save_personal_records(&current) {
+                warn!("Failed to save personal records: {}", e);
+            }
+            *self.personal_records.write().await = Some(current);
+        }
+        broken
+    }
+
+    /// Текущая лига — самая частая среди цен текущего сезона (None — цен ещё нет).
+    /// При равенстве побеждает большее имя лиги, чтобы ответ не зависел от порядка HashMap
+    pub async fn current_league(&self) -> Option<String> {
+        let prices = self.prices_cache.read().await;
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for entry in prices.values().filter(|e| e.is_current_league) {
+            if let Some(league) = entry.league_name.as_deref() {
+                *counts.entry(league).or_insert(0) += 1;
+            }
+        }
+        counts.into_iter().max_by_key(|(league, n)| (*n, *league)).map(|(league, _)| league.to_string())
+    }
+
+    /// Сверить лигу цен с лигой текущих данных: первая увиденная лига запоминается,
+    /// Some(новая лига) — цены уже из другой лиги, прошлую пора архивировать
+    pub async fn check_league_rollover(&self) -> Option<String> {
+        let current = self.current_league().await?;
+        let mut settings = self.settings.write().await;
+        match settings.active_league.as_deref() {
+            None => {
+                info!("Active league: {}", current);
+                settings.active_league = Some(current);
+                if let Err(e) = persistence::save_settings(&settings) {
+                    warn!("Failed to save settings: {}", e);
+                }
+                None
+            }
+            Some(active) if active == current => None,
+            Some(active) => {
+                debug!("League rollover pending: {} -> {}", active, current);
+                Some(current)
+            }
+        }
+    }
+
+    /// Лига текущих данных, лига по ценам и список архивов
+    pub async fn league_status(&self) -> LeagueStatus {
+        let current_league = self.current_league().await;
+        let active_league = self.settings.read().await.active_league.clone();
+        let rollover_pending = matches!((&active_league, &current_league), (Some(a), Some(c)) if a != c);
+        let archives = persistence::app_data_dir()
+            .map(|dir| league_archive::list_archives(&dir))
+            .unwrap_or_default();
+        LeagueStatus { active_league, current_league, rollover_pending, archives }
+    }
+
+    /// Перенести историю, цены (рыночные и ручные), курсы валют, рекорды и журнал карт прошлой
+    /// лиги в архив и начать с чистых кэшей.
+    /// Только без активной сессии
+    pub async fn archive_league_data(&self) -> Result<ArchivedLeague, String> {
+        if self.session.read().await.started_at.is_some() {
+            return Err("End the active session before archiving the league".to_string());
+        }
+        let current = self.current_league().await;
+        let league = self
+            .settings
+            .read()
+            .await
+            .active_league
+            .clone()
+            .or_else(|| current.clone())
+            .ok_or_else(|| "League is not known yet, wait for a price update".to_string())?;
+        let data_dir = persistence::app_data_dir().ok_or_else(|| "App data folder is unavailable".to_string())?;
+
+        let archived = {
+            // История уезжает в архив целиком — параллельная запись в неё потерялась бы
+            let _history = self.history_lock.lock().await;
+            // Держим кэш цен, чтобы flush не записал старые цены поверх переноса
+            let mut prices = self.prices_cache.write().await;
+            let mut manual_prices = self.manual_prices.write().await;
+            let mut exchange_rates = self.exchange_rates.write().await;
+            let archived = league_archive::archive_league(&data_dir, &league, Utc::now()).map_err(|e| e.to_string())?;
+            prices.clear();
+            self.prices_dirty.lock().unwrap().clear();
+            // Загрузятся заново — уже пустыми
+            *manual_prices = None;
+            *exchange_rates = None;
+            archived
+        };
+        *self.personal_records.write().await = Some(PersonalRecords::default());
+        self.init_base_currency_price().await;
+        {
+            let mut settings = self.settings.write().await;
+            // Новая лига запомнится при следующем обновлении цен
+            settings.active_league = current.filter(|c| *c != league);
+        }
+        self.save_settings_to_disk().await;
+        self.notify_changed();
+        Ok(archived)
+    }
+
+    /// Архив лиги для просмотра (история — текущего пользователя)
+    pub async fn get_archived_league(&self, id: &str) -> Result<ArchivedLeagueView, String> {
+        let data_dir = persistence::app_data_dir().ok_or_else(|| "App data folder is unavailable".to_string())?;
+        let (league, dir) = league_archive::find_archive(&data_dir, id)
+            .ok_or_else(|| "Archived league not found".to_string())?;
+        let sessions = match self.get_auth_user_id().await {
+            Some(user_id) => persistence::load_archived_session_history(&dir, &user_id).map_err(|e| e.to_string())?,
+            None => Vec::new(),
+        };
+        let records = persistence::load_archived_personal_records(&dir).map_err(|e| e.to_string())?;
+        Ok(ArchivedLeagueView { league, sessions, records })
+    }
+
+    /// Личные рекорды по картам за все сессии
+    pub async fn get_records(&self) -> PersonalRecords {
+        if let Some(records) = self.personal_records.read().await.clone() {
+            return records;
+        }
+        let loaded = persistence::load_personal_records()
+            .map_err(|e| warn!("Failed to load personal records: {}", e))
+            .unwrap_or_default();
+        *self.personal_records.write().await = Some(loaded.clone());
+        loaded
+    }
+
+    fn load_manual_prices() -> HashMap<i64, LastManualPrice> {
+        persistence::load_manual_prices()
+            .map_err(|e| warn!("Failed to load manual prices: {}", e))
+            .unwrap_or_default()
+    }
+
+    /// Запомнить цену, введённую вручную для предмета из базы (для подсказки в следующий раз)
+    async fn remember_manual_price(&self, game_id: Option<i64>, price: f64, kind: ManualPriceKind) {
+        let Some(game_id) = game_id.filter(|_| price > 0.0) else {
+            return;
+        };
+        let mut guard = self.manual_prices.write().await;
+        let prices = guard.get_or_insert_with(Self::load_manual_prices);
+        prices.insert(game_id, LastManualPrice { price, used_at: Utc::now(), kind });
+        if let Err(e) = persistence::save_manual_prices(prices) {
+            warn!("Failed to save manual prices: {}", e);
+        }
+    }
+
+    /// Подсказка цены для ручного ввода: последняя ручная цена и цена из кэша
+    pub async fn get_item_price_suggestion(&self, game_id: i64) -> PriceSuggestion {
+        let last_manual = {
+            let mut guard = self.manual_prices.write().await;
+            guard.get_or_insert_with(Self::load_manual_prices).get(&game_id).cloned()
+        };
+        let market_price = {
+            let items = self.items_cache.read().await;
+            let prices = self.prices_cache.read().await;
+            Valuation::unit_price(game_id, &items, &prices)
+        };
+        PriceSuggestion { game_id, last_manual, market_price }
+    }
+
+    fn load_exchange_rates() -> ExchangeRates {
+        persistence::load_exchange_rates()
+            .map_err(|e| warn!("Failed to load exchange rates: {}", e))
+            .unwrap_or_default()
+    }
+
+    /// Пересчитать сумму в валюте currency_id в FE по курсу на момент `at`.
+    /// None — курс валюты ещё не известен
+    pub async fn price_in_fe(&self, amount: f64, currency_id: i64, at: DateTime<Utc>) -> Option<f64> {
+        if currency_id == self.base_currency_id().await {
+            return Some(amount);
+        }
+        let mut guard = self.exchange_rates.write().await;
+        let rate = guard.get_or_insert_with(Self::load_exchange_rates).rate_at(currency_id, at)?;
+        Some(amount * rate)
+    }
+
+    /// Учесть прайсчек: цена `selected` в валюте поиска переводится в FE (None — курс неизвестен).
+    /// Если чекнули саму торговую валюту, её цена в FE становится новой точкой курса
+    pub async fn observe_price_search(&self, event: &PriceSearchEvent, selected: f64) -> Option<f64> {
+        let base_id = self.base_currency_id().await;
+        let denominations = self.settings.read().await.denomination_items.clone();
+        let mut guard = self.exchange_rates.write().await;
+        let rates = guard.get_or_insert_with(Self::load_exchange_rates);
+
+        let mut changed = false;
+        if event.currency_id != base_id && rates.track(event.currency_id) {
+            info!("Started tracking exchange rate of currency {}", event.currency_id);
+            changed = true;
+        }
+        let fe_price = if event.currency_id == base_id {
+            Some(selected)
+        } else {
+            rates.rate_at(event.currency_id, event.timestamp).map(|rate| selected * rate)
+        };
+        if let Some(fe_price) = fe_price {
+            if event.game_id != base_id
+                && (rates.is_tracked(event.game_id) || denominations.contains(&event.game_id))
+            {
+                rates.record(event.game_id, fe_price, event.timestamp);
+                debug!("Exchange rate of {}: {} FE", event.game_id, fe_price);
+                changed = true;
+            }
+        }
+        if changed {
+            if let Err(e) = persistence::save_exchange_rates(rates) {
+                warn!("Failed to save exchange rates: {}", e);
+            }
+        }
+        fe_price
+    }
+
+    /// Курсы отслеживаемых валют к FE (имена — на языке отображения)
+    pub async fn get_exchange_rates(&self) -> Vec<ExchangeRateSeries> {
+        let rates = {
+            let mut guard = self.exchange_rates.write().await;
+            guard.get_or_insert_with(Self::load_exchange_rates).clone()
+        };
+        let language = self.display_language().await;
+        let items = self.items_cache.read().await;
+        let mut series: Vec<ExchangeRateSeries> = rates
+            .currencies
+            .iter()
+            .map(|(currency_id, points)| ExchangeRateSeries {
+                currency_id: *currency_id,
+                name: items
+                    .get(currency_id)
+                    .map(|i| i.localized_name(&language).to_string())
+                    .unwrap_or_else(|| format!("#{}", currency_id)),
+                latest: rates.latest(*currency_id),
+                points: points.clone(),
+            })
+            .collect();
+        series.sort_by(|a, b| a.name.cmp(&b.name));
+        series
+    }
+
+    /// Цена ручного ввода в FE: если введена в другой валюте — пересчёт по текущему курсу
+    async fn quote_price(&self, price: f64, currency_id: Option<i64>) -> Result<(f64, Option<QuotedPrice>), String> {
+        let Some(currency_id) = currency_id.filter(|_| price > 0.0) else {
+            return Ok((price, None));
+        };
+        if currency_id == self.base_currency_id().await {
+            return Ok((price, None));
+        }
+        let fe_price = self
+            .price_in_fe(price, currency_id, Utc::now())
+            .await
+            .ok_or_else(|| format!("Exchange rate of currency {} is unknown, pricecheck it first", currency_id))?;
+        Ok((fe_price, Some(QuotedPrice { currency_id, amount: price })))
+    }
+
+    /// FE на руках в начале и в конце активной сессии (для записи в историю)
+    pub async fn session_currency_readings(&self) -> (Option<i64>, Option<i64>) {
+        let currency = self.get_session_currency().await;
+        (
+            currency.start.map(|r| r.amount),
+            currency.current.map(|r| r.amount),
+        )
+    }
+
+    /// Завести будильник по чистому профиту (None — выключить). Новый порог срабатывает заново
+    pub async fn set_profit_alarm(&self, threshold: Option<f64>) -> Result<Option<ProfitAlarm>, String> {
+        if let Some(threshold) = threshold {
+            if !threshold.is_finite() || threshold <= 0.0 {
+                return Err("Profit alarm threshold must be positive".to_string());
+            }
+        }
+        let mut session = self.session.write().await;
+        if session.started_at.is_none() {
+            return Err("No active session".to_string());
+        }
+        session.profit_alarm = threshold.map(|threshold| ProfitAlarm { threshold, fired_at: None });
+        info!("Profit alarm set: {:?}", threshold);
+        Self::save_session_internal(&session);
+        let alarm = session.profit_alarm.clone();
+        drop(session);
+        self.notify_changed();
+        Ok(alarm)
+    }
+
+    /// Проверить будильник по свежей статистике: срабатывает один раз при пересечении порога
+    pub async fn check_profit_alarm(&self, stats: &SessionStats) -> Option<ProfitAlarmEvent> {
+        let alarm = stats.profit_alarm.as_ref().filter(|a| a.fired_at.is_none())?;
+        let valuation = self.valuation().await;
+        let mut session = self.session.write().await;
+        let net_profit = valuation.round(stats.total_value - stats.total_fee - valuation.expenses_total(&session.expenses));
+        if net_profit < alarm.threshold {
+            return None;
+        }
+        let current = session.profit_alarm.as_mut().filter(|a| a.fired_at.is_none())?;
+        current.fired_at = Some(Utc::now());
+        let threshold = current.threshold;
+        Self::save_session_internal(&session);
+        info!("Profit alarm fired: {} >= {}", net_profit, threshold);
+        Some(ProfitAlarmEvent { threshold, net_profit })
+    }
+
+    /// Цели текущей сессии
+    pub async fn get_session_goals(&self) -> SessionGoals {
+        let session = self.session.read().await;
+        session.goals.clone()
     }
     
     /// Загрузить сессию с диска (для восстановления после краша)
     pub async fn load_session_from_disk(&self) -> bool {
+        let file_saved_at = persistence::session_saved_at();
         match persistence::load_session() {
-            Ok(Some(session)) => {
+            Ok(Some(mut session)) => {
                 info!("Restored session from disk, duration: {} sec, paused: {}", 
                     session.session_duration_sec, session.is_paused);
                 // Восстанавливаем состояние паузы
@@ -186,9 +1383,34 @@ impl AppState {
                     *p = was_paused;
                 }
                 
+                // Флаг не снят при выходе — прошлый запуск оборвался. Пропуск считаем
+                // от последнего признака жизни: heartbeat раз в минуту, а файл пишется
+                // при каждом изменении — берём более позднюю из двух отметок
+                let saved_at = session.heartbeat_at.max(file_saved_at);
+                if session.app_running {
+                    let gap_sec = saved_at.map(|at| (Utc::now() - at).num_seconds().max(0)).unwrap_or(0);
+                    session.recovered = true;
+                    session.recovery_gap_sec += gap_sec;
+                    warn!("Previous run did not exit cleanly, session recovered after a {} sec gap", gap_sec);
+                }
+                
+                session.app_running = true;
+                if !session.log_times_utc {
+                    let parser = self.log_parser.lock().unwrap();
+                    session.map_log_times(|ts| parser.legacy_to_utc(ts));
+                    session.log_times_utc = true;
+                    info!("Converted log timestamps of a restored session to UTC");
+                }
+                Self::save_session_durable(&session);
+                
                 let mut s = self.session.write().await;
                 *s = session;
+                // Время, пока приложение было закрыто, в сессию не засчитываем:
+                // секундомер продолжает идти с момента восстановления.
+                s.clock_resumed_at = if was_paused { None } else { Some(Utc::now()) };
+                self.journal_event(&s, Utc::now(), JournalEvent::Restored { offline_since: saved_at });
                 info!("Restored session from disk, paused: {}", was_paused);
+                self.notify_changed();
                 true
             }
             Ok(None) => false,
@@ -199,13 +1421,124 @@ impl AppState {
         }
     }
     
-    /// Внутренний helper для сохранения сессии
+    /// Статистика сессии на момент `until` по журналу событий (None — на последнее событие)
+    pub async fn replay_session_events(
+        &self,
+        journal_id: &str,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<crate::session_replay::ReplayStats, String> {
+        let path = crate::event_journal::journal_path(journal_id).ok_or("Invalid journal id")?;
+        if !path.exists() {
+            return Err("No event journal for this session".to_string());
+        }
+        let entries = crate::event_journal::read_journal(&path).map_err(|e| e.to_string())?;
+        let (primary_only, abandoned_map_minutes) = {
+            let settings = self.settings.read().await;
+            (settings.log_source_mode == LogSourceMode::PerSource, settings.abandoned_map_minutes)
+        };
+        let replayed = crate::session_replay::replay(&entries, until, primary_only, abandoned_map_minutes);
+
+        // Стоимость — тем же путём, что и живая статистика (get_session_stats)
+        let valuation = self.valuation().await;
+        let fees = self.fee_model().await;
+        let prices = replayed.price_entries();
+        let items_cache = self.items_cache.read().await;
+        let total_value = valuation.tiered_drops_value(&replayed.drops, &replayed.drop_tiers, &items_cache, &prices);
+        let total_fee = fees.tiered_drops_fee(&valuation, &replayed.drops, &replayed.drop_tiers, &items_cache, &prices);
+        let total_expenses = valuation.round(
+            replayed.expenses.values().map(|(price, quantity)| valuation.line_value(*price, *quantity)).sum(),
+        );
+        let net_profit = valuation.round(total_value - total_fee - total_expenses);
+        let unpriced_items = replayed
+            .drops
+            .keys()
+            .filter(|game_id| Valuation::unit_price(**game_id, &items_cache, &prices).is_none())
+            .count();
+        drop(items_cache);
+        Ok(crate::session_replay::ReplayStats {
+            session_started_at: replayed.started_at,
+            last_event_at: entries.iter().map(|e| e.ts).max(),
+            at: replayed.at,
+            duration_sec: replayed.session_sec,
+            map_time_sec: replayed.map_time_sec,
+            maps_completed: replayed.maps_completed,
+            abandoned_maps: replayed.abandoned_maps,
+            total_items: replayed.drops.values().sum(),
+            unique_items: replayed.drops.len(),
+            total_value,
+            total_fee,
+            total_expenses,
+            net_profit,
+            hourly_profit: valuation.per_hour(net_profit, replayed.session_sec.min(i32::MAX as i64) as i32),
+            unpriced_items,
+            is_paused: replayed.is_paused,
+            is_on_map: replayed.is_on_map,
+            events_applied: replayed.events_applied,
+        })
+    }
+
+    /// Подобранные предметы, которых нет в справочнике
+    pub fn get_unknown_items(&self) -> Vec<UnknownItem> {
+        self.unknown_items.lock().unwrap().list()
+    }
+
+    pub fn clear_unknown_items(&self) {
+        self.unknown_items.lock().unwrap().clear();
+    }
+
+    /// Отправить на сервер неизвестные предметы, собранные с прошлого отчёта.
+    /// Возвращает число отправленных позиций
+    pub async fn report_unknown_items(&self) -> Result<usize, String> {
+        let Some(api) = self.supabase_client().await else {
+            return Err("Supabase is not configured".to_string());
+        };
+        let pending = self.unknown_items.lock().unwrap().take_pending();
+        if pending.is_empty() {
+            return Ok(0);
+        }
+        let jwt = self.get_valid_access_token(&api).await;
+        if let Err(e) = crate::supabase_sync::report_unknown_items(&api, jwt.as_deref(), &pending).await {
+            self.unknown_items.lock().unwrap().restore(&pending);
+            return Err(e.to_string());
+        }
+        info!("Reported {} unknown items", pending.len());
+        Ok(pending.len())
+    }
+
+    /// Дописать событие в журнал активной сессии
+    fn journal_event(&self, session: &FarmSessionState, ts: DateTime<Utc>, event: JournalEvent) {
+        if let Some(started_at) = session.started_at {
+            self.journal.record(started_at, ts, event);
+        }
+    }
+    
+    /// Трата в журнал событий (для пересчёта прибыли при воспроизведении)
+    fn journal_expense(&self, session: &FarmSessionState, entry: &ExpenseEntry, added: bool) {
+        let event = if added {
+            JournalEvent::Expense { id: entry.id.clone(), quantity: entry.quantity, price: entry.price }
+        } else {
+            JournalEvent::ExpenseRemove { id: entry.id.clone() }
+        };
+        self.journal_event(session, Utc::now(), event);
+    }
+
+    /// Внутренний helper для сохранения сессии (быстрая запись без fsync и .bak)
     fn save_session_internal(session: &FarmSessionState) {
+        let _ = persistence::save_session_quick(session);
+    }
+
+    /// Надёжная запись сессии (fsync + .bak): heartbeat, выход и восстановление
+    fn save_session_durable(session: &FarmSessionState) {
         let _ = persistence::save_session(session);
     }
     
     /// Установить состояние паузы
     pub async fn set_paused(&self, paused: bool) {
+        self.set_paused_with_reason(paused, PauseReason::Manual).await;
+    }
+    
+    /// Установить состояние паузы с причиной (причина пишется в сессию, при снятии паузы сбрасывается)
+    pub async fn set_paused_with_reason(&self, paused: bool, reason: PauseReason) {
         {
             let mut p = self.is_paused.write().await;
             *p = paused;
@@ -215,53 +1548,183 @@ impl AppState {
         {
             let mut session = self.session.write().await;
             if session.started_at.is_some() {
+                // Останавливаем/запускаем секундомер
+                if paused {
+                    Self::fold_session_clock(&mut session, Utc::now());
+                    session.clock_resumed_at = None;
+                } else if session.clock_resumed_at.is_none() {
+                    session.clock_resumed_at = Some(Utc::now());
+                }
                 session.is_paused = paused;
+                session.pause_reason = paused.then_some(reason);
+                session.record_pause(paused, reason, Utc::now());
+                let event = if paused { JournalEvent::Pause { reason } } else { JournalEvent::Resume };
+                self.journal_event(&session, Utc::now(), event);
                 Self::save_session_internal(&session);
-                info!("Session paused: {}", paused);
+                info!("Session paused: {} ({:?})", paused, reason);
             }
         }
-    }
-    
-    /// Обновить время сессии (вызывается фронтендом)
-    pub async fn update_session_duration(&self, duration_sec: i32) {
-        let mut session = self.session.write().await;
-        if session.started_at.is_some() {
-            session.session_duration_sec = duration_sec;
-            Self::save_session_internal(&session);
+        self.notify_changed();
+    }
+    
+    /// Перенести набежавшие целые секунды секундомера в session_duration_sec.
+    /// Возвращает true, если длительность изменилась.
+    fn fold_session_clock(session: &mut FarmSessionState, now: DateTime<Utc>) -> bool {
+        let Some(resumed_at) = session.clock_resumed_at else {
+            return false;
+        };
+        let elapsed = (now - resumed_at).num_seconds();
+        if elapsed <= 0 {
+            return false;
+        }
+        session.session_duration_sec += elapsed as i32;
+        // Переносим только целые секунды, остаток миллисекунд не теряется
+        session.clock_resumed_at = Some(resumed_at + chrono::Duration::seconds(elapsed));
+        true
+    }
+    
+    /// Тик секундомера сессии (фоновая задача, раз в секунду).
+    /// Возвращает текущую длительность сессии, если сессия активна.
+    /// Секундомер живёт в памяти: на диск он попадает с паузой, изменениями сессии
+    /// и heartbeat'ом, а не каждую секунду.
+    pub async fn tick_session_clock(&self) -> Option<i32> {
+        let mut session = self.session.write().await;
+        session.started_at?;
+        Self::fold_session_clock(&mut session, Utc::now());
+        Some(session.session_duration_sec)
+    }
+    
+    /// Проверить, на паузе ли сессия
+    pub async fn is_paused(&self) -> bool {
+        *self.is_paused.read().await
+    }
+
+    /// История пауз текущей сессии (старые первыми)
+    pub async fn get_pause_history(&self) -> Vec<PauseRecord> {
+        self.session.read().await.pause_history.clone()
+    }
+
+    /// Отметить активность в логе; снимает AFK-автопаузу
+    async fn touch_log_activity(&self) {
+        *self.last_log_activity.lock().unwrap() = Utc::now();
+        let afk_paused = {
+            let session = self.session.read().await;
+            session.is_paused && session.pause_reason == Some(PauseReason::Afk)
+        };
+        if afk_paused {
+            info!("Log activity after AFK pause, resuming session");
+            self.set_paused_with_reason(false, PauseReason::Afk).await;
+        }
+    }
+
+    /// AFK-автопауза: сессия идёт, а в логе дольше AppSettings.afk_pause_minutes нет ни
+    /// дропа, ни смены карты. Отсчёт — от последней активности (или запуска приложения),
+    /// старта или снятия паузы.
+    /// Возвращает true, если сессия поставлена на паузу
+    pub async fn apply_afk_check(&self) -> bool {
+        let minutes = self.settings.read().await.afk_pause_minutes;
+        if minutes == 0 || self.is_paused().await {
+            return false;
+        }
+        let since = {
+            let session = self.session.read().await;
+            let Some(started_at) = session.started_at else {
+                return false;
+            };
+            let resumed_at = session.pause_history.last().and_then(|p| p.resumed_at);
+            let activity = *self.last_log_activity.lock().unwrap();
+            [Some(started_at), resumed_at].into_iter().flatten().fold(activity, DateTime::max)
+        };
+        if Utc::now() - since < chrono::Duration::minutes(minutes as i64) {
+            return false;
         }
+        info!("No log activity for {} min, auto-pausing session", minutes);
+        self.set_paused_with_reason(true, PauseReason::Afk).await;
+        true
     }
     
-    /// Проверить, на паузе ли сессия
-    pub async fn is_paused(&self) -> bool {
-        *self.is_paused.read().await
+    pub fn set_game_running(&self, running: bool) {
+        *self.game_running.lock().unwrap() = Some(running);
+    }
+
+    /// Запущена ли игра (None — процесс ещё не проверяли или проверить нельзя)
+    pub fn game_running(&self) -> Option<bool> {
+        *self.game_running.lock().unwrap()
+    }
+
+    /// Автопауза по процессу игры: закрылась — пауза, запустилась снова — снимаем
+    /// только свою автопаузу. Возвращает новое состояние паузы, если оно изменилось
+    pub async fn apply_game_process(&self, running: bool) -> Option<bool> {
+        let (active, reason) = {
+            let session = self.session.read().await;
+            (session.started_at.is_some(), session.pause_reason)
+        };
+        if !active {
+            return None;
+        }
+        let paused = self.is_paused().await;
+        if !running && !paused {
+            info!("Game process is gone, auto-pausing session");
+            self.set_paused_with_reason(true, PauseReason::GameClosed).await;
+            return Some(true);
+        }
+        if running && paused && reason == Some(PauseReason::GameClosed) {
+            info!("Game process is back, resuming session");
+            self.set_paused_with_reason(false, PauseReason::GameClosed).await;
+            return Some(false);
+        }
+        None
     }
     
-    /// Добавить трату вручную
-    pub async fn add_expense(&self, id: String, game_id: Option<i64>, name: String, name_ru: Option<String>, quantity: i32, price: f64) {
-        let mut session = self.session.write().await;
-        // Траты можно добавлять даже без активной сессии (пресет)
-        session.expenses.push(ExpenseEntry {
+    /// Добавить трату вручную (цена в FE или, если указан currency_id, в торговой валюте)
+    #[allow(clippy::too_many_arguments)]
+    pub async fn add_expense(&self, id: String, game_id: Option<i64>, name: String, name_ru: Option<String>, quantity: i32, price: f64, currency_id: Option<i64>) -> Result<(), String> {
+        let (price, quoted) = self.quote_price(price, currency_id).await?;
+        let entry = ExpenseEntry {
             id,
             game_id,
             name,
             name_ru,
             quantity,
             price,
-        });
-        info!("Added expense: {} (game_id={:?}) x{} @ {}", 
-            session.expenses.last().map(|e| &e.name).unwrap_or(&"?".to_string()), 
-            game_id, quantity, price);
-        // Auto-save if session is active
-        if session.started_at.is_some() {
-            Self::save_session_internal(&session);
+            quoted,
+        };
+        {
+            let mut session = self.session.write().await;
+            // Траты можно добавлять даже без активной сессии (пресет)
+            session.expenses.push(entry.clone());
+            self.journal_expense(&session, &entry, true);
+            info!("Added expense: {} (game_id={:?}) x{} @ {}", entry.name, game_id, quantity, price);
+            // Auto-save if session is active
+            if session.started_at.is_some() {
+                Self::save_session_internal(&session);
+            }
         }
+        self.remember_manual_price(game_id, price, ManualPriceKind::Expense).await;
+        self.record_edit(SessionEdit::AddExpense { entry }).await;
+        self.notify_changed();
+        Ok(())
     }
     
     /// Удалить трату
     pub async fn remove_expense(&self, id: &str) {
-        let mut session = self.session.write().await;
-        session.expenses.retain(|e| e.id != id);
+        let removed = {
+            let mut session = self.session.write().await;
+            let removed = session.expenses.iter().position(|e| e.id == id)
+                .map(|index| (index, session.expenses.remove(index)));
+            if let Some((_, entry)) = &removed {
+                self.journal_expense(&session, entry, false);
+            }
+            if removed.is_some() && session.started_at.is_some() {
+                Self::save_session_internal(&session);
+            }
+            removed
+        };
         info!("Removed expense: {}", id);
+        if let Some((index, entry)) = removed {
+            self.record_edit(SessionEdit::RemoveExpense { index, entry }).await;
+            self.notify_changed();
+        }
     }
     
     /// Получить список трат
@@ -270,55 +1733,226 @@ impl AppState {
         session.expenses.clone()
     }
     
-    /// Поиск предметов по названию (EN/RU)
+    /// Язык отображения имён предметов (из настроек)
+    pub async fn display_language(&self) -> String {
+        self.settings.read().await.language.clone()
+    }
+    
+    /// Поиск предметов по названию (EN/RU/CN), имена в результатах — на языке отображения
     pub async fn search_items(&self, query: &str) -> Vec<ItemInfo> {
+        let language = self.display_language().await;
+        // Пустой запрос — первые 30 предметов, иначе до 50 по релевантности
+        let limit = if query.trim().is_empty() { 30 } else { 50 };
+        let ids = self.item_search.read().await.search(query, limit);
         let cache = self.items_cache.read().await;
-        let q = query.to_lowercase();
-        
-        if q.is_empty() {
-            // Return first 30 items if no query
-            return cache.values().take(30).cloned().collect();
-        }
-        
-        cache.values()
-            .filter(|item| {
-                item.name.to_lowercase().contains(&q) ||
-                item.name_en.as_ref().map(|n| n.to_lowercase().contains(&q)).unwrap_or(false) ||
-                item.name_ru.as_ref().map(|n| n.to_lowercase().contains(&q)).unwrap_or(false)
-            })
-            .take(50)
-            .cloned()
+        ids.iter()
+            .filter_map(|id| cache.get(id))
+            .map(|item| item.localized(&language))
             .collect()
     }
     
-    /// Добавить ручной дроп (для уников/экипировки)
-    pub async fn add_manual_drop(&self, id: String, game_id: Option<i64>, name: String, name_ru: Option<String>, quantity: i32, price: f64) {
-        let mut session = self.session.write().await;
-        // Ручной дроп можно добавлять только в активную сессию
-        if session.started_at.is_some() {
-            session.manual_drops.push(ManualDropEntry {
-                id,
-                game_id,
-                name,
-                name_ru,
-                quantity,
-                price,
-            });
-            info!("Added manual drop: {} (game_id={:?}) x{} @ {}", 
-                session.manual_drops.last().map(|e| &e.name).unwrap_or(&"?".to_string()), 
-                game_id, quantity, price);
+    /// Добавить ручной дроп (для уников/экипировки; цена в FE или в торговой валюте currency_id)
+    #[allow(clippy::too_many_arguments)]
+    pub async fn add_manual_drop(&self, id: String, game_id: Option<i64>, name: String, name_ru: Option<String>, quantity: i32, price: f64, tier: Option<i32>, currency_id: Option<i64>) -> Result<(), String> {
+        let (price, quoted) = self.quote_price(price, currency_id).await?;
+        let entry = ManualDropEntry {
+            id,
+            game_id,
+            name,
+            name_ru,
+            quantity,
+            price,
+            tier,
+            sold: None,
+            quoted,
+        };
+        {
+            let mut session = self.session.write().await;
+            // Ручной дроп можно добавлять только в активную сессию
+            if session.started_at.is_none() {
+                return Ok(());
+            }
+            session.manual_drops.push(entry.clone());
+            info!("Added manual drop: {} (game_id={:?}) x{} @ {}", entry.name, game_id, quantity, price);
             // Auto-save session
             Self::save_session_internal(&session);
         }
+        self.remember_manual_price(game_id, price, ManualPriceKind::ManualDrop).await;
+        self.record_edit(SessionEdit::AddManualDrop { entry }).await;
+        self.notify_changed();
+        Ok(())
     }
     
-    /// Удалить ручной дроп
-    pub async fn remove_manual_drop(&self, id: &str) {
-        let mut session = self.session.write().await;
-        session.manual_drops.retain(|e| e.id != id);
+    /// Предмет из базы с точно таким именем (на любом языке, без учёта регистра)
+    pub async fn find_item_by_name(&self, name: &str) -> Option<ItemInfo> {
+        let needle = name.trim().to_lowercase();
+        let ids = self.item_search.read().await.search(&needle, 10);
+        let cache = self.items_cache.read().await;
+        ids.iter().filter_map(|id| cache.get(id)).find(|item| {
+            [Some(&item.name), item.name_en.as_ref(), item.name_ru.as_ref(), item.name_cn.as_ref()]
+                .into_iter()
+                .flatten()
+                .any(|n| n.to_lowercase() == needle)
+        }).cloned()
+    }
+
+    /// Добавить пачку ручного дропа одной записью на диск и одной правкой в журнале undo.
+    /// Как и add_manual_drop, без активной сессии ничего не добавляет
+    pub async fn add_manual_drops_bulk(&self, entries: Vec<ManualDropEntry>) -> Result<usize, String> {
+        let entries = crate::bulk_entry::sanitize(entries)?;
+        if entries.is_empty() {
+            return Ok(0);
+        }
+        {
+            let mut session = self.session.write().await;
+            // Ручной дроп можно добавлять только в активную сессию
+            if session.started_at.is_none() {
+                return Ok(0);
+            }
+            session.manual_drops.extend(entries.iter().cloned());
+            info!("Added {} manual drops in bulk", entries.len());
+            Self::save_session_internal(&session);
+        }
+        let count = entries.len();
+        for entry in &entries {
+            self.remember_manual_price(entry.game_id, entry.price, ManualPriceKind::ManualDrop).await;
+        }
+        self.record_edit(SessionEdit::AddManualDrops { entries }).await;
+        self.notify_changed();
+        Ok(count)
+    }
+
+    /// Удалить ручной дроп. Возвращает false, если записи с таким id нет
+    pub async fn remove_manual_drop(&self, id: &str) -> bool {
+        let removed = {
+            let mut session = self.session.write().await;
+            let removed = session.manual_drops.iter().position(|e| e.id == id)
+                .map(|index| (index, session.manual_drops.remove(index)));
+            if removed.is_some() && session.started_at.is_some() {
+                Self::save_session_internal(&session);
+            }
+            removed
+        };
+        let Some((index, entry)) = removed else {
+            return false;
+        };
         info!("Removed manual drop: {}", id);
+        self.record_edit(SessionEdit::RemoveManualDrop { index, entry }).await;
+        self.notify_changed();
+        true
+    }
+    
+    /// Записать ручную правку в журнал (очищает redo)
+    async fn record_edit(&self, edit: SessionEdit) {
+        let mut journal = self.edit_journal.write().await;
+        journal.push_undo(edit);
+        journal.redo.clear();
+    }
+    
+    /// Отменить последнюю ручную правку. Возвращает отменённую правку
+    pub async fn undo_last(&self) -> Option<SessionEdit> {
+        // Журнал не держим залоченным во время применения (порядок локов session -> journal)
+        let edit = self.edit_journal.write().await.undo.pop_back()?;
+        self.apply_edit(&edit, false).await;
+        info!("Undo: {:?}", edit);
+        self.edit_journal.write().await.redo.push(edit.clone());
+        Some(edit)
+    }
+    
+    /// Повторить последнюю отменённую правку
+    pub async fn redo_last(&self) -> Option<SessionEdit> {
+        let edit = self.edit_journal.write().await.redo.pop()?;
+        self.apply_edit(&edit, true).await;
+        info!("Redo: {:?}", edit);
+        self.edit_journal.write().await.push_undo(edit.clone());
+        Some(edit)
+    }
+    
+    /// Применить правку вперёд (redo) или откатить её (undo), не трогая журнал
+    async fn apply_edit(&self, edit: &SessionEdit, forward: bool) {
+        if let SessionEdit::PricePin { game_id, tier, price, previous } = edit {
+            if forward {
+                match tier {
+                    Some(tier) => self.set_tier_price(*game_id, *tier, *price).await,
+                    None => {
+                        self.update_price(*game_id, *price).await;
+                    }
+                }
+            } else {
+                self.restore_price_entry(*game_id, previous.clone()).await;
+            }
+            return;
+        }
+        
+        let mut session = self.session.write().await;
+        match (edit, forward) {
+            (SessionEdit::AddExpense { entry }, true) => session.expenses.push(entry.clone()),
+            (SessionEdit::AddExpense { entry }, false) | (SessionEdit::RemoveExpense { entry, .. }, true) => {
+                session.expenses.retain(|e| e.id != entry.id);
+            }
+            (SessionEdit::RemoveExpense { index, entry }, false) => {
+                let index = (*index).min(session.expenses.len());
+                session.expenses.insert(index, entry.clone());
+            }
+            (SessionEdit::AddManualDrop { entry }, true) => session.manual_drops.push(entry.clone()),
+            (SessionEdit::AddManualDrop { entry }, false) | (SessionEdit::RemoveManualDrop { entry, .. }, true) => {
+                session.manual_drops.retain(|e| e.id != entry.id);
+            }
+            (SessionEdit::AddManualDrops { entries }, true) => session.manual_drops.extend(entries.iter().cloned()),
+            (SessionEdit::AddManualDrops { entries }, false) => {
+                session.manual_drops.retain(|e| !entries.iter().any(|added| added.id == e.id));
+            }
+            (SessionEdit::RemoveManualDrop { index, entry }, false) => {
+                let index = (*index).min(session.manual_drops.len());
+                session.manual_drops.insert(index, entry.clone());
+            }
+            (SessionEdit::ManualDropSale { id, sale, previous }, forward) => {
+                if let Some(entry) = session.manual_drops.iter_mut().find(|e| &e.id == id) {
+                    entry.sold = if forward { sale.clone() } else { previous.clone() };
+                }
+            }
+            (SessionEdit::PricePin { .. }, _) => {}
+        }
+        if let SessionEdit::AddExpense { entry } | SessionEdit::RemoveExpense { entry, .. } = edit {
+            let added = matches!(edit, SessionEdit::AddExpense { .. }) == forward;
+            self.journal_expense(&session, entry, added);
+        }
+        if session.started_at.is_some() {
+            Self::save_session_internal(&session);
+        }
+        self.notify_changed();
     }
     
+    /// Отметить ручной дроп проданным по фактической цене за штуку (None — снять отметку)
+    pub async fn mark_manual_drop_sold(&self, id: &str, actual_price: Option<f64>, sold_at: Option<DateTime<Utc>>) -> Result<ManualDropEntry, String> {
+        if actual_price.is_some_and(|p| !p.is_finite() || p < 0.0) {
+            return Err("Invalid sale price".to_string());
+        }
+        let sale = actual_price.map(|actual_price| ManualDropSale {
+            actual_price,
+            sold_at: sold_at.unwrap_or_else(Utc::now),
+        });
+        let (entry, previous) = {
+            let mut session = self.session.write().await;
+            let entry = session
+                .manual_drops
+                .iter_mut()
+                .find(|e| e.id == id)
+                .ok_or_else(|| format!("Manual drop {} not found", id))?;
+            let previous = std::mem::replace(&mut entry.sold, sale.clone());
+            let entry = entry.clone();
+            info!("Manual drop {} sold state: {:?}", entry.name, entry.sold);
+            Self::save_session_internal(&session);
+            (entry, previous)
+        };
+        if let Some(sale) = &sale {
+            self.remember_manual_price(entry.game_id, sale.actual_price, ManualPriceKind::ManualDrop).await;
+        }
+        self.record_edit(SessionEdit::ManualDropSale { id: id.to_string(), sale, previous }).await;
+        self.notify_changed();
+        Ok(entry)
+    }
+
     /// Получить список ручного дропа
     pub async fn get_manual_drops(&self) -> Vec<ManualDropEntry> {
         let session = self.session.read().await;
@@ -336,6 +1970,8 @@ impl AppState {
         let session = self.session.read().await;
         let result = session.clone();
         drop(session);
+        self.journal_event(&result, Utc::now(), JournalEvent::SessionEnd);
+        self.journal.close();
         
         let mut session = self.session.write().await;
         *session = FarmSessionState::default();
@@ -343,10 +1979,131 @@ impl AppState {
         
         // Delete session file (normal end)
         let _ = persistence::delete_session();
+        drop(session);
+        // Отмена правок после завершения тронула бы уже пустую сессию
+        *self.edit_journal.write().await = EditJournal::default();
+        self.notify_changed();
+        self.fire_obs_trigger(ObsTrigger::SessionEnd, None).await;
+        crate::live_share::stop_on_shutdown(self).await;
         
         result
     }
     
+    /// Разделить активную сессию в момент `at`: накопленное закрывается (возвращается
+    /// для записи в историю), а сессия продолжается с чистого листа — без сброса парсера,
+    /// паузы и текущей карты.
+    ///
+    /// Дроп хранится без времени подбора, поэтому весь дроп до вызова уходит в закрытую часть;
+    /// `at` задаёт границу по времени: конец закрытой части и начало новой.
+    pub async fn split_session_at(&self, at: DateTime<Utc>) -> Result<FarmSessionState, String> {
+        let currency_now = self.observe_base_currency().await;
+        let now = Utc::now();
+        let mut session = self.session.write().await;
+        let started_at = session.started_at.ok_or("No active session")?;
+        if at < started_at || at > now {
+            return Err(format!("Split time must be between {} and now", started_at.to_rfc3339()));
+        }
+
+        // Секундомер: время после `at` (если он шёл) переходит в новую часть
+        let running_sec = session
+            .clock_resumed_at
+            .map(|t| (now - t).num_seconds().max(0) as i32)
+            .unwrap_or(0);
+        let total_sec = session.session_duration_sec + running_sec;
+        let after_split_sec = if session.clock_resumed_at.is_some() {
+            ((now - at).num_seconds().max(0) as i32).min(total_sec)
+        } else {
+            0
+        };
+
+        let mut closed = session.clone();
+        closed.session_duration_sec = total_sec - after_split_sec;
+        closed.clock_resumed_at = None;
+        // Валюта на границе частей: конец закрытой = начало новой
+        closed.currency_end = closed.currency_end.clone().or(currency_now);
+
+        *session = FarmSessionState {
+            started_at: Some(at),
+            preset_id: session.preset_id.clone(),
+            is_paused: session.is_paused,
+            pause_reason: session.pause_reason,
+            is_on_map: session.is_on_map,
+            current_map_started: session.current_map_started,
+            last_map_event_type: session.last_map_event_type.clone(),
+            last_map_event_ts: session.last_map_event_ts,
+            last_map_scene: session.last_map_scene.clone(),
+            current_map_scene: session.current_map_scene.clone(),
+            current_map_drops: session.current_map_drops.clone(),
+            current_map_tiers: session.current_map_tiers.clone(),
+            // Порог продолжает действовать для новой части, но срабатывает заново
+            profit_alarm: session.profit_alarm.clone().map(|alarm| ProfitAlarm { fired_at: None, ..alarm }),
+            session_duration_sec: after_split_sec,
+            clock_resumed_at: session.clock_resumed_at.map(|_| now),
+            goals: session.goals.clone(),
+            ignored_item_ids: session.ignored_item_ids.clone(),
+            currency_start: closed.currency_end.clone(),
+            // Идущая пауза продолжается в новой части
+            pause_history: session
+                .pause_reason
+                .filter(|_| session.is_paused)
+                .map(|reason| vec![PauseRecord { paused_at: at, resumed_at: None, reason }])
+                .unwrap_or_default(),
+            app_running: true,
+            log_times_utc: true,
+            heartbeat_at: Some(now),
+            ..FarmSessionState::default()
+        };
+        info!(
+            "Session split at {}: closed {} maps / {} sec, continuing",
+            at.to_rfc3339(), closed.maps_completed, closed.session_duration_sec
+        );
+        self.journal_event(&closed, at, JournalEvent::SessionEnd);
+        self.journal_event(&session, at, JournalEvent::SessionStart { preset_id: session.preset_id.clone() });
+        if session.is_paused {
+            if let Some(reason) = session.pause_reason {
+                self.journal_event(&session, at, JournalEvent::Pause { reason });
+            }
+        }
+        Self::save_session_internal(&session);
+        drop(session);
+
+        // Правки журнала ссылаются на индексы закрытой части
+        *self.edit_journal.write().await = EditJournal::default();
+        self.notify_changed();
+        Ok(closed)
+    }
+
+    /// Пересчитать сессию из истории по сегодняшним ценам: дроп — по кэшу цен,
+    /// непроданный ручной дроп в торговых валютах — по текущим курсам. Траты не меняются
+    pub async fn revalue_history_record(&self, record: &persistence::SessionHistoryRecord) -> SessionRevaluation {
+        let valuation = self.valuation().await;
+        let fees = self.fee_model().await;
+        let rates = {
+            let mut guard = self.exchange_rates.write().await;
+            guard.get_or_insert_with(Self::load_exchange_rates).clone()
+        };
+        let items_cache = self.items_cache.read().await;
+        let prices = self.prices_cache.read().await;
+        crate::valuation::revalue_record(record, &valuation, &fees, &items_cache, &prices, &rates)
+    }
+
+    /// Стоимость набора дропов по текущему кэшу цен (части с тиром — по цене тира)
+    pub async fn value_of_drops(&self, drops: &HashMap<i64, i32>, tiers: &HashMap<i64, HashMap<i32, i32>>) -> f64 {
+        let valuation = self.valuation().await;
+        let items_cache = self.items_cache.read().await;
+        let prices = self.prices_cache.read().await;
+        valuation.tiered_drops_value(drops, tiers, &items_cache, &prices)
+    }
+
+    /// Комиссия при продаже набора дропов по текущим ставкам
+    pub async fn fee_of_drops(&self, drops: &HashMap<i64, i32>, tiers: &HashMap<i64, HashMap<i32, i32>>) -> f64 {
+        let valuation = self.valuation().await;
+        let fees = self.fee_model().await;
+        let items_cache = self.items_cache.read().await;
+        let prices = self.prices_cache.read().await;
+        fees.tiered_drops_fee(&valuation, drops, tiers, &items_cache, &prices)
+    }
+    
     /// Обработать событие входа на карту (зарезервировано)
     #[allow(dead_code)]
     pub async fn handle_map_enter(&self, ts: DateTime<Utc>) {
@@ -394,18 +2151,48 @@ impl AppState {
     
     /// Обработать событие смены карты
     pub async fn handle_map_change(&self, event: &MapChangeEvent) {
+        self.touch_log_activity().await;
+
+        // Только фарм-зоны: переход в город/кампанию/испытание — не вход на карту, а выход с неё
+        let (farming_only, abandoned_map_minutes) = {
+            let settings = self.settings.read().await;
+            (settings.farming_zones_only, settings.abandoned_map_minutes)
+        };
+        let event_type = if farming_only && event.zone == ZoneKind::Other {
+            MapEventType::ExitToHideout
+        } else {
+            event.event_type.clone()
+        };
+
         let mut session = self.session.write().await;
         if session.started_at.is_none() {
             return;
         }
 
+        // Подсессия клиента: считаем завершённые карты каждого клиента отдельно (Enter -> Exit)
+        {
+            let source = session.sources.entry(event.source_id.clone()).or_default();
+            if event_type == MapEventType::ExitToHideout
+                && source.last_map_event_type == Some(MapEventType::EnterMap)
+            {
+                source.maps_completed += 1;
+            }
+            source.last_map_event_type = Some(event_type.clone());
+        }
+
+        // Таймер карт общей сессии ведём только по основному клиенту:
+        // события двух клиентов вперемешку ломают Enter/Exit логику.
+        if event.source_id != PRIMARY_LOG_SOURCE {
+            return;
+        }
+
         // 1) Жёсткая дедупликация: одинаковое событие по той же сцене, пришедшее почти сразу.
         if let (Some(last_ty), Some(last_ts), Some(last_scene)) = (
             &session.last_map_event_type,
             session.last_map_event_ts,
             &session.last_map_scene,
         ) {
-            if last_ty == &event.event_type && last_scene == &event.scene_name {
+            if last_ty == &event_type && last_scene == &event.scene_name {
                 let dt = (event.timestamp - last_ts).num_seconds().abs();
                 if dt <= 2 {
                     return;
@@ -414,7 +2201,7 @@ impl AppState {
         }
 
         // 2) Анти-дубль: два подряд Exit без Enter между ними — игнорируем второй и далее.
-        if event.event_type == MapEventType::ExitToHideout
+        if event_type == MapEventType::ExitToHideout
             && session.last_map_event_type == Some(MapEventType::ExitToHideout)
         {
             // Мы уже в “убежище” по логике сессии.
@@ -423,103 +2210,310 @@ impl AppState {
             return;
         }
 
-        match event.event_type {
+        self.journal_event(
+            &session,
+            event.timestamp,
+            JournalEvent::MapChange { event_type: event_type.clone(), scene: event.scene_name.clone() },
+        );
+        match event_type {
             MapEventType::EnterMap => {
                 if !session.is_on_map {
                     session.is_on_map = true;
                     session.current_map_started = Some(event.timestamp);
+                    session.current_map_scene = Some(event.scene_name.clone());
+                    session.current_map_drops.clear();
+                    session.current_map_tiers.clear();
                 }
             }
             MapEventType::ExitToHideout => {
                 // Считаем карту завершённой. Если EnterMap не был пойман (старт сессии внутри карты),
                 // считаем что карта началась в момент старта сессии.
                 let map_started = session.current_map_started.or(session.started_at);
-                session.maps_completed += 1;
+                let duration = map_started.map(|started| (event.timestamp - started).num_seconds().max(0)).unwrap_or(0);
+                // Слишком долгая карта — брошена (смерть и выход из игры, AFK): в среднее не идёт
+                let abandoned = crate::session_replay::is_abandoned_run(duration, abandoned_map_minutes);
+                if abandoned {
+                    session.abandoned_maps += 1;
+                    session.abandoned_map_sec += duration as i32;
+                    info!("Map abandoned after {} sec (cap {} min), not counted as completed", duration, abandoned_map_minutes);
+                } else {
+                    session.maps_completed += 1;
+                    session.total_duration_sec += duration as i32;
+                }
 
                 if let Some(started) = map_started {
-                    let duration = (event.timestamp - started).num_seconds();
-                    if duration > 0 {
-                        session.total_duration_sec += duration as i32;
-                    }
+                    let run = MapRun {
+                        index: session.maps_completed + session.abandoned_maps,
+                        scene_name: session.current_map_scene.take(),
+                        started_at: started,
+                        ended_at: event.timestamp,
+                        duration_sec: duration,
+                        drops: std::mem::take(&mut session.current_map_drops),
+                        tiers: std::mem::take(&mut session.current_map_tiers),
+                        value: None,
+                        abandoned,
+                    };
+                    session.map_runs.push(run);
                 }
 
                 session.is_on_map = false;
                 session.current_map_started = None;
+                session.current_map_scene = None;
             }
         }
 
-        session.last_map_event_type = Some(event.event_type.clone());
+        session.last_map_event_type = Some(event_type.clone());
         session.last_map_event_ts = Some(event.timestamp);
         session.last_map_scene = Some(event.scene_name.clone());
     }
     
+    /// Лог источника пересоздан: прежние состояния его слотов больше не дубли
+    pub fn reset_slot_tracker(&self, source_id: &str) {
+        self.slot_tracker.lock().unwrap().reset_source(source_id);
+    }
+
     /// Добавить дроп
-    /// Игнорирует предметы, которых нет в items_cache (неизвестные предметы)
-    pub async fn add_drop(&self, event: &ItemDropEvent) {
+    /// Игнорирует предметы, которых нет в items_cache (неизвестные предметы).
+    /// Возвращает засчитанное количество (None — дроп не засчитан)
+    pub async fn add_drop(&self, event: &ItemDropEvent) -> Option<i32> {
         let session_guard = self.session.read().await;
         if session_guard.started_at.is_none() {
-            return;
+            return None;
         }
         drop(session_guard);
         
+        // Повтор уже засчитанного состояния слота — не дроп (учитываем и на паузе)
+        let quantity = match self.slot_tracker.lock().unwrap().accept(event) {
+            Some(quantity) => quantity,
+            None => {
+                debug!("Ignoring re-logged slot update: game_id={}, page={}, slot={}, qty={}",
+                       event.game_id, event.page_id, event.slot_id, event.slot_quantity);
+                return None;
+            }
+        };
+        
+        self.touch_log_activity().await;
+
         // Игнорируем дроп если сессия на паузе
         if self.is_paused().await {
             debug!("Ignoring drop while paused: game_id={}", event.game_id);
-            return;
+            return None;
         }
         
-        // Проверяем, есть ли предмет в нашей БД
+        // Проверяем, есть ли предмет в нашей БД. Выброшенный из кэша предмет справочника
+        // засчитывается (имя вернётся с перезагрузкой справочника)
         let items = self.items_cache.read().await;
         if !items.contains_key(&event.game_id) {
-            debug!("Ignoring drop of unknown item: game_id={}", event.game_id);
-            return;
+            if !self.catalog_ids.lock().unwrap().contains(&event.game_id) {
+                debug!("Ignoring drop of unknown item: game_id={}", event.game_id);
+                self.unknown_items.lock().unwrap().record(event, quantity);
+                return None;
+            }
+            debug!("Counting drop of item evicted from cache: game_id={}", event.game_id);
         }
         drop(items);
+        self.item_last_used.lock().unwrap().insert(event.game_id, Utc::now());
+        
+        let (source_mode, farming_only) = {
+            let settings = self.settings.read().await;
+            (settings.log_source_mode, settings.farming_zones_only)
+        };
+        let unit_price = self.prices_cache.read().await.get(&event.game_id).map(|e| e.price);
         
         let mut session = self.session.write().await;
         // Повторная проверка после получения write lock
         if session.started_at.is_none() {
-            return;
+            return None;
+        }
+        
+        // Лог перечитан (режим Since, восстановленная сессия): дроп до старта сессии или
+        // уже засчитанный по этому клиенту — не новый
+        let already_counted = session
+            .sources
+            .get(&event.source_id)
+            .and_then(|s| s.last_drop_ts)
+            .map(|last| event.timestamp < last)
+            .unwrap_or(false);
+        if session.started_at.map(|started| event.timestamp < started).unwrap_or(false) || already_counted {
+            debug!("Ignoring drop logged before the session or already counted: game_id={}", event.game_id);
+            return None;
+        }
+        
+        // Игнор-лист сессии (из шаблона)
+        if session.ignored_item_ids.contains(&event.game_id) {
+            debug!("Ignoring drop of ignore-listed item: game_id={}", event.game_id);
+            return None;
+        }
+        
+        // Только фарм-зоны: дроп в убежище, городе или кампании не считается
+        if farming_only {
+            let on_map = if event.source_id == PRIMARY_LOG_SOURCE {
+                session.is_on_map
+            } else {
+                session.sources.get(&event.source_id)
+                    .map(|s| s.last_map_event_type == Some(MapEventType::EnterMap))
+                    .unwrap_or(false)
+            };
+            if !on_map {
+                debug!("Ignoring drop outside farming map: game_id={}", event.game_id);
+                return None;
+            }
+        }
+        
+        self.journal_event(
+            &session,
+            event.timestamp,
+            JournalEvent::Drop {
+                game_id: event.game_id,
+                quantity,
+                tier: event.tier,
+                source_id: event.source_id.clone(),
+                unit_price,
+            },
+        );
+
+        // Подсессия клиента ведётся всегда
+        let source = session.sources.entry(event.source_id.clone()).or_default();
+        *source.drops.entry(event.game_id).or_insert(0) += quantity;
+        source.last_drop_ts = Some(source.last_drop_ts.map_or(event.timestamp, |last| last.max(event.timestamp)));
+        
+        if source_mode == LogSourceMode::PerSource && event.source_id != PRIMARY_LOG_SOURCE {
+            debug!("Added drop to sub-session [{}]: game_id={}, qty={}",
+                   event.source_id, event.game_id, quantity);
+            Self::save_session_internal(&session);
+            return Some(quantity);
         }
         
         let current = session.drops.get(&event.game_id).copied().unwrap_or(0);
-        session.drops.insert(event.game_id, current + event.quantity);
+        session.drops.insert(event.game_id, current + quantity);
+        session.last_drop_at.insert(event.game_id, event.timestamp);
+        if let Some(tier) = event.tier {
+            *session.drop_tiers.entry(event.game_id).or_default().entry(tier).or_insert(0) += quantity;
+        }
+        if session.is_on_map {
+            *session.current_map_drops.entry(event.game_id).or_insert(0) += quantity;
+            if let Some(tier) = event.tier {
+                *session.current_map_tiers.entry(event.game_id).or_default().entry(tier).or_insert(0) += quantity;
+            }
+        }
         
         debug!("Added drop: game_id={}, qty={}, total={}", 
-               event.game_id, event.quantity, current + event.quantity);
+               event.game_id, quantity, current + quantity);
         
         // Auto-save session
         Self::save_session_internal(&session);
+        Some(quantity)
+    }
+    
+    /// Ручная цена предмета (из UI) — как update_price, но попадает в журнал undo
+    pub async fn pin_price(&self, game_id: i64, price: f64) {
+        let previous = self.prices_cache.read().await.get(&game_id).cloned();
+        if self.update_price(game_id, price).await {
+            self.record_edit(SessionEdit::PricePin { game_id, tier: None, price, previous }).await;
+        }
+    }
+    
+    /// Задать цену отдельного тира экипировки (с отменой, как pin_price).
+    /// Если у предмета ещё нет цены, она же становится общей
+    pub async fn pin_tier_price(&self, game_id: i64, tier: i32, price: f64) {
+        let previous = self.prices_cache.read().await.get(&game_id).cloned();
+        self.set_tier_price(game_id, tier, price).await;
+        self.record_edit(SessionEdit::PricePin { game_id, tier: Some(tier), price, previous }).await;
+    }
+
+    async fn set_tier_price(&self, game_id: i64, tier: i32, price: f64) {
+        {
+            let mut prices = self.prices_cache.write().await;
+            let entry = prices.entry(game_id).or_insert_with(|| persistence::PersistedPriceEntry {
+                price,
+                updated_at: Utc::now(),
+                is_current_league: true,
+                league_name: None,
+                sample_count: None,
+                spread: None,
+                tiers: HashMap::new(),
+            });
+            entry.tiers.insert(tier, price);
+        }
+        self.journal_event(&*self.session.read().await, Utc::now(), JournalEvent::TierPriceUpdate { game_id, tier, price });
+        debug!("Pinned tier price: game_id={}, tier={}, price={}", game_id, tier, price);
+        self.mark_prices_dirty(game_id);
+        self.notify_changed();
+    }
+    
+    /// Вернуть запись кэша цен в прежнее состояние (None — удалить цену)
+    async fn restore_price_entry(&self, game_id: i64, entry: Option<persistence::PersistedPriceEntry>) {
+        let (price, tiers) = entry.as_ref().map(|e| (Some(e.price), e.tiers.clone())).unwrap_or_default();
+        self.journal_event(&*self.session.read().await, Utc::now(), JournalEvent::PriceRestore { game_id, price, tiers });
+        let mut prices = self.prices_cache.write().await;
+        match entry {
+            Some(entry) => { prices.insert(game_id, entry); }
+            None => { prices.remove(&game_id); }
+        }
+        drop(prices);
+        self.mark_prices_dirty(game_id);
+        self.notify_changed();
     }
     
-    /// Обновить цену предмета в кэше
-    pub async fn update_price(&self, game_id: i64, price: f64) {
+    /// Обновить цену предмета в кэше. Возвращает false, если цена не применена (базовая валюта)
+    pub async fn update_price(&self, game_id: i64, price: f64) -> bool {
+        self.update_price_with_samples(game_id, price, None).await
+    }
+
+    /// Обновить цену из прайсчека вместе со статистикой лотов (для оценки уверенности)
+    pub async fn update_price_with_samples(&self, game_id: i64, price: f64, samples: Option<SampleStats>) -> bool {
         // Проверяем, является ли предмет базовой валютой
         let items = self.items_cache.read().await;
         if let Some(item) = items.get(&game_id) {
             if item.is_base_currency {
                 debug!("Skipping price update for base currency: game_id={}", game_id);
-                return;
+                return false;
             }
         }
         drop(items);
         
         let mut prices = self.prices_cache.write().await;
         let now = Utc::now();
+        let tiers = prices.get(&game_id).map(|p| p.tiers.clone()).unwrap_or_default();
         prices.insert(game_id, persistence::PersistedPriceEntry { 
             price, 
             updated_at: now,
             is_current_league: true,  // Цена получена через прайсчек = текущая лига
             league_name: None,
+            sample_count: samples.map(|s| s.count),
+            spread: samples.map(|s| s.spread),
+            tiers,
         });
         debug!("Updated price: game_id={}, price={}", game_id, price);
 
-        // Персистим на диск, чтобы цена переживала новую сессию/перезапуск.
-        // Ошибки не фейлят приложение.
-        let snapshot = prices.clone();
+        // На диск цена попадёт при ближайшем flush (см. flush_prices_cache),
+        // чтобы серия прайсчеков не переписывала файл на каждое событие
         drop(prices);
-        if let Err(e) = persistence::save_prices_cache(&snapshot) {
-            debug!("Failed to persist prices cache: {}", e);
+        self.mark_prices_dirty(game_id);
+        self.journal_event(&*self.session.read().await, now, JournalEvent::PriceUpdate { game_id, price });
+        self.notify_changed();
+        true
+    }
+
+    /// Отметить цену как изменённую (запишется при ближайшем flush)
+    fn mark_prices_dirty(&self, game_id: i64) {
+        self.prices_dirty.lock().unwrap().insert(game_id);
+    }
+
+    /// Записать кэш цен на диск, если с прошлой записи что-то менялось.
+    /// Вызывается периодически и перед выходом; при ошибке ключи остаются грязными
+    pub async fn flush_prices_cache(&self) {
+        let dirty = std::mem::take(&mut *self.prices_dirty.lock().unwrap());
+        if dirty.is_empty() {
+            return;
+        }
+        let snapshot = self.prices_cache.read().await.clone();
+        match persistence::save_prices_cache(&snapshot) {
+            Ok(()) => debug!("Flushed prices cache: {} changed of {}", dirty.len(), snapshot.len()),
+            Err(e) => {
+                debug!("Failed to persist prices cache: {}", e);
+                self.prices_dirty.lock().unwrap().extend(dirty);
+            }
         }
     }
 
@@ -540,12 +2534,96 @@ impl AppState {
         }
     }
 
+    /// Выбросить лишнее из кэшей цен и предметов по AppSettings.cache_limits.
+    /// Возвращает (выброшено цен, выброшено предметов)
+    pub async fn evict_caches(&self) -> (usize, usize) {
+        let (limits, mut keep) = {
+            let settings = self.settings.read().await;
+            (settings.cache_limits.clone(), settings.gamble_containers.iter().copied().collect::<HashSet<i64>>())
+        };
+        {
+            // Дроп текущей сессии, включая подсессии других клиентов
+            let session = self.session.read().await;
+            keep.extend(session.drops.keys().copied());
+            keep.extend(session.sources.values().flat_map(|s| s.drops.keys().copied()));
+        }
+        // Пользовательские предметы и их цены не восстановить с сервера
+        keep.extend(self.items_cache.read().await.values().filter(|i| i.is_custom()).map(|i| i.game_id));
+        let league = self.current_league().await;
+
+        let evicted_prices = {
+            let mut prices = self.prices_cache.write().await;
+            crate::cache_eviction::evict_prices(&mut prices, &limits, league.as_deref(), &keep, Utc::now())
+        };
+        if !evicted_prices.is_empty() {
+            self.prices_dirty.lock().unwrap().extend(evicted_prices.iter().copied());
+        }
+
+        let evicted_items = {
+            let mut items = self.items_cache.write().await;
+            let prices = self.prices_cache.read().await;
+            let last_used = self.item_last_used.lock().unwrap().clone();
+            let evicted = crate::cache_eviction::evict_items(&mut items, limits.max_items, &last_used, |game_id| {
+                keep.contains(&game_id) || prices.contains_key(&game_id)
+            });
+            if evicted > 0 {
+                *self.item_search.write().await = ItemSearchIndex::build(items.values());
+            }
+            evicted
+        };
+
+        if !evicted_prices.is_empty() || evicted_items > 0 {
+            info!("Cache eviction: {} prices, {} items removed", evicted_prices.len(), evicted_items);
+        }
+        (evicted_prices.len(), evicted_items)
+    }
+
+    /// Крупные валюты для разложения сумм: AppSettings.denomination_items с известной ценой
+    pub async fn denominations(&self) -> Vec<crate::value_format::Denomination> {
+        let (ids, language) = {
+            let settings = self.settings.read().await;
+            (settings.denomination_items.clone(), settings.language.clone())
+        };
+        let items_cache = self.items_cache.read().await;
+        let prices = self.prices_cache.read().await;
+        ids.into_iter()
+            .filter_map(|game_id| {
+                let unit_value = Valuation::unit_price(game_id, &items_cache, &prices)?;
+                let name = items_cache
+                    .get(&game_id)
+                    .map(|item| item.localized_name(&language).to_string())
+                    .unwrap_or_else(|| format!("#{}", game_id));
+                Some(crate::value_format::Denomination { game_id, name, unit_value })
+            })
+            .collect()
+    }
+
+    /// Размеры кэшей и их примерный объём в памяти
+    pub async fn cache_stats(&self) -> crate::cache_eviction::CacheStats {
+        use crate::cache_eviction::{is_current, item_bytes, price_bytes};
+
+        let limits = self.settings.read().await.cache_limits.clone();
+        let current_league = self.current_league().await;
+        let items = self.items_cache.read().await;
+        let prices = self.prices_cache.read().await;
+        crate::cache_eviction::CacheStats {
+            items: items.len(),
+            items_bytes: items.values().map(item_bytes).sum(),
+            prices: prices.len(),
+            prices_bytes: prices.values().map(price_bytes).sum(),
+            prices_current_league: prices.values().filter(|e| is_current(e, current_league.as_deref())).count(),
+            current_league,
+            limits,
+        }
+    }
+
     /// Слить remote цены (Supabase current prices) в локальный кэш.
     /// Не перетираем более свежие значения.
     pub async fn merge_remote_prices(&self, rows: Vec<(i64, f64, DateTime<Utc>)>) {
         let items = self.items_cache.read().await;
         let mut prices = self.prices_cache.write().await;
         let mut updated = 0usize;
+        let mut changed = Vec::new();
         for (game_id, price, ts) in rows {
             // Не обновляем цену базовой валюты
             if let Some(item) = items.get(&game_id) {
@@ -562,17 +2640,40 @@ impl AppState {
                 Some(existing) => ts > existing.updated_at,
             };
             if replace {
+                if prices.get(&game_id).map(|p| p.price) != Some(price) {
+                    changed.push((game_id, price));
+                }
+                let tiers = prices.get(&game_id).map(|p| p.tiers.clone()).unwrap_or_default();
                 prices.insert(game_id, persistence::PersistedPriceEntry { 
                     price, 
                     updated_at: ts,
                     is_current_league: true,
                     league_name: None,
+                    sample_count: None,
+                    spread: None,
+                    tiers,
                 });
                 updated += 1;
             }
         }
+        drop(prices);
+        drop(items);
+        self.journal_price_updates(changed).await;
         if updated > 0 {
             debug!("Merged remote prices: {} updated", updated);
+            self.notify_changed();
+        }
+    }
+
+    /// Цены с сервера, изменившиеся во время сессии, — в журнал (для воспроизведения прибыли)
+    async fn journal_price_updates(&self, changed: Vec<(i64, f64)>) {
+        if changed.is_empty() {
+            return;
+        }
+        let session = self.session.read().await;
+        let now = Utc::now();
+        for (game_id, price) in changed {
+            self.journal_event(&session, now, JournalEvent::PriceUpdate { game_id, price });
         }
     }
 
@@ -581,6 +2682,7 @@ impl AppState {
         let items = self.items_cache.read().await;
         let mut prices = self.prices_cache.write().await;
         let mut updated = 0usize;
+        let mut changed = Vec::new();
         
         for row in rows {
             // Не обновляем цену базовой валюты
@@ -604,18 +2706,30 @@ impl AppState {
             };
             
             if replace {
+                if prices.get(&row.game_id).map(|p| p.price) != Some(row.price) {
+                    changed.push((row.game_id, row.price));
+                }
+                let tiers = prices.get(&row.game_id).map(|p| p.tiers.clone()).unwrap_or_default();
                 prices.insert(row.game_id, persistence::PersistedPriceEntry { 
                     price: row.price, 
                     updated_at: row.last_updated,
                     is_current_league: row.is_current_league,
                     league_name: Some(row.league_name),
+                    sample_count: row.sample_count.map(|n| n.clamp(0, u32::MAX as i64) as u32),
+                    // Цена выше проверена на > 0; нечисловой stddev с сервера не сохраняем
+                    spread: row.price_stddev.map(|sd| sd / row.price).filter(|s| s.is_finite()),
+                    tiers,
                 });
                 updated += 1;
             }
         }
         
+        drop(prices);
+        drop(items);
+        self.journal_price_updates(changed).await;
         if updated > 0 {
             debug!("Merged prices with league info: {} updated", updated);
+            self.notify_changed();
         }
     }
 
@@ -656,19 +2770,203 @@ impl AppState {
         prices.iter().map(|(k, v)| (*k, v.price)).collect()
     }
     
-    /// Загрузить информацию о предметах в кэш
+    /// Загрузить информацию о предметах в кэш. Набор id справочника собирается заново:
+    /// убранные с сервера предметы снова считаются неизвестными
     pub async fn load_items_cache(&self, items: Vec<ItemInfo>) {
         let mut cache = self.items_cache.write().await;
-        for item in items {
-            cache.insert(item.game_id, item);
+        let resolved = {
+            let mut catalog = self.catalog_ids.lock().unwrap();
+            if !items.is_empty() {
+                catalog.clear();
+            }
+            for item in items {
+                catalog.insert(item.game_id);
+                cache.insert(item.game_id, item);
+            }
+            info!("Loaded {} items into cache", cache.len());
+            self.unknown_items.lock().unwrap().retain_unknown(|id| catalog.contains(&id))
+        };
+        if resolved > 0 {
+            info!("{} previously unknown items are now in the catalog", resolved);
         }
-        info!("Loaded {} items into cache", cache.len());
+        *self.item_search.write().await = ItemSearchIndex::build(cache.values());
         drop(cache);
         
         // Инициализируем базовую валюту с ценой 1.0
         self.init_base_currency_price().await;
+        self.evict_caches().await;
+        self.notify_changed();
     }
     
+    /// Перечитать список предметов из Supabase, дополнить кэш и вернуть, что изменилось
+    pub async fn refresh_items_cache(&self) -> Result<crate::items_diff::ItemsDiff, String> {
+        let api = self.supabase_client().await.ok_or("Supabase is not configured")?;
+        let items = crate::supabase_sync::fetch_game_items(&api).await?;
+        let language = self.settings.read().await.language.clone();
+        let report = crate::items_diff::diff(&*self.items_cache.read().await, &items, &language);
+        if report.is_empty() {
+            info!("Items refreshed: no changes in {} items", report.total);
+        } else {
+            info!(
+                "Items refreshed: {} new, {} renamed, {} moved to another category",
+                report.new_items.len(),
+                report.renamed.len(),
+                report.category_changes.len()
+            );
+        }
+        self.load_items_cache(items).await;
+        self.load_categories(&api).await;
+        Ok(report)
+    }
+
+    /// Звук и голосовое объявление засчитанного дропа (стоимость — по кэшу цен)
+    pub async fn alert_drop(&self, game_id: i64, quantity: i32, at: DateTime<Utc>) {
+        // Догонялка лога: старые дропы не озвучиваем и не объявляем пачкой
+        if self.is_catching_up() || (Utc::now() - at).num_seconds() > ALERT_MAX_AGE_SEC {
+            return;
+        }
+        let (sounds, tts, twitch, language) = {
+            let settings = self.settings.read().await;
+            let sounds = settings.drop_sounds.enabled && !settings.drop_sounds.rules.is_empty();
+            if !sounds && !settings.tts.enabled && !settings.obs.enabled && !settings.twitch.enabled {
+                return;
+            }
+            (
+                sounds.then(|| settings.drop_sounds.clone()),
+                settings.tts.clone(),
+                settings.twitch.clone(),
+                settings.language.clone(),
+            )
+        };
+        let valuation = self.valuation().await;
+        let items = self.items_cache.read().await;
+        let Some(item) = items.get(&game_id) else {
+            return;
+        };
+        let value = Valuation::unit_price(game_id, &items, &*self.prices_cache.read().await)
+            .map(|unit_price| valuation.line_value(unit_price, quantity));
+        if let Some(sounds) = sounds {
+            let catalog = self.categories.read().await;
+            if let Some(rule) = crate::drop_sounds::match_rule(&sounds, item, value, &catalog) {
+                debug!("Drop sound '{}' for game_id={} (value {:?})", rule.sound, game_id, value);
+                self.sound_player.play_rule(&sounds, rule);
+            }
+        }
+        if let Some(value) = value {
+            self.announcer.announce_drop(&tts, item.localized_name(&language), quantity, value, &language);
+            self.twitch.announce_drop(&twitch, item.localized_name(&language), quantity, value);
+            drop(items);
+            self.fire_obs_trigger(ObsTrigger::BigDrop, Some(value)).await;
+        }
+    }
+
+    /// Запустить в фоне действия OBS, привязанные к событию
+    pub async fn fire_obs_trigger(&self, trigger: ObsTrigger, value: Option<f64>) {
+        let settings = self.settings.read().await.obs.clone();
+        let actions = crate::obs::matching_actions(&settings, trigger, value);
+        if actions.is_empty() {
+            return;
+        }
+        if !self.obs_limiter.allow(trigger, std::time::Instant::now()) {
+            debug!("OBS trigger {:?} skipped (rate limit)", trigger);
+            return;
+        }
+        debug!("OBS trigger {:?}: {} action(s)", trigger, actions.len());
+        tauri::async_runtime::spawn(crate::obs::run_actions(settings, actions));
+    }
+
+    /// Проговорить пример объявления (проверка голоса и громкости в настройках)
+    pub async fn preview_tts(&self) {
+        let settings = self.settings.read().await;
+        let text = crate::drop_announcer::announcement_text("Flame Elementium", 1, settings.tts.min_value, &settings.language);
+        self.announcer.speak(text, &settings.language, settings.tts.volume);
+    }
+
+    /// Предпрослушать звук из настроек
+    pub async fn preview_drop_sound(&self, sound: &str, volume: Option<f32>) {
+        let master = self.settings.read().await.drop_sounds.volume;
+        self.sound_player.play(sound, master * volume.unwrap_or(1.0));
+    }
+
+    /// Загрузить справочник категорий. Нет таблицы, сети или таблица пуста — остаётся прежний
+    pub async fn load_categories(&self, api: &SupabaseClient) {
+        match crate::supabase_sync::fetch_categories(api).await {
+            Ok(categories) => {
+                let catalog = CategoryCatalog::new(categories);
+                if catalog.is_empty() {
+                    debug!("Item categories table is empty, keeping the current catalog");
+                    return;
+                }
+                debug!("Loaded {} item categories", catalog.len());
+                *self.categories.write().await = catalog;
+                self.notify_changed();
+            }
+            Err(e) => debug!("Failed to load item categories: {}", e),
+        }
+    }
+
+    /// Категории на языке отображения (в порядке иерархии)
+    pub async fn get_categories(&self) -> Vec<CategoryView> {
+        let language = self.settings.read().await.language.clone();
+        self.categories.read().await.views(&language)
+    }
+
+    /// Загрузить пользовательские предметы с диска (best-effort)
+    pub async fn load_custom_items_from_disk(&self) {
+        match persistence::load_custom_items() {
+            Ok(items) if !items.is_empty() => {
+                let mut cache = self.items_cache.write().await;
+                let count = items.len();
+                for item in items {
+                    cache.insert(item.game_id, item);
+                }
+                *self.item_search.write().await = ItemSearchIndex::build(cache.values());
+                debug!("Loaded {} custom items from disk", count);
+            }
+            Ok(_) => {}
+            Err(e) => debug!("Failed to load custom items: {}", e),
+        }
+    }
+
+    /// Создать пользовательский предмет (его нет в tli_game_items): отрицательный game_id,
+    /// хранится локально, находится поиском и помнит цену как обычный предмет
+    pub async fn create_custom_item(&self, name: String, category: String, default_price: Option<f64>) -> Result<ItemInfo, String> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err("Item name is empty".to_string());
+        }
+        let item = {
+            let mut cache = self.items_cache.write().await;
+            if cache.values().any(|i| i.is_custom() && i.name.eq_ignore_ascii_case(&name)) {
+                return Err(format!("Custom item '{}' already exists", name));
+            }
+            let game_id = cache.keys().copied().filter(|id| *id < 0).min().unwrap_or(0) - 1;
+            let item = ItemInfo {
+                game_id,
+                name: name.clone(),
+                name_en: Some(name.clone()),
+                name_ru: Some(name),
+                name_cn: None,
+                category: category.trim().to_string(),
+                icon_url: None,
+                is_base_currency: false,
+            };
+            let mut custom: Vec<ItemInfo> = cache.values().filter(|i| i.is_custom()).cloned().collect();
+            custom.push(item.clone());
+            custom.sort_by_key(|i| std::cmp::Reverse(i.game_id));
+            persistence::save_custom_items(&custom).map_err(|e| format!("Failed to save custom items: {}", e))?;
+            cache.insert(game_id, item.clone());
+            *self.item_search.write().await = ItemSearchIndex::build(cache.values());
+            item
+        };
+        if let Some(price) = default_price.filter(|p| *p > 0.0) {
+            self.update_price(item.game_id, price).await;
+        }
+        info!("Created custom item {} (game_id={})", item.name, item.game_id);
+        self.notify_changed();
+        Ok(item)
+    }
+
     /// Инициализировать цену базовой валюты (всегда 1.0)
     async fn init_base_currency_price(&self) {
         let items = self.items_cache.read().await;
@@ -686,6 +2984,9 @@ impl AppState {
                     updated_at: Utc::now(),
                     is_current_league: true,
                     league_name: None,
+                    sample_count: None,
+                    spread: None,
+                    tiers: HashMap::new(),
                 }
             );
             debug!("Initialized base currency price: game_id={}, price=1.0", game_id);
@@ -694,12 +2995,25 @@ impl AppState {
     
     /// Получить информацию о предмете
     pub async fn get_item_info(&self, game_id: i64) -> Option<ItemInfo> {
+        let language = self.display_language().await;
         let cache = self.items_cache.read().await;
-        cache.get(&game_id).cloned()
+        cache.get(&game_id).map(|item| item.localized(&language))
     }
     
+    /// Калькулятор стоимостей с точностью и округлением из настроек
+    pub async fn valuation(&self) -> Valuation {
+        Valuation::new(self.settings.read().await.valuation)
+    }
+
+    /// Ставки комиссии из настроек
+    pub async fn fee_model(&self) -> FeeModel {
+        FeeModel::new(&*self.settings.read().await)
+    }
+
     /// Получить статистику сессии
     pub async fn get_session_stats(&self) -> SessionStats {
+        let valuation = self.valuation().await;
+        let fees = self.fee_model().await;
         let session = self.session.read().await;
         let items_cache = self.items_cache.read().await;
         let prices = self.prices_cache.read().await;
@@ -707,31 +3021,25 @@ impl AppState {
         let total_items: i32 = session.drops.values().sum();
         let unique_items = session.drops.len() as i32;
         
-        // Вычисляем общую стоимость
-        let mut total_value = 0.0;
-        let mut stale_price_lines = 0i32;
-        for (game_id, qty) in &session.drops {
-            // Проверяем является ли предмет базовой валютой
-            let is_base_currency = items_cache.get(game_id)
-                .map(|i| i.is_base_currency)
-                .unwrap_or(false);
-            
-            if is_base_currency {
-                // Для базовой валюты цена всегда 1.0 и никогда не устаревает
-                total_value += 1.0 * (*qty as f64);
-            } else if let Some(price_entry) = prices.get(game_id) {
-                // Доход считаем всегда (даже по устаревшим ценам), но помечаем что часть цен старые,
-                // чтобы UI мог попросить пользователя обновить прайсчек.
-                total_value += price_entry.price * (*qty as f64);
-                if Self::is_price_stale_internal(price_entry) {
-                    stale_price_lines += 1;
-                }
-            }
-        }
+        // Доход считаем всегда (даже по устаревшим ценам), но помечаем что часть цен старые,
+        // чтобы UI мог попросить пользователя обновить прайсчек.
+        // Базовая валюта всегда стоит 1.0 и никогда не устаревает.
+        let total_value = valuation.tiered_drops_value(&session.drops, &session.drop_tiers, &items_cache, &prices);
+        let total_fee = fees.tiered_drops_fee(&valuation, &session.drops, &session.drop_tiers, &items_cache, &prices);
+        let stale_price_lines = session
+            .drops
+            .keys()
+            .filter(|game_id| !items_cache.get(game_id).map(|i| i.is_base_currency).unwrap_or(false))
+            .filter_map(|game_id| prices.get(game_id))
+            .filter(|entry| Self::is_price_stale_internal(entry))
+            .count() as i32;
         
-        // Длительность сессии — просто значение из session_duration_sec
-        // (обновляется фронтендом каждую секунду)
-        let duration_sec = session.session_duration_sec;
+        // Длительность сессии: накопленное время + ещё не перенесённый хвост секундомера
+        let duration_sec = session.session_duration_sec
+            + session
+                .clock_resumed_at
+                .map(|t| (Utc::now() - t).num_seconds().max(0) as i32)
+                .unwrap_or(0);
 
         // Средняя длительность карты: используем map-only время (total_duration_sec).
         // Если карт ещё нет, но мы на карте — показываем время текущей карты как “среднее” (удобно для первой карты).
@@ -750,13 +3058,25 @@ impl AppState {
         };
         
         // Доход в час
-        let hourly_profit = if duration_sec > 0 {
-            total_value / (duration_sec as f64) * 3600.0
-        } else {
-            0.0
-        };
+        let hourly_profit = valuation.per_hour(total_value, duration_sec);
+
+        // Доход в час по чистому времени на картах: убежище и продажа его не размывают
+        let map_time_sec = session.total_duration_sec
+            + if session.is_on_map { current_map_elapsed_sec } else { 0 };
+        let map_hourly_profit = valuation.per_hour(total_value, map_time_sec);
+
+        // Чистая прибыль в час, как итоговая прибыль: за вычетом комиссии и расходов
+        let net_profit = total_value - total_fee - valuation.expenses_total(&session.expenses);
+        let net_hourly_profit = valuation.per_hour(net_profit, duration_sec);
+        let net_map_hourly_profit = valuation.per_hour(net_profit, map_time_sec);
         
         let maps_completed = session.maps_completed;
+        let pause_reason = session.pause_reason;
+        let paused_sec = session.paused_sec(Utc::now());
+        let profit_alarm = session.profit_alarm.clone();
+        let (manual_realized, manual_pending) = session.manual_sales();
+        let (recovered, recovery_gap_sec) = (session.recovered, session.recovery_gap_sec);
+        let (abandoned_maps, abandoned_map_sec) = (session.abandoned_maps, session.abandoned_map_sec);
         
         // Освобождаем блокировки перед получением is_paused
         drop(session);
@@ -770,45 +3090,230 @@ impl AppState {
             total_items,
             unique_items,
             total_value,
+            total_fee,
             maps_completed,
             duration_sec,
             avg_map_duration_sec,
             stale_price_lines,
             hourly_profit,
+            map_time_sec,
+            map_hourly_profit,
+            net_hourly_profit,
+            net_map_hourly_profit,
             is_paused,
+            pause_reason: if is_paused { pause_reason } else { None },
+            paused_sec,
+            profit_alarm,
+            manual_realized_value: valuation.round(manual_realized),
+            manual_pending_value: valuation.round(manual_pending),
+            recovered,
+            recovery_gap_sec,
+            abandoned_maps,
+            abandoned_map_sec,
+        }
+    }
+    
+    /// Собрать анонимный снимок сессии из истории для публичной ссылки
+    pub async fn build_shared_snapshot(
+        &self,
+        record: &persistence::SessionHistoryRecord,
+    ) -> crate::supabase_sync::SharedSessionSnapshot {
+        crate::supabase_sync::SharedSessionSnapshot {
+            started_at: record.started_at,
+            ended_at: record.ended_at,
+            maps_completed: record.maps_completed,
+            total_duration_sec: record.total_duration_sec,
+            total_income: record.total_income,
+            total_expenses: record.total_expenses,
+            total_fee: record.total_fee,
+            total_profit: record.total_profit,
+            drops: self.shared_drops(&record.drops).await,
+            client_version: env!("CARGO_PKG_VERSION").to_string(),
+            is_live: false,
+        }
+    }
+
+    /// Снимок идущей сессии для live-шаринга. None — сессия не запущена
+    pub async fn build_live_snapshot(&self) -> Option<crate::supabase_sync::SharedSessionSnapshot> {
+        let stats = self.get_session_stats().await;
+        let valuation = self.valuation().await;
+        let (started_at, drops, expenses) = {
+            let session = self.session.read().await;
+            (session.started_at?, session.drops.clone(), valuation.expenses_total(&session.expenses))
+        };
+        Some(crate::supabase_sync::SharedSessionSnapshot {
+            started_at,
+            ended_at: Utc::now(),
+            maps_completed: stats.maps_completed,
+            total_duration_sec: stats.duration_sec,
+            total_income: stats.total_value,
+            total_expenses: expenses,
+            total_fee: stats.total_fee,
+            total_profit: valuation.round(stats.total_value - expenses - stats.total_fee),
+            drops: self.shared_drops(&drops).await,
+            client_version: env!("CARGO_PKG_VERSION").to_string(),
+            is_live: true,
+        })
+    }
+
+    /// Строки дропа для снимка сессии, самые дорогие первыми
+    async fn shared_drops(&self, drops: &HashMap<i64, i32>) -> Vec<crate::supabase_sync::SharedDrop> {
+        let valuation = self.valuation().await;
+        let items_cache = self.items_cache.read().await;
+        let prices = self.prices_cache.read().await;
+        
+        let mut drops: Vec<crate::supabase_sync::SharedDrop> = drops.iter().map(|(game_id, qty)| {
+            let item = items_cache.get(game_id);
+            let unit_price = Valuation::unit_price(*game_id, &items_cache, &prices).unwrap_or(0.0);
+            crate::supabase_sync::SharedDrop {
+                game_id: *game_id,
+                name: item.map(|i| i.name.clone()),
+                quantity: *qty,
+                unit_price,
+                total_value: valuation.line_value(unit_price, *qty),
+            }
+        }).collect();
+        drops.sort_by(|a, b| b.total_value.partial_cmp(&a.total_value).unwrap_or(std::cmp::Ordering::Equal));
+        drops
+    }
+    
+    /// Очередь прайсчека: предметы текущего дропа без актуальной цены.
+    /// Порядок: нет цены → цена прошлого сезона → устаревшая; внутри — по количеству.
+    /// Отложенные предметы идут в конец.
+    pub async fn get_pricecheck_queue(&self) -> PricecheckQueue {
+        let language = self.display_language().await;
+        let session = self.session.read().await;
+        let items_cache = self.items_cache.read().await;
+        let prices = self.prices_cache.read().await;
+        let skipped = self.pricecheck_skipped.read().await;
+        
+        let mut items: Vec<PricecheckItem> = session.drops.iter().filter_map(|(game_id, qty)| {
+            let item_info = items_cache.get(game_id).map(|item| item.localized(&language));
+            if item_info.as_ref().map(|i| i.is_base_currency).unwrap_or(false) {
+                return None;
+            }
+            let (reason, last_price) = match prices.get(game_id) {
+                None => (PricecheckReason::Missing, None),
+                Some(p) if !p.is_current_league => (PricecheckReason::PreviousSeason, Some(p.price)),
+                Some(p) if Self::is_price_stale_internal(p) => (PricecheckReason::Stale, Some(p.price)),
+                Some(_) => return None,
+            };
+            Some(PricecheckItem {
+                game_id: *game_id,
+                item_info,
+                quantity: *qty,
+                reason,
+                last_price,
+                skipped: skipped.contains(game_id),
+            })
+        }).collect();
+        
+        let reason_rank = |r: PricecheckReason| match r {
+            PricecheckReason::Missing => 0,
+            PricecheckReason::PreviousSeason => 1,
+            PricecheckReason::Stale => 2,
+        };
+        let skip_rank = |game_id: i64| skipped.iter().position(|id| *id == game_id);
+        items.sort_by(|a, b| {
+            skip_rank(a.game_id).cmp(&skip_rank(b.game_id))
+                .then_with(|| reason_rank(a.reason).cmp(&reason_rank(b.reason)))
+                .then_with(|| b.quantity.cmp(&a.quantity))
+                .then_with(|| a.game_id.cmp(&b.game_id))
+        });
+        
+        PricecheckQueue {
+            current: items.first().map(|i| i.game_id),
+            items,
         }
     }
     
+    /// Отложить предмет в очереди прайсчека (переносится в конец)
+    pub async fn skip_pricecheck_item(&self, game_id: i64) {
+        let mut skipped = self.pricecheck_skipped.write().await;
+        skipped.retain(|id| *id != game_id);
+        skipped.push(game_id);
+    }
+    
+    /// Отметить, что по предмету пришёл свежий прайсчек.
+    /// Возвращает true, если предмет есть в дропе сессии (очередь изменилась).
+    pub async fn complete_pricecheck(&self, game_id: i64) -> bool {
+        self.pricecheck_skipped.write().await.retain(|id| *id != game_id);
+        let session = self.session.read().await;
+        session.drops.contains_key(&game_id)
+    }
+    
+    /// Статистика подсессий по клиентам игры (мультибокс)
+    pub async fn get_source_sessions(&self) -> Vec<SourceSessionStats> {
+        let valuation = self.valuation().await;
+        let session = self.session.read().await;
+        let items_cache = self.items_cache.read().await;
+        let prices = self.prices_cache.read().await;
+        let log_sources = self.log_sources.read().await;
+        
+        let mut result: Vec<SourceSessionStats> = session.sources.iter().map(|(source_id, src)| {
+            SourceSessionStats {
+                source_id: source_id.clone(),
+                log_path: log_sources.get(source_id).cloned(),
+                total_items: src.drops.values().sum(),
+                unique_items: src.drops.len() as i32,
+                total_value: valuation.drops_value(&src.drops, &items_cache, &prices),
+                maps_completed: src.maps_completed,
+            }
+        }).collect();
+        result.sort_by(|a, b| a.source_id.cmp(&b.source_id));
+        result
+    }
+    
     /// Получить агрегированные дропы для отображения
     pub async fn get_aggregated_drops(&self) -> Vec<AggregatedDrop> {
+        let language = self.display_language().await;
+        let valuation = self.valuation().await;
+        let fees = self.fee_model().await;
         let session = self.session.read().await;
         let items_cache = self.items_cache.read().await;
         let prices = self.prices_cache.read().await;
         
-        let mut drops: Vec<AggregatedDrop> = session.drops.iter().map(|(game_id, qty)| {
-            let item_info = items_cache.get(game_id).cloned();
+        // Экипировка раскладывается по тирам, остаток без тира — отдельной строкой
+        // (у поправленного вручную предмета — даже нулевой: к ней относятся правки).
+        // Цена тира — своя, если задана, иначе общая цена предмета
+        let mut lines = crate::valuation::drop_lines(&session.drops, &session.drop_tiers);
+        for game_id in session.drops.keys() {
+            let has_untiered = lines.iter().any(|(id, tier, _)| id == game_id && tier.is_none());
+            if !has_untiered && session.adjustments.iter().any(|a| a.game_id == *game_id) {
+                lines.push((*game_id, None, 0));
+            }
+        }
+
+        let mut drops: Vec<AggregatedDrop> = lines.iter().map(|(game_id, tier, qty)| {
+            let item_info = items_cache.get(game_id).map(|item| item.localized(&language));
             
             // Для базовой валюты цена всегда 1.0 и никогда не устаревает
             let is_base_currency = item_info.as_ref().map(|i| i.is_base_currency).unwrap_or(false);
             
-            let (unit_price, price_updated_at, price_is_stale, is_previous_season, league_name) = if is_base_currency {
-                (1.0, Some(Utc::now()), false, false, None)
+            let (unit_price, price_updated_at, price_is_stale, is_previous_season, league_name, price_confidence) = if is_base_currency {
+                (1.0, Some(Utc::now()), false, false, None, Some(PriceConfidence::certain()))
             } else {
                 match prices.get(game_id) {
                     Some(p) => (
-                        p.price, 
+                        tier.and_then(|t| p.tiers.get(&t).copied()).unwrap_or(p.price),
                         Some(p.updated_at), 
                         Self::is_price_stale_internal(p),
                         !p.is_current_league,  // Если НЕ текущая лига = предыдущий сезон
                         p.league_name.clone(),
+                        Some(price_confidence::evaluate(p, PRICE_TTL_SEC, Utc::now())),
                     ),
-                    None => (0.0, None, false, false, None),
+                    None => (0.0, None, false, false, None, None),
                 }
             };
-            let total_value = unit_price * (*qty as f64);
+            let total_value = valuation.line_value(unit_price, *qty);
+            // Ручные правки относятся к строке без тира
+            let adjustments = session.adjustments.iter().filter(|a| tier.is_none() && a.game_id == *game_id);
+            let adjusted_delta: i32 = adjustments.clone().map(|a| a.new_quantity - a.previous_quantity).sum();
+            let is_adjusted = adjustments.count() > 0;
             
             AggregatedDrop {
                 game_id: *game_id,
+                tier: *tier,
                 item_info,
                 quantity: *qty,
                 total_value,
@@ -817,6 +3322,11 @@ impl AppState {
                 price_is_stale,
                 is_previous_season,
                 league_name,
+                price_confidence,
+                is_adjusted,
+                adjusted_delta,
+                last_dropped_at: session.last_drop_at.get(game_id).copied(),
+                fee_rate: fees.rate(items_cache.get(game_id)),
             }
         }).collect();
         
@@ -826,6 +3336,311 @@ impl AppState {
         drops
     }
     
+    /// Строки дропа для экспорта: локализованные имена, цены по текущему кэшу (по убыванию суммы)
+    pub async fn export_drop_rows(&self, drops: &HashMap<i64, i32>) -> Vec<DropRow> {
+        let language = self.display_language().await;
+        let valuation = self.valuation().await;
+        let fees = self.fee_model().await;
+        let items_cache = self.items_cache.read().await;
+        let prices = self.prices_cache.read().await;
+        let mut rows: Vec<DropRow> = drops
+            .iter()
+            .map(|(game_id, qty)| {
+                let item = items_cache.get(game_id);
+                let unit_price = Valuation::unit_price(*game_id, &items_cache, &prices).unwrap_or(0.0);
+                let total_value = valuation.line_value(unit_price, *qty);
+                DropRow {
+                    name: item
+                        .map(|i| i.localized_name(&language).to_string())
+                        .unwrap_or_else(|| game_id.to_string()),
+                    category: item.map(|i| i.category.clone()).unwrap_or_default(),
+                    quantity: *qty,
+                    unit_price,
+                    total_value,
+                    fee: valuation.round(total_value * fees.rate(item)),
+                    icon_url: item.and_then(|i| i.icon_url.clone()),
+                }
+            })
+            .collect();
+        rows.sort_by(|a, b| b.total_value.partial_cmp(&a.total_value).unwrap_or(std::cmp::Ordering::Equal));
+        rows
+    }
+    
+    /// Страница дропа с сортировкой и фильтром по категории
+    pub async fn get_drops_page(&self, query: &DropsQuery) -> DropsPage {
+        let drops = self.get_aggregated_drops().await;
+        query.apply(drops, &*self.categories.read().await)
+    }
+    
+    /// С какого момента разбирать лог при подключении watcher'а (None — с конца файла).
+    /// Режим Since разовый: после подключения сбрасывается на End, иначе каждый запуск
+    /// перечитывал бы лог с того же момента
+    pub async fn take_log_attach_start(&self) -> Option<DateTime<Utc>> {
+        let since = {
+            let mut settings = self.settings.write().await;
+            match settings.log_attach_mode {
+                LogAttachMode::End => return None,
+                LogAttachMode::Since => {
+                    settings.log_attach_mode = LogAttachMode::End;
+                    settings.log_attach_since.take()
+                }
+            }
+        };
+        self.save_settings_to_disk().await;
+        since
+    }
+
+    /// Прогресс догонялки лога по всем источникам (по source_id)
+    pub fn get_catchup_progress(&self) -> Vec<CatchupProgress> {
+        let mut progress: Vec<CatchupProgress> =
+            self.catchup_progress.lock().unwrap().values().cloned().collect();
+        progress.sort_by(|a, b| a.source_id.cmp(&b.source_id));
+        progress
+    }
+
+    /// Идёт ли сейчас догонялка хотя бы по одному источнику
+    pub fn is_catching_up(&self) -> bool {
+        self.catchup_progress.lock().unwrap().values().any(|p| p.is_catching_up)
+    }
+
+    /// Запомнить свежий снимок инвентаря (заменяет предыдущий, сохраняется на диск)
+    pub async fn set_inventory_snapshot(&self, snapshot: InventorySnapshotEvent) {
+        if let Err(e) = persistence::save_inventory_snapshot(&snapshot) {
+            warn!("Failed to save inventory snapshot: {}", e);
+        }
+        info!("Inventory snapshot: {} slots from {}", snapshot.slots.len(), snapshot.source_id);
+        *self.inventory_snapshot.write().await = Some(snapshot);
+    }
+
+    /// Оценить состояние по последнему снимку инвентаря кэшированными ценами.
+    /// None — снимка ещё не было (игра пишет его при входе в мир).
+    /// `category` — только эта категория или группа категорий (см. CategoryCatalog::matches_filter)
+    pub async fn get_net_worth(&self, category: Option<&str>) -> Option<NetWorth> {
+        let filter = category.filter(|c| !c.is_empty());
+        let cached = self.inventory_snapshot.read().await.clone();
+        let snapshot = match cached {
+            Some(snapshot) => snapshot,
+            None => {
+                let loaded = persistence::load_inventory_snapshot()
+                    .map_err(|e| warn!("Failed to load inventory snapshot: {}", e))
+                    .ok()
+                    .flatten()?;
+                *self.inventory_snapshot.write().await = Some(loaded.clone());
+                loaded
+            }
+        };
+
+        let valuation = self.valuation().await;
+        let language = self.settings.read().await.language.clone();
+        let catalog = self.categories.read().await;
+        let items_cache = self.items_cache.read().await;
+        let prices = self.prices_cache.read().await;
+
+        // Один предмет может лежать в нескольких слотах и на разных страницах
+        let mut quantities: HashMap<i64, i32> = HashMap::new();
+        for slot in &snapshot.slots {
+            *quantities.entry(slot.game_id).or_insert(0) += slot.quantity;
+        }
+
+        let mut by_category: HashMap<String, NetWorthCategory> = HashMap::new();
+        let mut unpriced_count = 0;
+        let mut stale_count = 0;
+        for (game_id, qty) in &quantities {
+            let category = items_cache
+                .get(game_id)
+                .map(|i| i.category.clone())
+                .unwrap_or_else(|| "unknown".to_string());
+            if filter.is_some_and(|f| !catalog.matches_filter(&category, f)) {
+                continue;
+            }
+            let entry = by_category.entry(category.clone()).or_insert_with(|| {
+                let view = catalog.view(&category, &language);
+                NetWorthCategory {
+                    category,
+                    name: view.name,
+                    group: view.group,
+                    value: 0.0,
+                    item_count: 0,
+                    unpriced_count: 0,
+                }
+            });
+            entry.item_count += qty;
+            match Valuation::unit_price(*game_id, &items_cache, &prices) {
+                Some(unit_price) => {
+                    entry.value += valuation.line_value(unit_price, *qty);
+                    if prices.get(game_id).map(Self::is_price_stale_internal).unwrap_or(false) {
+                        stale_count += 1;
+                    }
+                }
+                None => {
+                    entry.unpriced_count += 1;
+                    unpriced_count += 1;
+                }
+            }
+        }
+
+        let mut categories: Vec<NetWorthCategory> = by_category
+            .into_values()
+            .map(|mut c| {
+                c.value = valuation.round(c.value);
+                c
+            })
+            .collect();
+        categories.sort_by(|a, b| b.value.partial_cmp(&a.value).unwrap_or(std::cmp::Ordering::Equal));
+        let total_value = valuation.round(categories.iter().map(|c| c.value).sum());
+
+        Some(NetWorth {
+            snapshot_at: snapshot.timestamp,
+            source_id: snapshot.source_id,
+            total_value,
+            categories,
+            unpriced_count,
+            stale_count,
+        })
+    }
+
+    /// Открытия контейнеров из лога: расход контейнера и прибавки следом. Готовое открытие сохраняется
+    pub async fn track_opening(&self, event: &LogEvent) {
+        let containers = self.settings.read().await.gamble_containers.clone();
+        if containers.is_empty() {
+            return;
+        }
+        let finished = {
+            let mut tracker = self.opening_tracker.lock().unwrap();
+            match event {
+                LogEvent::ItemConsumed(consumed) => tracker.on_consumed(consumed, &containers),
+                LogEvent::ItemDrop(picked) => tracker.on_drop(picked),
+                LogEvent::ItemGained(gained) => tracker.on_gained(gained),
+                _ => tracker.flush_stale(),
+            }
+        };
+        if let Some(opening) = finished {
+            self.store_opening(opening);
+        }
+    }
+
+    /// Сохранить открытие, которое ждёт закрытия окна, если лог после него затих
+    pub fn flush_stale_opening(&self) {
+        let finished = self.opening_tracker.lock().unwrap().flush_stale();
+        if let Some(opening) = finished {
+            self.store_opening(opening);
+        }
+    }
+
+    fn store_opening(&self, opening: GambleOpening) {
+        info!("Container {} opened x{}: {} item types", opening.container_game_id, opening.opened_count, opening.outputs.len());
+        if let Err(e) = persistence::add_gamble_opening(opening) {
+            warn!("Failed to save container opening: {}", e);
+        }
+    }
+
+    /// Профиль для локальных данных (портфель): ID пользователя или "local" без входа
+    pub async fn profile_key(&self) -> String {
+        self.get_auth_user_id().await.unwrap_or_else(|| "local".to_string())
+    }
+
+    /// Переоценить портфель текущими ценами: стоимость, база и нереализованная прибыль партий
+    pub async fn value_portfolio(&self, holdings: Vec<Holding>) -> PortfolioValue {
+        let valuation = self.valuation().await;
+        let language = self.display_language().await;
+        let items_cache = self.items_cache.read().await;
+        let prices = self.prices_cache.read().await;
+
+        let mut unpriced_count = 0;
+        let mut values: Vec<HoldingValue> = holdings
+            .into_iter()
+            .map(|holding| {
+                let current_price = Valuation::unit_price(holding.game_id, &items_cache, &prices);
+                let cost_basis = valuation.line_value(holding.acquired_price, holding.quantity);
+                let current_value = current_price
+                    .map(|price| valuation.line_value(price, holding.quantity))
+                    .unwrap_or(0.0);
+                // Без текущей цены прибыль неизвестна — не считаем её убытком
+                let unrealized_gain = match current_price {
+                    Some(_) => valuation.round(current_value - cost_basis),
+                    None => {
+                        unpriced_count += 1;
+                        0.0
+                    }
+                };
+                HoldingValue {
+                    item_info: items_cache.get(&holding.game_id).map(|i| i.localized(&language)),
+                    holding,
+                    current_price,
+                    current_value,
+                    cost_basis,
+                    unrealized_gain,
+                }
+            })
+            .collect();
+        values.sort_by(|a, b| b.current_value.partial_cmp(&a.current_value).unwrap_or(std::cmp::Ordering::Equal));
+
+        let priced = values.iter().filter(|v| v.current_price.is_some());
+        let total_cost = valuation.round(priced.clone().map(|v| v.cost_basis).sum());
+        let total_value = valuation.round(priced.map(|v| v.current_value).sum());
+        PortfolioValue {
+            holdings: values,
+            total_value,
+            total_cost,
+            unrealized_gain: valuation.round(total_value - total_cost),
+            unpriced_count,
+        }
+    }
+
+    /// Ручная корректировка количества предмета в дропе (например, парсер задвоил стак).
+    /// Количество не уходит ниже нуля; корректировка пишется в аудит сессии.
+    pub async fn adjust_drop_quantity(&self, game_id: i64, delta: i32, reason: String) -> Result<QuantityAdjustment, String> {
+        let mut session = self.session.write().await;
+        if session.started_at.is_none() {
+            return Err("No active session".to_string());
+        }
+        if delta == 0 {
+            return Err("Delta must be non-zero".to_string());
+        }
+        
+        let previous_quantity = session.drops.get(&game_id).copied().unwrap_or(0);
+        let new_quantity = previous_quantity.saturating_add(delta).max(0);
+        if new_quantity == 0 {
+            session.drops.remove(&game_id);
+        } else {
+            session.drops.insert(game_id, new_quantity);
+        }
+        // Части по тирам не могут превышать количество (иначе остаток без тира уйдёт в минус)
+        if let Some(by_tier) = session.drop_tiers.get_mut(&game_id) {
+            crate::valuation::trim_tiers(by_tier, new_quantity);
+            if by_tier.is_empty() {
+                session.drop_tiers.remove(&game_id);
+            }
+        }
+        
+        let adjustment = QuantityAdjustment {
+            game_id,
+            delta,
+            reason,
+            previous_quantity,
+            new_quantity,
+            adjusted_at: Utc::now(),
+        };
+        info!("Adjusted drop quantity: game_id={} {} -> {} ({})",
+            game_id, previous_quantity, new_quantity, adjustment.reason);
+        let event = if new_quantity == 0 {
+            JournalEvent::Remove { game_id }
+        } else {
+            JournalEvent::Adjust { game_id, delta, new_quantity }
+        };
+        self.journal_event(&session, adjustment.adjusted_at, event);
+        session.adjustments.push(adjustment.clone());
+        Self::save_session_internal(&session);
+        self.notify_changed();
+        Ok(adjustment)
+    }
+    
+    /// Аудит ручных корректировок текущей сессии
+    pub async fn get_drop_adjustments(&self) -> Vec<QuantityAdjustment> {
+        let session = self.session.read().await;
+        session.adjustments.clone()
+    }
+    
     /// Проверить, активна ли сессия
     pub async fn is_session_active(&self) -> bool {
         let session = self.session.read().await;
@@ -851,6 +3666,127 @@ impl AppState {
         log_path.clone()
     }
     
+    /// Зарегистрировать запущенный источник логов
+    pub async fn register_log_source(&self, source_id: &str, path: &str) {
+        let mut sources = self.log_sources.write().await;
+        sources.insert(source_id.to_string(), path.to_string());
+    }
+    
+    /// Зарегистрировать парсер дополнительного клиента (чтобы перезагрузка паттернов дошла и до него)
+    pub async fn register_source_parser(&self, source_id: &str, parser: Arc<Mutex<LogParser>>) {
+        // Держим карту парсеров на запись, пока применяем набор: иначе параллельный
+        // apply_log_patterns может пропустить этот парсер
+        let mut parsers = self.source_parsers.write().await;
+        match self.log_patterns.read().await.compile() {
+            Ok(compiled) => {
+                if let Ok(mut p) = parser.lock() {
+                    p.set_patterns(compiled);
+                }
+            }
+            Err(e) => warn!("Active log patterns failed to compile: {}", e),
+        }
+        {
+            let s = self.settings.read().await;
+            if let Ok(mut p) = parser.lock() {
+                p.set_time_zone(s.log_time_zone, s.log_utc_offset_minutes);
+            }
+        }
+        parsers.insert(source_id.to_string(), parser);
+    }
+
+    /// Применить набор паттернов ко всем парсерам
+    pub async fn apply_log_patterns(&self, patterns: LogPatterns) -> Result<(), String> {
+        let compiled = patterns.compile()?;
+        let revision = patterns.revision;
+        *self.log_patterns.write().await = patterns;
+
+        let mut parsers = vec![self.log_parser.clone()];
+        parsers.extend(self.source_parsers.read().await.values().cloned());
+        for parser in parsers {
+            parser.lock().map_err(|e| e.to_string())?.set_patterns(compiled.clone());
+        }
+        info!("Applied log patterns r{}", revision);
+        Ok(())
+    }
+
+    /// Клиент игры: из настроек, а в режиме Auto — по пути к основному логу
+    pub async fn game_client(&self) -> GameClient {
+        let setting = self.settings.read().await.game_client;
+        let log_path = self.get_log_path().await;
+        crate::file_watcher::resolve_game_client(setting, log_path.as_deref().map(std::path::Path::new))
+    }
+
+    /// Override с диска (или встроенный набор) для текущего клиента — без сети
+    pub async fn apply_local_log_patterns(&self) -> Result<(), String> {
+        let client = self.game_client().await;
+        let override_patterns = persistence::load_log_patterns().unwrap_or_else(|e| {
+            warn!("Failed to load log patterns override: {}", e);
+            None
+        });
+        let (patterns, _) = crate::log_patterns::select(override_patterns, client);
+        self.apply_log_patterns(patterns).await
+    }
+
+    /// Перечитать паттерны лога: локальный override + набор из Supabase.
+    /// Набор выбирается под клиент игры (глобальный/китайский).
+    /// Более свежий удалённый набор кэшируется на диск и работает дальше офлайн
+    pub async fn reload_log_patterns(&self) -> Result<PatternsReloadResult, String> {
+        let client = self.game_client().await;
+        let mut override_patterns = persistence::load_log_patterns().unwrap_or_else(|e| {
+            warn!("Failed to load log patterns override: {}", e);
+            None
+        });
+
+        let mut remote_revision = None;
+        let mut remote_error = None;
+        if let Some(api) = self.supabase_client().await {
+            match crate::supabase_sync::fetch_log_patterns(&api, client).await {
+                Ok(Some(remote)) => {
+                    remote_revision = Some(remote.revision);
+                    let is_newer = override_patterns
+                        .as_ref()
+                        .is_none_or(|p| p.client != client || remote.revision > p.revision);
+                    if is_newer {
+                        match remote.compile() {
+                            Ok(_) => {
+                                if let Err(e) = persistence::save_log_patterns(&remote) {
+                                    warn!("Failed to cache log patterns r{}: {}", remote.revision, e);
+                                }
+                                override_patterns = Some(remote);
+                            }
+                            Err(e) => remote_error = Some(e),
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => remote_error = Some(e.to_string()),
+            }
+        }
+        if let Some(e) = &remote_error {
+            warn!("Remote log patterns unavailable: {}", e);
+        }
+
+        let (patterns, source) = crate::log_patterns::select(override_patterns, client);
+        let revision = patterns.revision;
+        self.apply_log_patterns(patterns).await?;
+        Ok(PatternsReloadResult { revision, client, source, remote_revision, remote_error })
+    }
+
+    /// Дополнительные лог-файлы (мультибокс) из настроек
+    pub async fn get_extra_log_paths(&self) -> Vec<String> {
+        let settings = self.settings.read().await;
+        settings.extra_log_paths.clone()
+    }
+    
+    /// Сохранить дополнительные лог-файлы (применяются при следующем запуске)
+    pub async fn set_extra_log_paths(&self, paths: Vec<String>) {
+        let mut settings = self.settings.write().await;
+        settings.extra_log_paths = paths;
+        if let Err(e) = persistence::save_settings(&settings) {
+            log::warn!("Failed to save settings with extra log paths: {}", e);
+        }
+    }
+    
     /// Получить custom_log_path из настроек
     pub async fn get_custom_log_path(&self) -> Option<String> {
         let settings = self.settings.read().await;
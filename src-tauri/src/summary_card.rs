@@ -0,0 +1,254 @@
+//! PNG-карточка сессии для соцсетей
+//!
+//! Рисуем прямо в Rust (image + imageproc): заголовок, четыре плитки со статистикой
+//! и топ дропа с иконками. Шрифт берём системный (Segoe UI / Microsoft YaHei для
+//! китайского), иконки — по icon_url предметов; не загрузилась иконка — рисуем
+//! пустую плашку, карточка от этого не ломается.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ab_glyph::{FontVec, PxScale};
+use image::{imageops::FilterType, Rgba, RgbaImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_text_mut, text_size};
+use imageproc::rect::Rect;
+use log::debug;
+
+use crate::session_summary::{duration, label, SessionSummary, SUMMARY_TOP_DROPS};
+use crate::value_format::fe;
+
+const WIDTH: u32 = 720;
+const PADDING: i32 = 28;
+const HEADER_HEIGHT: i32 = 86;
+const TILE_HEIGHT: i32 = 78;
+const ROW_HEIGHT: i32 = 50;
+const ICON_SIZE: u32 = 40;
+const ICON_TIMEOUT_SEC: u64 = 5;
+
+const BACKGROUND: Rgba<u8> = Rgba([21, 19, 31, 255]);
+const TILE: Rgba<u8> = Rgba([33, 30, 48, 255]);
+const ACCENT: Rgba<u8> = Rgba([124, 92, 255, 255]);
+const TEXT: Rgba<u8> = Rgba([236, 234, 245, 255]);
+const MUTED: Rgba<u8> = Rgba([150, 146, 170, 255]);
+const POSITIVE: Rgba<u8> = Rgba([74, 222, 128, 255]);
+const NEGATIVE: Rgba<u8> = Rgba([248, 113, 113, 255]);
+
+/// Обычное и жирное начертание
+pub struct CardFonts {
+    regular: FontVec,
+    bold: FontVec,
+}
+
+/// Кандидаты шрифтов: (обычный, жирный). Первый найденный на диске побеждает
+fn font_candidates(language: &str) -> Vec<(PathBuf, PathBuf)> {
+    let mut candidates = Vec::new();
+    if let Some(windir) = std::env::var_os("WINDIR").map(PathBuf::from) {
+        let fonts = windir.join("Fonts");
+        if language == "zh" {
+            candidates.push((fonts.join("msyh.ttc"), fonts.join("msyhbd.ttc")));
+        }
+        candidates.push((fonts.join("segoeui.ttf"), fonts.join("segoeuib.ttf")));
+        candidates.push((fonts.join("arial.ttf"), fonts.join("arialbd.ttf")));
+    }
+    let dejavu = PathBuf::from("/usr/share/fonts/truetype/dejavu");
+    candidates.push((dejavu.join("DejaVuSans.ttf"), dejavu.join("DejaVuSans-Bold.ttf")));
+    candidates
+}
+
+fn load_font(path: &Path) -> Option<FontVec> {
+    let data = std::fs::read(path).ok()?;
+    // .ttc — коллекция, берём первое начертание
+    FontVec::try_from_vec_and_index(data, 0).ok()
+}
+
+/// Загрузить системные шрифты для языка карточки
+pub fn load_fonts(language: &str) -> Result<CardFonts, String> {
+    font_candidates(language)
+        .into_iter()
+        .find_map(|(regular_path, bold_path)| {
+            let regular = load_font(&regular_path)?;
+            // Нет жирного — используем обычный
+            let bold = load_font(&bold_path).or_else(|| load_font(&regular_path))?;
+            Some(CardFonts { regular, bold })
+        })
+        .ok_or_else(|| "No usable system font found for the summary card".to_string())
+}
+
+/// Скачать иконки предметов (по порядку топа), уменьшенные до размера строки
+pub async fn fetch_icons(http: &reqwest::Client, urls: &[Option<String>]) -> Vec<Option<RgbaImage>> {
+    let mut icons = Vec::with_capacity(urls.len());
+    for url in urls {
+        let icon = match url {
+            Some(url) => fetch_icon(http, url).await,
+            None => None,
+        };
+        icons.push(icon);
+    }
+    icons
+}
+
+async fn fetch_icon(http: &reqwest::Client, url: &str) -> Option<RgbaImage> {
+    let resp = http
+        .get(url)
+        .timeout(Duration::from_secs(ICON_TIMEOUT_SEC))
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?;
+    let bytes = resp.bytes().await.ok()?;
+    match image::load_from_memory(&bytes) {
+        Ok(img) => Some(img.resize_exact(ICON_SIZE, ICON_SIZE, FilterType::Triangle).to_rgba8()),
+        Err(e) => {
+            debug!("Icon {} could not be decoded: {}", url, e);
+            None
+        }
+    }
+}
+
+/// Обрезать текст с многоточием, чтобы он влез в max_width пикселей
+fn fit(text: &str, scale: PxScale, font: &FontVec, max_width: u32) -> String {
+    if text_size(scale, font, text).0 <= max_width {
+        return text.to_string();
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let candidate = format!("{}…", chars.iter().collect::<String>().trim_end());
+        if text_size(scale, font, &candidate).0 <= max_width {
+            return candidate;
+        }
+    }
+    "…".to_string()
+}
+
+fn draw_right(canvas: &mut RgbaImage, color: Rgba<u8>, right: i32, y: i32, scale: PxScale, font: &FontVec, text: &str) {
+    let width = text_size(scale, font, text).0 as i32;
+    draw_text_mut(canvas, color, right - width, y, scale, font, text);
+}
+
+/// Плитки: время, карты, чистый профит и профит в час (как в остальном UI)
+fn tiles(summary: &SessionSummary, language: &str) -> [(&'static str, String, Rgba<u8>); 4] {
+    let profit = summary.profit();
+    [
+        (label(language, "Время", "Time"), duration(language, summary.duration_sec), TEXT),
+        (label(language, "Карт", "Maps"), summary.maps_completed.to_string(), TEXT),
+        (
+            label(language, "Профит, FE", "Profit, FE"),
+            format!("{}{}", if profit >= 0.0 { "+" } else { "" }, fe(profit)),
+            if profit >= 0.0 { POSITIVE } else { NEGATIVE },
+        ),
+        (label(language, "Профит в час, FE", "Profit/h, FE"), fe(summary.hourly_profit), TEXT),
+    ]
+}
+
+/// Нарисовать карточку. icons — по одной на позицию топа (None — плашка без иконки)
+pub fn render(summary: &SessionSummary, icons: &[Option<RgbaImage>], language: &str, fonts: &CardFonts) -> RgbaImage {
+    let drops: Vec<_> = summary.top_drops.iter().take(SUMMARY_TOP_DROPS).collect();
+    let drops_height = if drops.is_empty() { 0 } else { 44 + drops.len() as i32 * ROW_HEIGHT };
+    let height = (HEADER_HEIGHT + TILE_HEIGHT + 24 + drops_height + PADDING) as u32;
+    let mut canvas = RgbaImage::from_pixel(WIDTH, height, BACKGROUND);
+    let right = WIDTH as i32 - PADDING;
+
+    // Заголовок с акцентной полосой
+    draw_filled_rect_mut(&mut canvas, Rect::at(0, 0).of_size(WIDTH, 6), ACCENT);
+    draw_text_mut(&mut canvas, TEXT, PADDING, 24, PxScale::from(30.0), &fonts.bold, "TLI Companion");
+    draw_right(
+        &mut canvas, MUTED, right, 34, PxScale::from(18.0), &fonts.regular,
+        label(language, "Сессия фарма", "Farm session"),
+    );
+
+    let tiles = tiles(summary, language);
+    let gap = 12;
+    let tile_width = (WIDTH as i32 - PADDING * 2 - gap * 3) / 4;
+    for (i, (title, value, color)) in tiles.iter().enumerate() {
+        let x = PADDING + i as i32 * (tile_width + gap);
+        draw_filled_rect_mut(&mut canvas, Rect::at(x, HEADER_HEIGHT).of_size(tile_width as u32, TILE_HEIGHT as u32), TILE);
+        draw_text_mut(&mut canvas, MUTED, x + 14, HEADER_HEIGHT + 12, PxScale::from(15.0), &fonts.regular, title);
+        let value = fit(value, PxScale::from(26.0), &fonts.bold, tile_width as u32 - 28);
+        draw_text_mut(&mut canvas, *color, x + 14, HEADER_HEIGHT + 36, PxScale::from(26.0), &fonts.bold, &value);
+    }
+
+    // Топ дропа
+    if !drops.is_empty() {
+        let mut y = HEADER_HEIGHT + TILE_HEIGHT + 24;
+        draw_text_mut(&mut canvas, MUTED, PADDING, y, PxScale::from(17.0), &fonts.bold, label(language, "Топ дропа", "Top drops"));
+        y += 44;
+        let name_scale = PxScale::from(19.0);
+        for (i, drop) in drops.iter().enumerate() {
+            let icon_y = y + (ROW_HEIGHT - ICON_SIZE as i32) / 2 - 6;
+            match icons.get(i).and_then(|icon| icon.as_ref()) {
+                Some(icon) => image::imageops::overlay(&mut canvas, icon, PADDING as i64, icon_y as i64),
+                None => draw_filled_rect_mut(&mut canvas, Rect::at(PADDING, icon_y).of_size(ICON_SIZE, ICON_SIZE), TILE),
+            }
+
+            let value = format!("{} FE", fe(drop.total_value));
+            let value_width = text_size(name_scale, &fonts.bold, &value).0 as i32;
+            draw_right(&mut canvas, TEXT, right, y + 4, name_scale, &fonts.bold, &value);
+
+            let name_x = PADDING + ICON_SIZE as i32 + 14;
+            let quantity = format!(" ×{}", drop.quantity);
+            let quantity_width = text_size(name_scale, &fonts.regular, &quantity).0 as i32;
+            let name_max = (right - value_width - 20 - name_x - quantity_width).max(40) as u32;
+            let name = fit(&drop.name, name_scale, &fonts.regular, name_max);
+            draw_text_mut(&mut canvas, TEXT, name_x, y + 4, name_scale, &fonts.regular, &name);
+            let name_width = text_size(name_scale, &fonts.regular, &name).0 as i32;
+            draw_text_mut(&mut canvas, MUTED, name_x + name_width, y + 4, name_scale, &fonts.regular, &quantity);
+            y += ROW_HEIGHT;
+        }
+    }
+
+    canvas
+}
+
+/// Куда сохранять карточки: «Изображения/TLI Companion», иначе папка данных приложения
+pub fn cards_dir() -> Option<PathBuf> {
+    dirs::picture_dir()
+        .map(|d| d.join("TLI Companion"))
+        .or_else(|| crate::persistence::app_data_dir().map(|d| d.join("cards")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_summary::SummaryDrop;
+
+    fn summary(top: usize) -> SessionSummary {
+        SessionSummary {
+            duration_sec: 1800,
+            maps_completed: 6,
+            income: 1200.0,
+            expenses: 100.0,
+            fees: 100.0,
+            hourly_profit: 2000.0,
+            top_drops: (0..top)
+                .map(|i| SummaryDrop { name: format!("Item {}", i), quantity: 1, total_value: 100.0, icon_url: None })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn tiles_show_net_profit_per_hour() {
+        let winning = tiles(&summary(0), "en");
+        assert_eq!(winning[2].1, format!("+{}", fe(1000.0)));
+        assert_eq!(winning[2].2, POSITIVE);
+        assert_eq!(winning[3].0, "Profit/h, FE");
+        assert_eq!(winning[3].1, fe(2000.0));
+
+        let losing = SessionSummary { expenses: 2000.0, ..summary(0) };
+        assert_eq!(tiles(&losing, "en")[2].2, NEGATIVE);
+    }
+
+    #[test]
+    fn card_height_grows_with_top_drops() {
+        // Без системного шрифта (CI без DejaVu/Windows) рисовать нечем
+        let Ok(fonts) = load_fonts("en") else {
+            return;
+        };
+        let empty = render(&summary(0), &[], "en", &fonts);
+        let full = render(&summary(3), &[None, None, None], "en", &fonts);
+        assert_eq!(empty.width(), WIDTH);
+        assert_eq!(full.height() - empty.height(), (44 + 3 * ROW_HEIGHT) as u32);
+        assert_eq!(*empty.get_pixel(WIDTH / 2, 2), ACCENT);
+    }
+}
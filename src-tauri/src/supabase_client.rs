@@ -0,0 +1,492 @@
+//! Shared Supabase REST client
+//!
+//! One place for timeouts, retries and error classification, so `supabase_sync`
+//! functions only describe endpoints and payloads.
+//!
+//! - Timeouts: connect 5s, whole request 20s.
+//! - Retries: network errors, 429 and 5xx, up to 3 attempts with exponential backoff
+//!   (429 honours `Retry-After`). Non-idempotent inserts are retried only when the
+//!   request surely did not reach the server (connect error), so a session is never
+//!   uploaded twice.
+//! - Errors: `ApiError` categories; the string form (`"[network] ..."`) is what commands
+//!   return, so the frontend can tell "offline" from "sign in again".
+//! - Circuit breaker: shared by all clients built from `AppState`. After
+//!   `BREAKER_THRESHOLD` outage-type failures in a row (network, 5xx) calls fail
+//!   immediately for `BREAKER_COOLDOWN`, then one probe call decides whether to close it.
+//!   Status is reported by `get_connectivity_status`.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::supabase_sync::SupabaseConfig;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+const MAX_ATTEMPTS: u32 = 3;
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+/// Don't wait longer than this even if the server asks to
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10);
+/// Consecutive outage-type failures that open the circuit
+const BREAKER_THRESHOLD: u32 = 5;
+/// How long an open circuit short-circuits calls before a probe is allowed
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Error category of a Supabase call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum ApiError {
+    /// 401/403: token missing, expired or not allowed by RLS
+    Auth(String),
+    /// 429 after all retries
+    RateLimited(String),
+    /// Connection failed / timed out after all retries
+    Network(String),
+    /// Response doesn't match the expected shape (client/server version mismatch)
+    Schema(String),
+    /// 5xx after all retries
+    Server(String),
+    /// Other 4xx: the request itself is wrong
+    Request(String),
+}
+
+impl ApiError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ApiError::Auth(_) => "auth",
+            ApiError::RateLimited(_) => "rate_limited",
+            ApiError::Network(_) => "network",
+            ApiError::Schema(_) => "schema",
+            ApiError::Server(_) => "server",
+            ApiError::Request(_) => "request",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::Auth(m)
+            | ApiError::RateLimited(m)
+            | ApiError::Network(m)
+            | ApiError::Schema(m)
+            | ApiError::Server(m)
+            | ApiError::Request(m) => m,
+        }
+    }
+
+    /// Worth retrying later (background tasks use this to decide on backoff)
+    pub fn is_transient(&self) -> bool {
+        matches!(self, ApiError::RateLimited(_) | ApiError::Network(_) | ApiError::Server(_))
+    }
+
+    /// Looks like Supabase itself is down (counts towards the circuit breaker)
+    fn is_outage(&self) -> bool {
+        matches!(self, ApiError::Network(_) | ApiError::Server(_))
+    }
+
+    fn from_status(context: &str, status: StatusCode, body: &str) -> Self {
+        let msg = format!("{} failed: {} {}", context, status, body);
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ApiError::Auth(msg),
+            StatusCode::TOO_MANY_REQUESTS => ApiError::RateLimited(msg),
+            s if s.is_server_error() => ApiError::Server(msg),
+            _ => ApiError::Request(msg),
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.kind(), self.message())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<ApiError> for String {
+    fn from(e: ApiError) -> Self {
+        e.to_string()
+    }
+}
+
+/// Circuit state as seen by the UI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Calls go through
+    Closed,
+    /// Calls fail immediately until the cooldown ends
+    Open,
+    /// Cooldown is over, one probe call is in flight or allowed
+    HalfOpen,
+}
+
+/// Supabase connectivity for `get_connectivity_status`
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectivityStatus {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub opened_at: Option<DateTime<Utc>>,
+    /// When the next probe is allowed (open circuit only)
+    pub retry_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Calls rejected without touching the network since the app started
+    pub short_circuited: u64,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+    opened_at: Option<DateTime<Utc>>,
+    /// A half-open probe went out and its outcome is not recorded yet
+    probing: bool,
+    last_error: Option<String>,
+    short_circuited: u64,
+}
+
+/// Circuit breaker shared by every Supabase call
+#[derive(Debug, Default)]
+pub struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    /// May a call go out now? Err(remaining cooldown) when the circuit is open.
+    /// After the cooldown one probe is let through; the others keep failing fast until
+    /// it succeeds (or for another cooldown, so a cancelled probe can't wedge the circuit).
+    fn check(&self, now: Instant) -> Result<(), Duration> {
+        let mut s = self.state.lock().unwrap();
+        let Some(open_until) = s.open_until else {
+            return Ok(());
+        };
+        if now < open_until {
+            s.short_circuited += 1;
+            return Err(open_until - now);
+        }
+        s.open_until = Some(now + BREAKER_COOLDOWN);
+        s.probing = true;
+        Ok(())
+    }
+
+    fn record_success(&self) {
+        let mut s = self.state.lock().unwrap();
+        if s.open_until.is_some() {
+            log::info!("Supabase is reachable again, circuit closed");
+        }
+        s.failures = 0;
+        s.open_until = None;
+        s.opened_at = None;
+        s.probing = false;
+    }
+
+    /// Forget past failures (the backend endpoint changed)
+    pub fn reset(&self) {
+        let mut s = self.state.lock().unwrap();
+        let short_circuited = s.short_circuited;
+        *s = BreakerState { short_circuited, ..Default::default() };
+    }
+
+    fn record_failure(&self, error: &ApiError, now: Instant) {
+        let mut s = self.state.lock().unwrap();
+        s.probing = false;
+        if !error.is_outage() {
+            // The server answered: it is up, the request itself was rejected
+            s.failures = 0;
+            s.open_until = None;
+            s.opened_at = None;
+            return;
+        }
+        s.failures += 1;
+        s.last_error = Some(error.to_string());
+        if s.failures >= BREAKER_THRESHOLD {
+            if s.open_until.is_none() {
+                log::warn!("Supabase looks down ({} failures in a row), pausing calls for {:?}", s.failures, BREAKER_COOLDOWN);
+                s.opened_at = Some(Utc::now());
+            }
+            s.open_until = Some(now + BREAKER_COOLDOWN);
+        }
+    }
+
+    pub fn status(&self) -> ConnectivityStatus {
+        let s = self.state.lock().unwrap();
+        let now = Instant::now();
+        let state = match s.open_until {
+            None => CircuitState::Closed,
+            Some(until) if now < until && !s.probing => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        };
+        ConnectivityStatus {
+            state,
+            consecutive_failures: s.failures,
+            opened_at: s.opened_at,
+            retry_at: s
+                .open_until
+                .filter(|until| now < *until)
+                .and_then(|until| chrono::Duration::from_std(until - now).ok())
+                .map(|left| Utc::now() + left),
+            last_error: s.last_error.clone(),
+            short_circuited: s.short_circuited,
+        }
+    }
+}
+
+/// Supabase REST client: base URL + anon key + a shared HTTP client with timeouts
+#[derive(Debug, Clone)]
+pub struct SupabaseClient {
+    http: reqwest::Client,
+    cfg: SupabaseConfig,
+    breaker: Arc<CircuitBreaker>,
+}
+
+/// Builder with the standard timeouts (proxy settings are added on top, see `proxy`)
+pub fn http_client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+}
+
+/// HTTP client with the standard timeouts (shared by all Supabase calls)
+pub fn build_http_client() -> reqwest::Client {
+    http_client_builder()
+        .build()
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to build HTTP client with timeouts, using defaults: {}", e);
+            reqwest::Client::new()
+        })
+}
+
+impl SupabaseClient {
+    pub fn new(http: reqwest::Client, cfg: SupabaseConfig, breaker: Arc<CircuitBreaker>) -> Self {
+        Self { http, cfg, breaker }
+    }
+
+    pub fn config(&self) -> &SupabaseConfig {
+        &self.cfg
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.cfg.url.trim_end_matches('/'), self.cfg.resolve_path(path))
+    }
+
+    fn request(&self, method: &Method, path: &str, jwt: Option<&str>) -> RequestBuilder {
+        let bearer = jwt.unwrap_or(&self.cfg.anon_key);
+        self.http
+            .request(method.clone(), self.url(path))
+            .header("apikey", &self.cfg.anon_key)
+            .header("Authorization", format!("Bearer {}", bearer))
+    }
+
+    /// Send through the circuit breaker: an open circuit fails the call immediately
+    async fn send(
+        &self,
+        context: &str,
+        method: Method,
+        path: &str,
+        jwt: Option<&str>,
+        body: Option<(&serde_json::Value, Option<&str>)>,
+        idempotent: bool,
+    ) -> Result<Response, ApiError> {
+        if let Err(left) = self.breaker.check(Instant::now()) {
+            return Err(ApiError::Network(format!(
+                "{} skipped: Supabase unavailable, next try in {}s",
+                context,
+                left.as_secs()
+            )));
+        }
+        let result = self.send_with_retries(context, method, path, jwt, body, idempotent).await;
+        match &result {
+            Ok(_) => self.breaker.record_success(),
+            Err(e) => self.breaker.record_failure(e, Instant::now()),
+        }
+        result
+    }
+
+    /// Send with retries. `context` names the operation in error messages,
+    /// `body` is the JSON payload with an optional `Prefer` header.
+    /// `idempotent` = safe to repeat after the server may have processed the request.
+    async fn send_with_retries(
+        &self,
+        context: &str,
+        method: Method,
+        path: &str,
+        jwt: Option<&str>,
+        body: Option<(&serde_json::Value, Option<&str>)>,
+        idempotent: bool,
+    ) -> Result<Response, ApiError> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let mut req = self.request(&method, path, jwt);
+            if let Some((body, prefer)) = body {
+                req = req.json(body);
+                if let Some(prefer) = prefer {
+                    req = req.header("Prefer", prefer);
+                }
+            }
+
+            let last_attempt = attempt >= MAX_ATTEMPTS;
+            let backoff = BACKOFF_BASE * 2u32.pow(attempt - 1);
+
+            match req.send().await {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp) => {
+                    let status = resp.status();
+                    let retry_after = resp
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .map(|s| Duration::from_secs(s).min(MAX_RETRY_AFTER));
+                    let text = resp.text().await.unwrap_or_default();
+                    let error = ApiError::from_status(context, status, &text);
+
+                    let retryable = match &error {
+                        ApiError::RateLimited(_) => true,
+                        ApiError::Server(_) => idempotent,
+                        _ => false,
+                    };
+                    if !retryable || last_attempt {
+                        return Err(error);
+                    }
+                    let wait = retry_after.unwrap_or(backoff);
+                    log::debug!("{}: {} (attempt {}/{}), retrying in {:?}", context, status, attempt, MAX_ATTEMPTS, wait);
+                    tokio::time::sleep(wait).await;
+                }
+                Err(e) => {
+                    let error = ApiError::Network(format!("{} failed: {}", context, e));
+                    // Connect errors never reached the server; timeouts might have
+                    let retryable = e.is_connect() || (idempotent && (e.is_timeout() || e.is_request()));
+                    if !retryable || last_attempt {
+                        return Err(error);
+                    }
+                    log::debug!("{}: {} (attempt {}/{}), retrying in {:?}", context, e, attempt, MAX_ATTEMPTS, backoff);
+                    tokio::time::sleep(backoff).await;
+                }
+            }
+        }
+    }
+
+    async fn decode<T: DeserializeOwned>(context: &str, resp: Response) -> Result<T, ApiError> {
+        let text = resp
+            .text()
+            .await
+            .map_err(|e| ApiError::Network(format!("{} failed: {}", context, e)))?;
+        serde_json::from_str(&text).map_err(|e| ApiError::Schema(format!("{}: unexpected response: {}", context, e)))
+    }
+
+    /// GET and decode JSON. `jwt` = None uses the anon key (public reads).
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        context: &str,
+        path: &str,
+        jwt: Option<&str>,
+    ) -> Result<T, ApiError> {
+        let resp = self.send(context, Method::GET, path, jwt, None, true).await?;
+        Self::decode(context, resp).await
+    }
+
+    /// POST and decode JSON
+    pub async fn post_json<T: DeserializeOwned>(
+        &self,
+        context: &str,
+        path: &str,
+        jwt: Option<&str>,
+        body: &serde_json::Value,
+        prefer: Option<&str>,
+        idempotent: bool,
+    ) -> Result<T, ApiError> {
+        let resp = self.send(context, Method::POST, path, jwt, Some((body, prefer)), idempotent).await?;
+        Self::decode(context, resp).await
+    }
+
+    /// POST ignoring the response body
+    pub async fn post(
+        &self,
+        context: &str,
+        path: &str,
+        jwt: Option<&str>,
+        body: &serde_json::Value,
+        prefer: Option<&str>,
+        idempotent: bool,
+    ) -> Result<(), ApiError> {
+        self.send(context, Method::POST, path, jwt, Some((body, prefer)), idempotent).await?;
+        Ok(())
+    }
+
+    /// PATCH ignoring the response body (full-value updates are safe to repeat)
+    pub async fn patch(
+        &self,
+        context: &str,
+        path: &str,
+        jwt: Option<&str>,
+        body: &serde_json::Value,
+    ) -> Result<(), ApiError> {
+        self.send(context, Method::PATCH, path, jwt, Some((body, Some("return=minimal"))), true).await?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, context: &str, path: &str, jwt: Option<&str>) -> Result<(), ApiError> {
+        self.send(context, Method::DELETE, path, jwt, None, true).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_classification() {
+        let e = |s: u16| ApiError::from_status("op", StatusCode::from_u16(s).unwrap(), "");
+        assert_eq!(e(401).kind(), "auth");
+        assert_eq!(e(403).kind(), "auth");
+        assert_eq!(e(429).kind(), "rate_limited");
+        assert_eq!(e(503).kind(), "server");
+        assert_eq!(e(400).kind(), "request");
+        assert!(e(502).is_transient());
+        assert!(!e(401).is_transient());
+        assert!(String::from(e(429)).starts_with("[rate_limited] op failed"));
+    }
+
+    #[test]
+    fn breaker_opens_after_outages_and_probes_once() {
+        let breaker = CircuitBreaker::default();
+        let t0 = Instant::now();
+        let down = ApiError::Network("op failed: timeout".into());
+        for _ in 0..BREAKER_THRESHOLD {
+            assert!(breaker.check(t0).is_ok());
+            breaker.record_failure(&down, t0);
+        }
+        assert!(breaker.check(t0).is_err());
+        assert_eq!(breaker.status().state, CircuitState::Open);
+
+        // After the cooldown one probe goes out, the rest wait for its outcome
+        let later = t0 + BREAKER_COOLDOWN;
+        assert!(breaker.check(later).is_ok());
+        assert!(breaker.check(later).is_err());
+        breaker.record_success();
+        assert!(breaker.check(later).is_ok());
+        assert_eq!(breaker.status().short_circuited, 2);
+    }
+
+    #[test]
+    fn paths_follow_configured_prefixes() {
+        let endpoint = crate::types::BackendEndpoint {
+            enabled: true,
+            url: "https://prices.example.org/".into(),
+            anon_key: "key".into(),
+            table_prefix: "community_".into(),
+            rpc_prefix: "tli_".into(),
+        };
+        let cfg = SupabaseConfig::validate_endpoint(&endpoint).unwrap();
+        assert_eq!(cfg.url, "https://prices.example.org");
+        assert_eq!(cfg.resolve_path("rest/v1/tli_current_prices?select=game_id"), "rest/v1/community_current_prices?select=game_id");
+        assert_eq!(cfg.resolve_path("rest/v1/rpc/upsert_market_price"), "rest/v1/rpc/tli_upsert_market_price");
+        assert_eq!(cfg.resolve_path("/rest/v1/profiles?id=eq.1"), "rest/v1/profiles?id=eq.1");
+        assert!(SupabaseConfig::validate_endpoint(&crate::types::BackendEndpoint { table_prefix: "x;".into(), ..endpoint }).is_err());
+    }
+}
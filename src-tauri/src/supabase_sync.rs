@@ -3,22 +3,42 @@
 //! - Public read: fetch current prices from tli_current_prices (anon)
 //! - Optional write: send raw samples to RPC upsert_market_price (requires user JWT)
 //! - Session sync: upload farm sessions to tli_farm_sessions (requires user JWT)
+//! - Session sharing: anonymized snapshots in tli_shared_sessions (requires user JWT)
+//! - Leaderboard: public read of tli_leaderboard_current, opt-in submit (requires user JWT)
+//! - Log patterns: public read of tli_log_patterns (parser regex overrides)
+//! - Categories: public read of tli_item_categories (names, order, parent groups)
+//!
+//! HTTP details (timeouts, retries, error categories) live in `supabase_client`.
 //!
 //! Config via env:
 //! - VITE_SUPABASE_URL
 //! - VITE_SUPABASE_ANON_KEY
+//!
+//! or a self-hosted backend from settings (`AppSettings.backend`). Paths below use the
+//! built-in names (`tli_*` tables, unprefixed RPCs); `SupabaseConfig::resolve_path`
+//! maps them to the configured prefixes.
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use crate::supabase_defaults;
-use crate::types::{ItemInfo, FarmSessionState};
+use crate::log_patterns::LogPatterns;
+use crate::types::{ItemInfo, GameClient};
+use crate::persistence::SessionHistoryRecord;
+use crate::supabase_client::{ApiError, SupabaseClient};
 
 #[derive(Debug, Clone)]
 pub struct SupabaseConfig {
     pub url: String,
     pub anon_key: String,
+    /// Table prefix replacing the built-in `tli_`
+    pub table_prefix: String,
+    /// Prefix prepended to RPC function names
+    pub rpc_prefix: String,
 }
 
+/// Built-in table prefix (the names used in request paths)
+const BUILTIN_TABLE_PREFIX: &str = "tli_";
+
 impl SupabaseConfig {
     pub fn from_env_or_compile() -> Option<Self> {
         let url = std::env::var("VITE_SUPABASE_URL")
@@ -29,7 +49,53 @@ impl SupabaseConfig {
             .ok()
             .or_else(|| option_env!("VITE_SUPABASE_ANON_KEY").map(|s| s.to_string()))
             .unwrap_or_else(|| supabase_defaults::SUPABASE_ANON_KEY.to_string());
-        Some(Self { url, anon_key })
+        Some(Self {
+            url,
+            anon_key,
+            table_prefix: BUILTIN_TABLE_PREFIX.to_string(),
+            rpc_prefix: String::new(),
+        })
+    }
+
+    /// Self-hosted backend from settings (None when disabled or incomplete)
+    pub fn from_endpoint(endpoint: &crate::types::BackendEndpoint) -> Option<Self> {
+        let url = endpoint.url.trim().trim_end_matches('/');
+        let anon_key = endpoint.anon_key.trim();
+        if !endpoint.enabled || url.is_empty() || anon_key.is_empty() {
+            return None;
+        }
+        Some(Self {
+            url: url.to_string(),
+            anon_key: anon_key.to_string(),
+            table_prefix: endpoint.table_prefix.trim().to_string(),
+            rpc_prefix: endpoint.rpc_prefix.trim().to_string(),
+        })
+    }
+
+    /// Check a self-hosted endpoint before it is saved
+    pub fn validate_endpoint(endpoint: &crate::types::BackendEndpoint) -> Result<Self, String> {
+        let cfg = Self::from_endpoint(endpoint).ok_or("Backend URL and anon key are required")?;
+        let parsed = reqwest::Url::parse(&cfg.url).map_err(|e| format!("Invalid backend URL: {}", e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("Unsupported backend URL scheme: {}", parsed.scheme()));
+        }
+        let valid_name = |s: &str| s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name(&cfg.table_prefix) || !valid_name(&cfg.rpc_prefix) {
+            return Err("Table and RPC prefixes may only contain letters, digits and '_'".to_string());
+        }
+        Ok(cfg)
+    }
+
+    /// Map a built-in REST path to the configured table/RPC prefixes
+    pub fn resolve_path(&self, path: &str) -> String {
+        let path = path.trim_start_matches('/');
+        if let Some(rest) = path.strip_prefix("rest/v1/rpc/") {
+            return format!("rest/v1/rpc/{}{}", self.rpc_prefix, rest);
+        }
+        match path.strip_prefix("rest/v1/").and_then(|p| p.strip_prefix(BUILTIN_TABLE_PREFIX)) {
+            Some(rest) => format!("rest/v1/{}{}", self.table_prefix, rest),
+            None => path.to_string(),
+        }
     }
 }
 
@@ -49,31 +115,25 @@ pub struct PriceWithLeague {
     pub league_id: i32,
     pub league_name: String,
     pub is_current_league: bool,
+    /// Number of samples behind the price (if the RPC reports it)
+    #[serde(default)]
+    pub sample_count: Option<i64>,
+    /// Standard deviation of the samples (if the RPC reports it)
+    #[serde(default)]
+    pub price_stddev: Option<f64>,
 }
 
 /// Fetch current prices (legacy, без информации о лиге)
 pub async fn fetch_current_prices(
-    client: &reqwest::Client,
-    cfg: &SupabaseConfig,
-) -> Result<Vec<(i64, f64, DateTime<Utc>)>, String> {
-    let endpoint = format!(
-        "{}/rest/v1/tli_current_prices?select=game_id,price,last_updated",
-        cfg.url.trim_end_matches('/')
-    );
-
-    let resp = client
-        .get(endpoint)
-        .header("apikey", &cfg.anon_key)
-        .header("Authorization", format!("Bearer {}", cfg.anon_key))
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !resp.status().is_success() {
-        return Err(format!("Supabase fetch_current_prices failed: {}", resp.status()));
-    }
-
-    let rows: Vec<CurrentPriceRow> = resp.json().await.map_err(|e| e.to_string())?;
+    api: &SupabaseClient,
+) -> Result<Vec<(i64, f64, DateTime<Utc>)>, ApiError> {
+    let rows: Vec<CurrentPriceRow> = api
+        .get_json(
+            "fetch_current_prices",
+            "rest/v1/tli_current_prices?select=game_id,price,last_updated",
+            None,
+        )
+        .await?;
     Ok(rows
         .into_iter()
         .map(|r| (r.game_id, r.price, r.last_updated))
@@ -83,79 +143,115 @@ pub async fn fetch_current_prices(
 /// Fetch prices with fallback to previous season
 /// Возвращает цены текущей лиги + цены предыдущей лиги для предметов без цены в текущей
 pub async fn fetch_prices_with_fallback(
-    client: &reqwest::Client,
-    cfg: &SupabaseConfig,
-) -> Result<Vec<PriceWithLeague>, String> {
-    let endpoint = format!(
-        "{}/rest/v1/rpc/get_prices_with_fallback",
-        cfg.url.trim_end_matches('/')
-    );
-
-    let resp = client
-        .post(&endpoint)
-        .header("apikey", &cfg.anon_key)
-        .header("Authorization", format!("Bearer {}", cfg.anon_key))
-        .header("Content-Type", "application/json")
-        .body("{}")
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
+    api: &SupabaseClient,
+) -> Result<Vec<PriceWithLeague>, ApiError> {
+    // Read-only RPC: safe to retry
+    api.post_json(
+        "fetch_prices_with_fallback",
+        "rest/v1/rpc/get_prices_with_fallback",
+        None,
+        &serde_json::json!({}),
+        None,
+        true,
+    )
+    .await
+}
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        return Err(format!("fetch_prices_with_fallback failed: {} {}", status, text));
-    }
+/// Daily price aggregate for charting
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DailyPricePoint {
+    pub day: chrono::NaiveDate,
+    pub avg_price: f64,
+    pub min_price: Option<f64>,
+    pub max_price: Option<f64>,
+    #[serde(default)]
+    pub sample_count: i64,
+}
 
-    let rows: Vec<PriceWithLeague> = resp.json().await.map_err(|e| e.to_string())?;
-    Ok(rows)
+/// Fetch daily price aggregates of an item for the last `days` days (public read, anon key)
+pub async fn fetch_price_history(
+    api: &SupabaseClient,
+    game_id: i64,
+    days: i32,
+) -> Result<Vec<DailyPricePoint>, ApiError> {
+    let since = (Utc::now() - chrono::Duration::days(days as i64)).date_naive();
+    let path = format!(
+        "rest/v1/tli_price_history_daily?select=day,avg_price,min_price,max_price,sample_count&game_id=eq.{}&day=gte.{}&order=day.asc",
+        game_id,
+        since
+    );
+    api.get_json("fetch_price_history", &path, None).await
 }
 
 pub async fn upsert_market_price(
-    client: &reqwest::Client,
-    cfg: &SupabaseConfig,
+    api: &SupabaseClient,
     user_jwt: &str,
     game_id: i64,
     prices: &[f64],
     currency_id: i64,
-) -> Result<(), String> {
+) -> Result<(), ApiError> {
     if prices.is_empty() {
         return Ok(());
     }
 
-    let endpoint = format!(
-        "{}/rest/v1/rpc/upsert_market_price",
-        cfg.url.trim_end_matches('/')
-    );
-
     let body = serde_json::json!({
         "p_game_id": game_id,
         "p_prices": prices,
         "p_currency_id": currency_id
     });
 
-    let resp = client
-        .post(endpoint)
-        .header("apikey", &cfg.anon_key)
-        .header("Authorization", format!("Bearer {}", user_jwt))
-        .json(&body)
-        .send()
+    // Samples are aggregated server-side: a repeated call would count them twice
+    api.post("upsert_market_price", "rest/v1/rpc/upsert_market_price", Some(user_jwt), &body, None, false)
         .await
-        .map_err(|e| e.to_string())?;
+}
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        return Err(format!("Supabase upsert_market_price failed: {} {}", status, text));
+/// Report item ids missing from the catalog, with pickup counts and names seen in logs.
+/// Anonymous calls are accepted; the JWT only attributes the report to a user
+pub async fn report_unknown_items(
+    api: &SupabaseClient,
+    user_jwt: Option<&str>,
+    items: &[crate::unknown_items::UnknownItem],
+) -> Result<(), ApiError> {
+    if items.is_empty() {
+        return Ok(());
     }
+    let rows: Vec<serde_json::Value> = items
+        .iter()
+        .map(|item| {
+            serde_json::json!({
+                "game_id": item.game_id,
+                "seen_count": item.seen_count,
+                "total_quantity": item.total_quantity,
+                "name": item.name,
+                "tier": item.tier,
+                "first_seen": item.first_seen,
+                "last_seen": item.last_seen,
+            })
+        })
+        .collect();
+    let body = serde_json::json!({ "p_items": rows });
 
-    Ok(())
+    // Counts are summed server-side: a repeated call would count them twice
+    api.post("report_unknown_items", "rest/v1/rpc/report_unknown_items", user_jwt, &body, None, false)
+        .await
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
 // Game Items (names, categories, icons)
 // ─────────────────────────────────────────────────────────────────────────────
 
+/// Fetch category metadata (public read, anon key)
+pub async fn fetch_categories(
+    api: &SupabaseClient,
+) -> Result<Vec<crate::categories::CategoryInfo>, ApiError> {
+    api.get_json(
+        "fetch_categories",
+        "rest/v1/tli_item_categories?select=id,parent,name_en,name_ru,name_cn,sort_order",
+        None,
+    )
+    .await
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct GameItemRow {
     game_id: i64,
@@ -170,27 +266,15 @@ struct GameItemRow {
 
 /// Fetch all game items from Supabase (public read, anon key)
 pub async fn fetch_game_items(
-    client: &reqwest::Client,
-    cfg: &SupabaseConfig,
-) -> Result<Vec<ItemInfo>, String> {
-    let endpoint = format!(
-        "{}/rest/v1/tli_game_items?select=game_id,name_en,name_ru,name_cn,category,icon_url,is_base_currency",
-        cfg.url.trim_end_matches('/')
-    );
-
-    let resp = client
-        .get(endpoint)
-        .header("apikey", &cfg.anon_key)
-        .header("Authorization", format!("Bearer {}", cfg.anon_key))
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !resp.status().is_success() {
-        return Err(format!("Supabase fetch_game_items failed: {}", resp.status()));
-    }
-
-    let rows: Vec<GameItemRow> = resp.json().await.map_err(|e| e.to_string())?;
+    api: &SupabaseClient,
+) -> Result<Vec<ItemInfo>, ApiError> {
+    let rows: Vec<GameItemRow> = api
+        .get_json(
+            "fetch_game_items",
+            "rest/v1/tli_game_items?select=game_id,name_en,name_ru,name_cn,category,icon_url,is_base_currency",
+            None,
+        )
+        .await?;
     
     Ok(rows
         .into_iter()
@@ -207,6 +291,48 @@ pub async fn fetch_game_items(
         .collect())
 }
 
+// ─────────────────────────────────────────────────────────────────────────────
+// Log Patterns (remote parser definitions)
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Deserialize)]
+struct LogPatternsRow {
+    patterns: LogPatterns,
+}
+
+/// Fetch the newest log pattern definitions for a game client (public read, anon key).
+/// Returns None when the table has no rows for that client.
+pub async fn fetch_log_patterns(
+    api: &SupabaseClient,
+    game_client: GameClient,
+) -> Result<Option<LogPatterns>, ApiError> {
+    let client_key = match game_client {
+        GameClient::Cn => "cn",
+        GameClient::Global | GameClient::Auto => "global",
+    };
+    let path = format!(
+        "rest/v1/tli_log_patterns?select=patterns&client=eq.{}&order=revision.desc&limit=1",
+        client_key
+    );
+    let rows: Vec<LogPatternsRow> = api.get_json("fetch_log_patterns", &path, None).await?;
+    Ok(rows.into_iter().next().map(|r| r.patterns))
+}
+
+/// Fetch the client compatibility row (min app version, schema version).
+/// Returns None when the table is empty.
+pub async fn fetch_client_meta(
+    api: &SupabaseClient,
+) -> Result<Option<crate::schema_handshake::ClientMeta>, ApiError> {
+    let rows: Vec<crate::schema_handshake::ClientMeta> = api
+        .get_json(
+            "fetch_client_meta",
+            "rest/v1/tli_client_meta?select=min_app_version,schema_version,message&order=schema_version.desc&limit=1",
+            None,
+        )
+        .await?;
+    Ok(rows.into_iter().next())
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // Farm Sessions Sync
 // ─────────────────────────────────────────────────────────────────────────────
@@ -235,71 +361,86 @@ pub struct SessionHistoryItem {
     pub total_duration_sec: i32,
     pub total_profit_calculated: Option<f64>,
     pub expenses_calculated: Option<f64>,
+    /// Sale fees (already subtracted from total_profit_calculated).
+    /// Needs `alter table tli_farm_sessions add column if not exists fee_calculated numeric;`
+    /// on the server; without it the column is left out (see FEE_COLUMN)
+    #[serde(default)]
+    pub fee_calculated: Option<f64>,
+    #[serde(default)]
+    pub preset_id: Option<String>,
 }
 
-/// Sync completed session to Supabase
+/// Optional tli_farm_sessions column that older server schemas don't have
+const FEE_COLUMN: &str = "fee_calculated";
+
+/// PostgREST rejected the request because the table has no such column
+/// (42703 on select, PGRST204 on insert)
+fn is_unknown_column(e: &ApiError, column: &str) -> bool {
+    matches!(e, ApiError::Request(m) if m.contains(column)
+        && (m.contains("42703") || m.contains("PGRST204") || m.contains("does not exist")))
+}
+
+/// Sync a completed session (local history record) to Supabase. Returns the remote session id.
 pub async fn sync_farm_session(
-    client: &reqwest::Client,
-    cfg: &SupabaseConfig,
+    api: &SupabaseClient,
     user_jwt: &str,
     user_id: &str,
-    session: &FarmSessionState,
-    total_profit: f64,
-    total_expenses: f64,
+    record: &SessionHistoryRecord,
     app_version: &str,
-) -> Result<String, String> {
-    let started_at = session.started_at.ok_or("Session has no start time")?;
-    let ended_at = Utc::now();
-    
-    let endpoint = format!(
-        "{}/rest/v1/tli_farm_sessions",
-        cfg.url.trim_end_matches('/')
-    );
-
-    let body = serde_json::json!({
+) -> Result<String, ApiError> {
+    let mut body = serde_json::json!({
         "user_id": user_id,
-        "started_at": started_at,
-        "ended_at": ended_at,
-        "maps_completed": session.maps_completed,
-        "total_duration_sec": session.total_duration_sec,
-        "total_profit_calculated": total_profit,
-        "expenses_calculated": total_expenses,
+        "started_at": record.started_at,
+        "ended_at": record.ended_at,
+        "maps_completed": record.maps_completed,
+        "total_duration_sec": record.total_duration_sec,
+        "total_profit_calculated": record.total_profit,
+        "expenses_calculated": record.total_expenses,
+        "fee_calculated": record.total_fee,
         "client_version": app_version,
-        "preset_id": session.preset_id,
+        "preset_id": record.preset_id,
         "sync_status": "synced"
     });
 
-    let resp = client
-        .post(&endpoint)
-        .header("apikey", &cfg.anon_key)
-        .header("Authorization", format!("Bearer {}", user_jwt))
-        .header("Content-Type", "application/json")
-        .header("Prefer", "return=representation")
-        .json(&body)
-        .send()
+    // Plain insert: never retried after the server may have stored it.
+    // A rejected unknown column means nothing was stored — insert again without it
+    let insert = |body: serde_json::Value| async move {
+        api.post_json::<Vec<serde_json::Value>>(
+            "sync_farm_session",
+            "rest/v1/tli_farm_sessions",
+            Some(user_jwt),
+            &body,
+            Some("return=representation"),
+            false,
+        )
         .await
-        .map_err(|e| e.to_string())?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        return Err(format!("sync_farm_session failed: {} {}", status, text));
-    }
+    };
+    let result = match insert(body.clone()).await {
+        Err(e) if is_unknown_column(&e, FEE_COLUMN) => {
+            log::warn!("Server has no {} column, syncing the session without fees", FEE_COLUMN);
+            if let Some(fields) = body.as_object_mut() {
+                fields.remove(FEE_COLUMN);
+            }
+            insert(body).await?
+        }
+        other => other?,
+    };
 
     // Parse response to get session ID
-    let result: Vec<serde_json::Value> = resp.json().await.map_err(|e| e.to_string())?;
     let session_id = result
         .first()
         .and_then(|r| r.get("id"))
         .and_then(|v| v.as_str())
         .map(|s| s.to_string())
-        .unwrap_or_else(|| "unknown".to_string());
+        .ok_or_else(|| ApiError::Schema("sync_farm_session: response has no session id".to_string()))?;
     
     log::info!("Session synced to Supabase: {}", session_id);
     
     // Sync individual drops
-    if !session.drops.is_empty() {
-        let _ = sync_session_drops(client, cfg, user_jwt, &session_id, &session.drops).await;
+    if !record.drops.is_empty() {
+        if let Err(e) = sync_session_drops(api, user_jwt, &session_id, &record.drops).await {
+            log::warn!("Session {} synced without drops: {}", session_id, e);
+        }
     }
     
     Ok(session_id)
@@ -307,21 +448,15 @@ pub async fn sync_farm_session(
 
 /// Sync session drops to tli_session_drops
 async fn sync_session_drops(
-    client: &reqwest::Client,
-    cfg: &SupabaseConfig,
+    api: &SupabaseClient,
     user_jwt: &str,
     session_id: &str,
     drops: &std::collections::HashMap<i64, i32>,
-) -> Result<(), String> {
+) -> Result<(), ApiError> {
     if drops.is_empty() {
         return Ok(());
     }
     
-    let endpoint = format!(
-        "{}/rest/v1/tli_session_drops",
-        cfg.url.trim_end_matches('/')
-    );
-    
     let records: Vec<serde_json::Value> = drops
         .iter()
         .map(|(game_id, quantity)| {
@@ -333,52 +468,358 @@ async fn sync_session_drops(
         })
         .collect();
 
-    let resp = client
-        .post(&endpoint)
-        .header("apikey", &cfg.anon_key)
-        .header("Authorization", format!("Bearer {}", user_jwt))
-        .header("Content-Type", "application/json")
-        .json(&records)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        return Err(format!("sync_session_drops failed: {} {}", status, text));
-    }
-
-    Ok(())
+    api.post(
+        "sync_session_drops",
+        "rest/v1/tli_session_drops",
+        Some(user_jwt),
+        &serde_json::Value::Array(records),
+        None,
+        false,
+    )
+    .await
 }
 
 /// Fetch session history for current user
 pub async fn fetch_session_history(
-    client: &reqwest::Client,
-    cfg: &SupabaseConfig,
+    api: &SupabaseClient,
     user_jwt: &str,
     limit: i32,
-) -> Result<Vec<SessionHistoryItem>, String> {
-    let endpoint = format!(
-        "{}/rest/v1/tli_farm_sessions?select=id,started_at,ended_at,maps_completed,total_duration_sec,total_profit_calculated,expenses_calculated&order=started_at.desc&limit={}",
-        cfg.url.trim_end_matches('/'),
-        limit
+) -> Result<Vec<SessionHistoryItem>, ApiError> {
+    let path = |columns: &str| {
+        format!(
+            "rest/v1/tli_farm_sessions?select=id,started_at,ended_at,maps_completed,total_duration_sec,total_profit_calculated,expenses_calculated,{}preset_id&order=started_at.desc&limit={}",
+            columns, limit
+        )
+    };
+    match api.get_json("fetch_session_history", &path("fee_calculated,"), Some(user_jwt)).await {
+        Err(e) if is_unknown_column(&e, FEE_COLUMN) => {
+            log::warn!("Server has no {} column, fetching history without fees", FEE_COLUMN);
+            api.get_json("fetch_session_history", &path(""), Some(user_jwt)).await
+        }
+        other => other,
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SessionDropRow {
+    session_id: String,
+    game_id: i64,
+    quantity: i32,
+}
+
+/// Fetch drops of remote sessions: session_id -> (game_id -> quantity)
+pub async fn fetch_session_drops(
+    api: &SupabaseClient,
+    user_jwt: &str,
+    session_ids: &[String],
+) -> Result<std::collections::HashMap<String, std::collections::HashMap<i64, i32>>, ApiError> {
+    let mut result: std::collections::HashMap<String, std::collections::HashMap<i64, i32>> =
+        std::collections::HashMap::new();
+    if session_ids.is_empty() {
+        return Ok(result);
+    }
+
+    let path = format!(
+        "rest/v1/tli_session_drops?select=session_id,game_id,quantity&session_id=in.({})",
+        session_ids.join(",")
     );
+    let rows: Vec<SessionDropRow> = api.get_json("fetch_session_drops", &path, Some(user_jwt)).await?;
+    for row in rows {
+        *result.entry(row.session_id).or_default().entry(row.game_id).or_insert(0) += row.quantity;
+    }
+    Ok(result)
+}
 
-    let resp = client
-        .get(&endpoint)
-        .header("apikey", &cfg.anon_key)
-        .header("Authorization", format!("Bearer {}", user_jwt))
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
+// ─────────────────────────────────────────────────────────────────────────────
+// Public session sharing
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Anonymized session snapshot stored in tli_shared_sessions.payload
+/// (no user id / email inside — the row owner column is used only for RLS).
+/// Live shares carry the running session and are re-uploaded periodically.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedSessionSnapshot {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub maps_completed: i32,
+    pub total_duration_sec: i32,
+    pub total_income: f64,
+    pub total_expenses: f64,
+    #[serde(default)]
+    pub total_fee: f64,
+    pub total_profit: f64,
+    pub drops: Vec<SharedDrop>,
+    pub client_version: String,
+    /// Session is still running (ended_at = time of the snapshot)
+    #[serde(default)]
+    pub is_live: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedDrop {
+    pub game_id: i64,
+    pub name: Option<String>,
+    pub quantity: i32,
+    pub unit_price: f64,
+    pub total_value: f64,
+}
+
+/// Public URL of a shared session (rendered by kripika.com)
+pub fn shared_session_url(origin: &str, share_code: &str) -> String {
+    format!("{}/tli/shared/{}", origin.trim_end_matches('/'), share_code)
+}
+
+fn generate_share_code() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..12].to_string()
+}
+
+/// Upload a session snapshot for public sharing. Returns the share code.
+pub async fn create_session_share(
+    api: &SupabaseClient,
+    user_jwt: &str,
+    user_id: &str,
+    snapshot: &SharedSessionSnapshot,
+) -> Result<String, ApiError> {
+    let share_code = generate_share_code();
+
+    let body = serde_json::json!({
+        "share_code": share_code,
+        "user_id": user_id,
+        "payload": snapshot,
+    });
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        return Err(format!("fetch_session_history failed: {} {}", status, text));
+    api.post(
+        "create_session_share",
+        "rest/v1/tli_shared_sessions",
+        Some(user_jwt),
+        &body,
+        Some("return=minimal"),
+        false,
+    )
+    .await?;
+
+    Ok(share_code)
+}
+
+/// Remove a shared session snapshot (RLS allows deleting only own rows)
+pub async fn delete_session_share(
+    api: &SupabaseClient,
+    user_jwt: &str,
+    share_code: &str,
+) -> Result<(), ApiError> {
+    let path = format!(
+        "rest/v1/tli_shared_sessions?share_code=eq.{}",
+        urlencoding::encode(share_code)
+    );
+    api.delete("delete_session_share", &path, Some(user_jwt)).await
+}
+
+/// Replace the payload of an own shared session (live share updates)
+pub async fn update_session_share(
+    api: &SupabaseClient,
+    user_jwt: &str,
+    share_code: &str,
+    snapshot: &SharedSessionSnapshot,
+) -> Result<(), ApiError> {
+    let path = format!(
+        "rest/v1/tli_shared_sessions?share_code=eq.{}",
+        urlencoding::encode(share_code)
+    );
+    let body = serde_json::json!({ "payload": snapshot });
+    api.patch("update_session_share", &path, Some(user_jwt), &body).await
+}
+
+#[derive(Debug, Deserialize)]
+struct SharedSessionRow {
+    payload: SharedSessionSnapshot,
+}
+
+/// Read a shared session by code (public). None — no such share (revoked or never existed)
+pub async fn fetch_shared_session(
+    api: &SupabaseClient,
+    share_code: &str,
+) -> Result<Option<SharedSessionSnapshot>, ApiError> {
+    let path = format!(
+        "rest/v1/tli_shared_sessions?select=payload&share_code=eq.{}&limit=1",
+        urlencoding::encode(share_code)
+    );
+    let rows: Vec<SharedSessionRow> = api.get_json("fetch_shared_session", &path, None).await?;
+    Ok(rows.into_iter().next().map(|row| row.payload))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Leaderboard
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Sessions shorter than this produce meaningless per-hour rates
+const LEADERBOARD_MIN_DURATION_SEC: i32 = 10 * 60;
+
+/// Ranking metric (column of tli_leaderboard_current)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardMetric {
+    #[default]
+    ProfitPerHour,
+    MapsPerHour,
+}
+
+impl LeaderboardMetric {
+    fn column(self) -> &'static str {
+        match self {
+            LeaderboardMetric::ProfitPerHour => "profit_per_hour",
+            LeaderboardMetric::MapsPerHour => "maps_per_hour",
+        }
     }
+}
 
-    let sessions: Vec<SessionHistoryItem> = resp.json().await.map_err(|e| e.to_string())?;
-    Ok(sessions)
+/// Normalized session metrics submitted to the leaderboard
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaderboardSubmission {
+    pub session_id: String,
+    pub profit_per_hour: f64,
+    pub maps_per_hour: f64,
+    pub total_duration_sec: i32,
+    pub maps_completed: i32,
+    pub client_version: String,
+}
+
+impl LeaderboardSubmission {
+    /// Build from a local history record. None if the session is too short to rank.
+    pub fn from_history(record: &SessionHistoryRecord) -> Option<Self> {
+        if record.total_duration_sec < LEADERBOARD_MIN_DURATION_SEC {
+            return None;
+        }
+        let hours = record.total_duration_sec as f64 / 3600.0;
+        Some(Self {
+            session_id: record.id.clone(),
+            profit_per_hour: record.total_profit / hours,
+            maps_per_hour: record.maps_completed as f64 / hours,
+            total_duration_sec: record.total_duration_sec,
+            maps_completed: record.maps_completed,
+            client_version: env!("CARGO_PKG_VERSION").to_string(),
+        })
+    }
+}
+
+/// Leaderboard row (current league)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LeaderboardEntry {
+    /// 1-based position in the requested ranking (filled client-side)
+    #[serde(default)]
+    pub rank: i32,
+    pub display_name: Option<String>,
+    pub profit_per_hour: f64,
+    pub maps_per_hour: f64,
+    pub total_duration_sec: i32,
+    pub submitted_at: DateTime<Utc>,
+}
+
+/// Submit normalized session metrics (upsert by user + session)
+pub async fn submit_leaderboard_entry(
+    api: &SupabaseClient,
+    user_jwt: &str,
+    user_id: &str,
+    submission: &LeaderboardSubmission,
+) -> Result<(), ApiError> {
+    let mut body = serde_json::to_value(submission).map_err(|e| ApiError::Schema(e.to_string()))?;
+    body["user_id"] = serde_json::Value::String(user_id.to_string());
+
+    // Upsert by (user_id, session_id): safe to retry
+    api.post(
+        "submit_leaderboard_entry",
+        "rest/v1/tli_leaderboard?on_conflict=user_id,session_id",
+        Some(user_jwt),
+        &body,
+        Some("resolution=merge-duplicates,return=minimal"),
+        true,
+    )
+    .await
+}
+
+/// Fetch current league leaderboard page (public read, anon key)
+pub async fn fetch_leaderboard(
+    api: &SupabaseClient,
+    metric: LeaderboardMetric,
+    limit: i32,
+    offset: i32,
+) -> Result<Vec<LeaderboardEntry>, ApiError> {
+    let path = format!(
+        "rest/v1/tli_leaderboard_current?select=display_name,profit_per_hour,maps_per_hour,total_duration_sec,submitted_at&order={}.desc&limit={}&offset={}",
+        metric.column(),
+        limit,
+        offset
+    );
+    let mut rows: Vec<LeaderboardEntry> = api.get_json("fetch_leaderboard", &path, None).await?;
+    for (idx, row) in rows.iter_mut().enumerate() {
+        row.rank = offset + idx as i32 + 1;
+    }
+    Ok(rows)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Group sessions (party mode)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Member row of tli_group_members: the latest live snapshot of one party member
+#[derive(Debug, Clone, Deserialize)]
+pub struct GroupMemberRow {
+    pub user_id: String,
+    pub display_name: Option<String>,
+    pub payload: SharedSessionSnapshot,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Upsert own member row (by group + user): safe to retry
+pub async fn upsert_group_member(
+    api: &SupabaseClient,
+    user_jwt: &str,
+    user_id: &str,
+    group_id: &str,
+    display_name: &str,
+    snapshot: &SharedSessionSnapshot,
+) -> Result<(), ApiError> {
+    let body = serde_json::json!({
+        "group_id": group_id,
+        "user_id": user_id,
+        "display_name": display_name,
+        "payload": snapshot,
+        "updated_at": Utc::now(),
+    });
+    api.post(
+        "upsert_group_member",
+        "rest/v1/tli_group_members?on_conflict=group_id,user_id",
+        Some(user_jwt),
+        &body,
+        Some("resolution=merge-duplicates,return=minimal"),
+        true,
+    )
+    .await
+}
+
+/// All member rows of a group (RLS: visible to members of the same group)
+pub async fn fetch_group_members(
+    api: &SupabaseClient,
+    user_jwt: &str,
+    group_id: &str,
+) -> Result<Vec<GroupMemberRow>, ApiError> {
+    let path = format!(
+        "rest/v1/tli_group_members?select=user_id,display_name,payload,updated_at&group_id=eq.{}",
+        urlencoding::encode(group_id)
+    );
+    api.get_json("fetch_group_members", &path, Some(user_jwt)).await
+}
+
+/// Remove own member row (leaving the group)
+pub async fn delete_group_member(
+    api: &SupabaseClient,
+    user_jwt: &str,
+    group_id: &str,
+    user_id: &str,
+) -> Result<(), ApiError> {
+    let path = format!(
+        "rest/v1/tli_group_members?group_id=eq.{}&user_id=eq.{}",
+        urlencoding::encode(group_id),
+        urlencoding::encode(user_id)
+    );
+    api.delete("delete_group_member", &path, Some(user_jwt)).await
 }
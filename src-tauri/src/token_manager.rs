@@ -0,0 +1,85 @@
+//! Background access token renewal
+//!
+//! Refreshes the access token shortly before it expires, so commands never hit an expired
+//! token. Transient failures (no network, 5xx) keep the session and are retried with
+//! exponential backoff; only a rejected refresh token signs the user out
+//! (see `AppState::refresh_auth_session`, which also emits `auth-state-changed`).
+//! If the startup restore failed transiently, the manager keeps retrying it as long as
+//! a refresh token is stored. Attempts are at least MIN_REFRESH_INTERVAL apart, so a
+//! token that lives shorter than REFRESH_AHEAD_SEC is not refreshed back to back.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::{debug, info, warn};
+
+use crate::state::AppState;
+
+/// Refresh this long before the access token expires
+const REFRESH_AHEAD_SEC: i64 = 300;
+/// Minimum time between two refresh attempts
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+const BACKOFF_START: Duration = Duration::from_secs(5);
+const BACKOFF_MAX: Duration = Duration::from_secs(300);
+/// Idle re-check interval when logged out or Supabase is not configured
+const IDLE_CHECK: Duration = Duration::from_secs(60);
+
+pub async fn run(state: Arc<AppState>) {
+    let mut auth_rx = state.auth_events.subscribe();
+    let mut backoff = BACKOFF_START;
+    let refresh_ahead = chrono::Duration::seconds(REFRESH_AHEAD_SEC);
+    let mut last_attempt: Option<Instant> = None;
+
+    loop {
+        let mut wait = match state.auth_expires_at().await {
+            Some(expires_at) => (expires_at - refresh_ahead - chrono::Utc::now())
+                .to_std()
+                .unwrap_or(Duration::ZERO),
+            // Not logged in: try to restore only if a refresh token is stored
+            None => match crate::auth::load_refresh_token() {
+                Ok(Some(_)) => Duration::ZERO,
+                _ => IDLE_CHECK,
+            },
+        };
+        if let Some(since) = last_attempt.map(|at| at.elapsed()) {
+            wait = wait.max(MIN_REFRESH_INTERVAL.saturating_sub(since));
+        }
+
+        if !wait.is_zero() {
+            // Sign-in/out changes the deadline — recompute right away
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = auth_rx.changed() => {}
+            }
+            continue;
+        }
+
+        // Take the client on every attempt: proxy changes rebuild it
+        let Some(api) = state.supabase_client().await else {
+            tokio::time::sleep(IDLE_CHECK).await;
+            continue;
+        };
+
+        last_attempt = Some(Instant::now());
+        match state.refresh_auth_session(&api, refresh_ahead).await {
+            Ok(Some(_)) => {
+                debug!("Access token renewed proactively");
+                backoff = BACKOFF_START;
+            }
+            Ok(None) => {
+                tokio::time::sleep(IDLE_CHECK).await;
+            }
+            Err(e) if e.is_transient() => {
+                warn!("Proactive token refresh failed, retrying in {:?}: {}", backoff, e);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(BACKOFF_MAX);
+            }
+            Err(e) => {
+                // A stored refresh token that keeps being rejected must not spin the loop
+                info!("Session ended by token manager, next check in {:?}: {}", backoff, e);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(BACKOFF_MAX);
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Иконка в трее с быстрыми действиями над сессией
+//!
+//! Меню управляется с backend: Старт/Пауза/Завершить вызывают те же команды, что и UI,
+//! а доступность пунктов и тултип (профит/час) обновляются из AppState.
+
+use std::sync::Arc;
+use log::{debug, info, warn};
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::state::AppState;
+use crate::types::SessionStats;
+use crate::value_format::compact;
+
+const TRAY_ID: &str = "main";
+
+/// Пункты меню, состояние которых зависит от сессии
+#[derive(Clone)]
+pub struct TrayHandles {
+    tray: TrayIcon<Wry>,
+    start: MenuItem<Wry>,
+    pause: MenuItem<Wry>,
+    end: MenuItem<Wry>,
+}
+
+/// Состояние сессии для фронтенда после действия из трея
+#[derive(Debug, Clone, serde::Serialize)]
+struct SessionStatePayload {
+    active: bool,
+    paused: bool,
+}
+
+/// Создать иконку в трее с меню быстрых действий
+pub fn build(app: &AppHandle) -> tauri::Result<TrayHandles> {
+    let show_item = MenuItem::with_id(app, "show", "Показать", true, None::<&str>)?;
+    let start_item = MenuItem::with_id(app, "session_start", "Начать сессию", true, None::<&str>)?;
+    let pause_item = MenuItem::with_id(app, "session_pause", "Пауза", false, None::<&str>)?;
+    let end_item = MenuItem::with_id(app, "session_end", "Завершить сессию", false, None::<&str>)?;
+    let companion_item = MenuItem::with_id(app, "companion", "Мини-окно статистики", true, None::<&str>)?;
+    let log_folder_item = MenuItem::with_id(app, "open_log_folder", "Открыть папку логов", true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app, "quit", "Выход", true, None::<&str>)?;
+    let tray_menu = Menu::with_items(app, &[
+        &show_item,
+        &PredefinedMenuItem::separator(app)?,
+        &start_item,
+        &pause_item,
+        &end_item,
+        &PredefinedMenuItem::separator(app)?,
+        &companion_item,
+        &log_folder_item,
+        &PredefinedMenuItem::separator(app)?,
+        &quit_item,
+    ])?;
+
+    let tray = TrayIconBuilder::with_id(TRAY_ID)
+        .icon(app.default_window_icon().unwrap().clone())
+        .tooltip("TLI Companion")
+        .menu(&tray_menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| {
+            match event.id.as_ref() {
+                "show" => show_main_window(app),
+                "quit" => {
+                    info!("Quit requested from tray menu");
+                    // Состояние записывается в обработчике RunEvent::Exit (main.rs)
+                    app.exit(0);
+                }
+                id @ ("session_start" | "session_pause" | "session_end" | "companion" | "open_log_folder") => {
+                    let app = app.clone();
+                    let id = id.to_string();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = handle_action(&app, &id).await {
+                            warn!("Tray action '{}' failed: {}", id, e);
+                        }
+                    });
+                }
+                _ => {}
+            }
+        })
+        .on_tray_icon_event(|tray, event| {
+            // Клик левой кнопкой - показать окно
+            if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
+                show_main_window(tray.app_handle());
+            }
+        })
+        .build(app)?;
+
+    Ok(TrayHandles {
+        tray,
+        start: start_item,
+        pause: pause_item,
+        end: end_item,
+    })
+}
+
+/// Показать главное окно и отдать ему фокус (трей, повторный запуск приложения)
+pub(crate) fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+async fn handle_action(app: &AppHandle, id: &str) -> Result<(), String> {
+    let state = app.state::<Arc<AppState>>();
+    match id {
+        "session_start" => {
+            if !state.is_session_active().await {
+                state.start_session(None).await;
+            }
+        }
+        "session_pause" => {
+            let paused = state.is_paused().await;
+            state.set_paused(!paused).await;
+        }
+        "session_end" => {
+            if state.is_session_active().await {
+                crate::commands::end_session(app.state::<Arc<AppState>>(), app.clone()).await?;
+            }
+        }
+        "companion" => {
+            crate::companion_window::toggle(app, &state).await.map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+        "open_log_folder" => {
+            let log_path = state.get_log_path().await.ok_or("Log path is not configured")?;
+            let folder = std::path::Path::new(&log_path)
+                .parent()
+                .ok_or("Log path has no parent folder")?
+                .to_path_buf();
+            open::that(&folder).map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+        _ => return Ok(()),
+    }
+
+    // Сессия изменилась не из UI — сообщаем фронтенду
+    emit_session_state(app, &state).await
+}
+
+/// Сообщить фронтенду, что сессия запущена/поставлена на паузу/завершена не из UI
+pub async fn emit_session_state(app: &AppHandle, state: &AppState) -> Result<(), String> {
+    let payload = SessionStatePayload {
+        active: state.is_session_active().await,
+        paused: state.is_paused().await,
+    };
+    app.emit("session-state-changed", &payload).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+impl TrayHandles {
+    /// Обновить доступность пунктов и тултип по текущему состоянию сессии
+    pub fn refresh(&self, stats: &SessionStats, active: bool) {
+        let paused = stats.is_paused;
+        let results = [
+            self.start.set_enabled(!active),
+            self.pause.set_enabled(active),
+            self.pause.set_text(if paused { "Продолжить" } else { "Пауза" }),
+            self.end.set_enabled(active),
+        ];
+        if let Some(Err(e)) = results.into_iter().find(|r| r.is_err()) {
+            debug!("Failed to update tray menu: {}", e);
+        }
+
+        let tooltip = if !active {
+            "TLI Companion".to_string()
+        } else if paused {
+            format!("TLI Companion — пауза ({} FE)", compact(stats.total_value))
+        } else {
+            format!("TLI Companion — {} FE/ч ({} FE)", compact(stats.hourly_profit), compact(stats.total_value))
+        };
+        if let Err(e) = self.tray.set_tooltip(Some(tooltip)) {
+            debug!("Failed to update tray tooltip: {}", e);
+        }
+    }
+}
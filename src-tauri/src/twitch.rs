@@ -0,0 +1,367 @@
+//! Объявления в чат Twitch
+//!
+//! Крупные дропы и итог сессии публикуются в чат своего канала через IRC Twitch
+//! (wss://irc-ws.chat.twitch.tv). Тексты — шаблоны из настроек с подстановками
+//! `{item}`, `{quantity}`, `{value}` / `{maps}`, `{duration}`, `{profit}`, `{per_hour}`.
+//! Объявления о дропах не чаще раза в `min_interval_secs`, итог сессии — всегда.
+//! OAuth-токен (scope chat:edit) хранится в keychain.
+//!
+//! Соединение держит фоновая задача: поднимается при первом сообщении, отвечает
+//! на PING и переподключается после обрыва.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, info, warn};
+use serde::Serialize;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::drop_announcer::RateLimiter;
+use crate::types::{SessionStats, TwitchSettings};
+use crate::valuation::Valuation;
+
+const KEYRING_USERNAME: &str = "twitch-oauth-token";
+
+const TWITCH_IRC_URL: &str = "wss://irc-ws.chat.twitch.tv:443";
+/// Подключение и вход — не дольше
+const TWITCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Предел длины сообщения в чате Twitch
+const MAX_MESSAGE_CHARS: usize = 500;
+
+/// Сохранить OAuth-токен (с префиксом "oauth:" или без; пустой — удалить)
+pub fn store_token(token: &str) -> Result<(), String> {
+    let token = token.trim();
+    let token = token.strip_prefix("oauth:").unwrap_or(token);
+    if token.is_empty() {
+        return match crate::auth::keyring_entry(KEYRING_USERNAME)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        };
+    }
+    crate::auth::keyring_entry(KEYRING_USERNAME)?
+        .set_password(token)
+        .map_err(|e| format!("Failed to store Twitch token in keychain: {}", e))
+}
+
+fn load_token() -> Option<String> {
+    match crate::auth::keyring_entry(KEYRING_USERNAME).and_then(|e| e.get_password().map_err(|e| e.to_string())) {
+        Ok(token) if !token.is_empty() => Some(token),
+        _ => None,
+    }
+}
+
+/// Состояние интеграции для настроек
+#[derive(Debug, Clone, Serialize)]
+pub struct TwitchStatus {
+    pub has_token: bool,
+    /// Объявления приглушены до конца сессии (команда toggle_twitch_chat)
+    pub muted: bool,
+}
+
+/// Канал без '#' в нижнем регистре
+fn normalize_channel(channel: &str) -> String {
+    channel.trim().trim_start_matches('#').to_lowercase()
+}
+
+/// Подставить `{ключ}` из списка; перевод строки в чате невозможен — заменяется пробелом
+pub fn render(template: &str, vars: &[(&str, String)]) -> String {
+    let mut text = template.to_string();
+    for (key, value) in vars {
+        text = text.replace(&format!("{{{}}}", key), value);
+    }
+    let text = text.replace(['\r', '\n'], " ");
+    text.trim().chars().take(MAX_MESSAGE_CHARS).collect()
+}
+
+/// Текст объявления о дропе
+pub fn drop_message(settings: &TwitchSettings, name: &str, quantity: i32, value: f64) -> String {
+    render(
+        &settings.drop_template,
+        &[
+            ("item", name.to_string()),
+            ("quantity", quantity.to_string()),
+            ("value", crate::value_format::fe(value)),
+        ],
+    )
+}
+
+/// Текст итога сессии. `{per_hour}` считается от того же чистого `profit`,
+/// что и `{profit}`, чтобы строки не расходились
+pub fn summary_message(
+    settings: &TwitchSettings,
+    stats: &SessionStats,
+    profit: f64,
+    valuation: &Valuation,
+) -> String {
+    let sec = stats.duration_sec.max(0);
+    let per_hour = valuation.per_hour(profit, sec);
+    render(
+        &settings.summary_template,
+        &[
+            ("maps", stats.maps_completed.to_string()),
+            ("duration", format!("{}:{:02}", sec / 3600, sec / 60 % 60)),
+            ("profit", crate::value_format::compact(profit)),
+            ("per_hour", crate::value_format::compact(per_hour)),
+            ("income", crate::value_format::compact(stats.total_value)),
+        ],
+    )
+}
+
+/// Сообщение в очереди фоновой задачи
+struct Outgoing {
+    login: String,
+    channel: String,
+    text: String,
+}
+
+/// Куда и от чьего имени писать: логин бота, иначе сам канал
+fn outgoing(settings: &TwitchSettings, text: String) -> Option<Outgoing> {
+    let channel = normalize_channel(&settings.channel);
+    if channel.is_empty() || text.is_empty() {
+        return None;
+    }
+    let login = match settings.login.trim() {
+        "" => channel.clone(),
+        login => login.to_lowercase(),
+    };
+    Some(Outgoing { login, channel, text })
+}
+
+/// Объявления в чат: задача соединения поднимается при первом сообщении
+#[derive(Default)]
+pub struct TwitchChat {
+    tx: Mutex<Option<UnboundedSender<Outgoing>>>,
+    limiter: Mutex<RateLimiter>,
+    muted: AtomicBool,
+}
+
+impl TwitchChat {
+    pub fn status(&self) -> TwitchStatus {
+        TwitchStatus { has_token: load_token().is_some(), muted: self.is_muted() }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    pub fn set_muted(&self, muted: bool) {
+        self.muted.store(muted, Ordering::Relaxed);
+        info!("Twitch chat announcements {}", if muted { "muted" } else { "resumed" });
+    }
+
+    /// Объявить дроп, если он дороже порога и интервал выдержан
+    pub fn announce_drop(&self, settings: &TwitchSettings, name: &str, quantity: i32, value: f64) {
+        if !settings.enabled || self.is_muted() || value < settings.drop_min_value {
+            return;
+        }
+        let interval = Duration::from_secs(settings.min_interval_secs);
+        if !self.limiter.lock().unwrap().allow(Instant::now(), interval) {
+            debug!("Twitch announcement skipped (rate limit): {}", name);
+            return;
+        }
+        self.post(settings, drop_message(settings, name, quantity, value));
+    }
+
+    /// Итог сессии (без ограничения частоты)
+    pub fn announce_summary(&self, settings: &TwitchSettings, stats: &SessionStats, profit: f64, valuation: &Valuation) {
+        if !settings.enabled || !settings.summary_enabled || self.is_muted() {
+            return;
+        }
+        self.post(settings, summary_message(settings, stats, profit, valuation));
+    }
+
+    fn post(&self, settings: &TwitchSettings, text: String) {
+        let Some(message) = outgoing(settings, text) else {
+            return;
+        };
+        let mut tx = self.tx.lock().unwrap();
+        let sender = tx.get_or_insert_with(spawn_chat_task);
+        if let Err(mpsc::error::SendError(message)) = sender.send(message) {
+            let sender = spawn_chat_task();
+            let _ = sender.send(message);
+            *tx = Some(sender);
+        }
+    }
+}
+
+/// Вход в чат выполнен
+struct ChatConnection {
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    login: String,
+    channel: String,
+}
+
+impl ChatConnection {
+    async fn connect(login: &str, channel: &str) -> Result<Self, String> {
+        let token = load_token().ok_or("Twitch token is not set")?;
+        let (ws, _) = tokio::time::timeout(TWITCH_TIMEOUT, tokio_tungstenite::connect_async(TWITCH_IRC_URL))
+            .await
+            .map_err(|_| "Twitch connection timed out".to_string())?
+            .map_err(|e| format!("Cannot connect to Twitch chat: {}", e))?;
+        let mut conn = Self { ws, login: login.to_string(), channel: channel.to_string() };
+        conn.send_line(&format!("PASS oauth:{}", token)).await?;
+        conn.send_line(&format!("NICK {}", login)).await?;
+        tokio::time::timeout(TWITCH_TIMEOUT, conn.wait_welcome())
+            .await
+            .map_err(|_| "Twitch did not confirm the login".to_string())??;
+        conn.send_line(&format!("JOIN #{}", channel)).await?;
+        info!("Joined Twitch chat #{} as {}", channel, login);
+        Ok(conn)
+    }
+
+    async fn send_line(&mut self, line: &str) -> Result<(), String> {
+        self.ws.send(Message::Text(line.to_string())).await.map_err(|e| e.to_string())
+    }
+
+    /// Ждём 001 (вход выполнен) или NOTICE об ошибке входа
+    async fn wait_welcome(&mut self) -> Result<(), String> {
+        while let Some(message) = self.ws.next().await {
+            let Message::Text(text) = message.map_err(|e| e.to_string())? else {
+                continue;
+            };
+            for line in text.lines() {
+                if line.split(' ').nth(1) == Some("001") {
+                    return Ok(());
+                }
+                if line.contains("NOTICE") && line.contains("authentication failed") {
+                    return Err("Twitch rejected the token".to_string());
+                }
+            }
+        }
+        Err("Twitch closed the connection".to_string())
+    }
+
+    async fn privmsg(&mut self, text: &str) -> Result<(), String> {
+        let line = format!("PRIVMSG #{} :{}", self.channel, text);
+        self.send_line(&line).await
+    }
+
+    /// Ответить на PING сервера (иначе Twitch закрывает соединение)
+    async fn handle_incoming(&mut self, text: &str) -> Result<(), String> {
+        for line in text.lines() {
+            if let Some(payload) = line.strip_prefix("PING") {
+                self.send_line(&format!("PONG{}", payload)).await?;
+            } else if line.contains("NOTICE") {
+                debug!("Twitch: {}", line);
+            }
+        }
+        Ok(())
+    }
+
+    fn is_for(&self, message: &Outgoing) -> bool {
+        self.login == message.login && self.channel == message.channel
+    }
+}
+
+enum ChatEvent {
+    Send(Option<Outgoing>),
+    Incoming(Option<Result<Message, tungstenite::Error>>),
+}
+
+fn spawn_chat_task() -> UnboundedSender<Outgoing> {
+    let (tx, rx) = mpsc::unbounded_channel::<Outgoing>();
+    tauri::async_runtime::spawn(chat_task(rx));
+    tx
+}
+
+async fn chat_task(mut rx: UnboundedReceiver<Outgoing>) {
+    let mut conn: Option<ChatConnection> = None;
+    loop {
+        let event = match conn.as_mut() {
+            Some(c) => tokio::select! {
+                message = rx.recv() => ChatEvent::Send(message),
+                incoming = c.ws.next() => ChatEvent::Incoming(incoming),
+            },
+            None => ChatEvent::Send(rx.recv().await),
+        };
+        match event {
+            ChatEvent::Send(None) => break,
+            ChatEvent::Send(Some(message)) => {
+                if !conn.as_ref().map(|c| c.is_for(&message)).unwrap_or(false) {
+                    conn = None;
+                }
+                // Обрыв замечаем только на отправке — одна повторная попытка с новым соединением
+                for attempt in 0..2 {
+                    if conn.is_none() {
+                        match ChatConnection::connect(&message.login, &message.channel).await {
+                            Ok(c) => conn = Some(c),
+                            Err(e) => {
+                                warn!("Twitch announcement dropped: {}", e);
+                                break;
+                            }
+                        }
+                    }
+                    let Some(c) = conn.as_mut() else { break };
+                    match c.privmsg(&message.text).await {
+                        Ok(()) => {
+                            debug!("Twitch message sent: {}", message.text);
+                            break;
+                        }
+                        Err(e) => {
+                            debug!("Twitch send failed (attempt {}): {}", attempt + 1, e);
+                            conn = None;
+                        }
+                    }
+                }
+            }
+            ChatEvent::Incoming(Some(Ok(Message::Text(text)))) => {
+                if let Some(c) = conn.as_mut() {
+                    if let Err(e) = c.handle_incoming(&text).await {
+                        debug!("Twitch connection lost: {}", e);
+                        conn = None;
+                    }
+                }
+            }
+            ChatEvent::Incoming(Some(Ok(Message::Close(_)))) | ChatEvent::Incoming(None) => {
+                debug!("Twitch closed the chat connection");
+                conn = None;
+            }
+            ChatEvent::Incoming(Some(Err(e))) => {
+                debug!("Twitch connection error: {}", e);
+                conn = None;
+            }
+            ChatEvent::Incoming(Some(Ok(_))) => {}
+        }
+    }
+}
+
+/// Отправить тестовое сообщение отдельным соединением и дождаться результата
+pub async fn send_test_message(settings: &TwitchSettings, text: String) -> Result<(), String> {
+    let message = outgoing(settings, text).ok_or("Twitch channel is not set")?;
+    let mut conn = ChatConnection::connect(&message.login, &message.channel).await?;
+    let result = conn.privmsg(&message.text).await;
+    let _ = conn.ws.close(None).await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_templates_and_targets() {
+        let settings = TwitchSettings {
+            channel: "#StreamerName".into(),
+            drop_template: "{item} x{quantity} — {value} FE\n!".into(),
+            ..Default::default()
+        };
+        assert_eq!(drop_message(&settings, "Flame Elementium", 3, 1250.4), "Flame Elementium x3 — 1250 FE !");
+        assert_eq!(render("{unknown} ok", &[]), "{unknown} ok");
+        assert_eq!(render(&"a".repeat(600), &[]).chars().count(), MAX_MESSAGE_CHARS);
+
+        let target = outgoing(&settings, "hi".into()).unwrap();
+        assert_eq!((target.channel.as_str(), target.login.as_str()), ("streamername", "streamername"));
+        let bot = TwitchSettings { login: "FarmBot".into(), ..settings.clone() };
+        assert_eq!(outgoing(&bot, "hi".into()).unwrap().login, "farmbot");
+        assert!(outgoing(&TwitchSettings { channel: " ".into(), ..settings.clone() }, "hi".into()).is_none());
+
+        let summary = TwitchSettings { summary_template: "{profit} / {per_hour}".into(), ..settings };
+        let stats = SessionStats { duration_sec: 1800, hourly_profit: 9999.0, ..Default::default() };
+        assert_eq!(summary_message(&summary, &stats, 1500.0, &Valuation::default()), "1500 / 3000");
+    }
+}
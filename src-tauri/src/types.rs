@@ -5,6 +5,20 @@
 
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// ID основного лог-файла (клиента игры)
+pub const PRIMARY_LOG_SOURCE: &str = "main";
+
+/// Flame Elementium — базовая валюта (если в кэше предметов она не отмечена)
+pub const BASE_CURRENCY_ID: i64 = 100300;
+
+/// ID дополнительного лог-файла по его индексу в настройках (alt1, alt2, ...)
+pub fn extra_log_source_id(index: usize) -> String {
+    format!("alt{}", index + 1)
+}
+
+fn default_log_source() -> String { PRIMARY_LOG_SOURCE.to_string() }
 
 /// Событие подбора предмета из логов
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +33,43 @@ pub struct ItemDropEvent {
     pub page_id: i32,
     /// ID слота
     pub slot_id: i32,
+    /// Количество в слоте после изменения (для отсечения повторных строк)
+    #[serde(default)]
+    pub slot_quantity: i32,
+    /// Источник (лог-файл клиента), из которого пришло событие
+    #[serde(default = "default_log_source")]
+    pub source_id: String,
+    /// Тир экипировки (None — предмет без тира или строка лога его не содержит)
+    #[serde(default)]
+    pub tier: Option<i32>,
+    /// Редкость/качество экипировки
+    #[serde(default)]
+    pub rarity: Option<i32>,
+    /// Имя предмета из строки лога (если клиент его пишет)
+    #[serde(default)]
+    pub item_name: Option<String>,
+}
+
+/// Уменьшение стака вне подбора (использование, продажа, перекладывание)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemConsumedEvent {
+    pub game_id: i64,
+    /// На сколько уменьшился стак
+    pub quantity: i32,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default = "default_log_source")]
+    pub source_id: String,
+}
+
+/// Рост слота вне подбора (содержимое открытого контейнера, перекладывание)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemGainedEvent {
+    pub game_id: i64,
+    /// На сколько вырос слот
+    pub quantity: i32,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default = "default_log_source")]
+    pub source_id: String,
 }
 
 /// Событие оценки цены на аукционе
@@ -34,6 +85,9 @@ pub struct PriceSearchEvent {
     pub timestamp: DateTime<Utc>,
     /// Sync ID запроса
     pub sync_id: i32,
+    /// Источник (лог-файл клиента), из которого пришло событие
+    #[serde(default = "default_log_source")]
+    pub source_id: String,
 }
 
 /// Событие входа/выхода с карты
@@ -43,8 +97,37 @@ pub struct MapChangeEvent {
     pub event_type: MapEventType,
     /// Название сцены
     pub scene_name: String,
+    /// Тип зоны, в которую перешли
+    #[serde(default)]
+    pub zone: ZoneKind,
     /// Временная метка
     pub timestamp: DateTime<Utc>,
+    /// Источник (лог-файл клиента), из которого пришло событие
+    #[serde(default = "default_log_source")]
+    pub source_id: String,
+}
+
+/// Предмет в слоте инвентаря
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventorySlot {
+    pub page_id: i32,
+    pub slot_id: i32,
+    /// ID предмета из игры (ConfigBaseId)
+    pub game_id: i64,
+    pub quantity: i32,
+}
+
+/// Полный снимок инвентаря: пачка строк BagMgr@:InitBagData, которую игра пишет
+/// при входе в мир (содержимое всех страниц, включая склад)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventorySnapshotEvent {
+    /// Временная метка первой строки пачки
+    pub timestamp: DateTime<Utc>,
+    /// Непустые слоты
+    pub slots: Vec<InventorySlot>,
+    /// Источник (лог-файл клиента), из которого пришло событие
+    #[serde(default = "default_log_source")]
+    pub source_id: String,
 }
 
 /// Тип события карты
@@ -56,11 +139,45 @@ pub enum MapEventType {
     ExitToHideout,
 }
 
-/// Информация о предмете для отображения
+/// Причина паузы сессии
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseReason {
+    /// Пауза из UI
+    Manual,
+    /// Автопауза: процесс игры закрылся
+    GameClosed,
+    /// Автопауза: в логе нет ни дропа, ни смены карты дольше AppSettings.afk_pause_minutes
+    Afk,
+}
+
+/// Пауза сессии (история пауз)
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PauseRecord {
+    pub paused_at: DateTime<Utc>,
+    /// None — пауза ещё идёт
+    pub resumed_at: Option<DateTime<Utc>>,
+    pub reason: PauseReason,
+}
+
+/// Классификация сцены по паттернам лога
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ZoneKind {
+    /// Убежище
+    Hideout,
+    /// Карта для фарма (старые события без зоны считаем картами, как и раньше)
+    #[default]
+    FarmingMap,
+    /// Город, кампания, испытания и прочее
+    Other,
+}
+
+/// Информация о предмете для отображения
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ItemInfo {
     pub game_id: i64,
-    /// Primary display name (English for now)
+    /// Primary display name (локализуется по AppSettings.language, см. `localized`)
     pub name: String,
     pub name_en: Option<String>,
     pub name_ru: Option<String>,
@@ -72,6 +189,34 @@ pub struct ItemInfo {
     pub is_base_currency: bool,
 }
 
+impl ItemInfo {
+    /// Имя предмета на языке отображения ("ru", "en", "zh"; см. normalize_language).
+    /// Если перевода нет — английское имя, затем исходное `name`.
+    pub fn localized_name(&self, language: &str) -> &str {
+        let preferred = match language {
+            "ru" => self.name_ru.as_deref(),
+            "zh" => self.name_cn.as_deref(),
+            _ => self.name_en.as_deref(),
+        };
+        preferred
+            .filter(|n| !n.trim().is_empty())
+            .or_else(|| self.name_en.as_deref().filter(|n| !n.trim().is_empty()))
+            .unwrap_or(&self.name)
+    }
+
+    /// Копия с локализованным основным именем (`name`)
+    pub fn localized(&self, language: &str) -> ItemInfo {
+        let mut item = self.clone();
+        item.name = self.localized_name(language).to_string();
+        item
+    }
+
+    /// Пользовательский предмет (нет в tli_game_items): такие получают отрицательный game_id
+    pub fn is_custom(&self) -> bool {
+        self.game_id < 0
+    }
+}
+
 /// Состояние текущей сессии фарма
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct FarmSessionState {
@@ -94,21 +239,450 @@ pub struct FarmSessionState {
     /// Сцена последнего события (NextSceneName)
     pub last_map_scene: Option<String>,
     /// Дропы за сессию: game_id -> количество
-    pub drops: std::collections::HashMap<i64, i32>,
+    pub drops: HashMap<i64, i32>,
     /// ID предустановки (если выбрана)
     pub preset_id: Option<String>,
     /// Сессия на паузе
     #[serde(default)]
     pub is_paused: bool,
+    /// Почему сессия на паузе (None — не на паузе)
+    #[serde(default)]
+    pub pause_reason: Option<PauseReason>,
     /// Траты за сессию (ручной ввод, пресет)
     #[serde(default)]
     pub expenses: Vec<ExpenseEntry>,
     /// Ручной дроп за сессию (для уников/экипировки)
     #[serde(default)]
     pub manual_drops: Vec<ManualDropEntry>,
-    /// Общее время сессии в секундах (обновляется фронтендом)
+    /// Накопленное время сессии в секундах (секундомер ведёт backend, без учёта пауз)
     #[serde(default)]
     pub session_duration_sec: i32,
+    /// Момент, с которого секундомер идёт и ещё не учтён в session_duration_sec
+    /// (None — сессия на паузе)
+    #[serde(default)]
+    pub clock_resumed_at: Option<DateTime<Utc>>,
+    /// Подсессии по источникам логов (source_id -> дропы/карты клиента)
+    #[serde(default)]
+    pub sources: HashMap<String, SourceSession>,
+    /// Цели сессии (из шаблона)
+    #[serde(default)]
+    pub goals: SessionGoals,
+    /// Предметы, дроп которых не учитывается в этой сессии
+    #[serde(default)]
+    pub ignored_item_ids: Vec<i64>,
+    /// Ручные корректировки количества дропа (аудит)
+    #[serde(default)]
+    pub adjustments: Vec<QuantityAdjustment>,
+    /// Сколько FE было на руках при старте сессии
+    #[serde(default)]
+    pub currency_start: Option<CurrencyReading>,
+    /// Сколько FE на руках в конце (задаётся вручную; иначе снимается из лога при завершении)
+    #[serde(default)]
+    pub currency_end: Option<CurrencyReading>,
+    /// Сцена текущей карты (NextSceneName при входе)
+    #[serde(default)]
+    pub current_map_scene: Option<String>,
+    /// Завершённые карты сессии (по основному клиенту)
+    #[serde(default)]
+    pub map_runs: Vec<MapRun>,
+    /// Дроп текущей карты: game_id -> количество
+    #[serde(default)]
+    pub current_map_drops: HashMap<i64, i32>,
+    /// Тиры дропа текущей карты: game_id -> (тир -> количество)
+    #[serde(default)]
+    pub current_map_tiers: HashMap<i64, HashMap<i32, i32>>,
+    /// Будильник по чистому профиту
+    #[serde(default)]
+    pub profit_alarm: Option<ProfitAlarm>,
+    /// Время последнего дропа предмета: game_id -> метка из лога (для сортировки «недавние»)
+    #[serde(default)]
+    pub last_drop_at: HashMap<i64, DateTime<Utc>>,
+    /// Стоимость сессии во времени (точка раз в минуту времени сессии)
+    #[serde(default)]
+    pub value_timeline: Vec<ValuePoint>,
+    /// Паузы сессии (старые первыми)
+    #[serde(default)]
+    pub pause_history: Vec<PauseRecord>,
+    /// Часть дропа сессии с известным тиром: game_id -> (тир -> количество).
+    /// Остаток drops сверх суммы тиров — предметы без тира
+    #[serde(default)]
+    pub drop_tiers: HashMap<i64, HashMap<i32, i32>>,
+    /// Приложение работает с этой сессией; при штатном выходе сбрасывается.
+    /// Если при запуске флаг ещё стоит — прошлый запуск оборвался (падение, снятие процесса)
+    #[serde(default)]
+    pub app_running: bool,
+    /// Сессия восстановлена после аварийного выхода — дроп и время могут быть недосчитаны
+    #[serde(default)]
+    pub recovered: bool,
+    /// Сколько секунд приложение не работало при аварийных выходах (сумма)
+    #[serde(default)]
+    pub recovery_gap_sec: i64,
+    /// Метки из лога хранятся в настоящем UTC (с учётом пояса лога). У сессий, сохранённых
+    /// до поддержки поясов, false: их метки — местное время лога, прочитанное как UTC
+    #[serde(default)]
+    pub log_times_utc: bool,
+    /// Последний признак жизни приложения (пишется раз в SESSION_HEARTBEAT_SEC):
+    /// от него считается пропуск после сбоя, а не от времени изменения файла
+    #[serde(default)]
+    pub heartbeat_at: Option<DateTime<Utc>>,
+    /// Брошенные карты (дольше лимита) — учитываются отдельно от завершённых
+    #[serde(default)]
+    pub abandoned_maps: i32,
+    /// Суммарное время брошенных карт (сек)
+    #[serde(default)]
+    pub abandoned_map_sec: i32,
+}
+
+impl FarmSessionState {
+    /// Пересчитать все метки, пришедшие из лога (карты, последние дропы)
+    pub fn map_log_times(&mut self, f: impl Fn(DateTime<Utc>) -> DateTime<Utc>) {
+        self.current_map_started = self.current_map_started.map(&f);
+        self.last_map_event_ts = self.last_map_event_ts.map(&f);
+        for run in &mut self.map_runs {
+            run.started_at = f(run.started_at);
+            run.ended_at = f(run.ended_at);
+        }
+        for ts in self.last_drop_at.values_mut() {
+            *ts = f(*ts);
+        }
+        for source in self.sources.values_mut() {
+            source.last_drop_ts = source.last_drop_ts.map(&f);
+        }
+    }
+
+    /// Ручной дроп: (продано по фактическим ценам, ждёт продажи по оценке)
+    pub fn manual_sales(&self) -> (f64, f64) {
+        self.manual_drops.iter().fold((0.0, 0.0), |(realized, pending), m| match &m.sold {
+            Some(sale) => (realized + sale.actual_price * m.quantity as f64, pending),
+            None => (realized, pending + m.price * m.quantity as f64),
+        })
+    }
+
+    /// Непроданный ручной дроп, введённый в торговых валютах: сумма по каждой валюте
+    pub fn pending_quoted(&self) -> Vec<QuotedPrice> {
+        let mut totals: Vec<QuotedPrice> = Vec::new();
+        for (quoted, quantity) in self.manual_drops.iter().filter(|m| m.sold.is_none()).filter_map(|m| Some((m.quoted?, m.quantity))) {
+            let amount = quoted.amount * quantity as f64;
+            match totals.iter_mut().find(|t| t.currency_id == quoted.currency_id) {
+                Some(total) => total.amount += amount,
+                None => totals.push(QuotedPrice { currency_id: quoted.currency_id, amount }),
+            }
+        }
+        totals
+    }
+
+    /// Записать постановку на паузу или снятие с неё в историю пауз
+    pub fn record_pause(&mut self, paused: bool, reason: PauseReason, now: DateTime<Utc>) {
+        let open = self.pause_history.last_mut().filter(|p| p.resumed_at.is_none());
+        match (paused, open) {
+            (true, None) => self.pause_history.push(PauseRecord { paused_at: now, resumed_at: None, reason }),
+            (false, Some(pause)) => pause.resumed_at = Some(now),
+            _ => {}
+        }
+    }
+
+    /// Сколько секунд сессия провела на паузе (идущая пауза — по `now`)
+    pub fn paused_sec(&self, now: DateTime<Utc>) -> i32 {
+        self.pause_history
+            .iter()
+            .map(|p| (p.resumed_at.unwrap_or(now) - p.paused_at).num_seconds().max(0) as i32)
+            .sum()
+    }
+}
+
+/// Точка кривой стоимости сессии
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValuePoint {
+    pub at: DateTime<Utc>,
+    /// Время сессии на момент точки (без пауз)
+    pub duration_sec: i32,
+    /// Стоимость дропа по ценам на момент точки
+    pub total_value: f64,
+    /// Стоимость за вычетом трат
+    pub net_profit: f64,
+}
+
+/// Будильник: сработать, когда чистый профит сессии дойдёт до порога
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfitAlarm {
+    /// Порог чистого профита (FE)
+    pub threshold: f64,
+    /// Когда сработал (None — ещё не срабатывал)
+    #[serde(default)]
+    pub fired_at: Option<DateTime<Utc>>,
+}
+
+/// Сработал будильник профита (событие для UI)
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfitAlarmEvent {
+    pub threshold: f64,
+    pub net_profit: f64,
+}
+
+/// Завершённая карта активной сессии
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapRun {
+    /// Порядковый номер карты в сессии (с 1)
+    pub index: i32,
+    /// Сцена карты (None — вход на карту не был пойман)
+    pub scene_name: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub duration_sec: i64,
+    /// Дроп за карту: game_id -> количество
+    #[serde(default)]
+    pub drops: HashMap<i64, i32>,
+    /// Часть дропа карты с известным тиром: game_id -> (тир -> количество)
+    #[serde(default)]
+    pub tiers: HashMap<i64, HashMap<i32, i32>>,
+    /// Стоимость дропа за карту по ценам на момент выхода (None — ещё не оценена)
+    pub value: Option<f64>,
+    /// Карта дольше AppSettings.abandoned_map_minutes (умер и вышел из игры, ушёл AFK):
+    /// не входит в maps_completed и среднее время карты
+    #[serde(default)]
+    pub abandoned: bool,
+}
+
+/// Личный рекорд по карте
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapRecord {
+    pub value: f64,
+    pub duration_sec: i64,
+    pub scene_name: Option<String>,
+    pub achieved_at: DateTime<Utc>,
+    /// Начало сессии, в которой поставлен рекорд
+    pub session_started_at: Option<DateTime<Utc>>,
+}
+
+/// Личные рекорды по картам за все сессии
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PersonalRecords {
+    /// Самая дорогая карта
+    pub best_map_value: Option<MapRecord>,
+    /// Самая быстрая карта
+    pub fastest_map: Option<MapRecord>,
+}
+
+/// Вид рекорда
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordKind {
+    BestMapValue,
+    FastestMap,
+}
+
+/// Побит личный рекорд (событие для UI)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordBrokenEvent {
+    pub kind: RecordKind,
+    pub record: MapRecord,
+    pub previous: Option<MapRecord>,
+}
+
+/// Откуда взято количество валюты
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CurrencyReadingSource {
+    /// Сумма слотов с FE по строкам BagMgr в логе
+    Log,
+    /// Введено пользователем
+    Manual,
+}
+
+/// Количество базовой валюты на руках в момент времени
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurrencyReading {
+    pub amount: i64,
+    pub source: CurrencyReadingSource,
+    pub observed_at: DateTime<Utc>,
+}
+
+/// Валюта активной сессии: сколько было, сколько сейчас и сколько заработано «живыми» FE
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionCurrency {
+    pub start: Option<CurrencyReading>,
+    /// Ручное значение конца, иначе текущее по логу
+    pub current: Option<CurrencyReading>,
+    /// current - start (None, пока нет обоих значений)
+    pub gained: Option<i64>,
+}
+
+/// Точка сессии для ручного ввода валюты
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CurrencyPoint {
+    Start,
+    End,
+}
+
+/// Ручная корректировка количества предмета в дропе сессии
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuantityAdjustment {
+    pub game_id: i64,
+    /// Запрошенное изменение (может быть отрицательным)
+    pub delta: i32,
+    /// Причина корректировки (например, "parser double-count")
+    pub reason: String,
+    pub previous_quantity: i32,
+    pub new_quantity: i32,
+    pub adjusted_at: DateTime<Utc>,
+}
+
+/// Цели сессии фарма
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SessionGoals {
+    /// Сколько карт пройти
+    #[serde(default)]
+    pub target_maps: Option<i32>,
+    /// Сколько FE заработать (чистыми)
+    #[serde(default)]
+    pub target_profit: Option<f64>,
+    /// Сколько минут фармить
+    #[serde(default)]
+    pub target_duration_min: Option<i32>,
+}
+
+/// Шаблон сессии: пресет, траты, цели и игнор-лист, применяемые при старте
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTemplate {
+    /// Уникальный ID шаблона (пустой — будет сгенерирован при сохранении)
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub preset_id: Option<String>,
+    #[serde(default)]
+    pub expenses: Vec<ExpenseEntry>,
+    #[serde(default)]
+    pub goals: SessionGoals,
+    #[serde(default)]
+    pub ignored_item_ids: Vec<i64>,
+}
+
+/// Запланированный блок фарма
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FarmBlock {
+    /// Уникальный ID блока (пустой — будет сгенерирован при сохранении)
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub starts_at: DateTime<Utc>,
+    pub duration_min: u32,
+    /// Шаблон сессии для автостарта (приоритетнее preset_id)
+    #[serde(default)]
+    pub template_id: Option<String>,
+    #[serde(default)]
+    pub preset_id: Option<String>,
+    /// За сколько минут до начала напомнить (0 — в момент начала)
+    #[serde(default = "default_remind_before_min")]
+    pub remind_before_min: u32,
+    /// Начать сессию автоматически в момент начала блока
+    #[serde(default)]
+    pub auto_start: bool,
+    /// Напоминание уже отправлено
+    #[serde(default)]
+    pub reminded: bool,
+    /// Блок уже начался (автостарт выполнен или пропущен)
+    #[serde(default)]
+    pub started: bool,
+}
+
+fn default_remind_before_min() -> u32 { 5 }
+
+impl FarmBlock {
+    pub fn ends_at(&self) -> DateTime<Utc> {
+        self.starts_at + chrono::Duration::minutes(self.duration_min as i64)
+    }
+}
+
+/// Напоминание о блоке фарма (событие для UI)
+#[derive(Debug, Clone, Serialize)]
+pub struct FarmBlockReminder {
+    pub block: FarmBlock,
+    /// Сколько минут до начала (0 — блок начинается сейчас)
+    pub minutes_until: i64,
+    /// Сессия запущена автоматически
+    pub auto_started: bool,
+}
+
+/// Компонент рецепта крафта
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeInput {
+    pub game_id: i64,
+    pub quantity: i32,
+}
+
+/// Рецепт крафта: компоненты -> результат (локальное хранилище recipes.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recipe {
+    /// Уникальный ID рецепта (пустой — будет сгенерирован при сохранении)
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub inputs: Vec<RecipeInput>,
+    pub output_game_id: i64,
+    #[serde(default = "default_output_quantity")]
+    pub output_quantity: i32,
+    /// Шанс успеха (0..1): ожидаемый результат = стоимость x шанс
+    #[serde(default = "default_success_chance")]
+    pub success_chance: f64,
+}
+
+fn default_output_quantity() -> i32 { 1 }
+fn default_success_chance() -> f64 { 1.0 }
+
+/// Позиция рецепта с ценой
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeLine {
+    pub game_id: i64,
+    pub item_info: Option<ItemInfo>,
+    pub quantity: i32,
+    /// None — цены нет, позиция посчитана как 0
+    pub unit_price: Option<f64>,
+    pub value: f64,
+}
+
+/// Оценка рецепта по текущим ценам
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeEvaluation {
+    pub recipe_id: String,
+    pub inputs: Vec<RecipeLine>,
+    pub output: RecipeLine,
+    pub input_cost: f64,
+    /// Ожидаемая стоимость результата с учётом шанса успеха
+    pub expected_output_value: f64,
+    /// expected_output_value - input_cost
+    pub expected_profit: f64,
+    /// Предметы без цены: результат оценки неточен
+    pub missing_prices: Vec<i64>,
+}
+
+/// Подсессия одного клиента игры (при мультибоксе)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SourceSession {
+    /// Дропы клиента: game_id -> количество
+    pub drops: HashMap<i64, i32>,
+    /// Завершённые карты клиента
+    pub maps_completed: i32,
+    /// Последнее событие карты клиента (для подсчёта Enter -> Exit)
+    pub last_map_event_type: Option<MapEventType>,
+    /// Метка времени последнего засчитанного дропа: строки старше — повторное чтение лога
+    #[serde(default)]
+    pub last_drop_ts: Option<DateTime<Utc>>,
+}
+
+/// Статистика подсессии клиента для UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceSessionStats {
+    pub source_id: String,
+    /// Путь к лог-файлу клиента (если watcher запущен)
+    pub log_path: Option<String>,
+    pub total_items: i32,
+    pub unique_items: i32,
+    pub total_value: f64,
+    pub maps_completed: i32,
 }
 
 /// Запись о расходе (ручной ввод)
@@ -128,6 +702,16 @@ pub struct ExpenseEntry {
     pub quantity: i32,
     /// Цена за единицу (FE)
     pub price: f64,
+    /// Цена в другой торговой валюте, как её ввели (price — пересчёт в FE)
+    #[serde(default)]
+    pub quoted: Option<QuotedPrice>,
+}
+
+/// Цена, введённая не в FE: сумма за единицу в валюте currency_id
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuotedPrice {
+    pub currency_id: i64,
+    pub amount: f64,
 }
 
 /// Ручной дроп (для уников/экипировки)
@@ -147,12 +731,58 @@ pub struct ManualDropEntry {
     pub quantity: i32,
     /// Цена продажи (FE)
     pub price: f64,
+    /// Тир экипировки (если указан)
+    #[serde(default)]
+    pub tier: Option<i32>,
+    /// Продажа состоялась (None — ещё не продано, price — лишь оценка)
+    #[serde(default)]
+    pub sold: Option<ManualDropSale>,
+    /// Цена в другой торговой валюте, как её ввели (price — пересчёт в FE)
+    #[serde(default)]
+    pub quoted: Option<QuotedPrice>,
+}
+
+/// Фактическая продажа ручного дропа
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManualDropSale {
+    /// Фактическая цена за штуку (FE)
+    pub actual_price: f64,
+    pub sold_at: DateTime<Utc>,
+}
+
+/// Где цена была введена вручную
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ManualPriceKind {
+    ManualDrop,
+    Expense,
+}
+
+/// Последняя цена предмета, введённая вручную (ручной дроп или трата)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastManualPrice {
+    pub price: f64,
+    pub used_at: DateTime<Utc>,
+    pub kind: ManualPriceKind,
+}
+
+/// Подсказка цены для формы ручного ввода
+#[derive(Debug, Clone, Serialize)]
+pub struct PriceSuggestion {
+    pub game_id: i64,
+    /// Последняя введённая вручную цена (приоритетнее рыночной)
+    pub last_manual: Option<LastManualPrice>,
+    /// Цена из кэша цен (прайсчек/сервер)
+    pub market_price: Option<f64>,
 }
 
 /// Агрегированный дроп для отображения
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AggregatedDrop {
     pub game_id: i64,
+    /// Тир экипировки (None — предметы без тира; у одного game_id может быть несколько строк)
+    #[serde(default)]
+    pub tier: Option<i32>,
     pub item_info: Option<ItemInfo>,
     pub quantity: i32,
     pub total_value: f64,
@@ -166,20 +796,220 @@ pub struct AggregatedDrop {
     pub is_previous_season: bool,
     /// Название лиги откуда цена (SS10, SS11, etc)
     pub league_name: Option<String>,
+    /// Уверенность в цене (None — цены нет)
+    pub price_confidence: Option<crate::price_confidence::PriceConfidence>,
+    /// Количество корректировалось вручную
+    pub is_adjusted: bool,
+    /// Суммарная ручная поправка количества
+    pub adjusted_delta: i32,
+    /// Когда предмет падал последний раз (None — только ручные правки)
+    #[serde(default)]
+    pub last_dropped_at: Option<DateTime<Utc>>,
+    /// Ставка комиссии при продаже (по категории предмета)
+    #[serde(default)]
+    pub fee_rate: f64,
 }
 
-/// Настройки приложения
+/// Поле сортировки дропа
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DropsSortKey {
+    #[default]
+    Value,
+    Quantity,
+    Recent,
+    Name,
+}
+
+/// Направление сортировки
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    #[default]
+    Desc,
+    Asc,
+}
+
+/// Параметры выборки дропа: сортировка, фильтр по категории, страница
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DropsQuery {
+    #[serde(default)]
+    pub sort: DropsSortKey,
+    #[serde(default)]
+    pub direction: SortDirection,
+    /// Только предметы этой категории (без учёта регистра) или группы категорий
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub offset: usize,
+    /// None — до конца списка
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Страница дропа
+#[derive(Debug, Clone, Serialize)]
+pub struct DropsPage {
+    pub drops: Vec<AggregatedDrop>,
+    /// Сколько строк всего после фильтра
+    pub total: usize,
+    pub offset: usize,
+}
+
+impl DropsQuery {
+    /// Отфильтровать, отсортировать и вырезать страницу. При равенстве — по game_id
+    pub fn apply(&self, mut drops: Vec<AggregatedDrop>, catalog: &crate::categories::CategoryCatalog) -> DropsPage {
+        if let Some(category) = self.category.as_deref().filter(|c| !c.is_empty()) {
+            drops.retain(|d| {
+                d.item_info.as_ref().map(|i| catalog.matches_filter(&i.category, category)).unwrap_or(false)
+            });
+        }
+
+        let name = |d: &AggregatedDrop| {
+            d.item_info.as_ref().map(|i| i.name.to_lowercase()).unwrap_or_else(|| d.game_id.to_string())
+        };
+        drops.sort_by(|a, b| {
+            let ordering = match self.sort {
+                DropsSortKey::Value => a.total_value.partial_cmp(&b.total_value).unwrap_or(std::cmp::Ordering::Equal),
+                DropsSortKey::Quantity => a.quantity.cmp(&b.quantity),
+                DropsSortKey::Recent => a.last_dropped_at.cmp(&b.last_dropped_at),
+                DropsSortKey::Name => name(a).cmp(&name(b)),
+            };
+            let ordering = match self.direction {
+                SortDirection::Asc => ordering,
+                SortDirection::Desc => ordering.reverse(),
+            };
+            ordering.then(a.game_id.cmp(&b.game_id))
+        });
+
+        let total = drops.len();
+        let offset = self.offset.min(total);
+        let end = self.limit.map(|l| offset.saturating_add(l).min(total)).unwrap_or(total);
+        DropsPage {
+            drops: drops.drain(offset..end).collect(),
+            total,
+            offset,
+        }
+    }
+}
+
+/// Стоимость категории предметов в снимке инвентаря
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AppSettings {
-    /// Путь к файлу логов (если пользователь указал вручную)
-    pub custom_log_path: Option<String>,
-    /// Автозапуск при старте Windows
+pub struct NetWorthCategory {
+    pub category: String,
+    /// Имя категории на языке отображения
     #[serde(default)]
-    pub auto_start: bool,
-    /// Показывать в трее
-    #[serde(default = "default_true")]
-    pub minimize_to_tray: bool,
-    /// Язык интерфейса (ru/en)
+    pub name: String,
+    /// Верхняя группа категории (см. categories)
+    #[serde(default)]
+    pub group: String,
+    pub value: f64,
+    /// Штук предметов категории
+    pub item_count: i32,
+    /// Разных предметов категории без цены (не вошли в стоимость)
+    pub unpriced_count: i32,
+}
+
+/// Оценка состояния по последнему снимку инвентаря
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetWorth {
+    /// Когда снят снимок (время из лога)
+    pub snapshot_at: DateTime<Utc>,
+    pub source_id: String,
+    pub total_value: f64,
+    /// По убыванию стоимости
+    pub categories: Vec<NetWorthCategory>,
+    /// Разных предметов без цены (нужен прайсчек)
+    pub unpriced_count: i32,
+    /// Разных предметов с устаревшей ценой
+    pub stale_count: i32,
+}
+
+/// Партия предметов, отложенных на продажу (портфель)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Holding {
+    pub id: String,
+    pub game_id: i64,
+    pub quantity: i32,
+    /// Цена за единицу на момент добавления (FE)
+    pub acquired_price: f64,
+    pub acquired_at: DateTime<Utc>,
+}
+
+/// Партия портфеля, переоценённая по текущим ценам
+#[derive(Debug, Clone, Serialize)]
+pub struct HoldingValue {
+    #[serde(flatten)]
+    pub holding: Holding,
+    pub item_info: Option<ItemInfo>,
+    /// Текущая цена за единицу (None — цены нет, партия не переоценена)
+    pub current_price: Option<f64>,
+    pub current_value: f64,
+    /// Стоимость партии по цене добавления
+    pub cost_basis: f64,
+    /// Нереализованная прибыль (current_value - cost_basis); 0 без текущей цены
+    pub unrealized_gain: f64,
+}
+
+/// Оценка портфеля
+#[derive(Debug, Clone, Serialize)]
+pub struct PortfolioValue {
+    /// По убыванию текущей стоимости
+    pub holdings: Vec<HoldingValue>,
+    /// Итоги — только по партиям с текущей ценой
+    pub total_value: f64,
+    pub total_cost: f64,
+    pub unrealized_gain: f64,
+    /// Партий без текущей цены
+    pub unpriced_count: i32,
+}
+
+/// Почему предмет стоит в очереди прайсчека
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PricecheckReason {
+    /// Цены нет вообще
+    Missing,
+    /// Цена из предыдущего сезона
+    PreviousSeason,
+    /// Цена старше TTL
+    Stale,
+}
+
+/// Предмет в очереди прайсчека
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PricecheckItem {
+    pub game_id: i64,
+    pub item_info: Option<ItemInfo>,
+    /// Сколько штук выпало за сессию
+    pub quantity: i32,
+    pub reason: PricecheckReason,
+    /// Последняя известная цена (если есть)
+    pub last_price: Option<f64>,
+    /// Пользователь отложил предмет (уходит в конец очереди)
+    pub skipped: bool,
+}
+
+/// Очередь прайсчека для пошагового обновления цен
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PricecheckQueue {
+    /// Текущий предмет, который нужно прочекать на аукционе
+    pub current: Option<i64>,
+    pub items: Vec<PricecheckItem>,
+}
+
+/// Настройки приложения
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppSettings {
+    /// Путь к файлу логов (если пользователь указал вручную)
+    pub custom_log_path: Option<String>,
+    /// Автозапуск при старте Windows
+    #[serde(default)]
+    pub auto_start: bool,
+    /// Показывать в трее
+    #[serde(default = "default_true")]
+    pub minimize_to_tray: bool,
+    /// Язык интерфейса (ru/en)
     #[serde(default = "default_language")]
     pub language: String,
     /// URL API сервера
@@ -194,21 +1024,565 @@ pub struct AppSettings {
     /// Ставка комиссии аукциона (0.0 - 1.0), по умолчанию 0.125 (12.5%)
     #[serde(default = "default_auction_fee")]
     pub auction_fee_rate: f64,
+    /// Своя ставка комиссии для категорий предметов (категория -> 0.0 - 1.0)
+    #[serde(default)]
+    pub category_fee_rates: HashMap<String, f64>,
+    /// Категории, которые продаются напрямую (без аукциона) — комиссия не берётся
+    #[serde(default)]
+    pub no_fee_categories: Vec<String>,
     /// Прозрачность окна (0.5 - 1.0)
     #[serde(default = "default_opacity")]
     pub opacity: f64,
     /// Всегда поверх окон
     #[serde(default = "default_true")]
     pub always_on_top: bool,
+    /// Механизм отслеживания UE_game.log (native события ФС или опрос)
+    #[serde(default)]
+    pub log_watch_mode: LogWatchMode,
+    /// Интервал опроса лог-файла (мс)
+    #[serde(default = "default_log_poll_interval_ms")]
+    pub log_poll_interval_ms: u64,
+    /// С какого места читать UE_game.log при подключении (с конца или с момента времени)
+    #[serde(default)]
+    pub log_attach_mode: LogAttachMode,
+    /// Момент, с которого разбирать лог в режиме Since (время из меток лога).
+    /// Разовый: после подключения режим возвращается к End
+    #[serde(default)]
+    pub log_attach_since: Option<DateTime<Utc>>,
+    /// Учитывать только фарм-зоны: дроп вне карты не считается, город/кампания — не карта
+    #[serde(default)]
+    pub farming_zones_only: bool,
+    /// Ставить сессию на паузу, когда игра закрыта, и снимать при запуске
+    #[serde(default = "default_true")]
+    pub auto_pause_on_game_exit: bool,
+    /// Автопауза, если столько минут в логе нет активности (0 — выключено)
+    #[serde(default)]
+    pub afk_pause_minutes: u32,
+    /// Карта дольше стольких минут считается брошенной (0 — не проверять)
+    #[serde(default = "default_abandoned_map_minutes")]
+    pub abandoned_map_minutes: u32,
+    /// Дополнительные UE_game.log (второй клиент при мультибоксе)
+    #[serde(default)]
+    pub extra_log_paths: Vec<String>,
+    /// Как сессия учитывает дополнительные клиенты
+    #[serde(default)]
+    pub log_source_mode: LogSourceMode,
+    /// Как переводить метки времени лога в UTC
+    #[serde(default)]
+    pub log_time_zone: LogTimeZone,
+    /// Смещение от UTC в минутах для LogTimeZone::Fixed (UTC+3 — 180), в пределах LOG_UTC_OFFSET_MINUTES
+    #[serde(default)]
+    pub log_utc_offset_minutes: i32,
+    /// Согласие на отправку метрик сессий в лидерборд
+    #[serde(default)]
+    pub leaderboard_opt_in: bool,
+    /// Окно-компаньон с живой статистикой (геометрия, прозрачность, видимость)
+    #[serde(default)]
+    pub companion_window: CompanionWindowSettings,
+    /// Канал обновлений (stable/beta)
+    #[serde(default)]
+    pub update_channel: UpdateChannel,
+    /// Клиент игры (глобальный/китайский): пути установки и вариант формата лога
+    #[serde(default)]
+    pub game_client: GameClient,
+    /// Точность и округление стоимостей (FE)
+    #[serde(default)]
+    pub valuation: ValuationSettings,
+    /// Выгружать завершённые сессии в облако (tli_farm_sessions)
+    #[serde(default)]
+    pub sync_sessions_to_cloud: bool,
+    /// Офлайн-режим: фоновые запросы к серверу (обновление цен) не выполняются
+    #[serde(default)]
+    pub offline_mode: bool,
+    /// Дописывать завершённые сессии строкой в Google-таблицу
+    #[serde(default)]
+    pub google_sheets: GoogleSheetsSettings,
+    /// Контейнеры (game_id), открытия которых собираются из лога для расчёта EV
+    #[serde(default)]
+    pub gamble_containers: Vec<i64>,
+    /// Отсоединённые панели (восстанавливаются при запуске)
+    #[serde(default)]
+    pub detached_panels: Vec<crate::panel_windows::DetachedPanel>,
+    /// Глобальный хоткей режима «сквозь клики» главного окна
+    #[serde(default = "default_click_through_hotkey")]
+    pub click_through_hotkey: String,
+    /// Прятать окна, когда фокус не у игры (только Windows)
+    #[serde(default)]
+    pub hide_when_game_unfocused: bool,
+    /// Уровень лога приложения (error/warn/info/debug/trace)
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    /// Лимиты кэшей предметов и цен
+    #[serde(default)]
+    pub cache_limits: CacheLimits,
+    /// Крупные валюты (game_id) для разложения сумм (см. value_format)
+    #[serde(default)]
+    pub denomination_items: Vec<i64>,
+    /// Regex «шумных» строк лога: отбрасываются watcher'ом до парсера
+    #[serde(default)]
+    pub log_noise_patterns: Vec<String>,
+    /// Лига, к которой относятся текущие история и цены (другая лига в ценах — пора архивировать)
+    #[serde(default)]
+    pub active_league: Option<String>,
+    /// Прокси для запросов к Supabase и прочим сервисам
+    #[serde(default)]
+    pub proxy: ProxySettings,
+    /// Свой сервер цен вместо встроенного Supabase (меняется только после проверки)
+    #[serde(default)]
+    pub backend: BackendEndpoint,
+    /// Звуки на дроп (играются backend'ом, даже когда окно свёрнуто)
+    #[serde(default)]
+    pub drop_sounds: DropSoundSettings,
+    /// Голосовые объявления ценных дропов
+    #[serde(default)]
+    pub tts: TtsSettings,
+    /// Локальный API для Stream Deck и других контроллеров
+    #[serde(default)]
+    pub controller_api: ControllerApiSettings,
+    /// Автоматизация OBS по событиям сессии (пароль — в keychain)
+    #[serde(default)]
+    pub obs: ObsSettings,
+    /// Объявления в чат Twitch (токен — в keychain)
+    #[serde(default)]
+    pub twitch: TwitchSettings,
+    /// Файл live_stats.json для сторонних инструментов
+    #[serde(default)]
+    pub live_stats: LiveStatsSettings,
+    /// Отправлять неизвестные предметы на сервер после каждой сессии (opt-in)
+    #[serde(default)]
+    pub report_unknown_items: bool,
+}
+
+/// Лимиты кэшей (см. cache_eviction)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CacheLimits {
+    /// Максимум предметов в items_cache
+    #[serde(default = "default_max_cached_items")]
+    pub max_items: usize,
+    /// Максимум цен в prices_cache
+    #[serde(default = "default_max_cached_prices")]
+    pub max_prices: usize,
+    /// Через сколько дней выбрасывать цены прошлых лиг (0 — не выбрасывать по возрасту)
+    #[serde(default = "default_price_ttl_days")]
+    pub price_ttl_days: u32,
+}
+
+fn default_max_cached_items() -> usize { 50_000 }
+fn default_max_cached_prices() -> usize { 20_000 }
+fn default_price_ttl_days() -> u32 { 90 }
+
+impl Default for CacheLimits {
+    fn default() -> Self {
+        Self {
+            max_items: default_max_cached_items(),
+            max_prices: default_max_cached_prices(),
+            price_ttl_days: default_price_ttl_days(),
+        }
+    }
+}
+
+/// Тип прокси
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyKind {
+    #[default]
+    Http,
+    Socks5,
+}
+
+/// Прокси для сетевых запросов. Пароль хранится в keychain, не здесь
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProxySettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub kind: ProxyKind,
+    #[serde(default)]
+    pub host: String,
+    #[serde(default)]
+    pub port: u16,
+    /// Логин (если прокси требует авторизацию)
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+/// Звуки на дроп: правила проверяются по порядку, играет первое подходящее
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DropSoundSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Общая громкость 0..1
+    #[serde(default = "default_sound_volume")]
+    pub volume: f32,
+    #[serde(default)]
+    pub rules: Vec<DropSoundRule>,
+}
+
+fn default_sound_volume() -> f32 { 0.7 }
+
+impl Default for DropSoundSettings {
+    fn default() -> Self {
+        Self { enabled: false, volume: default_sound_volume(), rules: Vec::new() }
+    }
+}
+
+/// Правило звука: условия (все заданные должны выполниться) и звук
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DropSoundRule {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Минимальная стоимость дропа (цена × количество) в базовой валюте
+    #[serde(default)]
+    pub min_value: Option<f64>,
+    /// Категория предмета (None — любая)
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Встроенный звук (`bundled:<имя>`) или путь к своему файлу (wav/ogg/mp3/flac)
+    pub sound: String,
+    /// Громкость правила 0..1 (умножается на общую)
+    #[serde(default)]
+    pub volume: Option<f32>,
+}
+
+/// Голосовые объявления: дроп дороже порога проговаривается на языке отображения
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TtsSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Порог стоимости дропа (цена × количество) в базовой валюте
+    #[serde(default = "default_tts_min_value")]
+    pub min_value: f64,
+    /// Не чаще одного объявления за столько секунд
+    #[serde(default = "default_tts_interval")]
+    pub min_interval_secs: u64,
+    /// Громкость 0..1
+    #[serde(default = "default_sound_volume")]
+    pub volume: f32,
+}
+
+fn default_tts_min_value() -> f64 { 1000.0 }
+fn default_tts_interval() -> u64 { 5 }
+
+impl Default for TtsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_value: default_tts_min_value(),
+            min_interval_secs: default_tts_interval(),
+            volume: default_sound_volume(),
+        }
+    }
+}
+
+/// Локальный API для контроллеров (только 127.0.0.1; токен — в keychain)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ControllerApiSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_controller_port")]
+    pub port: u16,
+}
+
+fn default_controller_port() -> u16 { 49740 }
+
+impl Default for ControllerApiSettings {
+    fn default() -> Self {
+        Self { enabled: false, port: default_controller_port() }
+    }
+}
+
+/// Снимок статистики в файл (live_stats.json)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LiveStatsSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Путь к файлу (None — live_stats.json в папке данных приложения)
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Как часто перезаписывать файл (сек)
+    #[serde(default = "default_live_stats_interval")]
+    pub interval_secs: u64,
+    /// Сколько самых ценных дропов класть в файл
+    #[serde(default = "default_live_stats_top_drops")]
+    pub top_drops: usize,
+}
+
+fn default_live_stats_interval() -> u64 { 2 }
+fn default_live_stats_top_drops() -> usize { 5 }
+
+impl Default for LiveStatsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            interval_secs: default_live_stats_interval(),
+            top_drops: default_live_stats_top_drops(),
+        }
+    }
+}
+
+/// Объявления в чат Twitch: крупные дропы и итог сессии
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TwitchSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Канал, в чат которого пишем
+    #[serde(default)]
+    pub channel: String,
+    /// Логин аккаунта-бота (пусто — пишем от имени канала)
+    #[serde(default)]
+    pub login: String,
+    /// Объявлять дропы дороже (FE)
+    #[serde(default = "default_twitch_drop_min_value")]
+    pub drop_min_value: f64,
+    /// Шаблон: {item}, {quantity}, {value}
+    #[serde(default = "default_twitch_drop_template")]
+    pub drop_template: String,
+    #[serde(default = "default_true")]
+    pub summary_enabled: bool,
+    /// Шаблон: {maps}, {duration}, {profit}, {per_hour}, {income}
+    #[serde(default = "default_twitch_summary_template")]
+    pub summary_template: String,
+    /// Объявления о дропах не чаще раза в N секунд
+    #[serde(default = "default_twitch_interval")]
+    pub min_interval_secs: u64,
+}
+
+fn default_twitch_drop_min_value() -> f64 { 5000.0 }
+fn default_twitch_drop_template() -> String { "{item} x{quantity} — {value} FE".to_string() }
+fn default_twitch_summary_template() -> String {
+    "GG! {maps} maps in {duration}, profit {profit} FE ({per_hour}/h)".to_string()
+}
+fn default_twitch_interval() -> u64 { 30 }
+
+impl Default for TwitchSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channel: String::new(),
+            login: String::new(),
+            drop_min_value: default_twitch_drop_min_value(),
+            drop_template: default_twitch_drop_template(),
+            summary_enabled: true,
+            summary_template: default_twitch_summary_template(),
+            min_interval_secs: default_twitch_interval(),
+        }
+    }
+}
+
+/// Подключение к obs-websocket и правила автоматизации
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ObsSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_obs_host")]
+    pub host: String,
+    #[serde(default = "default_obs_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub rules: Vec<ObsRule>,
+}
+
+fn default_obs_host() -> String { "127.0.0.1".to_string() }
+fn default_obs_port() -> u16 { 4455 }
+
+impl Default for ObsSettings {
+    fn default() -> Self {
+        Self { enabled: false, host: default_obs_host(), port: default_obs_port(), rules: Vec::new() }
+    }
+}
+
+/// Событие, на которое реагирует правило OBS
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ObsTrigger {
+    SessionStart,
+    SessionEnd,
+    /// Дроп дороже `min_value` правила
+    BigDrop,
+}
+
+/// Правило: событие (и порог для крупного дропа) → действие в OBS
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ObsRule {
+    #[serde(default)]
+    pub enabled: bool,
+    pub trigger: ObsTrigger,
+    /// Порог стоимости дропа (только для big_drop)
+    #[serde(default)]
+    pub min_value: Option<f64>,
+    pub action: ObsAction,
+}
+
+/// Действие в OBS
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ObsAction {
+    /// Переключить программную сцену
+    SwitchScene { scene: String },
+    /// Показать/скрыть источник в сцене (visible = None — переключить)
+    SetSourceVisible {
+        scene: String,
+        source: String,
+        #[serde(default)]
+        visible: Option<bool>,
+    },
+}
+
+/// Свой бэкенд (Supabase-совместимый): адрес, публичный ключ и префиксы имён таблиц/RPC
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackendEndpoint {
+    /// Выключен — встроенный сервер (или VITE_SUPABASE_* из окружения)
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub anon_key: String,
+    /// Префикс таблиц вместо `tli_`
+    #[serde(default = "default_table_prefix")]
+    pub table_prefix: String,
+    /// Префикс RPC-функций (у встроенного сервера пустой)
+    #[serde(default)]
+    pub rpc_prefix: String,
+}
+
+pub fn default_table_prefix() -> String { "tli_".to_string() }
+
+impl Default for BackendEndpoint {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            anon_key: String::new(),
+            table_prefix: default_table_prefix(),
+            rpc_prefix: String::new(),
+        }
+    }
+}
+
+/// Результат проверки своего бэкенда перед применением
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendCheckResult {
+    /// Проверка пройдена, адрес сохранён
+    pub applied: bool,
+    /// Проверка соединения (None — до неё не дошло)
+    pub connection: Option<crate::proxy::ConnectionTestResult>,
+    /// Совместимость схемы (None — до tli_client_meta не дошли)
+    pub handshake: Option<crate::schema_handshake::HandshakeStatus>,
+    pub error: Option<String>,
+}
+
+/// Google-таблица для сессий. Ключ доступа хранится в keychain, не здесь
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GoogleSheetsSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// ID таблицы (из URL: /spreadsheets/d/<ID>/edit)
+    #[serde(default)]
+    pub spreadsheet_id: String,
+    /// Лист, в конец которого дописываются строки
+    #[serde(default = "default_sheet_name")]
+    pub sheet_name: String,
+}
+
+fn default_sheet_name() -> String { "Sessions".to_string() }
+
+impl Default for GoogleSheetsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            spreadsheet_id: String::new(),
+            sheet_name: default_sheet_name(),
+        }
+    }
+}
+
+/// Учёт дропа с нескольких клиентов игры
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSourceMode {
+    /// Дропы всех клиентов суммируются в общую сессию
+    #[default]
+    Combined,
+    /// Общая сессия считает только основной клиент, остальные — отдельные подсессии
+    PerSource,
+}
+
+/// Клиент игры: глобальный (Steam) или китайский (火炬之光：无限)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GameClient {
+    /// Определить по пути к логу
+    #[default]
+    Auto,
+    Global,
+    Cn,
+}
+
+/// Механизм отслеживания лог-файла
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogWatchMode {
+    /// Нативные события файловой системы (с редким опросом как страховкой)
+    Native,
+    /// Периодический опрос файла. Работает на сетевых дисках и с антивирусами,
+    /// которые глушат события ФС.
+    #[default]
+    Poll,
+}
+
+/// Допустимое смещение пояса лога: от UTC-12 до UTC+14
+pub const LOG_UTC_OFFSET_MINUTES: std::ops::RangeInclusive<i32> = -12 * 60..=14 * 60;
+
+/// Часовой пояс меток времени в логе (игра пишет местное время без пояса)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogTimeZone {
+    /// Пояс системы (определяется автоматически, с переходами на летнее время)
+    #[default]
+    Local,
+    /// Метки уже в UTC
+    Utc,
+    /// Фиксированное смещение log_utc_offset_minutes (игра на другой машине / в другом поясе)
+    Fixed,
+}
+
+/// С какого места читать лог при подключении watcher'а
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogAttachMode {
+    /// С конца файла: история игнорируется (только baseline слотов из хвоста)
+    #[default]
+    End,
+    /// С первой строки не раньше log_attach_since — дропы с этого момента попадут в сессию
+    Since,
 }
 
 fn default_true() -> bool { true }
 fn default_language() -> String { "ru".to_string() }
+/// Языки отображения имён предметов (ru/en/zh); остальные значения трактуются как en
+pub const SUPPORTED_LANGUAGES: [&str; 3] = ["ru", "en", "zh"];
+
+/// Единственное место, где приводится язык из настроек: старый код китайского "cn" → "zh",
+/// неизвестное → "en". Вызывается при загрузке и при сохранении настроек
+pub fn normalize_language(language: &str) -> String {
+    let language = language.trim().to_ascii_lowercase();
+    match language.as_str() {
+        "cn" => "zh".to_string(),
+        l if SUPPORTED_LANGUAGES.contains(&l) => language,
+        _ => "en".to_string(),
+    }
+}
 fn default_api_url() -> String { "https://www.kripika.com".to_string() }
 fn default_orientation() -> String { "vertical".to_string() }
 fn default_panel_direction() -> String { "right".to_string() }
 fn default_auction_fee() -> f64 { 0.125 }
 fn default_opacity() -> f64 { 1.0 }
+fn default_log_level() -> String { "debug".to_string() }
+fn default_click_through_hotkey() -> String { "CommandOrControl+Shift+X".to_string() }
+fn default_log_poll_interval_ms() -> u64 { 100 }
+fn default_abandoned_map_minutes() -> u32 { 60 }
 
 impl Default for AppSettings {
     fn default() -> Self {
@@ -221,8 +1595,120 @@ impl Default for AppSettings {
             layout_orientation: "vertical".to_string(),
             panel_direction: "right".to_string(),
             auction_fee_rate: 0.125,
+            category_fee_rates: HashMap::new(),
+            no_fee_categories: Vec::new(),
             opacity: 1.0,
             always_on_top: true,
+            log_watch_mode: LogWatchMode::Poll,
+            log_poll_interval_ms: 100,
+            log_attach_mode: LogAttachMode::End,
+            log_attach_since: None,
+            farming_zones_only: false,
+            auto_pause_on_game_exit: true,
+            afk_pause_minutes: 0,
+            abandoned_map_minutes: default_abandoned_map_minutes(),
+            extra_log_paths: Vec::new(),
+            log_source_mode: LogSourceMode::Combined,
+            log_time_zone: LogTimeZone::Local,
+            log_utc_offset_minutes: 0,
+            leaderboard_opt_in: false,
+            companion_window: CompanionWindowSettings::default(),
+            update_channel: UpdateChannel::Stable,
+            game_client: GameClient::Auto,
+            valuation: ValuationSettings::default(),
+            sync_sessions_to_cloud: false,
+            offline_mode: false,
+            google_sheets: GoogleSheetsSettings::default(),
+            gamble_containers: Vec::new(),
+            detached_panels: Vec::new(),
+            click_through_hotkey: default_click_through_hotkey(),
+            hide_when_game_unfocused: false,
+            log_level: default_log_level(),
+            cache_limits: CacheLimits::default(),
+            denomination_items: Vec::new(),
+            log_noise_patterns: Vec::new(),
+            active_league: None,
+            proxy: ProxySettings::default(),
+            backend: BackendEndpoint::default(),
+            drop_sounds: DropSoundSettings::default(),
+            tts: TtsSettings::default(),
+            controller_api: ControllerApiSettings::default(),
+            obs: ObsSettings::default(),
+            twitch: TwitchSettings::default(),
+            live_stats: LiveStatsSettings::default(),
+            report_unknown_items: false,
+        }
+    }
+}
+
+/// Канал обновлений приложения
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    /// Только стабильные релизы
+    #[default]
+    Stable,
+    /// Пре-релизы (ранние фиксы парсера)
+    Beta,
+}
+
+/// Способ округления стоимостей
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Половина — от нуля (1.005 -> 1.01)
+    #[default]
+    HalfUp,
+    /// Банковское: половина — к чётному
+    HalfEven,
+    /// Отбросить лишние знаки
+    Truncate,
+    /// Не округлять
+    None,
+}
+
+/// Точность стоимостей: сколько знаков после запятой и как округлять
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ValuationSettings {
+    /// Знаков после запятой (0..=4)
+    #[serde(default = "default_valuation_precision")]
+    pub precision: u8,
+    #[serde(default)]
+    pub rounding: RoundingMode,
+}
+
+fn default_valuation_precision() -> u8 { 2 }
+
+impl Default for ValuationSettings {
+    fn default() -> Self {
+        Self {
+            precision: default_valuation_precision(),
+            rounding: RoundingMode::HalfUp,
+        }
+    }
+}
+
+/// Окно-компаньон: геометрия в физических пикселях, прозрачность 0.2..1.0
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompanionWindowSettings {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub opacity: f64,
+    /// Было ли окно открыто (восстанавливается при запуске)
+    pub visible: bool,
+}
+
+impl Default for CompanionWindowSettings {
+    fn default() -> Self {
+        Self {
+            x: 60,
+            y: 60,
+            width: 260,
+            height: 140,
+            opacity: 0.85,
+            visible: false,
         }
     }
 }
@@ -234,6 +1720,11 @@ pub enum LogEvent {
     ItemDrop(ItemDropEvent),
     PriceSearch(PriceSearchEvent),
     MapChange(MapChangeEvent),
+    InventorySnapshot(InventorySnapshotEvent),
+    ItemConsumed(ItemConsumedEvent),
+    ItemGained(ItemGainedEvent),
+    /// Лог пересоздан — состояние слотов этого источника начинается заново
+    LogReset { source_id: String },
 }
 
 /// Статистика сессии для UI
@@ -245,6 +1736,9 @@ pub struct SessionStats {
     pub unique_items: i32,
     /// Общая стоимость (расчётная)
     pub total_value: f64,
+    /// Комиссия при продаже дропа (по ставкам категорий)
+    #[serde(default)]
+    pub total_fee: f64,
     /// Карт завершено
     pub maps_completed: i32,
     /// Время сессии в секундах
@@ -253,10 +1747,66 @@ pub struct SessionStats {
     pub avg_map_duration_sec: i32,
     /// Кол-во позиций дропа, у которых цена устарела (старше TTL)
     pub stale_price_lines: i32,
-    /// Доход в час (расчётный)
+    /// Доход в час (расчётный) по времени сессии, включая убежище и продажу
     pub hourly_profit: f64,
+    /// Время, проведённое на картах (сек), включая текущую карту
+    #[serde(default)]
+    pub map_time_sec: i32,
+    /// Доход в час только по времени на картах
+    #[serde(default)]
+    pub map_hourly_profit: f64,
+    /// Чистая прибыль в час (за вычетом комиссии и расходов) по времени сессии
+    #[serde(default)]
+    pub net_hourly_profit: f64,
+    /// Чистая прибыль в час только по времени на картах
+    #[serde(default)]
+    pub net_map_hourly_profit: f64,
     /// Сессия на паузе
     pub is_paused: bool,
+    /// Причина паузы
+    #[serde(default)]
+    pub pause_reason: Option<PauseReason>,
+    /// Сколько времени сессия провела на паузе (сек)
+    #[serde(default)]
+    pub paused_sec: i32,
+    /// Будильник по чистому профиту
+    #[serde(default)]
+    pub profit_alarm: Option<ProfitAlarm>,
+    /// Ручной дроп, уже проданный (по фактическим ценам)
+    #[serde(default)]
+    pub manual_realized_value: f64,
+    /// Ручной дроп, ожидающий продажи (по оценочным ценам)
+    #[serde(default)]
+    pub manual_pending_value: f64,
+    /// Сессия восстановлена после аварийного выхода (цифры могут быть занижены)
+    #[serde(default)]
+    pub recovered: bool,
+    /// Сколько секунд приложение не работало при аварийных выходах
+    #[serde(default)]
+    pub recovery_gap_sec: i64,
+    /// Брошенные карты (дольше лимита), не вошедшие в maps_completed и среднее
+    #[serde(default)]
+    pub abandoned_maps: i32,
+    /// Время брошенных карт (сек)
+    #[serde(default)]
+    pub abandoned_map_sec: i32,
+}
+
+/// Сессия из истории, пересчитанная по сегодняшним ценам (рядом с исходными цифрами)
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionRevaluation {
+    pub session_id: String,
+    pub original_income: f64,
+    pub original_fee: f64,
+    pub original_profit: f64,
+    pub revalued_income: f64,
+    pub revalued_fee: f64,
+    pub revalued_profit: f64,
+    /// Непроданный ручной дроп: как записан и по текущим курсам валют
+    pub original_manual_pending: f64,
+    pub revalued_manual_pending: f64,
+    /// Позиции дропа без цены в кэше (в пересчёте считаются как 0)
+    pub unpriced_items: i32,
 }
 
 /// Профиль пользователя kripika.com (public.profiles)
@@ -269,3 +1819,77 @@ pub struct UserProfile {
     pub level: Option<i32>,
     pub total_xp: Option<i32>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::categories::{CategoryCatalog, CategoryInfo};
+    use chrono::TimeZone;
+
+    fn drop_row(game_id: i64, name: &str, category: &str, quantity: i32, total_value: f64, minute: u32) -> AggregatedDrop {
+        AggregatedDrop {
+            game_id,
+            item_info: Some(ItemInfo {
+                game_id,
+                name: name.to_string(),
+                category: category.to_string(),
+                ..ItemInfo::default()
+            }),
+            quantity,
+            total_value,
+            last_dropped_at: Some(Utc.with_ymd_and_hms(2026, 3, 1, 12, minute, 0).unwrap()),
+            ..AggregatedDrop::default()
+        }
+    }
+
+    fn sample() -> Vec<AggregatedDrop> {
+        vec![
+            drop_row(1, "Ember", "currency", 5, 50.0, 10),
+            drop_row(2, "ashes", "Material", 20, 50.0, 30),
+            drop_row(3, "Cinder", "material", 1, 300.0, 20),
+        ]
+    }
+
+    fn ids(page: &DropsPage) -> Vec<i64> {
+        page.drops.iter().map(|d| d.game_id).collect()
+    }
+
+    #[test]
+    fn sorts_by_key_and_direction_with_game_id_tiebreak() {
+        let catalog = CategoryCatalog::default();
+        let by = |sort, direction| DropsQuery { sort, direction, ..Default::default() }.apply(sample(), &catalog);
+
+        assert_eq!(ids(&by(DropsSortKey::Value, SortDirection::Desc)), vec![3, 1, 2]);
+        assert_eq!(ids(&by(DropsSortKey::Value, SortDirection::Asc)), vec![1, 2, 3]);
+        assert_eq!(ids(&by(DropsSortKey::Quantity, SortDirection::Desc)), vec![2, 1, 3]);
+        assert_eq!(ids(&by(DropsSortKey::Recent, SortDirection::Desc)), vec![2, 3, 1]);
+        assert_eq!(ids(&by(DropsSortKey::Name, SortDirection::Asc)), vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn filters_category_ignoring_case_and_pages() {
+        let catalog = CategoryCatalog::default();
+        let query = DropsQuery { category: Some("MATERIAL".to_string()), ..Default::default() };
+        let page = query.apply(sample(), &catalog);
+        assert_eq!((ids(&page), page.total), (vec![3, 2], 2));
+
+        let page = DropsQuery { offset: 1, limit: Some(1), ..Default::default() }.apply(sample(), &catalog);
+        assert_eq!((ids(&page), page.total, page.offset), (vec![1], 3, 1));
+
+        let page = DropsQuery { offset: 10, limit: Some(5), ..Default::default() }.apply(sample(), &catalog);
+        assert_eq!((page.drops.len(), page.total, page.offset), (0, 3, 3));
+
+        let page = DropsQuery { category: Some(String::new()), ..Default::default() }.apply(sample(), &catalog);
+        assert_eq!(page.total, 3);
+    }
+
+    #[test]
+    fn filters_by_category_group() {
+        let catalog = CategoryCatalog::new(vec![
+            CategoryInfo { id: "crafting".into(), parent: None, name_en: None, name_ru: None, name_cn: None, sort_order: 0 },
+            CategoryInfo { id: "material".into(), parent: Some("crafting".into()), name_en: None, name_ru: None, name_cn: None, sort_order: 0 },
+        ]);
+        let query = DropsQuery { category: Some("crafting".to_string()), ..Default::default() };
+        assert_eq!(ids(&query.apply(sample(), &catalog)), vec![3]);
+    }
+}
@@ -0,0 +1,204 @@
+//! Неизвестные предметы
+//!
+//! Дроп с game_id, которого нет в справочнике, в сессию не идёт (add_drop). Такие id
+//! копятся здесь — сколько раз и сколько штук подобрано, имя из лога, если клиент его
+//! пишет — и по желанию пользователя отправляются на сервер (RPC report_unknown_items),
+//! чтобы справочник предметов пополнялся по реальному дропу.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::types::ItemDropEvent;
+
+/// Неизвестный предмет для UI и отчёта
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnknownItem {
+    pub game_id: i64,
+    /// Сколько раз встречался в подборе
+    pub seen_count: u32,
+    /// Сколько штук подобрано всего
+    pub total_quantity: i64,
+    /// Имя из строки лога (последнее встреченное)
+    pub name: Option<String>,
+    pub tier: Option<i32>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Всё собранное уже отправлено на сервер
+    pub reported: bool,
+}
+
+#[derive(Debug)]
+struct Entry {
+    item: UnknownItem,
+    /// Сколько из счётчиков уже отправлено (сервер суммирует отчёты — шлём только прирост)
+    reported_seen: u32,
+    reported_quantity: i64,
+}
+
+/// Собранные неизвестные предметы (game_id -> запись)
+#[derive(Debug, Default)]
+pub struct UnknownItems {
+    items: HashMap<i64, Entry>,
+}
+
+impl UnknownItems {
+    pub fn record(&mut self, event: &ItemDropEvent, quantity: i32) {
+        let entry = self.items.entry(event.game_id).or_insert_with(|| Entry {
+            item: UnknownItem {
+                game_id: event.game_id,
+                seen_count: 0,
+                total_quantity: 0,
+                name: None,
+                tier: None,
+                first_seen: event.timestamp,
+                last_seen: event.timestamp,
+                reported: false,
+            },
+            reported_seen: 0,
+            reported_quantity: 0,
+        });
+        let item = &mut entry.item;
+        item.seen_count += 1;
+        item.total_quantity += quantity as i64;
+        item.last_seen = item.last_seen.max(event.timestamp);
+        if event.item_name.is_some() {
+            item.name = event.item_name.clone();
+        }
+        if event.tier.is_some() {
+            item.tier = event.tier;
+        }
+    }
+
+    /// Список: чаще встречающиеся — выше
+    pub fn list(&self) -> Vec<UnknownItem> {
+        let mut items: Vec<UnknownItem> = self
+            .items
+            .values()
+            .map(|e| UnknownItem { reported: e.reported_seen == e.item.seen_count, ..e.item.clone() })
+            .collect();
+        items.sort_by(|a, b| b.seen_count.cmp(&a.seen_count).then(a.game_id.cmp(&b.game_id)));
+        items
+    }
+
+    /// Прирост с прошлого отчёта (счётчики — только новые подборы)
+    pub fn pending(&self) -> Vec<UnknownItem> {
+        let mut items: Vec<UnknownItem> = self
+            .items
+            .values()
+            .filter(|e| e.item.seen_count > e.reported_seen)
+            .map(|e| UnknownItem {
+                seen_count: e.item.seen_count - e.reported_seen,
+                total_quantity: e.item.total_quantity - e.reported_quantity,
+                ..e.item.clone()
+            })
+            .collect();
+        items.sort_by_key(|i| i.game_id);
+        items
+    }
+
+    /// Засчитать отправленный прирост (из pending)
+    pub fn mark_reported(&mut self, reported: &[UnknownItem]) {
+        for sent in reported {
+            if let Some(entry) = self.items.get_mut(&sent.game_id) {
+                entry.reported_seen += sent.seen_count;
+                entry.reported_quantity += sent.total_quantity;
+            }
+        }
+    }
+
+    /// Забрать прирост для отправки: он сразу считается отправленным, чтобы
+    /// параллельный отчёт не послал его второй раз. При ошибке — `restore`
+    pub fn take_pending(&mut self) -> Vec<UnknownItem> {
+        let pending = self.pending();
+        self.mark_reported(&pending);
+        pending
+    }
+
+    /// Вернуть прирост, который не удалось отправить (из take_pending)
+    pub fn restore(&mut self, failed: &[UnknownItem]) {
+        for sent in failed {
+            if let Some(entry) = self.items.get_mut(&sent.game_id) {
+                entry.reported_seen = entry.reported_seen.saturating_sub(sent.seen_count);
+                entry.reported_quantity = (entry.reported_quantity - sent.total_quantity).max(0);
+            }
+        }
+    }
+
+    /// Убрать предметы, которые появились в справочнике
+    pub fn retain_unknown(&mut self, is_known: impl Fn(i64) -> bool) -> usize {
+        let before = self.items.len();
+        self.items.retain(|id, _| !is_known(*id));
+        before - self.items.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pickup(game_id: i64, quantity: i32, name: Option<&str>) -> ItemDropEvent {
+        ItemDropEvent {
+            game_id,
+            quantity,
+            timestamp: Utc::now(),
+            page_id: 102,
+            slot_id: 1,
+            slot_quantity: quantity,
+            source_id: "main".to_string(),
+            tier: None,
+            rarity: None,
+            item_name: name.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn counts_names_and_report_state() {
+        let mut unknown = UnknownItems::default();
+        unknown.record(&pickup(999001, 2, None), 2);
+        unknown.record(&pickup(999001, 3, Some("Ember Shard")), 3);
+        unknown.record(&pickup(999002, 1, None), 1);
+
+        let list = unknown.list();
+        assert_eq!(list[0].game_id, 999001);
+        assert_eq!((list[0].seen_count, list[0].total_quantity), (2, 5));
+        assert_eq!(list[0].name.as_deref(), Some("Ember Shard"));
+
+        let pending = unknown.pending();
+        assert_eq!(pending.len(), 2);
+        unknown.mark_reported(&pending[..1]);
+        assert!(unknown.list()[0].reported);
+        assert_eq!(unknown.pending().len(), 1);
+
+        // Повторный отчёт несёт только новые подборы
+        unknown.record(&pickup(999001, 1, None), 1);
+        let pending = unknown.pending();
+        assert_eq!((pending[0].game_id, pending[0].seen_count, pending[0].total_quantity), (999001, 1, 1));
+        assert_eq!(unknown.list()[0].name.as_deref(), Some("Ember Shard"));
+
+        assert_eq!(unknown.retain_unknown(|id| id == 999002), 1);
+        assert_eq!(unknown.list().len(), 1);
+    }
+
+    #[test]
+    fn in_flight_report_is_not_sent_twice() {
+        let mut unknown = UnknownItems::default();
+        unknown.record(&pickup(999001, 2, None), 2);
+
+        let sent = unknown.take_pending();
+        assert_eq!(sent.len(), 1);
+        // Пока первый отчёт в пути, второй ничего не берёт
+        assert!(unknown.take_pending().is_empty());
+
+        // Отправка не удалась — прирост возвращается вместе с новыми подборами
+        unknown.record(&pickup(999001, 1, None), 1);
+        unknown.restore(&sent);
+        let pending = unknown.pending();
+        assert_eq!((pending[0].seen_count, pending[0].total_quantity), (2, 3));
+    }
+}
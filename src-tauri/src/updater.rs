@@ -0,0 +1,105 @@
+//! Проверка и установка обновлений с выбором канала (stable/beta)
+//!
+//! Канал берётся из AppSettings.update_channel: stable читает манифест последнего релиза,
+//! beta — манифест плавающего тега `beta` (пре-релизы с фиксами парсера).
+//! Найденное обновление запоминается до явного вызова `install`.
+
+use std::sync::{Arc, Mutex};
+use log::info;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, Url};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::state::AppState;
+use crate::types::UpdateChannel;
+
+const STABLE_ENDPOINT: &str = "https://github.com/kripakrip/tli_companion/releases/latest/download/latest.json";
+const BETA_ENDPOINT: &str = "https://github.com/kripakrip/tli_companion/releases/download/beta/latest.json";
+
+/// Результат проверки обновлений для фронтенда
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub channel: UpdateChannel,
+    pub current_version: String,
+    pub available: bool,
+    pub latest_version: Option<String>,
+    pub release_notes: Option<String>,
+    pub release_date: Option<String>,
+}
+
+/// Прогресс скачивания (событие `update-progress`)
+#[derive(Debug, Clone, Serialize)]
+struct UpdateProgress {
+    downloaded: u64,
+    total: Option<u64>,
+    finished: bool,
+}
+
+/// Обновление, найденное последней проверкой (managed state)
+#[derive(Default)]
+pub struct PendingUpdate(Mutex<Option<Update>>);
+
+fn endpoint(channel: UpdateChannel) -> &'static str {
+    match channel {
+        UpdateChannel::Stable => STABLE_ENDPOINT,
+        UpdateChannel::Beta => BETA_ENDPOINT,
+    }
+}
+
+/// Проверить наличие обновления в выбранном канале
+pub async fn check(app: &AppHandle, channel: UpdateChannel) -> Result<UpdateInfo, String> {
+    let url = Url::parse(endpoint(channel)).map_err(|e| e.to_string())?;
+    let updater = app
+        .updater_builder()
+        .endpoints(vec![url])
+        .map_err(|e| e.to_string())?
+        .build()
+        .map_err(|e| e.to_string())?;
+    let update = updater.check().await.map_err(|e| format!("check_for_updates failed: {}", e))?;
+
+    let info = UpdateInfo {
+        channel,
+        current_version: app.package_info().version.to_string(),
+        available: update.is_some(),
+        latest_version: update.as_ref().map(|u| u.version.clone()),
+        release_notes: update.as_ref().and_then(|u| u.body.clone()),
+        release_date: update.as_ref().and_then(|u| u.date.map(|d| d.to_string())),
+    };
+    info!("Update check ({:?}): current={}, latest={:?}", channel, info.current_version, info.latest_version);
+
+    *app.state::<PendingUpdate>().0.lock().unwrap() = update;
+    Ok(info)
+}
+
+/// Скачать и установить обновление, найденное последней проверкой
+pub async fn install(app: &AppHandle) -> Result<(), String> {
+    let update = app
+        .state::<PendingUpdate>()
+        .0
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| "No update available, run check_for_updates first".to_string())?;
+
+    info!("Installing update {}", update.version);
+    // Установщик может завершить процесс без RunEvent::Exit — записываем состояние заранее
+    let state = app.state::<Arc<AppState>>().inner().clone();
+    state.prepare_exit().await;
+    let mut downloaded: u64 = 0;
+    let result = update
+        .download_and_install(
+            |chunk, total| {
+                downloaded += chunk as u64;
+                let _ = app.emit("update-progress", UpdateProgress { downloaded, total, finished: false });
+            },
+            || {
+                let _ = app.emit("update-progress", UpdateProgress { downloaded: 0, total: None, finished: true });
+            },
+        )
+        .await
+        .map_err(|e| format!("install_update failed: {}", e));
+    if result.is_err() {
+        state.mark_app_running().await;
+    }
+    result
+}
@@ -0,0 +1,435 @@
+//! Единые расчёты стоимости (FE)
+//!
+//! Статистика, агрегированный дроп, снимки для ссылок, экспорт и выгрузки в облако
+//! считают стоимость здесь: цена единицы -> стоимость позиции -> итог, с одной политикой
+//! округления из AppSettings.valuation. Позиции округляются до суммирования, поэтому
+//! итог всегда равен сумме показанных строк. Комиссия при продаже — FeeModel: общая
+//! ставка аукциона, переопределения по категориям и категории без комиссии.
+
+use std::collections::HashMap;
+
+use crate::exchange_rates::ExchangeRates;
+use crate::persistence::{PersistedPriceEntry, SessionHistoryRecord};
+use crate::types::{AppSettings, ExpenseEntry, ItemInfo, RoundingMode, SessionRevaluation, ValuationSettings};
+
+/// Максимальная точность (знаков после запятой)
+pub const MAX_PRECISION: u8 = 4;
+
+/// Калькулятор стоимостей с заданной политикой округления
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Valuation {
+    settings: ValuationSettings,
+}
+
+impl Valuation {
+    pub fn new(settings: ValuationSettings) -> Self {
+        Self {
+            settings: ValuationSettings {
+                precision: settings.precision.min(MAX_PRECISION),
+                rounding: settings.rounding,
+            },
+        }
+    }
+
+    /// Округлить значение по политике
+    pub fn round(&self, value: f64) -> f64 {
+        if !value.is_finite() {
+            return 0.0;
+        }
+        let factor = 10f64.powi(self.settings.precision as i32);
+        let scaled = snap_to_half(value * factor);
+        let rounded = match self.settings.rounding {
+            RoundingMode::HalfUp => scaled.round(),
+            RoundingMode::HalfEven => scaled.round_ties_even(),
+            RoundingMode::Truncate => scaled.trunc(),
+            RoundingMode::None => return value,
+        };
+        rounded / factor
+    }
+
+    /// Цена единицы предмета: базовая валюта = 1.0, иначе цена из кэша (None — цены нет)
+    pub fn unit_price(
+        game_id: i64,
+        items_cache: &HashMap<i64, ItemInfo>,
+        prices: &HashMap<i64, PersistedPriceEntry>,
+    ) -> Option<f64> {
+        if items_cache.get(&game_id).map(|i| i.is_base_currency).unwrap_or(false) {
+            Some(1.0)
+        } else {
+            prices.get(&game_id).map(|p| p.price)
+        }
+    }
+
+    /// Цена единицы с учётом тира экипировки: своя цена тира, если задана, иначе общая
+    pub fn tier_unit_price(
+        game_id: i64,
+        tier: Option<i32>,
+        items_cache: &HashMap<i64, ItemInfo>,
+        prices: &HashMap<i64, PersistedPriceEntry>,
+    ) -> Option<f64> {
+        if items_cache.get(&game_id).map(|i| i.is_base_currency).unwrap_or(false) {
+            return Some(1.0);
+        }
+        let entry = prices.get(&game_id)?;
+        Some(tier.and_then(|t| entry.tiers.get(&t).copied()).unwrap_or(entry.price))
+    }
+
+    /// Стоимость позиции (цена x количество)
+    pub fn line_value(&self, unit_price: f64, quantity: i32) -> f64 {
+        self.round(unit_price * quantity as f64)
+    }
+
+    /// Стоимость набора дропов по кэшу цен (предметы без цены — 0)
+    pub fn drops_value(
+        &self,
+        drops: &HashMap<i64, i32>,
+        items_cache: &HashMap<i64, ItemInfo>,
+        prices: &HashMap<i64, PersistedPriceEntry>,
+    ) -> f64 {
+        self.tiered_drops_value(drops, &HashMap::new(), items_cache, prices)
+    }
+
+    /// Стоимость дропов с разбивкой по тирам (цена тира, остаток — по общей цене)
+    pub fn tiered_drops_value(
+        &self,
+        drops: &HashMap<i64, i32>,
+        tiers: &HashMap<i64, HashMap<i32, i32>>,
+        items_cache: &HashMap<i64, ItemInfo>,
+        prices: &HashMap<i64, PersistedPriceEntry>,
+    ) -> f64 {
+        let total = drop_lines(drops, tiers)
+            .into_iter()
+            .map(|(game_id, tier, qty)| {
+                let unit_price = Self::tier_unit_price(game_id, tier, items_cache, prices).unwrap_or(0.0);
+                self.line_value(unit_price, qty)
+            })
+            .sum();
+        self.round(total)
+    }
+
+    /// Сумма трат
+    pub fn expenses_total(&self, expenses: &[ExpenseEntry]) -> f64 {
+        let total = expenses.iter().map(|e| self.line_value(e.price, e.quantity)).sum();
+        self.round(total)
+    }
+
+    /// Доход в час за `seconds` секунд (0, если времени нет)
+    pub fn per_hour(&self, value: f64, seconds: i32) -> f64 {
+        if seconds > 0 {
+            self.round(value / seconds as f64 * 3600.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Строки дропа (game_id, тир, количество): части с известным тиром и остаток без тира.
+/// Тиров больше, чем предметов, не бывает (см. trim_tiers), но остаток не уходит в минус
+pub fn drop_lines(drops: &HashMap<i64, i32>, tiers: &HashMap<i64, HashMap<i32, i32>>) -> Vec<(i64, Option<i32>, i32)> {
+    let mut lines = Vec::new();
+    for (game_id, qty) in drops {
+        let mut tiered = 0;
+        if let Some(by_tier) = tiers.get(game_id) {
+            for (tier, tier_qty) in by_tier {
+                lines.push((*game_id, Some(*tier), *tier_qty));
+                tiered += tier_qty;
+            }
+        }
+        if *qty > tiered {
+            lines.push((*game_id, None, qty - tiered));
+        }
+    }
+    lines
+}
+
+/// Срезать тиры предмета до его количества (после ручной правки): сначала уходит
+/// остаток без тира, затем худшие тиры (больший номер)
+pub fn trim_tiers(by_tier: &mut HashMap<i32, i32>, quantity: i32) {
+    let mut excess = by_tier.values().sum::<i32>() - quantity.max(0);
+    let mut order: Vec<i32> = by_tier.keys().copied().collect();
+    order.sort_unstable_by(|a, b| b.cmp(a));
+    for tier in order {
+        if excess <= 0 {
+            break;
+        }
+        if let Some(count) = by_tier.get_mut(&tier) {
+            let taken = excess.min(*count);
+            *count -= taken;
+            excess -= taken;
+            if *count == 0 {
+                by_tier.remove(&tier);
+            }
+        }
+    }
+}
+
+/// Ставки комиссии при продаже
+#[derive(Debug, Clone)]
+pub struct FeeModel {
+    default_rate: f64,
+    /// Категория (в нижнем регистре) -> ставка
+    category_rates: HashMap<String, f64>,
+    /// Категории без комиссии (в нижнем регистре)
+    no_fee: Vec<String>,
+}
+
+impl FeeModel {
+    pub fn new(settings: &AppSettings) -> Self {
+        Self {
+            default_rate: settings.auction_fee_rate.clamp(0.0, 1.0),
+            category_rates: settings
+                .category_fee_rates
+                .iter()
+                .map(|(category, rate)| (category.to_lowercase(), rate.clamp(0.0, 1.0)))
+                .collect(),
+            no_fee: settings.no_fee_categories.iter().map(|c| c.to_lowercase()).collect(),
+        }
+    }
+
+    /// Ставка для предмета: базовая валюта не продаётся, неизвестный предмет — общая ставка
+    pub fn rate(&self, item: Option<&ItemInfo>) -> f64 {
+        let Some(item) = item else {
+            return self.default_rate;
+        };
+        if item.is_base_currency {
+            return 0.0;
+        }
+        let category = item.category.to_lowercase();
+        if self.no_fee.contains(&category) {
+            return 0.0;
+        }
+        self.category_rates.get(&category).copied().unwrap_or(self.default_rate)
+    }
+
+    /// Комиссия за дропы с разбивкой по тирам
+    pub fn tiered_drops_fee(
+        &self,
+        valuation: &Valuation,
+        drops: &HashMap<i64, i32>,
+        tiers: &HashMap<i64, HashMap<i32, i32>>,
+        items_cache: &HashMap<i64, ItemInfo>,
+        prices: &HashMap<i64, PersistedPriceEntry>,
+    ) -> f64 {
+        let total = drop_lines(drops, tiers)
+            .into_iter()
+            .map(|(game_id, tier, qty)| {
+                let unit_price = Valuation::tier_unit_price(game_id, tier, items_cache, prices).unwrap_or(0.0);
+                valuation.round(valuation.line_value(unit_price, qty) * self.rate(items_cache.get(&game_id)))
+            })
+            .sum();
+        valuation.round(total)
+    }
+}
+
+/// Сессия из истории по сегодняшним ценам: дроп — по кэшу цен, непроданный ручной дроп
+/// в торговых валютах — по разнице курса на конец сессии и последнего известного
+pub fn revalue_record(
+    record: &SessionHistoryRecord,
+    valuation: &Valuation,
+    fees: &FeeModel,
+    items_cache: &HashMap<i64, ItemInfo>,
+    prices: &HashMap<i64, PersistedPriceEntry>,
+    rates: &ExchangeRates,
+) -> SessionRevaluation {
+    let revalued_income = valuation.tiered_drops_value(&record.drops, &record.drop_tiers, items_cache, prices);
+    let revalued_fee = fees.tiered_drops_fee(valuation, &record.drops, &record.drop_tiers, items_cache, prices);
+    let unpriced_items = record
+        .drops
+        .keys()
+        .filter(|game_id| Valuation::unit_price(**game_id, items_cache, prices).is_none())
+        .count() as i32;
+    let delta: f64 = record
+        .manual_pending_quoted
+        .iter()
+        .filter_map(|q| {
+            let then = rates.rate_at(q.currency_id, record.ended_at)?;
+            let now = rates.latest(q.currency_id)?;
+            Some(q.amount * (now - then))
+        })
+        .sum();
+
+    SessionRevaluation {
+        session_id: record.id.clone(),
+        original_income: record.total_income,
+        original_fee: record.total_fee,
+        original_profit: record.total_profit,
+        revalued_income,
+        revalued_fee,
+        revalued_profit: valuation.round(revalued_income - record.total_expenses - revalued_fee),
+        original_manual_pending: record.manual_pending,
+        revalued_manual_pending: valuation.round(record.manual_pending + delta),
+        unpriced_items,
+    }
+}
+
+/// Убрать ошибку двоичного представления: 1.005 * 100 = 100.49999999999999 должно
+/// округляться как ровно половина, 0.29 * 100 = 28.999999999999996 — отбрасываться как 29
+fn snap_to_half(scaled: f64) -> f64 {
+    let half = (scaled * 2.0).round() / 2.0;
+    if (scaled - half).abs() <= 1e-9 * scaled.abs().max(1.0) {
+        half
+    } else {
+        scaled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(price: f64, tiers: &[(i32, f64)]) -> PersistedPriceEntry {
+        PersistedPriceEntry {
+            price,
+            updated_at: chrono::Utc::now(),
+            is_current_league: true,
+            league_name: None,
+            sample_count: None,
+            spread: None,
+            tiers: tiers.iter().copied().collect(),
+        }
+    }
+
+    fn item(game_id: i64, category: &str, is_base_currency: bool) -> ItemInfo {
+        ItemInfo {
+            game_id,
+            name: format!("item {}", game_id),
+            category: category.to_string(),
+            is_base_currency,
+            ..ItemInfo::default()
+        }
+    }
+
+    #[test]
+    fn fee_rates_by_category() {
+        let settings = AppSettings {
+            auction_fee_rate: 0.125,
+            category_fee_rates: HashMap::from([("Ember".to_string(), 0.05), ("Broken".to_string(), 3.0)]),
+            no_fee_categories: vec!["Quest".to_string()],
+            ..AppSettings::default()
+        };
+        let fees = FeeModel::new(&settings);
+
+        assert_eq!(fees.rate(None), 0.125);
+        assert_eq!(fees.rate(Some(&item(1, "equipment", false))), 0.125);
+        // Категории без учёта регистра, ставка ограничена 0..=1
+        assert_eq!(fees.rate(Some(&item(2, "ember", false))), 0.05);
+        assert_eq!(fees.rate(Some(&item(3, "BROKEN", false))), 1.0);
+        assert_eq!(fees.rate(Some(&item(4, "quest", false))), 0.0);
+        assert_eq!(fees.rate(Some(&item(5, "currency", true))), 0.0);
+    }
+
+    #[test]
+    fn fees_are_rounded_per_line() {
+        let valuation = Valuation::new(ValuationSettings { precision: 2, rounding: RoundingMode::HalfUp });
+        let fees = FeeModel::new(&AppSettings { auction_fee_rate: 0.125, ..AppSettings::default() });
+        let items = HashMap::from([(1, item(1, "ember", false)), (2, item(2, "currency", true))]);
+        let prices = HashMap::from([(1, price(0.3, &[])), (2, price(1.0, &[])), (3, price(0.3, &[]))]);
+        let drops = HashMap::from([(1, 1), (2, 100), (3, 1)]);
+
+        // 0.0375 -> 0.04 по каждой строке; базовая валюта без комиссии
+        assert_eq!(fees.tiered_drops_fee(&valuation, &drops, &HashMap::new(), &items, &prices), 0.08);
+    }
+
+    #[test]
+    fn tiers_are_priced_separately_and_trimmed_to_quantity() {
+        let valuation = Valuation::new(ValuationSettings { precision: 2, rounding: RoundingMode::HalfUp });
+        let drops = HashMap::from([(1, 5)]);
+        let mut tiers = HashMap::from([(1, HashMap::from([(0, 1), (2, 2)]))]);
+        let prices = HashMap::from([(1, price(10.0, &[(0, 1000.0)]))]);
+        let items = HashMap::new();
+
+        // T0 по своей цене, T2 и остаток без тира — по общей
+        assert_eq!(valuation.tiered_drops_value(&drops, &tiers, &items, &prices), 1000.0 + 2.0 * 10.0 + 2.0 * 10.0);
+        assert_eq!(valuation.drops_value(&drops, &items, &prices), 50.0);
+
+        // Правка до 2 штук: уходит остаток без тира, затем худший тир
+        trim_tiers(tiers.get_mut(&1).unwrap(), 2);
+        assert_eq!(tiers[&1], HashMap::from([(0, 1), (2, 1)]));
+        trim_tiers(tiers.get_mut(&1).unwrap(), 0);
+        assert!(tiers[&1].is_empty());
+        assert!(drop_lines(&HashMap::from([(1, 1)]), &HashMap::from([(1, HashMap::from([(0, 3)]))]))
+            .iter()
+            .all(|(_, _, qty)| *qty >= 0));
+    }
+
+    #[test]
+    fn rounding_half_way_and_negative_values() {
+        let with = |precision: u8, rounding: RoundingMode| Valuation::new(ValuationSettings { precision, rounding });
+
+        let half_up = with(2, RoundingMode::HalfUp);
+        assert_eq!(half_up.round(1.005), 1.01);
+        assert_eq!(half_up.round(-1.005), -1.01);
+        assert_eq!(half_up.round(0.125), 0.13);
+        assert_eq!(with(0, RoundingMode::HalfUp).round(2.5), 3.0);
+        assert_eq!(with(0, RoundingMode::HalfUp).round(-2.5), -3.0);
+
+        let half_even = with(2, RoundingMode::HalfEven);
+        assert_eq!(half_even.round(0.125), 0.12);
+        assert_eq!(half_even.round(0.135), 0.14);
+        assert_eq!(half_even.round(-0.125), -0.12);
+        assert_eq!(with(0, RoundingMode::HalfEven).round(2.5), 2.0);
+        assert_eq!(with(0, RoundingMode::HalfEven).round(-3.5), -4.0);
+
+        let truncate = with(2, RoundingMode::Truncate);
+        assert_eq!(truncate.round(0.29), 0.29);
+        assert_eq!(truncate.round(1.999), 1.99);
+        assert_eq!(truncate.round(-1.999), -1.99);
+
+        assert_eq!(with(2, RoundingMode::None).round(1.23456), 1.23456);
+        assert_eq!(half_up.round(f64::NAN), 0.0);
+        assert_eq!(half_up.round(f64::NEG_INFINITY), 0.0);
+        // Точность ограничена MAX_PRECISION
+        assert_eq!(with(9, RoundingMode::HalfUp).round(0.123456), 0.1235);
+    }
+
+    fn history_record(drops: HashMap<i64, i32>, manual_pending: f64, quoted: Vec<crate::types::QuotedPrice>) -> SessionHistoryRecord {
+        let ended_at = chrono::Utc::now() - chrono::Duration::days(7);
+        SessionHistoryRecord {
+            id: "s1".to_string(),
+            started_at: ended_at - chrono::Duration::hours(1),
+            ended_at,
+            maps_completed: 4,
+            total_duration_sec: 3600,
+            total_profit: 0.0,
+            total_expenses: 5.0,
+            total_income: 0.0,
+            total_fee: 0.0,
+            remote_id: None,
+            drops,
+            drop_tiers: HashMap::new(),
+            share_code: None,
+            preset_id: None,
+            raw_currency_start: None,
+            raw_currency_end: None,
+            raw_currency_gained: None,
+            manual_realized: 0.0,
+            manual_pending,
+            manual_pending_quoted: quoted,
+            recovered: false,
+            recovery_gap_sec: 0,
+            merged_remote_ids: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn revalues_history_record_at_current_prices_and_rates() {
+        let valuation = Valuation::new(ValuationSettings { precision: 2, rounding: RoundingMode::HalfUp });
+        let fees = FeeModel::new(&AppSettings { auction_fee_rate: 0.1, ..AppSettings::default() });
+        let items_cache = HashMap::from([(1, item(1, "equipment", false))]);
+        let prices = HashMap::from([(1, price(10.0, &[]))]);
+        let quoted = vec![
+            crate::types::QuotedPrice { currency_id: 77, amount: 2.0 },
+            // Курс неизвестен — остаётся как записан
+            crate::types::QuotedPrice { currency_id: 88, amount: 9.0 },
+        ];
+        let record = history_record(HashMap::from([(1, 3), (2, 5)]), 200.0, quoted);
+        let mut rates = ExchangeRates::default();
+        rates.record(77, 100.0, record.ended_at - chrono::Duration::hours(1));
+        rates.record(77, 150.0, record.ended_at + chrono::Duration::days(1));
+
+        let revalued = revalue_record(&record, &valuation, &fees, &items_cache, &prices, &rates);
+        assert_eq!((revalued.revalued_income, revalued.revalued_fee, revalued.revalued_profit), (30.0, 3.0, 22.0));
+        assert_eq!(revalued.unpriced_items, 1);
+        assert_eq!((revalued.original_manual_pending, revalued.revalued_manual_pending), (200.0, 300.0));
+    }
+}
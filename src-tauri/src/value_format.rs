@@ -0,0 +1,130 @@
+//! Форматирование сумм в FE
+//!
+//! Единые правила для сводок, карточек, трея, экспорта и фронтенда (команда
+//! format_values), чтобы одно и то же число везде выглядело одинаково:
+//! - `fe` — точная запись (крупные суммы без копеек, мелкие с двумя знаками);
+//! - `compact` — короткая запись для оверлея («12.3k», «1.5M»);
+//! - `breakdown` — разложение по крупным валютам из AppSettings.denomination_items
+//!   (сколько штук каждой и остаток в FE).
+
+use serde::Serialize;
+
+/// Сумма в FE: крупные — без копеек, мелкие — с двумя знаками
+pub fn fe(value: f64) -> String {
+    if value.abs() >= 100.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// Короткая запись суммы (как formatNumber во фронтенде, плюс миллионы)
+pub fn compact(value: f64) -> String {
+    let abs = value.abs();
+    if abs >= 1_000_000.0 {
+        format!("{:.1}M", value / 1_000_000.0)
+    } else if abs >= 10_000.0 {
+        format!("{:.1}k", value / 1000.0)
+    } else if abs >= 100.0 {
+        format!("{:.0}", value)
+    } else if abs >= 10.0 {
+        format!("{:.1}", value)
+    } else if abs == 0.0 {
+        "0".to_string()
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// Крупная валюта для разложения суммы
+#[derive(Debug, Clone)]
+pub struct Denomination {
+    pub game_id: i64,
+    pub name: String,
+    /// Цена одной штуки в FE
+    pub unit_value: f64,
+}
+
+/// Часть разложения: столько-то штук валюты
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DenominationPart {
+    pub game_id: i64,
+    pub name: String,
+    pub count: i64,
+}
+
+/// Сумма во всех видах (ответ format_values)
+#[derive(Debug, Clone, Serialize)]
+pub struct FormattedValue {
+    pub value: f64,
+    /// «1234 FE»
+    pub exact: String,
+    /// «12.3k FE»
+    pub compact: String,
+    /// Крупные валюты, от дорогой к дешёвой (пусто — валюты не заданы или сумма меньше штуки)
+    pub breakdown: Vec<DenominationPart>,
+    /// Остаток после разложения, FE
+    pub remainder_fe: f64,
+}
+
+/// Разложить сумму по валютам: жадно, от самой дорогой. Возвращает части и остаток в FE
+pub fn breakdown(value: f64, denominations: &[Denomination]) -> (Vec<DenominationPart>, f64) {
+    let mut sorted: Vec<&Denomination> = denominations.iter().filter(|d| d.unit_value > 0.0).collect();
+    sorted.sort_by(|a, b| b.unit_value.total_cmp(&a.unit_value));
+
+    let sign = value.signum();
+    let mut left = value.abs();
+    let mut parts = Vec::new();
+    for d in sorted {
+        let count = (left / d.unit_value).floor();
+        if count >= 1.0 {
+            left -= count * d.unit_value;
+            parts.push(DenominationPart { game_id: d.game_id, name: d.name.clone(), count: sign as i64 * count as i64 });
+        }
+    }
+    (parts, sign * left)
+}
+
+/// Отформатировать сумму всеми способами
+pub fn format_value(value: f64, denominations: &[Denomination]) -> FormattedValue {
+    let (breakdown, remainder_fe) = breakdown(value, denominations);
+    FormattedValue {
+        value,
+        exact: format!("{} FE", fe(value)),
+        compact: format!("{} FE", compact(value)),
+        breakdown,
+        remainder_fe,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_matches_overlay_rules() {
+        assert_eq!(compact(0.0), "0");
+        assert_eq!(compact(3.456), "3.46");
+        assert_eq!(compact(42.0), "42.0");
+        assert_eq!(compact(1234.4), "1234");
+        assert_eq!(compact(12_345.0), "12.3k");
+        assert_eq!(compact(-2_500_000.0), "-2.5M");
+    }
+
+    #[test]
+    fn breaks_down_largest_first() {
+        let denominations = vec![
+            Denomination { game_id: 1, name: "Small".into(), unit_value: 10.0 },
+            Denomination { game_id: 2, name: "Big".into(), unit_value: 250.0 },
+        ];
+        let (parts, rest) = breakdown(785.0, &denominations);
+        assert_eq!(
+            parts,
+            vec![
+                DenominationPart { game_id: 2, name: "Big".into(), count: 3 },
+                DenominationPart { game_id: 1, name: "Small".into(), count: 3 },
+            ]
+        );
+        assert_eq!(rest, 5.0);
+    }
+}
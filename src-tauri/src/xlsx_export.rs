@@ -0,0 +1,190 @@
+//! Экспорт сессий в .xlsx
+//!
+//! Сессия: листы дропа (локализованные имена и цены), трат и карт. История: один лист
+//! со сводкой по сессиям. Заголовки на языке интерфейса, числа с форматами,
+//! первая строка закреплена — файл открывается «красивым» без ручной правки.
+
+use std::path::Path;
+
+use chrono::{DateTime, Local, Utc};
+use rust_xlsxwriter::{Color, Format, FormatBorder, Workbook, Worksheet, XlsxError};
+
+use crate::persistence::SessionHistoryRecord;
+use crate::session_summary::label;
+use crate::types::{ExpenseEntry, MapRun};
+use crate::valuation::Valuation;
+
+/// Строка листа дропа
+#[derive(Debug, Clone)]
+pub struct DropRow {
+    pub name: String,
+    pub category: String,
+    pub quantity: i32,
+    pub unit_price: f64,
+    pub total_value: f64,
+    /// Комиссия при продаже позиции
+    pub fee: f64,
+    /// Иконка предмета (для карточки сессии; в таблицу не пишется)
+    pub icon_url: Option<String>,
+}
+
+/// Всё, что попадает в файл сессии
+#[derive(Debug, Clone)]
+pub struct SessionExport {
+    pub language: String,
+    pub drops: Vec<DropRow>,
+    pub expenses: Vec<ExpenseEntry>,
+    pub map_runs: Vec<MapRun>,
+    /// Округление сумм (как в статистике сессии)
+    pub valuation: Valuation,
+}
+
+/// Общие форматы книги
+struct Formats {
+    header: Format,
+    money: Format,
+    integer: Format,
+}
+
+impl Formats {
+    fn new() -> Self {
+        Self {
+            header: Format::new()
+                .set_bold()
+                .set_background_color(Color::RGB(0xE7E6E6))
+                .set_border_bottom(FormatBorder::Thin),
+            money: Format::new().set_num_format("#,##0.00"),
+            integer: Format::new().set_num_format("#,##0"),
+        }
+    }
+}
+
+fn local_time(at: DateTime<Utc>) -> String {
+    at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// Лист с закреплённой строкой заголовков и шириной колонок
+fn add_sheet<'a>(
+    workbook: &'a mut Workbook,
+    name: &str,
+    headers: &[(&str, f64)],
+    formats: &Formats,
+) -> Result<&'a mut Worksheet, XlsxError> {
+    let sheet = workbook.add_worksheet().set_name(name)?;
+    for (col, (title, width)) in headers.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *title, &formats.header)?;
+        sheet.set_column_width(col as u16, *width)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    Ok(sheet)
+}
+
+/// Записать файл сессии: дроп, траты, карты
+pub fn write_session(path: &Path, export: &SessionExport) -> Result<(), XlsxError> {
+    let lang = export.language.as_str();
+    let formats = Formats::new();
+    let mut workbook = Workbook::new();
+
+    let sheet = add_sheet(&mut workbook, label(lang, "Дроп", "Drops"), &[
+        (label(lang, "Предмет", "Item"), 36.0),
+        (label(lang, "Категория", "Category"), 16.0),
+        (label(lang, "Кол-во", "Quantity"), 10.0),
+        (label(lang, "Цена, FE", "Price, FE"), 12.0),
+        (label(lang, "Сумма, FE", "Total, FE"), 14.0),
+        (label(lang, "Комиссия, FE", "Fee, FE"), 14.0),
+    ], &formats)?;
+    for (i, drop) in export.drops.iter().enumerate() {
+        let row = i as u32 + 1;
+        sheet.write_string(row, 0, &drop.name)?;
+        sheet.write_string(row, 1, &drop.category)?;
+        sheet.write_number_with_format(row, 2, drop.quantity as f64, &formats.integer)?;
+        sheet.write_number_with_format(row, 3, drop.unit_price, &formats.money)?;
+        sheet.write_number_with_format(row, 4, drop.total_value, &formats.money)?;
+        sheet.write_number_with_format(row, 5, drop.fee, &formats.money)?;
+    }
+
+    let sheet = add_sheet(&mut workbook, label(lang, "Траты", "Expenses"), &[
+        (label(lang, "Название", "Name"), 36.0),
+        (label(lang, "Кол-во", "Quantity"), 10.0),
+        (label(lang, "Цена, FE", "Price, FE"), 12.0),
+        (label(lang, "Сумма, FE", "Total, FE"), 14.0),
+    ], &formats)?;
+    for (i, expense) in export.expenses.iter().enumerate() {
+        let row = i as u32 + 1;
+        let name = match (lang, &expense.name_ru) {
+            ("ru", Some(name_ru)) if !name_ru.is_empty() => name_ru,
+            _ => &expense.name,
+        };
+        sheet.write_string(row, 0, name)?;
+        sheet.write_number_with_format(row, 1, expense.quantity as f64, &formats.integer)?;
+        sheet.write_number_with_format(row, 2, expense.price, &formats.money)?;
+        sheet.write_number_with_format(row, 3, export.valuation.line_value(expense.price, expense.quantity), &formats.money)?;
+    }
+
+    let sheet = add_sheet(&mut workbook, label(lang, "Карты", "Maps"), &[
+        ("#", 6.0),
+        (label(lang, "Сцена", "Scene"), 40.0),
+        (label(lang, "Начало", "Started"), 18.0),
+        (label(lang, "Длительность, сек", "Duration, sec"), 16.0),
+        (label(lang, "Стоимость, FE", "Value, FE"), 14.0),
+        (label(lang, "Брошена", "Abandoned"), 10.0),
+    ], &formats)?;
+    for (i, run) in export.map_runs.iter().enumerate() {
+        let row = i as u32 + 1;
+        sheet.write_number_with_format(row, 0, run.index as f64, &formats.integer)?;
+        sheet.write_string(row, 1, run.scene_name.as_deref().unwrap_or("—"))?;
+        sheet.write_string(row, 2, local_time(run.started_at))?;
+        sheet.write_number_with_format(row, 3, run.duration_sec as f64, &formats.integer)?;
+        if let Some(value) = run.value {
+            sheet.write_number_with_format(row, 4, value, &formats.money)?;
+        }
+        if run.abandoned {
+            sheet.write_string(row, 5, label(lang, "да", "yes"))?;
+        }
+    }
+
+    workbook.save(path)
+}
+
+/// Записать сводку по истории сессий (одна строка — одна сессия)
+pub fn write_history(
+    path: &Path,
+    records: &[SessionHistoryRecord],
+    language: &str,
+    valuation: &Valuation,
+) -> Result<(), XlsxError> {
+    let lang = language;
+    let formats = Formats::new();
+    let mut workbook = Workbook::new();
+
+    let sheet = add_sheet(&mut workbook, label(lang, "Сессии", "Sessions"), &[
+        (label(lang, "Начало", "Started"), 18.0),
+        (label(lang, "Конец", "Ended"), 18.0),
+        (label(lang, "Длительность, мин", "Duration, min"), 16.0),
+        (label(lang, "Карт", "Maps"), 8.0),
+        (label(lang, "Доход, FE", "Income, FE"), 14.0),
+        (label(lang, "Траты, FE", "Expenses, FE"), 14.0),
+        (label(lang, "Комиссия, FE", "Fees, FE"), 14.0),
+        (label(lang, "Профит, FE", "Profit, FE"), 14.0),
+        (label(lang, "Профит/час, FE", "Profit/hour, FE"), 16.0),
+        (label(lang, "Живые FE", "Raw FE"), 12.0),
+    ], &formats)?;
+    for (i, record) in records.iter().enumerate() {
+        let row = i as u32 + 1;
+        let per_hour = valuation.per_hour(record.total_profit, record.total_duration_sec);
+        sheet.write_string(row, 0, local_time(record.started_at))?;
+        sheet.write_string(row, 1, local_time(record.ended_at))?;
+        sheet.write_number_with_format(row, 2, (record.total_duration_sec / 60) as f64, &formats.integer)?;
+        sheet.write_number_with_format(row, 3, record.maps_completed as f64, &formats.integer)?;
+        sheet.write_number_with_format(row, 4, record.total_income, &formats.money)?;
+        sheet.write_number_with_format(row, 5, record.total_expenses, &formats.money)?;
+        sheet.write_number_with_format(row, 6, record.total_fee, &formats.money)?;
+        sheet.write_number_with_format(row, 7, record.total_profit, &formats.money)?;
+        sheet.write_number_with_format(row, 8, per_hour, &formats.money)?;
+        if let Some(gained) = record.raw_currency_gained {
+            sheet.write_number_with_format(row, 9, gained as f64, &formats.integer)?;
+        }
+    }
+
+    workbook.save(path)
+}
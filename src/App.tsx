@@ -6,6 +6,7 @@ import { check } from "@tauri-apps/plugin-updater";
 import { relaunch } from "@tauri-apps/plugin-process";
 import "./App.css";
 import { getItemName, isKnownItem } from "./itemsData";
+import { applyDropsDelta, DropsDelta } from "./dropsDelta";
 
 // Типы данных
 interface SessionStats {
@@ -17,6 +18,8 @@ interface SessionStats {
   avg_map_duration_sec: number;
   stale_price_lines: number;
   hourly_profit: number;
+  map_time_sec: number;
+  map_hourly_profit: number;
   is_paused: boolean;
 }
 
@@ -32,6 +35,7 @@ interface ItemInfo {
 
 interface AggregatedDrop {
   game_id: number;
+  tier?: number | null;
   item_info: ItemInfo | null;
   quantity: number;
   total_value: number;
@@ -42,6 +46,12 @@ interface AggregatedDrop {
   is_previous_season: boolean;
   /** Название лиги откуда цена */
   league_name: string | null;
+  /** Уверенность в цене (null — цены нет) */
+  price_confidence: { score: number; level: "high" | "medium" | "low"; sample_count: number | null } | null;
+  /** Количество корректировалось вручную */
+  is_adjusted: boolean;
+  /** Суммарная ручная поправка количества */
+  adjusted_delta: number;
 }
 
 interface ItemDropEvent {
@@ -55,6 +65,10 @@ interface AuthStatus {
   email: string | null;
 }
 
+interface AuthStateChange extends AuthStatus {
+  reason: "signed_in" | "signed_out" | "session_expired";
+}
+
 interface UserProfile {
   id: string;
   username: string | null;
@@ -140,24 +154,33 @@ function App() {
     // Событие дропа предмета
     listen<ItemDropEvent>("item-drop", (event) => {
       console.log("Item dropped:", event.payload);
-      // Обновляем список дропов
-      invoke<AggregatedDrop[]>("get_drops").then(setDrops).catch(console.error);
     }).then((unlisten) => {
       console.log("item-drop listener registered");
       unlisteners.push(unlisten);
     });
 
-    // Обновление цен: пересчитываем totals в списке (иначе будет "-" пока не придёт следующий item-drop)
-    listen("price-update", () => {
-      invoke<AggregatedDrop[]>("get_drops").then(setDrops).catch(console.error);
+    // Backend присылает дроп и статистику при любом изменении (дроп, цены, карты, траты)
+    listen<DropsDelta<AggregatedDrop>>("drops-updated", (event) => {
+      setDrops((prev) => applyDropsDelta(prev, event.payload));
     }).then((unlisten) => unlisteners.push(unlisten));
 
-    // Событие обновления статистики
-    listen<SessionStats>("stats-update", (event) => {
-      console.log("Stats update received:", event.payload);
+    listen<SessionStats>("session-stats-updated", (event) => {
       setStats(event.payload);
     }).then((unlisten) => unlisteners.push(unlisten));
 
+    // Секундомер сессии тикает на backend
+    listen<number>("session-duration", (event) => {
+      setStats((prev) => (prev ? { ...prev, duration_sec: event.payload } : prev));
+    }).then((unlisten) => unlisteners.push(unlisten));
+
+    // Вход/выход (в т.ч. протухший refresh token) пушит backend
+    listen<AuthStateChange>("auth-state-changed", (event) => {
+      setAuth({ is_logged_in: event.payload.is_logged_in, email: event.payload.email });
+      if (!event.payload.is_logged_in) {
+        setProfile(null);
+      }
+    }).then((unlisten) => unlisteners.push(unlisten));
+
     // Событие что нужен путь к логам
     listen("log-path-needed", () => {
       console.log("Log path configuration needed");
@@ -194,33 +217,21 @@ function App() {
       }
     };
 
+    // Данные приходят push-событиями; разовая пересинхронизация при возврате в окно
+    // восстанавливает пропущенное событие без опроса по таймеру
+    const onVisibilityChange = () => {
+      if (document.visibilityState === "visible") sync();
+    };
     sync();
-    const id = window.setInterval(sync, 2000);
+    window.addEventListener("focus", sync);
+    document.addEventListener("visibilitychange", onVisibilityChange);
     return () => {
       cancelled = true;
-      window.clearInterval(id);
+      window.removeEventListener("focus", sync);
+      document.removeEventListener("visibilitychange", onVisibilityChange);
     };
   }, []);
 
-  // Тикер для таймера: обновляем stats даже если в логе нет новых событий.
-  useEffect(() => {
-    if (!isSessionActive) return;
-
-    let cancelled = false;
-    const id = window.setInterval(() => {
-      invoke<SessionStats>("get_session_stats")
-        .then((s) => {
-          if (!cancelled) setStats(s);
-        })
-        .catch(console.error);
-    }, 1000);
-
-    return () => {
-      cancelled = true;
-      window.clearInterval(id);
-    };
-  }, [isSessionActive]);
-
   // Начать сессию
   const handleStartSession = async () => {
     try {
@@ -235,6 +246,8 @@ function App() {
         avg_map_duration_sec: 0,
         stale_price_lines: 0,
         hourly_profit: 0,
+        map_time_sec: 0,
+        map_hourly_profit: 0,
         is_paused: false,
       });
       setDrops([]);
@@ -285,7 +298,7 @@ function App() {
   const getItemDisplayName = (drop: AggregatedDrop): string => {
     // Приоритет 1: данные из Supabase (через item_info)
     if (drop.item_info) {
-      // Пока только английские названия, потом добавим переключение языка
+      // name уже локализован backend'ом по языку из настроек
       return drop.item_info.name || drop.item_info.name_en || `ID: ${drop.game_id}`;
     }
     // Приоритет 2: локальный fallback (для offline режима)
@@ -578,7 +591,7 @@ function App() {
           <div className="drops-list">
             {drops.map((drop) => (
               <div 
-                key={drop.game_id} 
+                key={`${drop.game_id}-${drop.tier ?? ''}`} 
                 className={`drop-item ${!isItemKnown(drop) ? 'unknown' : ''}`}
               >
                 <div className="drop-info">
@@ -586,6 +599,11 @@ function App() {
                     {getItemDisplayName(drop)}
                   </span>
                   <span className="drop-qty">x{drop.quantity}</span>
+                  {drop.is_adjusted && (
+                    <span className="drop-adjusted-badge" title={`Количество исправлено вручную (${drop.adjusted_delta > 0 ? '+' : ''}${drop.adjusted_delta})`}>
+                      ✎
+                    </span>
+                  )}
                 </div>
                 <span className="drop-value">
                   {drop.total_value > 0 ? (
@@ -597,6 +615,15 @@ function App() {
                         </span>
                       ) : drop.price_is_stale ? (
                         <span className="price-stale-badge">стар. {formatPriceAge(drop.price_updated_at)}</span>
+                      ) : drop.price_confidence?.level === "low" ? (
+                        <span
+                          className="price-low-confidence-badge"
+                          title={drop.price_confidence.sample_count
+                            ? `Цена по ${drop.price_confidence.sample_count} лот(ам) — оценка ненадёжна`
+                            : "Мало данных о цене — оценка ненадёжна"}
+                        >
+                          ?
+                        </span>
                       ) : null}
                     </span>
                   ) : (
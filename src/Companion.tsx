@@ -0,0 +1,102 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+// Окно-компаньон: компактная живая статистика сессии (только чтение).
+// Окном управляет backend (commands: show/hide/toggle_companion_window).
+
+interface SessionStats {
+  total_value: number;
+  maps_completed: number;
+  duration_sec: number;
+  hourly_profit: number;
+  is_paused: boolean;
+}
+
+interface CompanionWindowSettings {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+  opacity: number;
+  visible: boolean;
+}
+
+interface AppSettings {
+  companion_window: CompanionWindowSettings;
+}
+
+const formatDuration = (seconds: number): string => {
+  const h = Math.floor(seconds / 3600);
+  const m = Math.floor((seconds % 3600) / 60);
+  const s = seconds % 60;
+  return `${h.toString().padStart(2, "0")}:${m.toString().padStart(2, "0")}:${s.toString().padStart(2, "0")}`;
+};
+
+const formatNumber = (num: number): string => {
+  const abs = Math.abs(num);
+  // Те же правила, что value_format::compact в backend
+  if (abs >= 1000000) return (num / 1000000).toFixed(1) + "M";
+  if (abs >= 10000) return (num / 1000).toFixed(1) + "k";
+  if (abs >= 100) return num.toFixed(0);
+  if (abs >= 10) return num.toFixed(1);
+  if (abs === 0) return "0";
+  return num.toFixed(2);
+};
+
+function Companion() {
+  const [stats, setStats] = useState<SessionStats | null>(null);
+  const [active, setActive] = useState(false);
+  const [opacity, setOpacity] = useState(0.85);
+
+  useEffect(() => {
+    const unlisteners: (() => void)[] = [];
+
+    invoke<boolean>("is_session_active").then(setActive).catch(console.error);
+    invoke<SessionStats>("get_session_stats").then(setStats).catch(console.error);
+    invoke<AppSettings>("get_settings")
+      .then((s) => setOpacity(s.companion_window.opacity))
+      .catch(console.error);
+
+    listen<SessionStats>("session-stats-updated", (event) => {
+      setStats(event.payload);
+      invoke<boolean>("is_session_active").then(setActive).catch(console.error);
+    }).then((unlisten) => unlisteners.push(unlisten));
+
+    listen<number>("session-duration", (event) => {
+      setStats((prev) => (prev ? { ...prev, duration_sec: event.payload } : prev));
+    }).then((unlisten) => unlisteners.push(unlisten));
+
+    listen<CompanionWindowSettings>("companion-window-updated", (event) => {
+      setOpacity(event.payload.opacity);
+    }).then((unlisten) => unlisteners.push(unlisten));
+
+    return () => unlisteners.forEach((fn) => fn());
+  }, []);
+
+  return (
+    <div className="companion" data-tauri-drag-region style={{ opacity }}>
+      {active && stats ? (
+        <>
+          <div className="companion-row" data-tauri-drag-region>
+            <span className="companion-time">{formatDuration(stats.duration_sec)}</span>
+            {stats.is_paused && <span className="companion-paused">II</span>}
+          </div>
+          <div className="companion-row" data-tauri-drag-region>
+            <span className="companion-value">{formatNumber(stats.total_value)} FE</span>
+            <span className="companion-rate">{formatNumber(stats.hourly_profit)}/h</span>
+          </div>
+          <div className="companion-row companion-muted" data-tauri-drag-region>
+            {stats.maps_completed} maps
+          </div>
+        </>
+      ) : (
+        <div className="companion-row companion-muted" data-tauri-drag-region>
+          No active session
+        </div>
+      )}
+    </div>
+  );
+}
+
+export default Companion;
@@ -4,8 +4,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { getCurrentWindow, PhysicalPosition } from "@tauri-apps/api/window";
 import { relaunch } from "@tauri-apps/plugin-process";
-import { check } from "@tauri-apps/plugin-updater";
-import { open } from "@tauri-apps/plugin-dialog";
+import { open, save } from "@tauri-apps/plugin-dialog";
 import { writeText } from "@tauri-apps/plugin-clipboard-manager";
 import { open as shellOpen } from "@tauri-apps/plugin-shell";
 import "./Overlay.css";
@@ -30,6 +29,7 @@ import {
   IconHeart,
   IconExternalLink,
 } from "./icons";
+import { applyDropsDelta, DropsDelta } from "./dropsDelta";
 
 // ============================================
 // Tooltip Component
@@ -89,6 +89,33 @@ const translations = {
     resume: 'Продолжить',
     paused: 'ПАУЗА',
     pausedDescription: 'Дроп не записывается. Нажмите для продолжения',
+    pausedGameClosed: 'Игра закрыта — сессия продолжится при запуске',
+    pausedAfk: 'Нет активности — сессия продолжится с новым дропом или картой',
+    pausedTotal: 'На паузе',
+    afkPause: 'AFK-автопауза через, мин (0 — выкл)',
+    abandonedMapCap: 'Карта брошена, если дольше, мин (0 — выкл)',
+    abandonedMaps: 'Брошено карт',
+    abandonedMapHint: 'Брошенная карта: слишком долгая (смерть и выход из игры, AFK) — не входит в среднее время карты',
+    farmBlockSoon: 'Скоро блок фарма',
+    farmBlockNow: 'Блок фарма начался',
+    farmBlockIn: 'через {n} мин',
+    farmBlockAutoStarted: 'сессия запущена',
+    profitAlarm: 'Будильник профита',
+    profitAlarmOff: 'выкл',
+    profitAlarmHit: 'Цель по профиту достигнута',
+    startupRecovered: 'Восстановлено из резервной копии',
+    updateRequired: 'Обновите приложение',
+    updateSchemaChanged: 'Сервер изменился, часть функций может не работать',
+    saveCustomItem: 'В базу',
+    bulkDrops: 'Списком (2x Имя @ цена)',
+    markSold: 'Продано (Enter — подтвердить цену)',
+    unmarkSold: 'Снять отметку о продаже',
+    pendingSales: 'Ждёт продажи',
+    bulkPreview: 'Разобрать',
+    bulkAdd: 'Добавить всё',
+    saveCustomItemHint: 'Сохранить как свой предмет с ценой',
+    startupReset: 'Файл повреждён и сброшен',
+    startupNewerVersion: 'Файл от более новой версии — не загружен и не будет перезаписан',
     
     // Stats
     time: 'Время',
@@ -96,6 +123,7 @@ const translations = {
     remaining: 'Осталось',
     perMap: 'На карту',
     fePerHour: 'FE/ч',
+    fePerMapHour: 'FE/ч на картах',
     income: 'Доход',
     expenses: 'Траты',
     commission: 'Комиссия',
@@ -125,6 +153,8 @@ const translations = {
     // Manual entry
     searchItem: 'Поиск предмета...',
     quantity: 'Кол-во',
+    tier: 'Тир',
+    currency: 'Валюта',
     price: 'Цена (FE)',
     add: 'Добавить',
     addCustomItem: 'Добавить вручную',
@@ -134,16 +164,196 @@ const translations = {
     login: 'Войти',
     logout: 'Выйти',
     loginViaKripika: 'Войти через kripika.com',
+    loginWithCode: 'Войти по коду',
+    loginWithToken: 'Войти по токену',
+    tokenPlaceholder: 'Refresh token',
+    deviceCodeHint: 'Откройте ссылку, войдите и подтвердите код:',
     loggingIn: 'Входим...',
     logoutConfirm: 'Выйти из аккаунта?',
+    otherAccounts: 'Другие аккаунты',
+    addAccount: 'Добавить аккаунт',
     yes: 'Да',
     no: 'Нет',
     sessionHistory: 'История сессий',
     noSessions: 'Нет сохранённых сессий',
     deleteSession: 'Удалить сессию',
+    exportXlsx: 'Экспорт в Excel (.xlsx)',
+    exportJournal: 'Журнал событий (.ndjson)',
+    replaySession: 'Статистика по времени сессии',
+    categoryFees: 'Комиссия по категориям',
+    categoryFeesHint: 'Пусто — общая ставка. «Без комиссии» — для предметов, которые продаёте напрямую.',
+    noFee: 'Без комиссии',
+    copySummary: 'Копировать сводку:',
+    summaryCopied: 'Скопировано!',
+    liveShare: 'Трансляция',
+    liveShareStart: 'Начать трансляцию сессии',
+    spectate: 'Напарник',
+    spectateCode: 'Код трансляции',
+    spectateEnded: 'закончил',
+    group: 'Группа',
+    groupCode: 'Код группы (пусто — новая)',
+    groupLeave: 'Выйти из группы',
+    lootSplitCopy: 'Поделить поровну и скопировать',
+    cacheTitle: 'Кэш предметов и цен',
+    cacheMaxPrices: 'Максимум цен',
+    cachePriceTtl: 'Цены прошлых лиг, дней (0 — хранить)',
+    noiseTitle: 'Шумные строки лога (regex, по одному в строке)',
+    noiseHint: 'Совпавшие строки отбрасываются до разбора',
+    leagueTitle: 'Лига',
+    archiveLeague: 'Началась новая лига — перенести прошлую в архив',
+    archiveLeagueConfirm: 'История, цены и рекорды прошлой лиги уйдут в архив (только просмотр)',
+    archivedSessions: 'сессий',
+    appLogLevel: 'Подробность лога приложения',
+    copyAppLogs: 'Скопировать последние 200 строк лога',
+    hideWhenUnfocused: 'Прятать, когда игра не в фокусе',
+    clickThrough: 'Сквозь клики',
+    clickThroughHint: 'Окно пропускает мышь в игру. Выключить — хоткеем:',
+    popOutDrops: 'Открыть дроп в отдельном окне',
+    popOutTicker: 'Лента лута в отдельном окне',
+    summaryCard: 'Карточка PNG (сохранить и скопировать)',
     
     // Settings
     language: 'Язык',
+    updateChannel: 'Канал обновлений',
+    gameClient: 'Клиент игры',
+    cloudSync: 'Синхронизация сессий с облаком',
+    cloudSyncOn: 'Вкл',
+    cloudSyncOff: 'Выкл',
+    googleSheets: 'Google Таблица',
+    sheetsSpreadsheetId: 'ID таблицы (из ссылки)',
+    sheetsSheetName: 'Лист',
+    sheetsCredentialPlaceholder: 'JSON-ключ сервисного аккаунта или OAuth-токен',
+    sheetsSaveCredential: 'Сохранить ключ',
+    sheetsClearCredential: 'Удалить ключ',
+    sheetsTest: 'Проверить',
+    sheetsConnected: 'Подключено',
+    sheetsShareWith: 'Откройте таблице доступ на редактирование для',
+    sheetsAccessToken: 'Используется OAuth-токен (действует ограниченное время)',
+    sheetsHint: 'Каждая завершённая сессия добавляется строкой. Ключ хранится в системном хранилище паролей',
+    offlineMode: 'Офлайн-режим',
+    priceSyncPaused: 'Обновление цен на паузе',
+    priceSyncLast: 'Цены обновлены',
+    priceSyncNext: 'следующее',
+    supabaseDown: 'Сервер недоступен — запросы приостановлены',
+    dropSounds: 'Звуки на дроп',
+    dropSoundsVolume: 'Громкость',
+    dropSoundsAddRule: '+ Правило',
+    dropSoundsMinValue: 'От стоимости',
+    dropSoundsAnyCategory: 'Любая категория',
+    dropSoundsFile: 'Свой файл…',
+    dropSoundsHint: 'Играет первое подходящее правило — даже когда окно свёрнуто',
+    tts: 'Голосовые объявления',
+    ttsMinValue: 'Дроп дороже',
+    ttsInterval: 'Не чаще раза в, с',
+    ttsPreview: 'Проверить',
+    ttsHint: 'Системный синтезатор речи называет предмет и стоимость на языке интерфейса',
+    controllerApi: 'Stream Deck / контроллеры',
+    controllerPort: 'Порт',
+    controllerCopyToken: 'Копировать токен',
+    controllerNewToken: 'Новый токен',
+    controllerCopied: 'Скопировано',
+    controllerNotRunning: 'Сервер не запущен (порт занят?)',
+    liveStats: 'Файл live_stats.json',
+    liveStatsDefaultPath: 'Путь (по умолчанию — папка данных приложения)',
+    liveStatsInterval: 'Обновлять раз в, с',
+    liveStatsTopDrops: 'Топ дропов',
+    liveStatsWriteNow: 'Записать сейчас',
+    liveStatsWritten: 'Записано',
+    liveStatsHint: 'Статистика сессии и самые ценные дропы для таблиц, текстовых источников OBS и скриптов — без сети',
+    twitch: 'Чат Twitch',
+    twitchChannel: 'Канал',
+    twitchLogin: 'Логин бота (необязательно)',
+    twitchToken: 'OAuth-токен (chat:edit)',
+    twitchTokenSaved: 'Токен сохранён — введите новый, чтобы заменить',
+    twitchTest: 'Тестовое сообщение',
+    twitchSent: 'Отправлено',
+    twitchMute: 'Приглушить до конца сессии',
+    twitchUnmute: 'Снова объявлять',
+    twitchHint: 'Дропы: {item}, {quantity}, {value}. Итог сессии: {maps}, {duration}, {profit}, {per_hour}, {income}',
+    obs: 'Автоматизация OBS',
+    obsPassword: 'Пароль obs-websocket',
+    obsSessionStart: 'Старт сессии',
+    obsSessionEnd: 'Конец сессии',
+    obsBigDrop: 'Крупный дроп',
+    obsSwitchScene: 'Сцена',
+    obsSourceVisible: 'Источник',
+    obsScene: 'Сцена',
+    obsSource: 'Источник',
+    obsShow: 'Показать',
+    obsHide: 'Скрыть',
+    obsToggle: 'Переключить',
+    obsActionDone: 'Готово',
+    obsHint: 'OBS 28+: Инструменты → Настройки сервера WebSocket. Действия выполняются по событиям сессии',
+    controllerHint: 'Локальный API: GET /v1/stats, POST /v1/session/start|pause|resume|toggle-pause|end, SSE /v1/events. Токен — в заголовке Authorization: Bearer или ?token=',
+    unknownItems: 'Неизвестные предметы',
+    unknownItemsAutoReport: 'Отправлять после сессии',
+    unknownItemsReport: 'Отправить сейчас',
+    unknownItemsClear: 'Очистить',
+    unknownItemsSent: 'Отправлено',
+    unknownItemsNone: 'Все подобранные предметы есть в справочнике',
+    unknownItemsHint: 'Этих предметов нет в справочнике — их дроп не засчитан. Отправка помогает добавить их в базу (id, число подборов, имя из лога)',
+    itemsList: 'Список предметов',
+    itemsRefresh: 'Обновить список',
+    itemsUpToDate: 'Изменений нет',
+    itemsNew: 'Новые',
+    itemsRenamed: 'Переименованы',
+    itemsRecategorized: 'Сменили категорию',
+    itemsRefreshHint: 'После патча игры: подтянуть новые предметы без перезапуска',
+    proxy: 'Прокси',
+    proxyHost: 'Хост',
+    proxyPort: 'Порт',
+    proxyUsername: 'Логин (необязательно)',
+    proxyPassword: 'Пароль',
+    proxyTest: 'Проверить соединение',
+    proxyOk: 'Соединение есть',
+    proxyFailed: 'Нет соединения',
+    proxyHint: 'Все запросы к серверу идут через прокси. Пароль хранится в системном хранилище паролей',
+    backend: 'Сервер цен',
+    backendBuiltin: 'Встроенный',
+    backendCustom: 'Свой',
+    backendUrl: 'Адрес (https://…)',
+    backendAnonKey: 'Публичный ключ (anon key)',
+    backendTablePrefix: 'Префикс таблиц',
+    backendRpcPrefix: 'Префикс RPC',
+    backendApply: 'Проверить и применить',
+    backendApplied: 'Сервер применён',
+    backendRejected: 'Сервер не прошёл проверку',
+    backendIncompatible: 'Схема сервера несовместима с этой версией',
+    backendHint: 'Для сообществ со своим сервером цен (совместимым с Supabase). Адрес сохраняется только после проверки',
+    netWorth: 'Состояние',
+    netWorthUnpriced: 'Без цены',
+    netWorthAllGroups: 'Все категории',
+    mapRuns: 'Карты',
+    profitCurve: 'Профит',
+    recordBestMap: 'Рекорд карты',
+    recordFastestMap: 'Быстрейшая карта',
+    recordBroken: 'Новый рекорд!',
+    rawCurrency: 'Живые FE',
+    recoveredSession: 'Восстановлена после сбоя',
+    recoveredHint: 'Приложение закрылось аварийно — дроп и время за пропуск могут быть недосчитаны',
+    exportHistoryJson: 'Экспорт истории (JSON) для другого компьютера',
+    importHistory: 'Импорт истории из файла',
+    importHistoryResult: 'Импортировано',
+    importHistorySkipped: 'уже были',
+    revalueSession: 'Пересчитать по текущим ценам',
+    revaluedProfit: 'По текущим ценам',
+    rawCurrencyStart: 'FE на старте',
+    rawCurrencyEnd: 'FE сейчас',
+    apiError_auth: 'Нужно войти заново',
+    apiError_rate_limited: 'Слишком много запросов, попробуйте позже',
+    apiError_network: 'Нет связи с сервером',
+    apiError_schema: 'Несовместимый ответ сервера — обновите приложение',
+    apiError_server: 'Ошибка сервера',
+    apiError_request: 'Запрос отклонён сервером',
+    gameClientAuto: 'Авто',
+    gameClientGlobal: 'Глобальный',
+    gameClientCn: 'Китайский',
+    logTimeZone: 'Часовой пояс лога',
+    logTimeZoneLocal: 'Системный',
+    logTimeZoneUtc: 'UTC',
+    logTimeZoneFixed: 'Смещение',
+    logUtcOffset: 'Смещение от UTC, мин',
+    logTimeZoneHint: 'Игра пишет в лог местное время. Системный пояс учитывает переход на летнее время; смещение — если игра запущена на машине с другим поясом',
     interfaceOrientation: 'Ориентация интерфейса',
     vertical: 'Вертикальная',
     horizontal: 'Горизонтальная',
@@ -163,6 +373,8 @@ const translations = {
     
     // Log modal
     logNotFound: 'Лог-файл не найден',
+    foundLogFiles: 'Найденные лог-файлы',
+    modifiedAgo: 'изменён',
     logRequired: 'Для работы TLI Companion нужен лог-файл игры.',
     pathToFile: 'Путь к файлу',
     fullPathExample: 'Пример полного пути',
@@ -171,6 +383,17 @@ const translations = {
     // Status
     logActive: 'Лог активен',
     logInactive: 'Лог неактивен',
+    logCatchup: 'Чтение лога',
+    logAttach: 'Чтение лога при запуске',
+    logAttachEnd: 'С конца',
+    logAttachSince: 'С момента',
+    logAttachHint: 'Применяется один раз при следующем запуске. Дропы с выбранного момента (но не раньше начала сессии) попадут в текущую сессию',
+    farmingZones: 'Учитываемые зоны',
+    farmingZonesAll: 'Все',
+    farmingZonesOnly: 'Только карты',
+    farmingZonesHint: 'Дроп в убежище, городе, кампании и испытаниях не считается, такие зоны — не карты',
+    autoPause: 'Пауза, когда игра закрыта',
+    autoPauseHint: 'Сессия встаёт на паузу при закрытии или вылете игры и продолжается при запуске',
     synced: 'Синхр.',
     notSynced: 'Не синхр.',
     
@@ -211,6 +434,33 @@ const translations = {
     resume: 'Resume',
     paused: 'PAUSED',
     pausedDescription: 'Drops not recorded. Click to continue',
+    pausedGameClosed: 'Game closed — session resumes when it starts',
+    pausedAfk: 'No activity — session resumes on the next drop or map',
+    pausedTotal: 'Paused',
+    afkPause: 'AFK auto-pause after, min (0 — off)',
+    abandonedMapCap: 'Map is abandoned after, min (0 — off)',
+    abandonedMaps: 'Abandoned maps',
+    abandonedMapHint: 'Abandoned map: ran too long (died and logged out, AFK) — excluded from the average map time',
+    farmBlockSoon: 'Farm block soon',
+    farmBlockNow: 'Farm block started',
+    farmBlockIn: 'in {n} min',
+    farmBlockAutoStarted: 'session started',
+    profitAlarm: 'Profit alarm',
+    profitAlarmOff: 'off',
+    profitAlarmHit: 'Profit target reached',
+    startupRecovered: 'Restored from backup',
+    updateRequired: 'Please update the app',
+    updateSchemaChanged: 'The server has changed, some features may not work',
+    saveCustomItem: 'Save',
+    bulkDrops: 'Bulk (2x Name @ price)',
+    markSold: 'Sold (Enter to confirm price)',
+    unmarkSold: 'Unmark as sold',
+    pendingSales: 'Pending sales',
+    bulkPreview: 'Preview',
+    bulkAdd: 'Add all',
+    saveCustomItemHint: 'Save as a custom item with price',
+    startupReset: 'File was corrupt and has been reset',
+    startupNewerVersion: 'File is from a newer version — not loaded and will not be overwritten',
     
     // Stats
     time: 'Time',
@@ -218,6 +468,7 @@ const translations = {
     remaining: 'Remaining',
     perMap: 'Per map',
     fePerHour: 'FE/hr',
+    fePerMapHour: 'FE/hr in maps',
     income: 'Income',
     expenses: 'Expenses',
     commission: 'Commission',
@@ -247,6 +498,8 @@ const translations = {
     // Manual entry
     searchItem: 'Search item...',
     quantity: 'Qty',
+    tier: 'Tier',
+    currency: 'Currency',
     price: 'Price (FE)',
     add: 'Add',
     addCustomItem: 'Add manually',
@@ -256,16 +509,196 @@ const translations = {
     login: 'Login',
     logout: 'Logout',
     loginViaKripika: 'Login via kripika.com',
+    loginWithCode: 'Login with code',
+    loginWithToken: 'Login with token',
+    tokenPlaceholder: 'Refresh token',
+    deviceCodeHint: 'Open the link, sign in and confirm the code:',
     loggingIn: 'Logging in...',
     logoutConfirm: 'Log out?',
+    otherAccounts: 'Other accounts',
+    addAccount: 'Add account',
     yes: 'Yes',
     no: 'No',
     sessionHistory: 'Session History',
     noSessions: 'No saved sessions',
     deleteSession: 'Delete session',
+    exportXlsx: 'Export to Excel (.xlsx)',
+    exportJournal: 'Event journal (.ndjson)',
+    replaySession: 'Stats over session time',
+    categoryFees: 'Fee by category',
+    categoryFeesHint: 'Empty — the default rate. "No fee" — for items you sell via direct trade.',
+    noFee: 'No fee',
+    copySummary: 'Copy summary:',
+    summaryCopied: 'Copied!',
+    liveShare: 'Live share',
+    liveShareStart: 'Start sharing the session live',
+    spectate: 'Partner',
+    spectateCode: 'Share code',
+    spectateEnded: 'ended',
+    group: 'Group',
+    groupCode: 'Group code (empty — new group)',
+    groupLeave: 'Leave the group',
+    lootSplitCopy: 'Split evenly and copy',
+    cacheTitle: 'Item and price cache',
+    cacheMaxPrices: 'Max prices',
+    cachePriceTtl: 'Old league prices, days (0 — keep)',
+    noiseTitle: 'Noisy log lines (regex, one per line)',
+    noiseHint: 'Matching lines are dropped before parsing',
+    leagueTitle: 'League',
+    archiveLeague: 'A new league has started — archive the previous one',
+    archiveLeagueConfirm: 'History, prices and records of the previous league will move to a read-only archive',
+    archivedSessions: 'sessions',
+    appLogLevel: 'App log level',
+    copyAppLogs: 'Copy the last 200 log lines',
+    hideWhenUnfocused: 'Hide when the game is not focused',
+    clickThrough: 'Click-through',
+    clickThroughHint: 'The window passes the mouse to the game. Turn off with the hotkey:',
+    popOutDrops: 'Open drops in a separate window',
+    popOutTicker: 'Loot ticker in a separate window',
+    summaryCard: 'PNG card (save and copy)',
     
     // Settings
     language: 'Language',
+    updateChannel: 'Update channel',
+    gameClient: 'Game client',
+    cloudSync: 'Sync sessions to cloud',
+    cloudSyncOn: 'On',
+    cloudSyncOff: 'Off',
+    googleSheets: 'Google Sheet',
+    sheetsSpreadsheetId: 'Spreadsheet ID (from the link)',
+    sheetsSheetName: 'Sheet',
+    sheetsCredentialPlaceholder: 'Service account JSON key or OAuth token',
+    sheetsSaveCredential: 'Save key',
+    sheetsClearCredential: 'Remove key',
+    sheetsTest: 'Test',
+    sheetsConnected: 'Connected',
+    sheetsShareWith: 'Share the sheet with edit access to',
+    sheetsAccessToken: 'Using an OAuth token (expires after a while)',
+    sheetsHint: 'Each completed session is appended as a row. The key is kept in the OS credential store',
+    offlineMode: 'Offline mode',
+    priceSyncPaused: 'Price updates paused',
+    priceSyncLast: 'Prices updated',
+    priceSyncNext: 'next',
+    supabaseDown: 'Server unreachable — requests paused',
+    dropSounds: 'Drop sounds',
+    dropSoundsVolume: 'Volume',
+    dropSoundsAddRule: '+ Rule',
+    dropSoundsMinValue: 'Min value',
+    dropSoundsAnyCategory: 'Any category',
+    dropSoundsFile: 'Custom file…',
+    dropSoundsHint: 'The first matching rule plays — even while the window is minimized',
+    tts: 'Voice announcements',
+    ttsMinValue: 'Drops worth over',
+    ttsInterval: 'At most once per, s',
+    ttsPreview: 'Test',
+    ttsHint: 'The system speech synthesizer reads out the item and its value in the interface language',
+    controllerApi: 'Stream Deck / controllers',
+    controllerPort: 'Port',
+    controllerCopyToken: 'Copy token',
+    controllerNewToken: 'New token',
+    controllerCopied: 'Copied',
+    controllerNotRunning: 'Server is not running (port in use?)',
+    liveStats: 'live_stats.json file',
+    liveStatsDefaultPath: 'Path (defaults to the app data folder)',
+    liveStatsInterval: 'Update every, s',
+    liveStatsTopDrops: 'Top drops',
+    liveStatsWriteNow: 'Write now',
+    liveStatsWritten: 'Written',
+    liveStatsHint: 'Session stats and the most valuable drops for spreadsheets, OBS text sources and scripts — no network needed',
+    twitch: 'Twitch chat',
+    twitchChannel: 'Channel',
+    twitchLogin: 'Bot login (optional)',
+    twitchToken: 'OAuth token (chat:edit)',
+    twitchTokenSaved: 'Token saved — enter a new one to replace it',
+    twitchTest: 'Test message',
+    twitchSent: 'Sent',
+    twitchMute: 'Mute until session end',
+    twitchUnmute: 'Resume announcements',
+    twitchHint: 'Drops: {item}, {quantity}, {value}. Session summary: {maps}, {duration}, {profit}, {per_hour}, {income}',
+    obs: 'OBS automation',
+    obsPassword: 'obs-websocket password',
+    obsSessionStart: 'Session start',
+    obsSessionEnd: 'Session end',
+    obsBigDrop: 'Big drop',
+    obsSwitchScene: 'Switch scene',
+    obsSourceVisible: 'Source visibility',
+    obsScene: 'Scene',
+    obsSource: 'Source',
+    obsShow: 'Show',
+    obsHide: 'Hide',
+    obsToggle: 'Toggle',
+    obsActionDone: 'Done',
+    obsHint: 'OBS 28+: Tools → WebSocket Server Settings. Actions run on session events',
+    controllerHint: 'Local API: GET /v1/stats, POST /v1/session/start|pause|resume|toggle-pause|end, SSE /v1/events. Pass the token as Authorization: Bearer or ?token=',
+    unknownItems: 'Unknown items',
+    unknownItemsAutoReport: 'Send after each session',
+    unknownItemsReport: 'Send now',
+    unknownItemsClear: 'Clear',
+    unknownItemsSent: 'Sent',
+    unknownItemsNone: 'All picked-up items are in the catalog',
+    unknownItemsHint: 'These items are missing from the catalog, so their drops were not counted. Sending them helps add them to the database (id, pickup count, name from the log)',
+    itemsList: 'Item list',
+    itemsRefresh: 'Refresh list',
+    itemsUpToDate: 'No changes',
+    itemsNew: 'New',
+    itemsRenamed: 'Renamed',
+    itemsRecategorized: 'Changed category',
+    itemsRefreshHint: 'After a game patch: pick up new items without restarting',
+    proxy: 'Proxy',
+    proxyHost: 'Host',
+    proxyPort: 'Port',
+    proxyUsername: 'Username (optional)',
+    proxyPassword: 'Password',
+    proxyTest: 'Test connection',
+    proxyOk: 'Connected',
+    proxyFailed: 'No connection',
+    proxyHint: 'All server requests go through the proxy. The password is kept in the OS credential store',
+    backend: 'Price server',
+    backendBuiltin: 'Built-in',
+    backendCustom: 'Custom',
+    backendUrl: 'URL (https://…)',
+    backendAnonKey: 'Public (anon) key',
+    backendTablePrefix: 'Table prefix',
+    backendRpcPrefix: 'RPC prefix',
+    backendApply: 'Check and apply',
+    backendApplied: 'Server applied',
+    backendRejected: 'Server failed the check',
+    backendIncompatible: 'Server schema is incompatible with this version',
+    backendHint: 'For communities running their own (Supabase-compatible) price server. The URL is saved only after a check',
+    netWorth: 'Net worth',
+    netWorthUnpriced: 'Unpriced',
+    netWorthAllGroups: 'All categories',
+    mapRuns: 'Maps',
+    profitCurve: 'Profit',
+    recordBestMap: 'Best map',
+    recordFastestMap: 'Fastest map',
+    recordBroken: 'New record!',
+    rawCurrency: 'Raw FE',
+    recoveredSession: 'Recovered after a crash',
+    recoveredHint: 'The app exited abnormally — drops and time during the gap may be undercounted',
+    exportHistoryJson: 'Export history (JSON) for another machine',
+    importHistory: 'Import history from file',
+    importHistoryResult: 'Imported',
+    importHistorySkipped: 'already present',
+    revalueSession: 'Re-value at current prices',
+    revaluedProfit: 'At current prices',
+    rawCurrencyStart: 'FE at start',
+    rawCurrencyEnd: 'FE now',
+    apiError_auth: 'Please sign in again',
+    apiError_rate_limited: 'Too many requests, try again later',
+    apiError_network: 'Cannot reach the server',
+    apiError_schema: 'Unexpected server response — please update the app',
+    apiError_server: 'Server error',
+    apiError_request: 'Request rejected by the server',
+    gameClientAuto: 'Auto',
+    gameClientGlobal: 'Global',
+    gameClientCn: 'CN',
+    logTimeZone: 'Log time zone',
+    logTimeZoneLocal: 'System',
+    logTimeZoneUtc: 'UTC',
+    logTimeZoneFixed: 'Offset',
+    logUtcOffset: 'UTC offset, min',
+    logTimeZoneHint: 'The game logs local time. System zone follows daylight saving changes; use an offset if the game runs on a machine in another time zone',
     interfaceOrientation: 'Interface Orientation',
     vertical: 'Vertical',
     horizontal: 'Horizontal',
@@ -285,6 +718,8 @@ const translations = {
     
     // Log modal
     logNotFound: 'Log file not found',
+    foundLogFiles: 'Found log files',
+    modifiedAgo: 'modified',
     logRequired: 'TLI Companion requires the game log file.',
     pathToFile: 'Path to file',
     fullPathExample: 'Full path example',
@@ -293,6 +728,17 @@ const translations = {
     // Status
     logActive: 'Log active',
     logInactive: 'Log inactive',
+    logCatchup: 'Reading log',
+    logAttach: 'Log reading on start',
+    logAttachEnd: 'From end',
+    logAttachSince: 'Since time',
+    logAttachHint: 'Applies once on next start. Drops since the chosen time (but not before the session started) go into the current session',
+    farmingZones: 'Counted zones',
+    farmingZonesAll: 'All',
+    farmingZonesOnly: 'Maps only',
+    farmingZonesHint: 'Drops in hideout, town, campaign and trials are ignored, and those zones do not count as maps',
+    autoPause: 'Pause while the game is closed',
+    autoPauseHint: 'The session pauses when the game closes or crashes and resumes when it starts',
     synced: 'Synced',
     notSynced: 'Not synced',
     
@@ -322,6 +768,20 @@ const translations = {
 type Lang = 'ru' | 'en';
 const t = (lang: Lang, key: keyof typeof translations.ru) => translations[lang][key] || key;
 
+// Ошибки Supabase приходят строкой "[kind] message" (см. supabase_client.rs)
+const API_ERROR_KINDS = ['auth', 'rate_limited', 'network', 'schema', 'server', 'request'] as const;
+type ApiErrorKind = typeof API_ERROR_KINDS[number];
+
+const describeError = (lang: Lang, e: unknown): string => {
+  const text = String(e);
+  const match = text.match(/^\[(\w+)\] ([\s\S]*)$/);
+  if (!match || !API_ERROR_KINDS.includes(match[1] as ApiErrorKind)) {
+    return text;
+  }
+  const key = `apiError_${match[1]}` as keyof typeof translations.ru;
+  return `${t(lang, key)} (${match[2]})`;
+};
+
 // ============================================
 // Types
 // ============================================
@@ -335,7 +795,20 @@ interface SessionStats {
   avg_map_duration_sec: number;
   stale_price_lines: number;
   hourly_profit: number;
+  map_time_sec: number;
+  map_hourly_profit: number;
+  net_hourly_profit?: number;
+  net_map_hourly_profit?: number;
   is_paused: boolean;
+  pause_reason?: 'manual' | 'game_closed' | 'afk' | null;
+  paused_sec?: number;
+  manual_realized_value?: number;
+  manual_pending_value?: number;
+  recovered?: boolean;
+  recovery_gap_sec?: number;
+  abandoned_maps?: number;
+  abandoned_map_sec?: number;
+  profit_alarm?: { threshold: number; fired_at: string | null } | null;
 }
 
 interface AppSettings {
@@ -347,8 +820,288 @@ interface AppSettings {
   layout_orientation: 'vertical' | 'horizontal';
   panel_direction: 'left' | 'right' | 'top' | 'bottom';
   auction_fee_rate: number;
+  category_fee_rates?: Record<string, number>;
+  no_fee_categories?: string[];
   opacity: number;
   always_on_top: boolean;
+  click_through_hotkey?: string;
+  hide_when_game_unfocused?: boolean;
+  log_level?: string;
+  cache_limits?: CacheLimits;
+  log_noise_patterns?: string[];
+  active_league?: string | null;
+  update_channel?: 'stable' | 'beta';
+  game_client?: 'auto' | 'global' | 'cn';
+  sync_sessions_to_cloud?: boolean;
+  report_unknown_items?: boolean;
+  offline_mode?: boolean;
+  log_attach_mode?: 'end' | 'since';
+  log_attach_since?: string | null;
+  farming_zones_only?: boolean;
+  auto_pause_on_game_exit?: boolean;
+  afk_pause_minutes?: number;
+  abandoned_map_minutes?: number;
+  log_time_zone?: 'local' | 'utc' | 'fixed';
+  log_utc_offset_minutes?: number;
+  google_sheets?: GoogleSheetsSettings;
+  proxy?: ProxySettings;
+  backend?: BackendEndpoint;
+  drop_sounds?: DropSoundSettings;
+  tts?: TtsSettings;
+  controller_api?: { enabled: boolean; port: number };
+  obs?: ObsSettings;
+  twitch?: TwitchSettings;
+  live_stats?: LiveStatsSettings;
+}
+
+interface SharedSessionSnapshot {
+  total_duration_sec: number;
+  maps_completed: number;
+  total_profit: number;
+  is_live: boolean;
+}
+
+interface SpectateUpdate {
+  share_code: string;
+  snapshot: SharedSessionSnapshot | null;
+  error: string | null;
+  ended: boolean;
+}
+
+interface GroupStats {
+  group_id: string;
+  members: { display_name: string; is_me: boolean; is_stale: boolean; income: number; profit: number; maps_completed: number }[];
+  total_profit: number;
+  total_maps: number;
+  fair_share: number;
+  transfers: { from: string; to: string; amount: number }[];
+}
+
+interface CacheLimits {
+  max_items: number;
+  max_prices: number;
+  price_ttl_days: number;
+}
+
+interface CacheStats {
+  items: number;
+  items_bytes: number;
+  prices: number;
+  prices_bytes: number;
+  prices_current_league: number;
+  current_league: string | null;
+  limits: CacheLimits;
+}
+
+interface BulkDropPreview {
+  entries: ManualDropEntry[];
+  errors: { line: number; text: string; error: string }[];
+}
+
+interface PriceSuggestion {
+  game_id: number;
+  last_manual: { price: number; used_at: string; kind: 'manual_drop' | 'expense' } | null;
+  market_price: number | null;
+}
+
+// Совместимость с бэкендом (tli_client_meta)
+interface HandshakeStatus {
+  app_version: string;
+  min_app_version: string | null;
+  server_schema_version: number | null;
+  expected_schema_version: number;
+  update_required: boolean;
+  schema_changed: boolean;
+  message: string | null;
+}
+
+interface StartupWarning {
+  file: string;
+  action: 'restored_from_backup' | 'reset' | 'newer_version';
+  quarantined_to: string | null;
+  error: string;
+  detected_at: string;
+}
+
+interface ArchivedLeague {
+  id: string;
+  league: string;
+  archived_at: string;
+  files: string[];
+}
+
+interface LeagueStatus {
+  active_league: string | null;
+  current_league: string | null;
+  rollover_pending: boolean;
+  archives: ArchivedLeague[];
+}
+
+interface ArchivedLeagueView {
+  league: ArchivedLeague;
+  sessions: SessionHistoryItem[];
+}
+
+interface NoiseFilterStats {
+  lines_checked: number;
+  lines_dropped: number;
+  patterns: { pattern: string; dropped: number }[];
+  invalid: string[];
+}
+
+interface DropSoundRule {
+  enabled: boolean;
+  min_value: number | null;
+  category: string | null;
+  sound: string;
+  volume: number | null;
+}
+
+interface DropSoundSettings {
+  enabled: boolean;
+  volume: number;
+  rules: DropSoundRule[];
+}
+
+interface TtsSettings {
+  enabled: boolean;
+  min_value: number;
+  min_interval_secs: number;
+  volume: number;
+}
+
+interface LiveStatsSettings {
+  enabled: boolean;
+  path: string | null;
+  interval_secs: number;
+  top_drops: number;
+}
+
+interface TwitchSettings {
+  enabled: boolean;
+  channel: string;
+  login: string;
+  drop_min_value: number;
+  drop_template: string;
+  summary_enabled: boolean;
+  summary_template: string;
+  min_interval_secs: number;
+}
+
+interface TwitchStatus {
+  has_token: boolean;
+  muted: boolean;
+}
+
+type ObsTrigger = 'session_start' | 'session_end' | 'big_drop';
+
+type ObsAction =
+  | { kind: 'switch_scene'; scene: string }
+  | { kind: 'set_source_visible'; scene: string; source: string; visible: boolean | null };
+
+interface ObsRule {
+  enabled: boolean;
+  trigger: ObsTrigger;
+  min_value: number | null;
+  action: ObsAction;
+}
+
+interface ObsSettings {
+  enabled: boolean;
+  host: string;
+  port: number;
+  rules: ObsRule[];
+}
+
+interface ObsStatus {
+  obs_version: string;
+  websocket_version: string;
+  current_scene: string | null;
+  scenes: string[];
+}
+
+interface ControllerApiInfo {
+  running: boolean;
+  url: string;
+  token: string;
+}
+
+interface UnknownItem {
+  game_id: number;
+  seen_count: number;
+  total_quantity: number;
+  name: string | null;
+  tier: number | null;
+  first_seen: string;
+  last_seen: string;
+  reported: boolean;
+}
+
+interface CategoryView {
+  id: string;
+  name: string;
+  parent: string | null;
+  path: string;
+  group: string;
+  depth: number;
+  sort_order: number;
+}
+
+interface ItemsDiff {
+  total: number;
+  new_items: ItemInfo[];
+  renamed: { game_id: number; old_name: string; new_name: string }[];
+  category_changes: { game_id: number; name: string; old_category: string; new_category: string }[];
+}
+
+interface ProxySettings {
+  enabled: boolean;
+  kind: 'http' | 'socks5';
+  host: string;
+  port: number;
+  username?: string | null;
+}
+
+interface ConnectionTestResult {
+  ok: boolean;
+  status: number | null;
+  latency_ms: number;
+  error: string | null;
+}
+
+interface BackendEndpoint {
+  enabled: boolean;
+  url: string;
+  anon_key: string;
+  table_prefix: string;
+  rpc_prefix: string;
+}
+
+interface BackendCheckResult {
+  applied: boolean;
+  connection: ConnectionTestResult | null;
+  handshake: HandshakeStatus | null;
+  error: string | null;
+}
+
+interface GoogleSheetsSettings {
+  enabled: boolean;
+  spreadsheet_id: string;
+  sheet_name: string;
+}
+
+type SheetsCredentialInfo =
+  | { kind: 'none' }
+  | { kind: 'service_account'; client_email: string }
+  | { kind: 'access_token' };
+
+interface UpdateInfo {
+  channel: 'stable' | 'beta';
+  current_version: string;
+  available: boolean;
+  latest_version: string | null;
+  release_notes: string | null;
+  release_date: string | null;
 }
 
 interface ItemInfo {
@@ -369,6 +1122,20 @@ interface ExpenseEntry {
   name_ru: string | null;
   quantity: number;
   price: number;
+  quoted?: QuotedPrice | null;
+}
+
+// Цена, введённая в торговой валюте (price — пересчёт в FE)
+interface QuotedPrice {
+  currency_id: number;
+  amount: number;
+}
+
+interface ExchangeRateSeries {
+  currency_id: number;
+  name: string;
+  latest: number | null;
+  points: { at: string; fe_per_unit: number }[];
 }
 
 interface ManualDropEntry {
@@ -378,6 +1145,9 @@ interface ManualDropEntry {
   name_ru: string | null;
   quantity: number;
   price: number;
+  tier?: number | null;
+  sold?: { actual_price: number; sold_at: string } | null;
+  quoted?: QuotedPrice | null;
 }
 
 interface SessionPreset {
@@ -393,6 +1163,7 @@ const STORAGE_KEY_ACTIVE_PRESET = 'tli-active-preset-id';
 
 interface AggregatedDrop {
   game_id: number;
+  tier?: number | null;
   item_info: ItemInfo | null;
   quantity: number;
   total_value: number;
@@ -401,6 +1172,16 @@ interface AggregatedDrop {
   price_is_stale: boolean;
   is_previous_season: boolean;
   league_name: string | null;
+  price_confidence: PriceConfidence | null;
+  is_adjusted: boolean;
+  adjusted_delta: number;
+  fee_rate?: number;
+}
+
+interface PriceConfidence {
+  score: number;
+  level: 'high' | 'medium' | 'low';
+  sample_count: number | null;
 }
 
 interface LogFileStatus {
@@ -410,11 +1191,140 @@ interface LogFileStatus {
   size_bytes: number | null;
 }
 
+interface LogCandidate {
+  path: string;
+  client: 'global' | 'cn';
+  origin: 'known_path' | 'steam_library' | 'registry';
+  last_modified_secs_ago: number | null;
+  size_bytes: number | null;
+}
+
 interface AuthStatus {
   is_logged_in: boolean;
   email: string | null;
 }
 
+// Предохранитель запросов к Supabase
+interface ConnectivityStatus {
+  state: 'closed' | 'open' | 'half_open';
+  consecutive_failures: number;
+  opened_at: string | null;
+  retry_at: string | null;
+  last_error: string | null;
+  short_circuited: number;
+}
+
+interface PriceSyncStatus {
+  last_success_at: string | null;
+  last_attempt_at: string | null;
+  next_run_at: string | null;
+  last_error: string | null;
+  consecutive_failures: number;
+  last_price_count: number;
+  offline: boolean;
+}
+
+interface CatchupProgress {
+  source_id: string;
+  is_catching_up: boolean;
+  bytes_done: number;
+  bytes_total: number;
+  started_at: string | null;
+  finished_at: string | null;
+}
+
+interface NetWorthCategory {
+  category: string;
+  name?: string;
+  group?: string;
+  value: number;
+  item_count: number;
+  unpriced_count: number;
+}
+
+interface NetWorth {
+  snapshot_at: string;
+  source_id: string;
+  total_value: number;
+  categories: NetWorthCategory[];
+  unpriced_count: number;
+  stale_count: number;
+}
+
+interface FarmBlock {
+  id: string;
+  name: string;
+  starts_at: string;
+  duration_min: number;
+  template_id: string | null;
+  preset_id: string | null;
+  remind_before_min: number;
+  auto_start: boolean;
+  reminded: boolean;
+  started: boolean;
+}
+
+interface FarmBlockReminder {
+  block: FarmBlock;
+  minutes_until: number;
+  auto_started: boolean;
+}
+
+interface ValuePoint {
+  at: string;
+  duration_sec: number;
+  total_value: number;
+  net_profit: number;
+}
+
+interface MapRun {
+  index: number;
+  scene_name: string | null;
+  started_at: string;
+  ended_at: string;
+  duration_sec: number;
+  drops: Record<string, number>;
+  value: number | null;
+  abandoned?: boolean;
+}
+
+interface MapRecord {
+  value: number;
+  duration_sec: number;
+  scene_name: string | null;
+  achieved_at: string;
+  session_started_at: string | null;
+}
+
+interface PersonalRecords {
+  best_map_value: MapRecord | null;
+  fastest_map: MapRecord | null;
+}
+
+interface RecordBrokenEvent {
+  kind: 'best_map_value' | 'fastest_map';
+  record: MapRecord;
+  previous: MapRecord | null;
+}
+
+interface DeviceCode {
+  user_code: string;
+  verification_uri: string;
+  verification_uri_complete: string | null;
+  expires_in: number;
+}
+
+interface AccountInfo {
+  user_id: string;
+  email: string | null;
+  last_used_at: string;
+  is_active: boolean;
+}
+
+interface AuthStateChange extends AuthStatus {
+  reason: "signed_in" | "signed_out" | "session_expired";
+}
+
 interface UserProfile {
   id: string;
   username: string | null;
@@ -433,6 +1343,63 @@ interface SessionHistoryItem {
   total_profit: number;
   total_expenses: number;
   total_income: number;
+  raw_currency_gained?: number | null;
+  recovered?: boolean;
+  recovery_gap_sec?: number;
+}
+
+interface HistoryImportReport {
+  total: number;
+  added: number;
+  skipped: number;
+}
+
+// Статистика сессии на момент времени (по журналу событий)
+interface ReplayStats {
+  session_started_at: string | null;
+  last_event_at: string | null;
+  at: string | null;
+  duration_sec: number;
+  map_time_sec: number;
+  maps_completed: number;
+  abandoned_maps: number;
+  total_items: number;
+  unique_items: number;
+  total_value: number;
+  total_fee: number;
+  total_expenses: number;
+  net_profit: number;
+  hourly_profit: number;
+  unpriced_items: number;
+  is_paused: boolean;
+  is_on_map: boolean;
+  events_applied: number;
+}
+
+// Сессия из истории по сегодняшним ценам
+interface SessionRevaluation {
+  session_id: string;
+  original_income: number;
+  original_fee: number;
+  original_profit: number;
+  revalued_income: number;
+  revalued_fee: number;
+  revalued_profit: number;
+  original_manual_pending: number;
+  revalued_manual_pending: number;
+  unpriced_items: number;
+}
+
+interface CurrencyReading {
+  amount: number;
+  source: 'log' | 'manual';
+  observed_at: string;
+}
+
+interface SessionCurrency {
+  start: CurrencyReading | null;
+  current: CurrencyReading | null;
+  gained: number | null;
 }
 
 type ViewMode = 'collapsed' | 'expanded';
@@ -464,11 +1431,13 @@ export function Overlay() {
   const [, setLogPath] = useState<string | null>(null);
   const [version, setVersion] = useState("");
   const [showLogModal, setShowLogModal] = useState(false);
+  const [logCandidates, setLogCandidates] = useState<LogCandidate[]>([]);
   
   // Update state
   const [updateAvailable, setUpdateAvailable] = useState<string | null>(null);
   const [isUpdating, setIsUpdating] = useState(false);
   const [updateProgress, setUpdateProgress] = useState<number | null>(null);
+  const [clickThrough, setClickThrough] = useState(false);
   const [appSettings, setAppSettings] = useState<AppSettings>({
     custom_log_path: null,
     auto_start: false,
@@ -488,15 +1457,77 @@ export function Overlay() {
   // Auth state
   const [auth, setAuth] = useState<AuthStatus | null>(null);
   const [profile, setProfile] = useState<UserProfile | null>(null);
+  const [accounts, setAccounts] = useState<AccountInfo[]>([]);
+  const [deviceCode, setDeviceCode] = useState<DeviceCode | null>(null);
+  const [pastedToken, setPastedToken] = useState('');
+  const [priceSync, setPriceSync] = useState<PriceSyncStatus | null>(null);
+  const [connectivity, setConnectivity] = useState<ConnectivityStatus | null>(null);
+  const [sheetsCredential, setSheetsCredential] = useState<SheetsCredentialInfo>({ kind: 'none' });
+  const [sheetsCredentialDraft, setSheetsCredentialDraft] = useState('');
+  const [cacheStats, setCacheStats] = useState<CacheStats | null>(null);
+  const [noiseStats, setNoiseStats] = useState<NoiseFilterStats | null>(null);
+  const [leagueStatus, setLeagueStatus] = useState<LeagueStatus | null>(null);
+  const [archivedLeague, setArchivedLeague] = useState<ArchivedLeagueView | null>(null);
+  const [confirmArchiveLeague, setConfirmArchiveLeague] = useState(false);
+  const [sheetsStatus, setSheetsStatus] = useState<string | null>(null);
+  const [proxyStatus, setProxyStatus] = useState<string | null>(null);
+  const [itemsDiff, setItemsDiff] = useState<ItemsDiff | null>(null);
+  const [categories, setCategories] = useState<CategoryView[]>([]);
+  const [controllerInfo, setControllerInfo] = useState<ControllerApiInfo | null>(null);
+  const [controllerCopied, setControllerCopied] = useState(false);
+  const [obsStatus, setObsStatus] = useState<ObsStatus | null>(null);
+  const [obsMessage, setObsMessage] = useState<string | null>(null);
+  const [twitchStatus, setTwitchStatus] = useState<TwitchStatus | null>(null);
+  const [twitchMessage, setTwitchMessage] = useState<string | null>(null);
+  const [liveStatsMessage, setLiveStatsMessage] = useState<string | null>(null);
+  const [bundledSounds, setBundledSounds] = useState<{ id: string; name: string }[]>([]);
+  const [itemsRefreshing, setItemsRefreshing] = useState(false);
+  const [unknownItems, setUnknownItems] = useState<UnknownItem[]>([]);
+  const [unknownReportStatus, setUnknownReportStatus] = useState<string | null>(null);
+  const [itemsRefreshError, setItemsRefreshError] = useState<string | null>(null);
+  const [backendDraft, setBackendDraft] = useState<BackendEndpoint | null>(null);
+  const [backendStatus, setBackendStatus] = useState<string | null>(null);
+  const [summaryCopied, setSummaryCopied] = useState(false);
+  const [netWorth, setNetWorth] = useState<NetWorth | null>(null);
+  // Фильтр оценки состояния: id группы или категории из справочника ('' — всё)
+  const [netWorthGroup, setNetWorthGroup] = useState('');
+  const [mapRuns, setMapRuns] = useState<MapRun[]>([]);
+  const [valueTimeline, setValueTimeline] = useState<ValuePoint[]>([]);
+  const [personalRecords, setPersonalRecords] = useState<PersonalRecords | null>(null);
+  const [recordFlash, setRecordFlash] = useState<RecordBrokenEvent | null>(null);
+  const [farmReminder, setFarmReminder] = useState<FarmBlockReminder | null>(null);
+  const [profitAlarmHit, setProfitAlarmHit] = useState<{ threshold: number; net_profit: number } | null>(null);
+  const [startupWarnings, setStartupWarnings] = useState<StartupWarning[]>([]);
+  const [handshake, setHandshake] = useState<HandshakeStatus | null>(null);
+  const [alarmInput, setAlarmInput] = useState<string | null>(null);
+  const [liveShareCode, setLiveShareCode] = useState<string | null>(null);
+  const [spectateInput, setSpectateInput] = useState<string | null>(null);
+  const [partner, setPartner] = useState<{ code: string; snapshot: SharedSessionSnapshot } | null>(null);
+  const [groupId, setGroupId] = useState<string | null>(null);
+  const [groupInput, setGroupInput] = useState<string | null>(null);
+  const [groupStats, setGroupStats] = useState<GroupStats | null>(null);
+  const [catchup, setCatchup] = useState<CatchupProgress[]>([]);
+  const [sessionCurrency, setSessionCurrency] = useState<SessionCurrency | null>(null);
+  const [currencyInput, setCurrencyInput] = useState<{ point: 'start' | 'end'; value: string } | null>(null);
   const [authError, setAuthError] = useState<string | null>(null);
   const [showLogoutConfirm, setShowLogoutConfirm] = useState(false);
   const [sessionHistory, setSessionHistory] = useState<SessionHistoryItem[]>([]);
   const [sessionHistoryLoading, setSessionHistoryLoading] = useState(false);
+  const [revaluations, setRevaluations] = useState<Record<string, SessionRevaluation>>({});
+  const [replay, setReplay] = useState<{ sessionId: string; stats: ReplayStats } | null>(null);
+  const [historyImportReport, setHistoryImportReport] = useState<HistoryImportReport | null>(null);
   
   // Manual entry state
   const [manualSearch, setManualSearch] = useState('');
   const [manualQuantity, setManualQuantity] = useState(1);
   const [manualPrice, setManualPrice] = useState(0);
+  const [manualTier, setManualTier] = useState<number | null>(null);
+  const [manualCurrency, setManualCurrency] = useState<number | null>(null);
+  const [exchangeRates, setExchangeRates] = useState<ExchangeRateSeries[]>([]);
+  const [bulkText, setBulkText] = useState('');
+  const [sellingId, setSellingId] = useState<string | null>(null);
+  const [sellPrice, setSellPrice] = useState(0);
+  const [bulkPreview, setBulkPreview] = useState<BulkDropPreview | null>(null);
   const [selectedItem, setSelectedItem] = useState<ItemInfo | null>(null);
   const [searchResults, setSearchResults] = useState<ItemInfo[]>([]);
   const [showSearchDropdown, setShowSearchDropdown] = useState(false);
@@ -561,33 +1592,71 @@ export function Overlay() {
   // Calculations
   // ============================================
 
-  const calculateFee = useCallback((grossIncome: number, drops: AggregatedDrop[]) => {
-    // Fee applies to everything except Flame Elementium
-    const feIncome = drops
-      .filter(d => d.game_id === BASE_CURRENCY_ID)
-      .reduce((sum, d) => sum + d.total_value, 0);
-    const taxableIncome = grossIncome - feIncome;
-    return taxableIncome > 0 ? taxableIncome * appSettings.auction_fee_rate : 0;
+  // Ставка по категории приходит с бэкенда (fee_rate); FE не продаётся
+  const feeRateOf = useCallback((gameId: number | null | undefined, drops: AggregatedDrop[]) => {
+    if (gameId === BASE_CURRENCY_ID) return 0;
+    return drops.find(d => d.game_id === gameId)?.fee_rate ?? appSettings.auction_fee_rate;
   }, [appSettings.auction_fee_rate]);
 
+  const calculateFee = useCallback((drops: AggregatedDrop[]) => {
+    return drops.reduce((sum, d) => sum + d.total_value * feeRateOf(d.game_id, drops), 0);
+  }, [feeRateOf]);
+
   // Calculate manual drops income
-  const manualDropsIncome = manualDrops.reduce((sum, d) => sum + d.quantity * d.price, 0);
-  // Комиссия только на ручные дропы, которые НЕ являются FE (Flame Elementium)
-  const manualDropsTaxable = manualDrops
-    .filter(d => d.game_id !== BASE_CURRENCY_ID)
-    .reduce((sum, d) => sum + d.quantity * d.price, 0);
-  const manualDropsFee = manualDropsTaxable * appSettings.auction_fee_rate;
+  // Проданное — по фактической цене, остальное — по оценке
+  const manualUnitPrice = (d: ManualDropEntry) => d.sold ? d.sold.actual_price : d.price;
+  const manualDropsIncome = manualDrops.reduce((sum, d) => sum + d.quantity * manualUnitPrice(d), 0);
+  // Комиссия на ручные дропы — по ставке категории связанного предмета
+  const manualDropsFee = manualDrops
+    .reduce((sum, d) => sum + d.quantity * manualUnitPrice(d) * feeRateOf(d.game_id, drops), 0);
   
   // Total income = auto drops (из локального состояния) + manual drops
   const autoDropsIncome = drops.reduce((sum, d) => sum + d.total_value, 0);
   const totalIncome = autoDropsIncome + manualDropsIncome;
-  const totalFee = calculateFee(autoDropsIncome, drops) + manualDropsFee;
+  const totalFee = calculateFee(drops) + manualDropsFee;
+
+  // Имя категории из справочника сервера (путь «Группа/Категория»), иначе сама строка
+  const categoryIndex = new Map(categories.map((c, i) => [c.id, { view: c, order: i }]));
+  const categoryLabel = (id: string) => categoryIndex.get(id)?.view.path ?? id;
+
+  // Категории для настройки комиссии: из текущего дропа и уже настроенные
+  // (в порядке справочника, неизвестные — в конце по алфавиту)
+  const feeCategories = Array.from(new Set([
+    ...drops.map(d => d.item_info?.category).filter((c): c is string => !!c),
+    ...Object.keys(appSettings.category_fee_rates ?? {}),
+    ...(appSettings.no_fee_categories ?? []),
+  ])).sort((a, b) =>
+    (categoryIndex.get(a)?.order ?? Infinity) - (categoryIndex.get(b)?.order ?? Infinity) || a.localeCompare(b));
+
+  const setCategoryFee = (category: string, percent: string) => {
+    const rates = { ...(appSettings.category_fee_rates ?? {}) };
+    const value = parseFloat(percent);
+    if (percent.trim() === '' || isNaN(value)) {
+      delete rates[category];
+    } else {
+      rates[category] = Math.min(Math.max(value, 0), 100) / 100;
+    }
+    handleSaveSettings({ ...appSettings, category_fee_rates: rates });
+  };
+
+  const toggleNoFee = (category: string) => {
+    const current = appSettings.no_fee_categories ?? [];
+    const next = current.includes(category)
+      ? current.filter(c => c !== category)
+      : [...current, category];
+    handleSaveSettings({ ...appSettings, no_fee_categories: next });
+  };
   
   const netProfit = totalIncome - totalExpenses - totalFee;
   // Use displayDuration for profit calculation to account for pauses
   const profitPerHour = displayDuration > 0 
     ? (netProfit / displayDuration) * 3600 
     : 0;
+  // Only time spent on maps (hideout/selling time excluded)
+  const mapTimeSec = stats?.map_time_sec ?? 0;
+  const mapProfitPerHour = mapTimeSec > 0
+    ? (netProfit / mapTimeSec) * 3600
+    : 0;
 
   // ============================================
   // Initialization
@@ -686,6 +1755,7 @@ export function Overlay() {
         try {
           const a = await invoke<AuthStatus>("auth_status");
           setAuth(a);
+          invoke<AccountInfo[]>("list_accounts").then(setAccounts).catch(() => {});
           if (a.is_logged_in) {
             const p = await invoke<UserProfile | null>("get_my_profile");
             setProfile(p);
@@ -790,11 +1860,77 @@ export function Overlay() {
   useEffect(() => {
     const unlisteners: (() => void)[] = [];
 
-    listen<AggregatedDrop>("item-drop", async () => {
-      // Обновляем drops только если сессия активна
+    // Backend сам присылает дроп и статистику при любом изменении состояния
+    listen<DropsDelta<AggregatedDrop>>("drops-updated", async (event) => {
+      // Обновляем drops только если сессия активна (после завершения список нужен для прайсчека)
+      const active = await invoke<boolean>("is_session_active");
+      if (active) {
+        setDrops(prev => applyDropsDelta(prev, event.payload));
+      }
+    }).then(unlisten => unlisteners.push(unlisten));
+
+    // Сессию запустили/поставили на паузу/завершили из трея
+    listen<{ active: boolean; paused: boolean }>("session-state-changed", (event) => {
+      setIsSessionActive(event.payload.active);
+      setIsPaused(event.payload.paused);
+      // Трансляция заканчивается вместе с сессией
+      if (!event.payload.active) setLiveShareCode(null);
+    }).then(unlisten => unlisteners.push(unlisten));
+
+    // Напоминание о запланированном блоке фарма (висит минуту или до клика)
+    let reminderTimer: ReturnType<typeof setTimeout> | undefined;
+    listen<FarmBlockReminder>("farm-block-reminder", (event) => {
+      setFarmReminder(event.payload);
+      clearTimeout(reminderTimer);
+      reminderTimer = setTimeout(() => setFarmReminder(null), 60000);
+    }).then(unlisten => unlisteners.push(unlisten));
+
+    // Режим «сквозь клики» и прозрачность переключаются и хоткеем из backend
+    listen<{ click_through: boolean; opacity: number }>("overlay-mode-changed", (event) => {
+      setClickThrough(event.payload.click_through);
+      setAppSettings(prev => ({ ...prev, opacity: event.payload.opacity }));
+    }).then(unlisten => unlisteners.push(unlisten));
+
+    // Будильник профита сработал: баннер висит до клика
+    listen<{ threshold: number; net_profit: number }>("profit-alarm", (event) => {
+      setProfitAlarmHit(event.payload);
+    }).then(unlisten => unlisteners.push(unlisten));
+
+    // Трансляция напарника: свежий снимок раз в 15 секунд
+    listen<SpectateUpdate>("spectate-updated", (event) => {
+      const { share_code, snapshot } = event.payload;
+      if (snapshot) setPartner({ code: share_code, snapshot });
+      else if (event.payload.ended) setPartner(prev => prev && { ...prev, snapshot: { ...prev.snapshot, is_live: false } });
+    }).then(unlisten => unlisteners.push(unlisten));
+
+    // Автопауза: игра закрылась или запустилась снова
+    listen<{ running: boolean; session_paused: boolean | null }>("game-process", (event) => {
+      if (event.payload.session_paused != null) {
+        setIsPaused(event.payload.session_paused);
+      }
+    }).then(unlisten => unlisteners.push(unlisten));
+
+    listen<AuthStateChange>("auth-state-changed", async (event) => {
+      setAuth({ is_logged_in: event.payload.is_logged_in, email: event.payload.email });
+      invoke<AccountInfo[]>("list_accounts").then(setAccounts).catch(() => {});
+      if (!event.payload.is_logged_in) {
+        setProfile(null);
+        setSessionHistory([]);
+        return;
+      }
+      // Вход или переключение аккаунта: профиль и история — другого пользователя
+      try {
+        setProfile(await invoke<UserProfile | null>("get_my_profile"));
+        setSessionHistory(await invoke<SessionHistoryItem[]>("get_session_history", { limit: 10 }));
+      } catch (e) {
+        console.error("Failed to reload account data:", e);
+      }
+    }).then(unlisten => unlisteners.push(unlisten));
+
+    listen<SessionStats>("session-stats-updated", async (event) => {
       const active = await invoke<boolean>("is_session_active");
       if (active) {
-        invoke<AggregatedDrop[]>("get_drops").then(setDrops).catch(console.error);
+        setStats(event.payload);
       }
     }).then(unlisten => unlisteners.push(unlisten));
 
@@ -803,11 +1939,9 @@ export function Overlay() {
       const newPrices = await invoke<Record<number, number>>("get_cached_prices");
       setPricesCache(newPrices);
       
-      // Обновляем drops только если сессия активна
+      // Во время сессии пересчитанный дроп придёт событием drops-updated
       const active = await invoke<boolean>("is_session_active");
-      if (active) {
-        invoke<AggregatedDrop[]>("get_drops").then(setDrops).catch(console.error);
-      } else {
+      if (!active) {
         // Если сессия завершена — пересчитываем стоимость локально
         setDrops(prevDrops => prevDrops.map(drop => {
           const newPrice = newPrices[drop.game_id];
@@ -826,37 +1960,21 @@ export function Overlay() {
       }
     }).then(unlisten => unlisteners.push(unlisten));
 
-    listen<SessionStats>("stats-update", async (event) => {
-      // Обновляем stats только если сессия активна
-      const active = await invoke<boolean>("is_session_active");
-      if (active) {
-        setStats(event.payload);
-      }
+    listen<number>("session-duration", (event) => {
+      setLocalDuration(event.payload);
     }).then(unlisten => unlisteners.push(unlisten));
 
-    return () => unlisteners.forEach(fn => fn());
-  }, []);
+    // Догонялка большого лога: показываем прогресс в статус-баре
+    invoke<CatchupProgress[]>("get_catchup_progress").then(setCatchup).catch(() => {});
+    listen<CatchupProgress[]>("log-catchup-progress", (event) => {
+      setCatchup(event.payload);
+    }).then(unlisten => unlisteners.push(unlisten));
 
-  // Локальный таймер: каждую секунду увеличиваем время если не на паузе
-  useEffect(() => {
-    if (!isSessionActive || isPaused) return;
-    const id = setInterval(() => {
-      setLocalDuration(prev => prev + 1);
-    }, 1000);
-    return () => clearInterval(id);
-  }, [isSessionActive, isPaused]);
-  
-  // Периодическая синхронизация: сохраняем время на бэкенд и получаем stats
-  useEffect(() => {
-    if (!isSessionActive) return;
-    const id = setInterval(() => {
-      // Сохраняем время на бэкенд
-      invoke("update_session_duration", { durationSec: localDuration }).catch(console.error);
-      // Получаем остальные stats
-      invoke<SessionStats>("get_session_stats").then(setStats).catch(console.error);
-    }, 5000);
-    return () => clearInterval(id);
-  }, [isSessionActive, localDuration]);
+    return () => {
+      clearTimeout(reminderTimer);
+      unlisteners.forEach(fn => fn());
+    };
+  }, []);
 
   // Check log status periodically
   useEffect(() => {
@@ -897,6 +2015,17 @@ export function Overlay() {
   // ============================================
 
 
+  // Сводка сессии в буфер обмена (Discord — Markdown, форумы — BBCode)
+  const handleCopySummary = async (format: 'text' | 'markdown' | 'bbcode') => {
+    try {
+      await invoke<string>("copy_session_summary", { format });
+      setSummaryCopied(true);
+      setTimeout(() => setSummaryCopied(false), 2000);
+    } catch (e) {
+      console.error("Failed to copy session summary:", e);
+    }
+  };
+
   const handleEndSession = async () => {
     try {
       const finalStats = await invoke<SessionStats>("end_session");
@@ -921,6 +2050,100 @@ export function Overlay() {
     }
   };
 
+  // Экспорт в .xlsx: сессия из истории (sessionId) или вся история (null)
+  // PNG-карточка сессии: сохраняется в «Изображения/TLI Companion» и копируется в буфер
+  const handleSummaryCard = async (sessionId: string | null) => {
+    try {
+      await invoke<string>("render_summary_card", { sessionId, copyToClipboard: true });
+      setSummaryCopied(true);
+      setTimeout(() => setSummaryCopied(false), 2000);
+    } catch (e) {
+      console.error("Failed to render summary card:", e);
+    }
+  };
+
+  const handleRevalueSession = async (id: string) => {
+    try {
+      const revaluation = await invoke<SessionRevaluation>("revalue_session", { id });
+      setRevaluations(prev => ({ ...prev, [id]: revaluation }));
+    } catch (e) {
+      console.error("Failed to revalue session:", e);
+    }
+  };
+
+  // Перенос истории между компьютерами: JSON-файл
+  const handleExportHistoryJson = async () => {
+    try {
+      const path = await save({
+        title: L('exportHistoryJson'),
+        defaultPath: 'tli-sessions.json',
+        filters: [{ name: 'JSON', extensions: ['json'] }],
+      });
+      if (!path) return;
+      await invoke<string>("export_session_history", { path });
+    } catch (e) {
+      console.error("Failed to export session history:", e);
+    }
+  };
+
+  const handleImportHistory = async () => {
+    try {
+      const selected = await open({
+        title: L('importHistory'),
+        filters: [{ name: 'JSON', extensions: ['json'] }],
+        multiple: false,
+      });
+      if (!selected || typeof selected !== "string") return;
+      setHistoryImportReport(await invoke<HistoryImportReport>("import_session_history", { path: selected }));
+      await loadSessionHistory();
+    } catch (e) {
+      console.error("Failed to import session history:", e);
+    }
+  };
+
+  const handleExportXlsx = async (sessionId: string | null) => {
+    try {
+      const path = await save({
+        title: L('exportXlsx'),
+        defaultPath: sessionId ? `tli-session-${sessionId.slice(0, 8)}.xlsx` : 'tli-sessions.xlsx',
+        filters: [{ name: 'Excel', extensions: ['xlsx'] }],
+      });
+      if (!path) return;
+      if (sessionId) {
+        await invoke<string>("export_session_xlsx", { path, sessionId });
+      } else {
+        await invoke<string>("export_history_xlsx", { path });
+      }
+    } catch (e) {
+      console.error("Failed to export xlsx:", e);
+    }
+  };
+
+  // Ползунок по времени сессии: статистика на выбранный момент
+  const handleReplay = async (sessionId: string, untilTs: string | null) => {
+    try {
+      const stats = await invoke<ReplayStats>("replay_session_events", { id: sessionId, untilTs });
+      setReplay({ sessionId, stats });
+    } catch (e) {
+      console.error("Failed to replay session events:", e);
+    }
+  };
+
+  // Сырые события сессии для анализа дроп-рейтов: NDJSON
+  const handleExportJournal = async (sessionId: string | null) => {
+    try {
+      const path = await save({
+        title: L('exportJournal'),
+        defaultPath: sessionId ? `tli-events-${sessionId.slice(0, 8)}.ndjson` : 'tli-events.ndjson',
+        filters: [{ name: 'NDJSON', extensions: ['ndjson', 'jsonl'] }],
+      });
+      if (!path) return;
+      await invoke<string>("export_session_journal", { path, sessionId });
+    } catch (e) {
+      console.error("Failed to export event journal:", e);
+    }
+  };
+
   const handleDeleteSession = async (sessionId: string) => {
     try {
       await invoke<boolean>("delete_session_history", { sessionId });
@@ -945,21 +2168,60 @@ export function Overlay() {
         setProfile(null);
       }
     } catch (e: unknown) {
-      setAuthError(String(e));
+      setAuthError(describeError(lang, e));
     }
   };
 
-  const handleAuthSignOut = async () => {
+  const handleDeviceSignIn = async () => {
     setAuthError(null);
+    const unlisten = await listen<DeviceCode>("auth-device-code", (event) => setDeviceCode(event.payload));
     try {
-      await invoke("auth_sign_out");
-      const res = await invoke<AuthStatus>("auth_status");
+      setAuth(await invoke<AuthStatus>("auth_sign_in_device"));
+    } catch (e: unknown) {
+      setAuthError(describeError(lang, e));
+    } finally {
+      unlisten();
+      setDeviceCode(null);
+    }
+  };
+
+  const handleTokenSignIn = async () => {
+    setAuthError(null);
+    try {
+      setAuth(await invoke<AuthStatus>("auth_sign_in_with_token", { refreshToken: pastedToken }));
+      setPastedToken('');
+    } catch (e: unknown) {
+      setAuthError(describeError(lang, e));
+    }
+  };
+
+  const handleDeviceCancel = async () => {
+    await invoke("auth_cancel_login").catch(() => {});
+    setDeviceCode(null);
+  };
+
+  const handleSwitchAccount = async (id: string) => {
+    setAuthError(null);
+    try {
+      // Профиль и история перезагрузятся по событию auth-state-changed
+      setAuth(await invoke<AuthStatus>("switch_account", { id }));
+    } catch (e: unknown) {
+      setAuthError(describeError(lang, e));
+      invoke<AccountInfo[]>("list_accounts").then(setAccounts).catch(() => {});
+    }
+  };
+
+  const handleAuthSignOut = async () => {
+    setAuthError(null);
+    try {
+      await invoke("auth_sign_out");
+      const res = await invoke<AuthStatus>("auth_status");
       setAuth(res);
       setProfile(null);
       setShowLogoutConfirm(false);
       setActiveTab('stats'); // Return to stats after logout
     } catch (e: unknown) {
-      setAuthError(String(e));
+      setAuthError(describeError(lang, e));
     }
   };
 
@@ -986,6 +2248,10 @@ export function Overlay() {
     if (cachedPrice) {
       setManualPrice(cachedPrice);
     }
+    // Последняя введённая вручную цена важнее рыночной
+    invoke<PriceSuggestion>("get_item_price_suggestion", { gameId: item.game_id })
+      .then(s => { if (s.last_manual) setManualPrice(s.last_manual.price); })
+      .catch(() => {});
   };
 
   const handleUseCustomItem = () => {
@@ -994,10 +2260,31 @@ export function Overlay() {
     setShowSearchDropdown(false);
   };
 
+  // Сохранить кастомный предмет в локальную базу: дальше он находится поиском и помнит цену
+  const handleSaveCustomItem = async () => {
+    const name = manualSearch.trim();
+    if (!name) return;
+    try {
+      const item = await invoke<ItemInfo>("create_custom_item", {
+        name,
+        category: 'custom',
+        defaultPrice: manualPrice > 0 ? manualPrice : null,
+      });
+      if (manualPrice > 0) {
+        setPricesCache(prev => ({ ...prev, [item.game_id]: manualPrice }));
+      }
+      handleSelectItem(item);
+    } catch (e) {
+      console.error("Failed to create custom item:", e);
+    }
+  };
+
   const resetManualForm = () => {
     setManualSearch('');
     setManualQuantity(1);
     setManualPrice(0);
+    setManualTier(null);
+    setManualCurrency(null);
     setSelectedItem(null);
     setIsCustomItem(false);
     setSearchResults([]);
@@ -1012,6 +2299,9 @@ export function Overlay() {
     const gameId = selectedItem?.game_id || null;
     const name = selectedItem?.name_en || selectedItem?.name || manualSearch.trim();
     const nameRu = selectedItem?.name_ru || null;
+    // Цена в торговой валюте: в пресете храним её как есть, в FE — по текущему курсу
+    const rate = exchangeRates.find(r => r.currency_id === manualCurrency)?.latest ?? null;
+    const quoted = manualCurrency !== null && rate !== null ? { currency_id: manualCurrency, amount: manualPrice } : null;
     
     // Add to local state (will be synced when preset is used)
     setExpenses(prev => [...prev, { 
@@ -1020,7 +2310,8 @@ export function Overlay() {
       name, 
       name_ru: nameRu, 
       quantity: manualQuantity, 
-      price: manualPrice 
+      price: quoted && rate !== null ? manualPrice * rate : manualPrice,
+      quoted,
     }]);
     
     resetManualForm();
@@ -1079,17 +2370,14 @@ export function Overlay() {
         name, 
         nameRu, 
         quantity: manualQuantity, 
-        price: manualPrice 
+        price: manualPrice,
+        tier: manualTier,
+        currencyId: manualCurrency
       });
       
-      setManualDrops(prev => [...prev, { 
-        id, 
-        game_id: gameId, 
-        name, 
-        name_ru: nameRu, 
-        quantity: manualQuantity, 
-        price: manualPrice 
-      }]);
+      // Цена в FE (с пересчётом по курсу) — в записи из бэкенда
+      const entries = await invoke<ManualDropEntry[]>("get_manual_drops");
+      setManualDrops(entries);
       
       resetManualForm();
       setActiveTab('stats'); // Return to stats after adding drop
@@ -1098,6 +2386,38 @@ export function Overlay() {
     }
   };
 
+  // Массовый ввод: «2x Имя @ 350» на строку -> предпросмотр -> добавить всё
+  const handleParseBulk = async () => {
+    try {
+      setBulkPreview(await invoke<BulkDropPreview>("parse_manual_drops_bulk", { text: bulkText }));
+    } catch (e) {
+      console.error("Failed to parse bulk drops:", e);
+    }
+  };
+
+  const handleAddBulk = async () => {
+    if (!bulkPreview || bulkPreview.entries.length === 0) return;
+    try {
+      await invoke<number>("add_manual_drops_bulk", { entries: bulkPreview.entries });
+      setManualDrops(prev => [...prev, ...bulkPreview.entries]);
+      setBulkPreview(null);
+      setBulkText('');
+    } catch (e) {
+      console.error("Failed to add bulk drops:", e);
+    }
+  };
+
+  // Продажа ручного дропа: фактическая цена за штуку (null — снять отметку)
+  const handleMarkSold = async (id: string, actualPrice: number | null) => {
+    try {
+      const entry = await invoke<ManualDropEntry>("mark_manual_drop_sold", { id, actualPrice, soldAt: null });
+      setManualDrops(prev => prev.map(e => e.id === id ? entry : e));
+    } catch (e) {
+      console.error("Failed to mark manual drop sold:", e);
+    }
+    setSellingId(null);
+  };
+
   const handleRemoveManualDrop = async (id: string) => {
     try {
       await invoke("remove_manual_drop", { id });
@@ -1257,6 +2577,8 @@ export function Overlay() {
         avg_map_duration_sec: 0,
         stale_price_lines: 0,
         hourly_profit: 0,
+        map_time_sec: 0,
+        map_hourly_profit: 0,
         is_paused: false,
       });
       setDrops([]);
@@ -1273,7 +2595,9 @@ export function Overlay() {
               name: item.name,
               nameRu: item.name_ru,
               quantity: item.quantity,
-              price: item.price,
+              // Цена в торговой валюте пересчитывается по курсу на момент старта
+              price: item.quoted?.amount ?? item.price,
+              currencyId: item.quoted?.currency_id ?? null,
             });
           } catch (e) {
             console.error("Failed to sync expense:", e);
@@ -1302,9 +2626,7 @@ export function Overlay() {
   const handleTogglePause = async () => {
     const newPaused = !isPaused;
     
-    // Сохраняем текущее время на бэкенд перед паузой
     try {
-      await invoke("update_session_duration", { durationSec: localDuration });
       await invoke("set_paused", { paused: newPaused });
     } catch (e) {
       console.error("Failed to set pause state:", e);
@@ -1341,10 +2663,6 @@ export function Overlay() {
 
   const handleClose = async () => {
     try {
-      // Сохраняем время перед закрытием
-      if (isSessionActive) {
-        await invoke("update_session_duration", { durationSec: localDuration });
-      }
       const win = getCurrentWindow();
       await win.close();
     } catch (err) {
@@ -1355,10 +2673,13 @@ export function Overlay() {
   // Проверка обновлений
   const checkForUpdates = async () => {
     try {
-      const update = await check();
-      if (update?.available) {
-        console.log("Update available:", update.version);
-        setUpdateAvailable(update.version);
+      // Канал (stable/beta) backend берёт из настроек
+      const info = await invoke<UpdateInfo>("check_for_updates");
+      if (info.available && info.latest_version) {
+        console.log("Update available:", info.latest_version, info.release_notes);
+        setUpdateAvailable(info.latest_version);
+      } else {
+        setUpdateAvailable(null);
       }
     } catch (e) {
       console.log("Update check failed (normal in dev):", e);
@@ -1372,32 +2693,53 @@ export function Overlay() {
     setIsUpdating(true);
     setUpdateProgress(0);
     
+    const unlisten = await listen<{ downloaded: number; total: number | null; finished: boolean }>("update-progress", (event) => {
+      const { downloaded, total, finished } = event.payload;
+      if (finished) {
+        setUpdateProgress(100);
+      } else if (total) {
+        setUpdateProgress(Math.min((downloaded / total) * 100, 99));
+      } else {
+        setUpdateProgress(Math.min(downloaded / 1024 / 10, 99));
+      }
+    });
+
     try {
-      const update = await check();
-      if (!update?.available) return;
-
-      // Скачиваем и устанавливаем с прогрессом
-      await update.downloadAndInstall((event) => {
-        if (event.event === "Started" && event.data.contentLength) {
-          setUpdateProgress(0);
-        } else if (event.event === "Progress") {
-          const progress = event.data.chunkLength;
-          setUpdateProgress((prev) => Math.min((prev || 0) + progress / 1024 / 10, 99));
-        } else if (event.event === "Finished") {
-          setUpdateProgress(100);
-        }
-      });
+      // Скачиваем и устанавливаем обновление, найденное check_for_updates
+      await invoke("install_update");
+      unlisten();
       
       // Перезапускаем приложение
       await relaunch();
     } catch (e) {
       console.error("Update failed:", e);
+      unlisten();
       setIsUpdating(false);
       setUpdateProgress(null);
       alert("Ошибка обновления: " + String(e));
     }
   };
 
+  // Кандидаты из библиотек Steam/реестра при открытии окна выбора лога
+  useEffect(() => {
+    if (!showLogModal) return;
+    invoke<LogCandidate[]>("list_log_candidates")
+      .then(setLogCandidates)
+      .catch((e) => console.error("Failed to list log candidates:", e));
+  }, [showLogModal]);
+
+  const handlePickLogCandidate = async (path: string) => {
+    try {
+      const result = await invoke<boolean>("set_log_path", { path });
+      if (result) {
+        setLogPath(path);
+        setShowLogModal(false);
+      }
+    } catch (e) {
+      console.error("Failed to set log path:", e);
+    }
+  };
+
   const handleSelectLogFile = async () => {
     try {
       const selected = await open({
@@ -1433,6 +2775,10 @@ export function Overlay() {
 
   const formatNumber = (num: number): string => {
     const abs = Math.abs(num);
+    // Те же правила, что value_format::compact в backend
+    if (abs >= 1000000) {
+      return (num / 1000000).toFixed(1) + "M";
+    }
     if (abs >= 10000) {
       return (num / 1000).toFixed(1) + "k";
     }
@@ -1504,8 +2850,441 @@ export function Overlay() {
   const orientationClass = `orientation-${appSettings.layout_orientation}`;
   const directionClass = `direction-${appSettings.panel_direction}`;
   
-  // Локализация
-  const lang = (appSettings.language || 'ru') as Lang;
+  // Локализация. Для 'zh' бэкенд отдаёт китайские имена предметов, а тексты интерфейса — английские
+  const lang: Lang = (appSettings.language || 'ru') === 'ru' ? 'ru' : 'en';
+  // Статус обновления цен показываем только на открытом экране настроек
+  useEffect(() => {
+    if (activeTab !== 'settings') return;
+    const load = () => {
+      invoke<PriceSyncStatus>("get_price_sync_status").then(setPriceSync).catch(() => {});
+      invoke<ConnectivityStatus>("get_connectivity_status").then(setConnectivity).catch(() => {});
+    };
+    load();
+    const timer = setInterval(load, 10000);
+    return () => clearInterval(timer);
+  }, [activeTab]);
+
+  useEffect(() => {
+    invoke<{ id: string; name: string }[]>("list_bundled_sounds").then(setBundledSounds).catch(() => {});
+  }, []);
+
+  // Справочник категорий: имена зависят от языка, а после обновления списка предметов он мог смениться
+  useEffect(() => {
+    invoke<CategoryView[]>("get_categories").then(setCategories).catch(() => {});
+  }, [lang, itemsDiff]);
+
+  // Повреждённые файлы данных, найденные при запуске
+  useEffect(() => {
+    invoke<StartupWarning[]>("get_startup_warnings").then(setStartupWarnings).catch(() => {});
+  }, []);
+
+  // Бэкенд требует обновить приложение (проверка при запуске)
+  useEffect(() => {
+    invoke<HandshakeStatus | null>("get_schema_handshake").then(setHandshake).catch(() => {});
+    const unlisten = listen<HandshakeStatus>("schema-handshake", (event) => setHandshake(event.payload));
+    return () => { unlisten.then(fn => fn()); };
+  }, []);
+
+  useEffect(() => {
+    if (activeTab !== 'settings') return;
+    invoke<SheetsCredentialInfo>("get_google_sheets_credential").then(setSheetsCredential).catch(() => {});
+    invoke<CacheStats>("get_cache_stats").then(setCacheStats).catch(() => {});
+    invoke<NoiseFilterStats>("get_noise_filter_stats").then(setNoiseStats).catch(() => {});
+    invoke<LeagueStatus>("get_league_status").then(setLeagueStatus).catch(() => {});
+  }, [activeTab]);
+
+  const handleArchiveLeague = async () => {
+    setConfirmArchiveLeague(false);
+    try {
+      await invoke<ArchivedLeague>("archive_league_data");
+      setLeagueStatus(await invoke<LeagueStatus>("get_league_status"));
+    } catch (e) {
+      console.error("Failed to archive league:", e);
+    }
+  };
+
+  const handleOpenArchivedLeague = async (id: string) => {
+    if (archivedLeague?.league.id === id) {
+      setArchivedLeague(null);
+      return;
+    }
+    try {
+      setArchivedLeague(await invoke<ArchivedLeagueView>("get_archived_league", { id }));
+    } catch (e) {
+      console.error("Failed to load archived league:", e);
+    }
+  };
+
+  useEffect(() => {
+    if (activeTab !== 'add-drop' && activeTab !== 'session-preset') return;
+    invoke<ExchangeRateSeries[]>("get_exchange_rates").then(setExchangeRates).catch(() => {});
+  }, [activeTab]);
+
+  const cacheLimits: CacheLimits = appSettings.cache_limits ?? { max_items: 50000, max_prices: 20000, price_ttl_days: 90 };
+
+  const sheets: GoogleSheetsSettings = appSettings.google_sheets ?? { enabled: false, spreadsheet_id: '', sheet_name: 'Sessions' };
+
+  const handleSaveSheetsCredential = async () => {
+    try {
+      setSheetsCredential(await invoke<SheetsCredentialInfo>("set_google_sheets_credential", { credential: sheetsCredentialDraft }));
+      setSheetsCredentialDraft('');
+      setSheetsStatus(null);
+    } catch (e) {
+      setSheetsStatus(String(e));
+    }
+  };
+
+  const handleClearSheetsCredential = async () => {
+    try {
+      await invoke("clear_google_sheets_credential");
+      setSheetsCredential({ kind: 'none' });
+    } catch (e) {
+      setSheetsStatus(String(e));
+    }
+  };
+
+  const handleRefreshItems = async () => {
+    setItemsRefreshing(true);
+    setItemsRefreshError(null);
+    try {
+      setItemsDiff(await invoke<ItemsDiff>("refresh_items_cache"));
+    } catch (e) {
+      setItemsRefreshError(String(e));
+    } finally {
+      setItemsRefreshing(false);
+    }
+  };
+
+  const loadUnknownItems = () =>
+    invoke<UnknownItem[]>("get_unknown_items").then(setUnknownItems).catch(() => {});
+
+  useEffect(() => {
+    if (activeTab === 'settings') loadUnknownItems();
+  }, [activeTab, itemsDiff]);
+
+  const handleReportUnknownItems = async () => {
+    setUnknownReportStatus('...');
+    try {
+      const sent = await invoke<number>("report_unknown_items");
+      setUnknownReportStatus(`${L('unknownItemsSent')}: ${sent}`);
+      loadUnknownItems();
+    } catch (e) {
+      setUnknownReportStatus(String(e));
+    }
+  };
+
+  const dropSounds: DropSoundSettings = appSettings.drop_sounds ?? { enabled: false, volume: 0.7, rules: [] };
+  const saveDropSounds = (next: DropSoundSettings) => handleSaveSettings({ ...appSettings, drop_sounds: next });
+  const updateSoundRule = (index: number, patch: Partial<DropSoundRule>) =>
+    saveDropSounds({ ...dropSounds, rules: dropSounds.rules.map((r, i) => i === index ? { ...r, ...patch } : r) });
+
+  const pickSoundFile = async (index: number) => {
+    const selected = await open({
+      title: L('dropSoundsFile'),
+      filters: [{ name: 'Audio', extensions: ['wav', 'ogg', 'mp3', 'flac'] }],
+      multiple: false,
+    });
+    if (!selected || typeof selected !== "string") return;
+    updateSoundRule(index, { sound: selected });
+  };
+
+  const controllerApi = appSettings.controller_api ?? { enabled: false, port: 49740 };
+
+  useEffect(() => {
+    if (activeTab !== 'settings' || !controllerApi.enabled) return;
+    invoke<ControllerApiInfo>("get_controller_api_info").then(setControllerInfo).catch(() => {});
+  }, [activeTab, controllerApi.enabled, controllerApi.port]);
+
+  const copyControllerToken = async () => {
+    if (!controllerInfo) return;
+    await writeText(controllerInfo.token);
+    setControllerCopied(true);
+    setTimeout(() => setControllerCopied(false), 1500);
+  };
+
+  const obs: ObsSettings = appSettings.obs ?? { enabled: false, host: '127.0.0.1', port: 4455, rules: [] };
+  const saveObs = (next: ObsSettings) => handleSaveSettings({ ...appSettings, obs: next });
+  const updateObsRule = (index: number, patch: Partial<ObsRule>) =>
+    saveObs({ ...obs, rules: obs.rules.map((r, i) => i === index ? { ...r, ...patch } : r) });
+
+  const handleTestObs = async () => {
+    setObsMessage('...');
+    try {
+      const status = await invoke<ObsStatus>("test_obs_connection", { obs });
+      setObsStatus(status);
+      setObsMessage(`OBS ${status.obs_version} · obs-websocket ${status.websocket_version}`);
+    } catch (e) {
+      setObsMessage(String(e));
+    }
+  };
+
+  const handleTestObsAction = (action: ObsAction) => {
+    setObsMessage('...');
+    invoke("test_obs_action", { action, obs })
+      .then(() => setObsMessage(L('obsActionDone')))
+      .catch(e => setObsMessage(String(e)));
+  };
+
+  const liveStats: LiveStatsSettings = appSettings.live_stats ?? { enabled: false, path: null, interval_secs: 2, top_drops: 5 };
+  const saveLiveStats = (next: LiveStatsSettings) => handleSaveSettings({ ...appSettings, live_stats: next });
+
+  const handleWriteLiveStats = () => {
+    invoke<string>("write_live_stats", { liveStats })
+      .then(path => setLiveStatsMessage(`${L('liveStatsWritten')}: ${path}`))
+      .catch(e => setLiveStatsMessage(String(e)));
+  };
+
+  const twitch: TwitchSettings = appSettings.twitch ?? {
+    enabled: false,
+    channel: '',
+    login: '',
+    drop_min_value: 5000,
+    drop_template: '{item} x{quantity} — {value} FE',
+    summary_enabled: true,
+    summary_template: 'GG! {maps} maps in {duration}, profit {profit} FE ({per_hour}/h)',
+    min_interval_secs: 30,
+  };
+  const saveTwitch = (next: TwitchSettings) => handleSaveSettings({ ...appSettings, twitch: next });
+
+  useEffect(() => {
+    if (activeTab !== 'settings' || !twitch.enabled) return;
+    invoke<TwitchStatus>("get_twitch_status").then(setTwitchStatus).catch(() => {});
+  }, [activeTab, twitch.enabled]);
+
+  const handleTestTwitch = () => {
+    setTwitchMessage('...');
+    invoke("send_twitch_test_message", { twitch })
+      .then(() => setTwitchMessage(L('twitchSent')))
+      .catch(e => setTwitchMessage(String(e)));
+  };
+
+  const tts: TtsSettings = appSettings.tts ?? { enabled: false, min_value: 1000, min_interval_secs: 5, volume: 0.7 };
+
+  const proxy: ProxySettings = appSettings.proxy ?? { enabled: false, kind: 'http', host: '', port: 0, username: null };
+
+  const handleTestProxy = async () => {
+    setProxyStatus('...');
+    try {
+      const result = await invoke<ConnectionTestResult>("test_connection", { proxy });
+      setProxyStatus(result.ok
+        ? `${L('proxyOk')} · ${result.latency_ms} ms`
+        : `${L('proxyFailed')}: ${result.error ?? result.status}`);
+    } catch (e) {
+      setProxyStatus(String(e));
+    }
+  };
+
+  const backend: BackendEndpoint = backendDraft
+    ?? appSettings.backend
+    ?? { enabled: false, url: '', anon_key: '', table_prefix: 'tli_', rpc_prefix: '' };
+
+  const handleApplyBackend = async (endpoint: BackendEndpoint) => {
+    setBackendStatus('...');
+    try {
+      const result = await invoke<BackendCheckResult>("set_backend_endpoint", { endpoint });
+      if (result.applied) {
+        setAppSettings(prev => ({ ...prev, backend: endpoint }));
+        setBackendDraft(null);
+        setBackendStatus(endpoint.enabled ? L('backendApplied') : null);
+        if (result.handshake) setHandshake(result.handshake);
+      } else if (result.handshake) {
+        setBackendStatus(L('backendIncompatible'));
+      } else {
+        const conn = result.connection;
+        setBackendStatus(`${L('backendRejected')}: ${result.error ?? conn?.error ?? conn?.status ?? ''}`);
+      }
+    } catch (e) {
+      setBackendStatus(String(e));
+    }
+  };
+
+  const handleTestSheets = async () => {
+    setSheetsStatus('...');
+    try {
+      const title = await invoke<string>("test_google_sheets");
+      setSheetsStatus(`${L('sheetsConnected')}: ${title}`);
+    } catch (e) {
+      setSheetsStatus(String(e));
+    }
+  };
+
+  // Сводка группы: участники присылают снимки раз в 30 секунд
+  useEffect(() => {
+    if (!groupId) {
+      setGroupStats(null);
+      return;
+    }
+    const load = () => invoke<GroupStats>("get_group_stats").then(setGroupStats).catch(console.error);
+    load();
+    const timer = setInterval(load, 30000);
+    return () => clearInterval(timer);
+  }, [groupId]);
+
+  // Оценка состояния: перечитываем на вкладке статистики и при новом снимке инвентаря
+  useEffect(() => {
+    if (activeTab !== 'stats') return;
+    const load = () => invoke<NetWorth | null>("get_net_worth", { category: netWorthGroup || null })
+      .then(setNetWorth)
+      .catch(() => {});
+    load();
+    const timer = setInterval(load, 30000);
+    const unlisten = listen("inventory-snapshot", load);
+    return () => {
+      clearInterval(timer);
+      unlisten.then(fn => fn());
+    };
+  }, [activeTab, netWorthGroup]);
+
+  // Кривая профита: backend пишет точку раз в минуту времени сессии
+  useEffect(() => {
+    if (activeTab !== 'stats' || !isSessionActive) {
+      setValueTimeline([]);
+      return;
+    }
+    const load = () => invoke<ValuePoint[]>("get_value_timeline").then(setValueTimeline).catch(() => {});
+    load();
+    const timer = setInterval(load, 60000);
+    return () => clearInterval(timer);
+  }, [activeTab, isSessionActive]);
+
+  // Журнал карт сессии и личные рекорды: перечитываем при смене карты
+  useEffect(() => {
+    if (activeTab !== 'stats' || !isSessionActive) {
+      setMapRuns([]);
+      return;
+    }
+    const load = () => {
+      invoke<MapRun[]>("get_map_runs").then(setMapRuns).catch(() => {});
+      invoke<PersonalRecords>("get_records").then(setPersonalRecords).catch(() => {});
+    };
+    load();
+    const unlistenMap = listen("map-change", load);
+    let flashTimer: ReturnType<typeof setTimeout> | undefined;
+    const unlistenRecord = listen<RecordBrokenEvent>("record-broken", (event) => {
+      setRecordFlash(event.payload);
+      clearTimeout(flashTimer);
+      flashTimer = setTimeout(() => setRecordFlash(null), 8000);
+      load();
+    });
+    return () => {
+      clearTimeout(flashTimer);
+      unlistenMap.then(fn => fn());
+      unlistenRecord.then(fn => fn());
+    };
+  }, [activeTab, isSessionActive]);
+
+  // «Живые» FE сессии: сколько было на старте и сколько сейчас
+  useEffect(() => {
+    if (activeTab !== 'stats' || !isSessionActive) {
+      setSessionCurrency(null);
+      return;
+    }
+    const load = () => invoke<SessionCurrency>("get_session_currency").then(setSessionCurrency).catch(() => {});
+    load();
+    const timer = setInterval(load, 15000);
+    return () => clearInterval(timer);
+  }, [activeTab, isSessionActive]);
+
+  const handleSaveCurrency = async () => {
+    if (!currencyInput) return;
+    const amount = Math.round(Number(currencyInput.value));
+    if (!Number.isFinite(amount) || amount < 0) return;
+    try {
+      await invoke<CurrencyReading>("set_session_currency", { point: currencyInput.point, amount });
+      setCurrencyInput(null);
+      setSessionCurrency(await invoke<SessionCurrency>("get_session_currency"));
+    } catch (e) {
+      console.error("Failed to set session currency:", e);
+    }
+  };
+
+  const handleSaveProfitAlarm = async () => {
+    if (alarmInput === null) return;
+    const threshold = alarmInput.trim() === '' ? null : Number(alarmInput);
+    if (threshold !== null && (!Number.isFinite(threshold) || threshold <= 0)) return;
+    try {
+      await invoke("set_profit_alarm", { thresholdFe: threshold });
+      setAlarmInput(null);
+      setProfitAlarmHit(null);
+    } catch (e) {
+      console.error("Failed to set profit alarm:", e);
+    }
+  };
+
+  const handleToggleLiveShare = async () => {
+    try {
+      if (liveShareCode) {
+        await invoke("stop_live_share");
+        setLiveShareCode(null);
+      } else {
+        const code = await invoke<string>("start_live_share");
+        setLiveShareCode(code);
+        await writeText(code);
+      }
+    } catch (e) {
+      console.error("Failed to toggle live share:", e);
+    }
+  };
+
+  const handleSpectate = async () => {
+    if (spectateInput === null) return;
+    const code = spectateInput.trim();
+    try {
+      if (code === '') {
+        await invoke("stop_spectating");
+        setPartner(null);
+      } else {
+        const snapshot = await invoke<SharedSessionSnapshot>("spectate_session", { shareCode: code });
+        setPartner({ code, snapshot });
+      }
+      setSpectateInput(null);
+    } catch (e) {
+      console.error("Failed to spectate session:", e);
+    }
+  };
+
+  const handleJoinGroup = async () => {
+    if (groupInput === null) return;
+    const code = groupInput.trim();
+    try {
+      const joined = code === ''
+        ? await invoke<string>("create_group_session", {})
+        : await invoke<string>("join_group_session", { groupId: code });
+      setGroupId(joined);
+      setGroupInput(null);
+      if (code === '') await writeText(joined);
+    } catch (e) {
+      console.error("Failed to join group session:", e);
+    }
+  };
+
+  // Дележ по сводке группы: лут = доход участника, затраты = доход минус профит
+  const handleCopyLootSplit = async () => {
+    if (!groupStats) return;
+    try {
+      const split = await invoke<{ text: string }>("compute_loot_split", {
+        participants: groupStats.members.map(m => ({
+          name: m.display_name,
+          loot_value: Math.max(0, m.income),
+          expenses: Math.max(0, m.income - m.profit),
+        })),
+        rules: { mode: 'fe_equalized', refund_expenses: true },
+      });
+      await writeText(split.text);
+    } catch (e) {
+      console.error("Failed to compute loot split:", e);
+    }
+  };
+
+  const handleLeaveGroup = async () => {
+    try {
+      await invoke("leave_group_session");
+      setGroupId(null);
+    } catch (e) {
+      console.error("Failed to leave group session:", e);
+    }
+  };
+
   const L = (key: keyof typeof translations.ru) => t(lang, key);
 
   return (
@@ -1642,7 +3421,9 @@ export function Overlay() {
             </div>
             <div 
               className="mini-stat"
-              onMouseEnter={showTooltip(lang === 'ru' ? `Профит в час: ${formatNumber(profitPerHour)} FE` : `Profit per hour: ${formatNumber(profitPerHour)} FE`)}
+              onMouseEnter={showTooltip(lang === 'ru'
+                ? `Профит в час: ${formatNumber(profitPerHour)} FE · на картах: ${formatNumber(mapProfitPerHour)} FE`
+                : `Profit per hour: ${formatNumber(profitPerHour)} FE · in maps: ${formatNumber(mapProfitPerHour)} FE`)}
               onMouseLeave={hideTooltip}
               onMouseMove={moveTooltip}
             >
@@ -1745,13 +3526,74 @@ export function Overlay() {
           {/* ======== STATS TAB ======== */}
           {activeTab === 'stats' && (
             <>
+              {/* Напоминание о блоке фарма */}
+              {farmReminder && (
+                <div className="overlay-paused-banner farm-block-banner" onClick={() => setFarmReminder(null)}>
+                  <div className="pause-banner-content">
+                    <span className="pause-banner-title">
+                      {farmReminder.minutes_until > 0 ? L('farmBlockSoon') : L('farmBlockNow')}
+                    </span>
+                    <span className="pause-banner-hint">
+                      {[
+                        farmReminder.block.name,
+                        farmReminder.minutes_until > 0 ? L('farmBlockIn').replace('{n}', String(farmReminder.minutes_until)) : null,
+                        farmReminder.auto_started ? L('farmBlockAutoStarted') : null,
+                      ].filter(Boolean).join(' · ')}
+                    </span>
+                  </div>
+                </div>
+              )}
+
+              {/* Приложение устарело для текущей схемы Supabase */}
+              {handshake && (handshake.update_required || handshake.schema_changed) && (
+                <div className="overlay-paused-banner farm-block-banner" onClick={() => setHandshake(null)}>
+                  <div className="pause-banner-content">
+                    <span className="pause-banner-title">{L('updateRequired')}</span>
+                    <span className="pause-banner-hint">
+                      {handshake.message ?? (handshake.update_required && handshake.min_app_version
+                        ? `${handshake.app_version} < ${handshake.min_app_version}`
+                        : L('updateSchemaChanged'))}
+                    </span>
+                  </div>
+                </div>
+              )}
+
+              {/* Повреждённые файлы данных (восстановлены или сброшены) */}
+              {startupWarnings.length > 0 && (
+                <div className="overlay-paused-banner farm-block-banner" onClick={() => setStartupWarnings([])}>
+                  <div className="pause-banner-content">
+                    {startupWarnings.map((w, i) => (
+                      <span key={i} className="pause-banner-hint" title={[w.error, w.quarantined_to].filter(Boolean).join('\n')}>
+                        {w.file}: {w.action === 'restored_from_backup' ? L('startupRecovered') : w.action === 'newer_version' ? L('startupNewerVersion') : L('startupReset')}
+                      </span>
+                    ))}
+                  </div>
+                </div>
+              )}
+
+              {/* Будильник профита сработал */}
+              {profitAlarmHit && (
+                <div className="overlay-paused-banner farm-block-banner" onClick={() => setProfitAlarmHit(null)}>
+                  <div className="pause-banner-content">
+                    <span className="pause-banner-title">{L('profitAlarmHit')}</span>
+                    <span className="pause-banner-hint">
+                      {formatNumber(profitAlarmHit.net_profit)} / {formatNumber(profitAlarmHit.threshold)} FE
+                    </span>
+                  </div>
+                </div>
+              )}
+
               {/* Paused Banner */}
               {isPaused && (
                 <div className="overlay-paused-banner" onClick={handleTogglePause}>
                   <IconPause size={16} />
                   <div className="pause-banner-content">
                     <span className="pause-banner-title">{L('paused')}</span>
-                    <span className="pause-banner-hint">{L('pausedDescription')}</span>
+                    <span className="pause-banner-hint">
+                      {stats?.pause_reason === 'game_closed'
+                        ? L('pausedGameClosed')
+                        : stats?.pause_reason === 'afk' ? L('pausedAfk') : L('pausedDescription')}
+                    </span>
                   </div>
                 </div>
               )}
@@ -1782,6 +3624,34 @@ export function Overlay() {
                 <span className="stat-value">{formatNumber(profitPerHour)}</span>
                 <span className="stat-label">{L('fePerHour')}</span>
               </div>
+              <div className="stat-item">
+                <span className="stat-value">{mapTimeSec > 0 ? formatNumber(mapProfitPerHour) : '—'}</span>
+                <span className="stat-label">{L('fePerMapHour')}</span>
+              </div>
+              {(stats.manual_pending_value ?? 0) > 0 && (
+                <div className="stat-item">
+                  <span className="stat-value">{formatNumber(stats.manual_pending_value ?? 0)}</span>
+                  <span className="stat-label">{L('pendingSales')}</span>
+                </div>
+              )}
+              {(stats.paused_sec ?? 0) > 0 && (
+                <div className="stat-item">
+                  <span className="stat-value">{formatDurationShort(stats.paused_sec ?? 0)}</span>
+                  <span className="stat-label">{L('pausedTotal')}</span>
+                </div>
+              )}
+              {(stats.abandoned_maps ?? 0) > 0 && (
+                <div className="stat-item" title={L('abandonedMapHint')}>
+                  <span className="stat-value">{stats.abandoned_maps} · {formatDurationShort(stats.abandoned_map_sec ?? 0)}</span>
+                  <span className="stat-label">{L('abandonedMaps')}</span>
+                </div>
+              )}
+              {stats.recovered && (
+                <div className="stat-item" title={L('recoveredHint')}>
+                  <span className="stat-value">⚠ {formatDurationShort(stats.recovery_gap_sec ?? 0)}</span>
+                  <span className="stat-label">{L('recoveredSession')}</span>
+                </div>
+              )}
             </div>
           )}
 
@@ -1834,6 +3704,20 @@ export function Overlay() {
                     +
                   </button>
                 )}
+                <button
+                  className="drops-add-btn"
+                  onClick={() => invoke('open_panel_window', { kind: 'drops' }).catch(console.error)}
+                  title={L('popOutDrops')}
+                >
+                  ⧉
+                </button>
+                <button
+                  className="drops-add-btn"
+                  onClick={() => invoke('open_panel_window', { kind: 'ticker' }).catch(console.error)}
+                  title={L('popOutTicker')}
+                >
+                  ≋
+                </button>
                 <span className="drops-count">{drops.length + manualDrops.length}</span>
               </div>
             </div>
@@ -1851,12 +3735,42 @@ export function Overlay() {
                     <div className="drop-info">
                       <span className="drop-manual-badge" title="Добавлено вручную">✎</span>
                       <span className="drop-name">{lang === 'ru' ? (drop.name_ru || drop.name) : (drop.name || drop.name_ru)}</span>
+                      {drop.tier != null && <span className="drop-tier-badge">T{drop.tier}</span>}
                       <span className="drop-qty">x{drop.quantity}</span>
                     </div>
                     <div className="drop-value-wrapper">
-                      <span className="drop-value manual">
-                        {formatNumber(drop.quantity * drop.price)}
-                      </span>
+                      {sellingId === drop.id ? (
+                        <input
+                          className="overlay-input drop-sell-input"
+                          type="number"
+                          min="0"
+                          autoFocus
+                          value={sellPrice || ''}
+                          onChange={e => setSellPrice(parseFloat(e.target.value) || 0)}
+                          onKeyDown={e => {
+                            if (e.key === 'Enter') handleMarkSold(drop.id, sellPrice);
+                            if (e.key === 'Escape') setSellingId(null);
+                          }}
+                        />
+                      ) : (
+                        <span className={`drop-value manual ${drop.sold ? 'sold' : 'pending'}`}>
+                          {formatNumber(drop.quantity * (drop.sold ? drop.sold.actual_price : drop.price))}
+                        </span>
+                      )}
+                      <button
+                        className={`drop-remove-btn ${drop.sold ? 'active' : ''}`}
+                        onClick={() => {
+                          if (drop.sold) {
+                            handleMarkSold(drop.id, null);
+                          } else {
+                            setSellingId(drop.id);
+                            setSellPrice(drop.price);
+                          }
+                        }}
+                        title={drop.sold ? L('unmarkSold') : L('markSold')}
+                      >
+                        ✓
+                      </button>
                       <button 
                         className="drop-remove-btn"
                         onClick={() => handleRemoveManualDrop(drop.id)}
@@ -1870,9 +3784,10 @@ export function Overlay() {
                 
                 {/* Auto-tracked drops */}
                 {drops.map((drop) => (
-                  <div key={drop.game_id} className="drop-item">
+                  <div key={`${drop.game_id}-${drop.tier ?? ''}`} className="drop-item">
                     <div className="drop-info">
                       <span className="drop-name">{getItemName(drop)}</span>
+                      {drop.tier != null && <span className="drop-tier-badge">T{drop.tier}</span>}
                       <span className="drop-qty">x{drop.quantity}</span>
                     </div>
                     <div className="drop-value-wrapper">
@@ -1897,6 +3812,17 @@ export function Overlay() {
                         >
                           {formatPriceAge(drop.price_updated_at)}
                         </span>
+                      ) : drop.price_confidence?.level === 'low' ? (
+                        <span 
+                          className="price-badge low-confidence"
+                          onMouseEnter={showTooltip(drop.price_confidence.sample_count
+                            ? `Цена по ${drop.price_confidence.sample_count} лот(ам) — оценка ненадёжна`
+                            : `Мало данных о цене — оценка ненадёжна`)}
+                          onMouseLeave={hideTooltip}
+                          onMouseMove={moveTooltip}
+                        >
+                          ?
+                        </span>
                       ) : null}
                     </div>
                   </div>
@@ -1916,11 +3842,302 @@ export function Overlay() {
                 <IconExpense size={14} /> {L('selectPreset')}
               </button>
             ) : (
-              <button className="control-btn stop" onClick={handleEndSession}>
-                {L('endSession')}
+              <>
+                <div className="summary-copy-row">
+                  <span>{summaryCopied ? L('summaryCopied') : L('copySummary')}</span>
+                  {(['text', 'markdown', 'bbcode'] as const).map(format => (
+                    <button key={format} className="summary-copy-btn" onClick={() => handleCopySummary(format)}>
+                      {format === 'text' ? 'Text' : format === 'markdown' ? 'Discord' : 'BBCode'}
+                    </button>
+                  ))}
+                  <button className="summary-copy-btn" onClick={() => handleSummaryCard(null)} title={L('summaryCard')}>
+                    PNG
+                  </button>
+                  <button className="summary-copy-btn" onClick={() => handleExportJournal(null)} title={L('exportJournal')}>
+                    NDJSON
+                  </button>
+                </div>
+                <button className="control-btn stop" onClick={handleEndSession}>
+                  {L('endSession')}
+                </button>
+              </>
+            )}
+          </div>
+
+          {/* Будильник по чистому профиту */}
+          {isSessionActive && (
+            <div className="raw-currency">
+              <span className="raw-currency-label">{L('profitAlarm')}</span>
+              {alarmInput !== null ? (
+                <>
+                  <input
+                    className="raw-currency-input"
+                    type="number"
+                    min={0}
+                    autoFocus
+                    placeholder="FE"
+                    value={alarmInput}
+                    onChange={(e) => setAlarmInput(e.target.value)}
+                    onKeyDown={(e) => {
+                      if (e.key === 'Enter') handleSaveProfitAlarm();
+                      if (e.key === 'Escape') setAlarmInput(null);
+                    }}
+                  />
+                  <button className="raw-currency-btn" onClick={handleSaveProfitAlarm}>OK</button>
+                </>
+              ) : (
+                <>
+                  <span className="raw-currency-value">
+                    {stats?.profit_alarm
+                      ? `${formatNumber(stats.profit_alarm.threshold)} FE${stats.profit_alarm.fired_at ? ' ✓' : ''}`
+                      : L('profitAlarmOff')}
+                  </span>
+                  <button
+                    className="raw-currency-btn"
+                    onClick={() => setAlarmInput(stats?.profit_alarm ? String(stats.profit_alarm.threshold) : '')}
+                    title={L('profitAlarm')}
+                  >
+                    ✎
+                  </button>
+                </>
+              )}
+            </div>
+          )}
+
+          {/* Live-трансляция своей сессии и наблюдение за напарником */}
+          {isSessionActive && (
+            <div className="raw-currency">
+              <span className="raw-currency-label">{L('liveShare')}</span>
+              <span className="raw-currency-value">{liveShareCode ?? '—'}</span>
+              <button className="raw-currency-btn" onClick={handleToggleLiveShare} title={L('liveShareStart')}>
+                {liveShareCode ? '■' : '📡'}
               </button>
+            </div>
+          )}
+          <div className="raw-currency">
+            <span className="raw-currency-label">{L('spectate')}</span>
+            {spectateInput !== null ? (
+              <>
+                <input
+                  className="raw-currency-input"
+                  autoFocus
+                  placeholder={L('spectateCode')}
+                  value={spectateInput}
+                  onChange={(e) => setSpectateInput(e.target.value)}
+                  onKeyDown={(e) => {
+                    if (e.key === 'Enter') handleSpectate();
+                    if (e.key === 'Escape') setSpectateInput(null);
+                  }}
+                />
+                <button className="raw-currency-btn" onClick={handleSpectate}>OK</button>
+              </>
+            ) : (
+              <>
+                <span className={`raw-currency-value ${partner && partner.snapshot.total_profit >= 0 ? 'positive' : ''}`}>
+                  {partner
+                    ? `${formatNumber(partner.snapshot.total_profit)} FE · ${partner.snapshot.maps_completed} maps · ${formatDuration(partner.snapshot.total_duration_sec)}${partner.snapshot.is_live ? '' : ` (${L('spectateEnded')})`}`
+                    : '—'}
+                </span>
+                <button className="raw-currency-btn" onClick={() => setSpectateInput(partner?.code ?? '')} title={L('spectateCode')}>
+                  ✎
+                </button>
+              </>
+            )}
+          </div>
+
+          {/* Групповая сессия: общий профит и дележ поровну */}
+          <div className="raw-currency">
+            <span className="raw-currency-label">{L('group')}</span>
+            {groupInput !== null ? (
+              <>
+                <input
+                  className="raw-currency-input"
+                  autoFocus
+                  placeholder={L('groupCode')}
+                  value={groupInput}
+                  onChange={(e) => setGroupInput(e.target.value)}
+                  onKeyDown={(e) => {
+                    if (e.key === 'Enter') handleJoinGroup();
+                    if (e.key === 'Escape') setGroupInput(null);
+                  }}
+                />
+                <button className="raw-currency-btn" onClick={handleJoinGroup}>OK</button>
+              </>
+            ) : groupId ? (
+              <>
+                <span className="raw-currency-value">
+                  {groupId}
+                  {groupStats && ` · ${formatNumber(groupStats.total_profit)} FE · ${groupStats.total_maps} maps`}
+                </span>
+                <button className="raw-currency-btn" onClick={handleLeaveGroup} title={L('groupLeave')}>×</button>
+              </>
+            ) : (
+              <>
+                <span className="raw-currency-value">—</span>
+                <button className="raw-currency-btn" onClick={() => setGroupInput('')} title={L('groupCode')}>✎</button>
+              </>
             )}
           </div>
+          {groupId && groupStats && (
+            <div className="group-stats">
+              {groupStats.members.map(m => (
+                <div key={m.display_name} className={`group-member ${m.is_stale ? 'stale' : ''} ${m.is_me ? 'me' : ''}`}>
+                  <span>{m.display_name}</span>
+                  <span>{formatNumber(m.profit)} FE · {m.maps_completed}</span>
+                </div>
+              ))}
+              {groupStats.transfers.map(tr => (
+                <div key={`${tr.from}-${tr.to}`} className="group-transfer">
+                  {tr.from} → {tr.to}: {formatNumber(tr.amount)} FE
+                </div>
+              ))}
+              <button className="raw-currency-btn" onClick={handleCopyLootSplit}>{L('lootSplitCopy')}</button>
+            </div>
+          )}
+
+          {/* Живые FE за сессию (по логу или введённые вручную) */}
+          {sessionCurrency && (
+            <div className="raw-currency">
+              <span className="raw-currency-label">{L('rawCurrency')}</span>
+              {currencyInput ? (
+                <>
+                  <input
+                    className="raw-currency-input"
+                    type="number"
+                    min={0}
+                    autoFocus
+                    placeholder={currencyInput.point === 'start' ? L('rawCurrencyStart') : L('rawCurrencyEnd')}
+                    value={currencyInput.value}
+                    onChange={(e) => setCurrencyInput({ ...currencyInput, value: e.target.value })}
+                    onKeyDown={(e) => {
+                      if (e.key === 'Enter') handleSaveCurrency();
+                      if (e.key === 'Escape') setCurrencyInput(null);
+                    }}
+                  />
+                  <button className="raw-currency-btn" onClick={handleSaveCurrency}>OK</button>
+                </>
+              ) : (
+                <>
+                  <span className={`raw-currency-value ${(sessionCurrency.gained ?? 0) >= 0 ? 'positive' : 'negative'}`}>
+                    {sessionCurrency.gained != null
+                      ? `${sessionCurrency.gained >= 0 ? '+' : ''}${formatNumber(sessionCurrency.gained)} FE`
+                      : '—'}
+                  </span>
+                  <button
+                    className="raw-currency-btn"
+                    onClick={() => setCurrencyInput({ point: sessionCurrency.start ? 'end' : 'start', value: '' })}
+                    title={sessionCurrency.start ? L('rawCurrencyEnd') : L('rawCurrencyStart')}
+                  >
+                    ✎
+                  </button>
+                </>
+              )}
+            </div>
+          )}
+
+          {/* Net worth (последний снимок инвентаря) */}
+          {netWorth && (
+            <div
+              className="net-worth"
+              onMouseEnter={showTooltip(
+                netWorth.categories
+                  .slice(0, 5)
+                  .map(c => `${c.name || c.category}: ${formatNumber(c.value)}`)
+                  .join(' · ')
+                  + (netWorth.unpriced_count > 0 ? ` · ${L('netWorthUnpriced')}: ${netWorth.unpriced_count}` : '')
+              )}
+              onMouseLeave={hideTooltip}
+              onMouseMove={moveTooltip}
+            >
+              <span className="net-worth-label">{L('netWorth')}</span>
+              {categories.length > 0 && (
+                <select
+                  className="net-worth-filter"
+                  value={netWorthGroup}
+                  onChange={e => setNetWorthGroup(e.target.value)}
+                >
+                  <option value="">{L('netWorthAllGroups')}</option>
+                  {categories.map(c => (
+                    <option key={c.id} value={c.id}>{'\u00a0\u00a0'.repeat(c.depth)}{c.name}</option>
+                  ))}
+                </select>
+              )}
+              <span className="net-worth-value">{formatNumber(netWorth.total_value)} FE</span>
+              <span className="net-worth-age">{new Date(netWorth.snapshot_at).toLocaleString()}</span>
+            </div>
+          )}
+
+          {/* Кривая профита сессии */}
+          {valueTimeline.length > 1 && (() => {
+            const values = valueTimeline.map(p => p.net_profit);
+            const min = Math.min(0, ...values);
+            const max = Math.max(...values);
+            const span = max - min || 1;
+            const last = valueTimeline[valueTimeline.length - 1];
+            const points = valueTimeline
+              .map((p, i) => `${(i / (valueTimeline.length - 1)) * 100},${30 - ((p.net_profit - min) / span) * 30}`)
+              .join(' ');
+            return (
+              <div
+                className="value-curve"
+                onMouseEnter={showTooltip(`${L('profitCurve')}: ${formatNumber(last.net_profit)} FE · ${formatDurationCompact(last.duration_sec)}`)}
+                onMouseLeave={hideTooltip}
+                onMouseMove={moveTooltip}
+              >
+                <span className="net-worth-label">{L('profitCurve')}</span>
+                <svg viewBox="0 0 100 30" preserveAspectRatio="none">
+                  <polyline points={points} />
+                </svg>
+              </div>
+            );
+          })()}
+
+          {/* Журнал карт: лучшая и худшая карта сессии подсвечены */}
+          {mapRuns.length > 0 && (() => {
+            const valued = mapRuns.filter(r => r.value != null && !r.abandoned);
+            const best = valued.length > 1 ? valued.reduce((a, b) => (b.value! > a.value! ? b : a)) : null;
+            const worst = valued.length > 1 ? valued.reduce((a, b) => (b.value! < a.value! ? b : a)) : null;
+            return (
+              <div className="map-runs">
+                <div
+                  className="map-runs-header"
+                  onMouseEnter={showTooltip(
+                    [
+                      personalRecords?.best_map_value
+                        ? `${L('recordBestMap')}: ${formatNumber(personalRecords.best_map_value.value)} FE`
+                        : null,
+                      personalRecords?.fastest_map
+                        ? `${L('recordFastestMap')}: ${formatDurationCompact(personalRecords.fastest_map.duration_sec)}`
+                        : null,
+                    ].filter(Boolean).join(' · ') || L('mapRuns')
+                  )}
+                  onMouseLeave={hideTooltip}
+                  onMouseMove={moveTooltip}
+                >
+                  <span>{L('mapRuns')}: {mapRuns.length}</span>
+                  {recordFlash && (
+                    <span className="map-runs-record">
+                      {L('recordBroken')} {recordFlash.kind === 'best_map_value'
+                        ? `${formatNumber(recordFlash.record.value)} FE`
+                        : formatDurationCompact(recordFlash.record.duration_sec)}
+                    </span>
+                  )}
+                </div>
+                {mapRuns.slice(-8).reverse().map(run => (
+                  <div
+                    key={run.index}
+                    className={`map-run ${run === best ? 'best' : ''} ${run === worst ? 'worst' : ''} ${run.abandoned ? 'abandoned' : ''}`}
+                    title={run.abandoned ? L('abandonedMapHint') : undefined}
+                  >
+                    <span className="map-run-index">#{run.index}</span>
+                    <span className="map-run-scene">{run.scene_name ?? '—'}</span>
+                    <span className="map-run-duration">{formatDurationCompact(run.duration_sec)}</span>
+                    <span className="map-run-value">{run.value != null ? `${formatNumber(run.value)} FE` : '…'}</span>
+                  </div>
+                ))}
+              </div>
+            );
+          })()}
 
           {/* Status Bar */}
           <div className="overlay-status">
@@ -1940,6 +4157,12 @@ export function Overlay() {
               <span className={`status-dot ${logStatus?.is_active ? 'ok' : logStatus?.exists ? 'pending' : 'error'}`} />
               {logStatus?.is_active ? L('logActive') : logStatus?.exists ? L('logInactive') : (lang === 'ru' ? 'Нет лога' : 'No log')}
             </button>
+            {catchup.filter(c => c.is_catching_up).map(c => (
+              <div key={c.source_id} className="status-item">
+                <span className="status-dot pending" />
+                {L('logCatchup')} {c.bytes_total > 0 ? Math.min(100, Math.floor(c.bytes_done / c.bytes_total * 100)) : 0}%
+              </div>
+            ))}
             <div 
               className="status-item"
               onMouseEnter={showTooltip(
@@ -1991,7 +4214,37 @@ export function Overlay() {
                       >
                         {sessionHistoryLoading ? '...' : '↻'}
                       </button>
+                      {sessionHistory.length > 0 && (
+                        <button
+                          className="refresh-btn"
+                          onClick={() => handleExportXlsx(null)}
+                          title={L('exportXlsx')}
+                        >
+                          ⤓
+                        </button>
+                      )}
+                      {sessionHistory.length > 0 && (
+                        <button
+                          className="refresh-btn"
+                          onClick={handleExportHistoryJson}
+                          title={L('exportHistoryJson')}
+                        >
+                          ⇪
+                        </button>
+                      )}
+                      <button
+                        className="refresh-btn"
+                        onClick={handleImportHistory}
+                        title={L('importHistory')}
+                      >
+                        ⇩
+                      </button>
                     </div>
+                    {historyImportReport && (
+                      <div className="settings-hint">
+                        {L('importHistoryResult')}: +{historyImportReport.added} · {L('importHistorySkipped')}: {historyImportReport.skipped}
+                      </div>
+                    )}
                     {sessionHistoryLoading ? (
                       <div className="sessions-loading">{lang === 'ru' ? 'Загрузка...' : 'Loading...'}</div>
                     ) : sessionHistory.length === 0 ? (
@@ -2020,6 +4273,41 @@ export function Overlay() {
                             <div key={session.id} className="session-card">
                               <div className="session-card-header">
                                 <div className="session-date">{dateStr}</div>
+                                <button
+                                  className="session-delete-btn export"
+                                  onClick={() => handleSummaryCard(session.id)}
+                                  title={L('summaryCard')}
+                                >
+                                  ▣
+                                </button>
+                                <button
+                                  className="session-delete-btn export"
+                                  onClick={() => handleRevalueSession(session.id)}
+                                  title={L('revalueSession')}
+                                >
+                                  ↻
+                                </button>
+                                <button
+                                  className="session-delete-btn export"
+                                  onClick={() => handleExportXlsx(session.id)}
+                                  title={L('exportXlsx')}
+                                >
+                                  ⤓
+                                </button>
+                                <button
+                                  className="session-delete-btn export"
+                                  onClick={() => replay?.sessionId === session.id ? setReplay(null) : handleReplay(session.id, null)}
+                                  title={L('replaySession')}
+                                >
+                                  ⏱
+                                </button>
+                                <button
+                                  className="session-delete-btn export"
+                                  onClick={() => handleExportJournal(session.id)}
+                                  title={L('exportJournal')}
+                                >
+                                  ≡
+                                </button>
                                 <button 
                                   className="session-delete-btn"
                                   onClick={() => handleDeleteSession(session.id)}
@@ -2044,6 +4332,39 @@ export function Overlay() {
                                   </span>
                                 )}
                               </div>
+                              {session.raw_currency_gained != null && (
+                                <div className="session-raw-currency">
+                                  {L('rawCurrency')}: {session.raw_currency_gained >= 0 ? '+' : ''}{formatNumber(session.raw_currency_gained)} FE
+                                </div>
+                              )}
+                              {session.recovered && (
+                                <div className="session-raw-currency" title={L('recoveredHint')}>
+                                  ⚠ {L('recoveredSession')} ({formatDurationShort(session.recovery_gap_sec ?? 0)})
+                                </div>
+                              )}
+                              {revaluations[session.id] && (
+                                <div className="session-raw-currency">
+                                  {L('revaluedProfit')}: {revaluations[session.id].revalued_profit >= 0 ? '+' : ''}{formatNumber(revaluations[session.id].revalued_profit)} FE
+                                  {revaluations[session.id].unpriced_items > 0 && ` · ${L('netWorthUnpriced')}: ${revaluations[session.id].unpriced_items}`}
+                                </div>
+                              )}
+                              {replay?.sessionId === session.id && replay.stats.session_started_at && replay.stats.last_event_at && (
+                                <div className="session-raw-currency">
+                                  <input
+                                    type="range"
+                                    min={Date.parse(replay.stats.session_started_at)}
+                                    max={Date.parse(replay.stats.last_event_at)}
+                                    step={60000}
+                                    value={replay.stats.at ? Date.parse(replay.stats.at) : Date.parse(replay.stats.last_event_at)}
+                                    onChange={(e) => handleReplay(session.id, new Date(Number(e.target.value)).toISOString())}
+                                  />
+                                  <div>
+                                    {formatDuration(replay.stats.duration_sec)} · {L('maps')}: {replay.stats.maps_completed}
+                                    {' · '}{formatNumber(replay.stats.total_value)} FE · {L('netProfit')}: {formatNumber(replay.stats.net_profit)} FE · {formatNumber(replay.stats.hourly_profit)}/h
+                                    {replay.stats.is_paused && ` · ${L('paused')}`}
+                                  </div>
+                                </div>
+                              )}
                             </div>
                           );
                         })}
@@ -2051,6 +4372,21 @@ export function Overlay() {
                     )}
                   </div>
 
+                  {/* Account switcher */}
+                  <div className="account-switcher">
+                    {accounts.filter(a => !a.is_active).length > 0 && (
+                      <span className="account-switcher-title">{L('otherAccounts')}</span>
+                    )}
+                    {accounts.filter(a => !a.is_active).map(a => (
+                      <button key={a.user_id} className="account-switcher-item" onClick={() => handleSwitchAccount(a.user_id)}>
+                        {a.email ?? a.user_id}
+                      </button>
+                    ))}
+                    <button className="account-switcher-item add" onClick={handleAuthSignIn}>
+                      + {L('addAccount')}
+                    </button>
+                  </div>
+
                   {/* Logout Button */}
                   <div className="profile-actions">
                     {!showLogoutConfirm ? (
@@ -2096,8 +4432,36 @@ export function Overlay() {
                     <button className="control-btn start" onClick={handleAuthSignIn}>
                       {L('loginViaKripika')}
                     </button>
+                    <button className="control-btn" onClick={handleDeviceSignIn} disabled={deviceCode !== null}>
+                      {L('loginWithCode')}
+                    </button>
+                  </div>
+
+                  <div className="token-login">
+                    <input
+                      type="password"
+                      value={pastedToken}
+                      placeholder={L('tokenPlaceholder')}
+                      onChange={(e) => setPastedToken(e.target.value)}
+                    />
+                    <button className="control-btn" onClick={handleTokenSignIn} disabled={!pastedToken.trim()}>
+                      {L('loginWithToken')}
+                    </button>
                   </div>
 
+                  {deviceCode && (
+                    <div className="device-code">
+                      <span className="hint">{L('deviceCodeHint')}</span>
+                      <a href={deviceCode.verification_uri_complete ?? deviceCode.verification_uri} target="_blank" rel="noreferrer">
+                        {deviceCode.verification_uri}
+                      </a>
+                      <span className="device-code-value">{deviceCode.user_code}</span>
+                      <button className="confirm-btn cancel" onClick={handleDeviceCancel}>
+                        {L('cancel')}
+                      </button>
+                    </div>
+                  )}
+
                   {authError && (
                     <div className="auth-error">{authError}</div>
                   )}
@@ -2181,7 +4545,12 @@ export function Overlay() {
                           )}
                         </>
                       ) : (
-                        <span className="custom-badge">{lang === 'ru' ? 'Кастомный:' : 'Custom:'} {manualSearch}</span>
+                        <>
+                          <span className="custom-badge">{lang === 'ru' ? 'Кастомный:' : 'Custom:'} {manualSearch}</span>
+                          <button className="settings-btn-small" onClick={handleSaveCustomItem} title={L('saveCustomItemHint')}>
+                            {L('saveCustomItem')}
+                          </button>
+                        </>
                       )}
                     </div>
                   )}
@@ -2210,6 +4579,32 @@ export function Overlay() {
                       onChange={e => setManualPrice(parseFloat(e.target.value) || 0)}
                     />
                   </div>
+                  {exchangeRates.some(r => r.latest !== null) && (
+                    <div className="form-group">
+                      <label>{L('currency')}</label>
+                      <select
+                        className="overlay-input"
+                        value={manualCurrency ?? ''}
+                        onChange={e => setManualCurrency(e.target.value ? Number(e.target.value) : null)}
+                      >
+                        <option value="">FE</option>
+                        {exchangeRates.filter(r => r.latest !== null).map(r => (
+                          <option key={r.currency_id} value={r.currency_id}>{r.name}</option>
+                        ))}
+                      </select>
+                    </div>
+                  )}
+                  <div className="form-group">
+                    <label>{L('tier')}</label>
+                    <input 
+                      type="number" 
+                      className="overlay-input"
+                      min="1"
+                      placeholder="-"
+                      value={manualTier ?? ''}
+                      onChange={e => setManualTier(parseInt(e.target.value) || null)}
+                    />
+                  </div>
                 </div>
                 
                 <div className="form-total">
@@ -2217,6 +4612,37 @@ export function Overlay() {
                 </div>
               </div>
 
+              {/* Массовый ввод */}
+              <div className="form-group">
+                <label>{L('bulkDrops')}</label>
+                <textarea
+                  className="overlay-input import-textarea"
+                  rows={3}
+                  placeholder="2x Deification Ember @ 350"
+                  value={bulkText}
+                  onChange={e => { setBulkText(e.target.value); setBulkPreview(null); }}
+                />
+                {bulkPreview ? (
+                  <>
+                    {bulkPreview.entries.map(entry => (
+                      <div key={entry.id} className="settings-hint">
+                        {entry.game_id === null ? '? ' : ''}{lang === 'ru' ? (entry.name_ru || entry.name) : entry.name} x{entry.quantity} @ {formatNumber(entry.price)}
+                      </div>
+                    ))}
+                    {bulkPreview.errors.map(err => (
+                      <div key={err.line} className="settings-hint error">#{err.line} {err.text}: {err.error}</div>
+                    ))}
+                    <button className="settings-toggle" onClick={handleAddBulk} disabled={!isSessionActive || bulkPreview.entries.length === 0}>
+                      {L('bulkAdd')} ({bulkPreview.entries.length})
+                    </button>
+                  </>
+                ) : (
+                  <button className="settings-toggle" onClick={handleParseBulk} disabled={!bulkText.trim()}>
+                    {L('bulkPreview')}
+                  </button>
+                )}
+              </div>
+
               {/* Current manual drops */}
               {manualDrops.length > 0 && (
                 <div className="expenses-list">
@@ -2461,7 +4887,12 @@ export function Overlay() {
                             )}
                           </>
                         ) : (
+                          <>
                           <span className="custom-badge">{lang === 'ru' ? 'Кастомный:' : 'Custom:'} {manualSearch}</span>
+                          <button className="settings-btn-small" onClick={handleSaveCustomItem} title={L('saveCustomItemHint')}>
+                            {L('saveCustomItem')}
+                          </button>
+                        </>
                         )}
                       </div>
                     )}
@@ -2490,6 +4921,21 @@ export function Overlay() {
                         onChange={e => setManualPrice(parseFloat(e.target.value) || 0)}
                       />
                     </div>
+                    {exchangeRates.some(r => r.latest !== null) && (
+                      <div className="form-group">
+                        <label>{L('currency')}</label>
+                        <select
+                          className="overlay-input"
+                          value={manualCurrency ?? ''}
+                          onChange={e => setManualCurrency(e.target.value ? Number(e.target.value) : null)}
+                        >
+                          <option value="">FE</option>
+                          {exchangeRates.filter(r => r.latest !== null).map(r => (
+                            <option key={r.currency_id} value={r.currency_id}>{r.name}</option>
+                          ))}
+                        </select>
+                      </div>
+                    )}
                     <button 
                       className="add-item-btn"
                       onClick={handleAddExpense}
@@ -2652,112 +5098,1237 @@ export function Overlay() {
                     >
                       English
                     </button>
+                    <button 
+                      className={`settings-toggle ${appSettings.language === 'zh' ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, language: 'zh' })}
+                    >
+                      中文
+                    </button>
                   </div>
                 </div>
 
-                {/* Ориентация */}
+                {/* Канал обновлений */}
                 <div className="settings-group">
-                  <label className="settings-label">{L('interfaceOrientation')}</label>
+                  <label className="settings-label">{L('updateChannel')}</label>
                   <div className="settings-row">
                     <button 
-                      className={`settings-toggle ${appSettings.layout_orientation === 'vertical' ? 'active' : ''}`}
-                      onClick={() => handleSaveSettings({ 
-                        ...appSettings, 
-                        layout_orientation: 'vertical',
-                        panel_direction: appSettings.panel_direction === 'top' || appSettings.panel_direction === 'bottom' 
-                          ? 'right' : appSettings.panel_direction
-                      })}
+                      className={`settings-toggle ${(appSettings.update_channel ?? 'stable') === 'stable' ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, update_channel: 'stable' }).then(checkForUpdates)}
                     >
-                      {L('vertical')}
+                      Stable
                     </button>
                     <button 
-                      className={`settings-toggle ${appSettings.layout_orientation === 'horizontal' ? 'active' : ''}`}
-                      onClick={() => handleSaveSettings({ 
-                        ...appSettings, 
-                        layout_orientation: 'horizontal',
-                        panel_direction: appSettings.panel_direction === 'left' || appSettings.panel_direction === 'right' 
-                          ? 'bottom' : appSettings.panel_direction
-                      })}
+                      className={`settings-toggle ${appSettings.update_channel === 'beta' ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, update_channel: 'beta' }).then(checkForUpdates)}
                     >
-                      {L('horizontal')}
+                      Beta
                     </button>
                   </div>
                 </div>
 
-                {/* Направление панелей */}
+                {/* Выгрузка сессий в облако (opt-in); при включении дозагружаем историю */}
                 <div className="settings-group">
-                  <label className="settings-label">{L('panelDirection')}</label>
+                  <label className="settings-label">{L('cloudSync')}</label>
                   <div className="settings-row">
-                    {appSettings.layout_orientation === 'vertical' ? (
-                      <>
-                        <button 
-                          className={`settings-toggle ${appSettings.panel_direction === 'left' ? 'active' : ''}`}
-                          onClick={() => handleSaveSettings({ ...appSettings, panel_direction: 'left' })}
-                        >
-                          ← {L('left')}
-                        </button>
-                        <button 
-                          className={`settings-toggle ${appSettings.panel_direction === 'right' ? 'active' : ''}`}
-                          onClick={() => handleSaveSettings({ ...appSettings, panel_direction: 'right' })}
-                        >
-                          {L('right')} →
-                        </button>
-                      </>
-                    ) : (
-                      <>
-                        <button 
-                          className={`settings-toggle ${appSettings.panel_direction === 'top' ? 'active' : ''}`}
-                          onClick={() => handleSaveSettings({ ...appSettings, panel_direction: 'top' })}
-                        >
-                          ↑ {L('top')}
-                        </button>
-                        <button 
-                          className={`settings-toggle ${appSettings.panel_direction === 'bottom' ? 'active' : ''}`}
-                          onClick={() => handleSaveSettings({ ...appSettings, panel_direction: 'bottom' })}
-                        >
-                          {L('bottom')} ↓
-                        </button>
-                      </>
-                    )}
+                    <button 
+                      className={`settings-toggle ${appSettings.sync_sessions_to_cloud ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, sync_sessions_to_cloud: true })
+                        .then(() => invoke("sync_pending_sessions"))
+                        .catch((e) => console.error("Failed to sync pending sessions:", e))}
+                    >
+                      {L('cloudSyncOn')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${!appSettings.sync_sessions_to_cloud ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, sync_sessions_to_cloud: false })}
+                    >
+                      {L('cloudSyncOff')}
+                    </button>
                   </div>
                 </div>
 
-                {/* Комиссия аукциона */}
-                <div className="settings-group">
-                  <label className="settings-label">
-                    {L('auctionFee')}: {(appSettings.auction_fee_rate * 100).toFixed(1)}%
-                  </label>
-                  <input 
-                    type="range" 
-                    min="0" 
-                    max="25" 
-                    step="0.5"
-                    value={appSettings.auction_fee_rate * 100}
-                    onChange={(e) => handleSaveSettings({ 
-                      ...appSettings, 
-                      auction_fee_rate: parseFloat(e.target.value) / 100 
-                    })}
-                    className="settings-slider"
-                  />
-                </div>
-
-                {/* Прозрачность */}
+                {/* Дописывание завершённых сессий в Google-таблицу */}
                 <div className="settings-group">
-                  <label className="settings-label">
-                    {L('windowOpacity')}: {Math.round(appSettings.opacity * 100)}%
-                  </label>
-                  <input 
-                    type="range" 
-                    min="50" 
-                    max="100" 
-                    step="5"
-                    value={appSettings.opacity * 100}
+                  <label className="settings-label">{L('googleSheets')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${sheets.enabled ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, google_sheets: { ...sheets, enabled: true } })}
+                    >
+                      {L('cloudSyncOn')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${!sheets.enabled ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, google_sheets: { ...sheets, enabled: false } })}
+                    >
+                      {L('cloudSyncOff')}
+                    </button>
+                  </div>
+                  {sheets.enabled && (
+                    <>
+                      <input
+                        className="overlay-input"
+                        placeholder={L('sheetsSpreadsheetId')}
+                        defaultValue={sheets.spreadsheet_id}
+                        onBlur={(e) => handleSaveSettings({ ...appSettings, google_sheets: { ...sheets, spreadsheet_id: e.target.value.trim() } })}
+                      />
+                      <input
+                        className="overlay-input"
+                        placeholder={L('sheetsSheetName')}
+                        defaultValue={sheets.sheet_name}
+                        onBlur={(e) => handleSaveSettings({ ...appSettings, google_sheets: { ...sheets, sheet_name: e.target.value.trim() || 'Sessions' } })}
+                      />
+                      {sheetsCredential.kind === 'none' ? (
+                        <>
+                          <textarea
+                            className="overlay-input import-textarea"
+                            placeholder={L('sheetsCredentialPlaceholder')}
+                            value={sheetsCredentialDraft}
+                            onChange={(e) => setSheetsCredentialDraft(e.target.value)}
+                            rows={3}
+                          />
+                          <div className="settings-row">
+                            <button className="settings-toggle" onClick={handleSaveSheetsCredential} disabled={!sheetsCredentialDraft.trim()}>
+                              {L('sheetsSaveCredential')}
+                            </button>
+                          </div>
+                        </>
+                      ) : (
+                        <div className="settings-row">
+                          <button className="settings-toggle" onClick={handleTestSheets}>{L('sheetsTest')}</button>
+                          <button className="settings-toggle" onClick={handleClearSheetsCredential}>{L('sheetsClearCredential')}</button>
+                        </div>
+                      )}
+                      <span className="hint">
+                        {sheetsCredential.kind === 'service_account'
+                          ? `${L('sheetsShareWith')}: ${sheetsCredential.client_email}`
+                          : sheetsCredential.kind === 'access_token'
+                            ? L('sheetsAccessToken')
+                            : L('sheetsHint')}
+                        {sheetsStatus && <><br />{sheetsStatus}</>}
+                      </span>
+                    </>
+                  )}
+                </div>
+
+                {/* Офлайн-режим и статус обновления цен */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('offlineMode')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${appSettings.offline_mode ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, offline_mode: true })}
+                    >
+                      {L('cloudSyncOn')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${!appSettings.offline_mode ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, offline_mode: false })}
+                    >
+                      {L('cloudSyncOff')}
+                    </button>
+                  </div>
+                  {priceSync && (
+                    <span className="hint">
+                      {priceSync.offline
+                        ? L('priceSyncPaused')
+                        : `${L('priceSyncLast')}: ${priceSync.last_success_at ? new Date(priceSync.last_success_at).toLocaleTimeString() : '—'}`
+                          + (priceSync.next_run_at ? ` · ${L('priceSyncNext')}: ${new Date(priceSync.next_run_at).toLocaleTimeString()}` : '')}
+                      {priceSync.last_error && <><br />{describeError(lang, priceSync.last_error)}</>}
+                    </span>
+                  )}
+                  {connectivity && connectivity.state !== 'closed' && (
+                    <span className="hint">
+                      {L('supabaseDown')}
+                      {connectivity.retry_at && ` · ${L('priceSyncNext')}: ${new Date(connectivity.retry_at).toLocaleTimeString()}`}
+                    </span>
+                  )}
+                </div>
+
+                {/* Обновление списка предметов после патча */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('itemsList')}</label>
+                  <div className="settings-row">
+                    <button className="settings-toggle" onClick={handleRefreshItems} disabled={itemsRefreshing}>
+                      {itemsRefreshing ? '...' : L('itemsRefresh')}
+                    </button>
+                  </div>
+                  <span className="hint">
+                    {L('itemsRefreshHint')}
+                    {itemsRefreshError && <><br />{itemsRefreshError}</>}
+                    {itemsDiff && (
+                      itemsDiff.new_items.length + itemsDiff.renamed.length + itemsDiff.category_changes.length === 0
+                        ? <><br />{L('itemsUpToDate')} · {itemsDiff.total}</>
+                        : <>
+                            {itemsDiff.new_items.length > 0 && (
+                              <><br />{L('itemsNew')} ({itemsDiff.new_items.length}): {itemsDiff.new_items.map(i => i.name).join(', ')}</>
+                            )}
+                            {itemsDiff.renamed.length > 0 && (
+                              <><br />{L('itemsRenamed')}: {itemsDiff.renamed.map(r => `${r.old_name} → ${r.new_name}`).join(', ')}</>
+                            )}
+                            {itemsDiff.category_changes.length > 0 && (
+                              <><br />{L('itemsRecategorized')}: {itemsDiff.category_changes.map(c => `${c.name} (${c.old_category} → ${c.new_category})`).join(', ')}</>
+                            )}
+                          </>
+                    )}
+                  </span>
+                </div>
+
+                {/* Неизвестные предметы: сбор и отправка на сервер (opt-in) */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('unknownItems')}</label>
+                  <div className="settings-row">
+                    <span>{L('unknownItemsAutoReport')}</span>
+                    <button 
+                      className={`settings-toggle ${appSettings.report_unknown_items ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, report_unknown_items: true })}
+                    >
+                      {L('cloudSyncOn')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${!appSettings.report_unknown_items ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, report_unknown_items: false })}
+                    >
+                      {L('cloudSyncOff')}
+                    </button>
+                  </div>
+                  {unknownItems.length > 0 && (
+                    <>
+                      <span className="hint">
+                        {unknownItems.slice(0, 10).map(i => (
+                          <span key={i.game_id}>
+                            {i.game_id}{i.name && ` ${i.name}`} × {i.total_quantity} ({i.seen_count}){i.reported && ' ✓'}<br />
+                          </span>
+                        ))}
+                        {unknownItems.length > 10 && `… +${unknownItems.length - 10}`}
+                      </span>
+                      <div className="settings-row">
+                        <button
+                          className="settings-toggle"
+                          onClick={handleReportUnknownItems}
+                          disabled={unknownItems.every(i => i.reported)}
+                        >
+                          {L('unknownItemsReport')}
+                        </button>
+                        <button
+                          className="settings-toggle"
+                          onClick={() => invoke("clear_unknown_items").then(loadUnknownItems).catch(console.error)}
+                        >
+                          {L('unknownItemsClear')}
+                        </button>
+                      </div>
+                    </>
+                  )}
+                  <span className="hint">
+                    {unknownItems.length === 0 ? L('unknownItemsNone') : L('unknownItemsHint')}
+                    {unknownReportStatus && <><br />{unknownReportStatus}</>}
+                  </span>
+                </div>
+
+                {/* Прокси для запросов к серверу */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('proxy')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${proxy.enabled ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, proxy: { ...proxy, enabled: true } })}
+                    >
+                      {L('cloudSyncOn')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${!proxy.enabled ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, proxy: { ...proxy, enabled: false } })}
+                    >
+                      {L('cloudSyncOff')}
+                    </button>
+                  </div>
+                  {proxy.enabled && (
+                    <>
+                      <div className="settings-row">
+                        {(['http', 'socks5'] as const).map(kind => (
+                          <button
+                            key={kind}
+                            className={`settings-toggle ${proxy.kind === kind ? 'active' : ''}`}
+                            onClick={() => handleSaveSettings({ ...appSettings, proxy: { ...proxy, kind } })}
+                          >
+                            {kind === 'http' ? 'HTTP' : 'SOCKS5'}
+                          </button>
+                        ))}
+                      </div>
+                      <div className="settings-row">
+                        <input
+                          className="overlay-input"
+                          placeholder={L('proxyHost')}
+                          defaultValue={proxy.host}
+                          onBlur={(e) => handleSaveSettings({ ...appSettings, proxy: { ...proxy, host: e.target.value.trim() } })}
+                        />
+                        <input
+                          type="number"
+                          className="overlay-input"
+                          placeholder={L('proxyPort')}
+                          min={1}
+                          max={65535}
+                          defaultValue={proxy.port || ''}
+                          onBlur={(e) => handleSaveSettings({ ...appSettings, proxy: { ...proxy, port: Math.min(65535, Math.max(0, parseInt(e.target.value) || 0)) } })}
+                        />
+                      </div>
+                      <input
+                        className="overlay-input"
+                        placeholder={L('proxyUsername')}
+                        defaultValue={proxy.username ?? ''}
+                        onBlur={(e) => handleSaveSettings({ ...appSettings, proxy: { ...proxy, username: e.target.value.trim() || null } })}
+                      />
+                      <input
+                        type="password"
+                        className="overlay-input"
+                        placeholder={L('proxyPassword')}
+                        onBlur={(e) => {
+                          if (!e.target.value) return;
+                          invoke("set_proxy_password", { password: e.target.value }).catch(err => setProxyStatus(String(err)));
+                          e.target.value = '';
+                        }}
+                      />
+                    </>
+                  )}
+                  <div className="settings-row">
+                    <button className="settings-toggle" onClick={handleTestProxy}>{L('proxyTest')}</button>
+                  </div>
+                  <span className="hint">
+                    {L('proxyHint')}
+                    {proxyStatus && <><br />{proxyStatus}</>}
+                  </span>
+                </div>
+
+                {/* Свой сервер цен (адрес, ключ, префиксы) */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('backend')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${!backend.enabled ? 'active' : ''}`}
+                      onClick={() => handleApplyBackend({ ...backend, enabled: false })}
+                    >
+                      {L('backendBuiltin')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${backend.enabled ? 'active' : ''}`}
+                      onClick={() => setBackendDraft({ ...backend, enabled: true })}
+                    >
+                      {L('backendCustom')}
+                    </button>
+                  </div>
+                  {backend.enabled && (
+                    <>
+                      <input
+                        className="overlay-input"
+                        placeholder={L('backendUrl')}
+                        value={backend.url}
+                        onChange={(e) => setBackendDraft({ ...backend, url: e.target.value })}
+                      />
+                      <input
+                        className="overlay-input"
+                        placeholder={L('backendAnonKey')}
+                        value={backend.anon_key}
+                        onChange={(e) => setBackendDraft({ ...backend, anon_key: e.target.value })}
+                      />
+                      <div className="settings-row">
+                        <input
+                          className="overlay-input"
+                          placeholder={L('backendTablePrefix')}
+                          value={backend.table_prefix}
+                          onChange={(e) => setBackendDraft({ ...backend, table_prefix: e.target.value })}
+                        />
+                        <input
+                          className="overlay-input"
+                          placeholder={L('backendRpcPrefix')}
+                          value={backend.rpc_prefix}
+                          onChange={(e) => setBackendDraft({ ...backend, rpc_prefix: e.target.value })}
+                        />
+                      </div>
+                      <div className="settings-row">
+                        <button
+                          className="settings-toggle"
+                          onClick={() => handleApplyBackend(backend)}
+                          disabled={!backend.url.trim() || !backend.anon_key.trim()}
+                        >
+                          {L('backendApply')}
+                        </button>
+                      </div>
+                    </>
+                  )}
+                  <span className="hint">
+                    {L('backendHint')}
+                    {backendStatus && <><br />{backendStatus}</>}
+                  </span>
+                </div>
+
+                {/* Откуда читать лог при запуске: с конца или с момента времени */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('logAttach')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${(appSettings.log_attach_mode ?? 'end') === 'end' ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, log_attach_mode: 'end' })}
+                    >
+                      {L('logAttachEnd')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${appSettings.log_attach_mode === 'since' ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({
+                        ...appSettings,
+                        log_attach_mode: 'since',
+                        log_attach_since: appSettings.log_attach_since ?? new Date().toISOString(),
+                      })}
+                    >
+                      {L('logAttachSince')}
+                    </button>
+                  </div>
+                  {appSettings.log_attach_mode === 'since' && (
+                    <input
+                      type="datetime-local"
+                      className="overlay-input"
+                      value={appSettings.log_attach_since
+                        ? (() => {
+                            const d = new Date(appSettings.log_attach_since);
+                            return new Date(d.getTime() - d.getTimezoneOffset() * 60000).toISOString().slice(0, 16);
+                          })()
+                        : ''}
+                      onChange={(e) => {
+                        if (!e.target.value) return;
+                        handleSaveSettings({ ...appSettings, log_attach_since: new Date(e.target.value).toISOString() });
+                      }}
+                    />
+                  )}
+                  <span className="hint">{L('logAttachHint')}</span>
+                </div>
+
+                {/* Какие зоны учитывать: все или только фарм-карты */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('farmingZones')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${!appSettings.farming_zones_only ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, farming_zones_only: false })}
+                    >
+                      {L('farmingZonesAll')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${appSettings.farming_zones_only ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, farming_zones_only: true })}
+                    >
+                      {L('farmingZonesOnly')}
+                    </button>
+                  </div>
+                  <span className="hint">{L('farmingZonesHint')}</span>
+                </div>
+
+                {/* Автопауза по процессу игры */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('autoPause')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${(appSettings.auto_pause_on_game_exit ?? true) ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, auto_pause_on_game_exit: true })}
+                    >
+                      {L('cloudSyncOn')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${!(appSettings.auto_pause_on_game_exit ?? true) ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, auto_pause_on_game_exit: false })}
+                    >
+                      {L('cloudSyncOff')}
+                    </button>
+                  </div>
+                  <span className="hint">{L('autoPauseHint')}</span>
+                  <div className="settings-row">
+                    <span>{L('afkPause')}</span>
+                    <input
+                      className="overlay-input"
+                      type="number"
+                      min={0}
+                      defaultValue={appSettings.afk_pause_minutes ?? 0}
+                      onBlur={(e) => handleSaveSettings({ ...appSettings, afk_pause_minutes: Math.max(0, Math.floor(Number(e.target.value) || 0)) })}
+                    />
+                  </div>
+                  <div className="settings-row">
+                    <span>{L('abandonedMapCap')}</span>
+                    <input
+                      className="overlay-input"
+                      type="number"
+                      min={0}
+                      defaultValue={appSettings.abandoned_map_minutes ?? 60}
+                      onBlur={(e) => handleSaveSettings({ ...appSettings, abandoned_map_minutes: Math.max(0, Math.floor(Number(e.target.value) || 0)) })}
+                    />
+                  </div>
+                </div>
+
+                {/* Клиент игры (пути установки и формат лога) */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('gameClient')}</label>
+                  <div className="settings-row">
+                    {(['auto', 'global', 'cn'] as const).map((client) => (
+                      <button 
+                        key={client}
+                        className={`settings-toggle ${(appSettings.game_client ?? 'auto') === client ? 'active' : ''}`}
+                        onClick={() => handleSaveSettings({ ...appSettings, game_client: client })}
+                      >
+                        {L(client === 'auto' ? 'gameClientAuto' : client === 'global' ? 'gameClientGlobal' : 'gameClientCn')}
+                      </button>
+                    ))}
+                  </div>
+                </div>
+
+                {/* Часовой пояс меток времени в логе */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('logTimeZone')}</label>
+                  <div className="settings-row">
+                    {(['local', 'utc', 'fixed'] as const).map((zone) => (
+                      <button
+                        key={zone}
+                        className={`settings-toggle ${(appSettings.log_time_zone ?? 'local') === zone ? 'active' : ''}`}
+                        onClick={() => handleSaveSettings({ ...appSettings, log_time_zone: zone })}
+                      >
+                        {L(zone === 'local' ? 'logTimeZoneLocal' : zone === 'utc' ? 'logTimeZoneUtc' : 'logTimeZoneFixed')}
+                      </button>
+                    ))}
+                  </div>
+                  {appSettings.log_time_zone === 'fixed' && (
+                    <div className="settings-row">
+                      <span>{L('logUtcOffset')}</span>
+                      <input
+                        className="overlay-input"
+                        type="number"
+                        min={-720}
+                        max={840}
+                        step={15}
+                        defaultValue={appSettings.log_utc_offset_minutes ?? 0}
+                        onBlur={(e) => handleSaveSettings({ ...appSettings, log_utc_offset_minutes: Math.max(-720, Math.min(840, Math.floor(Number(e.target.value) || 0))) })}
+                      />
+                    </div>
+                  )}
+                  <span className="hint">{L('logTimeZoneHint')}</span>
+                </div>
+
+                {/* Ориентация */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('interfaceOrientation')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${appSettings.layout_orientation === 'vertical' ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ 
+                        ...appSettings, 
+                        layout_orientation: 'vertical',
+                        panel_direction: appSettings.panel_direction === 'top' || appSettings.panel_direction === 'bottom' 
+                          ? 'right' : appSettings.panel_direction
+                      })}
+                    >
+                      {L('vertical')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${appSettings.layout_orientation === 'horizontal' ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ 
+                        ...appSettings, 
+                        layout_orientation: 'horizontal',
+                        panel_direction: appSettings.panel_direction === 'left' || appSettings.panel_direction === 'right' 
+                          ? 'bottom' : appSettings.panel_direction
+                      })}
+                    >
+                      {L('horizontal')}
+                    </button>
+                  </div>
+                </div>
+
+                {/* Направление панелей */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('panelDirection')}</label>
+                  <div className="settings-row">
+                    {appSettings.layout_orientation === 'vertical' ? (
+                      <>
+                        <button 
+                          className={`settings-toggle ${appSettings.panel_direction === 'left' ? 'active' : ''}`}
+                          onClick={() => handleSaveSettings({ ...appSettings, panel_direction: 'left' })}
+                        >
+                          ← {L('left')}
+                        </button>
+                        <button 
+                          className={`settings-toggle ${appSettings.panel_direction === 'right' ? 'active' : ''}`}
+                          onClick={() => handleSaveSettings({ ...appSettings, panel_direction: 'right' })}
+                        >
+                          {L('right')} →
+                        </button>
+                      </>
+                    ) : (
+                      <>
+                        <button 
+                          className={`settings-toggle ${appSettings.panel_direction === 'top' ? 'active' : ''}`}
+                          onClick={() => handleSaveSettings({ ...appSettings, panel_direction: 'top' })}
+                        >
+                          ↑ {L('top')}
+                        </button>
+                        <button 
+                          className={`settings-toggle ${appSettings.panel_direction === 'bottom' ? 'active' : ''}`}
+                          onClick={() => handleSaveSettings({ ...appSettings, panel_direction: 'bottom' })}
+                        >
+                          {L('bottom')} ↓
+                        </button>
+                      </>
+                    )}
+                  </div>
+                </div>
+
+                {/* Комиссия аукциона */}
+                <div className="settings-group">
+                  <label className="settings-label">
+                    {L('auctionFee')}: {(appSettings.auction_fee_rate * 100).toFixed(1)}%
+                  </label>
+                  <input 
+                    type="range" 
+                    min="0" 
+                    max="25" 
+                    step="0.5"
+                    value={appSettings.auction_fee_rate * 100}
                     onChange={(e) => handleSaveSettings({ 
                       ...appSettings, 
-                      opacity: parseFloat(e.target.value) / 100 
+                      auction_fee_rate: parseFloat(e.target.value) / 100 
                     })}
                     className="settings-slider"
                   />
+                  {feeCategories.length > 0 && (
+                    <>
+                      <label className="settings-label">{L('categoryFees')}</label>
+                      {feeCategories.map(category => {
+                        const noFee = appSettings.no_fee_categories?.includes(category) ?? false;
+                        const rate = appSettings.category_fee_rates?.[category];
+                        return (
+                          <div key={category} className="settings-row fee-category-row">
+                            <span className="fee-category-name">{categoryLabel(category)}</span>
+                            <input
+                              type="number"
+                              min="0"
+                              max="100"
+                              step="0.5"
+                              className="fee-category-input"
+                              disabled={noFee}
+                              placeholder={(appSettings.auction_fee_rate * 100).toFixed(1)}
+                              value={rate !== undefined ? +(rate * 100).toFixed(2) : ''}
+                              onChange={(e) => setCategoryFee(category, e.target.value)}
+                            />
+                            <button
+                              className={`settings-toggle ${noFee ? 'active' : ''}`}
+                              onClick={() => toggleNoFee(category)}
+                            >
+                              {L('noFee')}
+                            </button>
+                          </div>
+                        );
+                      })}
+                      <p className="settings-hint">{L('categoryFeesHint')}</p>
+                    </>
+                  )}
+                </div>
+
+                {/* Звуки на дроп: правила по стоимости и категории */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('dropSounds')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${dropSounds.enabled ? 'active' : ''}`}
+                      onClick={() => saveDropSounds({ ...dropSounds, enabled: true })}
+                    >
+                      {L('cloudSyncOn')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${!dropSounds.enabled ? 'active' : ''}`}
+                      onClick={() => saveDropSounds({ ...dropSounds, enabled: false })}
+                    >
+                      {L('cloudSyncOff')}
+                    </button>
+                  </div>
+                  {dropSounds.enabled && (
+                    <>
+                      <label className="settings-label">
+                        {L('dropSoundsVolume')}: {Math.round(dropSounds.volume * 100)}%
+                      </label>
+                      <input 
+                        type="range" 
+                        min="0" 
+                        max="100" 
+                        step="5"
+                        value={dropSounds.volume * 100}
+                        onChange={(e) => saveDropSounds({ ...dropSounds, volume: parseFloat(e.target.value) / 100 })}
+                        className="settings-slider"
+                      />
+                      {dropSounds.rules.map((rule, index) => (
+                        <div key={index} className="settings-row">
+                          <input
+                            type="checkbox"
+                            checked={rule.enabled}
+                            onChange={(e) => updateSoundRule(index, { enabled: e.target.checked })}
+                          />
+                          <input
+                            type="number"
+                            min="0"
+                            className="overlay-input"
+                            placeholder={L('dropSoundsMinValue')}
+                            defaultValue={rule.min_value ?? ''}
+                            onBlur={(e) => {
+                              const value = parseFloat(e.target.value);
+                              updateSoundRule(index, { min_value: isNaN(value) ? null : value });
+                            }}
+                          />
+                          <select
+                            className="overlay-input"
+                            value={rule.category ?? ''}
+                            onChange={e => updateSoundRule(index, { category: e.target.value || null })}
+                          >
+                            <option value="">{L('dropSoundsAnyCategory')}</option>
+                            {categories.map(c => (
+                              <option key={c.id} value={c.id}>{c.path}</option>
+                            ))}
+                            {rule.category && !categoryIndex.has(rule.category) && (
+                              <option value={rule.category}>{rule.category}</option>
+                            )}
+                          </select>
+                          <select
+                            className="overlay-input"
+                            value={rule.sound}
+                            onChange={e => e.target.value === '' ? pickSoundFile(index) : updateSoundRule(index, { sound: e.target.value })}
+                          >
+                            {bundledSounds.map(s => (
+                              <option key={s.id} value={s.id}>{s.name}</option>
+                            ))}
+                            {!rule.sound.startsWith('bundled:') && (
+                              <option value={rule.sound}>{rule.sound.split(/[\\/]/).pop()}</option>
+                            )}
+                            <option value="">{L('dropSoundsFile')}</option>
+                          </select>
+                          <button
+                            className="settings-toggle"
+                            onClick={() => invoke('preview_drop_sound', { sound: rule.sound, volume: rule.volume }).catch(console.error)}
+                          >
+                            ▶
+                          </button>
+                          <button
+                            className="settings-toggle"
+                            onClick={() => saveDropSounds({ ...dropSounds, rules: dropSounds.rules.filter((_, i) => i !== index) })}
+                          >
+                            ×
+                          </button>
+                        </div>
+                      ))}
+                      <div className="settings-row">
+                        <button
+                          className="settings-toggle"
+                          onClick={() => saveDropSounds({
+                            ...dropSounds,
+                            rules: [...dropSounds.rules, { enabled: true, min_value: null, category: null, sound: bundledSounds[0]?.id ?? 'bundled:chime', volume: null }],
+                          })}
+                        >
+                          {L('dropSoundsAddRule')}
+                        </button>
+                      </div>
+                    </>
+                  )}
+                  <p className="settings-hint">{L('dropSoundsHint')}</p>
+                </div>
+
+                {/* Локальный API для Stream Deck */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('controllerApi')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${controllerApi.enabled ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, controller_api: { ...controllerApi, enabled: true } })}
+                    >
+                      {L('cloudSyncOn')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${!controllerApi.enabled ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, controller_api: { ...controllerApi, enabled: false } })}
+                    >
+                      {L('cloudSyncOff')}
+                    </button>
+                  </div>
+                  {controllerApi.enabled && (
+                    <>
+                      <div className="settings-row">
+                        <span>{L('controllerPort')}</span>
+                        <input
+                          type="number"
+                          min="1024"
+                          max="65535"
+                          className="overlay-input"
+                          defaultValue={controllerApi.port}
+                          onBlur={(e) => {
+                            const port = parseInt(e.target.value);
+                            if (port >= 1024 && port <= 65535 && port !== controllerApi.port) {
+                              handleSaveSettings({ ...appSettings, controller_api: { ...controllerApi, port } });
+                            }
+                          }}
+                        />
+                      </div>
+                      {controllerInfo && (
+                        <>
+                          <span className="hint">
+                            {controllerInfo.url}
+                            {!controllerInfo.running && <><br />{L('controllerNotRunning')}</>}
+                          </span>
+                          <div className="settings-row">
+                            <button className="settings-toggle" onClick={copyControllerToken}>
+                              {controllerCopied ? L('controllerCopied') : L('controllerCopyToken')}
+                            </button>
+                            <button
+                              className="settings-toggle"
+                              onClick={() => invoke<ControllerApiInfo>("regenerate_controller_api_token").then(setControllerInfo).catch(console.error)}
+                            >
+                              {L('controllerNewToken')}
+                            </button>
+                          </div>
+                        </>
+                      )}
+                    </>
+                  )}
+                  <p className="settings-hint">{L('controllerHint')}</p>
+                </div>
+
+                {/* live_stats.json для сторонних инструментов */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('liveStats')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${liveStats.enabled ? 'active' : ''}`}
+                      onClick={() => saveLiveStats({ ...liveStats, enabled: true })}
+                    >
+                      {L('cloudSyncOn')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${!liveStats.enabled ? 'active' : ''}`}
+                      onClick={() => saveLiveStats({ ...liveStats, enabled: false })}
+                    >
+                      {L('cloudSyncOff')}
+                    </button>
+                  </div>
+                  {liveStats.enabled && (
+                    <>
+                      <input
+                        className="overlay-input"
+                        placeholder={L('liveStatsDefaultPath')}
+                        defaultValue={liveStats.path ?? ''}
+                        onBlur={(e) => saveLiveStats({ ...liveStats, path: e.target.value.trim() || null })}
+                      />
+                      <div className="settings-row">
+                        <span>{L('liveStatsInterval')}</span>
+                        <input
+                          type="number"
+                          min="1"
+                          className="overlay-input"
+                          defaultValue={liveStats.interval_secs}
+                          onBlur={(e) => {
+                            const secs = parseInt(e.target.value);
+                            if (secs >= 1) saveLiveStats({ ...liveStats, interval_secs: secs });
+                          }}
+                        />
+                        <span>{L('liveStatsTopDrops')}</span>
+                        <input
+                          type="number"
+                          min="0"
+                          max="50"
+                          className="overlay-input"
+                          defaultValue={liveStats.top_drops}
+                          onBlur={(e) => {
+                            const count = parseInt(e.target.value);
+                            if (count >= 0 && count <= 50) saveLiveStats({ ...liveStats, top_drops: count });
+                          }}
+                        />
+                      </div>
+                      <div className="settings-row">
+                        <button className="settings-toggle" onClick={handleWriteLiveStats}>{L('liveStatsWriteNow')}</button>
+                      </div>
+                    </>
+                  )}
+                  <span className="hint">
+                    {L('liveStatsHint')}
+                    {liveStatsMessage && <><br />{liveStatsMessage}</>}
+                  </span>
+                </div>
+
+                {/* Объявления в чат Twitch */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('twitch')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${twitch.enabled ? 'active' : ''}`}
+                      onClick={() => saveTwitch({ ...twitch, enabled: true })}
+                    >
+                      {L('cloudSyncOn')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${!twitch.enabled ? 'active' : ''}`}
+                      onClick={() => saveTwitch({ ...twitch, enabled: false })}
+                    >
+                      {L('cloudSyncOff')}
+                    </button>
+                  </div>
+                  {twitch.enabled && (
+                    <>
+                      <div className="settings-row">
+                        <input
+                          className="overlay-input"
+                          placeholder={L('twitchChannel')}
+                          defaultValue={twitch.channel}
+                          onBlur={(e) => saveTwitch({ ...twitch, channel: e.target.value.trim() })}
+                        />
+                        <input
+                          className="overlay-input"
+                          placeholder={L('twitchLogin')}
+                          defaultValue={twitch.login}
+                          onBlur={(e) => saveTwitch({ ...twitch, login: e.target.value.trim() })}
+                        />
+                      </div>
+                      <input
+                        type="password"
+                        className="overlay-input"
+                        placeholder={twitchStatus?.has_token ? L('twitchTokenSaved') : L('twitchToken')}
+                        onBlur={(e) => {
+                          if (!e.target.value) return;
+                          invoke<TwitchStatus>("set_twitch_token", { token: e.target.value })
+                            .then(setTwitchStatus)
+                            .catch(err => setTwitchMessage(String(err)));
+                          e.target.value = '';
+                        }}
+                      />
+                      <div className="settings-row">
+                        <span>{L('ttsMinValue')}</span>
+                        <input
+                          type="number"
+                          min="0"
+                          className="overlay-input"
+                          defaultValue={twitch.drop_min_value}
+                          onBlur={(e) => {
+                            const value = parseFloat(e.target.value);
+                            if (!isNaN(value) && value >= 0) saveTwitch({ ...twitch, drop_min_value: value });
+                          }}
+                        />
+                        <span>{L('ttsInterval')}</span>
+                        <input
+                          type="number"
+                          min="0"
+                          className="overlay-input"
+                          defaultValue={twitch.min_interval_secs}
+                          onBlur={(e) => {
+                            const secs = parseInt(e.target.value);
+                            if (!isNaN(secs) && secs >= 0) saveTwitch({ ...twitch, min_interval_secs: secs });
+                          }}
+                        />
+                      </div>
+                      <input
+                        className="overlay-input"
+                        defaultValue={twitch.drop_template}
+                        onBlur={(e) => saveTwitch({ ...twitch, drop_template: e.target.value })}
+                      />
+                      <div className="settings-row">
+                        <input
+                          type="checkbox"
+                          checked={twitch.summary_enabled}
+                          onChange={(e) => saveTwitch({ ...twitch, summary_enabled: e.target.checked })}
+                        />
+                        <input
+                          className="overlay-input"
+                          defaultValue={twitch.summary_template}
+                          disabled={!twitch.summary_enabled}
+                          onBlur={(e) => saveTwitch({ ...twitch, summary_template: e.target.value })}
+                        />
+                      </div>
+                      <div className="settings-row">
+                        <button className="settings-toggle" onClick={handleTestTwitch}>{L('twitchTest')}</button>
+                        <button
+                          className={`settings-toggle ${twitchStatus?.muted ? 'active' : ''}`}
+                          onClick={() => invoke<TwitchStatus>("toggle_twitch_chat").then(setTwitchStatus).catch(console.error)}
+                        >
+                          {twitchStatus?.muted ? L('twitchUnmute') : L('twitchMute')}
+                        </button>
+                      </div>
+                    </>
+                  )}
+                  <span className="hint">
+                    {L('twitchHint')}
+                    {twitchMessage && <><br />{twitchMessage}</>}
+                  </span>
+                </div>
+
+                {/* Автоматизация OBS (obs-websocket) */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('obs')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${obs.enabled ? 'active' : ''}`}
+                      onClick={() => saveObs({ ...obs, enabled: true })}
+                    >
+                      {L('cloudSyncOn')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${!obs.enabled ? 'active' : ''}`}
+                      onClick={() => saveObs({ ...obs, enabled: false })}
+                    >
+                      {L('cloudSyncOff')}
+                    </button>
+                  </div>
+                  {obs.enabled && (
+                    <>
+                      <div className="settings-row">
+                        <input
+                          className="overlay-input"
+                          placeholder="127.0.0.1"
+                          defaultValue={obs.host}
+                          onBlur={(e) => saveObs({ ...obs, host: e.target.value.trim() || '127.0.0.1' })}
+                        />
+                        <input
+                          type="number"
+                          min={1}
+                          max={65535}
+                          className="overlay-input"
+                          defaultValue={obs.port}
+                          onBlur={(e) => {
+                            const port = parseInt(e.target.value);
+                            if (port >= 1 && port <= 65535 && port !== obs.port) saveObs({ ...obs, port });
+                          }}
+                        />
+                      </div>
+                      <input
+                        type="password"
+                        className="overlay-input"
+                        placeholder={L('obsPassword')}
+                        onBlur={(e) => {
+                          if (!e.target.value) return;
+                          invoke("set_obs_password", { password: e.target.value }).catch(err => setObsMessage(String(err)));
+                          e.target.value = '';
+                        }}
+                      />
+                      <div className="settings-row">
+                        <button className="settings-toggle" onClick={handleTestObs}>{L('proxyTest')}</button>
+                      </div>
+                      {obs.rules.map((rule, index) => (
+                        <div key={index} className="settings-row">
+                          <input
+                            type="checkbox"
+                            checked={rule.enabled}
+                            onChange={(e) => updateObsRule(index, { enabled: e.target.checked })}
+                          />
+                          <select
+                            className="overlay-input"
+                            value={rule.trigger}
+                            onChange={e => updateObsRule(index, { trigger: e.target.value as ObsTrigger })}
+                          >
+                            <option value="session_start">{L('obsSessionStart')}</option>
+                            <option value="session_end">{L('obsSessionEnd')}</option>
+                            <option value="big_drop">{L('obsBigDrop')}</option>
+                          </select>
+                          {rule.trigger === 'big_drop' && (
+                            <input
+                              type="number"
+                              min="0"
+                              className="overlay-input"
+                              placeholder={L('ttsMinValue')}
+                              defaultValue={rule.min_value ?? ''}
+                              onBlur={(e) => {
+                                const value = parseFloat(e.target.value);
+                                updateObsRule(index, { min_value: isNaN(value) ? null : value });
+                              }}
+                            />
+                          )}
+                          <select
+                            className="overlay-input"
+                            value={rule.action.kind}
+                            onChange={e => updateObsRule(index, {
+                              action: e.target.value === 'switch_scene'
+                                ? { kind: 'switch_scene', scene: rule.action.scene }
+                                : { kind: 'set_source_visible', scene: rule.action.scene, source: '', visible: null },
+                            })}
+                          >
+                            <option value="switch_scene">{L('obsSwitchScene')}</option>
+                            <option value="set_source_visible">{L('obsSourceVisible')}</option>
+                          </select>
+                          {obsStatus && obsStatus.scenes.length > 0 ? (
+                            <select
+                              className="overlay-input"
+                              value={rule.action.scene}
+                              onChange={e => updateObsRule(index, { action: { ...rule.action, scene: e.target.value } })}
+                            >
+                              <option value="">{L('obsScene')}</option>
+                              {obsStatus.scenes.map(scene => (
+                                <option key={scene} value={scene}>{scene}</option>
+                              ))}
+                              {rule.action.scene && !obsStatus.scenes.includes(rule.action.scene) && (
+                                <option value={rule.action.scene}>{rule.action.scene}</option>
+                              )}
+                            </select>
+                          ) : (
+                            <input
+                              className="overlay-input"
+                              placeholder={L('obsScene')}
+                              defaultValue={rule.action.scene}
+                              onBlur={(e) => updateObsRule(index, { action: { ...rule.action, scene: e.target.value.trim() } })}
+                            />
+                          )}
+                          {rule.action.kind === 'set_source_visible' && (
+                            <>
+                              <input
+                                className="overlay-input"
+                                placeholder={L('obsSource')}
+                                defaultValue={rule.action.source}
+                                onBlur={(e) => updateObsRule(index, { action: { ...rule.action, source: e.target.value.trim() } as ObsAction })}
+                              />
+                              <select
+                                className="overlay-input"
+                                value={rule.action.visible === null ? 'toggle' : String(rule.action.visible)}
+                                onChange={e => updateObsRule(index, {
+                                  action: { ...rule.action, visible: e.target.value === 'toggle' ? null : e.target.value === 'true' } as ObsAction,
+                                })}
+                              >
+                                <option value="true">{L('obsShow')}</option>
+                                <option value="false">{L('obsHide')}</option>
+                                <option value="toggle">{L('obsToggle')}</option>
+                              </select>
+                            </>
+                          )}
+                          <button className="settings-toggle" onClick={() => handleTestObsAction(rule.action)}>
+                            ▶
+                          </button>
+                          <button
+                            className="settings-toggle"
+                            onClick={() => saveObs({ ...obs, rules: obs.rules.filter((_, i) => i !== index) })}
+                          >
+                            ×
+                          </button>
+                        </div>
+                      ))}
+                      <div className="settings-row">
+                        <button
+                          className="settings-toggle"
+                          onClick={() => saveObs({
+                            ...obs,
+                            rules: [...obs.rules, {
+                              enabled: true,
+                              trigger: 'session_start',
+                              min_value: null,
+                              action: { kind: 'switch_scene', scene: obsStatus?.current_scene ?? '' },
+                            }],
+                          })}
+                        >
+                          {L('dropSoundsAddRule')}
+                        </button>
+                      </div>
+                    </>
+                  )}
+                  <span className="hint">
+                    {L('obsHint')}
+                    {obsMessage && <><br />{obsMessage}</>}
+                  </span>
+                </div>
+
+                {/* Голосовые объявления ценных дропов */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('tts')}</label>
+                  <div className="settings-row">
+                    <button 
+                      className={`settings-toggle ${tts.enabled ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, tts: { ...tts, enabled: true } })}
+                    >
+                      {L('cloudSyncOn')}
+                    </button>
+                    <button 
+                      className={`settings-toggle ${!tts.enabled ? 'active' : ''}`}
+                      onClick={() => handleSaveSettings({ ...appSettings, tts: { ...tts, enabled: false } })}
+                    >
+                      {L('cloudSyncOff')}
+                    </button>
+                  </div>
+                  {tts.enabled && (
+                    <>
+                      <div className="settings-row">
+                        <span>{L('ttsMinValue')}</span>
+                        <input
+                          type="number"
+                          min="0"
+                          className="overlay-input"
+                          defaultValue={tts.min_value}
+                          onBlur={(e) => {
+                            const value = parseFloat(e.target.value);
+                            if (!isNaN(value) && value >= 0) handleSaveSettings({ ...appSettings, tts: { ...tts, min_value: value } });
+                          }}
+                        />
+                      </div>
+                      <div className="settings-row">
+                        <span>{L('ttsInterval')}</span>
+                        <input
+                          type="number"
+                          min="0"
+                          className="overlay-input"
+                          defaultValue={tts.min_interval_secs}
+                          onBlur={(e) => {
+                            const value = parseInt(e.target.value);
+                            if (!isNaN(value) && value >= 0) handleSaveSettings({ ...appSettings, tts: { ...tts, min_interval_secs: value } });
+                          }}
+                        />
+                      </div>
+                      <label className="settings-label">
+                        {L('dropSoundsVolume')}: {Math.round(tts.volume * 100)}%
+                      </label>
+                      <input 
+                        type="range" 
+                        min="0" 
+                        max="100" 
+                        step="5"
+                        value={tts.volume * 100}
+                        onChange={(e) => handleSaveSettings({ ...appSettings, tts: { ...tts, volume: parseFloat(e.target.value) / 100 } })}
+                        className="settings-slider"
+                      />
+                      <div className="settings-row">
+                        <button className="settings-toggle" onClick={() => invoke('preview_tts').catch(console.error)}>
+                          {L('ttsPreview')}
+                        </button>
+                      </div>
+                    </>
+                  )}
+                  <p className="settings-hint">{L('ttsHint')}</p>
+                </div>
+
+                {/* Прозрачность */}
+                <div className="settings-group">
+                  <label className="settings-label">
+                    {L('windowOpacity')}: {Math.round(appSettings.opacity * 100)}%
+                  </label>
+                  <input 
+                    type="range" 
+                    min="50" 
+                    max="100" 
+                    step="5"
+                    value={appSettings.opacity * 100}
+                    onChange={(e) => invoke('set_overlay_opacity', { opacity: parseFloat(e.target.value) / 100 })
+                      .catch(console.error)}
+                    className="settings-slider"
+                  />
+                </div>
+
+                {/* Сквозь клики */}
+                <div className="settings-group">
+                  <button
+                    className={`settings-toggle ${clickThrough ? 'active' : ''}`}
+                    onClick={() => invoke('set_click_through', { enabled: !clickThrough }).catch(console.error)}
+                  >
+                    {L('clickThrough')}
+                  </button>
+                  <p className="settings-hint">{L('clickThroughHint')}</p>
+                  <input
+                    type="text"
+                    className="overlay-input"
+                    defaultValue={appSettings.click_through_hotkey ?? 'CommandOrControl+Shift+X'}
+                    onBlur={(e) => {
+                      const hotkey = e.target.value.trim();
+                      if (hotkey && hotkey !== appSettings.click_through_hotkey) {
+                        handleSaveSettings({ ...appSettings, click_through_hotkey: hotkey });
+                      }
+                    }}
+                  />
                 </div>
 
                 {/* Всегда поверх окон */}
@@ -2772,6 +6343,18 @@ export function Overlay() {
                   </label>
                 </div>
 
+                {/* Прятать, когда игра не в фокусе */}
+                <div className="settings-group">
+                  <label className="settings-checkbox">
+                    <input
+                      type="checkbox"
+                      checked={appSettings.hide_when_game_unfocused ?? false}
+                      onChange={(e) => handleSaveSettings({ ...appSettings, hide_when_game_unfocused: e.target.checked })}
+                    />
+                    <span>{L('hideWhenUnfocused')}</span>
+                  </label>
+                </div>
+
                 {/* Сворачивать в трей */}
                 <div className="settings-group">
                   <label className="settings-checkbox">
@@ -2784,6 +6367,131 @@ export function Overlay() {
                   </label>
                 </div>
 
+                {/* Лог приложения (для поддержки) */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('appLogLevel')}</label>
+                  <div className="settings-row">
+                    {['info', 'debug', 'trace'].map(level => (
+                      <button
+                        key={level}
+                        className={`settings-toggle ${(appSettings.log_level ?? 'debug') === level ? 'active' : ''}`}
+                        onClick={() => invoke<string>('set_log_level', { level })
+                          .then(saved => setAppSettings(prev => ({ ...prev, log_level: saved })))
+                          .catch(console.error)}
+                      >
+                        {level}
+                      </button>
+                    ))}
+                  </div>
+                  <button
+                    className="settings-toggle"
+                    onClick={async () => {
+                      const lines = await invoke<string[]>('get_recent_app_logs', { n: 200 });
+                      await writeText(lines.join('\n'));
+                    }}
+                  >
+                    {L('copyAppLogs')}
+                  </button>
+                </div>
+
+                {/* Лимиты кэшей */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('cacheTitle')}</label>
+                  {cacheStats && (
+                    <div className="settings-hint">
+                      {cacheStats.items} items · {(cacheStats.items_bytes / 1048576).toFixed(1)} MB ·{' '}
+                      {cacheStats.prices} prices ({cacheStats.prices_current_league} {cacheStats.current_league ?? ''}) ·{' '}
+                      {(cacheStats.prices_bytes / 1048576).toFixed(1)} MB
+                    </div>
+                  )}
+                  <div className="settings-row">
+                    <span>{L('cacheMaxPrices')}</span>
+                    <input
+                      className="overlay-input"
+                      type="number"
+                      min={100}
+                      defaultValue={cacheLimits.max_prices}
+                      onBlur={(e) => handleSaveSettings({ ...appSettings, cache_limits: { ...cacheLimits, max_prices: Number(e.target.value) || cacheLimits.max_prices } })}
+                    />
+                  </div>
+                  <div className="settings-row">
+                    <span>{L('cachePriceTtl')}</span>
+                    <input
+                      className="overlay-input"
+                      type="number"
+                      min={0}
+                      defaultValue={cacheLimits.price_ttl_days}
+                      onBlur={(e) => handleSaveSettings({ ...appSettings, cache_limits: { ...cacheLimits, price_ttl_days: Math.max(0, Number(e.target.value) || 0) } })}
+                    />
+                  </div>
+                </div>
+
+                {/* Лига и архив прошлых лиг */}
+                {leagueStatus && (
+                  <div className="settings-group">
+                    <label className="settings-label">{L('leagueTitle')}</label>
+                    <div className="settings-hint">
+                      {leagueStatus.active_league ?? '—'}
+                      {leagueStatus.rollover_pending && ` → ${leagueStatus.current_league}`}
+                    </div>
+                    {leagueStatus.rollover_pending && !confirmArchiveLeague && (
+                      <button className="settings-toggle" onClick={() => setConfirmArchiveLeague(true)}>
+                        {L('archiveLeague')}
+                      </button>
+                    )}
+                    {confirmArchiveLeague && (
+                      <>
+                        <div className="settings-hint">{L('archiveLeagueConfirm')}</div>
+                        <div className="settings-row">
+                          <button className="settings-btn-small" onClick={handleArchiveLeague}>{L('archiveLeague')}</button>
+                          <button className="settings-btn-small" onClick={() => setConfirmArchiveLeague(false)}>{L('cancel')}</button>
+                        </div>
+                      </>
+                    )}
+                    {leagueStatus.archives.map(a => (
+                      <div key={a.id}>
+                        <button className="settings-btn-small" onClick={() => handleOpenArchivedLeague(a.id)}>
+                          {a.league} · {new Date(a.archived_at).toLocaleDateString(lang === 'ru' ? 'ru-RU' : 'en-US')}
+                        </button>
+                        {archivedLeague?.league.id === a.id && (
+                          <div className="settings-hint">
+                            {archivedLeague.sessions.length} {L('archivedSessions')} ·{' '}
+                            {formatNumber(archivedLeague.sessions.reduce((sum, s) => sum + s.total_profit, 0))} FE
+                          </div>
+                        )}
+                      </div>
+                    ))}
+                  </div>
+                )}
+
+                {/* Фильтр шумных строк лога */}
+                <div className="settings-group">
+                  <label className="settings-label">{L('noiseTitle')}</label>
+                  <textarea
+                    className="overlay-input import-textarea"
+                    rows={3}
+                    placeholder="^\[.*\]LogStreaming:"
+                    defaultValue={(appSettings.log_noise_patterns ?? []).join('\n')}
+                    onBlur={async (e) => {
+                      const patterns = e.target.value.split('\n').map(p => p.trim()).filter(Boolean);
+                      await handleSaveSettings({ ...appSettings, log_noise_patterns: patterns });
+                      invoke<NoiseFilterStats>("get_noise_filter_stats").then(setNoiseStats).catch(() => {});
+                    }}
+                  />
+                  <div className="settings-hint">
+                    {L('noiseHint')}
+                    {noiseStats && noiseStats.lines_checked > 0 && (
+                      <> · {noiseStats.lines_dropped} / {noiseStats.lines_checked}</>
+                    )}
+                  </div>
+                  {noiseStats && noiseStats.patterns.filter(p => p.dropped > 0).map(p => (
+                    <div key={p.pattern} className="settings-hint">{p.pattern}: {p.dropped}</div>
+                  ))}
+                  {noiseStats && noiseStats.invalid.map(p => (
+                    <div key={p} className="settings-hint error">✗ {p}</div>
+                  ))}
+                </div>
+
                 {/* Путь к логу */}
                 <div className="settings-group">
                   <label className="settings-label">{L('logFilePath')}</label>
@@ -2875,6 +6583,21 @@ export function Overlay() {
               <p style={{ marginTop: 12, fontSize: 12, color: 'var(--text-secondary)' }}>
                 {L('selectLogFile')} <strong>UE_game.log</strong>
               </p>
+              {logCandidates.length > 0 && (
+                <div className="log-candidates">
+                  <p style={{ marginTop: 12, marginBottom: 8, fontWeight: 600 }}>{L('foundLogFiles')}:</p>
+                  {logCandidates.map((c) => (
+                    <button key={c.path} className="log-candidate" onClick={() => handlePickLogCandidate(c.path)}>
+                      <code>{c.path}</code>
+                      {c.last_modified_secs_ago != null && (
+                        <span className="log-candidate-age">
+                          {L('modifiedAgo')} {formatDuration(c.last_modified_secs_ago)}
+                        </span>
+                      )}
+                    </button>
+                  ))}
+                </div>
+              )}
             </div>
             <div className="overlay-modal-actions">
               <button className="modal-btn" onClick={() => setShowLogModal(false)}>
@@ -0,0 +1,173 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { applyDropsDelta, DropsDelta } from "./dropsDelta";
+
+// Отсоединённая панель (статистика, список дропа, лента лута).
+// Окно открывает backend (open_panel_window) с ?view=panel&panel=<kind>; панель
+// подписывается только на свои события, остальное состояние ей не рассылается.
+
+type PanelKind = "stats" | "drops" | "ticker";
+
+interface SessionStats {
+  total_value: number;
+  maps_completed: number;
+  duration_sec: number;
+  hourly_profit: number;
+  is_paused: boolean;
+}
+
+interface ItemInfo {
+  name: string;
+  icon_url: string | null;
+}
+
+interface AggregatedDrop {
+  game_id: number;
+  tier?: number | null;
+  item_info: ItemInfo | null;
+  quantity: number;
+  total_value: number;
+}
+
+interface ItemDropEvent {
+  game_id: number;
+  quantity: number;
+  timestamp: string;
+}
+
+const PANEL_EVENTS: Record<PanelKind, string[]> = {
+  stats: ["session-stats-updated", "session-duration"],
+  drops: ["drops-updated"],
+  ticker: ["item-drop", "drops-updated"],
+};
+
+const TICKER_SIZE = 8;
+
+const formatDuration = (seconds: number): string => {
+  const h = Math.floor(seconds / 3600);
+  const m = Math.floor((seconds % 3600) / 60);
+  const s = seconds % 60;
+  return `${h.toString().padStart(2, "0")}:${m.toString().padStart(2, "0")}:${s.toString().padStart(2, "0")}`;
+};
+
+const formatNumber = (num: number): string => {
+  const abs = Math.abs(num);
+  // Те же правила, что value_format::compact в backend
+  if (abs >= 1000000) return (num / 1000000).toFixed(1) + "M";
+  if (abs >= 10000) return (num / 1000).toFixed(1) + "k";
+  if (abs >= 100) return num.toFixed(0);
+  if (abs >= 10) return num.toFixed(1);
+  if (abs === 0) return "0";
+  return num.toFixed(2);
+};
+
+function Panel({ kind }: { kind: PanelKind }) {
+  const [stats, setStats] = useState<SessionStats | null>(null);
+  const [drops, setDrops] = useState<AggregatedDrop[]>([]);
+  const [ticker, setTicker] = useState<ItemDropEvent[]>([]);
+
+  useEffect(() => {
+    let disposed = false;
+    let unlisteners: (() => void)[] = [];
+    // Дельты до прихода снимка копятся и применяются поверх него
+    let pending: DropsDelta<AggregatedDrop>[] | null = [];
+
+    // Сначала подписка, потом снимок: дельта, пришедшая между ними, не теряется
+    Promise.all([
+      listen<SessionStats>("session-stats-updated", (event) => setStats(event.payload)),
+      listen<number>("session-duration", (event) => {
+        setStats((prev) => (prev ? { ...prev, duration_sec: event.payload } : prev));
+      }),
+      listen<DropsDelta<AggregatedDrop>>("drops-updated", (event) => {
+        if (pending) {
+          pending.push(event.payload);
+        } else {
+          setDrops((prev) => applyDropsDelta(prev, event.payload));
+        }
+      }),
+      listen<ItemDropEvent>("item-drop", (event) => {
+        setTicker((prev) => [event.payload, ...prev].slice(0, TICKER_SIZE));
+      }),
+    ]).then((fns) => {
+      if (disposed) {
+        fns.forEach((fn) => fn());
+        return;
+      }
+      unlisteners = fns;
+      // Backend рассылает панели только то, на что она подписалась, — снимок после подписки
+      invoke("subscribe_window_events", { events: PANEL_EVENTS[kind] })
+        .catch(console.error)
+        .then(() => {
+          invoke<SessionStats>("get_session_stats").then(setStats).catch(console.error);
+          return invoke<AggregatedDrop[]>("get_drops");
+        })
+        .then((snapshot) => {
+          const deltas = pending ?? [];
+          pending = null;
+          setDrops(deltas.reduce((acc, delta) => applyDropsDelta(acc, delta), snapshot));
+        })
+        .catch((e) => {
+          pending = null;
+          console.error(e);
+        });
+    });
+
+    return () => {
+      disposed = true;
+      unlisteners.forEach((fn) => fn());
+    };
+  }, [kind]);
+
+  const itemName = (gameId: number) =>
+    drops.find((d) => d.game_id === gameId)?.item_info?.name ?? `#${gameId}`;
+
+  return (
+    <div className="companion panel-window" data-tauri-drag-region>
+      <div className="companion-row companion-muted" data-tauri-drag-region>
+        <span>{kind}</span>
+        <button className="panel-close" onClick={() => invoke("close_panel_window", { kind })}>×</button>
+      </div>
+
+      {kind === "stats" && stats && (
+        <>
+          <div className="companion-row" data-tauri-drag-region>
+            <span className="companion-time">{formatDuration(stats.duration_sec)}</span>
+            {stats.is_paused && <span className="companion-paused">II</span>}
+          </div>
+          <div className="companion-row" data-tauri-drag-region>
+            <span className="companion-value">{formatNumber(stats.total_value)} FE</span>
+            <span className="companion-rate">{formatNumber(stats.hourly_profit)}/h</span>
+          </div>
+          <div className="companion-row companion-muted" data-tauri-drag-region>
+            {stats.maps_completed} maps
+          </div>
+        </>
+      )}
+
+      {kind === "drops" && (
+        <div className="panel-list">
+          {drops.map((d) => (
+            <div key={`${d.game_id}-${d.tier ?? ''}`} className="companion-row">
+              <span className="panel-item-name">{d.item_info?.name ?? `#${d.game_id}`}{d.tier != null ? ` T${d.tier}` : ''} ×{d.quantity}</span>
+              <span className="companion-value">{formatNumber(d.total_value)}</span>
+            </div>
+          ))}
+        </div>
+      )}
+
+      {kind === "ticker" && (
+        <div className="panel-list">
+          {ticker.map((t, i) => (
+            <div key={`${t.timestamp}-${i}`} className="companion-row">
+              <span className="panel-item-name">{itemName(t.game_id)}</span>
+              <span className="companion-rate">+{t.quantity}</span>
+            </div>
+          ))}
+        </div>
+      )}
+    </div>
+  );
+}
+
+export default Panel;
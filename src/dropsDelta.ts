@@ -0,0 +1,22 @@
+// Сборка списка дропа из события drops-updated (общая для главного окна, оверлея и панелей)
+
+/** Ключ строки дропа: предмет и тир */
+export interface DropKey {
+  game_id: number;
+  tier?: number | null;
+}
+
+/** Событие drops-updated: изменившиеся строки и ключи удалённых */
+export interface DropsDelta<T extends DropKey> {
+  changed: T[];
+  removed: DropKey[];
+}
+
+const dropKey = (d: DropKey) => `${d.game_id}:${d.tier ?? ""}`;
+
+// Собрать список из изменений (backend шлёт только поменявшиеся строки), порядок — по стоимости
+export function applyDropsDelta<T extends DropKey & { total_value: number }>(prev: T[], delta: DropsDelta<T>): T[] {
+  const replaced = new Set([...delta.removed, ...delta.changed].map(dropKey));
+  return [...prev.filter((d) => !replaced.has(dropKey(d))), ...delta.changed]
+    .sort((a, b) => b.total_value - a.total_value);
+}
@@ -1,10 +1,17 @@
 import React from "react";
 import ReactDOM from "react-dom/client";
 import Overlay from "./Overlay";
+import Companion from "./Companion";
+import Panel from "./Panel";
 import "./styles.css";
 
+// Окно-компаньон открывается backend'ом с ?view=companion, панели — с ?view=panel&panel=<kind>
+const params = new URLSearchParams(window.location.search);
+const view = params.get("view");
+const panelKind = params.get("panel") as "stats" | "drops" | "ticker" | null;
+
 ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
   <React.StrictMode>
-    <Overlay />
+    {view === "companion" ? <Companion /> : view === "panel" && panelKind ? <Panel kind={panelKind} /> : <Overlay />}
   </React.StrictMode>,
 );